@@ -1,21 +1,56 @@
 use crate::error::CollectorError;
+use crate::webhook::Webhook;
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, CharacterFittingEntry, UserEntry};
-use caph_eve_data_wrapper::{CharacterId, CharacterService, EveClient, EveDataWrapper, EveOAuthUser, FittingId, ItemId};
-use std::collections::HashMap;
+use caph_db_v2::{CacheName, CalendarEventEntry, CharacterAssetEntry, CharacterBlueprintEntry, CharacterCloneEntry, CharacterContractEntry, CharacterMiningEntry, CharacterNotificationEntry, CharacterPlanetEntry, CharacterSkillEntry, CharacterSyncEntry, CloneLocationEntry, CharacterFittingEntry, CorporationAssetEntry, CorporationMiningEntry, CorporationStructureEntry, JumpCloneEntry, MarketPriceEntry, NetWorthEntry, Skill, SkillHistoryEntry, UserEntry, UserPreferenceEntry, WalletTransactionEntry, WorkspaceEntry, WorkspaceKey};
+use caph_eve_data_wrapper::{AllianceId, CharacterId, CharacterService, ContractService, CorporationId, EveClient, EveDataWrapper, EveOAuthUser, FittingId, ItemId, LocationId, TransactionId, TypeId};
+use chrono::{Timelike, Utc};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use uuid::Uuid;
 
 
 pub struct Character {
-    eve:  EveDataWrapper,
-    pool: ConnectionPool,
+    eve:       EveDataWrapper,
+    pool:      ConnectionPool,
+    /// Timestamp in milliseconds of the last sync per character and data
+    /// type, loaded from the database on every run
+    last_sync: HashMap<(CharacterId, &'static str), u64>,
 }
 
 impl Character {
+    /// Role required to read the corporation assets
+    const ROLE_DIRECTOR:        &'static str = "Director";
+    /// Role required to read the mining ledgers of the corporation
+    const ROLE_ACCOUNTANT:      &'static str = "Accountant";
+    /// Role required to read the corporation structures
+    const ROLE_STATION_MANAGER: &'static str = "Station_Manager";
+
+    /// Notifications that are forwarded to the webhook of the user
+    const WEBHOOK_NOTIFICATIONS: &'static [&'static str] = &[
+        "StructureDestroyed",
+        "StructureFuelAlert",
+        "StructureLostArmor",
+        "StructureLostShields",
+        "StructureServicesOffline",
+        "StructureUnderAttack",
+        "TowerAlertMsg",
+        "TowerResourceAlertMsg",
+    ];
+
+    /// Time in milliseconds after which a new skill snapshot is taken, even
+    /// if nothing changed
+    const SKILL_HISTORY_INTERVAL: u64   = 24 * 60 * 60 * 1_000;
+
+    /// Runs are not exactly 30 minutes apart, so a data type is also synced
+    /// if its next sync is at most this many milliseconds away
+    const SYNC_TOLERANCE:         u64   = 5 * 60 * 1_000;
+
     pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Self {
         Self {
             eve,
-            pool
+            pool,
+            last_sync: HashMap::new(),
         }
     }
 
@@ -24,9 +59,12 @@ impl Character {
     pub async fn task(&mut self) -> Result<(), CollectorError> {
         log::info!("Loading eve services");
         let character_service = self.eve.character().await?;
+        let contract_service = self.eve.contracts().await?;
         log::info!("Services loaded");
 
         let mut con = self.pool.acquire().await?;
+        self.load_last_sync().await?;
+
         let characters = con
             .keys::<_, CharacterId>(CacheName::User)
             .await
@@ -36,40 +74,289 @@ impl Character {
             .await
             .unwrap();
         let mut tokens = Vec::new();
+        // Maps every character to its main
+        let mut mains = HashMap::new();
         for character in characters {
             let character = character.unwrap();
+            // deauthorized mains keep their data but have no tokens
+            if character.refresh_token.is_empty() {
+                continue;
+            }
             let token = self.refresh_token(&character.refresh_token).await?;
+            mains.insert(token.user_id, character.user_id);
             tokens.push(token);
 
             for alt in character.aliase {
                 let token = self.refresh_token(&alt.refresh_token).await?;
+                mains.insert(token.user_id, character.user_id);
                 tokens.push(token);
             }
         }
 
-        for token in tokens {
-            let _ = tokio::join! {
-                self.assets(
+        // Every corporation is only collected once, even if multiple
+        // directors of it are registered
+        let mut corporations = HashSet::new();
+        for token in tokens.iter() {
+            if corporations.contains(&token.corp_id) {
+                continue;
+            }
+
+            let collected = self
+                .corporation(
                     token.access_token.clone(),
                     token.user_id,
+                    token.corp_id,
                     character_service.clone()
+                )
+                .await?;
+            if collected {
+                corporations.insert(token.corp_id);
+            }
+        }
+
+        let main_ids = mains
+            .values()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let preferences = con
+            .mget::<_, _, UserPreferenceEntry>(CacheName::UserPreference, main_ids.clone())
+            .await?
+            .into_iter()
+            .zip(main_ids)
+            .filter_map(|(x, id)| x.map(|x| (id, x)))
+            .collect::<HashMap<_, _>>();
+
+        // Corporations of every main and its alts, used to find the
+        // workspaces the main is a member of
+        let mut corp_ids = HashMap::<CharacterId, Vec<CorporationId>>::new();
+        for token in tokens.iter() {
+            let main_id = mains.get(&token.user_id).copied().unwrap_or(token.user_id);
+            corp_ids.entry(main_id).or_default().push(token.corp_id);
+        }
+        let workspace_ids = con
+            .keys::<_, Uuid>(CacheName::Workspace)
+            .await?;
+        let workspaces = con
+            .mget::<_, _, WorkspaceEntry>(CacheName::Workspace, workspace_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        for token in tokens {
+            let main_id = mains.get(&token.user_id).copied().unwrap_or(token.user_id);
+            let due = self.due(token.user_id, preferences.get(&main_id));
+            let main_corp_ids = corp_ids.get(&main_id).cloned().unwrap_or_default();
+            let workspace_ids = workspaces
+                .iter()
+                .filter(|x| x.is_member(main_id, &main_corp_ids))
+                .map(|x| x.id)
+                .collect::<Vec<_>>();
+
+            let _ = tokio::join! {
+                Self::when(
+                    due.contains(&"assets"),
+                    self.assets(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
                 ),
-                self.blueprints(
-                    token.access_token.clone(),
-                    token.user_id,
-                    character_service.clone()
+                Self::when(
+                    due.contains(&"blueprints"),
+                    self.blueprints(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
                 ),
-                self.fittings(
-                    token.access_token,
-                    token.user_id,
-                    character_service.clone()
+                Self::when(
+                    due.contains(&"calendar"),
+                    self.calendar(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"clones"),
+                    self.clones(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"contracts"),
+                    self.contracts(
+                        token.access_token.clone(),
+                        token.user_id,
+                        contract_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"fittings"),
+                    self.fittings(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"mining"),
+                    self.mining(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"net_worth"),
+                    self.net_worth(
+                        token.access_token.clone(),
+                        token.user_id,
+                        workspace_ids,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"notifications"),
+                    self.notifications(
+                        token.access_token.clone(),
+                        token.user_id,
+                            main_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"planets"),
+                    self.planets(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"skills"),
+                    self.skills(
+                        token.access_token.clone(),
+                        token.user_id,
+                        character_service.clone()
+                    )
+                ),
+                Self::when(
+                    due.contains(&"wallet_transactions"),
+                    self.wallet_transactions(
+                        token.access_token,
+                        token.user_id,
+                        character_service.clone()
+                    )
                 )
             };
+
+            let now = Utc::now().timestamp() as u64 * 1_000;
+            for typ in due {
+                self.last_sync.insert((token.user_id, typ), now);
+            }
+            self.save_last_sync(token.user_id).await?;
         }
 
         Ok(())
     }
 
+    /// Loads the last sync of every character and data type
+    async fn load_last_sync(&mut self) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, CharacterId>(CacheName::CharacterSync)
+            .await?;
+        let entries = con
+            .mget::<_, _, Vec<CharacterSyncEntry>>(CacheName::CharacterSync, keys.clone())
+            .await?;
+
+        self.last_sync.clear();
+        for (user_id, entries) in keys.into_iter().zip(entries) {
+            for entry in entries.unwrap_or_default() {
+                // The keys of the map must be static
+                let typ = UserPreferenceEntry::SYNC_TYPES
+                    .iter()
+                    .find(|x| **x == entry.typ);
+                if let Some(typ) = typ {
+                    self.last_sync.insert((user_id, typ), entry.timestamp);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the last sync of every data type of the character, so that
+    /// it survives a restart and can be shown to the user
+    async fn save_last_sync(&self, user_id: CharacterId) -> Result<(), CollectorError> {
+        let entries = self
+            .last_sync
+            .iter()
+            .filter(|((uid, _), _)| *uid == user_id)
+            .map(|((_, typ), timestamp)| CharacterSyncEntry::new(typ.to_string(), *timestamp))
+            .collect::<Vec<_>>();
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::CharacterSync, user_id, entries)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets all data types of the character that should be synced in this
+    /// run.
+    ///
+    /// Outside of the sync window of the user nothing is synced. Data types
+    /// are only synced if enough time has passed since their last sync, see
+    /// [UserPreferenceEntry::sync_frequency].
+    fn due(
+        &self,
+        user_id:    CharacterId,
+        preference: Option<&UserPreferenceEntry>,
+    ) -> Vec<&'static str> {
+        let now = Utc::now();
+        let in_window = preference
+            .map(|x| x.in_window(now.hour() as u8))
+            .unwrap_or(true);
+        if !in_window {
+            return Vec::new();
+        }
+
+        let now = now.timestamp() as u64 * 1_000;
+        UserPreferenceEntry::SYNC_TYPES
+            .iter()
+            .filter(|typ| {
+                let minutes = UserPreferenceEntry::sync_frequency(preference, typ) as u64;
+                let last = self
+                    .last_sync
+                    .get(&(user_id, **typ))
+                    .copied()
+                    .unwrap_or_default();
+                now.saturating_sub(last) + Self::SYNC_TOLERANCE >= minutes * 60 * 1_000
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Only runs the given future if `run` is true
+    async fn when<F>(run: bool, f: F) -> Result<(), CollectorError>
+    where
+        F: Future<Output = Result<(), CollectorError>>,
+    {
+        if run {
+            f.await
+        } else {
+            Ok(())
+        }
+    }
+
     async fn assets(
         &self,
         token: String,
@@ -85,6 +372,24 @@ impl Character {
             .map(|x| CharacterAssetEntry::from(x, user_id))
             .map(|x| (x.item_id, x))
             .collect::<HashMap<ItemId, CharacterAssetEntry>>();
+
+        // Remove all assets of the character that are not returned anymore,
+        // for example because they were sold or consumed
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let stale = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.user_id == user_id && !prices.contains_key(&x.item_id))
+            .map(|x| x.item_id)
+            .collect::<Vec<_>>();
+        if !stale.is_empty() {
+            con.mdel(CacheName::CharacterAsset, stale).await?;
+        }
+
         con.mset(CacheName::CharacterAsset, prices).await.unwrap();
         Ok(())
     }
@@ -108,6 +413,135 @@ impl Character {
         Ok(())
     }
 
+    async fn clones(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let clones = character_service
+            .clones(&token, user_id)
+            .await?;
+        let implants = character_service
+            .implants(&token, user_id)
+            .await
+            .unwrap_or_default();
+
+        let home_location = if let Some(x) = clones.home_location {
+            Some(self.clone_location(&token, x.location_id, &character_service).await?)
+        } else {
+            None
+        };
+
+        let mut jump_clones = Vec::new();
+        for clone in clones.jump_clones {
+            let location = self
+                .clone_location(&token, clone.location_id, &character_service)
+                .await?;
+            jump_clones.push(JumpCloneEntry {
+                jump_clone_id: clone.jump_clone_id,
+                location,
+                implants:      clone.implants,
+                name:          clone.name,
+            });
+        }
+
+        let entry = CharacterCloneEntry {
+            user_id,
+            home_location,
+            implants,
+            jump_clones,
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::CharacterClone, user_id, entry)
+            .await?;
+        Ok(())
+    }
+
+    /// Resolves the name of a station or structure.
+    ///
+    /// Station names are taken from the database, structures are requested
+    /// from ESI and only resolve if the character has docking access.
+    async fn clone_location(
+        &self,
+        token: &str,
+        location_id: LocationId,
+        character_service: &CharacterService,
+    ) -> Result<CloneLocationEntry, CollectorError> {
+        let name = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, String>(CacheName::Name, ItemId(*location_id))
+            .await?;
+        let name = match name {
+            Some(x) => x,
+            None    => {
+                character_service
+                    .item_location(token, *location_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|x| x.name)
+                    .unwrap_or_default()
+            },
+        };
+
+        Ok(CloneLocationEntry {
+            location_id,
+            name,
+        })
+    }
+
+    async fn contracts(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        contract_service: ContractService
+    ) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let contracts = contract_service
+            .character_contracts(&token, user_id)
+            .await
+            .unwrap_or_default();
+        let known = con
+            .mget::<_, _, CharacterContractEntry>(
+                CacheName::CharacterContract,
+                contracts.iter().map(|x| x.contract_id).collect::<Vec<_>>()
+            )
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.contract_id, x.items))
+            .collect::<HashMap<_, _>>();
+
+        let mut entries = HashMap::new();
+        for contract in contracts {
+            // The items of a contract never change, so they are only
+            // requested once
+            let items = if let Some(x) = known.get(&contract.contract_id) {
+                x.clone()
+            } else {
+                contract_service
+                    .character_contract_items(&token, user_id, contract.contract_id)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect::<Vec<_>>()
+            };
+
+            let entry = CharacterContractEntry::from(contract, user_id, items);
+            entries.insert(entry.contract_id, entry);
+        }
+        con.mset(CacheName::CharacterContract, entries).await?;
+        Ok(())
+    }
+
     async fn fittings(
         &self,
         token: String,
@@ -127,6 +561,428 @@ impl Character {
         Ok(())
     }
 
+    async fn mining(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let ledger = character_service
+            .mining(&token, user_id)
+            .await?
+            .into_iter()
+            .map(|x| CharacterMiningEntry::from(x, user_id))
+            .collect::<Vec<_>>();
+
+        // ESI only returns the last 30 days, so the new records are merged
+        // with the already stored ones
+        let mut con = self.pool.acquire().await?;
+        let mut entries = con
+            .get::<_, _, Vec<CharacterMiningEntry>>(CacheName::CharacterMining, user_id)
+            .await?
+            .unwrap_or_default();
+        entries.retain(|x| !ledger.iter().any(|y| y.same_record(x)));
+        entries.extend(ledger);
+
+        con.set(CacheName::CharacterMining, user_id, entries).await?;
+        Ok(())
+    }
+
+    async fn notifications(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        main_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let notifications = character_service
+            .notifications(&token, user_id)
+            .await?
+            .into_iter()
+            .map(|x| CharacterNotificationEntry::from(x, user_id))
+            .collect::<Vec<_>>();
+
+        let mut con = self.pool.acquire().await?;
+        let stored = con
+            .get::<_, _, Vec<CharacterNotificationEntry>>(CacheName::CharacterNotification, user_id)
+            .await?;
+
+        // On the first run all notifications are new, sending them would only
+        // flood the webhook
+        if let Some(stored) = stored.as_ref() {
+            let webhook = con
+                .get::<_, _, String>(CacheName::CharacterWebhook, main_id)
+                .await?;
+
+            if let Some(webhook) = webhook {
+                let new = notifications
+                    .iter()
+                    .filter(|x| Self::WEBHOOK_NOTIFICATIONS.contains(&x.typ.as_str()))
+                    .filter(|x| !stored.iter().any(|y| y.notification_id == x.notification_id));
+                for notification in new {
+                    let message = format!("**{}**\n{}", notification.typ, notification.text);
+                    Webhook::send(&webhook, &message).await;
+                }
+            }
+        }
+
+        // ESI only returns the latest notifications, older ones are kept and
+        // the read state of the known ones is not overwritten
+        let mut entries = stored.unwrap_or_default();
+        for notification in notifications {
+            if let Some(x) = entries
+                .iter_mut()
+                .find(|x| x.notification_id == notification.notification_id) {
+                x.is_read |= notification.is_read;
+            } else {
+                entries.push(notification);
+            }
+        }
+
+        con.set(CacheName::CharacterNotification, user_id, entries).await?;
+        Ok(())
+    }
+
+    async fn calendar(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let events = character_service
+            .calendar(&token, user_id)
+            .await?
+            .into_iter()
+            .map(|x| CalendarEventEntry::from(x, user_id))
+            .collect::<Vec<_>>();
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::CharacterCalendar, user_id, events)
+            .await?;
+        Ok(())
+    }
+
+    async fn planets(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let planets = character_service
+            .planets(&token, user_id)
+            .await?;
+
+        let mut entries = Vec::new();
+        for planet in planets {
+            let pins = character_service
+                .planet(&token, user_id, planet.planet_id)
+                .await
+                .map(|x| x.pins)
+                .unwrap_or_default();
+            entries.push(CharacterPlanetEntry::from(planet, pins));
+        }
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::CharacterPlanet, user_id, entries)
+            .await?;
+        Ok(())
+    }
+
+    async fn skills(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let skills = character_service
+            .skills(&token, user_id)
+            .await?;
+        let attributes = character_service
+            .attributes(&token, user_id)
+            .await?;
+        let training = character_service
+            .skillqueue(&token, user_id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .min_by_key(|x| x.queue_position)
+            .map(|x| TypeId::from(x.skill_id));
+
+        let mut con = self.pool.acquire().await?;
+
+        // The omega expiry is set by the user and must be kept
+        let omega_expiry = con
+            .get::<_, _, CharacterSkillEntry>(CacheName::CharacterSkill, user_id)
+            .await?
+            .and_then(|x| x.omega_expiry);
+
+        let mut entry = CharacterSkillEntry::new(
+            user_id,
+            skills.total_sp,
+            skills.unallocated_sp.unwrap_or_default(),
+            attributes,
+            training,
+            skills
+                .skills
+                .iter()
+                .map(|x| Skill::new(x.active_skill_level, x.skill_id.into()))
+                .collect::<Vec<_>>(),
+        );
+        entry.omega_expiry = omega_expiry;
+        con.set(CacheName::CharacterSkill, user_id, entry).await?;
+
+        // Only take a snapshot if something changed or the last one is old,
+        // otherwise every sync would add an entry
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let history = con
+            .get::<_, _, Vec<SkillHistoryEntry>>(CacheName::SkillHistory, user_id)
+            .await?
+            .unwrap_or_default();
+        let changed = history
+            .last()
+            .map(|x| {
+                x.total_sp != skills.total_sp ||
+                x.unallocated_sp != skills.unallocated_sp.unwrap_or_default() ||
+                now.saturating_sub(x.timestamp) >= Self::SKILL_HISTORY_INTERVAL
+            })
+            .unwrap_or(true);
+        if changed {
+            // the history only appends new entries
+            let entry = SkillHistoryEntry::new(
+                now,
+                skills.total_sp,
+                skills.unallocated_sp.unwrap_or_default(),
+            );
+            con.set(CacheName::SkillHistory, user_id, vec![entry]).await?;
+        }
+        Ok(())
+    }
+
+    async fn wallet_transactions(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+        let transactions = character_service
+            .wallet_transactions(&token, user_id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| WalletTransactionEntry::from(x, user_id))
+            .map(|x| (x.transaction_id, x))
+            .collect::<HashMap<TransactionId, WalletTransactionEntry>>();
+        con.mset(CacheName::WalletTransaction, transactions).await.unwrap();
+        Ok(())
+    }
+
+    /// Takes a snapshot of the asset value and the wallet balance.
+    ///
+    /// The assets are valued with the stored assets, so the snapshot may be
+    /// one asset sync behind. The snapshot is stored private and for every
+    /// workspace in `workspace_ids`.
+    async fn net_worth(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        workspace_ids: Vec<Uuid>,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let wallet_balance = character_service
+            .wallet_balance(&token, user_id)
+            .await?;
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.user_id == user_id)
+            .collect::<Vec<_>>();
+        let type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids.clone())
+            .await?
+            .into_iter()
+            .zip(type_ids)
+            .filter_map(|(x, tid)| x.map(|x| (tid, x.average_price as f64)))
+            .collect::<HashMap<_, _>>();
+        let asset_value = assets
+            .iter()
+            .map(|x| prices.get(&x.type_id).copied().unwrap_or_default() * x.quantity as f64)
+            .sum::<f64>();
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let entry = NetWorthEntry::new(
+            now,
+            asset_value.round() as u64,
+            wallet_balance.max(0f64).round() as u64,
+        );
+        let entries = workspace_ids
+            .into_iter()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .map(|x| (WorkspaceKey::new(x, user_id), vec![entry.clone()]))
+            .collect::<HashMap<_, _>>();
+        con.mset(CacheName::NetWorthHistory, entries).await?;
+        Ok(())
+    }
+
+    /// Collects the assets and structures of the characters corporation.
+    ///
+    /// Only done if the character has the required roles in the corporation,
+    /// registering a character with those roles counts as consent to share
+    /// the corporation data with its alliance leadership.
+    ///
+    /// Returns `true` if anything was collected
+    async fn corporation(
+        &self,
+        token: String,
+        user_id: CharacterId,
+        corp_id: CorporationId,
+        character_service: CharacterService
+    ) -> Result<bool, CollectorError> {
+        let roles = character_service
+            .roles(&token, user_id)
+            .await
+            .unwrap_or_default();
+        let is_director = roles.iter().any(|x| x == Self::ROLE_DIRECTOR);
+        let is_station_manager = is_director ||
+            roles.iter().any(|x| x == Self::ROLE_STATION_MANAGER);
+        let is_accountant = is_director ||
+            roles.iter().any(|x| x == Self::ROLE_ACCOUNTANT);
+        if !is_director && !is_station_manager && !is_accountant {
+            return Ok(false);
+        }
+
+        let alliance_id = character_service
+            .character(&token, user_id)
+            .await?
+            .alliance_id
+            .map(AllianceId::from);
+
+        let mut con = self.pool.acquire().await?;
+
+        if is_director {
+            let assets = character_service
+                .corporation_assets(&token, corp_id)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| CorporationAssetEntry::from(x, corp_id, alliance_id))
+                .map(|x| (x.item_id, x))
+                .collect::<HashMap<_, _>>();
+
+            // Remove all assets of the corporation that are not returned
+            // anymore
+            let keys = con
+                .keys::<_, ItemId>(CacheName::CorporationAsset)
+                .await?;
+            let stale = con
+                .mget::<_, _, CorporationAssetEntry>(CacheName::CorporationAsset, keys)
+                .await?
+                .into_iter()
+                .flatten()
+                .filter(|x| x.corporation_id == corp_id && !assets.contains_key(&x.item_id))
+                .map(|x| x.item_id)
+                .collect::<Vec<_>>();
+            if !stale.is_empty() {
+                con.mdel(CacheName::CorporationAsset, stale).await?;
+            }
+
+            con.mset(CacheName::CorporationAsset, assets).await?;
+        }
+
+        if is_station_manager {
+            let structures = character_service
+                .corporation_structures(&token, corp_id)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| CorporationStructureEntry::from(x, alliance_id))
+                .map(|x| (x.structure_id, x))
+                .collect::<HashMap<_, _>>();
+            con.mset(CacheName::CorporationStructure, structures).await?;
+        }
+
+        if is_accountant {
+            self.corporation_mining(
+                &token,
+                corp_id,
+                character_service
+            )
+            .await?;
+        }
+
+        Ok(true)
+    }
+
+    async fn corporation_mining(
+        &self,
+        token: &str,
+        corp_id: CorporationId,
+        character_service: CharacterService
+    ) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let observers = character_service
+            .corporation_mining_observers(&token, corp_id)
+            .await
+            .unwrap_or_default();
+        for observer in observers {
+            let observer_id = observer.observer_id;
+
+            let system_id = if let Some(x) = con
+                .get::<_, _, CorporationStructureEntry>(CacheName::CorporationStructure, observer_id)
+                .await? {
+                x.system_id
+            } else if let Ok(Some(x)) = character_service
+                .item_location(&token, *observer_id)
+                .await {
+                x.system_id
+            } else {
+                continue;
+            };
+
+            let ledger = character_service
+                .corporation_mining_observer(&token, corp_id, observer_id)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| CorporationMiningEntry::from(x, observer_id, corp_id, system_id))
+                .collect::<Vec<_>>();
+
+            // The observer ledger only covers the last 30 days, keep the
+            // older records
+            let mut entries = con
+                .get::<_, _, Vec<CorporationMiningEntry>>(CacheName::CorporationMining, observer_id)
+                .await?
+                .unwrap_or_default();
+            entries.retain(|x| !ledger.iter().any(|y| y.same_record(x)));
+            entries.extend(ledger);
+
+            con.set(CacheName::CorporationMining, observer_id, entries).await?;
+        }
+
+        Ok(())
+    }
+
     async fn refresh_token(&self, token: &str) -> Result<EveOAuthUser, CollectorError> {
         let oauth = EveClient::retrieve_refresh_token(&token)
             .await
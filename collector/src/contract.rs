@@ -0,0 +1,131 @@
+use crate::error::CollectorError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::*;
+use caph_eve_data_wrapper::{ContractId, ContractService, ContractType, EveDataWrapper, PublicContract, RegionId, TypeId};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::{HashMap, HashSet};
+
+pub struct Contract {
+    eve:  EveDataWrapper,
+    pool: ConnectionPool,
+}
+
+impl Contract {
+    /// Comma separated list of region ids that should be scanned
+    const ENV_REGIONS:    &'static str = "CONTRACT_REGIONS";
+    /// The Forge
+    const DEFAULT_REGION: u32          = 10000002;
+
+    pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Self {
+        Self {
+            eve,
+            pool
+        }
+    }
+
+    /// Scans all public item exchange contracts in the configured regions and
+    /// values them based on the market price of their items.
+    ///
+    /// Contracts that are already known are not fetched again, contracts that
+    /// are no longer public are removed.
+    pub async fn task(&mut self) -> Result<(), CollectorError> {
+        let contract_service = self.eve.contracts().await?;
+
+        let mut con = self.pool.acquire().await?;
+
+        let known = con
+            .keys::<_, ContractId>(CacheName::Contract)
+            .await?;
+        let price_ids = con
+            .keys::<_, TypeId>(CacheName::MarketPrice)
+            .await?;
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, price_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        let mut active  = HashSet::new();
+        let mut entries = HashMap::new();
+        for region in Self::regions() {
+            let contracts = contract_service
+                .public_contracts(region)
+                .await?
+                .into_iter()
+                .filter(|x| x.typ == ContractType::ItemExchange)
+                .collect::<Vec<_>>();
+            active.extend(contracts.iter().map(|x| x.contract_id));
+
+            let mut requests = contracts
+                .into_iter()
+                .filter(|x| !known.contains(&x.contract_id))
+                .map(|x| Self::value(contract_service.clone(), &prices, x))
+                .collect::<FuturesUnordered<_>>();
+            while let Some(return_val) = requests.next().await {
+                if let Ok((contract, value, items)) = return_val {
+                    let entry = ContractEntry::from(contract, region, value, items);
+                    entries.insert(entry.contract_id, entry);
+                }
+            }
+        }
+
+        let stale = known
+            .into_iter()
+            .filter(|x| !active.contains(x))
+            .collect::<Vec<_>>();
+        if !stale.is_empty() {
+            con.mdel(CacheName::Contract, stale).await?;
+        }
+        if !entries.is_empty() {
+            con.mset(CacheName::Contract, entries).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Values the items of a contract.
+    ///
+    /// Items the issuer gives are added, items the issuer wants in return are
+    /// subtracted. Blueprint copies have no market price and are ignored.
+    async fn value(
+        contract_service: ContractService,
+        prices:           &HashMap<TypeId, f32>,
+        contract:         PublicContract,
+    ) -> Result<(PublicContract, f32, Vec<ContractItemEntry>), CollectorError> {
+        let items = contract_service
+            .public_contract_items(contract.contract_id)
+            .await?
+            .into_iter()
+            .filter(|x| !x.is_blueprint_copy.unwrap_or_default())
+            .collect::<Vec<_>>();
+        let value = items
+            .iter()
+            .map(|x| {
+                let value = prices
+                    .get(&x.type_id)
+                    .map(|p| *p * x.quantity as f32)
+                    .unwrap_or_default();
+                if x.is_included { value } else { -value }
+            })
+            .sum::<f32>();
+        let items = items
+            .into_iter()
+            .map(ContractItemEntry::from)
+            .collect::<Vec<_>>();
+        Ok((contract, value, items))
+    }
+
+    fn regions() -> Vec<RegionId> {
+        std::env::var(Self::ENV_REGIONS)
+            .map(|x| x
+                .split(',')
+                .filter_map(|x| x.trim().parse::<u32>().ok())
+                .map(RegionId::from)
+                .collect::<Vec<_>>()
+            )
+            .unwrap_or_else(|_| vec![Self::DEFAULT_REGION.into()])
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::CollectorError;
+use crate::webhook::Webhook;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterAssetEntry, UserEntry, UserPreferenceEntry, WalletTransactionEntry};
+use caph_eve_data_wrapper::{CharacterId, ItemId, TransactionId};
+use chrono::Utc;
+use serde_json::json;
+
+/// Exports the data of users that have not logged in for the configured
+/// time and removes their tokens afterwards
+pub struct Deadman {
+    pool: ConnectionPool,
+}
+
+impl Deadman {
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    pub async fn task(&mut self) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user_ids = con
+            .keys::<_, CharacterId>(CacheName::User)
+            .await?;
+        let preferences = con
+            .mget::<_, _, UserPreferenceEntry>(CacheName::UserPreference, user_ids.clone())
+            .await?;
+        let logins = con
+            .mget::<_, _, u64>(CacheName::UserLogin, user_ids.clone())
+            .await?;
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        for ((user_id, preference), login) in user_ids.into_iter().zip(preferences).zip(logins) {
+            let (days, webhook) = match preference {
+                Some(UserPreferenceEntry {
+                    deadman_days:    Some(days),
+                    deadman_webhook: Some(webhook),
+                    ..
+                }) => (days, webhook),
+                _ => continue,
+            };
+
+            // Users that logged in before the last login was tracked start
+            // with the current time
+            let login = if let Some(x) = login {
+                x
+            } else {
+                con.set(CacheName::UserLogin, user_id, now).await?;
+                continue;
+            };
+
+            if now.saturating_sub(login) < days as u64 * 24 * 60 * 60 * 1_000 {
+                continue;
+            }
+
+            if let Err(e) = self.export(user_id, &webhook).await {
+                log::error!("Error exporting data of {} {:?}", user_id, e);
+                continue;
+            }
+
+            // Only remove the tokens after the export was successful
+            con.del(CacheName::User, user_id).await?;
+            con.del(CacheName::UserLogin, user_id).await?;
+            con.del(CacheName::UserPreference, user_id).await?;
+            log::info!("Exported data of {} and removed its tokens", user_id);
+        }
+
+        Ok(())
+    }
+
+    /// Sends the assets and wallet transactions of the main and all its alts
+    /// to the webhook
+    async fn export(&self, user_id: CharacterId, webhook: &str) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = if let Some(x) = con
+            .get::<_, _, UserEntry>(CacheName::User, user_id)
+            .await? {
+            x
+        } else {
+            return Ok(());
+        };
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let keys = con
+            .keys::<_, TransactionId>(CacheName::WalletTransaction)
+            .await?;
+        let transactions = con
+            .mget::<_, _, WalletTransactionEntry>(CacheName::WalletTransaction, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let export = json!({
+            "characters":          user_ids,
+            "assets":              assets,
+            "wallet_transactions": transactions,
+        });
+        let export = serde_json::to_vec_pretty(&export)?;
+
+        let message = format!(
+            "No login for character {} in the configured time, this is the \
+            export of all data. The stored tokens are removed.",
+            user_id
+        );
+        Webhook::send_file(webhook, &message, "export.json", export).await
+    }
+}
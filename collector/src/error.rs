@@ -13,6 +13,12 @@ pub enum CollectorError {
     DbConnectionPoolError(cachem::CachemError),
     /// There was an error with the database protocol
     DbProtocolError(cachem::CachemError),
+    /// Error sending a message to a webhook
+    WebhookError(reqwest::Error),
+    /// Error serializing data
+    SerdeJsonError(serde_json::Error),
+    /// The market dump could not be read
+    ImportError(String),
 }
 impl std::error::Error for CollectorError {}
 
@@ -42,3 +48,15 @@ impl From<chrono::ParseError> for CollectorError {
         Self::ChronoError
     }
 }
+
+impl From<reqwest::Error> for CollectorError {
+    fn from(x: reqwest::Error) -> Self {
+        Self::WebhookError(x)
+    }
+}
+
+impl From<serde_json::Error> for CollectorError {
+    fn from(x: serde_json::Error) -> Self {
+        Self::SerdeJsonError(x)
+    }
+}
@@ -0,0 +1,68 @@
+use crate::error::CollectorError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::*;
+use caph_eve_data_wrapper::EveDataWrapper;
+use chrono::Utc;
+
+pub struct Killboard {
+    eve:      EveDataWrapper,
+    pool:     ConnectionPool,
+    queue_id: String,
+}
+
+impl Killboard {
+    /// Queue id for the zKillboard RedisQ, if not set no killmails are
+    /// collected
+    const ENV_QUEUE_ID: &'static str = "ZKILL_QUEUE_ID";
+    /// Kills older than 24 hours are removed
+    const MAX_AGE:      u64          = 24 * 60 * 60 * 1_000;
+
+    /// Creates a new instance, returns [None] if no queue id is configured
+    pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Option<Self> {
+        let queue_id = std::env::var(Self::ENV_QUEUE_ID).ok()?;
+
+        Some(Self {
+            eve,
+            pool,
+            queue_id,
+        })
+    }
+
+    /// Waits for the next killmail from zKillboard, verifies it against ESI
+    /// and stores it together with the other recent kills of the system.
+    pub async fn task(&mut self) -> Result<(), CollectorError> {
+        let killmail_service = self.eve.killmails().await?;
+
+        let package = if let Some(x) = killmail_service
+            .redisq(&self.queue_id)
+            .await? {
+            x
+        } else {
+            return Ok(());
+        };
+
+        let killmail = killmail_service
+            .killmail(package.kill_id, &package.zkb.hash)
+            .await?;
+        let entry = KillmailEntry::from(killmail, package.zkb.total_value);
+
+        let mut con = self.pool.acquire().await?;
+
+        let oldest = Utc::now().timestamp() as u64 * 1_000 - Self::MAX_AGE;
+        let mut kills = con
+            .get::<_, _, Vec<KillmailEntry>>(CacheName::Killmail, entry.system_id)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|x| x.timestamp >= oldest)
+            .filter(|x| x.killmail_id != entry.killmail_id)
+            .collect::<Vec<_>>();
+        kills.push(entry.clone());
+        kills.sort_by_key(|x| x.timestamp);
+
+        con.set(CacheName::Killmail, entry.system_id, kills).await?;
+
+        Ok(())
+    }
+}
@@ -1,12 +1,25 @@
 mod character;
+mod contract;
+mod deadman;
 mod error;
+mod killboard;
 mod market;
+mod market_import;
 mod sde;
+mod sovereignty;
+mod stock;
 mod time;
+mod webhook;
 
 use self::character::*;
+use self::contract::*;
+use self::deadman::*;
+use self::killboard::*;
 use self::market::*;
+use self::market_import::*;
 use self::sde::*;
+use self::sovereignty::*;
+use self::stock::*;
 use self::time::*;
 
 use cachem::v2::ConnectionPool;
@@ -23,6 +36,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let eve = EveDataWrapper::new().await?;
     log::info!("Prepared SDE");
 
+    let eve_copy = eve.clone();
+    let status = tokio::task::spawn(async move {
+        eve_copy.poll_esi_status().await;
+    });
+
+    let pool_copy = pool.clone();
+    let market_import = tokio::task::spawn(async {
+        let market_import = if let Some(x) = MarketImport::new(pool_copy) {
+            x
+        } else {
+            return;
+        };
+
+        log::info!("Market import start");
+        if let Err(e) = market_import.task().await {
+            log::error!("Error running market import {:?}", e);
+        }
+        log::info!("Market import done");
+    });
+
     let eve_copy = eve.clone();
     let pool_copy = pool.clone();
     let sde = tokio::task::spawn(async {
@@ -43,10 +76,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let eve_copy = eve.clone();
     let pool_copy = pool.clone();
-    let character = tokio::task::spawn(async {
-        let mut market = Character::new(eve_copy, pool_copy);
+    let character = tokio::task::spawn(async move {
+        let mut market = Character::new(eve_copy.clone(), pool_copy);
 
         loop {
+            eve_copy.wait_for_esi().await;
             log::info!("Character start");
             if let Err(e) = market.task().await {
                 log::error!("Error running market task {:?}", e);
@@ -59,6 +93,131 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
+    let eve_copy = eve.clone();
+    let pool_copy = pool.clone();
+    let contract = tokio::task::spawn(async move {
+        let mut contract = Contract::new(eve_copy.clone(), pool_copy);
+
+        loop {
+            eve_copy.wait_for_esi().await;
+            log::info!("Contract start");
+            if let Err(e) = contract.task().await {
+                log::error!("Error running contract task {:?}", e);
+            }
+            log::info!("Contract done");
+
+            let next_run = duration_to_next_30_minute()
+                .unwrap_or_else(|_| Duration::from_secs(30 * 60));
+            tokio::time::sleep(next_run).await; // Run on the next 30 minute interval
+        }
+    });
+
+    let eve_copy = eve.clone();
+    let pool_copy = pool.clone();
+    let killboard = tokio::task::spawn(async {
+        let mut killboard = if let Some(x) = Killboard::new(eve_copy, pool_copy) {
+            x
+        } else {
+            log::info!("No zKillboard queue configured, skipping killmails");
+            return;
+        };
+
+        loop {
+            // RedisQ holds the request until a kill comes in, so there is
+            // no need to wait between the runs
+            if let Err(e) = killboard.task().await {
+                log::error!("Error running killboard task {:?}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+
+    let eve_copy = eve.clone();
+    let pool_copy = pool.clone();
+    let sovereignty = tokio::task::spawn(async move {
+        let mut sovereignty = Sovereignty::new(eve_copy.clone(), pool_copy);
+
+        loop {
+            eve_copy.wait_for_esi().await;
+            log::info!("Sovereignty start");
+            if let Err(e) = sovereignty.task().await {
+                log::error!("Error running sovereignty task {:?}", e);
+            }
+            log::info!("Sovereignty done");
+
+            // ESI caches the sovereignty map for one hour
+            tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+        }
+    });
+
+    let pool_copy = pool.clone();
+    let deadman = tokio::task::spawn(async {
+        let mut deadman = Deadman::new(pool_copy);
+
+        loop {
+            log::info!("Deadman start");
+            if let Err(e) = deadman.task().await {
+                log::error!("Error running deadman task {:?}", e);
+            }
+            log::info!("Deadman done");
+
+            tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+        }
+    });
+
+    let pool_copy = pool.clone();
+    let stock = tokio::task::spawn(async {
+        let mut stock = Stock::new(pool_copy);
+
+        loop {
+            log::info!("Stock start");
+            if let Err(e) = stock.task().await {
+                log::error!("Error running stock task {:?}", e);
+            }
+            log::info!("Stock done");
+
+            // Assets are synced every 30 minutes
+            tokio::time::sleep(Duration::from_secs(30 * 60)).await;
+        }
+    });
+
+    let eve_copy = eve.clone();
+    let pool_copy = pool.clone();
+    let prices = tokio::task::spawn(async move {
+        let mut market = Market::new(eve_copy.clone(), pool_copy);
+
+        loop {
+            eve_copy.wait_for_esi().await;
+            log::info!("Prices start");
+            if let Err(e) = market.prices().await {
+                log::error!("Error running prices task {:?}", e);
+            }
+            log::info!("Prices done");
+
+            // ESI caches the prices and cost indices for one hour
+            tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+        }
+    });
+
+    let eve_copy = eve.clone();
+    let pool_copy = pool.clone();
+    let undercut = tokio::task::spawn(async move {
+        let mut market = Market::new(eve_copy.clone(), pool_copy);
+
+        loop {
+            eve_copy.wait_for_esi().await;
+            log::info!("Undercut start");
+            if let Err(e) = market.undercut().await {
+                log::error!("Error running undercut task {:?}", e);
+            }
+            log::info!("Undercut done");
+
+            let next_run = duration_to_next_30_minute()
+                .unwrap_or_else(|_| Duration::from_secs(30 * 60));
+            tokio::time::sleep(next_run).await; // Run on the next 30 minute interval
+        }
+    });
+
     /*let eve_copy = eve.clone();
     let pool_copy = pool.clone();
     let market = tokio::task::spawn(async {
@@ -79,8 +238,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let _ = tokio::join!(
         character,
+        contract,
+        deadman,
+        killboard,
         //market,
+        market_import,
+        prices,
         sde,
+        sovereignty,
+        status,
+        stock,
+        undercut,
     );
 
     Ok(())
@@ -3,7 +3,7 @@ use crate::time::previous_30_minute;
 
 use cachem::v2::ConnectionPool;
 use caph_db_v2::*;
-use caph_eve_data_wrapper::{EveDataWrapper, IndustryService, MarketService, SolarSystemId, SystemService, TypeId};
+use caph_eve_data_wrapper::{EveDataWrapper, IndustryService, InsuranceService, LocationId, MarketOrder, MarketService, RegionId, SolarSystemId, SystemService, TypeId};
 use chrono::{DateTime, Utc};
 use futures::stream::{FuturesUnordered, StreamExt};
 use std::collections::HashMap;
@@ -14,6 +14,11 @@ pub struct Market {
 }
 
 impl Market {
+    /// Jita IV - Moon 4 - Caldari Navy Assembly Plant
+    const JITA_STATION: LocationId = LocationId(60003760);
+    /// Region of Jita
+    const THE_FORGE:    RegionId   = RegionId(10000002);
+
     pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Self {
         Self {
             eve,
@@ -25,20 +30,48 @@ impl Market {
     /// entries from all markets and writes them into the database.
     pub async fn task(&mut self) -> Result<(), CollectorError> {
         log::info!("Loading eve services");
-        let market_service  = self.eve.market().await?;
-        let system_service  = self.eve.systems().await?;
-        let indutry_service = self.eve.industry().await?;
+        let market_service = self.eve.market().await?;
+        let system_service = self.eve.systems().await?;
         log::info!("Services loaded");
 
-        let _ = tokio::join! {
-            self.market_data(market_service.clone(), system_service),
-            self.market_price(market_service.clone()),
-            self.industry_cost(indutry_service)
+        self.market_data(market_service, system_service).await?;
+
+        Ok(())
+    }
+
+    /// Collects the adjusted market prices, the industry cost indices and the
+    /// ship insurance prices and records the prices and cost indices in their
+    /// history.
+    pub async fn prices(&mut self) -> Result<(), CollectorError> {
+        let market_service    = self.eve.market().await?;
+        let industry_service  = self.eve.industry().await?;
+        let insurance_service = self.eve.insurance().await?;
+
+        let (price, cost, insurance) = tokio::join! {
+            self.market_price(market_service),
+            self.industry_cost(industry_service),
+            self.insurance_price(insurance_service)
         };
+        price?;
+        cost?;
+        insurance?;
 
         Ok(())
     }
 
+    /// Collects the sell orders of The Forge and updates the Jita undercut
+    /// statistics.
+    ///
+    /// Only runs on a single region, so that it can run every 30 minutes
+    /// without the full market sync of `task`.
+    pub async fn undercut(&mut self) -> Result<(), CollectorError> {
+        let market_service = self.eve.market().await?;
+        let timestamp = previous_30_minute(Utc::now().timestamp() as u64)? * 1_000;
+
+        let orders = market_service.orders(Self::THE_FORGE).await?;
+        self.market_undercut(&orders, timestamp).await
+    }
+
     async fn market_data(
         &self,
         market_service: MarketService,
@@ -75,10 +108,10 @@ impl Market {
             let market_info = MarketInfoEntry {
                 issued:       issued.timestamp() as u64 * 1000,
                 expire:       expire.timestamp() as u64 * 100,
-                order_id:     entry.order_id.into(),
-                location_id:  entry.location_id.into(),
-                system_id:    entry.system_id.into(),
-                type_id:      entry.type_id.into(),
+                order_id:     entry.order_id,
+                location_id:  entry.location_id,
+                system_id:    entry.system_id,
+                type_id:      entry.type_id,
                 volume_total: entry.volume_total,
                 price:        entry.price,
                 is_buy_order: entry.is_buy_order,
@@ -88,10 +121,10 @@ impl Market {
 
         for entry in entries {
             let market_order = MarketOrderEntry {
-                order_id:      entry.order_id.into(),
+                order_id:      entry.order_id,
                 timestamp,
                 volume_remain: entry.volume_remain,
-                type_id:       entry.type_id.into(),
+                type_id:       entry.type_id,
             };
             market_orders
                 .entry(entry.type_id)
@@ -107,6 +140,60 @@ impl Market {
         Ok(())
     }
 
+    /// Compares the Jita sell orders with the previous snapshot and counts
+    /// how often the best price was undercut in between.
+    async fn market_undercut(
+        &self,
+        orders:    &[MarketOrder],
+        timestamp: u64,
+    ) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let mut jita = HashMap::new();
+        orders
+            .iter()
+            .filter(|x| !x.is_buy_order && x.location_id == Self::JITA_STATION)
+            .for_each(|x| {
+                jita
+                    .entry(x.type_id)
+                    .or_insert_with(Vec::new)
+                    .push(x)
+            });
+
+        let type_ids = jita.keys().copied().collect::<Vec<_>>();
+        let previous = con
+            .mget::<_, _, MarketUndercutEntry>(CacheName::MarketUndercut, type_ids.clone())
+            .await?;
+
+        let mut entries = HashMap::new();
+        for (type_id, previous) in type_ids.into_iter().zip(previous) {
+            let orders = &jita[&type_id];
+            let best_price = orders
+                .iter()
+                .map(|x| x.price)
+                .fold(f32::MAX, f32::min);
+
+            let entry = if let Some(mut entry) = previous {
+                let undercuts = count_undercuts(
+                    orders,
+                    entry.timestamp,
+                    entry.best_price
+                )?;
+                entry.push(best_price, timestamp, undercuts);
+                entry
+            } else {
+                MarketUndercutEntry::new(type_id, best_price, timestamp)
+            };
+            entries.insert(type_id, entry);
+        }
+
+        if !entries.is_empty() {
+            con.mset(CacheName::MarketUndercut, entries).await?;
+        }
+
+        Ok(())
+    }
+
     async fn market_price(&self, market_service: MarketService) -> Result<(), CollectorError> {
         let mut con = self.pool.acquire().await?;
 
@@ -118,7 +205,33 @@ impl Market {
             .map(MarketPriceEntry::from)
             .map(|x| (x.type_id, x))
             .collect::<HashMap<TypeId, MarketPriceEntry>>();
-        con.mset(CacheName::MarketPrice, prices).await.unwrap();
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let history = prices
+            .values()
+            .map(|x| (x.type_id, vec![MarketPriceHistoryEntry::new(now, x)]))
+            .collect::<HashMap<_, _>>();
+        con.mset(CacheName::MarketPrice, prices).await?;
+        con.mset(CacheName::MarketPriceHistory, history).await?;
+        Ok(())
+    }
+
+    async fn insurance_price(&self, insurance_service: InsuranceService) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let prices = insurance_service
+            .prices()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(InsurancePriceEntry::from)
+            .map(|x| (x.type_id, x))
+            .collect::<HashMap<TypeId, InsurancePriceEntry>>();
+
+        log::info!("Collected {} insurance prices", prices.len());
+        if !prices.is_empty() {
+            con.mset(CacheName::InsurancePrice, prices).await?;
+        }
         Ok(())
     }
 
@@ -132,7 +245,79 @@ impl Market {
             .map(IndustryCostEntry::from)
             .map(|x| (x.solar_system_id, x))
             .collect::<HashMap<SolarSystemId, IndustryCostEntry>>();
-        con.mset(CacheName::IndustryCost, cost).await.unwrap();
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let history = cost
+            .values()
+            .map(|x| {
+                let entry = IndustryCostHistoryEntry::new(now, x.cost_indices.clone());
+                (x.solar_system_id, vec![entry])
+            })
+            .collect::<HashMap<_, _>>();
+        con.mset(CacheName::IndustryCost, cost).await?;
+        con.mset(CacheName::IndustryCostHistory, history).await?;
         Ok(())
     }
 }
+
+/// Counts all orders that were placed or modified after `since` and are
+/// cheaper than the previous best price.
+///
+/// Modifying an order resets its issued date, so every price change is
+/// counted.
+fn count_undercuts(
+    orders:     &[&MarketOrder],
+    since:      u64,
+    best_price: f32,
+) -> Result<u32, CollectorError> {
+    let mut undercuts = 0u32;
+    for order in orders {
+        let issued = order.issued.parse::<DateTime<Utc>>()?.timestamp() as u64 * 1_000;
+        if issued > since && order.price < best_price {
+            undercuts += 1;
+        }
+    }
+    Ok(undercuts)
+}
+
+#[cfg(test)]
+mod undercut_tests {
+    use super::*;
+
+    fn order(issued: &str, price: f32) -> MarketOrder {
+        MarketOrder {
+            duration:      90,
+            is_buy_order:  false,
+            issued:        issued.into(),
+            location_id:   Market::JITA_STATION,
+            min_volume:    1,
+            order_id:      1.into(),
+            price,
+            range:         "region".into(),
+            system_id:     30000142.into(),
+            type_id:       34.into(),
+            volume_remain: 1,
+            volume_total:  1,
+        }
+    }
+
+    #[test]
+    fn undercuts_after_snapshot() {
+        // 2021-06-01T12:00:00Z
+        let since = 1622548800000;
+        let orders = vec![
+            order("2021-06-01T11:59:00Z", 4.0),
+            order("2021-06-01T12:10:00Z", 4.5),
+            order("2021-06-01T12:15:00Z", 4.9),
+            order("2021-06-01T12:20:00Z", 5.1),
+        ];
+        let orders = orders.iter().collect::<Vec<_>>();
+
+        assert_eq!(count_undercuts(&orders, since, 5.0).unwrap(), 2);
+    }
+
+    #[test]
+    fn no_orders() {
+        assert_eq!(count_undercuts(&[], 0, 5.0).unwrap(), 0);
+    }
+}
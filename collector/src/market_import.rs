@@ -0,0 +1,251 @@
+use crate::error::CollectorError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::*;
+use caph_eve_data_wrapper::TypeId;
+use chrono::{NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Seeds the market caches from a CSV dump, so that a fresh deployment has
+/// prices before the market collector has run.
+///
+/// Supported are the aggregate dump of Fuzzwork
+/// (https://market.fuzzwork.co.uk/aggregatecsv.csv) and the region price
+/// history exports of Adam4EVE. The format is detected by the header. Only
+/// items without a price are imported, prices from ESI are never replaced.
+pub struct MarketImport {
+    pool:   ConnectionPool,
+    /// Path or url of the dump
+    source: String,
+}
+
+impl MarketImport {
+    /// Path or http url of an uncompressed dump, if not set nothing is
+    /// imported
+    const ENV_SOURCE: &'static str = "MARKET_IMPORT_SOURCE";
+    /// The Forge, region of Jita
+    const REGION:     u32          = 10000002;
+
+    /// Creates a new instance, returns [None] if no source is configured
+    pub fn new(pool: ConnectionPool) -> Option<Self> {
+        let source = std::env::var(Self::ENV_SOURCE).ok()?;
+
+        Some(Self {
+            pool,
+            source,
+        })
+    }
+
+    /// Loads the dump and writes the prices of all items that do not have a
+    /// price yet.
+    pub async fn task(&self) -> Result<(), CollectorError> {
+        let content = self.load().await?;
+        let now = Utc::now().timestamp_millis() as u64;
+        let prices = parse_dump(&content, now)?;
+
+        let mut con = self.pool.acquire().await?;
+        let existing = con
+            .keys::<_, TypeId>(CacheName::MarketPrice)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut history = HashMap::new();
+        for price in prices.into_iter().filter(|x| !existing.contains(&x.type_id)) {
+            history
+                .entry(price.type_id)
+                .or_insert_with(Vec::new)
+                .push(price);
+        }
+
+        let mut entries = HashMap::new();
+        let mut history_entries = HashMap::new();
+        for (type_id, mut prices) in history {
+            prices.sort_by_key(|x| x.timestamp);
+
+            let latest = &prices[prices.len() - 1];
+            let entry = MarketPriceEntry {
+                adjusted_price: latest.price,
+                average_price:  latest.price,
+                type_id,
+            };
+            let history = prices
+                .iter()
+                .map(|x| MarketPriceHistoryEntry {
+                    timestamp:      x.timestamp,
+                    adjusted_price: x.price,
+                    average_price:  x.price,
+                })
+                .collect::<Vec<_>>();
+
+            entries.insert(type_id, entry);
+            history_entries.insert(type_id, history);
+        }
+
+        log::info!("Importing {} market prices from {}", entries.len(), self.source);
+        if !entries.is_empty() {
+            con.mset(CacheName::MarketPrice, entries).await?;
+            con.mset(CacheName::MarketPriceHistory, history_entries).await?;
+        }
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<String, CollectorError> {
+        if self.source.starts_with("http://") || self.source.starts_with("https://") {
+            reqwest::get(&self.source)
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .map_err(Into::into)
+        } else {
+            tokio::fs::read_to_string(&self.source)
+                .await
+                .map_err(|e| CollectorError::ImportError(format!("{}: {}", self.source, e)))
+        }
+    }
+}
+
+/// Price of an item at a point in time
+#[derive(Debug, PartialEq)]
+struct ImportedPrice {
+    type_id:   TypeId,
+    /// Timestamp in milliseconds
+    timestamp: u64,
+    price:     f32,
+}
+
+/// Parses a dump, the format is detected by the header
+///
+/// # Params
+///
+/// `content` -> Content of the CSV file
+/// `now`     -> Timestamp for dumps without dates
+///
+fn parse_dump(content: &str, now: u64) -> Result<Vec<ImportedPrice>, CollectorError> {
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or_default().trim();
+
+    if header.starts_with("what,") {
+        parse_fuzzwork(lines, now)
+    } else if header.starts_with("type_id;region_id;date;") {
+        parse_adam4eve(lines)
+    } else {
+        Err(CollectorError::ImportError(format!("Unknown market dump header {}", header)))
+    }
+}
+
+/// Fuzzwork aggregates, the first column is `region|type|is_buy`, the
+/// median of the sell orders is used as price
+///
+/// `what,weightedaverage,maxval,minval,stddev,median,volume,numorders,fivepercent,orderSet`
+fn parse_fuzzwork<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    now:   u64,
+) -> Result<Vec<ImportedPrice>, CollectorError> {
+    let mut prices = Vec::new();
+    for line in lines.filter(|x| !x.trim().is_empty()) {
+        let columns = line.split(',').collect::<Vec<_>>();
+        let what = columns[0].split('|').collect::<Vec<_>>();
+        if columns.len() < 6 || what.len() != 3 {
+            return Err(CollectorError::ImportError(format!("Invalid line {}", line)));
+        }
+
+        if parse_column::<u32>(what[0], line)? != MarketImport::REGION || what[2] != "false" {
+            continue;
+        }
+
+        let price = parse_column::<f32>(columns[5], line)?;
+        if price > 0f32 {
+            prices.push(ImportedPrice {
+                type_id:   parse_column::<u32>(what[1], line)?.into(),
+                timestamp: now,
+                price,
+            });
+        }
+    }
+    Ok(prices)
+}
+
+/// Adam4EVE region price history, the average sell price of every day is
+/// used as price
+///
+/// `type_id;region_id;date;buy_price_low;buy_price_avg;buy_price_high;sell_price_low;sell_price_avg;sell_price_high`
+fn parse_adam4eve<'a>(
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<Vec<ImportedPrice>, CollectorError> {
+    let mut prices = Vec::new();
+    for line in lines.filter(|x| !x.trim().is_empty()) {
+        let columns = line.split(';').collect::<Vec<_>>();
+        if columns.len() < 9 {
+            return Err(CollectorError::ImportError(format!("Invalid line {}", line)));
+        }
+
+        if parse_column::<u32>(columns[1], line)? != MarketImport::REGION {
+            continue;
+        }
+
+        let date = NaiveDate::parse_from_str(columns[2].trim(), "%Y-%m-%d")?;
+        // Adam4EVE leaves the column empty if there were no sell orders
+        let price = columns[7].trim().parse::<f32>().unwrap_or_default();
+        if price > 0f32 {
+            prices.push(ImportedPrice {
+                type_id:   parse_column::<u32>(columns[0], line)?.into(),
+                timestamp: date.and_hms(0, 0, 0).timestamp() as u64 * 1_000,
+                price,
+            });
+        }
+    }
+    Ok(prices)
+}
+
+fn parse_column<T: std::str::FromStr>(column: &str, line: &str) -> Result<T, CollectorError> {
+    column
+        .trim()
+        .parse::<T>()
+        .map_err(|_| CollectorError::ImportError(format!("Invalid value {} in line {}", column, line)))
+}
+
+#[cfg(test)]
+mod market_import_tests {
+    use super::*;
+
+    #[test]
+    fn fuzzwork() {
+        let content = "\
+what,weightedaverage,maxval,minval,stddev,median,volume,numorders,fivepercent,orderSet
+10000002|34|false,5.1,10,4,1.2,5.0,1000,20,4.5,1
+10000002|34|true,4.1,4.5,1,1.2,4.2,1000,20,4.4,1
+10000043|34|false,5.3,10,4,1.2,5.5,1000,20,4.5,1
+10000002|35|false,0,0,0,0,0,0,0,0,1
+";
+        let prices = parse_dump(content, 100).unwrap();
+        assert_eq!(prices, vec![ImportedPrice {
+            type_id:   34.into(),
+            timestamp: 100,
+            price:     5.0,
+        }]);
+    }
+
+    #[test]
+    fn adam4eve() {
+        let content = "\
+type_id;region_id;date;buy_price_low;buy_price_avg;buy_price_high;sell_price_low;sell_price_avg;sell_price_high
+34;10000002;2021-06-01;4.0;4.1;4.2;5.0;5.1;5.2
+34;10000002;2021-06-02;4.0;4.1;4.2;5.0;5.3;5.2
+35;10000002;2021-06-02;4.0;4.1;4.2;;;
+34;10000043;2021-06-02;4.0;4.1;4.2;5.0;5.6;5.2
+";
+        let prices = parse_dump(content, 0).unwrap();
+        assert_eq!(prices.len(), 2);
+        // 2021-06-02T00:00:00Z
+        assert_eq!(prices[1].timestamp, 1622592000000);
+        assert_eq!(prices[1].price, 5.3);
+    }
+
+    #[test]
+    fn unknown_header() {
+        assert!(parse_dump("typeID,price\n34,5.0", 0).is_err());
+        assert!(parse_dump("what,weightedaverage\n34,5.0", 0).is_err());
+    }
+}
@@ -2,17 +2,29 @@ use crate::error::CollectorError;
 
 use cachem::v2::ConnectionPool;
 use caph_db_v2::*;
-use caph_eve_data_wrapper::{EveDataWrapper, SolarsystemEntry};
+use caph_eve_data_wrapper::{EveDataWrapper, ItemId, SolarsystemEntry, TypeId};
+use chrono::Utc;
 use std::collections::HashMap;
 
 pub struct Sde {
-    eve:  EveDataWrapper,
-    pool: ConnectionPool,
+    eve:       EveDataWrapper,
+    pool:      ConnectionPool,
+    /// Number of previous versions to keep of every blueprint
+    retention: usize,
 }
 
 impl Sde {
+    /// Number of previous blueprint versions that are kept
+    const ENV_RETENTION:     &'static str = "SDE_HISTORY_RETENTION";
+    const DEFAULT_RETENTION: usize        = 5;
+
     pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Self {
-        Self { eve, pool }
+        let retention = std::env::var(Self::ENV_RETENTION)
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(Self::DEFAULT_RETENTION);
+
+        Self { eve, pool, retention }
     }
 
     pub async fn run(&mut self) -> Result<(), CollectorError> {
@@ -22,7 +34,11 @@ impl Sde {
         self.save_items(&self.eve).await?;
         self.save_names(&self.eve).await?;
         self.save_system_region(&self.eve).await?;
+        self.save_universe_graph(&self.eve).await?;
 
+        for x in self.eve.skipped_sde_files() {
+            log::error!("Skipped SDE file {}", x);
+        }
         Ok(())
     }
 
@@ -34,7 +50,10 @@ impl Sde {
         let unique_names = sde.names().await?;
 
         let mut stations = stations.collect_names();
-        let types = types.collect_names();
+        let types = types
+            .collect_names()
+            .into_iter()
+            .map(|(id, name)| (ItemId::from(id), name));
         let unique_names = unique_names.collect_names();
 
         stations.extend(types);
@@ -140,6 +159,31 @@ impl Sde {
         Ok(())
     }
 
+    /// Collects the stargate connections of all systems and stores them as
+    /// jump graph
+    async fn save_universe_graph(&self, sde: &EveDataWrapper) -> Result<(), CollectorError> {
+        let system_service = sde.systems().await?;
+
+        let mut con = self.pool.acquire().await?;
+
+        let security = system_service
+            .eve_systems()
+            .iter()
+            .map(|x| (x.solar_system_id, x.security))
+            .collect::<HashMap<_, _>>();
+        let entries = system_service
+            .jump_graph()
+            .into_iter()
+            .map(|(sid, neighbours)| {
+                let security = security.get(&sid).copied().unwrap_or_default();
+                (sid, UniverseGraphEntry::new(sid, security, neighbours))
+            })
+            .collect::<HashMap<_, _>>();
+        con.mset(CacheName::UniverseGraph, entries).await.unwrap();
+
+        Ok(())
+    }
+
     async fn save_blueprints(&self, sde: &EveDataWrapper) -> Result<(), CollectorError> {
         let blueprint_service = sde.blueprints().await?;
 
@@ -150,6 +194,44 @@ impl Sde {
             .iter()
             .map(|(bid, entry)| (*bid, BlueprintEntry::from(entry)))
             .collect::<HashMap<_, _>>();
+
+        // Keep the versions that are about to be replaced by the new import
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Blueprint)
+            .await?;
+        let changed = con
+            .mget::<_, _, BlueprintEntry>(CacheName::Blueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| entries.get(&x.bid) != Some(x))
+            .collect::<Vec<_>>();
+
+        if !changed.is_empty() && self.retention > 0 {
+            let replaced = Utc::now().timestamp_millis() as u64;
+            let bids = changed
+                .iter()
+                .map(|x| x.bid)
+                .collect::<Vec<_>>();
+            let mut history = con
+                .mget::<_, _, Vec<BlueprintHistoryEntry>>(CacheName::BlueprintHistory, bids)
+                .await?
+                .into_iter()
+                .map(|x| x.unwrap_or_default());
+
+            let mut updates = HashMap::new();
+            for blueprint in changed {
+                let mut versions = history.next().unwrap_or_default();
+                let bid = blueprint.bid;
+
+                // Newest version first
+                versions.insert(0, BlueprintHistoryEntry::new(replaced, blueprint));
+                versions.truncate(self.retention);
+                updates.insert(bid, versions);
+            }
+            con.mset(CacheName::BlueprintHistory, updates).await?;
+        }
+
         con.mset(CacheName::Blueprint, entries).await.unwrap();
 
         Ok(())
@@ -0,0 +1,50 @@
+use crate::error::CollectorError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::*;
+use caph_eve_data_wrapper::EveDataWrapper;
+use std::collections::HashMap;
+
+pub struct Sovereignty {
+    eve:  EveDataWrapper,
+    pool: ConnectionPool,
+}
+
+impl Sovereignty {
+    pub fn new(eve: EveDataWrapper, pool: ConnectionPool) -> Self {
+        Self {
+            eve,
+            pool
+        }
+    }
+
+    /// Collects the sovereignty map and the faction warfare state of all
+    /// systems and writes them into the database.
+    pub async fn task(&mut self) -> Result<(), CollectorError> {
+        let sov_service = self.eve.sovereignty().await?;
+
+        let mut entries = HashMap::new();
+        for system in sov_service.map().await? {
+            let entry = entries
+                .entry(system.system_id)
+                .or_insert_with(|| SovereigntyEntry::new(system.system_id));
+            entry.alliance_id    = system.alliance_id;
+            entry.corporation_id = system.corporation_id;
+            entry.faction_id     = system.faction_id;
+        }
+
+        for system in sov_service.fw_systems().await? {
+            let entry = entries
+                .entry(system.solar_system_id)
+                .or_insert_with(|| SovereigntyEntry::new(system.solar_system_id));
+            entry.fw_owner     = Some(system.owner_faction_id);
+            entry.fw_occupier  = Some(system.occupier_faction_id);
+            entry.fw_contested = Some(system.contested);
+        }
+
+        let mut con = self.pool.acquire().await?;
+        con.mset(CacheName::Sovereignty, entries).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use crate::error::CollectorError;
+use crate::webhook::Webhook;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterAssetEntry, CorporationAssetEntry, MarketInfoEntry, MarketOrderEntry, StockRuleEntry, UserEntry};
+use caph_eve_data_wrapper::ItemId;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Checks the stock rules of all users and sends an alert to the webhook of
+/// the main when the stock falls below the minimum
+pub struct Stock {
+    pool: ConnectionPool,
+}
+
+impl Stock {
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    pub async fn task(&mut self) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let keys = con
+            .keys::<_, Uuid>(CacheName::StockRule)
+            .await?;
+        let rules = con
+            .mget::<_, _, StockRuleEntry>(CacheName::StockRule, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let character_assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CorporationAsset)
+            .await?;
+        let corporation_assets = con
+            .mget::<_, _, CorporationAssetEntry>(CacheName::CorporationAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut updated = HashMap::new();
+        for mut rule in rules {
+            let user = if let Some(x) = con
+                .get::<_, _, UserEntry>(CacheName::User, rule.user_id)
+                .await? {
+                x
+            } else {
+                continue;
+            };
+            let mut user_ids = user
+                .aliase
+                .iter()
+                .map(|x| x.user_id)
+                .collect::<Vec<_>>();
+            user_ids.push(user.user_id);
+            let mut corp_ids = user
+                .aliase
+                .iter()
+                .map(|x| x.corp_id)
+                .collect::<Vec<_>>();
+            corp_ids.push(user.corp_id);
+
+            let mut current = character_assets
+                .iter()
+                .filter(|x| user_ids.contains(&x.user_id))
+                .filter(|x| x.type_id == rule.type_id && x.location_id == rule.location_id)
+                .map(|x| x.quantity)
+                .sum::<u32>();
+            current += corporation_assets
+                .iter()
+                .filter(|x| corp_ids.contains(&x.corporation_id))
+                .filter(|x| x.type_id == rule.type_id && x.location_id == rule.location_id)
+                .map(|x| x.quantity)
+                .sum::<u32>();
+            if rule.include_market {
+                current += self.market_stock(&rule).await?;
+            }
+
+            let alerted = if current >= rule.minimum {
+                false
+            } else if rule.alerted {
+                true
+            } else {
+                self.alert(&rule, current).await?;
+                true
+            };
+
+            if rule.current != current || rule.alerted != alerted {
+                rule.current = current;
+                rule.alerted = alerted;
+                updated.insert(rule.id, rule);
+            }
+        }
+
+        if !updated.is_empty() {
+            con.mset(CacheName::StockRule, updated).await?;
+        }
+        Ok(())
+    }
+
+    /// Volume of the open sell orders at the location of the rule
+    async fn market_stock(&self, rule: &StockRuleEntry) -> Result<u32, CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        // Only the orders of the latest snapshot are still open
+        let orders = con
+            .get::<_, _, Vec<MarketOrderEntry>>(CacheName::MarketOrder, rule.type_id)
+            .await?
+            .unwrap_or_default();
+        let latest = orders
+            .iter()
+            .map(|x| x.timestamp)
+            .max()
+            .unwrap_or_default();
+        let orders = orders
+            .into_iter()
+            .filter(|x| x.timestamp == latest)
+            .collect::<Vec<_>>();
+
+        let order_ids = orders
+            .iter()
+            .map(|x| x.order_id)
+            .collect::<Vec<_>>();
+        let volume = con
+            .mget::<_, _, MarketInfoEntry>(CacheName::MarketInfo, order_ids)
+            .await?
+            .into_iter()
+            .zip(orders)
+            .filter_map(|(info, order)| info.map(|x| (x, order.volume_remain)))
+            .filter(|(x, _)| !x.is_buy_order && x.location_id == rule.location_id)
+            .map(|(_, volume)| volume)
+            .sum::<u32>();
+        Ok(volume)
+    }
+
+    /// Sends the alert to the webhook of the main, if one is configured
+    async fn alert(
+        &self,
+        rule:    &StockRuleEntry,
+        current: u32,
+    ) -> Result<(), CollectorError> {
+        let mut con = self.pool.acquire().await?;
+
+        let webhook = if let Some(x) = con
+            .get::<_, _, String>(CacheName::CharacterWebhook, rule.user_id)
+            .await? {
+            x
+        } else {
+            return Ok(());
+        };
+        let name = con
+            .get::<_, _, String>(CacheName::Name, ItemId::from(rule.type_id))
+            .await?
+            .unwrap_or_else(|| rule.type_id.to_string());
+
+        let message = format!(
+            "**Low stock**\n{} at {}: {} of {}",
+            name,
+            rule.location_id,
+            current,
+            rule.minimum
+        );
+        Webhook::send(&webhook, &message).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::error::CollectorError;
+
+use reqwest::Client;
+use reqwest::multipart::{Form, Part};
+use serde_json::json;
+
+/// Sends messages to a discord compatible webhook
+pub struct Webhook;
+
+impl Webhook {
+    /// Discord rejects messages that are longer
+    const MAX_LENGTH: usize = 2000;
+
+    /// Posts the given message to the webhook, errors are only logged
+    pub async fn send(url: &str, message: &str) {
+        let content = message
+            .chars()
+            .take(Self::MAX_LENGTH)
+            .collect::<String>();
+
+        let result = Client::new()
+            .post(url)
+            .json(&json!({ "content": content }))
+            .send()
+            .await
+            .and_then(|x| x.error_for_status());
+        if let Err(e) = result {
+            log::error!("Error sending webhook {:?}", e);
+        }
+    }
+
+    /// Posts the given message together with a file to the webhook
+    pub async fn send_file(
+        url:       &str,
+        message:   &str,
+        file_name: &str,
+        file:      Vec<u8>,
+    ) -> Result<(), CollectorError> {
+        let content = message
+            .chars()
+            .take(Self::MAX_LENGTH)
+            .collect::<String>();
+        let form = Form::new()
+            .text("content", content)
+            .part("file", Part::bytes(file).file_name(file_name.to_string()));
+
+        Client::new()
+            .post(url)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "with_grpc")]
+    tonic_build::compile_protos("proto/cache.proto").unwrap();
+}
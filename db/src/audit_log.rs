@@ -0,0 +1,47 @@
+use caph_eve_data_wrapper::CharacterId;
+use cachem::Parse;
+
+use crate::{DAY, TimeSeries, TimeSeriesCache};
+
+/// Audit log of every user, keyed by the acting user
+pub type AuditLogCache = TimeSeriesCache<CharacterId, AuditLogEntry>;
+
+/// Sensitive operation done by a user
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct AuditLogEntry {
+    /// Timestamp in milliseconds, unique per user
+    pub timestamp: u64,
+    /// Kind of the operation, for example `login` or `export`
+    pub action:    String,
+    /// Character the operation was done for, if it was not the acting user
+    pub target:    Option<CharacterId>,
+    /// Additional information, like the name of the export
+    pub details:   String,
+}
+
+impl AuditLogEntry {
+    pub fn new(
+        timestamp: u64,
+        action:    String,
+        target:    Option<CharacterId>,
+        details:   String,
+    ) -> Self {
+        Self {
+            timestamp,
+            action,
+            target,
+            details,
+        }
+    }
+}
+
+impl TimeSeries for AuditLogEntry {
+    const NAME: &'static str = "audit_log";
+    const FILE: &'static str = "./db/audit_log.cachem";
+    const MAX_AGE: Option<u64> = Some(2 * 365 * DAY);
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
@@ -1,17 +1,44 @@
 use cachem::v2::*;
 use caph_db_v2::*;
+use std::sync::Arc;
 
 macro_rules! load_and_register {
-    ($name:path, $cache:ident, $cnc:ident, $server:ident) => {
-        let x = $cache::new($cnc.clone());
+    // Only reachable over the cachem protocol, used for caches that contain
+    // credentials, the query server has no authentication
+    ($name:path, $cache:ident, $cnc:ident, $server:ident, $invalidation:ident) => {
+        let x = Arc::new($cache::new($cnc.clone()));
         x.load().await;
-        $server.add($name, x.into());
+        $server.add($name, SharedCache::new(x, $name.into(), $invalidation.clone()).into());
+    };
+    // Readable over the query server
+    ($name:path, $cache:ident, $cnc:ident, $server:ident, $invalidation:ident, $query:ident) => {
+        let x = Arc::new($cache::new($cnc.clone()));
+        x.load().await;
+        #[cfg(feature = "with_serde")]
+        $query.add(x.name(), x.clone());
+        $server.add($name, SharedCache::new(x, $name.into(), $invalidation.clone()).into());
+    };
+    // Readable over the query server and read- and writable over gRPC, only
+    // for market and SDE caches, gRPC has no authentication
+    ($name:path, $cache:ident, $cnc:ident, $server:ident, $invalidation:ident, $query:ident, $grpc:ident) => {
+        let x = Arc::new($cache::new($cnc.clone()));
+        x.load().await;
+        #[cfg(feature = "with_serde")]
+        $query.add(x.name(), x.clone());
+        #[cfg(feature = "with_grpc")]
+        $grpc.add(x.name(), $name.into(), x.clone());
+        $server.add($name, SharedCache::new(x, $name.into(), $invalidation.clone()).into());
     };
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (cnc, mut server) = Server::new("0.0.0.0:55555".into());
+    let invalidation = Invalidation::new();
+    #[cfg(feature = "with_serde")]
+    let mut query = QueryServer::default();
+    #[cfg(feature = "with_grpc")]
+    let mut grpc = GrpcServer::new(invalidation.clone());
 
     let market_info = MarketInfoCache::new(cnc.clone());
     //market_info.load().await;
@@ -19,23 +46,83 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let market_order = MarketOrderCache::new(cnc.clone(), market_info.clone());
     //market_order.load().await;
 
-    server.add(CacheName::MarketInfo, market_info.clone().into());
-    server.add(CacheName::MarketOrder, market_order.into());
-
-    load_and_register!(CacheName::Blueprint,            BlueprintCache,            cnc, server);
-    load_and_register!(CacheName::CharacterAsset,       CharacterAssetCache,       cnc, server);
-    load_and_register!(CacheName::CharacterBlueprint,   CharacterBlueprintCache,   cnc, server);
-    load_and_register!(CacheName::CharacterFitting,     CharacterFittingCache,     cnc, server);
-    load_and_register!(CacheName::CorporationBlueprint, CorporationBlueprintCache, cnc, server);
-    load_and_register!(CacheName::IndustryCost,         IndustryCostCache,         cnc, server);
-    load_and_register!(CacheName::Item,                 ItemCache,                 cnc, server);
-    load_and_register!(CacheName::Name,                 NameCache,                 cnc, server);
-    load_and_register!(CacheName::Project,              ProjectCache,              cnc, server);
-    load_and_register!(CacheName::MarketPrice,          MarketPriceCache,          cnc, server);
-    load_and_register!(CacheName::Reprocess,            ReprocessCache,            cnc, server);
-    load_and_register!(CacheName::Schematic,            SchematicCache,            cnc, server);
-    load_and_register!(CacheName::SystemRegion,         SystemRegionCache,         cnc, server);
-    load_and_register!(CacheName::User,                 UserCache,                 cnc, server);
+    server.add(
+        CacheName::MarketInfo,
+        SharedCache::new(Arc::new(market_info.clone()), CacheName::MarketInfo.into(), invalidation.clone()).into()
+    );
+    server.add(
+        CacheName::MarketOrder,
+        SharedCache::new(Arc::new(market_order), CacheName::MarketOrder.into(), invalidation.clone()).into()
+    );
+    #[cfg(feature = "with_serde")]
+    query.add(market_info.name(), Arc::new(market_info.clone()));
+
+    load_and_register!(CacheName::Blueprint,             BlueprintCache,             cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::CharacterAsset,        CharacterAssetCache,        cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterBlueprint,    CharacterBlueprintCache,    cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterFitting,      CharacterFittingCache,      cnc, server, invalidation, query);
+    load_and_register!(CacheName::CorporationBlueprint,  CorporationBlueprintCache,  cnc, server, invalidation, query);
+    load_and_register!(CacheName::IndustryCost,          IndustryCostCache,          cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Item,                  ItemCache,                  cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Name,                  NameCache,                  cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Project,               ProjectCache,               cnc, server, invalidation, query);
+    load_and_register!(CacheName::MarketPrice,           MarketPriceCache,           cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Reprocess,             ReprocessCache,             cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Schematic,             SchematicCache,             cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::SystemRegion,          SystemRegionCache,          cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::User,                  UserCache,                  cnc, server, invalidation);
+    load_and_register!(CacheName::WalletTransaction,     WalletTransactionCache,     cnc, server, invalidation, query);
+    load_and_register!(CacheName::UniverseGraph,         UniverseGraphCache,         cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Contract,              ContractCache,              cnc, server, invalidation, query);
+    load_and_register!(CacheName::Killmail,              KillmailCache,              cnc, server, invalidation, query);
+    load_and_register!(CacheName::MarketUndercut,        MarketUndercutCache,        cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::Sovereignty,           SovereigntyCache,           cnc, server, invalidation, query);
+    load_and_register!(CacheName::CorporationAsset,      CorporationAssetCache,      cnc, server, invalidation, query);
+    load_and_register!(CacheName::CorporationStructure,  CorporationStructureCache,  cnc, server, invalidation, query);
+    load_and_register!(CacheName::EsiResponse,           EsiResponseCache,           cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterContract,     CharacterContractCache,     cnc, server, invalidation, query);
+    load_and_register!(CacheName::BlueprintHistory,      BlueprintHistoryCache,      cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterClone,        CharacterCloneCache,        cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterPlanet,       CharacterPlanetCache,       cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterSkill,        CharacterSkillCache,        cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterMining,       CharacterMiningCache,       cnc, server, invalidation, query);
+    load_and_register!(CacheName::CorporationMining,     CorporationMiningCache,     cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterNotification, CharacterNotificationCache, cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterCalendar,     CharacterCalendarCache,     cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterWebhook,      CharacterWebhookCache,      cnc, server, invalidation);
+    load_and_register!(CacheName::StructureFee,          StructureFeeCache,          cnc, server, invalidation, query);
+    load_and_register!(CacheName::Fitting,               FittingCache,               cnc, server, invalidation, query);
+    load_and_register!(CacheName::UserPreference,        UserPreferenceCache,        cnc, server, invalidation, query);
+    load_and_register!(CacheName::UserLogin,             UserLoginCache,             cnc, server, invalidation);
+    load_and_register!(CacheName::Session,               SessionCache,               cnc, server, invalidation);
+    load_and_register!(CacheName::SkillHistory,          SkillHistoryCache,          cnc, server, invalidation, query);
+    load_and_register!(CacheName::UserRole,              UserRoleCache,              cnc, server, invalidation, query);
+    load_and_register!(CacheName::CharacterSync,         CharacterSyncCache,         cnc, server, invalidation, query);
+    load_and_register!(CacheName::StockRule,             StockRuleCache,             cnc, server, invalidation, query);
+    load_and_register!(CacheName::PriceAlert,            PriceAlertCache,            cnc, server, invalidation, query);
+    load_and_register!(CacheName::NetWorthHistory,       NetWorthHistoryCache,       cnc, server, invalidation, query);
+    load_and_register!(CacheName::MarketPriceHistory,    MarketPriceHistoryCache,    cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::IndustryCostHistory,   IndustryCostHistoryCache,   cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::InsurancePrice,        InsurancePriceCache,        cnc, server, invalidation, query, grpc);
+    load_and_register!(CacheName::AuditLog,              AuditLogCache,              cnc, server, invalidation, query);
+    load_and_register!(CacheName::Workspace,             WorkspaceCache,             cnc, server, invalidation, query);
+
+    #[cfg(feature = "with_serde")]
+    tokio::spawn(async move {
+        query.listen("127.0.0.1:55556").await;
+    });
+    #[cfg(feature = "with_grpc")]
+    tokio::spawn(async move {
+        grpc.listen("127.0.0.1:55558").await;
+    });
+    // Subscribers like the server connect to this port to get notified about
+    // modified caches. It only listens locally, CAPH_DB_INVALIDATION_ADDRESS
+    // opens it for subscribers on other hosts.
+    let invalidation_address = std::env::var("CAPH_DB_INVALIDATION_ADDRESS")
+        .unwrap_or_else(|_| "127.0.0.1:55557".into());
+    tokio::spawn(async move {
+        invalidation.listen(&invalidation_address).await;
+    });
 
     server.listen_tcp().await;
 
@@ -0,0 +1,204 @@
+use async_trait::*;
+use caph_eve_data_wrapper::TypeId;
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use crate::{BlueprintEntry, Entries};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+type Idx = TypeId;
+type Val = Vec<BlueprintHistoryEntry>;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct BlueprintHistoryCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl BlueprintHistoryCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for BlueprintHistoryCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for BlueprintHistoryCache {
+    fn name(&self) -> String {
+        "blueprint_history".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for BlueprintHistoryCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for BlueprintHistoryCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for BlueprintHistoryCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for BlueprintHistoryCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for BlueprintHistoryCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/blueprint_history.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for BlueprintHistoryCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+/// Version of a blueprint that was replaced by a newer SDE import
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct BlueprintHistoryEntry {
+    /// Timestamp in milliseconds when this version was replaced
+    pub replaced:  u64,
+    pub blueprint: BlueprintEntry,
+}
+
+impl BlueprintHistoryEntry {
+    pub fn new(
+        replaced:  u64,
+        blueprint: BlueprintEntry,
+    ) -> Self {
+        Self {
+            replaced,
+            blueprint,
+        }
+    }
+}
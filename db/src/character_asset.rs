@@ -8,6 +8,8 @@ use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = ItemId;
 type Val = CharacterAssetEntry;
 type Typ = HashMap<Idx, Val>;
@@ -44,11 +46,13 @@ impl Cache for CharacterAssetCache {
             Command::Del => {
                 let key = Idx::read(buf).await.unwrap();
                 self.del(key).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::MDel => {
                 let keys = Vec::<Idx>::read(buf).await.unwrap();
                 self.mdel(keys).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::Get => {
@@ -172,9 +176,20 @@ impl Save for CharacterAssetCache {
     }
 }
 
+impl Entries for CharacterAssetCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct CharacterAssetEntry {
+    /// Item the asset is stored in, for example a container or a ship
+    pub container_id:  Option<ItemId>,
     pub item_id:       ItemId,
     pub location_flag: String,
     pub location_id:   LocationId,
@@ -185,7 +200,14 @@ pub struct CharacterAssetEntry {
 
 impl CharacterAssetEntry {
     pub fn from(x: CharacterAsset, user_id: CharacterId) -> Self {
+        let container_id = if x.location_type == "item" {
+            Some(ItemId(*x.location_id))
+        } else {
+            None
+        };
+
         Self {
+            container_id,
             item_id:       x.item_id,
             location_flag: x.location_flag,
             location_id:   x.location_id,
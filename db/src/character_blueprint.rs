@@ -7,6 +7,8 @@ use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = ItemId;
 type Val = CharacterBlueprintEntry;
 type Typ = HashMap<Idx, Val>;
@@ -43,11 +45,13 @@ impl Cache for CharacterBlueprintCache {
             Command::Del => {
                 let key = Idx::read(buf).await.unwrap();
                 self.del(key).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::MDel => {
                 let keys = Vec::<Idx>::read(buf).await.unwrap();
                 self.mdel(keys).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::Get => {
@@ -171,7 +175,16 @@ impl Save for CharacterBlueprintCache {
     }
 }
 
+impl Entries for CharacterBlueprintCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct CharacterBlueprintEntry {
     pub item_id:             ItemId,
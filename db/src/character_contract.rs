@@ -0,0 +1,234 @@
+use async_trait::*;
+use caph_eve_data_wrapper::{CharacterContract, CharacterId, ContractId, LocationId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use chrono::{DateTime, Utc};
+use crate::{ContractItemEntry, Entries};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+type Idx = ContractId;
+type Val = CharacterContractEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct CharacterContractCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl CharacterContractCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for CharacterContractCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for CharacterContractCache {
+    fn name(&self) -> String {
+        "character_contract".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for CharacterContractCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for CharacterContractCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for CharacterContractCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for CharacterContractCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for CharacterContractCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/character_contract.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for CharacterContractCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct CharacterContractEntry {
+    pub contract_id:       ContractId,
+    pub user_id:           CharacterId,
+    pub issuer_id:         CharacterId,
+    /// Same as the ESI contract type, for example `courier`
+    pub typ:               String,
+    pub status:            String,
+    pub expire:            u64,
+    pub price:             f32,
+    pub reward:            f32,
+    pub collateral:        f32,
+    /// Volume as reported by ESI, only set for courier contracts
+    pub volume:            f32,
+    pub start_location_id: Option<LocationId>,
+    pub end_location_id:   Option<LocationId>,
+    pub items:             Vec<ContractItemEntry>,
+}
+
+impl CharacterContractEntry {
+    pub fn from(
+        x:       CharacterContract,
+        user_id: CharacterId,
+        items:   Vec<ContractItemEntry>,
+    ) -> Self {
+        let expire = x.date_expired
+            .parse::<DateTime<Utc>>()
+            .map(|x| x.timestamp() as u64 * 1_000)
+            .unwrap_or_default();
+
+        Self {
+            contract_id:       x.contract_id,
+            user_id,
+            issuer_id:         x.issuer_id,
+            typ:               x.typ.as_str().into(),
+            status:            x.status,
+            expire,
+            price:             x.price.unwrap_or_default() as f32,
+            reward:            x.reward.unwrap_or_default() as f32,
+            collateral:        x.collateral.unwrap_or_default() as f32,
+            volume:            x.volume.unwrap_or_default() as f32,
+            start_location_id: x.start_location_id,
+            end_location_id:   x.end_location_id,
+            items,
+        }
+    }
+}
@@ -7,6 +7,8 @@ use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = FittingId;
 type Val = CharacterFittingEntry;
 type Typ = HashMap<Idx, Val>;
@@ -35,7 +37,7 @@ impl Into<Arc<Box<dyn Cache>>> for CharacterFittingCache {
 #[async_trait]
 impl Cache for CharacterFittingCache {
     fn name(&self) -> String {
-        "market_prices".into()
+        "character_fitting".into()
     }
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
@@ -43,11 +45,13 @@ impl Cache for CharacterFittingCache {
             Command::Del => {
                 let key = Idx::read(buf).await.unwrap();
                 self.del(key).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::MDel => {
                 let keys = Vec::<Idx>::read(buf).await.unwrap();
                 self.mdel(keys).await;
+                self.save().await;
                 0u8.write(buf).await.unwrap();
             }
             Command::Get => {
@@ -171,6 +175,14 @@ impl Save for CharacterFittingCache {
     }
 }
 
+impl Entries for CharacterFittingCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct CharacterFittingEntry {
@@ -0,0 +1,246 @@
+use async_trait::*;
+use caph_eve_data_wrapper::{CharacterId, CharacterPlanet, PlanetId, PlanetPin, SchematicId, SolarSystemId, TypeId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = CharacterId;
+type Val = Vec<CharacterPlanetEntry>;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct CharacterPlanetCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl CharacterPlanetCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for CharacterPlanetCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for CharacterPlanetCache {
+    fn name(&self) -> String {
+        "character_planet".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for CharacterPlanetCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for CharacterPlanetCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for CharacterPlanetCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for CharacterPlanetCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for CharacterPlanetCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/character_planet.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for CharacterPlanetCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct CharacterPlanetEntry {
+    pub planet_id:     PlanetId,
+    pub user_id:       CharacterId,
+    pub system_id:     SolarSystemId,
+    pub planet_type:   String,
+    pub upgrade_level: u8,
+    pub pins:          Vec<PlanetPinEntry>,
+}
+
+impl CharacterPlanetEntry {
+    pub fn from(
+        x:    CharacterPlanet,
+        pins: Vec<PlanetPin>,
+    ) -> Self {
+        let pins = pins
+            .into_iter()
+            .map(PlanetPinEntry::from)
+            .collect::<Vec<_>>();
+
+        Self {
+            planet_id:     x.planet_id,
+            user_id:       x.owner_id,
+            system_id:     x.solar_system_id,
+            planet_type:   x.planet_type,
+            upgrade_level: x.upgrade_level,
+            pins,
+        }
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct PlanetPinEntry {
+    pub pin_id:          u64,
+    pub type_id:         TypeId,
+    /// Set for factories
+    pub schematic_id:    Option<SchematicId>,
+    /// Set for extractors
+    pub product_type_id: Option<TypeId>,
+    /// Timestamp in milliseconds when the extractor stops
+    pub expiry:          Option<u64>,
+}
+
+impl From<PlanetPin> for PlanetPinEntry {
+    fn from(x: PlanetPin) -> Self {
+        let expiry = x.expiry_time
+            .and_then(|x| x.parse::<DateTime<Utc>>().ok())
+            .map(|x| x.timestamp() as u64 * 1_000);
+
+        Self {
+            pin_id:          x.pin_id,
+            type_id:         x.type_id,
+            schematic_id:    x.schematic_id,
+            product_type_id: x.extractor_details.and_then(|x| x.product_type_id),
+            expiry,
+        }
+    }
+}
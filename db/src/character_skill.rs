@@ -0,0 +1,251 @@
+use crate::{Entries, Skill};
+
+use async_trait::*;
+use caph_eve_data_wrapper::{CharacterAttributes, CharacterId, TypeId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+type Idx = CharacterId;
+type Val = CharacterSkillEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct CharacterSkillCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl CharacterSkillCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for CharacterSkillCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for CharacterSkillCache {
+    fn name(&self) -> String {
+        "character_skill".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for CharacterSkillCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for CharacterSkillCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for CharacterSkillCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for CharacterSkillCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for CharacterSkillCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/character_skill.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for CharacterSkillCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct CharacterSkillEntry {
+    pub user_id:        CharacterId,
+    pub total_sp:       u64,
+    pub unallocated_sp: u32,
+    pub charisma:       u32,
+    pub intelligence:   u32,
+    pub memory:         u32,
+    pub perception:     u32,
+    pub willpower:      u32,
+    /// Skill that is currently in training
+    pub training:       Option<TypeId>,
+    /// All trained skills with their active level
+    pub skills:         Vec<Skill>,
+    /// Timestamp in milliseconds when the omega subscription ends, set by
+    /// the user as ESI does not expose it
+    pub omega_expiry:   Option<u64>,
+}
+
+impl CharacterSkillEntry {
+    pub fn new(
+        user_id:        CharacterId,
+        total_sp:       u64,
+        unallocated_sp: u32,
+        attributes:     CharacterAttributes,
+        training:       Option<TypeId>,
+        skills:         Vec<Skill>,
+    ) -> Self {
+        Self {
+            user_id,
+            total_sp,
+            unallocated_sp,
+            charisma:     attributes.charisma,
+            intelligence: attributes.intelligence,
+            memory:       attributes.memory,
+            perception:   attributes.perception,
+            willpower:    attributes.willpower,
+            training,
+            skills,
+            omega_expiry: None,
+        }
+    }
+
+    /// Gets the active level of a skill, 0 if it is not trained
+    pub fn skill_level(&self, tid: TypeId) -> u32 {
+        self
+            .skills
+            .iter()
+            .find(|x| x.type_id == tid)
+            .map(|x| x.level)
+            .unwrap_or_default()
+    }
+
+    /// Gets the value of an attribute by its dogma attribute id
+    pub fn attribute(&self, aid: u32) -> u32 {
+        match aid {
+            164 => self.charisma,
+            165 => self.intelligence,
+            166 => self.memory,
+            167 => self.perception,
+            168 => self.willpower,
+            _   => 0,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use async_trait::*;
+use caph_eve_data_wrapper::{ContractId, LocationId, PublicContract, PublicContractItem, RegionId, TypeId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = ContractId;
+type Val = ContractEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct ContractCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl ContractCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for ContractCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for ContractCache {
+    fn name(&self) -> String {
+        "contract".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for ContractCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for ContractCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for ContractCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for ContractCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for ContractCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/contract.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for ContractCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct ContractEntry {
+    pub contract_id: ContractId,
+    pub expire:      u64,
+    pub location_id: LocationId,
+    pub price:       f32,
+    pub region_id:   RegionId,
+    /// Value of all included items based on the market price
+    pub value:       f32,
+    pub items:       Vec<ContractItemEntry>,
+}
+
+impl ContractEntry {
+    pub fn from(
+        x:         PublicContract,
+        region_id: RegionId,
+        value:     f32,
+        items:     Vec<ContractItemEntry>,
+    ) -> Self {
+        let expire = x.date_expired
+            .parse::<DateTime<Utc>>()
+            .map(|x| x.timestamp() as u64 * 1_000)
+            .unwrap_or_default();
+
+        Self {
+            contract_id: x.contract_id,
+            expire,
+            location_id: x.start_location_id.unwrap_or_else(|| 0u64.into()),
+            price:       x.price.unwrap_or_default() as f32,
+            region_id,
+            value,
+            items,
+        }
+    }
+
+    /// Difference between the value of the items and the contract price
+    pub fn profit(&self) -> f32 {
+        self.value - self.price
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct ContractItemEntry {
+    pub type_id:             TypeId,
+    pub quantity:            u32,
+    /// true if the item is given by the issuer, false if the issuer wants
+    /// the item in return
+    pub is_included:         bool,
+    /// true if the item is a blueprint copy, false for all other items
+    pub is_blueprint_copy:   bool,
+    /// Only set for blueprints, 0 for all other items
+    pub material_efficiency: u32,
+    /// Only set for blueprints, 0 for all other items
+    pub time_efficiency:     u32,
+    /// Number of runs of a blueprint copy, -1 for originals and 0 for all
+    /// other items
+    pub runs:                i32,
+}
+
+impl From<PublicContractItem> for ContractItemEntry {
+    fn from(x: PublicContractItem) -> Self {
+        Self {
+            type_id:             x.type_id,
+            quantity:            x.quantity,
+            is_included:         x.is_included,
+            is_blueprint_copy:   x.is_blueprint_copy.unwrap_or_default(),
+            material_efficiency: x.material_efficiency.unwrap_or_default(),
+            time_efficiency:     x.time_efficiency.unwrap_or_default(),
+            runs:                x.runs.unwrap_or_default(),
+        }
+    }
+}
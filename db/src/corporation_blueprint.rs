@@ -8,6 +8,8 @@ use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = Uuid;
 type Val = CorporationBlueprintEntry;
 type Typ = HashMap<Idx, Val>;
@@ -41,6 +43,12 @@ impl Cache for CorporationBlueprintCache {
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
         match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::MDel => {
                 let keys = Vec::<Idx>::read(buf).await.unwrap();
                 self.mdel(keys).await;
@@ -168,6 +176,14 @@ impl Save for CorporationBlueprintCache {
     }
 }
 
+impl Entries for CorporationBlueprintCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct CorporationBlueprintEntry {
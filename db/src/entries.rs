@@ -0,0 +1,11 @@
+use tokio::sync::RwLock;
+
+/// Borrowed access to the entries of a cache.
+///
+/// Used to read a cache without cloning all of its entries, for example by
+/// the query server.
+pub trait Entries {
+    type Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ>;
+}
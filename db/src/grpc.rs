@@ -0,0 +1,245 @@
+//! gRPC interface for the caches, so that tools that are not written in rust
+//! can read and write the caches without implementing the cachem protocol.
+//!
+//! The service is defined in `proto/cache.proto`. Keys and values are
+//! exchanged as json, the same representation the query server uses.
+//!
+//! Writes are saved to disk and announced over the invalidation channel, the
+//! same way as writes over the tcp protocol.
+//!
+//! The service has no authentication, only the market and SDE caches are
+//! registered. Caches with user data, like users, sessions and roles, are
+//! only reachable over the cachem protocol.
+
+use crate::Invalidation;
+
+use async_trait::*;
+use cachem::v2::{Get, Key, Save, Set};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use self::proto::cache_server::{Cache as CacheRpc, CacheServer};
+use self::proto::*;
+
+#[allow(clippy::all)]
+mod proto {
+    tonic::include_proto!("cachem");
+}
+
+/// Reads and writes the entries of a cache as json
+#[async_trait]
+pub trait JsonCache: Send + Sync {
+    async fn get_json(&self, key: &str) -> Result<Option<String>, Status>;
+    async fn set_json(&self, entries: Vec<(String, String)>) -> Result<(), Status>;
+    async fn keys_json(&self) -> Vec<String>;
+}
+
+#[async_trait]
+impl<T, K, V> JsonCache for T
+    where
+        T: Get<Idx = K, Res = V> + Set<Idx = K, Val = V> + Key<Idx = K> + Save + Send + Sync,
+        K: Serialize + DeserializeOwned + Send + Sync + 'static,
+        V: Serialize + DeserializeOwned + Send + Sync + 'static {
+
+    async fn get_json(&self, key: &str) -> Result<Option<String>, Status> {
+        let key = parse::<K>(key)?;
+        self
+            .get(key, None)
+            .await
+            .map(|x| to_json(&x))
+            .transpose()
+    }
+
+    async fn set_json(&self, entries: Vec<(String, String)>) -> Result<(), Status> {
+        // parse everything first, so that nothing is written if a single
+        // entry is invalid
+        let mut parsed = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            parsed.push((parse::<K>(&key)?, parse::<V>(&value)?));
+        }
+
+        for (key, value) in parsed {
+            self.set(key, value).await;
+        }
+        self.save().await;
+        Ok(())
+    }
+
+    async fn keys_json(&self) -> Vec<String> {
+        self
+            .keys()
+            .await
+            .iter()
+            .filter_map(|x| serde_json::to_string(x).ok())
+            .collect::<Vec<_>>()
+    }
+}
+
+/// Serves the registered caches over gRPC
+pub struct GrpcServer {
+    /// Name of the cache -> id of the cache and the cache
+    caches:       HashMap<String, (u8, Arc<dyn JsonCache>)>,
+    invalidation: Invalidation,
+}
+
+impl GrpcServer {
+    /// Creates a new server without any cache
+    pub fn new(invalidation: Invalidation) -> Self {
+        Self {
+            caches: HashMap::new(),
+            invalidation,
+        }
+    }
+
+    /// Registers a cache under the given name
+    ///
+    /// # Parameters
+    ///
+    /// * `name`  - Name of the cache in the requests
+    /// * `id`    - Id of the cache, used for the invalidation
+    /// * `cache` - Cache to expose
+    ///
+    pub fn add(&mut self, name: String, id: u8, cache: Arc<dyn JsonCache>) {
+        self.caches.insert(name, (id, cache));
+    }
+
+    /// Starts the gRPC server
+    ///
+    /// This function is blocking
+    pub async fn listen(self, addr: &str) {
+        let addr = match addr.parse() {
+            Ok(x)  => x,
+            Err(e) => {
+                log::error!("Invalid gRPC address {} {:?}", addr, e);
+                return;
+            }
+        };
+
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(CacheServer::new(self))
+            .serve(addr)
+            .await {
+            log::error!("Error running the gRPC server {:?}", e);
+        }
+    }
+
+    fn cache(&self, name: &str) -> Result<&(u8, Arc<dyn JsonCache>), Status> {
+        self
+            .caches
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("Unknown cache {}", name)))
+    }
+
+    async fn entry(cache: &Arc<dyn JsonCache>, key: String) -> Result<Entry, Status> {
+        let value = cache.get_json(&key).await?;
+        Ok(Entry {
+            key,
+            found: value.is_some(),
+            value: value.unwrap_or_default(),
+        })
+    }
+
+    async fn set_entries(
+        &self,
+        cache:   &str,
+        entries: Vec<(String, String)>,
+    ) -> Result<Response<SetResponse>, Status> {
+        let (id, cache) = self.cache(cache)?;
+        cache.set_json(entries).await?;
+        self.invalidation.notify(*id);
+        Ok(Response::new(SetResponse {}))
+    }
+}
+
+#[tonic::async_trait]
+impl CacheRpc for GrpcServer {
+    async fn caches(
+        &self,
+        _: Request<CachesRequest>,
+    ) -> Result<Response<CachesResponse>, Status> {
+        let mut caches = self.caches.keys().cloned().collect::<Vec<_>>();
+        caches.sort();
+        Ok(Response::new(CachesResponse { caches }))
+    }
+
+    async fn get(
+        &self,
+        request: Request<GetRequest>,
+    ) -> Result<Response<GetResponse>, Status> {
+        let request = request.into_inner();
+        let (_, cache) = self.cache(&request.cache)?;
+        let entry = Self::entry(cache, request.key).await?;
+        Ok(Response::new(GetResponse { entry: Some(entry) }))
+    }
+
+    async fn m_get(
+        &self,
+        request: Request<MGetRequest>,
+    ) -> Result<Response<MGetResponse>, Status> {
+        let request = request.into_inner();
+        let (_, cache) = self.cache(&request.cache)?;
+
+        let mut entries = Vec::with_capacity(request.keys.len());
+        for key in request.keys {
+            entries.push(Self::entry(cache, key).await?);
+        }
+        Ok(Response::new(MGetResponse { entries }))
+    }
+
+    async fn set(
+        &self,
+        request: Request<SetRequest>,
+    ) -> Result<Response<SetResponse>, Status> {
+        let request = request.into_inner();
+        self.set_entries(&request.cache, vec![(request.key, request.value)]).await
+    }
+
+    async fn m_set(
+        &self,
+        request: Request<MSetRequest>,
+    ) -> Result<Response<SetResponse>, Status> {
+        let request = request.into_inner();
+        let entries = request
+            .entries
+            .into_iter()
+            .map(|x| (x.key, x.value))
+            .collect::<Vec<_>>();
+        self.set_entries(&request.cache, entries).await
+    }
+
+    async fn keys(
+        &self,
+        request: Request<KeysRequest>,
+    ) -> Result<Response<KeysResponse>, Status> {
+        let request = request.into_inner();
+        let (_, cache) = self.cache(&request.cache)?;
+        let keys = cache.keys_json().await;
+        Ok(Response::new(KeysResponse { keys }))
+    }
+}
+
+fn parse<T: DeserializeOwned>(x: &str) -> Result<T, Status> {
+    serde_json::from_str(x)
+        .map_err(|e| Status::invalid_argument(format!("Invalid json {}: {}", x, e)))
+}
+
+fn to_json<T: Serialize>(x: &T) -> Result<String, Status> {
+    serde_json::to_string(x)
+        .map_err(|e| Status::internal(e.to_string()))
+}
+
+#[cfg(test)]
+mod grpc_tests {
+    use super::*;
+    use caph_eve_data_wrapper::CharacterId;
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(parse::<CharacterId>("2117441999").unwrap(), CharacterId(2117441999));
+        assert_eq!(parse::<String>("\"abc\"").unwrap(), "abc".to_string());
+        assert!(parse::<CharacterId>("abc").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use caph_eve_data_wrapper::SolarSystemId;
+use cachem::Parse;
+
+use crate::{CostIndex, DAY, TimeSeries, TimeSeriesCache};
+
+pub type IndustryCostHistoryCache = TimeSeriesCache<SolarSystemId, IndustryCostHistoryEntry>;
+
+/// Cost indices of a system at the time of a refresh
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct IndustryCostHistoryEntry {
+    /// Timestamp in milliseconds
+    pub timestamp:    u64,
+    pub cost_indices: Vec<CostIndex>,
+}
+
+impl IndustryCostHistoryEntry {
+    pub fn new(timestamp: u64, cost_indices: Vec<CostIndex>) -> Self {
+        Self {
+            timestamp,
+            cost_indices,
+        }
+    }
+}
+
+impl TimeSeries for IndustryCostHistoryEntry {
+    const NAME: &'static str = "industry_cost_history";
+    const FILE: &'static str = "./db/industry_cost_history.cachem";
+    const MAX_AGE: Option<u64> = Some(365 * DAY);
+    const DOWNSAMPLE_AFTER: Option<u64> = Some(7 * DAY);
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
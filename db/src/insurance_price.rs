@@ -0,0 +1,277 @@
+use async_trait::*;
+use caph_eve_data_wrapper::{InsurancePrice, TypeId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = TypeId;
+type Val = InsurancePriceEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct InsurancePriceCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl InsurancePriceCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for InsurancePriceCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for InsurancePriceCache {
+    fn name(&self) -> String {
+        "insurance_prices".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for InsurancePriceCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for InsurancePriceCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for InsurancePriceCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for InsurancePriceCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for InsurancePriceCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/insurance_price.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for InsurancePriceCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct InsurancePriceEntry {
+    pub type_id: TypeId,
+    pub levels:  Vec<InsuranceLevel>,
+}
+
+impl InsurancePriceEntry {
+    /// Gets the insurance level with the given name, for example `Platinum`
+    pub fn level(&self, name: &str) -> Option<&InsuranceLevel> {
+        self
+            .levels
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl From<InsurancePrice> for InsurancePriceEntry {
+    fn from(x: InsurancePrice) -> Self {
+        Self {
+            type_id: x.type_id,
+            levels:  x
+                .levels
+                .into_iter()
+                .map(|x| InsuranceLevel {
+                    cost:   x.cost,
+                    name:   x.name,
+                    payout: x.payout,
+                })
+                .collect::<Vec<_>>(),
+        }
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct InsuranceLevel {
+    pub cost:   f32,
+    /// Basic, Standard, Bronze, Silver, Gold or Platinum
+    pub name:   String,
+    pub payout: f32,
+}
+
+#[cfg(test)]
+mod insurance_price_tests {
+    use super::*;
+
+    use caph_eve_data_wrapper::InsurancePriceLevel;
+    use tokio::sync::watch;
+
+    fn price(type_id: u32, payout: f32) -> InsurancePrice {
+        InsurancePrice {
+            type_id: type_id.into(),
+            levels:  vec![
+                InsurancePriceLevel {
+                    cost:   payout * 0.1,
+                    name:   "Basic".into(),
+                    payout: payout * 0.5,
+                },
+                InsurancePriceLevel {
+                    cost:   payout * 0.3,
+                    name:   "Platinum".into(),
+                    payout,
+                },
+            ],
+        }
+    }
+
+    // The collector writes the ESI prices with a single mset, every hull
+    // must be readable by its type id afterwards
+    #[tokio::test]
+    async fn filled_from_esi_prices() {
+        let (_, rx) = watch::channel(Command::Ping);
+        let cache = InsurancePriceCache::new(rx);
+
+        let prices = vec![price(587, 100_000f32), price(24690, 50_000_000f32)]
+            .into_iter()
+            .map(InsurancePriceEntry::from)
+            .map(|x| (x.type_id, x))
+            .collect::<HashMap<_, _>>();
+        cache.mset(prices).await;
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+        assert_eq!(keys, vec![TypeId(587), TypeId(24690)]);
+
+        let entry = cache.get(24690u32.into(), None).await.unwrap();
+        assert_eq!(entry.level("platinum").map(|x| x.payout), Some(50_000_000f32));
+        assert_eq!(entry.level("basic").map(|x| x.payout), Some(25_000_000f32));
+        assert!(entry.level("gold").is_none());
+    }
+}
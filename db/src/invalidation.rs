@@ -0,0 +1,141 @@
+//! Notifies subscribers as soon as a cache is modified.
+//!
+//! Subscribers connect to a separate tcp port and receive a line with the
+//! id of the [crate::CacheName] every time a cache is modified. If a
+//! subscriber is too slow and misses notifications, it receives `*` and
+//! should consider every cache as modified.
+//!
+//! The port is `127.0.0.1:55557` by default and can be changed with
+//! `CAPH_DB_INVALIDATION_ADDRESS`. It only sends cache ids and has no
+//! authentication.
+
+use async_trait::*;
+use cachem::v2::{Cache, Command};
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufStream};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Distributes notifications about modified caches
+#[derive(Clone)]
+pub struct Invalidation {
+    tx: broadcast::Sender<u8>,
+}
+
+impl Invalidation {
+    /// Number of notifications that are buffered for every subscriber
+    const CAPACITY: usize = 1024;
+
+    /// Creates a new instance
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        Self {
+            tx,
+        }
+    }
+
+    /// Notifies all subscribers that the given cache was modified
+    pub fn notify(&self, cache: u8) {
+        // Fails if there is no subscriber
+        let _ = self.tx.send(cache);
+    }
+
+    /// Checks if the command modifies the cache
+    pub fn modifies(cmd: Command) -> bool {
+        matches!(
+            cmd,
+            Command::Del | Command::MDel | Command::Set | Command::MSet
+        )
+    }
+
+    /// Accepts subscribers and forwards all notifications to them.
+    ///
+    /// This function is blocking
+    pub async fn listen(self, addr: &str) {
+        let listener = TcpListener::bind(addr).await.unwrap();
+
+        loop {
+            let mut socket = match listener.accept().await {
+                Ok((x, _)) => x,
+                Err(e)     => {
+                    log::error!("Error accepting invalidation subscriber {:?}", e);
+                    continue;
+                }
+            };
+
+            let mut rx = self.tx.subscribe();
+            tokio::spawn(async move {
+                loop {
+                    let message = match rx.recv().await {
+                        Ok(x)                     => x.to_string(),
+                        Err(RecvError::Lagged(_)) => "*".into(),
+                        Err(RecvError::Closed)    => break,
+                    };
+                    if socket.write_all(format!("{}\n", message).as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl Default for Invalidation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps a cache so that it can be shared with the query server and sends a
+/// notification every time the cache is modified
+pub struct SharedCache<T> {
+    cache:        Arc<T>,
+    id:           u8,
+    invalidation: Invalidation,
+}
+
+impl<T> SharedCache<T> {
+    /// Creates a new instance
+    pub fn new(
+        cache:        Arc<T>,
+        id:           u8,
+        invalidation: Invalidation,
+    ) -> Self {
+        Self {
+            cache,
+            id,
+            invalidation,
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Cache for SharedCache<T>
+    where
+        T: Cache + Send + Sync + 'static {
+
+    fn name(&self) -> String {
+        self.cache.name()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        self.cache.handle(cmd, buf).await;
+
+        if Invalidation::modifies(cmd) {
+            self.invalidation.notify(self.id);
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        self.cache.cnc_listener().await
+    }
+}
+
+impl<T> Into<Arc<Box<dyn Cache>>> for SharedCache<T>
+    where
+        T: Cache + Send + Sync + 'static {
+
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
@@ -1,75 +1,231 @@
+mod audit_log;
 mod blueprint;
+mod blueprint_history;
 mod character_asset;
 mod character_blueprint;
+mod character_calendar;
+mod character_clone;
+mod character_contract;
 mod character_fitting;
+mod character_mining;
+mod character_notification;
+mod character_planet;
+mod character_skill;
+mod character_sync;
+mod character_webhook;
+mod contract;
+mod corporation_asset;
 mod corporation_blueprint;
+mod corporation_mining;
+mod corporation_structure;
+mod entries;
+mod esi_response;
+mod fitting;
+#[cfg(feature = "with_grpc")]
+mod grpc;
 mod industry_cost;
+mod industry_cost_history;
+mod insurance_price;
+mod invalidation;
 mod item;
+mod killmail;
+mod lru;
 mod market_info;
 mod market_order;
 mod market_price;
+mod market_price_history;
+mod market_undercut;
 mod name;
+mod net_worth_history;
+mod price_alert;
 mod project;
+#[cfg(feature = "with_serde")]
+mod query;
 mod reprocess;
 mod schematic;
+mod session;
+mod skill_history;
+mod sovereignty;
+mod stock_rule;
+mod structure_fee;
 mod system_region;
+mod time_series;
+mod universe_graph;
 mod user;
+mod user_login;
+mod user_preference;
+mod user_role;
+mod wallet_transaction;
+mod workspace;
 
+pub use self::audit_log::*;
 pub use self::blueprint::*;
+pub use self::blueprint_history::*;
 pub use self::character_asset::*;
 pub use self::character_blueprint::*;
+pub use self::character_calendar::*;
+pub use self::character_clone::*;
+pub use self::character_contract::*;
 pub use self::character_fitting::*;
+pub use self::character_mining::*;
+pub use self::character_notification::*;
+pub use self::character_planet::*;
+pub use self::character_skill::*;
+pub use self::character_sync::*;
+pub use self::character_webhook::*;
+pub use self::contract::*;
+pub use self::corporation_asset::*;
 pub use self::corporation_blueprint::*;
+pub use self::corporation_mining::*;
+pub use self::corporation_structure::*;
+pub use self::entries::*;
+pub use self::esi_response::*;
+pub use self::fitting::*;
+#[cfg(feature = "with_grpc")]
+pub use self::grpc::*;
 pub use self::industry_cost::*;
+pub use self::industry_cost_history::*;
+pub use self::insurance_price::*;
+pub use self::invalidation::*;
 pub use self::item::*;
+pub use self::killmail::*;
+pub use self::lru::*;
 pub use self::market_info::*;
 pub use self::market_order::*;
 pub use self::market_price::*;
+pub use self::market_price_history::*;
+pub use self::market_undercut::*;
 pub use self::name::*;
+pub use self::net_worth_history::*;
+pub use self::price_alert::*;
 pub use self::project::*;
+#[cfg(feature = "with_serde")]
+pub use self::query::*;
 pub use self::reprocess::*;
 pub use self::schematic::*;
+pub use self::session::*;
+pub use self::skill_history::*;
+pub use self::sovereignty::*;
+pub use self::stock_rule::*;
+pub use self::structure_fee::*;
 pub use self::system_region::*;
+pub use self::time_series::*;
+pub use self::universe_graph::*;
 pub use self::user::*;
+pub use self::user_login::*;
+pub use self::user_preference::*;
+pub use self::user_role::*;
+pub use self::wallet_transaction::*;
+pub use self::workspace::*;
 
 pub enum CacheName {
+    AuditLog,
     Blueprint,
+    BlueprintHistory,
     CharacterAsset,
     CharacterBlueprint,
+    CharacterCalendar,
+    CharacterClone,
+    CharacterContract,
     CharacterFitting,
+    CharacterMining,
+    CharacterNotification,
+    CharacterPlanet,
+    CharacterSkill,
+    CharacterSync,
+    CharacterWebhook,
+    Contract,
+    CorporationAsset,
     CorporationBlueprint,
+    CorporationMining,
+    CorporationStructure,
+    EsiResponse,
+    Fitting,
     IndustryCost,
+    IndustryCostHistory,
+    InsurancePrice,
     Item,
+    Killmail,
     MarketInfo,
     MarketOrder,
     MarketPrice,
+    MarketPriceHistory,
+    MarketUndercut,
     Name,
+    NetWorthHistory,
+    PriceAlert,
     Project,
     Reprocess,
     Schematic,
+    Session,
+    SkillHistory,
+    Sovereignty,
+    StockRule,
+    StructureFee,
     SystemRegion,
+    UniverseGraph,
     User,
+    UserLogin,
+    UserPreference,
+    UserRole,
+    WalletTransaction,
+    Workspace,
 }
 
 impl Into<u8> for CacheName {
     fn into(self) -> u8 {
         match self {
-            Self::Blueprint            => 0,
-            Self::CharacterAsset       => 1,
-            Self::CharacterBlueprint   => 2,
-            Self::CharacterFitting     => 3,
-            Self::CorporationBlueprint => 4,
-            Self::IndustryCost         => 5,
-            Self::Item                 => 6,
-            Self::MarketInfo           => 7,
-            Self::MarketOrder          => 8,
-            Self::MarketPrice          => 9,
-            Self::Name                 => 10,
-            Self::Project              => 11,
-            Self::Reprocess            => 12,
-            Self::Schematic            => 13,
-            Self::SystemRegion         => 14,
-            Self::User                 => 15,
+            Self::AuditLog              => 48,
+            Self::Blueprint             => 0,
+            Self::BlueprintHistory      => 25,
+            Self::CharacterAsset        => 1,
+            Self::CharacterBlueprint    => 2,
+            Self::CharacterCalendar     => 32,
+            Self::CharacterClone        => 26,
+            Self::CharacterContract     => 24,
+            Self::CharacterFitting      => 3,
+            Self::CharacterMining       => 29,
+            Self::CharacterNotification => 31,
+            Self::CharacterPlanet       => 27,
+            Self::CharacterSkill        => 28,
+            Self::CharacterSync         => 41,
+            Self::CharacterWebhook      => 33,
+            Self::Contract              => 18,
+            Self::CorporationAsset      => 22,
+            Self::CorporationBlueprint  => 4,
+            Self::CorporationMining     => 30,
+            Self::CorporationStructure  => 23,
+            Self::EsiResponse           => 50,
+            Self::Fitting               => 35,
+            Self::IndustryCost          => 5,
+            Self::IndustryCostHistory   => 46,
+            Self::InsurancePrice        => 47,
+            Self::Item                  => 6,
+            Self::Killmail              => 19,
+            Self::MarketInfo            => 7,
+            Self::MarketOrder           => 8,
+            Self::MarketPrice           => 9,
+            Self::MarketPriceHistory    => 45,
+            Self::MarketUndercut        => 20,
+            Self::Name                  => 10,
+            Self::NetWorthHistory       => 44,
+            Self::PriceAlert            => 43,
+            Self::Project               => 11,
+            Self::Reprocess             => 12,
+            Self::Schematic             => 13,
+            Self::Session               => 38,
+            Self::SkillHistory          => 39,
+            Self::Sovereignty           => 21,
+            Self::StockRule             => 42,
+            Self::StructureFee          => 34,
+            Self::SystemRegion          => 14,
+            Self::UniverseGraph         => 17,
+            Self::User                  => 15,
+            Self::UserLogin             => 37,
+            Self::UserPreference        => 36,
+            Self::UserRole              => 40,
+            Self::WalletTransaction     => 16,
+            Self::Workspace             => 49,
         }
     }
 }
@@ -0,0 +1,424 @@
+//! Storage for caches that should not keep all entries in memory.
+//!
+//! When a [MemoryBound] is exceeded the least recently used entries are
+//! written into their own file and removed from memory. Reading an evicted
+//! entry loads it from disk again.
+//!
+//! Evicted entries are not part of the regular cache file, they are stored
+//! in a directory next to it, together with an index of all evicted keys.
+//! The index is only written by [Lru::replace] and [Lru::flush], so that a
+//! batch of changes writes it once.
+//! Without a bound nothing is evicted and the storage behaves like a
+//! `HashMap`.
+//!
+//! Only the time series caches, for example the market price and industry
+//! cost histories, are stored this way. All other caches, including the
+//! market info and market order caches, keep every entry in memory and
+//! cannot be bounded.
+
+use cachem::Parse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+
+/// Maximum number of entries or bytes that are kept in memory
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoryBound {
+    pub max_entries: Option<usize>,
+    pub max_bytes:   Option<usize>,
+}
+
+impl MemoryBound {
+    /// Reads the bound of a time series cache from the environment.
+    ///
+    /// The variables are `CAPH_DB_<NAME>_MAX_ENTRIES` and
+    /// `CAPH_DB_<NAME>_MAX_BYTES`, for example
+    /// `CAPH_DB_MARKET_PRICE_HISTORY_MAX_BYTES=100000000`.
+    pub fn from_env(name: &str) -> Self {
+        let var = |x: &str| {
+            std::env::var(format!("CAPH_DB_{}_{}", name.to_uppercase(), x))
+                .ok()
+                .and_then(|x| x.parse::<usize>().ok())
+        };
+
+        Self {
+            max_entries: var("MAX_ENTRIES"),
+            max_bytes:   var("MAX_BYTES"),
+        }
+    }
+
+    fn exceeded(&self, entries: usize, bytes: usize) -> bool {
+        self.max_entries.map(|x| entries > x).unwrap_or(false) ||
+        self.max_bytes.map(|x| bytes > x).unwrap_or(false)
+    }
+}
+
+struct LruEntry<V> {
+    value:     V,
+    /// Size of the entry in bytes
+    size:      usize,
+    last_used: u64,
+}
+
+pub struct Lru<K, V> {
+    /// Directory for the evicted entries
+    dir:     String,
+    bound:   MemoryBound,
+
+    entries: HashMap<K, LruEntry<V>>,
+    /// Keys in memory ordered by their last use, the first one is the least
+    /// recently used
+    recency: BTreeMap<u64, K>,
+    evicted: HashSet<K>,
+    /// Set if the evicted keys changed since the index was written
+    dirty:   bool,
+    /// Size of all entries in memory
+    bytes:   usize,
+    tick:    u64,
+}
+
+impl<K, V> Lru<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync,
+        V: Clone + Parse + Send + Sync {
+
+    pub fn new(dir: String, bound: MemoryBound) -> Self {
+        Self {
+            dir,
+            bound,
+
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            evicted: HashSet::new(),
+            dirty:   false,
+            bytes:   0,
+            tick:    0,
+        }
+    }
+
+    /// Gets an entry and loads it from disk if it was evicted
+    pub async fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+
+        if let Some(x) = self.entries.get_mut(key) {
+            self.recency.remove(&x.last_used);
+            self.recency.insert(self.tick, *key);
+            x.last_used = self.tick;
+            return Some(x.value.clone());
+        }
+
+        if !self.evicted.contains(key) {
+            return None;
+        }
+        let value = self.load_evicted(key).await?;
+        self.insert(*key, value.clone()).await;
+        Some(value)
+    }
+
+    /// Inserts or replaces an entry and evicts other entries if the bound
+    /// is exceeded
+    pub async fn insert(&mut self, key: K, value: V) {
+        self.put(key, value).await;
+        self.evict().await;
+    }
+
+    pub async fn remove(&mut self, key: &K) {
+        if let Some(x) = self.entries.remove(key) {
+            self.recency.remove(&x.last_used);
+            self.bytes -= x.size;
+        }
+        if self.evicted.remove(key) {
+            self.remove_evicted(key).await;
+        }
+    }
+
+    #[cfg(test)]
+    pub async fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.bytes = 0;
+        self.dirty = false;
+
+        if !self.evicted.is_empty() {
+            self.evicted.clear();
+            if let Err(e) = tokio::fs::remove_dir_all(&self.dir).await {
+                log::error!("Error removing evicted entries {} {:?}", self.dir, e);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key) || self.evicted.contains(key)
+    }
+
+    /// Keys of all entries, including the evicted ones
+    pub fn keys(&self) -> Vec<K> {
+        self
+            .entries
+            .keys()
+            .chain(self.evicted.iter())
+            .copied()
+            .collect::<Vec<_>>()
+    }
+
+    /// Number of all entries, including the evicted ones
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.evicted.len()
+    }
+
+    /// Values of all entries that are currently in memory
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self
+            .entries
+            .values()
+            .map(|x| &x.value)
+    }
+
+    /// All entries that are currently in memory
+    pub fn in_memory(&self) -> HashMap<K, V> {
+        self
+            .entries
+            .iter()
+            .map(|(k, v)| (*k, v.value.clone()))
+            .collect::<HashMap<_, _>>()
+    }
+
+    /// Replaces all entries in memory with the given ones, used when the
+    /// cache file is loaded. Evicted entries are read from the index.
+    pub async fn replace(&mut self, data: HashMap<K, V>) {
+        self.entries.clear();
+        self.recency.clear();
+        self.bytes = 0;
+        self.evicted = self.load_index().await;
+
+        for (key, value) in data {
+            self.put(key, value).await;
+        }
+        self.evict().await;
+        self.flush().await;
+    }
+
+    /// Writes the index of the evicted keys if it changed since it was
+    /// written last
+    pub async fn flush(&mut self) {
+        if self.dirty {
+            self.save_index().await;
+            self.dirty = false;
+        }
+    }
+
+    /// Inserts or replaces an entry without evicting other entries
+    async fn put(&mut self, key: K, value: V) {
+        self.tick += 1;
+
+        if self.evicted.remove(&key) {
+            self.remove_evicted(&key).await;
+        }
+
+        let size = Self::size(&value).await;
+        if let Some(x) = self.entries.insert(key, LruEntry {
+            value,
+            size,
+            last_used: self.tick,
+        }) {
+            self.recency.remove(&x.last_used);
+            self.bytes -= x.size;
+        }
+        self.recency.insert(self.tick, key);
+        self.bytes += size;
+    }
+
+    /// Evicts the least recently used entries until the bound is met, the
+    /// last used entry is always kept
+    async fn evict(&mut self) {
+        while self.entries.len() > 1 &&
+            self.bound.exceeded(self.entries.len(), self.bytes) {
+
+            let (tick, key) = match self.recency.iter().next() {
+                Some((t, k)) => (*t, *k),
+                None         => break,
+            };
+
+            let entry = &self.entries[&key];
+            if let Err(e) = Self::write(&self.path(&key), &entry.value).await {
+                log::error!("Error evicting entry {} {:?}", key, e);
+                break;
+            }
+
+            self.recency.remove(&tick);
+            if let Some(x) = self.entries.remove(&key) {
+                self.bytes -= x.size;
+            }
+            self.evicted.insert(key);
+            self.dirty = true;
+        }
+    }
+
+    async fn load_evicted(&mut self, key: &K) -> Option<V> {
+        let bytes = match tokio::fs::read(self.path(key)).await {
+            Ok(x)  => x,
+            Err(e) => {
+                log::error!("Error loading evicted entry {} {:?}", key, e);
+                return None;
+            }
+        };
+        V::read(&mut bytes.as_slice()).await.ok()
+    }
+
+    async fn remove_evicted(&mut self, key: &K) {
+        if let Err(e) = tokio::fs::remove_file(self.path(key)).await {
+            log::error!("Error removing evicted entry {} {:?}", key, e);
+        }
+        self.dirty = true;
+    }
+
+    async fn load_index(&self) -> HashSet<K> {
+        let bytes = match tokio::fs::read(self.index_path()).await {
+            Ok(x)  => x,
+            // nothing was evicted yet
+            Err(_) => return HashSet::new(),
+        };
+        Vec::<K>::read(&mut bytes.as_slice())
+            .await
+            .map(|x| x.into_iter().collect::<HashSet<_>>())
+            .unwrap_or_default()
+    }
+
+    async fn save_index(&self) {
+        let keys = self
+            .evicted
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        if let Err(e) = Self::write(&self.index_path(), &keys).await {
+            log::error!("Error saving evicted index {} {:?}", self.dir, e);
+        }
+    }
+
+    async fn write<T: Parse + Sync>(path: &str, value: &T) -> std::io::Result<()> {
+        let mut bytes = Vec::new();
+        value
+            .write(&mut bytes)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+
+        if let Some(x) = std::path::Path::new(path).parent() {
+            tokio::fs::create_dir_all(x).await?;
+        }
+        tokio::fs::write(path, bytes).await
+    }
+
+    async fn size(value: &V) -> usize {
+        let mut bytes = Vec::new();
+        let _ = value.write(&mut bytes).await;
+        bytes.len()
+    }
+
+    fn path(&self, key: &K) -> String {
+        format!("{}/{}.cachem", self.dir, key)
+    }
+
+    fn index_path(&self) -> String {
+        format!("{}/index.cachem", self.dir)
+    }
+}
+
+#[cfg(test)]
+mod lru_tests {
+    use super::*;
+
+    fn dir(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("caph_lru_{}_{}", name, std::process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let bound = MemoryBound {
+            max_entries: Some(2),
+            max_bytes:   None,
+        };
+        let mut lru = Lru::<u32, u32>::new(dir("entries"), bound);
+
+        lru.insert(1, 10).await;
+        lru.insert(2, 20).await;
+        assert_eq!(lru.get(&1).await, Some(10));
+        lru.insert(3, 30).await;
+
+        // 2 was used last the longest ago
+        assert!(!lru.in_memory().contains_key(&2));
+        assert!(lru.contains(&2));
+        assert_eq!(lru.len(), 3);
+
+        // loading 2 evicts 1
+        assert_eq!(lru.get(&2).await, Some(20));
+        assert!(!lru.in_memory().contains_key(&1));
+        assert_eq!(lru.get(&1).await, Some(10));
+
+        lru.clear().await;
+        assert_eq!(lru.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn replace_keeps_evicted() {
+        let bound = MemoryBound {
+            max_entries: Some(1),
+            max_bytes:   None,
+        };
+        let dir = dir("replace");
+        let mut lru = Lru::<u32, u32>::new(dir.clone(), bound);
+        lru.insert(1, 10).await;
+        lru.insert(2, 20).await;
+        lru.flush().await;
+
+        // same as loading the cache file after a restart
+        let mut reloaded = Lru::<u32, u32>::new(dir, bound);
+        reloaded.replace(lru.in_memory()).await;
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.get(&1).await, Some(10));
+
+        reloaded.clear().await;
+    }
+
+    #[tokio::test]
+    async fn replace_evicts_once() {
+        let bound = MemoryBound {
+            max_entries: Some(2),
+            max_bytes:   None,
+        };
+        let dir = dir("replace_batch");
+        let data = (0..10u32)
+            .map(|x| (x, x * 10))
+            .collect::<HashMap<_, _>>();
+
+        let mut lru = Lru::<u32, u32>::new(dir.clone(), bound);
+        lru.replace(data).await;
+        assert_eq!(lru.in_memory().len(), 2);
+        assert_eq!(lru.len(), 10);
+
+        // the index was written by replace
+        let mut reloaded = Lru::<u32, u32>::new(dir, bound);
+        reloaded.replace(lru.in_memory()).await;
+        assert_eq!(reloaded.len(), 10);
+        assert_eq!(reloaded.get(&7).await, Some(70));
+
+        reloaded.clear().await;
+    }
+
+    #[tokio::test]
+    async fn unbounded() {
+        let mut lru = Lru::<u32, u32>::new(dir("unbounded"), MemoryBound::default());
+        for i in 0..100 {
+            lru.insert(i, i).await;
+        }
+        assert_eq!(lru.in_memory().len(), 100);
+
+        lru.remove(&5).await;
+        assert_eq!(lru.get(&5).await, None);
+        assert_eq!(lru.len(), 99);
+    }
+}
@@ -1,12 +1,14 @@
 use async_trait::*;
 use caph_eve_data_wrapper::{TypeId, OrderId, LocationId, SolarSystemId};
-use cachem::{Parse, v2::{Cache, Command, Get, Key, Set, Save}};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = OrderId;
 type Val = MarketInfoEntry;
 type Typ = HashMap<Idx, Val>;
@@ -48,6 +50,18 @@ impl Cache for MarketInfoCache {
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
         match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::Get => {
                 let key = Idx::read(buf).await.unwrap();
                 let val = self.get(key, None).await;
@@ -94,6 +108,19 @@ impl Cache for MarketInfoCache {
     }
 }
 
+#[async_trait]
+impl Del for MarketInfoCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
 #[async_trait]
 impl Get for MarketInfoCache {
     type Idx =   Idx;
@@ -156,7 +183,16 @@ impl Save for MarketInfoCache {
     }
 }
 
+impl Entries for MarketInfoCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct MarketInfoEntry {
     /// Timestamp in seconds, when this order was placed
@@ -1,13 +1,13 @@
 use async_trait::*;
 use caph_eve_data_wrapper::{TypeId, OrderId};
-use cachem::{Parse, v2::{Cache, Command, Get, Key, Set, Save}};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
-use crate::MarketInfoCache;
+use crate::{Entries, MarketInfoCache};
 
 type Idx = TypeId;
 type Val = MarketOrder;
@@ -49,6 +49,18 @@ impl Cache for MarketOrderCache {
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
         match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::Get => {
                 let key = Idx::read(buf).await.unwrap();
                 let params = Option::<MarketOrderRequest>::read(buf).await.unwrap();
@@ -91,6 +103,19 @@ impl Cache for MarketOrderCache {
     }
 }
 
+#[async_trait]
+impl Del for MarketOrderCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
 #[async_trait]
 impl Get for MarketOrderCache {
     type Idx   = Idx;
@@ -342,6 +367,14 @@ impl Save for MarketOrderCache {
     }
 }
 
+impl Entries for MarketOrderCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct MarketOrderEntry {
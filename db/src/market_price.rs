@@ -1,12 +1,14 @@
 use async_trait::*;
 use caph_eve_data_wrapper::{MarketPrice, TypeId};
-use cachem::{Parse, v2::{Cache, Command, Get, Key, Set, Save}};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
+use crate::Entries;
+
 type Idx = TypeId;
 type Val = MarketPriceEntry;
 type Typ = HashMap<Idx, Val>;
@@ -40,6 +42,18 @@ impl Cache for MarketPriceCache {
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
         match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::Get => {
                 let key = Idx::read(buf).await.unwrap();
                 let val = self.get(key, None).await;
@@ -86,6 +100,19 @@ impl Cache for MarketPriceCache {
     }
 }
 
+#[async_trait]
+impl Del for MarketPriceCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
 #[async_trait]
 impl Get for MarketPriceCache {
     type Idx =   Idx;
@@ -148,6 +175,14 @@ impl Save for MarketPriceCache {
     }
 }
 
+impl Entries for MarketPriceCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct MarketPriceEntry {
@@ -0,0 +1,47 @@
+use caph_eve_data_wrapper::TypeId;
+use cachem::Parse;
+
+use crate::{DAY, MarketPriceEntry, TimeSeries, TimeSeriesCache};
+
+pub type MarketPriceHistoryCache = TimeSeriesCache<TypeId, MarketPriceHistoryEntry>;
+
+/// Average and adjusted price of an item at the time of a price refresh
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct MarketPriceHistoryEntry {
+    /// Timestamp in milliseconds
+    pub timestamp:      u64,
+    pub adjusted_price: f32,
+    pub average_price:  f32,
+}
+
+impl MarketPriceHistoryEntry {
+    pub fn new(timestamp: u64, price: &MarketPriceEntry) -> Self {
+        Self {
+            timestamp,
+            adjusted_price: price.adjusted_price,
+            average_price:  price.average_price,
+        }
+    }
+}
+
+impl TimeSeries for MarketPriceHistoryEntry {
+    const NAME: &'static str = "market_price_history";
+    const FILE: &'static str = "./db/market_price_history.cachem";
+    const MAX_AGE: Option<u64> = Some(365 * DAY);
+    const DOWNSAMPLE_AFTER: Option<u64> = Some(7 * DAY);
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Mean of all prices of the bucket
+    fn merge(entries: &[Self]) -> Self {
+        let count = entries.len() as f32;
+        Self {
+            timestamp:      entries[0].timestamp,
+            adjusted_price: entries.iter().map(|x| x.adjusted_price).sum::<f32>() / count,
+            average_price:  entries.iter().map(|x| x.average_price).sum::<f32>() / count,
+        }
+    }
+}
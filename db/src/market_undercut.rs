@@ -0,0 +1,265 @@
+use async_trait::*;
+use caph_eve_data_wrapper::TypeId;
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = TypeId;
+type Val = MarketUndercutEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct MarketUndercutCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl MarketUndercutCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for MarketUndercutCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for MarketUndercutCache {
+    fn name(&self) -> String {
+        "market_undercut".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for MarketUndercutCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for MarketUndercutCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for MarketUndercutCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for MarketUndercutCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for MarketUndercutCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/market_undercut.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for MarketUndercutCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct MarketUndercutEntry {
+    pub type_id:    TypeId,
+    /// Lowest sell price of the last snapshot
+    pub best_price: f32,
+    /// Timestamp of the last snapshot
+    pub timestamp:  u64,
+    pub snapshots:  Vec<MarketUndercutSnapshot>,
+}
+
+impl MarketUndercutEntry {
+    /// Snapshots older than 24 hours are removed
+    pub const MAX_AGE: u64 = 24 * 60 * 60 * 1_000;
+
+    pub fn new(
+        type_id:    TypeId,
+        best_price: f32,
+        timestamp:  u64,
+    ) -> Self {
+        Self {
+            type_id,
+            best_price,
+            timestamp,
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Adds a new snapshot and removes all snapshots that are older than
+    /// [MarketUndercutEntry::MAX_AGE]
+    pub fn push(
+        &mut self,
+        best_price: f32,
+        timestamp:  u64,
+        undercuts:  u32,
+    ) {
+        let oldest = timestamp.saturating_sub(Self::MAX_AGE);
+
+        self.snapshots.retain(|x| x.timestamp > oldest);
+        self.snapshots.push(MarketUndercutSnapshot {
+            since: self.timestamp,
+            timestamp,
+            undercuts,
+        });
+        self.best_price = best_price;
+        self.timestamp  = timestamp;
+    }
+
+    /// Average number of undercuts per hour over all stored snapshots
+    pub fn per_hour(&self) -> f32 {
+        let first = self.snapshots.first().map(|x| x.since);
+        let last  = self.snapshots.last().map(|x| x.timestamp);
+
+        let hours = match (first, last) {
+            (Some(first), Some(last)) if last > first => {
+                (last - first) as f32 / (60f32 * 60f32 * 1_000f32)
+            },
+            _ => return 0f32
+        };
+
+        let undercuts = self
+            .snapshots
+            .iter()
+            .map(|x| x.undercuts)
+            .sum::<u32>();
+        undercuts as f32 / hours
+    }
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct MarketUndercutSnapshot {
+    /// Timestamp of the previous snapshot
+    pub since:     u64,
+    /// Timestamp of this snapshot
+    pub timestamp: u64,
+    /// Number of orders that were placed or modified between both snapshots
+    /// and undercut the previous best price
+    pub undercuts: u32,
+}
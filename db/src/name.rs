@@ -1,13 +1,17 @@
 use async_trait::*;
-use caph_eve_data_wrapper::TypeId;
-use cachem::{Parse, v2::{Cache, Command, Get, Key, Set, Save}};
+use caph_eve_data_wrapper::ItemId;
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::BufStream;
 use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 
-type Idx = TypeId;
+use crate::Entries;
+
+/// Types, stations and unique names share the same id space, structure
+/// ids exceed u32
+type Idx = ItemId;
 type Val = String;
 type Typ = HashMap<Idx, Val>;
 
@@ -39,6 +43,18 @@ impl Cache for NameCache {
 
     async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
         match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::Get => {
                 let key = Idx::read(buf).await.unwrap();
                 let val = self.get(key, None).await;
@@ -85,6 +101,19 @@ impl Cache for NameCache {
     }
 }
 
+#[async_trait]
+impl Del for NameCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
 #[async_trait]
 impl Get for NameCache {
     type Idx =   Idx;
@@ -135,7 +164,9 @@ impl Save for NameCache {
     type Typ = Typ;
 
     fn file(&self) -> &str {
-        "./db/names.cachem"
+        // the keys were u32 in names.cachem, the collector fills the new
+        // file with the next sde run
+        "./db/names_v2.cachem"
     }
 
     async fn read(&self) -> Self::Typ {
@@ -147,3 +178,11 @@ impl Save for NameCache {
     }
 }
 
+impl Entries for NameCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
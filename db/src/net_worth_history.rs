@@ -0,0 +1,50 @@
+use cachem::Parse;
+
+use crate::{TimeSeries, TimeSeriesCache, WorkspaceKey};
+
+/// Snapshots are stored once private and once for every workspace the main
+/// of the character is a member of
+pub type NetWorthHistoryCache = TimeSeriesCache<WorkspaceKey, NetWorthEntry>;
+
+/// Daily snapshot of the net worth of a character
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct NetWorthEntry {
+    /// Timestamp in milliseconds
+    pub timestamp:      u64,
+    /// Average market price of all assets in ISK
+    pub asset_value:    u64,
+    /// ISK in the wallet
+    pub wallet_balance: u64,
+}
+
+impl NetWorthEntry {
+    pub fn new(
+        timestamp:      u64,
+        asset_value:    u64,
+        wallet_balance: u64,
+    ) -> Self {
+        Self {
+            timestamp,
+            asset_value,
+            wallet_balance,
+        }
+    }
+
+    /// Assets and wallet together
+    pub fn total(&self) -> u64 {
+        self.asset_value + self.wallet_balance
+    }
+}
+
+impl TimeSeries for NetWorthEntry {
+    const NAME: &'static str = "net_worth_history";
+    // the keys were character ids in net_worth_history.cachem, the
+    // snapshots start again with the workspace keys
+    const FILE: &'static str = "./db/net_worth_history_v2.cachem";
+    const MAX_ENTRIES: Option<usize> = Some(2 * 365);
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
@@ -8,6 +8,8 @@ use tokio::net::TcpStream;
 use tokio::sync::{RwLock, watch::Receiver};
 use uuid::Uuid;
 
+use crate::Entries;
+
 type Idx = Uuid;
 type Val = ProjectEntry;
 type Typ = HashMap<Idx, Val>;
@@ -47,6 +49,12 @@ impl Cache for ProjectCache {
                 self.save().await;
                 0u8.write(buf).await.unwrap();
             }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
             Command::Get => {
                 let key = Idx::read(buf).await.unwrap();
                 let val = self.get(key, None).await;
@@ -162,6 +170,14 @@ impl Save for ProjectCache {
     }
 }
 
+impl Entries for ProjectCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
 #[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, PartialEq, Parse)]
 pub struct ProjectEntry {
@@ -0,0 +1,432 @@
+//! Small query language for exploring the caches without exporting them.
+//!
+//! A query selects a cache by its name, optionally followed by filters, the
+//! columns that should be returned and a limit:
+//!
+//! ```text
+//! items where category_id = 6 and volume > 1000 select item_id, name limit 50
+//! ```
+//!
+//! Supported operators are `=`, `!=`, `<`, `<=`, `>` and `>=`. Nested fields
+//! are accessed with a dot, for example `output.pid`.
+//!
+//! The queries are executed by the database itself and are available over a
+//! separate tcp port. Every line is a query, the answer is a json array in a
+//! single line.
+//!
+//! The port has no authentication, caches that contain credentials, like
+//! users, sessions, logins and webhooks, are not registered.
+
+use crate::Entries;
+
+use async_trait::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Returns the entries of a cache that match the query as json.
+///
+/// Entries that are lists are flattened, so that every element is its own
+/// row. Entries that are not an object are wrapped as `{ "value": <entry> }`.
+#[async_trait]
+pub trait Rows: Send + Sync {
+    async fn rows(&self, query: &Query) -> Vec<Value>;
+}
+
+/// The entries are read under the read lock of the cache, so that the
+/// cache is not cloned for every query
+#[async_trait]
+impl<T, K, V> Rows for T
+    where
+        T: Entries<Typ = HashMap<K, V>> + Send + Sync,
+        K: Send + Sync,
+        V: Serialize + Send + Sync {
+
+    async fn rows(&self, query: &Query) -> Vec<Value> {
+        query.select(self.entries().read().await.values())
+    }
+}
+
+/// Executes queries against the registered caches
+#[derive(Default)]
+pub struct QueryServer {
+    caches: HashMap<String, Arc<dyn Rows>>,
+}
+
+impl QueryServer {
+    /// Registers a cache under the given name
+    pub fn add(&mut self, name: String, cache: Arc<dyn Rows>) {
+        self.caches.insert(name, cache);
+    }
+
+    /// Parses and executes the given query
+    pub async fn execute(&self, query: &str) -> Result<Vec<Value>, QueryError> {
+        let query = Query::parse(query)?;
+        let cache = self
+            .caches
+            .get(&query.cache)
+            .ok_or_else(|| QueryError::UnknownCache(query.cache.clone()))?;
+
+        Ok(cache.rows(&query).await)
+    }
+
+    /// Listens for queries, every line is a single query.
+    ///
+    /// This function is blocking
+    pub async fn listen(self, addr: &str) {
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let server = Arc::new(self);
+
+        loop {
+            let socket = match listener.accept().await {
+                Ok((x, _)) => x,
+                Err(e)     => {
+                    log::error!("Error accepting query connection {:?}", e);
+                    continue;
+                }
+            };
+
+            let server = server.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = socket.into_split();
+                let mut lines = BufReader::new(read).lines();
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    let answer = match server.execute(&line).await {
+                        Ok(x)  => serde_json::to_string(&x).unwrap_or_default(),
+                        Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+                    };
+                    if write.write_all(format!("{}\n", answer).as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub cache:   String,
+    pub filters: Vec<Filter>,
+    /// Empty if all columns should be returned
+    pub columns: Vec<String>,
+    pub limit:   Option<usize>,
+}
+
+impl Query {
+    /// Parses a query in the format
+    /// `<cache> [where <field> <op> <value> [and ...]] [select <field>, ...] [limit <n>]`
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let tokens = Self::tokenize(query)?;
+        let mut tokens = tokens.into_iter().peekable();
+
+        let cache = tokens
+            .next()
+            .ok_or_else(|| QueryError::Parse("Missing cache name".into()))?;
+        let mut result = Self {
+            cache,
+            filters: Vec::new(),
+            columns: Vec::new(),
+            limit:   None,
+        };
+
+        while let Some(keyword) = tokens.next() {
+            match keyword.to_lowercase().as_str() {
+                "where" => loop {
+                    let field = Self::expect(tokens.next(), "field")?;
+                    let op = Op::parse(&Self::expect(tokens.next(), "operator")?)?;
+                    let value = Self::value(&Self::expect(tokens.next(), "value")?);
+                    result.filters.push(Filter { field, op, value });
+
+                    if tokens.peek().map(|x| x.eq_ignore_ascii_case("and")).unwrap_or(false) {
+                        tokens.next();
+                    } else {
+                        break;
+                    }
+                },
+                "select" => loop {
+                    result.columns.push(Self::expect(tokens.next(), "column")?);
+
+                    if tokens.peek().map(|x| x == ",").unwrap_or(false) {
+                        tokens.next();
+                    } else {
+                        break;
+                    }
+                },
+                "limit" => {
+                    let limit = Self::expect(tokens.next(), "limit")?;
+                    let limit = limit
+                        .parse::<usize>()
+                        .map_err(|_| QueryError::Parse(format!("Invalid limit {}", limit)))?;
+                    result.limit = Some(limit);
+                },
+                x => return Err(QueryError::Parse(format!("Unexpected token {}", x))),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Checks if the row matches all filters
+    pub fn matches(&self, row: &Value) -> bool {
+        self
+            .filters
+            .iter()
+            .all(|x| x.matches(row))
+    }
+
+    /// Reduces the row to the selected columns
+    pub fn project(&self, row: Value) -> Value {
+        if self.columns.is_empty() {
+            return row;
+        }
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|x| (x.clone(), Self::field(&row, x).cloned().unwrap_or(Value::Null)))
+            .collect::<serde_json::Map<_, _>>();
+        Value::Object(columns)
+    }
+
+    /// Turns the entries into rows and returns the ones that match the
+    /// filters, reduced to the selected columns.
+    ///
+    /// The entries are only read until the limit is reached.
+    pub fn select<'a, I, V>(&self, entries: I) -> Vec<Value>
+        where
+            I: Iterator<Item = &'a V>,
+            V: Serialize + 'a {
+
+        entries
+            .filter_map(|x| serde_json::to_value(x).ok())
+            .flat_map(|x| match x {
+                Value::Array(x) => x,
+                x               => vec![x],
+            })
+            .map(|x| match x {
+                Value::Object(_) => x,
+                x                => serde_json::json!({ "value": x }),
+            })
+            .filter(|x| self.matches(x))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|x| self.project(x))
+            .collect::<Vec<_>>()
+    }
+
+    /// Resolves a dot separated field path
+    fn field<'a>(row: &'a Value, path: &str) -> Option<&'a Value> {
+        path
+            .split('.')
+            .try_fold(row, |x, field| x.get(field))
+    }
+
+    fn expect(token: Option<String>, name: &str) -> Result<String, QueryError> {
+        token.ok_or_else(|| QueryError::Parse(format!("Missing {}", name)))
+    }
+
+    /// Quoted values are strings, everything else is parsed as json with a
+    /// fallback to a string
+    fn value(token: &str) -> Value {
+        if let Some(x) = token.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')) {
+            Value::String(x.into())
+        } else {
+            serde_json::from_str(token).unwrap_or_else(|_| Value::String(token.into()))
+        }
+    }
+
+    fn tokenize(query: &str) -> Result<Vec<String>, QueryError> {
+        let mut tokens = Vec::new();
+        let mut chars = query.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                x if x.is_whitespace() => continue,
+                ',' => tokens.push(",".into()),
+                '\'' => {
+                    let mut token = String::from("'");
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(x)    => token.push(x),
+                            None       => return Err(QueryError::Parse("Unclosed quote".into())),
+                        }
+                    }
+                    token.push('\'');
+                    tokens.push(token);
+                },
+                '=' | '!' | '<' | '>' => {
+                    let mut token = c.to_string();
+                    if chars.peek() == Some(&'=') {
+                        token.push(chars.next().unwrap());
+                    }
+                    tokens.push(token);
+                },
+                _ => {
+                    let mut token = c.to_string();
+                    while let Some(x) = chars.peek() {
+                        if x.is_whitespace() || ",=!<>'".contains(*x) {
+                            break;
+                        }
+                        token.push(chars.next().unwrap());
+                    }
+                    tokens.push(token);
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op:    Op,
+    pub value: Value,
+}
+
+impl Filter {
+    /// Numbers and strings are compared by value, everything else can only
+    /// be checked for equality. Missing fields never match.
+    pub fn matches(&self, row: &Value) -> bool {
+        let field = if let Some(x) = Query::field(row, &self.field) {
+            x
+        } else {
+            return false;
+        };
+
+        let ordering = match (field, &self.value) {
+            (Value::Number(a), Value::Number(b)) => {
+                a.as_f64().partial_cmp(&b.as_f64())
+            },
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b                     => Some(Ordering::Equal),
+            _                                    => None,
+        };
+
+        match (self.op, ordering) {
+            (Op::Eq, Some(x)) => x == Ordering::Equal,
+            (Op::Ne, Some(x)) => x != Ordering::Equal,
+            (Op::Ne, None)    => true,
+            (Op::Lt, Some(x)) => x == Ordering::Less,
+            (Op::Le, Some(x)) => x != Ordering::Greater,
+            (Op::Gt, Some(x)) => x == Ordering::Greater,
+            (Op::Ge, Some(x)) => x != Ordering::Less,
+            _                 => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn parse(op: &str) -> Result<Self, QueryError> {
+        match op {
+            "="  => Ok(Self::Eq),
+            "!=" => Ok(Self::Ne),
+            "<"  => Ok(Self::Lt),
+            "<=" => Ok(Self::Le),
+            ">"  => Ok(Self::Gt),
+            ">=" => Ok(Self::Ge),
+            x    => Err(QueryError::Parse(format!("Unknown operator {}", x))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum QueryError {
+    Parse(String),
+    UnknownCache(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(x)        => write!(f, "Invalid query: {}", x),
+            Self::UnknownCache(x) => write!(f, "Unknown cache: {}", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse() {
+        let query = Query::parse("items where category_id = 6 and volume>1000 select item_id, name limit 50").unwrap();
+
+        assert_eq!(query.cache, "items");
+        assert_eq!(query.filters, vec![
+            Filter { field: "category_id".into(), op: Op::Eq, value: json!(6) },
+            Filter { field: "volume".into(),      op: Op::Gt, value: json!(1000) },
+        ]);
+        assert_eq!(query.columns, vec!["item_id".to_string(), "name".to_string()]);
+        assert_eq!(query.limit, Some(50));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("items where volume").is_err());
+        assert!(Query::parse("items where volume ~ 5").is_err());
+        assert!(Query::parse("items limit many").is_err());
+        assert!(Query::parse("items where name = 'Tritanium").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let row = json!({
+            "name":   "Tritanium",
+            "volume": 0.01,
+            "output": { "pid": 34 }
+        });
+
+        assert!(Query::parse("items where name = 'Tritanium'").unwrap().matches(&row));
+        assert!(Query::parse("items where volume < 1").unwrap().matches(&row));
+        assert!(Query::parse("items where output.pid >= 34").unwrap().matches(&row));
+        assert!(Query::parse("items where name != Pyerite").unwrap().matches(&row));
+        assert!(!Query::parse("items where volume > 1").unwrap().matches(&row));
+        assert!(!Query::parse("items where missing = 1").unwrap().matches(&row));
+    }
+
+    #[test]
+    fn project() {
+        let row = json!({ "item_id": 34, "name": "Tritanium", "volume": 0.01 });
+        let query = Query::parse("items select name, group_id").unwrap();
+
+        assert_eq!(query.project(row), json!({ "name": "Tritanium", "group_id": null }));
+    }
+
+    #[test]
+    fn select() {
+        let entries = vec![json!([1, 2, 3]), json!(4), json!(5)];
+
+        let query = Query::parse("numbers where value > 1 limit 3").unwrap();
+        assert_eq!(query.select(entries.iter()), vec![
+            json!({ "value": 2 }),
+            json!({ "value": 3 }),
+            json!({ "value": 4 }),
+        ]);
+    }
+}
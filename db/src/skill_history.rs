@@ -0,0 +1,40 @@
+use caph_eve_data_wrapper::CharacterId;
+use cachem::Parse;
+
+use crate::{TimeSeries, TimeSeriesCache};
+
+pub type SkillHistoryCache = TimeSeriesCache<CharacterId, SkillHistoryEntry>;
+
+/// Snapshot of the skillpoints of a character
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct SkillHistoryEntry {
+    /// Timestamp in milliseconds
+    pub timestamp:      u64,
+    pub total_sp:       u64,
+    pub unallocated_sp: u32,
+}
+
+impl SkillHistoryEntry {
+    pub fn new(
+        timestamp:      u64,
+        total_sp:       u64,
+        unallocated_sp: u32,
+    ) -> Self {
+        Self {
+            timestamp,
+            total_sp,
+            unallocated_sp,
+        }
+    }
+}
+
+impl TimeSeries for SkillHistoryEntry {
+    const NAME: &'static str = "skill_history";
+    const FILE: &'static str = "./db/skill_history.cachem";
+    const MAX_ENTRIES: Option<usize> = Some(2 * 365);
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
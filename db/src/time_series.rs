@@ -0,0 +1,505 @@
+//! Append only storage for values that are recorded over time.
+//!
+//! Every key holds a list of entries sorted by their timestamp. Writing
+//! appends the given entries, entries that are not newer than the last
+//! stored entry are ignored. On every write the retention of the series is
+//! applied, old entries are first downsampled and then removed.
+//!
+//! A new series only needs an entry that implements [TimeSeries] and a type
+//! alias:
+//!
+//! ```text
+//! pub type SkillHistoryCache = TimeSeriesCache<CharacterId, SkillHistoryEntry>;
+//! ```
+//!
+//! The series can be bounded in memory with a [MemoryBound], see
+//! [MemoryBound::from_env].
+
+use async_trait::*;
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::{Lru, MemoryBound};
+
+/// One day in milliseconds
+pub const DAY: u64 = 24 * 60 * 60 * 1_000;
+
+/// Entry of a [TimeSeriesCache] together with the retention of the series
+pub trait TimeSeries: Clone + Parse + Send + Sync + 'static {
+    /// Name of the cache
+    const NAME: &'static str;
+    /// File the cache is stored in
+    const FILE: &'static str;
+    /// Entries older than this many milliseconds are removed
+    const MAX_AGE: Option<u64> = None;
+    /// Maximum number of entries per key, the oldest are removed first
+    const MAX_ENTRIES: Option<usize> = None;
+    /// Entries older than this many milliseconds are merged into one entry
+    /// per [TimeSeries::BUCKET]
+    const DOWNSAMPLE_AFTER: Option<u64> = None;
+    /// Size of a downsampled bucket in milliseconds
+    const BUCKET: u64 = DAY;
+
+    /// Timestamp in milliseconds
+    fn timestamp(&self) -> u64;
+
+    /// Merges all entries of a bucket into a single entry.
+    ///
+    /// The entries are sorted and never empty, by default the last one is
+    /// kept.
+    fn merge(entries: &[Self]) -> Self {
+        entries[entries.len() - 1].clone()
+    }
+}
+
+type Typ<K, V> = HashMap<K, Vec<V>>;
+
+pub struct TimeSeriesCache<K, V> {
+    cache: Arc<RwLock<Lru<K, Vec<V>>>>,
+    cnc:   Receiver<Command>,
+
+    _value: PhantomData<V>,
+}
+
+impl<K, V> Clone for TimeSeriesCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            cache:  self.cache.clone(),
+            cnc:    self.cnc.clone(),
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<K, V> TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        // the evicted entries are stored next to the cache file
+        let dir = V::FILE.trim_end_matches(".cachem").to_string();
+        let lru = Lru::new(dir, MemoryBound::from_env(V::NAME));
+
+        Self {
+            cache:  Arc::new(RwLock::new(lru)),
+            cnc,
+            _value: PhantomData,
+        }
+    }
+
+    /// Gets all entries of a key in the given range
+    pub async fn range(&self, idx: K, range: TimeSeriesRange) -> Option<Vec<V>> {
+        self
+            .cache
+            .write()
+            .await
+            .get(&idx)
+            .await
+            .map(|x| Self::select(&x, &range))
+    }
+
+    /// Appends all entries that are newer than the last stored entry and
+    /// applies the retention
+    pub async fn append(&self, idx: K, entries: Vec<V>) {
+        let now = Utc::now().timestamp_millis() as u64;
+
+        let mut cache = self.cache.write().await;
+        let mut series = cache.get(&idx).await.unwrap_or_default();
+        for entry in entries {
+            let newer = series
+                .last()
+                .map(|x| entry.timestamp() > x.timestamp())
+                .unwrap_or(true);
+            if newer {
+                series.push(entry);
+            }
+        }
+        Self::retain(&mut series, now);
+        cache.insert(idx, series).await;
+    }
+
+    /// Writes the cache file and the index of the evicted entries
+    pub async fn persist(&self) {
+        self.cache.write().await.flush().await;
+        self.save().await;
+    }
+
+    /// Filters the entries by the range and downsamples them if requested
+    fn select(entries: &[V], range: &TimeSeriesRange) -> Vec<V> {
+        let end = if range.end == 0 { u64::MAX } else { range.end };
+        let entries = entries
+            .iter()
+            .filter(|x| x.timestamp() >= range.start && x.timestamp() <= end)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if range.bucket == 0 {
+            entries
+        } else {
+            Self::downsample(&entries, range.bucket)
+        }
+    }
+
+    /// Removes and downsamples entries according to the retention of the
+    /// series
+    fn retain(entries: &mut Vec<V>, now: u64) {
+        if let Some(max_age) = V::MAX_AGE {
+            let oldest = now.saturating_sub(max_age);
+            entries.retain(|x| x.timestamp() >= oldest);
+        }
+
+        if let Some(after) = V::DOWNSAMPLE_AFTER {
+            let until = now.saturating_sub(after);
+            let split = entries
+                .iter()
+                .position(|x| x.timestamp() >= until)
+                .unwrap_or(entries.len());
+            let recent = entries.split_off(split);
+            *entries = Self::downsample(entries, V::BUCKET);
+            entries.extend(recent);
+        }
+
+        if let Some(max_entries) = V::MAX_ENTRIES {
+            if entries.len() > max_entries {
+                let remove = entries.len() - max_entries;
+                entries.drain(..remove);
+            }
+        }
+    }
+
+    /// Merges all sorted entries that are in the same bucket
+    fn downsample(entries: &[V], bucket: u64) -> Vec<V> {
+        let mut result = Vec::new();
+        let mut start = 0;
+        for i in 1..=entries.len() {
+            let same = i < entries.len() &&
+                entries[i].timestamp() / bucket == entries[start].timestamp() / bucket;
+            if !same && start < entries.len() {
+                result.push(V::merge(&entries[start..i]));
+                start = i;
+            }
+        }
+        result
+    }
+}
+
+impl<K, V> Into<Arc<Box<dyn Cache>>> for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl<K, V> Cache for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    fn name(&self) -> String {
+        V::NAME.into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = K::read(buf).await.unwrap();
+                self.del(key).await;
+                self.persist().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<K>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.persist().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = K::read(buf).await.unwrap();
+                let params = Option::<TimeSeriesRange>::read(buf).await.unwrap();
+                let val = self.get(key, params).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<K>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = K::read(buf).await.unwrap();
+                let val = Vec::<V>::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.persist().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<K, Vec<V>>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.persist().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.persist().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<K, V> Del for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    type Idx = K;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx)
+            .await;
+    }
+}
+
+#[async_trait]
+impl<K, V> Get for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    type Idx =   K;
+    type Res =   Vec<V>;
+    type Param = TimeSeriesRange;
+
+    async fn get(&self, idx: Self::Idx, range: Option<Self::Param>) -> Option<Self::Res> {
+        self.range(idx, range.unwrap_or_default()).await
+    }
+}
+
+/// Setting a key appends the entries instead of replacing the series
+#[async_trait]
+impl<K, V> Set for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    type Idx = K;
+    type Val = Vec<V>;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self.append(idx, val).await;
+    }
+}
+
+#[async_trait]
+impl<K, V> Key for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    type Idx = K;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+    }
+}
+
+#[async_trait]
+impl<K, V> Save for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries {
+
+    type Typ = Typ<K, V>;
+
+    fn file(&self) -> &str {
+        V::FILE
+    }
+
+    /// Only the entries in memory, evicted entries are already on disk
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.in_memory()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        self.cache.write().await.replace(data).await;
+    }
+}
+
+/// Only the entries in memory, the same as in the cache file
+#[cfg(feature = "with_serde")]
+#[async_trait]
+impl<K, V> crate::Rows for TimeSeriesCache<K, V>
+    where
+        K: Copy + Display + Eq + Hash + Parse + Send + Sync + 'static,
+        V: TimeSeries + serde::Serialize {
+
+    async fn rows(&self, query: &crate::Query) -> Vec<serde_json::Value> {
+        query.select(self.cache.read().await.values())
+    }
+}
+
+/// Range of entries that should be returned.
+///
+/// Without a range the whole series is returned.
+#[derive(Clone, Debug, Default, Parse)]
+pub struct TimeSeriesRange {
+    /// Timestamp in milliseconds of the first entry
+    pub start:  u64,
+    /// Timestamp in milliseconds of the last entry, 0 for no limit
+    pub end:    u64,
+    /// Merges the entries into buckets of this many milliseconds, 0 to
+    /// return all entries
+    pub bucket: u64,
+}
+
+#[cfg(test)]
+mod time_series_tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Parse)]
+    struct Entry {
+        timestamp: u64,
+        value:     u32,
+    }
+
+    impl TimeSeries for Entry {
+        const NAME: &'static str = "test";
+        const FILE: &'static str = "./db/test.cachem";
+        const MAX_AGE: Option<u64> = Some(10 * DAY);
+        const MAX_ENTRIES: Option<usize> = Some(5);
+        const DOWNSAMPLE_AFTER: Option<u64> = Some(2 * DAY);
+
+        fn timestamp(&self) -> u64 {
+            self.timestamp
+        }
+
+        fn merge(entries: &[Self]) -> Self {
+            Self {
+                timestamp: entries[0].timestamp,
+                value:     entries.iter().map(|x| x.value).sum(),
+            }
+        }
+    }
+
+    type Series = TimeSeriesCache<u32, Entry>;
+
+    fn entry(timestamp: u64, value: u32) -> Entry {
+        Entry { timestamp, value }
+    }
+
+    #[test]
+    fn downsample() {
+        let entries = vec![
+            entry(0, 1),
+            entry(DAY - 1, 2),
+            entry(DAY, 3),
+            entry(3 * DAY, 4),
+        ];
+        assert_eq!(
+            Series::downsample(&entries, DAY),
+            vec![entry(0, 3), entry(DAY, 3), entry(3 * DAY, 4)]
+        );
+        assert_eq!(Series::downsample(&[], DAY), vec![]);
+    }
+
+    #[test]
+    fn retain() {
+        let now = 20 * DAY;
+        let mut entries = vec![
+            // older than the maximum age
+            entry(5 * DAY, 1),
+            // downsampled into one entry
+            entry(15 * DAY, 1),
+            entry(15 * DAY + 1, 1),
+            entry(17 * DAY, 1),
+            // recent enough to be kept as they are
+            entry(19 * DAY, 1),
+            entry(19 * DAY + 1, 1),
+        ];
+        Series::retain(&mut entries, now);
+        assert_eq!(
+            entries,
+            vec![
+                entry(15 * DAY, 2),
+                entry(17 * DAY, 1),
+                entry(19 * DAY, 1),
+                entry(19 * DAY + 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn select() {
+        let entries = vec![
+            entry(DAY, 1),
+            entry(DAY + 1, 1),
+            entry(2 * DAY, 1),
+            entry(3 * DAY, 1),
+        ];
+        let range = TimeSeriesRange {
+            start:  DAY,
+            end:    2 * DAY,
+            bucket: 0,
+        };
+        assert_eq!(Series::select(&entries, &range).len(), 3);
+
+        let range = TimeSeriesRange {
+            start:  0,
+            end:    0,
+            bucket: DAY,
+        };
+        assert_eq!(
+            Series::select(&entries, &range),
+            vec![entry(DAY, 2), entry(2 * DAY, 1), entry(3 * DAY, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn append_ignores_older_entries() {
+        let (_, rx) = tokio::sync::watch::channel(Command::Ping);
+        let series = Series::new(rx);
+        let now = Utc::now().timestamp_millis() as u64;
+
+        series.append(0, vec![entry(now, 1)]).await;
+        series.append(0, vec![entry(now - 1, 2), entry(now, 3), entry(now + 1, 4)]).await;
+        assert_eq!(
+            series.range(0, TimeSeriesRange::default()).await,
+            Some(vec![entry(now, 1), entry(now + 1, 4)])
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use async_trait::*;
+use caph_eve_data_wrapper::CharacterId;
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = CharacterId;
+type Val = UserPreferenceEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct UserPreferenceCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl UserPreferenceCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for UserPreferenceCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for UserPreferenceCache {
+    fn name(&self) -> String {
+        "user_preference".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for UserPreferenceCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for UserPreferenceCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for UserPreferenceCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for UserPreferenceCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for UserPreferenceCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/user_preference.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for UserPreferenceCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+/// Preferences of a main, they apply to the main and all its alts
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct UserPreferenceEntry {
+    #[cfg_attr(feature = "with_serde", serde(skip_deserializing, default = "default_character_id"))]
+    pub user_id:         CharacterId,
+    /// Hour in EVE time the sync window starts, inclusive
+    pub sync_start:      Option<u8>,
+    /// Hour in EVE time the sync window ends, exclusive. If the end is
+    /// before the start, the window goes over midnight
+    pub sync_end:        Option<u8>,
+    /// Data types that should be synced less often than on every run
+    pub frequencies:     Vec<SyncFrequencyEntry>,
+    /// Days without a login after which the data of the user is exported
+    /// and the tokens are removed
+    pub deadman_days:    Option<u32>,
+    /// Discord compatible webhook the export is sent to
+    pub deadman_webhook: Option<String>,
+}
+
+impl UserPreferenceEntry {
+    /// Data types that can be configured
+    pub const SYNC_TYPES: &'static [&'static str] = &[
+        "assets",
+        "blueprints",
+        "calendar",
+        "clones",
+        "contracts",
+        "fittings",
+        "mining",
+        "net_worth",
+        "notifications",
+        "planets",
+        "skills",
+        "wallet_transactions",
+    ];
+
+    /// Checks if the given hour is within the sync window, if no window is
+    /// configured every hour is valid
+    pub fn in_window(&self, hour: u8) -> bool {
+        match (self.sync_start, self.sync_end) {
+            (Some(start), Some(end)) if start <= end => hour >= start && hour < end,
+            (Some(start), Some(end)) => hour >= start || hour < end,
+            _ => true,
+        }
+    }
+
+    /// Frequency in minutes that is used if the user did not configure one
+    pub const DEFAULT_FREQUENCIES: &'static [(&'static str, u32)] = &[
+        ("assets",              60),
+        ("blueprints",          60),
+        ("calendar",            30),
+        ("clones",              6 * 60),
+        ("contracts",           30),
+        ("fittings",            24 * 60),
+        ("mining",              60),
+        ("net_worth",           24 * 60),
+        ("notifications",       10),
+        ("planets",             60),
+        ("skills",              24 * 60),
+        ("wallet_transactions", 30),
+    ];
+
+    /// Time in minutes ESI caches the data type, syncing more often would
+    /// only return the same data
+    pub const ESI_CACHE: &'static [(&'static str, u32)] = &[
+        ("assets",              60),
+        ("blueprints",          60),
+        ("calendar",            5),
+        ("clones",              2),
+        ("contracts",           5),
+        ("fittings",            5),
+        ("mining",              10),
+        ("net_worth",           2),
+        ("notifications",       10),
+        ("planets",             10),
+        ("skills",              2),
+        ("wallet_transactions", 60),
+    ];
+
+    /// Configured frequency of the data type in minutes
+    pub fn frequency(&self, typ: &str) -> Option<u32> {
+        self
+            .frequencies
+            .iter()
+            .find(|x| x.typ == typ)
+            .map(|x| x.minutes)
+    }
+
+    /// Frequency in minutes the data type is synced with.
+    ///
+    /// Uses the configured frequency or the default if there is none, but
+    /// never syncs more often than ESI refreshes the data.
+    pub fn sync_frequency(preference: Option<&Self>, typ: &str) -> u32 {
+        let lookup = |table: &[(&str, u32)]| table
+            .iter()
+            .find(|(x, _)| *x == typ)
+            .map(|(_, x)| *x)
+            .unwrap_or_default();
+
+        let frequency = preference
+            .and_then(|x| x.frequency(typ))
+            .unwrap_or_else(|| lookup(Self::DEFAULT_FREQUENCIES));
+        frequency.max(lookup(Self::ESI_CACHE))
+    }
+}
+
+#[cfg(feature = "with_serde")]
+fn default_character_id() -> CharacterId {
+    0u32.into()
+}
+
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct SyncFrequencyEntry {
+    /// One of [UserPreferenceEntry::SYNC_TYPES]
+    #[cfg_attr(feature = "with_serde", serde(rename = "type"))]
+    pub typ:     String,
+    pub minutes: u32,
+}
+
+#[cfg(test)]
+mod user_preference_tests {
+    use super::*;
+
+    fn preference(start: Option<u8>, end: Option<u8>) -> UserPreferenceEntry {
+        UserPreferenceEntry {
+            user_id:         0u32.into(),
+            sync_start:      start,
+            sync_end:        end,
+            frequencies:     Vec::new(),
+            deadman_days:    None,
+            deadman_webhook: None,
+        }
+    }
+
+    #[test]
+    fn in_window() {
+        let x = preference(Some(18), Some(23));
+        assert!(!x.in_window(17));
+        assert!(x.in_window(18));
+        assert!(x.in_window(22));
+        assert!(!x.in_window(23));
+    }
+
+    #[test]
+    fn in_window_over_midnight() {
+        let x = preference(Some(22), Some(2));
+        assert!(x.in_window(23));
+        assert!(x.in_window(1));
+        assert!(!x.in_window(2));
+        assert!(!x.in_window(12));
+    }
+
+    #[test]
+    fn sync_frequency() {
+        assert_eq!(UserPreferenceEntry::sync_frequency(None, "skills"), 24 * 60);
+        // Wallet transactions are cached for an hour by ESI
+        assert_eq!(UserPreferenceEntry::sync_frequency(None, "wallet_transactions"), 60);
+
+        let mut x = preference(None, None);
+        x.frequencies.push(SyncFrequencyEntry {
+            typ:     "skills".into(),
+            minutes: 0,
+        });
+        assert_eq!(UserPreferenceEntry::sync_frequency(Some(&x), "skills"), 2);
+        assert_eq!(UserPreferenceEntry::sync_frequency(Some(&x), "assets"), 60);
+    }
+
+    #[test]
+    fn in_window_not_configured() {
+        assert!(preference(None, None).in_window(12));
+        assert!(preference(Some(18), None).in_window(12));
+    }
+}
@@ -0,0 +1,258 @@
+use async_trait::*;
+use caph_eve_data_wrapper::{CharacterId, CorporationId};
+use cachem::{Parse, v2::{Cache, Command, Del, Get, Key, Set, Save}};
+use uuid::Uuid;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::io::BufStream;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, watch::Receiver};
+
+use crate::Entries;
+
+type Idx = Uuid;
+type Val = WorkspaceEntry;
+type Typ = HashMap<Idx, Val>;
+
+#[derive(Clone)]
+pub struct WorkspaceCache {
+    cache: Arc<RwLock<Typ>>,
+    cnc:   Receiver<Command>,
+}
+
+impl WorkspaceCache {
+    pub fn new(cnc: Receiver<Command>) -> Self {
+        Self {
+            cache: Arc::new(RwLock::default()),
+            cnc,
+        }
+    }
+}
+
+impl Into<Arc<Box<dyn Cache>>> for WorkspaceCache {
+    fn into(self) -> Arc<Box<dyn Cache>> {
+        Arc::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl Cache for WorkspaceCache {
+    fn name(&self) -> String {
+        "workspace".into()
+    }
+
+    async fn handle(&self, cmd: Command, buf: &mut BufStream<TcpStream>) {
+        match cmd {
+            Command::Del => {
+                let key = Idx::read(buf).await.unwrap();
+                self.del(key).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MDel => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                self.mdel(keys).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::Get => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = self.get(key, None).await;
+                val.write(buf).await.unwrap();
+            }
+            Command::MGet => {
+                let keys = Vec::<Idx>::read(buf).await.unwrap();
+                let vals = self.mget(keys, None).await;
+                vals.write(buf).await.unwrap();
+            }
+            Command::Keys => {
+                self.keys().await.write(buf).await.unwrap();
+            }
+            Command::Set => {
+                let key = Idx::read(buf).await.unwrap();
+                let val = Val::read(buf).await.unwrap();
+                self.set(key, val).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            Command::MSet => {
+                let vals = HashMap::<Idx, Val>::read(buf).await.unwrap();
+                self.mset(vals).await;
+                self.save().await;
+                0u8.write(buf).await.unwrap();
+            }
+            _ => {
+                log::error!("Invalid cmd {:?}", cmd);
+            }
+        }
+    }
+
+    async fn cnc_listener(&self) {
+        let mut cnc_copy = self.cnc.clone();
+        loop {
+            cnc_copy.changed().await.unwrap();
+            let cmd = *cnc_copy.borrow();
+
+            match cmd {
+                Command::Save => { self.save().await; },
+                _ => { log::warn!("Invalid cmd send over cnc: {:?}", cmd); }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Del for WorkspaceCache {
+    type Idx = Idx;
+
+    async fn del(&self, idx: Self::Idx) {
+        self
+            .cache
+            .write()
+            .await
+            .remove(&idx);
+    }
+}
+
+#[async_trait]
+impl Get for WorkspaceCache {
+    type Idx =   Idx;
+    type Res =   Val;
+    type Param = ();
+
+    async fn get(&self, idx: Self::Idx, _: Option<Self::Param>) -> Option<Self::Res> {
+        self
+            .cache
+            .read()
+            .await
+            .get(&idx)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Set for WorkspaceCache {
+    type Idx = Idx;
+    type Val = Val;
+
+    async fn set(&self, idx: Self::Idx, val: Self::Val) {
+        self
+            .cache
+            .write()
+            .await
+            .insert(idx, val);
+    }
+}
+
+#[async_trait]
+impl Key for WorkspaceCache {
+    type Idx = Idx;
+
+    async fn keys(&self) -> Vec<Self::Idx> {
+        self
+            .cache
+            .read()
+            .await
+            .keys()
+            .map(|x| *x)
+            .collect::<Vec<_>>()
+    }
+}
+
+#[async_trait]
+impl Save for WorkspaceCache {
+    type Typ = Typ;
+
+    fn file(&self) -> &str {
+        "./db/workspace.cachem"
+    }
+
+    async fn read(&self) -> Self::Typ {
+        self.cache.read().await.clone()
+    }
+
+    async fn write(&self, data: Self::Typ) {
+        *self.cache.write().await = data;
+    }
+}
+
+impl Entries for WorkspaceCache {
+    type Typ = Typ;
+
+    fn entries(&self) -> &RwLock<Self::Typ> {
+        &self.cache
+    }
+}
+
+/// Group of mains that share data, for example stock rules
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Parse)]
+pub struct WorkspaceEntry {
+    pub id:             Uuid,
+    pub name:           String,
+    /// Main that created the workspace, only it can change or delete it
+    pub user_id:        CharacterId,
+    /// If set, every main with the main or an alt in the corporation is a
+    /// member
+    pub corporation_id: Option<CorporationId>,
+    /// Mains that are members in addition to the corporation
+    pub members:        Vec<CharacterId>,
+}
+
+impl WorkspaceEntry {
+    /// Checks if the main or one of its alts is a member
+    ///
+    /// # Params
+    ///
+    /// `user_id`  -> Id of the main
+    /// `corp_ids` -> Corporations of the main and its alts
+    ///
+    pub fn is_member(
+        &self,
+        user_id:  CharacterId,
+        corp_ids: &[CorporationId],
+    ) -> bool {
+        self.user_id == user_id ||
+        self.members.contains(&user_id) ||
+        self.corporation_id.map(|x| corp_ids.contains(&x)).unwrap_or_default()
+    }
+}
+
+/// Key of caches whose entries are scoped by a workspace, currently the net
+/// worth history.
+///
+/// Entries without a workspace are private to the main of the character,
+/// entries with one are only visible to the members of that workspace.
+#[cfg_attr(feature = "with_serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Parse)]
+pub struct WorkspaceKey {
+    pub workspace_id: Option<Uuid>,
+    pub user_id:      CharacterId,
+}
+
+impl WorkspaceKey {
+    pub fn new(
+        workspace_id: Option<Uuid>,
+        user_id:      CharacterId,
+    ) -> Self {
+        Self {
+            workspace_id,
+            user_id,
+        }
+    }
+
+    /// Key of the entry that only the main of the character can see
+    pub fn private(user_id: CharacterId) -> Self {
+        Self::new(None, user_id)
+    }
+}
+
+impl fmt::Display for WorkspaceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.workspace_id {
+            Some(x) => write!(f, "{}_{}", x, self.user_id),
+            None    => write!(f, "{}", self.user_id),
+        }
+    }
+}
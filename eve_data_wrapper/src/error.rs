@@ -1,17 +1,26 @@
+use reqwest::Response;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
 use std::error::Error;
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum EveConnectError {
     CannotParse,
     EnvError(String),
+    EsiError(EsiError),
+    /// The eve servers are in downtime or ESI reported them as offline
+    EveOffline,
     IoError(std::io::Error),
     LoadingService,
     OAuthPayload(String),
     ReqwestError(reqwest::Error),
+    /// A request that was shared with other callers failed
+    SharedRequest(String),
     JsonError(serde_json::Error),
+    SdeError(SdeError),
     YamlError(serde_yaml::Error),
-    TooManyRetries(String),
     Unauthorized,
     ZipError(zip::result::ZipError),
 }
@@ -24,6 +33,18 @@ impl fmt::Display for EveConnectError {
     }
 }
 
+impl EveConnectError {
+    /// Returns `true` if ESI was not reachable and the request can be
+    /// tried again later
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::EsiError(x) => x.is_transient(),
+            Self::EveOffline  => true,
+            _                 => false,
+        }
+    }
+}
+
 impl From<std::io::Error> for EveConnectError {
     fn from(x: std::io::Error) -> Self {
         Self::IoError(x)
@@ -42,6 +63,12 @@ impl From<serde_yaml::Error> for EveConnectError {
     }
 }
 
+impl From<SdeError> for EveConnectError {
+    fn from(x: SdeError) -> Self {
+        Self::SdeError(x)
+    }
+}
+
 impl From<reqwest::Error> for EveConnectError {
     fn from(x: reqwest::Error) -> Self {
         Self::ReqwestError(x)
@@ -54,3 +81,138 @@ impl From<zip::result::ZipError> for EveConnectError {
     }
 }
 
+/// Error while reading a file of the SDE
+#[derive(Clone, Debug)]
+pub enum SdeError {
+    /// The file does not exist or its zip entry is corrupt
+    CorruptEntry {
+        path:    String,
+        message: String,
+    },
+    /// The file could not be inflated
+    Inflate {
+        path:    String,
+        message: String,
+    },
+    /// The yaml does not match the expected structure
+    Schema {
+        path:    String,
+        /// Line in the file, starting with 1
+        line:    usize,
+        column:  usize,
+        /// Byte offset in the inflated file
+        offset:  usize,
+        message: String,
+    },
+}
+
+impl SdeError {
+    pub(crate) fn inflate(path: &str, error: std::io::Error) -> Self {
+        Self::Inflate {
+            path:    path.into(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Creates a schema error, `line` and `offset` are the position of the
+    /// parsed yaml in the file
+    pub(crate) fn schema(
+        path:   &str,
+        error:  serde_yaml::Error,
+        line:   usize,
+        offset: usize,
+    ) -> Self {
+        let location = error.location();
+        Self::Schema {
+            path:    path.into(),
+            line:    line + location.as_ref().map(|x| x.line()).unwrap_or_default(),
+            column:  location.as_ref().map(|x| x.column()).unwrap_or_default(),
+            offset:  offset + location.as_ref().map(|x| x.index()).unwrap_or_default(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Path of the file in the SDE zip
+    pub fn path(&self) -> &str {
+        match self {
+            Self::CorruptEntry { path, .. } |
+            Self::Inflate { path, .. }      |
+            Self::Schema { path, .. }       => path,
+        }
+    }
+}
+
+impl fmt::Display for SdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CorruptEntry { path, message } => {
+                write!(f, "{}: corrupt zip entry, {}", path, message)
+            }
+            Self::Inflate { path, message } => {
+                write!(f, "{}: inflate failed, {}", path, message)
+            }
+            Self::Schema { path, line, column, offset, message } => {
+                write!(f, "{}:{}:{} (byte {}): {}", path, line, column, offset, message)
+            }
+        }
+    }
+}
+
+/// Error response of ESI
+#[derive(Clone, Debug)]
+pub struct EsiError {
+    /// Http status code
+    pub status:             u16,
+    /// Message that ESI send in the body
+    pub message:            Option<String>,
+    /// Time ESI asked us to wait, taken from the `Retry-After` header
+    pub retry_after:        Option<Duration>,
+    /// Number of errors left until ESI blocks us
+    pub error_limit_remain: Option<u16>,
+    /// Time until the error limit is reset
+    pub error_limit_reset:  Option<Duration>,
+}
+
+impl EsiError {
+    /// Reads the status code, headers and body of an error response
+    pub(crate) async fn from_response(response: Response) -> Self {
+        #[derive(Deserialize)]
+        struct Body {
+            error: String,
+        }
+
+        let status = response.status().as_u16();
+        let headers = response.headers();
+        let retry_after = Self::header::<u64>(headers, "retry-after")
+            .map(Duration::from_secs);
+        let error_limit_remain = Self::header::<u16>(headers, "x-esi-error-limit-remain");
+        let error_limit_reset = Self::header::<u64>(headers, "x-esi-error-limit-reset")
+            .map(Duration::from_secs);
+        let message = response
+            .json::<Body>()
+            .await
+            .map(|x| x.error)
+            .ok();
+
+        Self {
+            status,
+            message,
+            retry_after,
+            error_limit_remain,
+            error_limit_reset,
+        }
+    }
+
+    /// Returns `true` if ESI is currently not available, that is the case
+    /// for the status codes 502, 503 and 504
+    pub fn is_transient(&self) -> bool {
+        matches!(self.status, 502 | 503 | 504)
+    }
+
+    fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+        headers
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.trim().parse::<T>().ok())
+    }
+}
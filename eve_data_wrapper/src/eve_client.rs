@@ -1,54 +1,118 @@
-use crate::{Character, CharacterId, CorporationId, EveConnectError};
+use crate::{Character, CharacterId, CorporationId, Downtime, EsiError, EveConnectError, ServerStatus};
 
-use reqwest::{Client, Response, StatusCode};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
-use url::Url;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// This struct contains all functions for communicating with the Eve Online
 /// REST API.
+///
+/// The url of the API can be overwritten with the environment variable
+/// `EVE_API_URL`, for example to use a mock server.
 #[derive(Clone, Debug)]
-pub struct EveClient(Client);
+pub struct EveClient {
+    client:       Client,
+    api_url:      String,
+    /// Unix timestamp in milliseconds of the last successful request, 0 if
+    /// there was none yet
+    last_success: Arc<AtomicU64>,
+    /// Set if the last request of the server status failed or the server is
+    /// in VIP mode, no other requests are send until it is reset
+    offline:      Arc<AtomicBool>,
+}
 
 impl EveClient {
-    const EVE_API_URL:    &'static str = "https://esi.evetech.net/latest";
-    const EVE_LOGIN_URL:  &'static str = "https://login.eveonline.com/v2/oauth/authorize";
-    const EVE_TOKEN_URL:  &'static str = "https://login.eveonline.com/v2/oauth/token";
-    const ENV_REDIRECT:   &'static str = "EVE_REDIRECT_URL";
-    const ENV_CLIENT_ID:  &'static str = "EVE_CLIENT_ID";
-    const ENV_SECRET_KEY: &'static str = "EVE_SECRET_KEY";
+    const EVE_API_URL:       &'static str = "https://esi.evetech.net/latest";
+    pub const EVE_LOGIN_URL: &'static str = "https://login.eveonline.com/v2/oauth/authorize";
+    const EVE_TOKEN_URL:     &'static str = "https://login.eveonline.com/v2/oauth/token";
+    const EVE_REVOKE_URL:    &'static str = "https://login.eveonline.com/v2/oauth/revoke";
+    const ENV_CLIENT_ID:     &'static str = "EVE_CLIENT_ID";
+    const ENV_SECRET_KEY:    &'static str = "EVE_SECRET_KEY";
+    const ENV_API_URL:       &'static str = "EVE_API_URL";
+
+    /// Maximum number of retries for transient errors
+    const MAX_RETRIES:      u32 = 3;
+    /// Wait time before the first retry, doubled on every retry
+    const RETRY_BACKOFF_MS: u64 = 500;
 
     pub fn new() -> Result<Self, EveConnectError> {
+        let api_url = std::env::var(Self::ENV_API_URL)
+            .unwrap_or_else(|_| Self::EVE_API_URL.into());
+        Self::with_url(api_url)
+    }
+
+    /// Creates a new client that sends all API requests to the given url
+    /// instead of ESI
+    pub fn with_url<S: Into<String>>(api_url: S) -> Result<Self, EveConnectError> {
         let client = Client::builder()
             .user_agent("github.com/lholznagel")
             .build()?;
 
-        Ok(Self(client))
+        Ok(Self {
+            client,
+            api_url:      api_url.into().trim_end_matches('/').into(),
+            last_success: Arc::new(AtomicU64::new(0)),
+            offline:      Arc::new(AtomicBool::new(false)),
+        })
     }
 
-    pub fn eve_auth_uri(state: &str) -> Result<Url, EveConnectError> {
-        let mut url = Url::parse(Self::EVE_LOGIN_URL).unwrap();
+    /// Unix timestamp in milliseconds of the last request that was answered
+    /// by ESI, [None] if no request succeeded yet
+    pub fn last_success(&self) -> Option<u64> {
+        match self.last_success.load(Ordering::Relaxed) {
+            0 => None,
+            x => Some(x),
+        }
+    }
 
-        let client_id    = std::env::var(Self::ENV_CLIENT_ID)
-            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_CLIENT_ID)))?;
-        let redirect_uri = std::env::var(Self::ENV_REDIRECT)
-            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_REDIRECT)))?;
-
-        url.query_pairs_mut()
-            .append_pair("response_type", "code")
-            .append_pair("redirect_uri", &redirect_uri)
-            .append_pair("client_id", &client_id)
-            .append_pair("scope", &scope())
-            .append_pair("state", state);
-        Ok(url)
+    /// Checks if requests can be send to ESI, false during the downtime or
+    /// if the last request of the server status failed
+    pub fn is_online(&self) -> bool {
+        !self.offline.load(Ordering::Relaxed) && !Downtime::is_now()
+    }
+
+    /// Requests the status of the eve server and remembers if it is online.
+    ///
+    /// Unlike all other requests, the status is requested even if the
+    /// server was offline before, so that it is noticed when it is back.
+    pub async fn fetch_status(&self) -> Result<ServerStatus, EveConnectError> {
+        let url = format!("{}/status/", self.api_url);
+        let result = match self.client.get(&url).send().await {
+            Ok(x) if x.status() == StatusCode::OK => x
+                .json::<ServerStatus>()
+                .await
+                .map_err(EveConnectError::ReqwestError),
+            Ok(x)  => Err(EveConnectError::EsiError(EsiError::from_response(x).await)),
+            Err(e) => Err(EveConnectError::ReqwestError(e)),
+        };
+
+        let online = result
+            .as_ref()
+            .map(|x| !x.is_vip())
+            .unwrap_or_default();
+        self.offline.store(!online, Ordering::Relaxed);
+        result
+    }
+
+    /// Id of the application that is registered at the eve auth server
+    pub fn client_id() -> Result<String, EveConnectError> {
+        Self::credentials().map(|(client_id, _)| client_id)
     }
 
     // https://docs.esi.evetech.net/docs/sso/web_based_sso_flow.html
-    pub async fn retrieve_authorization_token(code: &str) -> Result<EveOAuthUser, EveConnectError> {
+    // https://docs.esi.evetech.net/docs/sso/sso_authorization_flow.html#pkce
+    pub async fn retrieve_authorization_token(
+        code:          &str,
+        code_verifier: &str,
+    ) -> Result<EveOAuthUser, EveConnectError> {
         let mut map = HashMap::new();
         map.insert("grant_type", "authorization_code");
         map.insert("code", code);
+        map.insert("code_verifier", code_verifier);
 
         let result = Self::send(map).await?;
         EveOAuthUser::from(result).await
@@ -64,11 +128,29 @@ impl EveClient {
         EveOAuthUser::from(result).await
     }
 
+    /// Revokes a refresh token, afterwards it can no longer be used to
+    /// request access tokens
+    // https://docs.esi.evetech.net/docs/sso/revoking_refresh_tokens.html
+    pub async fn revoke_refresh_token(refresh_token: &str) -> Result<(), EveConnectError> {
+        let mut map = HashMap::new();
+        map.insert("token_type_hint", "refresh_token");
+        map.insert("token", refresh_token);
+
+        let (client_id, secret_key) = Self::credentials()?;
+        Client::new()
+            .post(Self::EVE_REVOKE_URL)
+            .basic_auth(client_id, Some(secret_key))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Host", "login.eveonline.com")
+            .form(&map)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
     async fn send<T: Serialize>(form: T) -> Result<EveOAuthToken, EveConnectError> {
-        let client_id = std::env::var(Self::ENV_CLIENT_ID)
-            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_CLIENT_ID)))?;
-        let secret_key = std::env::var(Self::ENV_SECRET_KEY)
-            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_SECRET_KEY)))?;
+        let (client_id, secret_key) = Self::credentials()?;
 
         Client::new()
             .post(Self::EVE_TOKEN_URL)
@@ -83,39 +165,56 @@ impl EveClient {
             .map_err(Into::into)
     }
 
+    /// Client id and secret key of the application
+    fn credentials() -> Result<(String, String), EveConnectError> {
+        let client_id = std::env::var(Self::ENV_CLIENT_ID)
+            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_CLIENT_ID)))?;
+        let secret_key = std::env::var(Self::ENV_SECRET_KEY)
+            .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_SECRET_KEY)))?;
+        Ok((client_id, secret_key))
+    }
+
     /// Wraps reqwest´s client
-    /// When requesting the eve online API often the server returns 502 or 503
-    /// this results in a broken payload. If that happens, we just retry the request.
-    /// The function will try 3 times, after that it will return an error.
+    /// When requesting the eve online API often the server returns 502, 503
+    /// or 504. If that happens, we retry the request with a backoff.
     pub(crate) async fn fetch(&self, path: &str) -> Result<Response, EveConnectError> {
-        let mut retry_counter = 0usize;
+        let url = format!("{}/{}", self.api_url, path);
+        self.request(&url, || self.client.get(&url)).await
+    }
 
-        loop {
-            let url = format!("{}/{}", Self::EVE_API_URL, path);
-            if retry_counter == 3 {
-                log::error!("Too many retries requesting {}.", url);
-                return Err(EveConnectError::TooManyRetries(url));
-            }
+    /// Requests a public route and returns the body together with the
+    /// caching headers, for routes that are passed through unchanged
+    ///
+    /// # Params
+    ///
+    /// `path` -> Path of the route including the query, without the api url
+    ///
+    pub async fn fetch_raw(&self, path: &str) -> Result<RawResponse, EveConnectError> {
+        let response = self.fetch(path).await?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(String::from)
+        };
 
-            let response = self.0
-                .get(&url)
-                .send()
-                .await;
-            let response = response.map_err(EveConnectError::ReqwestError)?;
-
-            // status 200 and 404 are ok
-            if response.status() != StatusCode::OK &&
-               response.status() != StatusCode::NOT_FOUND {
-                retry_counter += 1;
-                log::error!(
-                    "Fetch resulted in non 200 or 404 status code. Statuscode was {}. Retrying.",
-                    response.status()
-                );
-                continue;
-            }
+        let expires = header("expires");
+        let etag = header("etag");
+        let pages = self.page_count(&response);
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .map_err(EveConnectError::ReqwestError)?;
 
-            return Ok(response);
-        }
+        Ok(RawResponse {
+            status,
+            body,
+            expires,
+            etag,
+            pages,
+        })
     }
 
     pub(crate) async fn fetch_oauth(
@@ -123,40 +222,8 @@ impl EveClient {
         token: &str,
         path: &str
     ) -> Result<Response, EveConnectError> {
-        let mut retry_counter = 0usize;
-
-        loop {
-            let url = format!("{}/{}", Self::EVE_API_URL, path);
-            if retry_counter == 3 {
-                log::error!("Too many retries requesting {}.", url);
-                return Err(EveConnectError::TooManyRetries(url));
-            }
-
-            let response = self.0
-                .get(&url)
-                .bearer_auth(token)
-                .send()
-                .await;
-            let response = response.map_err(EveConnectError::ReqwestError)?;
-
-            if response.status() == StatusCode::UNAUTHORIZED ||
-               response.status() == StatusCode::FORBIDDEN {
-                return Err(EveConnectError::Unauthorized);
-            }
-
-            // status 200 and 404 are ok
-            if response.status() != StatusCode::OK &&
-               response.status() != StatusCode::NOT_FOUND {
-                retry_counter += 1;
-                log::error!(
-                    "Fetch resulted in non 200 or 404 status code. Statuscode was {}. Retrying.",
-                    response.status()
-                );
-                continue;
-            }
-
-            return Ok(response);
-        }
+        let url = format!("{}/{}", self.api_url, path);
+        self.request(&url, || self.client.get(&url).bearer_auth(token)).await
     }
 
     pub(crate) async fn fetch_page<T: DeserializeOwned>(
@@ -241,43 +308,111 @@ impl EveClient {
         R: serde::de::DeserializeOwned {
 
         dbg!(&serde_json::to_string(&body));
-        let mut retry_counter = 0usize;
+        let url = format!("{}/{}", self.api_url, path);
+        self
+            .request(&url, || self.client.post(&url).json(body).bearer_auth(token))
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
 
-        loop {
-            let url = format!("{}/{}", Self::EVE_API_URL, path);
-            if retry_counter == 3 {
-                log::error!("Too many retries requesting {}.", url);
-                return Err(EveConnectError::TooManyRetries(url));
-            }
+    pub(crate) async fn post<T, R>(
+        &self,
+        path: &str,
+        body: &T
+    ) -> Result<R, EveConnectError>
+    where
+        T: serde::Serialize,
+        R: serde::de::DeserializeOwned {
+
+        let url = format!("{}/{}", self.api_url, path);
+        self
+            .request(&url, || self.client.post(&url).json(body))
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Sends the request build by `request`.
+    ///
+    /// Status 200 and 404 are ok. If ESI responds with 502, 503 or 504 the
+    /// request is send again after a jittered backoff or the time ESI asked
+    /// us to wait. All other status codes are returned as
+    /// [EveConnectError::EsiError].
+    ///
+    /// # Parameters
+    ///
+    /// * `url`     - Requested url, used for logging
+    /// * `request` - Builds the request, called for every try
+    ///
+    async fn request<F>(
+        &self,
+        url:     &str,
+        request: F,
+    ) -> Result<Response, EveConnectError>
+        where F: Fn() -> RequestBuilder {
+
+        if self.offline.load(Ordering::Relaxed) {
+            return Err(EveConnectError::EveOffline);
+        }
+
+        let mut retry_counter = 0u32;
 
-            let response = self.0
-                .post(&url)
-                .json(body)
-                .bearer_auth(token)
+        loop {
+            let response = request()
                 .send()
-                .await;
-            let response = response.map_err(EveConnectError::ReqwestError)?;
+                .await
+                .map_err(EveConnectError::ReqwestError)?;
 
-            if response.status() == StatusCode::UNAUTHORIZED ||
-               response.status() == StatusCode::FORBIDDEN {
+            let status = response.status();
+            if status == StatusCode::OK || status == StatusCode::NOT_FOUND {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|x| x.as_millis() as u64)
+                    .unwrap_or_default();
+                self.last_success.store(now, Ordering::Relaxed);
+                return Ok(response);
+            }
+            if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
                 return Err(EveConnectError::Unauthorized);
             }
 
-            // status 200 and 404 are ok
-            if response.status() != StatusCode::OK &&
-               response.status() != StatusCode::NOT_FOUND {
-                retry_counter += 1;
-                log::error!(
-                    "Fetch resulted in non 200 or 404 status code. Statuscode was {}. Retrying.",
-                    response.status()
-                );
-                continue;
-           } else {
-               return response.json().await.map_err(Into::into);
-           }
+            let error = EsiError::from_response(response).await;
+            if error.is_transient() && Downtime::is_now() {
+                log::warn!("Requesting {} failed during the downtime", url);
+                return Err(EveConnectError::EveOffline);
+            }
+            if !error.is_transient() || retry_counter == Self::MAX_RETRIES {
+                log::error!("Requesting {} failed. {:?}", url, error);
+                return Err(EveConnectError::EsiError(error));
+            }
+
+            let wait = error
+                .retry_after
+                .unwrap_or_else(|| Self::backoff(retry_counter));
+            retry_counter += 1;
+            log::warn!(
+                "Requesting {} resulted in status code {}. Retrying in {}ms.",
+                url,
+                error.status,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
         }
     }
 
+    /// Exponential backoff with a random jitter of up to the same length
+    fn backoff(retry_counter: u32) -> Duration {
+        let base = Self::RETRY_BACKOFF_MS * 2u64.pow(retry_counter);
+        let jitter = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.subsec_nanos() as u64 % base)
+            .unwrap_or_default();
+        Duration::from_millis(base + jitter)
+    }
+
     fn page_count(&self, response: &Response) -> u8 {
         let headers = response.headers();
         if let Some(x) = headers.get("x-pages") {
@@ -291,34 +426,60 @@ impl EveClient {
     }
 }
 
-fn scope() -> String {
-    vec![
-        "publicData",
-        "esi-assets.read_assets.v1",
-        "esi-characters.read_agents_research.v1",
-        "esi-characters.read_blueprints.v1",
-        "esi-characterstats.read.v1",
-        "esi-fittings.read_fittings.v1",
-        "esi-fittings.write_fittings.v1",
-        "esi-industry.read_character_jobs.v1",
-        "esi-industry.read_corporation_jobs.v1",
-        "esi-industry.read_character_mining.v1",
-        "esi-markets.read_character_orders.v1",
-        "esi-markets.structure_markets.v1",
-        "esi-planets.manage_planets.v1",
-        "esi-search.search_structures.v1",
-        "esi-skills.read_skillqueue.v1",
-        "esi-skills.read_skills.v1",
-        "esi-universe.read_structures.v1",
-        "esi-wallet.read_character_wallet.v1",
-    ]
-    .join(" ")
+/// Unparsed response of ESI
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    /// Either 200 or 404, all other status codes are errors
+    pub status:  u16,
+    pub body:    String,
+    /// Value of the `expires` header
+    pub expires: Option<String>,
+    pub etag:    Option<String>,
+    /// Value of the `x-pages` header, 0 if the route has no pages
+    pub pages:   u8,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct EveOAuthPayload {
     pub sub: String,
     pub name: String,
+    /// Unix timestamp in seconds when the access token expires
+    #[serde(default)]
+    pub exp: u64,
+    /// Scopes the character granted
+    #[serde(default, deserialize_with = "deserialize_scopes")]
+    pub scp: Vec<String>,
+}
+
+impl EveOAuthPayload {
+    /// Decodes the payload of an access token, the signature is not
+    /// validated
+    pub fn decode(access_token: &str) -> Result<Self, EveConnectError> {
+        let payload = access_token.split('.').collect::<Vec<_>>();
+        let payload = payload.get(1).copied().unwrap_or_default();
+        let decoded = base64::decode(payload)
+            .map_err(|_| EveConnectError::OAuthPayload("Failed to decode base64".into()))?;
+        serde_json::from_slice(&decoded).map_err(Into::into)
+    }
+}
+
+/// The eve auth server sends a single scope as string and multiple scopes
+/// as array
+fn deserialize_scopes<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scopes {
+        Single(String),
+        Multiple(Vec<String>),
+    }
+
+    match Scopes::deserialize(deserializer)? {
+        Scopes::Single(x)   => Ok(vec![x]),
+        Scopes::Multiple(x) => Ok(x),
+    }
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -331,12 +492,7 @@ pub struct EveOAuthToken {
 
 impl EveOAuthToken {
     pub fn payload(&self) -> Result<EveOAuthPayload, EveConnectError> {
-        let payload = self.access_token.to_string();
-        let payload = payload.split('.').collect::<Vec<_>>();
-        let payload = payload.get(1).copied().unwrap_or_default();
-        let decoded = base64::decode(payload)
-            .map_err(|_| EveConnectError::OAuthPayload("Failed to decode base64".into()))?;
-        serde_json::from_slice(&decoded).map_err(Into::into)
+        EveOAuthPayload::decode(&self.access_token)
     }
 }
 
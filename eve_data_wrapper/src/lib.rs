@@ -15,56 +15,41 @@
 mod eve_client;
 mod error;
 mod macros;
+#[cfg(feature = "test_support")]
+mod mock;
+mod sde;
 mod service;
+mod single_flight;
+mod status;
 
 pub use self::eve_client::*;
 pub use self::error::*;
+#[cfg(feature = "test_support")]
+pub use self::mock::*;
 pub use self::service::*;
+pub use self::status::*;
 
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
+pub(crate) use self::sde::*;
+pub(crate) use self::single_flight::*;
+
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
-use std::{collections::HashMap, io::Read};
-use std::{fs, io::Cursor};
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use zip::ZipArchive;
 
 pub use url::Url;
 
-/// Type alias for `ZipArchive<Cursor<Vec<u8>>>`
-pub(crate) type SdeZipArchive = ZipArchive<Cursor<Vec<u8>>>;
-
-/// Takes a path and a zip file and parses the file content into a defined
-/// structure.
-///
-/// # Parameters
-///
-/// * `T`    - Type the file should be parsed to (in most cases rust figures
-///            out the type)
-/// * `path` - Path in the zip file for the file to parse
-/// * `zip`  - Zip file that contains the file
-///
-/// # Returns
-///
-/// Parsed yaml version of the file, based on the generic parameter `T`
-///
-pub(crate) fn parse_zip_file<T>(
-    path: &str,
-    zip: &mut SdeZipArchive
-) -> Result<T, EveConnectError>
-    where T: DeserializeOwned {
-
-    let mut file = zip.by_name(path)?;
-    let mut buf = Vec::with_capacity(file.size() as usize);
-    file.read_to_end(&mut buf)?;
-    serde_yaml::from_slice(&buf).map_err(Into::into)
-}
-
 #[derive(Clone)]
 pub struct EveDataWrapper {
     /// Client for communicating with eve
     eve_client: EveClient,
 
+    /// Identical raw requests that run at the same time are only send once
+    raw:        SingleFlight<RawResponse>,
+
     /// Stores all services that are managed by this lib
     services:   Arc<RwLock<HashMap<ServiceGroupName, ServiceGroup>>>,
 
@@ -75,53 +60,181 @@ pub struct EveDataWrapper {
 impl EveDataWrapper {
     const ZIP_URL:  &'static str = "https://eve-static-data-export.s3-eu-west-1.amazonaws.com/tranquility/sde.zip";
     const ZIP_PATH: &'static str = "./sde.zip";
+    /// Skips SDE files that cannot be parsed instead of failing, see
+    /// [EveDataWrapper::skipped_sde_files]
+    const ENV_LENIENT: &'static str = "SDE_LENIENT";
+    /// Number of threads used for parsing the SDE, parsing in parallel is
+    /// faster but needs more memory
+    const ENV_THREADS: &'static str = "SDE_THREADS";
+    /// Time between two requests of the server status
+    const STATUS_INTERVAL: Duration = Duration::from_secs(60);
 
     /// Creates a new service loader instance.
     ///
     /// Downloads the zip archive from eve.
     pub async fn new() -> Result<Self, EveConnectError> {
+        Self::with_client(EveClient::new()?).await
+    }
+
+    /// Creates a new service loader instance that uses the given client for
+    /// all requests.
+    ///
+    /// Downloads the zip archive from eve.
+    pub async fn with_client(eve_client: EveClient) -> Result<Self, EveConnectError> {
         let zip = if Path::new(Self::ZIP_PATH).exists() {
-            fs::read("./sde.zip").map(Cursor::new)?
+            fs::read("./sde.zip")?
         } else {
             Self::download_zip().await?
         };
 
         let x = Self {
-            eve_client: EveClient::new()?,
+            eve_client,
+            raw:        SingleFlight::new(),
             services:   Arc::new(RwLock::new(HashMap::new())),
-            zip:        ZipArchive::new(zip)?,
+            zip:        SdeZipArchive::new(zip, Self::lenient(), Self::threads())?,
         };
 
         Ok(x)
     }
 
-    async fn download_zip() -> Result<Cursor<Vec<u8>>, EveConnectError> {
+    /// Creates an instance that sends all requests to the given mock server.
+    ///
+    /// The SDE is replaced with an empty archive, so only services that do
+    /// not read the SDE can be used.
+    #[cfg(feature = "test_support")]
+    pub fn mock(esi: &MockEsi) -> Result<Self, EveConnectError> {
+        let zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new())).finish()?;
+
+        Ok(Self {
+            eve_client: esi.client()?,
+            raw:        SingleFlight::new(),
+            services:   Arc::new(RwLock::new(HashMap::new())),
+            zip:        SdeZipArchive::new(zip.into_inner(), false, 1)?,
+        })
+    }
+
+    /// Unix timestamp in milliseconds of the last successful ESI request,
+    /// see [EveClient::last_success]
+    pub fn last_esi_success(&self) -> Option<u64> {
+        self.eve_client.last_success()
+    }
+
+    /// Checks if requests can be send to ESI, see [EveClient::is_online]
+    pub fn is_esi_online(&self) -> bool {
+        self.eve_client.is_online()
+    }
+
+    /// Requests the status of the eve server every minute, so that no
+    /// requests are send while the server is offline.
+    ///
+    /// This function is blocking
+    pub async fn poll_esi_status(&self) {
+        let mut online = true;
+
+        loop {
+            match self.eve_client.fetch_status().await {
+                Ok(x) if x.is_vip() => {
+                    if online {
+                        log::warn!("Eve server is in VIP mode");
+                    }
+                    online = false;
+                },
+                Ok(_) => {
+                    if !online {
+                        log::info!("Eve server is online again");
+                    }
+                    online = true;
+                },
+                Err(e) => {
+                    if online {
+                        log::warn!("Eve server is offline {:?}", e);
+                    }
+                    online = false;
+                }
+            }
+
+            tokio::time::sleep(Self::STATUS_INTERVAL).await;
+        }
+    }
+
+    /// Waits until ESI can be requested again, background tasks call it
+    /// before every run so that they pause during the downtime
+    pub async fn wait_for_esi(&self) {
+        while !self.is_esi_online() {
+            let wait = Downtime::remaining(Downtime::now())
+                .unwrap_or(Self::STATUS_INTERVAL);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Requests a public ESI route without parsing the response, see
+    /// [EveClient::fetch_raw]
+    pub async fn fetch_esi_raw(&self, path: &str) -> Result<RawResponse, EveConnectError> {
+        self
+            .raw
+            .run(path.into(), self.eve_client.fetch_raw(path))
+            .await
+    }
+
+    /// All SDE files that were skipped in lenient mode, only contains the
+    /// files of services that were already loaded
+    pub fn skipped_sde_files(&self) -> Vec<SdeError> {
+        self.zip.skipped()
+    }
+
+    fn lenient() -> bool {
+        std::env::var(Self::ENV_LENIENT)
+            .map(|x| x == "1" || x.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    }
+
+    fn threads() -> usize {
+        std::env::var(Self::ENV_THREADS)
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(1)
+    }
+
+    async fn download_zip() -> Result<Vec<u8>, EveConnectError> {
         reqwest::get(Self::ZIP_URL)
             .await?
             .bytes()
             .await
             .map(|x| x.to_vec())
-            .map(Cursor::new)
             .map_err(Into::into)
     }
 
+    service_loader_gen!(agents, Agents, AgentService);
     service_loader_gen!(blueprints, Blueprints, BlueprintService);
     service_loader_gen!(categories, Categories, CategoryService);
+    service_loader_gen!(celestials, Celestials, CelestialService);
     service_loader_gen!(character, Character, CharacterService);
+    service_loader_gen!(contracts, Contracts, ContractService);
     service_loader_gen!(corporations, Corporations, CorporationService);
     service_loader_gen!(dogma, Dogmas, DogmaService);
+    service_loader_gen!(factions, Factions, FactionService);
+    service_loader_gen!(graphics, Graphics, GraphicService);
     service_loader_gen!(groups, Groups, GroupService);
+    service_loader_gen!(icons, Icons, IconService);
+    service_loader_gen!(incursions, Incursions, IncursionService);
     service_loader_gen!(industry, Industry, IndustryService);
+    service_loader_gen!(insurance, Insurance, InsuranceService);
+    service_loader_gen!(killmails, Killmails, KillmailService);
+    service_loader_gen!(loyalty, Loyalty, LoyaltyService);
     service_loader_gen!(market, Market, MarketService);
+    service_loader_gen!(market_groups, MarketGroups, MarketGroupService);
     service_loader_gen!(meta_groups, MetaGroups, MetaGroupService);
     service_loader_gen!(names, Names, NameService);
+    service_loader_gen!(npc_damage, NpcDamage, NpcDamageService);
     service_loader_gen!(planet_schematics, PlanetSchematics, PlanceSchematicService);
     service_loader_gen!(races, Races, RaceService);
     service_loader_gen!(research_agents, ResearchAgents, ResearchAgentService);
     service_loader_gen!(skins, Skins, SkinService);
+    service_loader_gen!(sovereignty, Sovereignty, SovereigntyService);
     service_loader_gen!(stations, Stations, StationService);
     service_loader_gen!(systems, Systems, SystemService);
     service_loader_gen!(types, Types, TypeService);
+    service_loader_gen!(universe_names, UniverseNames, UniverseNameService);
 
     /// Gets a specific service. If the service is not loaded yet, it will
     /// be read from the zip file and stored for later use.
@@ -142,10 +255,13 @@ impl EveDataWrapper {
 // TODO: validate if all are needed or if some can be merged
 eve_id!(ActivityId, u32);
 eve_id!(AgentId, u32);
+eve_id!(AgentTypeId, u32);
+eve_id!(AllianceId, u32);
 eve_id!(AttributeId, u32);
 eve_id!(CategoryId, u32);
 eve_id!(CharacterId, u32);
 eve_id!(ConstellationId, u32);
+eve_id!(ContractId, u32);
 eve_id!(CorporationId, u32);
 eve_id!(DisplayNameId, u32);
 eve_id!(DivisionId, u32);
@@ -157,6 +273,7 @@ eve_id!(GraphicId, u32);
 eve_id!(GroupId, u32);
 eve_id!(IconId, u32);
 eve_id!(ItemId, u64);
+eve_id!(KillmailId, u32);
 eve_id!(LocationId, u64);
 eve_id!(MarketGroupId, u32);
 eve_id!(MaterialSetId, u32);
@@ -176,6 +293,29 @@ eve_id!(SolarSystemId, u32);
 eve_id!(SoundId, u32);
 eve_id!(StarId, u32);
 eve_id!(StargateId, u32);
-eve_id!(StationId, u32);
+eve_id!(StationId, u64);
+eve_id!(StructureId, u64);
+eve_id!(TransactionId, u64);
 eve_id!(TypeId, u32);
 eve_id!(UnitId, u32);
+
+/// NPC stations and Upwell structures are both locations, ESI returns both
+/// in fields like `location_id`
+impl From<StationId> for LocationId {
+    fn from(x: StationId) -> Self {
+        Self(*x)
+    }
+}
+
+impl From<StructureId> for LocationId {
+    fn from(x: StructureId) -> Self {
+        Self(*x)
+    }
+}
+
+/// Types and items share the ids of the name cache
+impl From<TypeId> for ItemId {
+    fn from(x: TypeId) -> Self {
+        Self(*x as u64)
+    }
+}
@@ -64,6 +64,7 @@ macro_rules! eve_id {
             PartialOrd, Ord,
             Deserialize, Serialize,
          )]
+        #[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
         #[serde(transparent)]
         pub struct $name(pub $typ);
 
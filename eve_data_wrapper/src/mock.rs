@@ -0,0 +1,219 @@
+//! Local HTTP server that answers like ESI, so that services can be tested
+//! without tokens or network access.
+//!
+//! Only available with the feature `test_support`.
+//!
+//! ```ignore
+//! let esi = MockEsi::start().await?;
+//! let eve = EveDataWrapper::mock(&esi)?;
+//!
+//! let assets = eve.character().await?.assets("", 1.into()).await?;
+//! assert_eq!(esi.hits("characters/*/assets"), 1);
+//! ```
+use crate::{EveClient, EveConnectError};
+
+use std::sync::{Arc, RwLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Canned ESI responses, `*` matches a single path segment
+const FIXTURES: &[(&str, &str)] = &[
+    ("characters/*",                   include_str!("../fixtures/esi/character.json")),
+    ("characters/*/assets",            include_str!("../fixtures/esi/assets.json")),
+    ("characters/*/blueprints",        include_str!("../fixtures/esi/blueprints.json")),
+    ("characters/*/industry/jobs",     include_str!("../fixtures/esi/industry_jobs.json")),
+    ("characters/*/wallet",            include_str!("../fixtures/esi/wallet.json")),
+    ("corporations/*/assets",          include_str!("../fixtures/esi/assets.json")),
+    ("corporations/*/blueprints",      include_str!("../fixtures/esi/blueprints.json")),
+    ("corporations/*/industry/jobs",   include_str!("../fixtures/esi/industry_jobs.json")),
+    ("markets/*/orders",               include_str!("../fixtures/esi/market_orders.json")),
+    ("markets/prices",                 include_str!("../fixtures/esi/market_prices.json")),
+    ("status",                         include_str!("../fixtures/esi/status.json")),
+];
+
+/// Mock of the ESI API, the server is stopped when the instance is dropped.
+///
+/// Unknown paths are answered with 404.
+pub struct MockEsi {
+    url:    String,
+    routes: Arc<RwLock<Vec<MockRoute>>>,
+    server: JoinHandle<()>,
+}
+
+impl MockEsi {
+    /// Starts the server on a random local port with the default fixtures
+    pub async fn start() -> Result<Self, EveConnectError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let routes = FIXTURES
+            .iter()
+            .map(|(pattern, body)| MockRoute::new(pattern, 200, body))
+            .collect::<Vec<_>>();
+        let routes = Arc::new(RwLock::new(routes));
+
+        let server_routes = routes.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(Self::handle(stream, server_routes.clone()));
+            }
+        });
+
+        Ok(Self {
+            url,
+            routes,
+            server,
+        })
+    }
+
+    /// Url of the server
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Client that sends all requests to this server
+    pub fn client(&self) -> Result<EveClient, EveConnectError> {
+        EveClient::with_url(&self.url)
+    }
+
+    /// Answers all requests matching the pattern with the given status and
+    /// body, overwrites existing responses for the same pattern
+    ///
+    /// # Parameters
+    ///
+    /// * `pattern` - Path without leading slash, `*` matches a single segment
+    /// * `status`  - Http status code of the response
+    /// * `body`    - Json body of the response
+    ///
+    pub fn route(&self, pattern: &str, status: u16, body: &str) {
+        let route = MockRoute::new(pattern, status, body);
+        let mut routes = self.routes.write().unwrap();
+        routes.retain(|x| x.pattern != route.pattern);
+        routes.push(route);
+    }
+
+    /// Number of requests that were answered by the given pattern
+    pub fn hits(&self, pattern: &str) -> usize {
+        self
+            .routes
+            .read()
+            .unwrap()
+            .iter()
+            .find(|x| x.pattern == pattern.trim_matches('/'))
+            .map(|x| x.hits)
+            .unwrap_or_default()
+    }
+
+    async fn handle(mut stream: TcpStream, routes: Arc<RwLock<Vec<MockRoute>>>) {
+        let path = match Self::read_path(&mut stream).await {
+            Some(x) => x,
+            None    => return,
+        };
+
+        let (status, body) = {
+            let mut routes = routes.write().unwrap();
+            match routes.iter_mut().find(|x| x.matches(&path)) {
+                Some(x) => {
+                    x.hits += 1;
+                    (x.status, x.body.clone())
+                },
+                None    => (404, r#"{"error":"Not found"}"#.into()),
+            }
+        };
+
+        let response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-Pages: 1\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+
+    /// Reads the request and returns its path without query and slashes at
+    /// the start and end
+    async fn read_path(stream: &mut TcpStream) -> Option<String> {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+
+        let header_end = loop {
+            let read = stream.read(&mut buf).await.ok()?;
+            if read == 0 {
+                return None;
+            }
+            request.extend_from_slice(&buf[..read]);
+
+            if let Some(x) = request.windows(4).position(|x| x == b"\r\n\r\n") {
+                break x + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+        let content_length = head
+            .lines()
+            .filter_map(|x| x.split_once(':'))
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+            .unwrap_or_default();
+
+        // the body is not needed, but must be read before answering
+        let mut body_read = request.len() - header_end;
+        while body_read < content_length {
+            let read = stream.read(&mut buf).await.ok()?;
+            if read == 0 {
+                break;
+            }
+            body_read += read;
+        }
+
+        let path = head
+            .lines()
+            .next()?
+            .split_whitespace()
+            .nth(1)?;
+        let path = path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .trim_matches('/')
+            .to_string();
+        Some(path)
+    }
+}
+
+impl Drop for MockEsi {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+struct MockRoute {
+    pattern: String,
+    status:  u16,
+    body:    String,
+    hits:    usize,
+}
+
+impl MockRoute {
+    fn new(pattern: &str, status: u16, body: &str) -> Self {
+        Self {
+            pattern: pattern.trim_matches('/').into(),
+            status,
+            body:    body.into(),
+            hits:    0,
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let pattern = self.pattern.split('/').collect::<Vec<_>>();
+        let path = path.split('/').collect::<Vec<_>>();
+
+        pattern.len() == path.len() &&
+        pattern
+            .iter()
+            .zip(path)
+            .all(|(p, x)| *p == "*" || *p == x)
+    }
+}
@@ -0,0 +1,365 @@
+//! Reading and parsing of the files in the SDE zip.
+//!
+//! By default every file is parsed after the other. With more than one
+//! thread, services start parsing all their files at once, see
+//! [SdeZipArchive::collection] and [SdeZipArchive::files]. Every thread
+//! works on its own copy of the archive, the zip itself is shared.
+
+use crate::{EveConnectError, SdeError};
+
+use serde::de::DeserializeOwned;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use zip::ZipArchive;
+use zip::read::ZipFile;
+
+/// Size in bytes after which the collected yaml is parsed
+const COLLECTION_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// SDE zip archive together with the files that could not be parsed.
+///
+/// In lenient mode files that cannot be parsed are skipped and reported,
+/// otherwise the first error is returned.
+#[derive(Clone)]
+pub(crate) struct SdeZipArchive {
+    zip:     ZipArchive<Cursor<Arc<[u8]>>>,
+    /// Number of threads used for parsing, 1 parses everything on the
+    /// calling thread
+    threads: usize,
+    report:  SdeReport,
+}
+
+impl SdeZipArchive {
+    pub(crate) fn new(
+        zip:     Vec<u8>,
+        lenient: bool,
+        threads: usize,
+    ) -> Result<Self, EveConnectError> {
+        let zip: Arc<[u8]> = zip.into();
+        Ok(Self {
+            zip:     ZipArchive::new(Cursor::new(zip))?,
+            threads: threads.max(1),
+            report:  SdeReport {
+                lenient,
+                skipped: Arc::new(Mutex::new(Vec::new())),
+            },
+        })
+    }
+
+    pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.zip.file_names()
+    }
+
+    /// All files that were skipped in lenient mode
+    pub(crate) fn skipped(&self) -> Vec<SdeError> {
+        self
+            .report
+            .skipped
+            .lock()
+            .map(|x| x.clone())
+            .unwrap_or_default()
+    }
+
+    /// Starts parsing a file that contains a map or a list, in parallel
+    /// mode on its own thread.
+    ///
+    /// # Parameters
+    ///
+    /// * `T`    - Collection the file should be parsed to
+    /// * `path` - Path in the zip file for the file to parse
+    ///
+    /// # Returns
+    ///
+    /// Task that returns the parsed file when it is joined
+    ///
+    pub(crate) fn collection<T>(&self, path: &str) -> SdeTask<T>
+        where
+            T: DeserializeOwned + Default + IntoIterator + Extend<<T as IntoIterator>::Item> + Send + 'static {
+
+        let path = path.to_string();
+        let mut zip = self.clone();
+
+        let task = if self.threads > 1 {
+            Task::Running(path.clone(), thread::spawn(move || read_zip_collection(&path, &mut zip)))
+        } else {
+            Task::Done(read_zip_collection(&path, &mut zip))
+        };
+
+        SdeTask {
+            report: self.report.clone(),
+            task,
+        }
+    }
+
+    /// Parses all given files, in parallel mode the files are split
+    /// between all threads.
+    ///
+    /// # Parameters
+    ///
+    /// * `T`     - Type every file should be parsed to
+    /// * `paths` - Paths in the zip file for the files to parse
+    ///
+    /// # Returns
+    ///
+    /// All parsed files in the same order as the paths, in lenient mode
+    /// files that could not be parsed are missing
+    ///
+    pub(crate) fn files<T>(&self, paths: Vec<String>) -> Result<Vec<T>, EveConnectError>
+        where
+            T: DeserializeOwned + Send + 'static {
+
+        self
+            .files_with_path(paths)
+            .map(|x| x.into_iter().map(|(_, x)| x).collect::<Vec<_>>())
+    }
+
+    /// Same as [SdeZipArchive::files] but every file is returned together
+    /// with its path, for files where the path carries information, like the
+    /// directory structure of `universe`.
+    pub(crate) fn files_with_path<T>(
+        &self,
+        paths: Vec<String>
+    ) -> Result<Vec<(String, T)>, EveConnectError>
+        where
+            T: DeserializeOwned + Send + 'static {
+
+        let chunk_size = (paths.len() / self.threads).max(1) + 1;
+        let tasks = paths
+            .chunks(chunk_size)
+            .map(|paths| {
+                let first = paths[0].clone();
+                let paths = paths.to_vec();
+                let mut zip = self.clone();
+                let parse = move || {
+                    paths
+                        .into_iter()
+                        .map(|x| {
+                            let file = read_zip_file::<T>(&x, &mut zip);
+                            file.map(|file| (x, file))
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                if self.threads > 1 {
+                    Task::Running(first, thread::spawn(move || Ok(parse())))
+                } else {
+                    Task::Done(Ok(parse()))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = Vec::with_capacity(paths.len());
+        for task in tasks {
+            let files = match task.join() {
+                Ok(x)  => x,
+                // only happens if the thread panicked
+                Err(e) => {
+                    self.report.skip::<()>(Err(e))?;
+                    continue;
+                }
+            };
+            for file in files {
+                if let Some(x) = self.report.skip(file)? {
+                    result.push(x);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn by_name(&mut self, path: &str) -> Result<ZipFile, SdeError> {
+        self
+            .zip
+            .by_name(path)
+            .map_err(|e| SdeError::CorruptEntry {
+                path:    path.into(),
+                message: e.to_string(),
+            })
+    }
+}
+
+/// Mode of the parser and all files that were skipped
+#[derive(Clone)]
+struct SdeReport {
+    lenient: bool,
+    skipped: Arc<Mutex<Vec<SdeError>>>,
+}
+
+impl SdeReport {
+    /// Returns the value, or in lenient mode records the error and returns
+    /// `None`
+    fn skip<T>(&self, result: Result<T, SdeError>) -> Result<Option<T>, EveConnectError> {
+        match result {
+            Ok(x)                  => Ok(Some(x)),
+            Err(e) if self.lenient => {
+                log::warn!("Skipping SDE file {}", e);
+                self
+                    .skipped
+                    .lock()
+                    .map(|mut x| x.push(e))
+                    .unwrap_or_default();
+                Ok(None)
+            }
+            Err(e)                 => Err(e.into()),
+        }
+    }
+}
+
+/// File that is parsed, either already done or on its own thread
+pub(crate) struct SdeTask<T> {
+    report: SdeReport,
+    task:   Task<T>,
+}
+
+impl<T> SdeTask<T>
+    where
+        T: Default {
+
+    /// Waits until the file is parsed
+    ///
+    /// # Returns
+    ///
+    /// All entries of the file, in lenient mode no entries if the file
+    /// could not be parsed
+    ///
+    pub(crate) fn join(self) -> Result<T, EveConnectError> {
+        let result = self.task.join();
+        self.report.skip(result).map(Option::unwrap_or_default)
+    }
+}
+
+enum Task<T> {
+    Done(Result<T, SdeError>),
+    /// Path of the file and the thread that parses it
+    Running(String, JoinHandle<Result<T, SdeError>>),
+}
+
+impl<T> Task<T> {
+    fn join(self) -> Result<T, SdeError> {
+        match self {
+            Self::Done(x)          => x,
+            Self::Running(path, x) => {
+                x
+                    .join()
+                    .unwrap_or_else(|_| Err(SdeError::Inflate {
+                        path,
+                        message: "parser thread panicked".into(),
+                    }))
+            }
+        }
+    }
+}
+
+/// Takes a path and a zip file and parses the file content into a defined
+/// structure.
+///
+/// # Parameters
+///
+/// * `T`    - Type the file should be parsed to (in most cases rust figures
+///            out the type)
+/// * `path` - Path in the zip file for the file to parse
+/// * `zip`  - Zip file that contains the file
+///
+/// # Returns
+///
+/// Parsed yaml version of the file, based on the generic parameter `T`
+///
+fn read_zip_file<T>(
+    path: &str,
+    zip: &mut SdeZipArchive
+) -> Result<T, SdeError>
+    where T: DeserializeOwned {
+
+    let mut file = zip.by_name(path)?;
+    let mut buf = Vec::with_capacity(file.size() as usize);
+    file
+        .read_to_end(&mut buf)
+        .map_err(|e| SdeError::inflate(path, e))?;
+    serde_yaml::from_slice(&buf).map_err(|e| SdeError::schema(path, e, 0, 0))
+}
+
+/// Same as [read_zip_file] for files that contain a map or a list, without
+/// inflating the whole file into memory.
+///
+/// The file is inflated line by line and parsed in chunks of about
+/// [COLLECTION_CHUNK_SIZE] bytes. A chunk always ends before an entry of
+/// the root map or list, so every chunk is valid yaml on its own. Files like
+/// `typeIDs.yaml` would otherwise need several hundred MB while parsing.
+///
+/// # Parameters
+///
+/// * `T`    - Collection the file should be parsed to, for example a
+///            `HashMap` or `Vec`
+/// * `path` - Path in the zip file for the file to parse
+/// * `zip`  - Zip file that contains the file
+///
+/// # Returns
+///
+/// All entries of the file, in lenient mode no entries if the file could
+/// not be parsed
+///
+pub(crate) fn parse_zip_collection<T>(
+    path: &str,
+    zip: &mut SdeZipArchive
+) -> Result<T, EveConnectError>
+    where
+        T: DeserializeOwned + Default + IntoIterator + Extend<<T as IntoIterator>::Item> {
+
+    let result = read_zip_collection(path, zip);
+    zip.report.skip(result).map(Option::unwrap_or_default)
+}
+
+fn read_zip_collection<T>(
+    path: &str,
+    zip: &mut SdeZipArchive
+) -> Result<T, SdeError>
+    where
+        T: DeserializeOwned + Default + IntoIterator + Extend<<T as IntoIterator>::Item> {
+
+    let file = zip.by_name(path)?;
+    let reader = BufReader::new(file);
+
+    let mut result = T::default();
+    let mut chunk = String::with_capacity(COLLECTION_CHUNK_SIZE);
+    // position of the chunk in the file, for error messages
+    let mut chunk_line = 0;
+    let mut chunk_offset = 0;
+    let mut line_no = 0;
+    let mut offset = 0;
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| SdeError::inflate(path, e))?;
+        line_no += 1;
+        offset += line.len() + 1;
+
+        // document markers
+        if line == "---" || line == "..." {
+            continue;
+        }
+
+        // everything that is not indented starts a new entry of the root
+        let root = !line.is_empty() &&
+            !line.starts_with(|c: char| c.is_whitespace() || c == '#');
+        if root && chunk.len() >= COLLECTION_CHUNK_SIZE {
+            let entries = serde_yaml::from_str::<T>(&chunk)
+                .map_err(|e| SdeError::schema(path, e, chunk_line, chunk_offset))?;
+            result.extend(entries);
+            chunk.clear();
+        }
+
+        if chunk.is_empty() {
+            chunk_line = line_no - 1;
+            chunk_offset = offset - line.len() - 1;
+        }
+        chunk.push_str(&line);
+        chunk.push('\n');
+    }
+
+    if !chunk.trim().is_empty() {
+        let entries = serde_yaml::from_str::<T>(&chunk)
+            .map_err(|e| SdeError::schema(path, e, chunk_line, chunk_offset))?;
+        result.extend(entries);
+    }
+    Ok(result)
+}
@@ -1,60 +1,102 @@
+mod agent;
 mod blueprint;
 mod category_ids;
+mod celestial;
 mod character;
+mod contract;
 mod corporation;
 mod dogma;
+mod faction;
+mod graphic;
 mod group_ids;
+mod icon;
+mod incursion;
 mod industry;
+mod insurance;
+mod killmail;
+mod loyalty;
 mod market;
+mod market_group;
 mod meta_group;
 mod name;
+mod npc_damage;
 mod planet_schematic;
 mod race;
 mod research_agent;
 mod skin;
+mod sovereignty;
 mod station;
 mod system;
 mod typ;
+mod universe_name;
 
 use crate::{SdeZipArchive, error::EveConnectError, eve_client::EveClient};
 
+pub use self::agent::*;
 pub use self::blueprint::*;
 pub use self::category_ids::*;
+pub use self::celestial::*;
 pub use self::character::*;
+pub use self::contract::*;
 pub use self::corporation::*;
 pub use self::dogma::*;
+pub use self::faction::*;
+pub use self::graphic::*;
 pub use self::group_ids::*;
+pub use self::icon::*;
+pub use self::incursion::*;
 pub use self::industry::*;
+pub use self::insurance::*;
+pub use self::killmail::*;
+pub use self::loyalty::*;
 pub use self::market::*;
+pub use self::market_group::*;
 pub use self::meta_group::*;
 pub use self::name::*;
+pub use self::npc_damage::*;
 pub use self::planet_schematic::*;
 pub use self::race::*;
 pub use self::research_agent::*;
 pub use self::skin::*;
+pub use self::sovereignty::*;
 pub use self::station::*;
 pub use self::system::*;
 pub use self::typ::*;
+pub use self::universe_name::*;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum ServiceGroupName {
+    Agents,
     Blueprints,
     Categories,
+    Celestials,
     Character,
+    Contracts,
     Corporations,
     Dogmas,
+    Factions,
+    Graphics,
     Groups,
+    Icons,
+    Incursions,
     Industry,
+    Insurance,
+    Killmails,
+    Loyalty,
     Market,
+    MarketGroups,
     MetaGroups,
     Names,
+    NpcDamage,
     PlanetSchematics,
     Races,
     ResearchAgents,
     Skins,
+    Sovereignty,
     Stations,
     Systems,
     Types,
+    UniverseNames,
 }
 
 impl ServiceGroupName {
@@ -64,23 +106,37 @@ impl ServiceGroupName {
         zip: SdeZipArchive
     ) -> Result<ServiceGroup, EveConnectError> {
         let r = match self {
+            Self::Agents => ServiceGroup::Agents(AgentService::new(zip)?),
             Self::Blueprints => ServiceGroup::Blueprints(BlueprintService::new(zip)?),
             Self::Categories => ServiceGroup::Categories(CategoryService::new(zip)?),
+            Self::Celestials => ServiceGroup::Celestials(CelestialService::new(zip)?),
             Self::Character => ServiceGroup::Character(CharacterService::new(eve_client, zip)?),
+            Self::Contracts => ServiceGroup::Contracts(ContractService::new(eve_client, zip)?),
             Self::Corporations => ServiceGroup::Corporations(CorporationService::new(zip)?),
             Self::Dogmas => ServiceGroup::Dogmas(DogmaService::new(zip)?),
+            Self::Factions => ServiceGroup::Factions(FactionService::new(zip)?),
+            Self::Graphics => ServiceGroup::Graphics(GraphicService::new(zip)?),
             Self::Groups => ServiceGroup::Groups(GroupService::new(zip)?),
+            Self::Icons => ServiceGroup::Icons(IconService::new(zip)?),
+            Self::Incursions => ServiceGroup::Incursions(IncursionService::new(eve_client, zip)?),
             Self::Industry => ServiceGroup::Industry(IndustryService::new(eve_client, zip)?),
+            Self::Insurance => ServiceGroup::Insurance(InsuranceService::new(eve_client, zip)?),
+            Self::Killmails => ServiceGroup::Killmails(KillmailService::new(eve_client, zip)?),
+            Self::Loyalty => ServiceGroup::Loyalty(LoyaltyService::new(eve_client, zip)?),
             Self::Market => ServiceGroup::Market(MarketService::new(eve_client, zip)?),
+            Self::MarketGroups => ServiceGroup::MarketGroups(MarketGroupService::new(zip)?),
             Self::MetaGroups => ServiceGroup::MetaGroups(MetaGroupService::new(zip)?),
             Self::Names => ServiceGroup::Names(NameService::new(zip)?),
+            Self::NpcDamage => ServiceGroup::NpcDamage(NpcDamageService::new(zip)?),
             Self::PlanetSchematics => ServiceGroup::PlanetSchematics(PlanceSchematicService::new(zip)?),
             Self::Races => ServiceGroup::Races(RaceService::new(zip)?),
             Self::ResearchAgents => ServiceGroup::ResearchAgents(ResearchAgentService::new(zip)?),
             Self::Skins => ServiceGroup::Skins(SkinService::new(zip)?),
+            Self::Sovereignty => ServiceGroup::Sovereignty(SovereigntyService::new(eve_client, zip)?),
             Self::Stations => ServiceGroup::Stations(StationService::new(zip)?),
             Self::Systems => ServiceGroup::Systems(SystemService::new(eve_client, zip).await?),
             Self::Types => ServiceGroup::Types(TypeService::new(zip)?),
+            Self::UniverseNames => ServiceGroup::UniverseNames(UniverseNameService::new(eve_client, zip)?),
         };
         Ok(r)
     }
@@ -88,21 +144,35 @@ impl ServiceGroupName {
 
 #[derive(Clone)]
 pub enum ServiceGroup {
+    Agents(AgentService),
     Blueprints(BlueprintService),
     Categories(CategoryService),
+    Celestials(CelestialService),
     Character(CharacterService),
+    Contracts(ContractService),
     Corporations(CorporationService),
     Dogmas(DogmaService),
+    Factions(FactionService),
+    Graphics(GraphicService),
     Groups(GroupService),
+    Icons(IconService),
+    Incursions(IncursionService),
     Industry(IndustryService),
+    Insurance(InsuranceService),
+    Killmails(KillmailService),
+    Loyalty(LoyaltyService),
     Market(MarketService),
+    MarketGroups(MarketGroupService),
     MetaGroups(MetaGroupService),
     Names(NameService),
+    NpcDamage(NpcDamageService),
     PlanetSchematics(PlanceSchematicService),
     Races(RaceService),
     ResearchAgents(ResearchAgentService),
     Skins(SkinService),
+    Sovereignty(SovereigntyService),
     Stations(StationService),
     Systems(SystemService),
     Types(TypeService),
+    UniverseNames(UniverseNameService),
 }
@@ -0,0 +1,145 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Service for all NPC agents.
+///
+/// Research agents have their own [ResearchAgentService].
+#[derive(Clone, Debug)]
+pub struct AgentService {
+    agents:   HashMap<AgentId, AgentEntry>,
+    /// Agents that are located in a system instead of a station
+    in_space: HashMap<AgentId, AgentInSpaceEntry>,
+    types:    HashMap<AgentTypeId, String>,
+}
+
+impl AgentService {
+    const PATH_AGENTS:   &'static str = "sde/bsd/agtAgents.yaml";
+    const PATH_IN_SPACE: &'static str = "sde/bsd/agtAgentsInSpace.yaml";
+    const PATH_TYPES:    &'static str = "sde/bsd/agtAgentTypes.yaml";
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let agents   = zip.collection::<Vec<AgentEntry>>(Self::PATH_AGENTS);
+        let in_space = zip.collection::<Vec<AgentInSpaceEntry>>(Self::PATH_IN_SPACE);
+        let types    = zip.collection::<Vec<AgentTypeEntry>>(Self::PATH_TYPES);
+
+        Ok(Self {
+            agents:   agents
+                .join()?
+                .into_iter()
+                .map(|x| (x.agent_id, x))
+                .collect::<HashMap<_, _>>(),
+            in_space: in_space
+                .join()?
+                .into_iter()
+                .map(|x| (x.agent_id, x))
+                .collect::<HashMap<_, _>>(),
+            types:    types
+                .join()?
+                .into_iter()
+                .map(|x| (x.agent_type_id, x.agent_type))
+                .collect::<HashMap<_, _>>(),
+        })
+    }
+
+    pub fn agents(&self) -> &HashMap<AgentId, AgentEntry> {
+        &self.agents
+    }
+
+    pub fn agent<T: Into<AgentId>>(&self, aid: T) -> Option<&AgentEntry> {
+        self.agents.get(&aid.into())
+    }
+
+    /// Name of the agent type, for example `BasicAgent` or `ResearchAgent`
+    pub fn agent_type<T: Into<AgentTypeId>>(&self, tid: T) -> Option<&String> {
+        self.types.get(&tid.into())
+    }
+
+    /// Gets all agents that work for a corporation
+    pub fn agents_by_corporation<T: Into<CorporationId>>(&self, cid: T) -> Vec<&AgentEntry> {
+        let cid = cid.into();
+        self
+            .agents
+            .values()
+            .filter(|x| x.corporation_id == cid)
+            .collect::<Vec<_>>()
+    }
+
+    /// Gets the system an agent is located in.
+    ///
+    /// Most agents are located in a station, agents in space have their
+    /// system as location.
+    ///
+    /// # Parameters
+    ///
+    /// * `aid`      - Agent to get the system for
+    /// * `stations` - Service containing all stations
+    ///
+    /// # Returns
+    ///
+    /// System of the agent, [None] if the agent does not exist
+    ///
+    pub fn agent_system<T: Into<AgentId>>(
+        &self,
+        aid:      T,
+        stations: &StationService,
+    ) -> Option<SolarSystemId> {
+        let aid = aid.into();
+
+        if let Some(x) = self.in_space.get(&aid) {
+            return Some(x.solar_system_id);
+        }
+
+        let location = *self.agents.get(&aid)?.location_id;
+        stations
+            .stations()
+            .iter()
+            .find(|x| *x.station_id == location)
+            .map(|x| x.solar_system_id)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentEntry {
+    #[serde(rename = "agentID")]
+    pub agent_id:       AgentId,
+    #[serde(rename = "agentTypeID")]
+    pub agent_type_id:  AgentTypeId,
+    #[serde(rename = "corporationID")]
+    pub corporation_id: CorporationId,
+    #[serde(rename = "divisionID")]
+    pub division_id:    DivisionId,
+    #[serde(rename = "isLocator")]
+    pub is_locator:     bool,
+    #[serde(rename = "level")]
+    pub level:          u8,
+    /// Station or system the agent is located in
+    #[serde(rename = "locationID")]
+    pub location_id:    LocationId,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentInSpaceEntry {
+    #[serde(rename = "agentID")]
+    pub agent_id:        AgentId,
+    #[serde(rename = "dungeonID")]
+    pub dungeon_id:      u32,
+    #[serde(rename = "solarSystemID")]
+    pub solar_system_id: SolarSystemId,
+    #[serde(rename = "spawnPointID")]
+    pub spawn_point_id:  u32,
+    #[serde(rename = "typeID")]
+    pub type_id:         TypeId,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentTypeEntry {
+    #[serde(rename = "agentType")]
+    pub agent_type:    String,
+    #[serde(rename = "agentTypeID")]
+    pub agent_type_id: AgentTypeId,
+}
@@ -13,7 +13,7 @@ impl BlueprintService {
 
     pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            blueprints: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            blueprints: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
 
@@ -13,7 +13,7 @@ impl CategoryService {
 
     pub fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            categories: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            categories: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
 }
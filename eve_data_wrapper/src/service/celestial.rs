@@ -0,0 +1,472 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Service for the map of k-space, wormhole space and abyssal space, from
+/// regions down to every celestial in a system.
+///
+/// All data is taken from the directory trees in `universe`, the names
+/// from `invNames.yaml`.
+#[derive(Clone, Debug)]
+pub struct CelestialService {
+    regions:        HashMap<RegionId, CelestialRegion>,
+    constellations: HashMap<ConstellationId, CelestialConstellation>,
+    systems:        HashMap<SolarSystemId, CelestialSystem>,
+    celestials:     HashMap<ItemId, Celestial>,
+}
+
+impl CelestialService {
+    const PATH_UNIVERSE:      &'static str = "sde/fsd/universe/";
+    const PATH_NAMES:         &'static str = "sde/bsd/invNames.yaml";
+
+    const FILE_REGION:        &'static str = "region.staticdata";
+    const FILE_CONSTELLATION: &'static str = "constellation.staticdata";
+    const FILE_SYSTEM:        &'static str = "solarsystem.staticdata";
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let names = zip.collection::<Vec<NameEntry>>(Self::PATH_NAMES);
+
+        let mut region_files        = Vec::new();
+        let mut constellation_files = Vec::new();
+        let mut system_files        = Vec::new();
+        for file in zip.file_names() {
+            if SpaceType::from_path(file).is_none() {
+                continue;
+            }
+
+            if file.ends_with(Self::FILE_REGION) {
+                region_files.push(file.to_string());
+            } else if file.ends_with(Self::FILE_CONSTELLATION) {
+                constellation_files.push(file.to_string());
+            } else if file.ends_with(Self::FILE_SYSTEM) {
+                system_files.push(file.to_string());
+            }
+        }
+
+        let regions        = zip.files_with_path::<RegionStaticEntry>(region_files)?;
+        let constellations = zip.files_with_path::<ConstellationStaticEntry>(constellation_files)?;
+        let systems        = zip.files_with_path::<SolarsystemEntry>(system_files)?;
+
+        let names = names
+            .join()?
+            .into_iter()
+            .map(|x| (x.item_id, x.name))
+            .collect::<HashMap<_, _>>();
+
+        Ok(Self::from_entries(regions, constellations, systems, names))
+    }
+
+    pub fn regions(&self) -> &HashMap<RegionId, CelestialRegion> {
+        &self.regions
+    }
+
+    pub fn region<T: Into<RegionId>>(&self, rid: T) -> Option<&CelestialRegion> {
+        self.regions.get(&rid.into())
+    }
+
+    pub fn constellations(&self) -> &HashMap<ConstellationId, CelestialConstellation> {
+        &self.constellations
+    }
+
+    pub fn constellation<T: Into<ConstellationId>>(
+        &self,
+        cid: T
+    ) -> Option<&CelestialConstellation> {
+        self.constellations.get(&cid.into())
+    }
+
+    pub fn systems(&self) -> &HashMap<SolarSystemId, CelestialSystem> {
+        &self.systems
+    }
+
+    pub fn system<T: Into<SolarSystemId>>(&self, sid: T) -> Option<&CelestialSystem> {
+        self.systems.get(&sid.into())
+    }
+
+    pub fn celestials(&self) -> &HashMap<ItemId, Celestial> {
+        &self.celestials
+    }
+
+    pub fn celestial<T: Into<ItemId>>(&self, iid: T) -> Option<&Celestial> {
+        self.celestials.get(&iid.into())
+    }
+
+    /// Gets all celestials in a system.
+    ///
+    /// # Parameters
+    ///
+    /// * `sid` - System to get the celestials for
+    ///
+    /// # Returns
+    ///
+    /// All celestials of the system, empty if the system does not exist
+    ///
+    pub fn system_celestials<T: Into<SolarSystemId>>(&self, sid: T) -> Vec<&Celestial> {
+        self
+            .system(sid)
+            .map(|x| x
+                .celestials
+                .iter()
+                .filter_map(|x| self.celestials.get(x))
+                .collect::<Vec<_>>()
+            )
+            .unwrap_or_default()
+    }
+
+    /// Collects the names of all regions, constellations, systems and
+    /// celestials.
+    pub fn collect_names(&self) -> HashMap<ItemId, String> {
+        let regions = self
+            .regions
+            .values()
+            .map(|x| (ItemId(*x.region_id as u64), x.name.clone()));
+        let constellations = self
+            .constellations
+            .values()
+            .map(|x| (ItemId(*x.constellation_id as u64), x.name.clone()));
+        let systems = self
+            .systems
+            .values()
+            .map(|x| (ItemId(*x.solar_system_id as u64), x.name.clone()));
+        let celestials = self
+            .celestials
+            .values()
+            .map(|x| (x.item_id, x.name.clone()));
+
+        regions
+            .chain(constellations)
+            .chain(systems)
+            .chain(celestials)
+            .collect::<HashMap<_, _>>()
+    }
+
+    /// Gets all systems of the given space type.
+    ///
+    /// # Parameters
+    ///
+    /// * `space` - Type of space to get the systems for
+    ///
+    /// # Returns
+    ///
+    /// All systems in the given space
+    ///
+    pub fn systems_by_space(&self, space: SpaceType) -> Vec<&CelestialSystem> {
+        self
+            .systems
+            .values()
+            .filter(|x| x.space == space)
+            .collect::<Vec<_>>()
+    }
+
+    /// Builds the hierarchy from the parsed files.
+    ///
+    /// The files do not reference their parent, the relation is given by
+    /// the directory structure
+    /// `universe/{space}/{region}/{constellation}/{system}/`.
+    ///
+    /// The wormhole class is only set on systems that differ from their
+    /// constellation or region, so it is inherited from them.
+    fn from_entries(
+        regions:        Vec<(String, RegionStaticEntry)>,
+        constellations: Vec<(String, ConstellationStaticEntry)>,
+        systems:        Vec<(String, SolarsystemEntry)>,
+        names:          HashMap<ItemId, String>,
+    ) -> Self {
+        let name = |x: u64| names
+            .get(&ItemId(x))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut region_by_dir = HashMap::new();
+        let mut result_regions = HashMap::new();
+        for (path, region) in regions {
+            let space = match SpaceType::from_path(&path) {
+                Some(x) => x,
+                None    => continue,
+            };
+
+            region_by_dir.insert(
+                Self::parent_dir(&path, 1),
+                (region.region_id, region.wormhole_class_id)
+            );
+            result_regions.insert(region.region_id, CelestialRegion {
+                region_id:      region.region_id,
+                name:           name(*region.region_id as u64),
+                space,
+                constellations: Vec::new(),
+            });
+        }
+
+        let mut constellation_by_dir = HashMap::new();
+        let mut result_constellations = HashMap::new();
+        for (path, constellation) in constellations {
+            let region = region_by_dir.get(&Self::parent_dir(&path, 2));
+            let (region_id, region_class) = match region {
+                Some(x) => *x,
+                None    => continue,
+            };
+            let cid = constellation.constellation_id;
+            let wormhole_class = constellation.wormhole_class_id.or(region_class);
+
+            constellation_by_dir.insert(
+                Self::parent_dir(&path, 1),
+                (cid, region_id, wormhole_class)
+            );
+            result_regions
+                .entry(region_id)
+                .and_modify(|x: &mut CelestialRegion| x.constellations.push(cid));
+            result_constellations.insert(cid, CelestialConstellation {
+                constellation_id: cid,
+                region_id,
+                name:             name(*cid as u64),
+                systems:          Vec::new(),
+            });
+        }
+
+        let mut result_systems = HashMap::new();
+        let mut celestials = HashMap::new();
+        for (path, system) in systems {
+            let (cid, rid, constellation_class) = match constellation_by_dir.get(&Self::parent_dir(&path, 2)) {
+                Some(x) => *x,
+                None    => continue,
+            };
+            let sid = system.solar_system_id;
+
+            result_constellations
+                .entry(cid)
+                .and_modify(|x: &mut CelestialConstellation| x.systems.push(sid));
+
+            let mut celestial = |
+                item_id: u64,
+                kind:    CelestialKind,
+                type_id: TypeId,
+                parent:  Option<u64>
+            | {
+                celestials.insert(ItemId(item_id), Celestial {
+                    item_id:         ItemId(item_id),
+                    kind,
+                    type_id,
+                    solar_system_id: sid,
+                    parent:          parent.map(ItemId),
+                    name:            name(item_id),
+                });
+                ItemId(item_id)
+            };
+
+            let mut system_celestials = Vec::new();
+            if let Some(x) = &system.star {
+                system_celestials.push(
+                    celestial(*x.id as u64, CelestialKind::Star, x.type_id, None)
+                );
+            }
+            for (gid, gate) in system.stargates.iter() {
+                system_celestials.push(
+                    celestial(**gid as u64, CelestialKind::Stargate, gate.type_id, None)
+                );
+            }
+            for (pid, planet) in system.planets.iter() {
+                let pid = **pid as u64;
+                system_celestials.push(
+                    celestial(pid, CelestialKind::Planet, planet.type_id, None)
+                );
+
+                for (bid, belt) in planet.asteroid_belts.iter() {
+                    system_celestials.push(
+                        celestial(*bid as u64, CelestialKind::AsteroidBelt, belt.type_id, Some(pid))
+                    );
+                }
+                for (stid, station) in planet.npc_stations.iter() {
+                    system_celestials.push(
+                        celestial(**stid, CelestialKind::Station, station.type_id, Some(pid))
+                    );
+                }
+                for (mid, moon) in planet.moons.iter() {
+                    let mid = *mid as u64;
+                    system_celestials.push(
+                        celestial(mid, CelestialKind::Moon, moon.type_id, Some(pid))
+                    );
+
+                    for (stid, station) in moon.npc_stations.iter() {
+                        system_celestials.push(
+                            celestial(**stid, CelestialKind::Station, station.type_id, Some(mid))
+                        );
+                    }
+                }
+            }
+
+            result_systems.insert(sid, CelestialSystem {
+                solar_system_id:  sid,
+                constellation_id: cid,
+                region_id:        rid,
+                name:             name(*sid as u64),
+                security:         system.security,
+                space:            SpaceType::from_path(&path).unwrap_or_default(),
+                wormhole_class:   system.wormhole_class_id.or(constellation_class),
+                wormhole_effect:  system
+                    .seconday_sun
+                    .as_ref()
+                    .map(|x| x.effect_beacon_type_id),
+                celestials:       system_celestials,
+            });
+        }
+
+        Self {
+            regions:        result_regions,
+            constellations: result_constellations,
+            systems:        result_systems,
+            celestials,
+        }
+    }
+
+    /// Removes the given number of path segments from the end of the path
+    fn parent_dir(path: &str, levels: usize) -> String {
+        let mut path = path;
+        for _ in 0..levels {
+            path = path
+                .rsplit_once('/')
+                .map(|(x, _)| x)
+                .unwrap_or_default();
+        }
+        path.to_string()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CelestialRegion {
+    pub region_id:      RegionId,
+    pub name:           String,
+    pub space:          SpaceType,
+    pub constellations: Vec<ConstellationId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CelestialConstellation {
+    pub constellation_id: ConstellationId,
+    pub region_id:        RegionId,
+    pub name:             String,
+    pub systems:          Vec<SolarSystemId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CelestialSystem {
+    pub solar_system_id:  SolarSystemId,
+    pub constellation_id: ConstellationId,
+    pub region_id:        RegionId,
+    pub name:             String,
+    pub security:         f32,
+    pub space:            SpaceType,
+    /// Wormhole class of the system, set for all wormhole and abyssal
+    /// systems and for k-space systems that have a class
+    pub wormhole_class:   Option<u32>,
+    /// Type of the beacon of the system effect, for example a Pulsar or
+    /// Wolf-Rayet, the name is found in the [TypeService]
+    pub wormhole_effect:  Option<TypeId>,
+    pub celestials:       Vec<ItemId>,
+}
+
+/// Part of the universe a region belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpaceType {
+    /// Filaments and the Triglavian abyss
+    Abyssal,
+    /// Known space, including Pochven
+    KSpace,
+    Wormhole,
+}
+
+impl SpaceType {
+    /// Gets the space from a path in the zip, [None] if the path is not in
+    /// a universe folder that is parsed
+    fn from_path(path: &str) -> Option<Self> {
+        let space = path
+            .split(CelestialService::PATH_UNIVERSE)
+            .nth(1)?
+            .split('/')
+            .next()?;
+
+        match space {
+            "abyssal"  => Some(Self::Abyssal),
+            "eve"      => Some(Self::KSpace),
+            "wormhole" => Some(Self::Wormhole),
+            _          => None,
+        }
+    }
+}
+
+impl Default for SpaceType {
+    fn default() -> Self {
+        Self::KSpace
+    }
+}
+
+/// Single object in a system
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Celestial {
+    pub item_id:         ItemId,
+    pub kind:            CelestialKind,
+    pub type_id:         TypeId,
+    pub solar_system_id: SolarSystemId,
+    /// Planet or moon the celestial orbits, [None] for stars, planets and
+    /// stargates
+    pub parent:          Option<ItemId>,
+    pub name:            String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CelestialKind {
+    AsteroidBelt,
+    Moon,
+    Planet,
+    Star,
+    Stargate,
+    Station,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegionStaticEntry {
+    #[serde(rename = "center")]
+    pub center:            Vec<f32>,
+    #[serde(rename = "max")]
+    pub max:               Vec<f32>,
+    #[serde(rename = "min")]
+    pub min:               Vec<f32>,
+    #[serde(rename = "nameID")]
+    pub name_id:           u32,
+    #[serde(rename = "regionID")]
+    pub region_id:         RegionId,
+
+    #[serde(rename = "descriptionID")]
+    pub description_id:    Option<u32>,
+    #[serde(rename = "factionID")]
+    pub faction_id:        Option<FactionId>,
+    #[serde(rename = "nebula")]
+    pub nebula:            Option<u32>,
+    #[serde(rename = "wormholeClassID")]
+    pub wormhole_class_id: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstellationStaticEntry {
+    #[serde(rename = "center")]
+    pub center:            Vec<f32>,
+    #[serde(rename = "constellationID")]
+    pub constellation_id:  ConstellationId,
+    #[serde(rename = "max")]
+    pub max:               Vec<f32>,
+    #[serde(rename = "min")]
+    pub min:               Vec<f32>,
+    #[serde(rename = "nameID")]
+    pub name_id:           u32,
+    #[serde(rename = "radius")]
+    pub radius:            f32,
+
+    #[serde(rename = "factionID")]
+    pub faction_id:        Option<FactionId>,
+    #[serde(rename = "wormholeClassID")]
+    pub wormhole_class_id: Option<u32>,
+}
@@ -3,6 +3,8 @@ use crate::*;
 #[derive(Clone, Debug)]
 pub struct CharacterService {
     eve_client: EveClient,
+    /// Shares running asset requests of the same character
+    assets:     SingleFlight<Vec<CharacterAsset>>,
 }
 
 impl CharacterService {
@@ -11,14 +13,15 @@ impl CharacterService {
         _: SdeZipArchive,
     ) -> Result<Self, EveConnectError> {
         Ok(Self {
-            eve_client
+            eve_client,
+            assets: SingleFlight::new(),
         })
     }
 
     pub async fn portrait(
         &self,
         token: &str,
-        character_id: u32,
+        character_id: CharacterId,
     ) -> Result<String, EveConnectError> {
         #[derive(Deserialize)]
         struct Portrait {
@@ -58,11 +61,13 @@ impl CharacterService {
         character_id: CharacterId,
     ) -> Result<Vec<CharacterAsset>, EveConnectError> {
         let path = format!("characters/{}/assets", character_id);
-        self
+        let request = self
             .eve_client
-            .fetch_page_oauth::<CharacterAsset>(&token, &path)
+            .fetch_page_oauth::<CharacterAsset>(&token, &path);
+        self
+            .assets
+            .run(path.clone(), request)
             .await
-            .map_err(Into::into)
     }
 
     pub async fn asset_names(
@@ -107,6 +112,22 @@ impl CharacterService {
             .map_err(Into::into)
     }
 
+    /// Gets the attributes of the character, including implant bonuses
+    pub async fn attributes(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<CharacterAttributes, EveConnectError> {
+        let path = format!("characters/{}/attributes", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn skillqueue(
         &self,
         token: &str,
@@ -122,6 +143,71 @@ impl CharacterService {
             .map_err(Into::into)
     }
 
+    /// Gets the home location and all jump clones of the character
+    pub async fn clones(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<CharacterClones, EveConnectError> {
+        let path = format!("characters/{}/clones", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the implants of the active clone
+    pub async fn implants(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<TypeId>, EveConnectError> {
+        let path = format!("characters/{}/implants", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets all planetary colonies of the character
+    pub async fn planets(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<CharacterPlanet>, EveConnectError> {
+        let path = format!("characters/{}/planets", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the layout of a single planetary colony
+    pub async fn planet(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+        planet_id: PlanetId,
+    ) -> Result<PlanetLayout, EveConnectError> {
+        let path = format!("characters/{}/planets/{}", character_id, planet_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn corporation_name(
         &self,
         cid: CorporationId,
@@ -144,7 +230,7 @@ impl CharacterService {
 
     pub async fn alliance_name(
         &self,
-        aid: u32,
+        aid: AllianceId,
     ) -> Result<String, EveConnectError> {
         #[derive(Deserialize)]
         struct Alliance {
@@ -162,6 +248,166 @@ impl CharacterService {
             .map_err(Into::into)
     }
 
+    /// Gets the executor corporation and all member corporations of an
+    /// alliance
+    pub async fn alliance(
+        &self,
+        aid: AllianceId,
+    ) -> Result<Alliance, EveConnectError> {
+        let path = format!("alliances/{}", aid);
+        let alliance = self
+            .eve_client
+            .fetch(&path)
+            .await?
+            .json::<Alliance>()
+            .await?;
+
+        let path = format!("alliances/{}/corporations", aid);
+        let corporations = self
+            .eve_client
+            .fetch(&path)
+            .await?
+            .json::<Vec<CorporationId>>()
+            .await?;
+
+        Ok(Alliance {
+            corporations,
+            ..alliance
+        })
+    }
+
+    /// Gets the corporation roles of the character
+    pub async fn roles(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<String>, EveConnectError> {
+        #[derive(Deserialize)]
+        struct Roles {
+            roles: Vec<String>
+        }
+
+        let path = format!("characters/{}/roles", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json::<Roles>()
+            .await
+            .map(|x| x.roles)
+            .map_err(Into::into)
+    }
+
+    /// Gets all assets of a corporation, requires the director role
+    pub async fn corporation_assets(
+        &self,
+        token: &str,
+        corporation_id: CorporationId,
+    ) -> Result<Vec<CharacterAsset>, EveConnectError> {
+        let path = format!("corporations/{}/assets", corporation_id);
+        self
+            .eve_client
+            .fetch_page_oauth::<CharacterAsset>(&token, &path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets all structures of a corporation, requires the station manager
+    /// role
+    pub async fn corporation_structures(
+        &self,
+        token: &str,
+        corporation_id: CorporationId,
+    ) -> Result<Vec<CorporationStructure>, EveConnectError> {
+        let path = format!("corporations/{}/structures", corporation_id);
+        self
+            .eve_client
+            .fetch_page_oauth::<CorporationStructure>(&token, &path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the mining ledger of the character for the last 30 days
+    pub async fn mining(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<CharacterMining>, EveConnectError> {
+        let path = format!("characters/{}/mining", character_id);
+        self
+            .eve_client
+            .fetch_page_oauth::<CharacterMining>(&token, &path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets all mining observers of a corporation, requires the accountant
+    /// role
+    pub async fn corporation_mining_observers(
+        &self,
+        token: &str,
+        corporation_id: CorporationId,
+    ) -> Result<Vec<MiningObserver>, EveConnectError> {
+        let path = format!("corporation/{}/mining/observers", corporation_id);
+        self
+            .eve_client
+            .fetch_page_oauth::<MiningObserver>(&token, &path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the mining ledger of a single observer, requires the accountant
+    /// role
+    pub async fn corporation_mining_observer(
+        &self,
+        token: &str,
+        corporation_id: CorporationId,
+        observer_id: StructureId,
+    ) -> Result<Vec<MiningObserverEntry>, EveConnectError> {
+        let path = format!(
+            "corporation/{}/mining/observers/{}",
+            corporation_id,
+            observer_id
+        );
+        self
+            .eve_client
+            .fetch_page_oauth::<MiningObserverEntry>(&token, &path)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the latest notifications of the character
+    pub async fn notifications(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<CharacterNotification>, EveConnectError> {
+        let path = format!("characters/{}/notifications", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the next 50 calendar events of the character
+    pub async fn calendar(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<CalendarEvent>, EveConnectError> {
+        let path = format!("characters/{}/calendar", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn item_location(
         &self,
         token: &str,
@@ -180,7 +426,7 @@ impl CharacterService {
     pub async fn whoami(
         &self,
         token: &str,
-        character_id: u32,
+        character_id: CharacterId,
     ) -> Result<String, EveConnectError> {
         #[derive(Deserialize)]
         struct Character {
@@ -198,6 +444,37 @@ impl CharacterService {
             .map_err(Into::into)
     }
 
+    /// Current ISK in the wallet of the character
+    pub async fn wallet_balance(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<f64, EveConnectError> {
+        let path = format!("characters/{}/wallet", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json::<f64>()
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn wallet_transactions(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<WalletTransaction>, EveConnectError> {
+        let path = format!("characters/{}/wallet/transactions", character_id);
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json::<Vec<WalletTransaction>>()
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn fitting(
         &self,
         token: &str,
@@ -216,8 +493,8 @@ impl CharacterService {
 
 #[derive(Deserialize)]
 pub struct Character {
-    pub alliance_id:    Option<u32>,
-    pub corporation_id: u32,
+    pub alliance_id:    Option<AllianceId>,
+    pub corporation_id: CorporationId,
     pub name:           String,
 }
 
@@ -234,6 +511,141 @@ pub struct CharacterAsset {
     pub is_blueprint_copy: Option<bool>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alliance {
+    pub executor_corporation_id: Option<CorporationId>,
+    pub name:                    String,
+    #[serde(default)]
+    pub corporations:            Vec<CorporationId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CorporationStructure {
+    pub corporation_id:  CorporationId,
+    pub state:           String,
+    pub structure_id:    StructureId,
+    pub system_id:       SolarSystemId,
+    pub type_id:         TypeId,
+
+    /// Date the fuel runs out, not set if the structure has no fuel
+    pub fuel_expires:    Option<String>,
+    pub name:            Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterClones {
+    pub jump_clones:              Vec<JumpClone>,
+
+    pub home_location:            Option<CloneLocation>,
+    pub last_clone_jump_date:     Option<String>,
+    pub last_station_change_date: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CloneLocation {
+    pub location_id:   LocationId,
+    /// Either `station` or `structure`
+    pub location_type: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JumpClone {
+    pub implants:      Vec<TypeId>,
+    pub jump_clone_id: u32,
+    pub location_id:   LocationId,
+    /// Either `station` or `structure`
+    pub location_type: String,
+
+    pub name:          Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterPlanet {
+    pub last_update:     String,
+    pub num_pins:        u32,
+    pub owner_id:        CharacterId,
+    pub planet_id:       PlanetId,
+    pub planet_type:     String,
+    pub solar_system_id: SolarSystemId,
+    pub upgrade_level:   u8,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlanetLayout {
+    pub pins: Vec<PlanetPin>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlanetPin {
+    pub latitude:          f32,
+    pub longitude:         f32,
+    pub pin_id:            u64,
+    pub type_id:           TypeId,
+
+    pub expiry_time:       Option<String>,
+    pub extractor_details: Option<PlanetExtractor>,
+    pub install_time:      Option<String>,
+    pub last_cycle_start:  Option<String>,
+    pub schematic_id:      Option<SchematicId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlanetExtractor {
+    pub cycle_time:      Option<u32>,
+    pub head_radius:     Option<f32>,
+    pub product_type_id: Option<TypeId>,
+    pub qty_per_cycle:   Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterMining {
+    /// Format: YYYY-MM-DD
+    pub date:            String,
+    pub quantity:        u64,
+    pub solar_system_id: SolarSystemId,
+    pub type_id:         TypeId,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MiningObserver {
+    pub last_updated:  String,
+    pub observer_id:   StructureId,
+    pub observer_type: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MiningObserverEntry {
+    pub character_id:            CharacterId,
+    /// Format: YYYY-MM-DD
+    pub last_updated:            String,
+    pub quantity:                u64,
+    pub recorded_corporation_id: CorporationId,
+    pub type_id:                 TypeId,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterNotification {
+    pub notification_id: u64,
+    pub sender_id:       u32,
+    pub sender_type:     String,
+    pub timestamp:       String,
+    #[serde(rename = "type")]
+    pub typ:             String,
+
+    pub is_read:         Option<bool>,
+    /// Yaml formatted
+    pub text:            Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CalendarEvent {
+    pub event_date:     String,
+    pub event_id:       u32,
+    pub event_response: String,
+    pub importance:     i32,
+    pub title:          String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CharacterAssetName {
     pub item_id: ItemId,
@@ -272,6 +684,15 @@ pub struct CharacterSkill {
     pub trained_skill_level:  u32,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterAttributes {
+    pub charisma:     u32,
+    pub intelligence: u32,
+    pub memory:       u32,
+    pub perception:   u32,
+    pub willpower:    u32,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CharacterSkillQueue {
     pub finished_level:    u32,
@@ -285,6 +706,7 @@ pub struct CharacterSkillQueue {
     pub training_start_sp: Option<u32>,
 }
 
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ItemLocation {
     pub name:      String,
@@ -309,3 +731,19 @@ pub struct CharacterFittingItem {
     pub quantity: u32,
     pub type_id:  TypeId,
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WalletTransaction {
+    pub client_id:      u32,
+    /// Date and time of the transaction
+    pub date:           String,
+    pub is_buy:         bool,
+    pub is_personal:    bool,
+    pub journal_ref_id: u64,
+    pub location_id:    LocationId,
+    pub quantity:       u32,
+    pub transaction_id: TransactionId,
+    pub type_id:        TypeId,
+    /// Amount paid per unit
+    pub unit_price:     f32,
+}
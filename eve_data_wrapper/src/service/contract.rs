@@ -0,0 +1,160 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct ContractService {
+    eve_client: EveClient,
+}
+
+impl ContractService {
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches all public contracts in the given region
+    pub async fn public_contracts<T: Into<RegionId>>(
+        &self,
+        rid: T,
+    ) -> Result<Vec<PublicContract>, EveConnectError> {
+        self
+            .eve_client
+            .fetch_page(&format!("contracts/public/{}", *rid.into()))
+            .await
+    }
+
+    /// Fetches all items of a public contract
+    ///
+    /// Only item exchange and auction contracts contain items
+    pub async fn public_contract_items<T: Into<ContractId>>(
+        &self,
+        cid: T,
+    ) -> Result<Vec<PublicContractItem>, EveConnectError> {
+        self
+            .eve_client
+            .fetch_page(&format!("contracts/public/items/{}", *cid.into()))
+            .await
+    }
+
+    /// Fetches all contracts the character issued, accepted or is assigned
+    /// to
+    pub async fn character_contracts(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+    ) -> Result<Vec<CharacterContract>, EveConnectError> {
+        let path = format!("characters/{}/contracts", character_id);
+        self
+            .eve_client
+            .fetch_page_oauth::<CharacterContract>(&token, &path)
+            .await
+    }
+
+    /// Fetches all items of a contract the character has access to
+    pub async fn character_contract_items<T: Into<ContractId>>(
+        &self,
+        token: &str,
+        character_id: CharacterId,
+        cid: T,
+    ) -> Result<Vec<PublicContractItem>, EveConnectError> {
+        let path = format!("characters/{}/contracts/{}/items", character_id, *cid.into());
+        self
+            .eve_client
+            .fetch_oauth(&token, &path)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterContract {
+    pub assignee_id:           u32,
+    pub acceptor_id:           u32,
+    pub contract_id:           ContractId,
+    pub date_expired:          String,
+    pub date_issued:           String,
+    pub for_corporation:       bool,
+    pub issuer_corporation_id: CorporationId,
+    pub issuer_id:             CharacterId,
+    /// outstanding, in_progress, finished_issuer, finished_contractor,
+    /// finished, cancelled, rejected, failed, deleted or reversed
+    pub status:                String,
+    #[serde(rename = "type")]
+    pub typ:                   ContractType,
+
+    pub buyout:                Option<f64>,
+    pub collateral:            Option<f64>,
+    pub date_completed:        Option<String>,
+    pub end_location_id:       Option<LocationId>,
+    pub price:                 Option<f64>,
+    pub reward:                Option<f64>,
+    pub start_location_id:     Option<LocationId>,
+    pub title:                 Option<String>,
+    pub volume:                Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PublicContract {
+    pub contract_id:           ContractId,
+    pub date_expired:          String,
+    pub date_issued:           String,
+    pub issuer_corporation_id: CorporationId,
+    pub issuer_id:             CharacterId,
+    #[serde(rename = "type")]
+    pub typ:                   ContractType,
+
+    pub buyout:                Option<f64>,
+    pub collateral:            Option<f64>,
+    pub days_to_complete:      Option<u32>,
+    pub end_location_id:       Option<LocationId>,
+    pub for_corporation:       Option<bool>,
+    pub price:                 Option<f64>,
+    pub reward:                Option<f64>,
+    pub start_location_id:     Option<LocationId>,
+    pub title:                 Option<String>,
+    pub volume:                Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractType {
+    Auction,
+    Courier,
+    ItemExchange,
+    Loan,
+    Unknown,
+}
+
+impl ContractType {
+    /// Same name as used by ESI
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auction      => "auction",
+            Self::Courier      => "courier",
+            Self::ItemExchange => "item_exchange",
+            Self::Loan         => "loan",
+            Self::Unknown      => "unknown",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PublicContractItem {
+    /// true if the item is given by the issuer, false if the issuer wants
+    /// the item in return
+    pub is_included:         bool,
+    pub quantity:            u32,
+    pub record_id:           u64,
+    pub type_id:             TypeId,
+
+    pub is_blueprint_copy:   Option<bool>,
+    pub item_id:             Option<ItemId>,
+    pub material_efficiency: Option<u32>,
+    pub runs:                Option<i32>,
+    pub time_efficiency:     Option<u32>,
+}
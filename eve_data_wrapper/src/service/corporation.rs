@@ -18,10 +18,57 @@ impl CorporationService {
     const PATH_NPC_CORPORATIONS: &'static str = "sde/fsd/npcCorporations.yaml";
     const PATH_NPC_DIVISIONS:    &'static str = "sde/fsd/npcCorporationDivisions.yaml";
 
-    pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let npc_corporations = zip.collection(Self::PATH_NPC_CORPORATIONS);
+        let npc_divisions    = zip.collection(Self::PATH_NPC_DIVISIONS);
+
         Ok(Self {
-            npc_corporations: crate::parse_zip_file(Self::PATH_NPC_CORPORATIONS, &mut zip)?,
-            npc_divisions:    crate::parse_zip_file(Self::PATH_NPC_DIVISIONS, &mut zip)?,
+            npc_corporations: npc_corporations.join()?,
+            npc_divisions:    npc_divisions.join()?,
         })
     }
+
+    pub fn npc_corporations(&self) -> &HashMap<CorporationId, NpcCorporationEntry> {
+        &self.npc_corporations
+    }
+
+    pub fn npc_corporation<T: Into<CorporationId>>(
+        &self,
+        cid: T
+    ) -> Option<&NpcCorporationEntry> {
+        self.npc_corporations.get(&cid.into())
+    }
+
+    pub fn npc_divisions(&self) -> &HashMap<DivisionId, NpcCorporationDivisionEntry> {
+        &self.npc_divisions
+    }
+
+    /// Gets all npc corporations that belong to a faction
+    pub fn npc_corporations_by_faction<T: Into<FactionId>>(
+        &self,
+        fid: T
+    ) -> Vec<CorporationId> {
+        let fid = fid.into();
+        self
+            .npc_corporations
+            .iter()
+            .filter(|(_, x)| x.faction_id == Some(fid))
+            .map(|(cid, _)| *cid)
+            .collect::<Vec<_>>()
+    }
+
+    /// Gets all npc corporations that have a LP store
+    pub fn npc_corporations_with_store(&self) -> Vec<CorporationId> {
+        self
+            .npc_corporations
+            .iter()
+            .filter(|(_, x)| {
+                x.lpoffer_tables
+                    .as_ref()
+                    .map(|x| !x.is_empty())
+                    .unwrap_or_default()
+            })
+            .map(|(cid, _)| *cid)
+            .collect::<Vec<_>>()
+    }
 }
@@ -10,28 +10,258 @@ pub use self::type_dogma::*;
 
 use crate::*;
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub struct DogmaService {
     attributes: HashMap<AttributeId, DogmaAttributeEntry>,
     categories: HashMap<DogmaCategoryId, DogmaAttributeCategoryEntry>,
-    effects:    HashMap<u32, DogmaEffectEntry>,
-    typ:        HashMap<AttributeId, TypeDogmaEntry>,
+    effects:    HashMap<EffectId, DogmaEffectEntry>,
+    typ:        HashMap<TypeId, TypeDogmaEntry>,
 }
 
 impl DogmaService {
+    /// Attribute of a skill that contains the primary training attribute
+    pub const ATTRIBUTE_PRIMARY:   u32 = 180;
+    /// Attribute of a skill that contains the secondary training attribute
+    pub const ATTRIBUTE_SECONDARY: u32 = 181;
+    /// Attributes that contain a required skill together with the attribute
+    /// that contains the required level, `requiredSkill1` to
+    /// `requiredSkill6`
+    const ATTRIBUTE_REQUIRED_SKILLS: [(u32, u32); 6] = [
+        (182,  277),
+        (183,  278),
+        (184,  279),
+        (1285, 1286),
+        (1289, 1287),
+        (1290, 1288),
+    ];
+
     const PATH_ATTRIBUTES: &'static str = "sde/fsd/dogmaAttributes.yaml";
     const PATH_CATEGORIES: &'static str = "sde/fsd/dogmaAttributeCategories.yaml";
     const PATH_EFFECTS:    &'static str = "sde/fsd/dogmaEffects.yaml";
     const PATH_TYPE:       &'static str = "sde/fsd/typeDogma.yaml";
 
-    pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let attributes = zip.collection(Self::PATH_ATTRIBUTES);
+        let categories = zip.collection(Self::PATH_CATEGORIES);
+        let effects    = zip.collection(Self::PATH_EFFECTS);
+        let typ        = zip.collection(Self::PATH_TYPE);
+
         Ok(Self {
-            attributes: crate::parse_zip_file(Self::PATH_ATTRIBUTES, &mut zip)?,
-            categories: crate::parse_zip_file(Self::PATH_CATEGORIES, &mut zip)?,
-            effects:    crate::parse_zip_file(Self::PATH_EFFECTS, &mut zip)?,
-            typ:        crate::parse_zip_file(Self::PATH_TYPE, &mut zip)?,
+            attributes: attributes.join()?,
+            categories: categories.join()?,
+            effects:    effects.join()?,
+            typ:        typ.join()?,
         })
     }
+
+    /// Returns all attributes
+    pub fn attributes(&self) -> &HashMap<AttributeId, DogmaAttributeEntry> {
+        &self.attributes
+    }
+
+    pub fn attribute<A: Into<AttributeId>>(&self, aid: A) -> Option<&DogmaAttributeEntry> {
+        self.attributes.get(&aid.into())
+    }
+
+    /// Gets an attribute by its internal name, for example `cpuOutput`
+    pub fn attribute_by_name(&self, name: &str) -> Option<&DogmaAttributeEntry> {
+        self
+            .attributes
+            .values()
+            .find(|x| x.name == name)
+    }
+
+    /// Returns all attribute categories
+    pub fn categories(&self) -> &HashMap<DogmaCategoryId, DogmaAttributeCategoryEntry> {
+        &self.categories
+    }
+
+    /// Returns all effects
+    pub fn effects(&self) -> &HashMap<EffectId, DogmaEffectEntry> {
+        &self.effects
+    }
+
+    pub fn effect<E: Into<EffectId>>(&self, eid: E) -> Option<&DogmaEffectEntry> {
+        self.effects.get(&eid.into())
+    }
+
+    /// Gets the value of a dogma attribute of a type
+    pub fn type_attribute<T: Into<TypeId>, A: Into<AttributeId>>(
+        &self,
+        tid: T,
+        aid: A,
+    ) -> Option<f32> {
+        let aid = aid.into();
+        self
+            .typ
+            .get(&tid.into())?
+            .attributes
+            .iter()
+            .find(|x| x.attribute_id == aid)
+            .map(|x| x.value)
+    }
+
+    /// Same as [DogmaService::type_attribute], but falls back to the
+    /// default value of the attribute if the type does not set it
+    pub fn type_attribute_or_default<T: Into<TypeId>, A: Into<AttributeId>>(
+        &self,
+        tid: T,
+        aid: A,
+    ) -> Option<f32> {
+        let aid = aid.into();
+        self
+            .type_attribute(tid, aid)
+            .or_else(|| self.attribute(aid).map(|x| x.default_value))
+    }
+
+    /// Gets all attribute values of a type
+    ///
+    /// # Returns
+    ///
+    /// Map of attribute id to the value, empty if the type has no
+    /// attributes
+    ///
+    pub fn type_attributes<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> HashMap<AttributeId, f32> {
+        self
+            .typ
+            .get(&tid.into())
+            .map(|x| {
+                x.attributes
+                    .iter()
+                    .map(|x| (x.attribute_id, x.value))
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets all effects of a type
+    pub fn type_effects<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> Vec<&DogmaEffectEntry> {
+        self
+            .typ
+            .get(&tid.into())
+            .map(|x| {
+                x.effects
+                    .iter()
+                    .filter_map(|x| self.effects.get(&x.effect_id))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets the primary and secondary attribute that are used for training
+    /// the given skill.
+    ///
+    /// # Returns
+    ///
+    /// Tuple of the primary and secondary attribute id, for example `165`
+    /// for intelligence
+    ///
+    pub fn skill_attributes<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> Option<(u32, u32)> {
+        let tid = tid.into();
+        let primary = self.type_attribute(tid, Self::ATTRIBUTE_PRIMARY)?;
+        let secondary = self.type_attribute(tid, Self::ATTRIBUTE_SECONDARY)?;
+        Some((primary as u32, secondary as u32))
+    }
+
+    /// Gets the skills that are directly required by a type, without the
+    /// prerequisites of the skills themself.
+    ///
+    /// # Returns
+    ///
+    /// List of skills with their required level
+    ///
+    pub fn required_skills<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> Vec<(TypeId, u8)> {
+        let attributes = self.type_attributes(tid);
+        Self::ATTRIBUTE_REQUIRED_SKILLS
+            .iter()
+            .filter_map(|(skill, level)| {
+                let skill = attributes.get(&AttributeId(*skill))?;
+                let level = attributes.get(&AttributeId(*level)).copied().unwrap_or(1f32);
+                Some((TypeId(*skill as u32), level as u8))
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Resolves all skills that are required for a type, including the
+    /// prerequisites of every skill.
+    ///
+    /// # Returns
+    ///
+    /// Tree of all required skills, empty if the type does not require a
+    /// skill
+    ///
+    pub fn skill_tree<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> Vec<SkillRequirement> {
+        self.skill_tree_inner(tid.into(), &mut Vec::new())
+    }
+
+    /// Same as [DogmaService::skill_tree] but flattened, every skill is only
+    /// contained once with the highest level required anywhere in the tree
+    pub fn skill_tree_flat<T: Into<TypeId>>(
+        &self,
+        tid: T,
+    ) -> HashMap<TypeId, u8> {
+        fn flatten(tree: &[SkillRequirement], result: &mut HashMap<TypeId, u8>) {
+            for x in tree {
+                let level = result.entry(x.skill_id).or_default();
+                *level = x.level.max(*level);
+                flatten(&x.requires, result);
+            }
+        }
+
+        let mut result = HashMap::new();
+        flatten(&self.skill_tree(tid), &mut result);
+        result
+    }
+
+    /// `path` contains all skills from the root to the current skill and
+    /// protects against cycles
+    fn skill_tree_inner(
+        &self,
+        tid:  TypeId,
+        path: &mut Vec<TypeId>,
+    ) -> Vec<SkillRequirement> {
+        path.push(tid);
+        let tree = self
+            .required_skills(tid)
+            .into_iter()
+            .filter(|(skill, _)| !path.contains(skill))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(skill_id, level)| SkillRequirement {
+                skill_id,
+                level,
+                requires: self.skill_tree_inner(skill_id, path),
+            })
+            .collect::<Vec<_>>();
+        path.pop();
+        tree
+    }
+}
+
+/// Skill that is required for a type
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "with_schema", derive(schemars::JsonSchema))]
+pub struct SkillRequirement {
+    pub skill_id: TypeId,
+    pub level:    u8,
+    /// Skills that are required to train this skill
+    pub requires: Vec<SkillRequirement>,
 }
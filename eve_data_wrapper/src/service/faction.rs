@@ -0,0 +1,99 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct FactionService {
+    factions: HashMap<FactionId, FactionEntry>,
+}
+
+impl FactionService {
+    const PATH: &'static str = "sde/fsd/factions.yaml";
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let factions = zip.collection(Self::PATH);
+
+        Ok(Self {
+            factions: factions.join()?,
+        })
+    }
+
+    pub fn factions(&self) -> &HashMap<FactionId, FactionEntry> {
+        &self.factions
+    }
+
+    pub fn faction<T: Into<FactionId>>(&self, fid: T) -> Option<&FactionEntry> {
+        self.factions.get(&fid.into())
+    }
+
+    /// Gets the faction a npc corporation belongs to.
+    ///
+    /// # Parameters
+    ///
+    /// * `cid`          - Npc corporation
+    /// * `corporations` - Service containing all npc corporations
+    ///
+    /// # Returns
+    ///
+    /// Faction of the corporation, [None] if the corporation does not exist
+    /// or does not belong to a faction
+    ///
+    pub fn faction_by_corporation<T: Into<CorporationId>>(
+        &self,
+        cid:          T,
+        corporations: &CorporationService,
+    ) -> Option<(FactionId, &FactionEntry)> {
+        let fid = corporations
+            .npc_corporation(cid)?
+            .faction_id?;
+        self
+            .factions
+            .get(&fid)
+            .map(|x| (fid, x))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FactionEntry {
+    #[serde(rename = "descriptionID")]
+    #[serde(default)]
+    pub description:            HashMap<String, String>,
+    #[serde(rename = "iconID")]
+    pub icon_id:                IconId,
+    #[serde(rename = "memberRaces")]
+    #[serde(default)]
+    pub member_races:           Vec<RaceId>,
+    #[serde(rename = "nameID")]
+    pub name:                   HashMap<String, String>,
+    #[serde(rename = "sizeFactor")]
+    pub size_factor:            f32,
+    #[serde(rename = "solarSystemID")]
+    pub solar_system_id:        SolarSystemId,
+    #[serde(rename = "uniqueName")]
+    pub unique_name:            bool,
+
+    #[serde(rename = "corporationID")]
+    pub corporation_id:         Option<CorporationId>,
+    #[serde(rename = "militiaCorporationID")]
+    pub militia_corporation_id: Option<CorporationId>,
+    #[serde(rename = "shortDescriptionID")]
+    #[serde(default)]
+    pub short_description:      HashMap<String, String>,
+}
+
+impl FactionEntry {
+    /// Gets the english name for a faction.
+    ///
+    /// # Returns
+    ///
+    /// If the english translation exists, it is returned, if not [None] is
+    /// returned.
+    pub fn name(&self) -> Option<String> {
+        self
+            .name
+            .get("en")
+            .cloned()
+    }
+}
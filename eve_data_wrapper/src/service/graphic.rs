@@ -0,0 +1,140 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct GraphicService {
+    graphics: HashMap<GraphicId, GraphicEntry>,
+}
+
+impl GraphicService {
+    const PATH: &'static str = "sde/fsd/graphicIDs.yaml";
+
+    /// Base url of the image server
+    const IMAGE_SERVER: &'static str = "https://images.evetech.net/types";
+    /// Category of all blueprints
+    const CATEGORY_BLUEPRINT: CategoryId = CategoryId(9);
+    /// Category of all relics, they have their own image variant
+    const CATEGORY_RELIC: CategoryId = CategoryId(34);
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let graphics = zip.collection(Self::PATH);
+
+        Ok(Self {
+            graphics: graphics.join()?,
+        })
+    }
+
+    pub fn graphics(&self) -> &HashMap<GraphicId, GraphicEntry> {
+        &self.graphics
+    }
+
+    pub fn graphic<T: Into<GraphicId>>(&self, gid: T) -> Option<&GraphicEntry> {
+        self.graphics.get(&gid.into())
+    }
+
+    /// Gets all images the image server provides for a type.
+    ///
+    /// Blueprints only have the `bp` and `bpc` variants and relics only the
+    /// `relic` variant. All other types have an `icon` and, if they have a
+    /// 3D model, a `render`.
+    ///
+    /// # Parameters
+    ///
+    /// * `tid`         - Type to get the images for
+    /// * `typ`         - SDE entry of the type
+    /// * `category_id` - Category of the group of the type
+    ///
+    /// # Returns
+    ///
+    /// List of all image variants with their url
+    ///
+    pub fn type_images<T: Into<TypeId>>(
+        &self,
+        tid:         T,
+        typ:         &TypeIdEntry,
+        category_id: CategoryId,
+    ) -> Vec<TypeImage> {
+        let tid = tid.into();
+
+        let variants = if category_id == Self::CATEGORY_BLUEPRINT {
+            vec![TypeImageVariant::Bp, TypeImageVariant::Bpc]
+        } else if category_id == Self::CATEGORY_RELIC {
+            vec![TypeImageVariant::Relic]
+        } else {
+            let mut variants = vec![TypeImageVariant::Icon];
+            let render = typ
+                .graphic_id
+                .and_then(|x| self.graphic(x))
+                .map(|x| x.sof_hull_name.is_some())
+                .unwrap_or(false);
+            if render {
+                variants.push(TypeImageVariant::Render);
+            }
+            variants
+        };
+
+        variants
+            .into_iter()
+            .map(|variant| TypeImage {
+                url: format!("{}/{}/{}", Self::IMAGE_SERVER, tid, variant.as_str()),
+                variant,
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+/// The file contains a lot of fields that are only used for rendering in the
+/// client, they are ignored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GraphicEntry {
+    #[serde(rename = "description")]
+    pub description:   Option<String>,
+    #[serde(rename = "graphicFile")]
+    pub graphic_file:  Option<String>,
+    #[serde(rename = "iconInfo")]
+    pub icon_info:     Option<GraphicIconInfo>,
+    #[serde(rename = "sofFactionName")]
+    pub sof_faction:   Option<String>,
+    #[serde(rename = "sofHullName")]
+    pub sof_hull_name: Option<String>,
+    #[serde(rename = "sofRaceName")]
+    pub sof_race_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GraphicIconInfo {
+    #[serde(rename = "folder")]
+    pub folder: String,
+}
+
+/// Single image of a type on the image server
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TypeImage {
+    pub variant: TypeImageVariant,
+    /// Url without size, the size can be set with `?size=64`
+    pub url:     String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeImageVariant {
+    Bp,
+    Bpc,
+    Icon,
+    Relic,
+    Render,
+}
+
+impl TypeImageVariant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bp     => "bp",
+            Self::Bpc    => "bpc",
+            Self::Icon   => "icon",
+            Self::Relic  => "relic",
+            Self::Render => "render",
+        }
+    }
+}
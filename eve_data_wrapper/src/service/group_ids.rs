@@ -13,7 +13,7 @@ impl GroupService {
 
     pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            groups: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            groups: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
 
@@ -0,0 +1,44 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct IconService {
+    icons: HashMap<IconId, IconEntry>,
+}
+
+impl IconService {
+    const PATH: &'static str = "sde/fsd/iconIDs.yaml";
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let icons = zip.collection(Self::PATH);
+
+        Ok(Self {
+            icons: icons.join()?,
+        })
+    }
+
+    pub fn icons(&self) -> &HashMap<IconId, IconEntry> {
+        &self.icons
+    }
+
+    pub fn icon<T: Into<IconId>>(&self, iid: T) -> Option<&IconEntry> {
+        self.icons.get(&iid.into())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IconEntry {
+    /// Path of the icon in the game client, for example
+    /// `res:/ui/texture/icons/7_64_15.png`
+    #[serde(rename = "iconFile")]
+    pub icon_file:   String,
+
+    #[serde(rename = "description")]
+    pub description: Option<String>,
+    #[serde(rename = "obsolete")]
+    #[serde(default)]
+    pub obsolete:    bool,
+}
@@ -0,0 +1,42 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct IncursionService {
+    eve_client: EveClient,
+}
+
+impl IncursionService {
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches all active incursions from `/incursions`
+    pub async fn incursions(&self) -> Result<Vec<Incursion>, EveConnectError> {
+        self
+            .eve_client
+            .fetch("incursions")
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Incursion {
+    pub constellation_id:        ConstellationId,
+    pub faction_id:              FactionId,
+    pub has_boss:                bool,
+    pub infested_solar_systems:  Vec<SolarSystemId>,
+    pub influence:               f32,
+    pub staging_solar_system_id: SolarSystemId,
+    /// established, mobilizing or withdrawing
+    pub state:                   String,
+    #[serde(rename = "type")]
+    pub typ:                     String,
+}
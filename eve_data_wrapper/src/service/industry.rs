@@ -58,21 +58,21 @@ impl IndustryService {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct IndustryJob {
-    pub activity_id:            u32,
-    pub blueprint_id:           u64,
-    pub blueprint_location_id:  u64,
-    pub blueprint_type_id:      u32,
+    pub activity_id:            ActivityId,
+    pub blueprint_id:           ItemId,
+    pub blueprint_location_id:  LocationId,
+    pub blueprint_type_id:      TypeId,
     pub duration:               u32,
     pub end_date:               String,
-    pub facility_id:            u64,
-    pub installer_id:           u32,
+    pub facility_id:            LocationId,
+    pub installer_id:           CharacterId,
     pub job_id:                 u32,
-    pub output_location_id:     u64,
+    pub output_location_id:     LocationId,
     pub runs:                   u32,
     pub start_date:             String,
     pub status:                 String,
 
-    pub completed_character_id: Option<u32>,
+    pub completed_character_id: Option<CharacterId>,
     pub completed_date:         Option<String>,
     pub cost:                   Option<f32>,
     pub licensed_runs:          Option<u32>,
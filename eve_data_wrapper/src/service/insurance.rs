@@ -0,0 +1,41 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct InsuranceService {
+    eve_client: EveClient,
+}
+
+impl InsuranceService {
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches the insurance levels of all ships from `/insurance/prices`
+    pub async fn prices(&self) -> Result<Vec<InsurancePrice>, EveConnectError> {
+        self
+            .eve_client
+            .fetch("insurance/prices")
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InsurancePrice {
+    pub type_id: TypeId,
+    pub levels:  Vec<InsurancePriceLevel>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InsurancePriceLevel {
+    pub cost:   f32,
+    pub name:   String,
+    pub payout: f32,
+}
@@ -0,0 +1,105 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct KillmailService {
+    eve_client: EveClient,
+}
+
+impl KillmailService {
+    const REDISQ_URL: &'static str = "https://redisq.zkillboard.com/listen.php";
+
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches a single killmail from ESI
+    pub async fn killmail<T: Into<KillmailId>>(
+        &self,
+        kid:  T,
+        hash: &str,
+    ) -> Result<Killmail, EveConnectError> {
+        self
+            .eve_client
+            .fetch(&format!("killmails/{}/{}", *kid.into(), hash))
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Waits for the next killmail from the zKillboard RedisQ.
+    ///
+    /// RedisQ keeps the request open for up to 10 seconds. If there was no
+    /// kill in that time [None] is returned.
+    ///
+    /// # Parameters
+    ///
+    /// * `queue_id` - Identifies the queue, zKillboard remembers which
+    ///                killmails were already sent to it
+    ///
+    /// # Returns
+    ///
+    /// Id and hash of the next killmail
+    ///
+    pub async fn redisq(
+        &self,
+        queue_id: &str,
+    ) -> Result<Option<ZkillPackage>, EveConnectError> {
+        reqwest::get(&format!("{}?queueID={}", Self::REDISQ_URL, queue_id))
+            .await?
+            .json::<ZkillRedisQ>()
+            .await
+            .map(|x| x.package)
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Killmail {
+    pub attackers:       Vec<KillmailAttacker>,
+    pub killmail_id:     KillmailId,
+    pub killmail_time:   String,
+    pub solar_system_id: SolarSystemId,
+    pub victim:          KillmailVictim,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KillmailAttacker {
+    pub final_blow:     bool,
+
+    pub character_id:   Option<CharacterId>,
+    pub corporation_id: Option<CorporationId>,
+    pub ship_type_id:   Option<TypeId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KillmailVictim {
+    pub ship_type_id:   TypeId,
+
+    pub character_id:   Option<CharacterId>,
+    pub corporation_id: Option<CorporationId>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ZkillRedisQ {
+    package: Option<ZkillPackage>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ZkillPackage {
+    #[serde(rename = "killID")]
+    pub kill_id: KillmailId,
+    pub zkb:     ZkillInfo,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ZkillInfo {
+    pub hash:        String,
+    #[serde(rename = "totalValue")]
+    pub total_value: f32,
+}
@@ -0,0 +1,51 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct LoyaltyService {
+    eve_client: EveClient,
+}
+
+impl LoyaltyService {
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches the LP store of a npc corporation from
+    /// `/loyalty/stores/{corporation_id}/offers`
+    pub async fn offers<T: Into<CorporationId>>(
+        &self,
+        cid: T,
+    ) -> Result<Vec<LoyaltyOffer>, EveConnectError> {
+        self
+            .eve_client
+            .fetch(&format!("loyalty/stores/{}/offers", *cid.into()))
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoyaltyOffer {
+    pub offer_id:       u32,
+    pub type_id:        TypeId,
+    pub quantity:       u32,
+    pub lp_cost:        u32,
+    pub isk_cost:       u64,
+    pub required_items: Vec<LoyaltyRequiredItem>,
+
+    /// Analysis kredits, only used by the concord store
+    pub ak_cost:        Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoyaltyRequiredItem {
+    pub type_id:  TypeId,
+    pub quantity: u32,
+}
@@ -79,13 +79,13 @@ pub struct MarketOrder {
     pub is_buy_order:  bool,
     /// Date this market order was placed
     pub issued:        String,
-    pub location_id:   u64,
+    pub location_id:   LocationId,
     pub min_volume:    u32,
-    pub order_id:      u64,
+    pub order_id:      OrderId,
     pub price:         f32,
     pub range:         String,
-    pub system_id:     u32,
-    pub type_id:       u32,
+    pub system_id:     SolarSystemId,
+    pub type_id:       TypeId,
     pub volume_remain: u32,
     pub volume_total:  u32,
 }
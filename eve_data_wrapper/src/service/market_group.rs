@@ -0,0 +1,157 @@
+use crate::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct MarketGroupService {
+    groups: HashMap<MarketGroupId, MarketGroupEntry>,
+}
+
+impl MarketGroupService {
+    const PATH: &'static str = "sde/fsd/marketGroups.yaml";
+
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let groups = zip.collection(Self::PATH);
+
+        Ok(Self {
+            groups: groups.join()?,
+        })
+    }
+
+    pub fn groups(&self) -> &HashMap<MarketGroupId, MarketGroupEntry> {
+        &self.groups
+    }
+
+    pub fn group<T: Into<MarketGroupId>>(&self, gid: T) -> Option<&MarketGroupEntry> {
+        self.groups.get(&gid.into())
+    }
+
+    /// Gets all parents of a market group, starting with the root group.
+    ///
+    /// # Parameters
+    ///
+    /// * `gid` - Market group to get the parents for
+    ///
+    /// # Returns
+    ///
+    /// All parent groups from the root down to the direct parent, empty if
+    /// the group is a root group or does not exist
+    ///
+    pub fn parents<T: Into<MarketGroupId>>(&self, gid: T) -> Vec<MarketGroupId> {
+        let mut parents = Vec::new();
+
+        let mut current = self.group(gid).and_then(|x| x.parent_group_id);
+        while let Some(x) = current {
+            // protects against broken data that references itself
+            if parents.contains(&x) {
+                break;
+            }
+            parents.push(x);
+            current = self.group(x).and_then(|x| x.parent_group_id);
+        }
+
+        parents.reverse();
+        parents
+    }
+
+    /// Builds the market group hierarchy as it is shown in the in-game
+    /// market browser.
+    ///
+    /// # Returns
+    ///
+    /// All root groups with their children, sorted by name
+    ///
+    pub fn tree(&self) -> Vec<MarketGroupNode> {
+        let mut children = HashMap::new();
+        for (gid, group) in self.groups.iter() {
+            children
+                .entry(group.parent_group_id)
+                .or_insert_with(Vec::new)
+                .push(*gid);
+        }
+
+        self.nodes(None, &children)
+    }
+
+    fn nodes(
+        &self,
+        parent:   Option<MarketGroupId>,
+        children: &HashMap<Option<MarketGroupId>, Vec<MarketGroupId>>,
+    ) -> Vec<MarketGroupNode> {
+        let mut nodes = children
+            .get(&parent)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|gid| {
+                let group = &self.groups[&gid];
+                MarketGroupNode {
+                    market_group_id: gid,
+                    name:            group.name().unwrap_or_default(),
+                    icon_id:         group.icon_id,
+                    has_types:       group.has_types,
+                    children:        self.nodes(Some(gid), children),
+                }
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        nodes
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketGroupEntry {
+    #[serde(rename = "descriptionID")]
+    #[serde(default)]
+    pub description:     HashMap<String, String>,
+    #[serde(rename = "hasTypes")]
+    pub has_types:       bool,
+    #[serde(rename = "nameID")]
+    pub name:            HashMap<String, String>,
+
+    #[serde(rename = "iconID")]
+    pub icon_id:         Option<IconId>,
+    #[serde(rename = "parentGroupID")]
+    pub parent_group_id: Option<MarketGroupId>,
+}
+
+impl MarketGroupEntry {
+    /// Gets the english description for a market group.
+    ///
+    /// # Returns
+    ///
+    /// If the english translation exists, it is returned, if not [None] is
+    /// returned.
+    pub fn description(&self) -> Option<String> {
+        self
+            .description
+            .get("en")
+            .cloned()
+    }
+
+    /// Gets the english name for a market group.
+    ///
+    /// # Returns
+    ///
+    /// If the english translation exists, it is returned, if not [None] is
+    /// returned.
+    pub fn name(&self) -> Option<String> {
+        self
+            .name
+            .get("en")
+            .cloned()
+    }
+}
+
+/// Single market group in the market group tree
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MarketGroupNode {
+    pub market_group_id: MarketGroupId,
+    pub name:            String,
+    pub icon_id:         Option<IconId>,
+    /// Only groups without children contain types
+    pub has_types:       bool,
+    pub children:        Vec<MarketGroupNode>,
+}
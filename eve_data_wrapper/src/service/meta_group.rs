@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub struct MetaGroupService {
-    groups: HashMap<u32, MetaGroupEntry>,
+    groups: HashMap<MetaGroupId, MetaGroupEntry>,
 }
 
 impl MetaGroupService {
@@ -13,9 +13,17 @@ impl MetaGroupService {
 
     pub fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            groups: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            groups: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
+
+    pub fn groups(&self) -> &HashMap<MetaGroupId, MetaGroupEntry> {
+        &self.groups
+    }
+
+    pub fn group<T: Into<MetaGroupId>>(&self, gid: T) -> Option<&MetaGroupEntry> {
+        self.groups.get(&gid.into())
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -32,3 +40,18 @@ pub struct MetaGroupEntry {
     #[serde(rename = "iconSuffix")]
     pub icon_suffix: Option<String>,
 }
+
+impl MetaGroupEntry {
+    /// Gets the english name for a meta group.
+    ///
+    /// # Returns
+    ///
+    /// If the english translation exists, it is returned, if not [None] is
+    /// returned.
+    pub fn name(&self) -> Option<String> {
+        self
+            .name
+            .get("en")
+            .cloned()
+    }
+}
@@ -12,14 +12,17 @@ impl NameService {
     const NAME_PATH:        &'static str = "sde/bsd/invNames.yaml";
     const UNIQUE_NAME_PATH: &'static str = "sde/bsd/invUniqueNames.yaml";
 
-    pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let names  = zip.collection(Self::NAME_PATH);
+        let unique = zip.collection(Self::UNIQUE_NAME_PATH);
+
         Ok(Self {
-            names:  crate::parse_zip_file(Self::NAME_PATH, &mut zip)?,
-            unique: crate::parse_zip_file(Self::UNIQUE_NAME_PATH, &mut zip)?,
+            names:  names.join()?,
+            unique: unique.join()?,
         })
     }
 
-    pub fn collect_names(&self) -> HashMap<TypeId, String> {
+    pub fn collect_names(&self) -> HashMap<ItemId, String> {
         self
             .unique
             .iter()
@@ -32,7 +35,7 @@ impl NameService {
 #[serde(deny_unknown_fields)]
 pub struct NameEntry {
     #[serde(rename = "itemID")]
-    pub item_id: TypeId,
+    pub item_id: ItemId,
     #[serde(rename = "itemName")]
     pub name:    String
 }
@@ -43,7 +46,7 @@ pub struct UniqueNameEntry {
     #[serde(rename = "groupID")]
     pub grou_id: GroupId,
     #[serde(rename = "itemID")]
-    pub item_id: TypeId,
+    pub item_id: ItemId,
     #[serde(rename = "itemName")]
     pub name:    String
 }
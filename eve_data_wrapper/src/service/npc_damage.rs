@@ -0,0 +1,88 @@
+use crate::*;
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Curated list of the damage NPCs of a faction deal and the damage they are
+/// weak against.
+///
+/// The SDE does not contain this information, so it is maintained by hand.
+#[derive(Clone, Debug)]
+pub struct NpcDamageService {
+    profiles: HashMap<FactionId, NpcDamageProfile>,
+}
+
+impl NpcDamageService {
+    pub fn new(_: SdeZipArchive) -> Result<Self, EveConnectError> {
+        use DamageType::*;
+
+        let profiles = vec![
+            NpcDamageProfile::new(500001, "Caldari State",         &[Kinetic, Thermal],   &[Kinetic, Thermal]),
+            NpcDamageProfile::new(500002, "Minmatar Republic",     &[Explosive, Kinetic], &[Explosive, Kinetic]),
+            NpcDamageProfile::new(500003, "Amarr Empire",          &[Em, Thermal],        &[Em, Thermal]),
+            NpcDamageProfile::new(500004, "Gallente Federation",   &[Kinetic, Thermal],   &[Kinetic, Thermal]),
+            NpcDamageProfile::new(500010, "Guristas Pirates",      &[Kinetic, Thermal],   &[Kinetic, Thermal]),
+            NpcDamageProfile::new(500011, "Angel Cartel",          &[Explosive, Kinetic], &[Explosive, Kinetic]),
+            NpcDamageProfile::new(500012, "Blood Raider Covenant", &[Em, Thermal],        &[Em, Thermal]),
+            NpcDamageProfile::new(500018, "Mordu's Legion",        &[Kinetic, Thermal],   &[Kinetic, Thermal]),
+            NpcDamageProfile::new(500019, "Sansha's Nation",       &[Em, Thermal],        &[Em, Thermal]),
+            NpcDamageProfile::new(500020, "Serpentis",             &[Kinetic, Thermal],   &[Kinetic, Thermal]),
+            NpcDamageProfile::new(500025, "Rogue Drones",          &[Explosive, Kinetic], &[Em, Thermal]),
+            NpcDamageProfile::new(500026, "Triglavian Collective", &[Explosive, Thermal], &[Explosive, Thermal]),
+        ]
+        .into_iter()
+        .map(|x| (x.faction_id, x))
+        .collect::<HashMap<_, _>>();
+
+        Ok(Self {
+            profiles,
+        })
+    }
+
+    pub fn profiles(&self) -> &HashMap<FactionId, NpcDamageProfile> {
+        &self.profiles
+    }
+
+    pub fn by_faction<F: Into<FactionId>>(&self, fid: F) -> Option<NpcDamageProfile> {
+        self
+            .profiles
+            .get(&fid.into())
+            .cloned()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NpcDamageProfile {
+    pub faction_id: FactionId,
+    pub name:       String,
+    /// Damage types the NPCs deal, most damage first. Tank against these.
+    pub deals:      Vec<DamageType>,
+    /// Damage types the NPCs are weakest against, weakest first. Shoot
+    /// these.
+    pub weak:       Vec<DamageType>,
+}
+
+impl NpcDamageProfile {
+    fn new(
+        faction_id: u32,
+        name:       &str,
+        deals:      &[DamageType],
+        weak:       &[DamageType],
+    ) -> Self {
+        Self {
+            faction_id: faction_id.into(),
+            name:       name.into(),
+            deals:      deals.to_vec(),
+            weak:       weak.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DamageType {
+    Em,
+    Explosive,
+    Kinetic,
+    Thermal,
+}
@@ -13,7 +13,7 @@ impl PlanceSchematicService {
 
     pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            schematics: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            schematics: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
 
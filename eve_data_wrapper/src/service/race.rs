@@ -13,7 +13,7 @@ impl RaceService {
 
     pub fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            races: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            races: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
 }
@@ -13,9 +13,19 @@ impl ResearchAgentService {
 
     pub fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
         Ok(Self {
-            research_agents: crate::parse_zip_file(Self::PATH, &mut zip)?,
+            research_agents: crate::parse_zip_collection(Self::PATH, &mut zip)?,
         })
     }
+
+    pub fn research_agents(&self) -> &HashMap<AgentId, ResearchAgentEntry> {
+        &self.research_agents
+    }
+
+    /// Skills the agent can research, [None] if the agent is no research
+    /// agent
+    pub fn research_agent<T: Into<AgentId>>(&self, aid: T) -> Option<&ResearchAgentEntry> {
+        self.research_agents.get(&aid.into())
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -21,11 +21,15 @@ impl SkinService {
     const PATH_MATERIALS: &'static str = "sde/fsd/skinMaterials.yaml";
     const PATH_SKINS:     &'static str = "sde/fsd/skins.yaml";
 
-    pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let licenses  = zip.collection(Self::PATH_LICENSES);
+        let materials = zip.collection(Self::PATH_MATERIALS);
+        let skins     = zip.collection(Self::PATH_SKINS);
+
         Ok(Self {
-            licenses:  crate::parse_zip_file(Self::PATH_LICENSES, &mut zip)?,
-            materials: crate::parse_zip_file(Self::PATH_MATERIALS, &mut zip)?,
-            skins:     crate::parse_zip_file(Self::PATH_SKINS, &mut zip)?,
+            licenses:  licenses.join()?,
+            materials: materials.join()?,
+            skins:     skins.join()?,
         })
     }
 }
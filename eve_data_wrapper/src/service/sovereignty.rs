@@ -0,0 +1,58 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct SovereigntyService {
+    eve_client: EveClient,
+}
+
+impl SovereigntyService {
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Fetches the owner of every system from `/sovereignty/map`
+    pub async fn map(&self) -> Result<Vec<SovereigntySystem>, EveConnectError> {
+        self
+            .eve_client
+            .fetch("sovereignty/map")
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Fetches the faction warfare state of all systems from `/fw/systems`
+    pub async fn fw_systems(&self) -> Result<Vec<FactionWarfareSystem>, EveConnectError> {
+        self
+            .eve_client
+            .fetch("fw/systems")
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SovereigntySystem {
+    pub system_id:      SolarSystemId,
+
+    pub alliance_id:    Option<AllianceId>,
+    pub corporation_id: Option<CorporationId>,
+    pub faction_id:     Option<FactionId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FactionWarfareSystem {
+    pub contested:                String,
+    pub occupier_faction_id:      FactionId,
+    pub owner_faction_id:         FactionId,
+    pub solar_system_id:          SolarSystemId,
+    pub victory_points:           u32,
+    pub victory_points_threshold: u32,
+}
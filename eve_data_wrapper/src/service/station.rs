@@ -21,11 +21,15 @@ impl StationService {
     const PATH_SERVICES:   &'static str = "sde/fsd/stationServices.yaml";
     const PATH_STATION:    &'static str = "sde/bsd/staStations.yaml";
 
-    pub(crate) fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub(crate) fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let operations = zip.collection(Self::PATH_OPERATIONS);
+        let services   = zip.collection(Self::PATH_SERVICES);
+        let stations   = zip.collection(Self::PATH_STATION);
+
         Ok(Self {
-            operations: crate::parse_zip_file(Self::PATH_OPERATIONS, &mut zip)?,
-            services:   crate::parse_zip_file(Self::PATH_SERVICES, &mut zip)?,
-            stations:   crate::parse_zip_file(Self::PATH_STATION, &mut zip)?,
+            operations: operations.join()?,
+            services:   services.join()?,
+            stations:   stations.join()?,
         })
     }
 
@@ -33,11 +37,11 @@ impl StationService {
         &self.stations
     }
 
-    pub fn collect_names(&self) -> HashMap<TypeId, String> {
+    pub fn collect_names(&self) -> HashMap<ItemId, String> {
         self
             .stations
             .iter()
-            .map(|x| ((*x.station_id).into(), x.station_name.clone()))
+            .map(|x| (ItemId(*x.station_id), x.station_name.clone()))
             .collect::<HashMap<_, _>>()
     }
 
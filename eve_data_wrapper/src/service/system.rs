@@ -23,9 +23,11 @@ impl SystemService {
     const PATH_CONSTELLATIONS: &'static str = "universe/constellations";
     const PATH_REGIONS:        &'static str = "universe/regions";
 
+    const METERS_PER_LIGHTYEAR: f64 = 9_460_730_472_580_800f64;
+
     pub(crate) async fn new(
         eve_client: EveClient,
-        mut zip:    SdeZipArchive
+        zip:        SdeZipArchive
     ) -> Result<Self, EveConnectError> {
         let mut abyssal  = Vec::new();
         let mut eve      = Vec::new();
@@ -48,26 +50,10 @@ impl SystemService {
             }
         }
 
-        let mut abyssal_entries  = Vec::with_capacity(abyssal.len());
-        let mut eve_entries      = Vec::with_capacity(eve.len());
-        let mut penalty_entries  = Vec::with_capacity(penalty.len());
-        let mut wormhole_entries = Vec::with_capacity(wormhole.len());
-
-        for path in abyssal {
-            abyssal_entries.push(crate::parse_zip_file(&path, &mut zip)?);
-        }
-
-        for path in eve {
-            eve_entries.push(crate::parse_zip_file(&path, &mut zip)?);
-        }
-
-        for path in penalty {
-            penalty_entries.push(crate::parse_zip_file(&path, &mut zip)?);
-        }
-
-        for path in wormhole {
-            wormhole_entries.push(crate::parse_zip_file(&path, &mut zip)?);
-        }
+        let abyssal_entries  = zip.files(abyssal)?;
+        let eve_entries      = zip.files(eve)?;
+        let penalty_entries  = zip.files(penalty)?;
+        let wormhole_entries = zip.files(wormhole)?;
 
         let constellations = Self::fetch_constellations(eve_client.clone()).await?;
         let regions = Self::fetch_regions(eve_client).await?;
@@ -91,6 +77,41 @@ impl SystemService {
         &self.eve
     }
 
+    /// Builds the jump graph of all k-space systems.
+    ///
+    /// Stargates only reference the gate they lead to, so every gate is
+    /// first mapped to the system it is located in.
+    ///
+    /// # Returns
+    ///
+    /// Map of every system and all systems that are connected by a stargate
+    ///
+    pub fn jump_graph(&self) -> HashMap<SolarSystemId, Vec<SolarSystemId>> {
+        let gate_system = self
+            .eve
+            .iter()
+            .flat_map(|x| x
+                .stargates
+                .keys()
+                .map(move |gate| (*gate, x.solar_system_id))
+            )
+            .collect::<HashMap<_, _>>();
+
+        self
+            .eve
+            .iter()
+            .map(|x| {
+                let neighbours = x
+                    .stargates
+                    .values()
+                    .filter_map(|gate| gate_system.get(&gate.destination))
+                    .copied()
+                    .collect::<Vec<_>>();
+                (x.solar_system_id, neighbours)
+            })
+            .collect::<HashMap<_, _>>()
+    }
+
     pub fn regions(&self) -> &HashMap<RegionId, RegionEntry> {
         &self.regions
     }
@@ -99,6 +120,73 @@ impl SystemService {
         self.regions.iter().map(|(id, _)| id).collect()
     }
 
+    /// Calculates the distance between two systems in lightyears.
+    ///
+    /// # Parameters
+    ///
+    /// * `from` - First system
+    /// * `to`   - Second system
+    ///
+    /// # Returns
+    ///
+    /// Distance in lightyears or [None] if one of the systems is not a k-space
+    /// system
+    ///
+    pub fn distance_ly<S: Into<SolarSystemId>>(
+        &self,
+        from: S,
+        to:   S,
+    ) -> Option<f64> {
+        let from = self.eve_system(from.into())?;
+        let to   = self.eve_system(to.into())?;
+        Some(Self::light_years(&from.center, &to.center))
+    }
+
+    /// Collects all systems that can be reached with a single jump drive
+    /// jump.
+    ///
+    /// Jumps can neither start in nor go into high security space.
+    ///
+    /// # Parameters
+    ///
+    /// * `system`     - System to jump from
+    /// * `ship_class` - Class of the ship that jumps
+    /// * `jdc_level`  - Level of the skill Jump Drive Calibration
+    ///
+    /// # Returns
+    ///
+    /// All systems in range together with their distance in lightyears,
+    /// sorted by distance
+    ///
+    pub fn systems_in_jump_range<S: Into<SolarSystemId>>(
+        &self,
+        system:     S,
+        ship_class: JumpShipClass,
+        jdc_level:  u8,
+    ) -> Vec<(SolarSystemId, f64)> {
+        let origin = if let Some(x) = self.eve_system(system.into()) {
+            x
+        } else {
+            return Vec::new();
+        };
+
+        if Self::is_high_sec(origin.security) {
+            return Vec::new();
+        }
+
+        let range = ship_class.range(jdc_level);
+        let mut systems = self
+            .eve
+            .iter()
+            .filter(|x| x.solar_system_id != origin.solar_system_id)
+            .filter(|x| !Self::is_high_sec(x.security))
+            .map(|x| (x.solar_system_id, Self::light_years(&origin.center, &x.center)))
+            .filter(|(_, distance)| *distance <= range)
+            .collect::<Vec<_>>();
+        systems.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        systems
+    }
+
     pub fn find_region_by_system<S: Into<SolarSystemId>>(
         &self,
         system: S
@@ -111,6 +199,28 @@ impl SystemService {
             .map(|(_, e)| e.region_id)
     }
 
+    fn eve_system(&self, system: SolarSystemId) -> Option<&SolarsystemEntry> {
+        self
+            .eve
+            .iter()
+            .find(|x| x.solar_system_id == system)
+    }
+
+    /// Systems with a security status of 0.45 or higher are rounded to 0.5
+    fn is_high_sec(security: f32) -> bool {
+        security >= 0.45
+    }
+
+    fn light_years(a: &[f32], b: &[f32]) -> f64 {
+        let distance = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        distance / Self::METERS_PER_LIGHTYEAR
+    }
+
     async fn fetch_constellations(
         eve_client: EveClient
     ) -> Result<HashMap<ConstellationId, ConstellationEntry>, EveConnectError> {
@@ -148,6 +258,41 @@ impl SystemService {
     }
 }
 
+/// Ship classes with a jump drive
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpShipClass {
+    /// Black Ops battleships
+    BlackOps,
+    /// Carriers, Dreadnoughts, Force Auxiliaries, Supercarriers and Titans
+    Capital,
+    /// Jump Freighters
+    JumpFreighter,
+    /// Rorqual
+    Rorqual,
+}
+
+impl JumpShipClass {
+    /// Every level of Jump Drive Calibration increases the range by 20%
+    const JDC_BONUS: f64 = 0.2;
+
+    /// Base jump range in lightyears without any skills
+    pub fn base_range(&self) -> f64 {
+        match self {
+            Self::BlackOps      => 4.0,
+            Self::Capital       => 3.5,
+            Self::JumpFreighter => 5.0,
+            Self::Rorqual       => 5.0,
+        }
+    }
+
+    /// Jump range in lightyears for the given Jump Drive Calibration level
+    pub fn range(&self, jdc_level: u8) -> f64 {
+        let jdc_level = jdc_level.min(5) as f64;
+        self.base_range() * (1f64 + Self::JDC_BONUS * jdc_level)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SolarsystemEntry {
@@ -219,7 +364,7 @@ pub struct Planet {
     pub moons:             HashMap<u32, Moon>, // FIXME: id
     #[serde(rename = "npcStations")]
     #[serde(default)]
-    pub npc_stations:      HashMap<u32, NpcStation>,
+    pub npc_stations:      HashMap<StationId, NpcStation>,
     #[serde(rename = "planetAttributes")]
     pub planet_attributes: PlanetAttribute,
     #[serde(rename = "position")]
@@ -304,7 +449,7 @@ pub struct AsteroidBelt {
 pub struct Moon {
     #[serde(rename = "npcStations")]
     #[serde(default)]
-    pub npc_stations:      HashMap<u32, NpcStation>,
+    pub npc_stations:      HashMap<StationId, NpcStation>,
     #[serde(rename = "planetAttributes")]
     pub planet_attributes: PlanetAttribute,
     #[serde(rename = "position")]
@@ -26,10 +26,13 @@ impl TypeService {
     ///
     /// Instance of itself with parsed fields.
     ///
-    pub fn new(mut zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+    pub fn new(zip: SdeZipArchive) -> Result<Self, EveConnectError> {
+        let types     = zip.collection(Self::PATH_ID);
+        let materials = zip.collection(Self::PATH_MATERIAL);
+
         Ok(Self {
-            types:     crate::parse_zip_file(Self::PATH_ID, &mut zip)?,
-            materials: crate::parse_zip_file(Self::PATH_MATERIAL, &mut zip)?
+            types:     types.join()?,
+            materials: materials.join()?,
         })
     }
 
@@ -70,6 +73,35 @@ impl TypeService {
     pub fn type_can_be_reprocessed<T: Into<TypeId>>(&self, id: T) -> bool {
         self.materials.contains_key(&id.into())
     }
+
+    /// Maps every ore and ice to its compressed variant
+    ///
+    /// The SDE has no direct relation between them, the compressed variant
+    /// has the same name prefixed with `Compressed`.
+    ///
+    /// # Returns
+    ///
+    /// Map with the uncompressed [TypeId] as key and the compressed [TypeId]
+    /// as value
+    ///
+    pub fn compressed_ores(&self) -> HashMap<TypeId, TypeId> {
+        let names = self
+            .types
+            .iter()
+            .filter(|(id, x)| x.published && self.type_can_be_reprocessed(**id))
+            .filter_map(|(id, x)| x.name().map(|name| (name, *id)))
+            .collect::<HashMap<_, _>>();
+
+        names
+            .iter()
+            .filter_map(|(name, id)| {
+                name
+                    .strip_prefix("Compressed ")
+                    .and_then(|x| names.get(x))
+                    .map(|x| (*x, *id))
+            })
+            .collect::<HashMap<_, _>>()
+    }
 }
 
 /// Represents a single entry in the yaml for a type
@@ -17,7 +17,7 @@ pub struct UniqueNameEntry {
     #[serde(rename = "groupID")]
     pub grou_id: GroupId,
     #[serde(rename = "itemID")]
-    pub item_id: u32, // FIXME
+    pub item_id: ItemId,
     #[serde(rename = "itemName")]
     pub name:    String
 }
@@ -0,0 +1,51 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub struct UniverseNameService {
+    eve_client: EveClient,
+}
+
+impl UniverseNameService {
+    /// Maximum number of ids ESI accepts in a single request
+    pub const MAX_IDS: usize = 1000;
+
+    pub fn new(
+        eve_client: EveClient,
+        _: SdeZipArchive
+    ) -> Result<Self, EveConnectError> {
+        Ok(Self {
+            eve_client
+        })
+    }
+
+    /// Resolves the names of arbitrary ids using `/universe/names`.
+    ///
+    /// ESI rejects the whole request if a single id is invalid.
+    ///
+    /// # Parameters
+    ///
+    /// * `ids` - Up to [UniverseNameService::MAX_IDS] ids
+    ///
+    /// # Returns
+    ///
+    /// Name and category of every id
+    ///
+    pub async fn resolve(
+        &self,
+        ids: &[u64],
+    ) -> Result<Vec<UniverseName>, EveConnectError> {
+        self
+            .eve_client
+            .post("universe/names", &ids)
+            .await
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UniverseName {
+    /// alliance, character, constellation, corporation, inventory_type,
+    /// region, solar_system, station or faction
+    pub category: String,
+    pub id:       u64,
+    pub name:     String,
+}
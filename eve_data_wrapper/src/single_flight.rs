@@ -0,0 +1,99 @@
+use crate::EveConnectError;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Result that is shared with all callers waiting for the same request
+type Shared<T> = Result<T, String>;
+
+/// Makes sure that only one request per key is send to ESI at a time.
+///
+/// If a request with the same key is already running, the caller waits for
+/// it and gets a copy of its result instead of sending its own request.
+/// The key is the endpoint, which already contains the character id.
+#[derive(Clone, Debug)]
+pub(crate) struct SingleFlight<T> {
+    inflight: Arc<Mutex<HashMap<String, broadcast::Sender<Shared<T>>>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            inflight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Runs the given request, or waits for an identical one that is already
+    /// running
+    ///
+    /// # Parameters
+    ///
+    /// * `key`     - Identifies the request, for example the path
+    /// * `request` - Request to run if no other is running for the key
+    ///
+    /// # Returns
+    ///
+    /// Result of the request, errors of a request that was started by
+    /// another caller are returned as [EveConnectError::SharedRequest]
+    ///
+    pub async fn run<F>(
+        &self,
+        key:     String,
+        request: F,
+    ) -> Result<T, EveConnectError>
+        where F: Future<Output = Result<T, EveConnectError>> {
+
+        let (tx, _) = broadcast::channel(1);
+        let running = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&key) {
+                Some(x) => Some(x.subscribe()),
+                None    => {
+                    inflight.insert(key.clone(), tx.clone());
+                    None
+                }
+            }
+        };
+
+        if let Some(mut rx) = running {
+            return match rx.recv().await {
+                Ok(x)  => x.map_err(EveConnectError::SharedRequest),
+                Err(_) => Err(EveConnectError::SharedRequest(
+                    format!("Request for {} was cancelled", key)
+                )),
+            };
+        }
+
+        // Makes sure the key is removed, even if the caller drops the future
+        let guard = Guard {
+            key:      key.clone(),
+            inflight: self.inflight.clone(),
+        };
+        let result = request.await;
+        drop(guard);
+
+        let shared = match &result {
+            Ok(x)  => Ok(x.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        // an error only means that nobody else is waiting
+        let _ = tx.send(shared);
+        result
+    }
+}
+
+/// Removes the key from the running requests when dropped
+struct Guard<T> {
+    key:      String,
+    inflight: Arc<Mutex<HashMap<String, broadcast::Sender<Shared<T>>>>>,
+}
+
+impl<T> Drop for Guard<T> {
+    fn drop(&mut self) {
+        if let Ok(mut x) = self.inflight.lock() {
+            x.remove(&self.key);
+        }
+    }
+}
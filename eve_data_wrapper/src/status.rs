@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Daily downtime of the eve servers.
+///
+/// The downtime starts at 11:00 UTC and usually takes about 15 minutes, the
+/// window is longer so that ESI has time to come back.
+pub struct Downtime;
+
+impl Downtime {
+    /// Seconds after midnight UTC the downtime starts
+    const START:  u64 = 11 * 60 * 60;
+    /// Seconds after the start until ESI is expected to answer again
+    const LENGTH: u64 = 30 * 60;
+    const DAY:    u64 = 24 * 60 * 60;
+
+    /// Checks if the given time is in the downtime window
+    ///
+    /// # Params
+    ///
+    /// `timestamp` -> Unix timestamp in seconds
+    ///
+    pub fn contains(timestamp: u64) -> bool {
+        Self::remaining(timestamp).is_some()
+    }
+
+    /// Time until the downtime window ends
+    ///
+    /// # Params
+    ///
+    /// `timestamp` -> Unix timestamp in seconds
+    ///
+    /// # Returns
+    ///
+    /// [None] if the given time is not in the downtime window
+    ///
+    pub fn remaining(timestamp: u64) -> Option<Duration> {
+        let time_of_day = timestamp % Self::DAY;
+        if time_of_day >= Self::START && time_of_day < Self::START + Self::LENGTH {
+            Some(Duration::from_secs(Self::START + Self::LENGTH - time_of_day))
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the current time is in the downtime window
+    pub fn is_now() -> bool {
+        Self::contains(Self::now())
+    }
+
+    /// Unix timestamp in seconds
+    pub(crate) fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// Response of `/status/`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServerStatus {
+    pub players:        u32,
+    pub server_version: String,
+    pub start_time:     String,
+    /// Only set if the server is in VIP mode, then only developers can log
+    /// in
+    #[serde(default)]
+    pub vip:            Option<bool>,
+}
+
+impl ServerStatus {
+    pub fn is_vip(&self) -> bool {
+        self.vip.unwrap_or_default()
+    }
+}
@@ -0,0 +1,122 @@
+use caph_eve_data_wrapper::{CharacterId, Downtime, EveConnectError, EveDataWrapper, ItemId, LocationId, MockEsi, SolarSystemId, StationId, StructureId, TypeId};
+
+#[tokio::test]
+async fn character_assets() {
+    let esi = MockEsi::start().await.unwrap();
+    let eve = EveDataWrapper::mock(&esi).unwrap();
+
+    let assets = eve
+        .character()
+        .await
+        .unwrap()
+        .assets("", 1.into())
+        .await
+        .unwrap();
+    assert_eq!(assets.len(), 3);
+    assert_eq!(esi.hits("characters/*/assets"), 1);
+}
+
+#[tokio::test]
+async fn market_prices() {
+    let esi = MockEsi::start().await.unwrap();
+    let eve = EveDataWrapper::mock(&esi).unwrap();
+
+    let prices = eve
+        .market()
+        .await
+        .unwrap()
+        .prices()
+        .await
+        .unwrap();
+    assert_eq!(prices.len(), 2);
+}
+
+#[tokio::test]
+async fn typed_ids() {
+    let esi = MockEsi::start().await.unwrap();
+    let eve = EveDataWrapper::mock(&esi).unwrap();
+
+    let orders = eve
+        .market()
+        .await
+        .unwrap()
+        .orders(10000002)
+        .await
+        .unwrap();
+    assert_eq!(orders[0].location_id, LocationId(60003760));
+    assert_eq!(orders[0].system_id, SolarSystemId(30000142));
+
+    let jobs = eve
+        .industry()
+        .await
+        .unwrap()
+        .jobs("", 1.into())
+        .await
+        .unwrap();
+    assert_eq!(jobs[0].installer_id, CharacterId(2117848511));
+    assert_eq!(jobs[0].facility_id, LocationId(60003760));
+}
+
+#[test]
+fn location_ids() {
+    let structure = StructureId(1_035_466_617_946);
+    assert_eq!(LocationId::from(structure), LocationId(1_035_466_617_946));
+    assert_eq!(LocationId::from(StationId(60003760)), LocationId(60003760));
+    assert_eq!(ItemId::from(TypeId(34)), ItemId(34));
+
+    let station = serde_json::from_str::<StationId>("1035466617946").unwrap();
+    assert_eq!(*station, 1_035_466_617_946);
+}
+
+#[tokio::test]
+async fn esi_error() {
+    let esi = MockEsi::start().await.unwrap();
+    esi.route("characters/*/blueprints", 420, r#"{"error":"Error limited"}"#);
+    let eve = EveDataWrapper::mock(&esi).unwrap();
+
+    let error = eve
+        .character()
+        .await
+        .unwrap()
+        .blueprints("", 1.into())
+        .await
+        .unwrap_err();
+    match error {
+        EveConnectError::EsiError(x) => {
+            assert_eq!(x.status, 420);
+            assert_eq!(x.message, Some("Error limited".into()));
+            assert!(!x.is_transient());
+        },
+        _ => panic!("Expected an ESI error, got {:?}", error),
+    }
+    assert_eq!(esi.hits("characters/*/blueprints"), 1);
+}
+
+#[tokio::test]
+async fn server_offline() {
+    let esi = MockEsi::start().await.unwrap();
+    esi.route("status", 503, r#"{"error":"The datasource tranquility is temporarily unavailable"}"#);
+    let client = esi.client().unwrap();
+
+    assert!(client.fetch_status().await.is_err());
+    assert!(!client.is_online());
+    match client.fetch_raw("markets/prices/").await {
+        Err(EveConnectError::EveOffline) => (),
+        x => panic!("Expected the server to be offline, got {:?}", x),
+    }
+    assert_eq!(esi.hits("markets/prices"), 0);
+
+    esi.route("status", 200, include_str!("../fixtures/esi/status.json"));
+    let status = client.fetch_status().await.unwrap();
+    assert!(!status.is_vip());
+    assert!(client.fetch_raw("markets/prices/").await.is_ok());
+}
+
+#[test]
+fn downtime_window() {
+    let day = 10 * 24 * 60 * 60;
+    assert!(!Downtime::contains(day + 10 * 60 * 60 + 59 * 60));
+    assert!(Downtime::contains(day + 11 * 60 * 60));
+    assert!(Downtime::contains(day + 11 * 60 * 60 + 29 * 60));
+    assert!(!Downtime::contains(day + 11 * 60 * 60 + 30 * 60));
+}
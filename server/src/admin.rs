@@ -0,0 +1,257 @@
+use crate::character::{CharacterService, CharacterSync};
+use crate::delete_user::{AuditEntry, DeleteUserService};
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, UserEntry};
+use caph_eve_data_wrapper::{CharacterId, CorporationId, EveOAuthPayload};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Manages the registered users, all functions must be restricted to
+/// admins
+#[derive(Clone)]
+pub struct AdminService {
+    pool:        ConnectionPool,
+    eve_auth:    EveAuthService,
+    character:   CharacterService,
+    delete_user: DeleteUserService,
+    /// Characters whose refresh token was rejected by the eve auth server
+    /// since the last start
+    revoked:     Arc<RwLock<HashSet<CharacterId>>>,
+}
+
+impl AdminService {
+    /// Creates a new instance
+    pub fn new(
+        pool:        ConnectionPool,
+        eve_auth:    EveAuthService,
+        character:   CharacterService,
+        delete_user: DeleteUserService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            character,
+            delete_user,
+            revoked: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// Lists all registered mains
+    ///
+    /// # Returns
+    ///
+    /// Every main with its alts and the last login
+    ///
+    pub async fn users(&self) -> Result<Vec<AdminUser>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, CharacterId>(CacheName::User)
+            .await?;
+        let users = con
+            .mget::<_, _, UserEntry>(CacheName::User, keys.clone())
+            .await?;
+        let logins = con
+            .mget::<_, _, u64>(CacheName::UserLogin, keys)
+            .await?;
+
+        let mut result = users
+            .into_iter()
+            .zip(logins)
+            .filter_map(|(user, last_login)| user.map(|x| (x, last_login)))
+            .map(|(user, last_login)| AdminUser {
+                user_id: user.user_id,
+                corp_id: user.corp_id,
+                alts:    user.aliase.iter().map(|x| x.user_id).collect::<Vec<_>>(),
+                last_login,
+            })
+            .collect::<Vec<_>>();
+        result.sort_by_key(|x| x.user_id);
+        Ok(result)
+    }
+
+    /// Status of the eve tokens of a main and its alts
+    ///
+    /// # Params
+    ///
+    /// `uid` -> Id of the main
+    ///
+    /// # Returns
+    ///
+    /// Token status of every character
+    ///
+    pub async fn tokens(
+        &self,
+        uid: CharacterId,
+    ) -> Result<Vec<TokenStatus>, EveServerError> {
+        let user = self.main(uid).await?;
+        let revoked = self.revoked.read().await;
+        let now = Utc::now().timestamp() as u64;
+
+        let result = std::iter::once(&user)
+            .chain(user.aliase.iter())
+            .map(|x| {
+                let expire = EveOAuthPayload::decode(&x.access_token)
+                    .ok()
+                    .map(|x| x.exp);
+                TokenStatus {
+                    user_id: x.user_id,
+                    state:   TokenState::of(revoked.contains(&x.user_id), expire, now),
+                    expire:  expire.map(|x| x * 1_000),
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(result)
+    }
+
+    /// Requests a new access token for a main or alt.
+    ///
+    /// If the eve auth server rejects the refresh token, the character is
+    /// marked as revoked until the next successful refresh.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting admin
+    /// `uid`   -> Id of the main or alt
+    ///
+    pub async fn refresh(
+        &self,
+        token: &str,
+        uid:   CharacterId,
+    ) -> Result<(), EveServerError> {
+        let admin = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        match self.eve_auth.refresh_character(admin.user_id, uid).await {
+            Ok(_)  => {
+                self.revoked.write().await.remove(&uid);
+                Ok(())
+            },
+            Err(EveServerError::EveConnectError(e)) => {
+                log::warn!("Refreshing the token of {} failed {:?}", uid, e);
+                self.revoked.write().await.insert(uid);
+                Err(EveServerError::EveConnectError(e))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Shows when the data of a main and its alts was synced the last time
+    ///
+    /// # Params
+    ///
+    /// `uid` -> Id of the main
+    ///
+    pub async fn sync_status(
+        &self,
+        uid: CharacterId,
+    ) -> Result<Vec<CharacterSync>, EveServerError> {
+        let user = self.main(uid).await?;
+        self.character.sync_status_of(&user).await
+    }
+
+    /// Deletes a main, its alts and all their data, see
+    /// [DeleteUserService]
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting admin
+    /// `uid`   -> Id of the main
+    ///
+    pub async fn delete(
+        &self,
+        token: &str,
+        uid:   CharacterId,
+    ) -> Result<AuditEntry, EveServerError> {
+        let admin = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let entry = self.delete_user.delete(uid, admin.user_id).await?;
+
+        let mut revoked = self.revoked.write().await;
+        revoked.remove(&entry.user_id);
+        revoked.retain(|x| !entry.alts.contains(x));
+        Ok(entry)
+    }
+
+    async fn main(
+        &self,
+        uid: CharacterId,
+    ) -> Result<UserEntry, EveServerError> {
+        self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, UserEntry>(CacheName::User, uid)
+            .await?
+            .ok_or(EveServerError::UserNotFound)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUser {
+    pub user_id:    CharacterId,
+    pub corp_id:    CorporationId,
+    pub alts:       Vec<CharacterId>,
+    /// Timestamp in milliseconds, None if the main never logged in since
+    /// the login was recorded
+    pub last_login: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenStatus {
+    pub user_id: CharacterId,
+    pub state:   TokenState,
+    /// Timestamp in milliseconds when the access token expires
+    pub expire:  Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenState {
+    Valid,
+    /// The access token is expired and is refreshed with the next request
+    Expired,
+    /// The refresh token was rejected, the character must login again
+    Revoked,
+}
+
+impl TokenState {
+    /// Determines the state of a token
+    ///
+    /// # Params
+    ///
+    /// `revoked` -> The last refresh was rejected
+    /// `expire`  -> Unix timestamp in seconds of the access token
+    /// `now`     -> Unix timestamp in seconds
+    ///
+    fn of(revoked: bool, expire: Option<u64>, now: u64) -> Self {
+        match expire {
+            _ if revoked      => Self::Revoked,
+            Some(x) if x > now => Self::Valid,
+            _                  => Self::Expired,
+        }
+    }
+}
+
+#[cfg(test)]
+mod admin_tests {
+    use super::*;
+
+    #[test]
+    fn token_state() {
+        assert_eq!(TokenState::of(false, Some(200), 100), TokenState::Valid);
+        assert_eq!(TokenState::of(false, Some(100), 200), TokenState::Expired);
+        assert_eq!(TokenState::of(false, None, 200), TokenState::Expired);
+        assert_eq!(TokenState::of(true, Some(200), 100), TokenState::Revoked);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::invalidation::InvalidationService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CorporationAssetEntry, CorporationStructureEntry, MarketPriceEntry};
+use caph_eve_data_wrapper::{AllianceId, CorporationId, EveDataWrapper, ItemId, SolarSystemId, StructureId, TypeId};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Aggregates the data of all corporations in an alliance
+#[derive(Clone)]
+pub struct AllianceService {
+    pool:         ConnectionPool,
+    eve_auth:     EveAuthService,
+    eve_data:     EveDataWrapper,
+    invalidation: InvalidationService,
+    reports:      Arc<RwLock<HashMap<AllianceId, (Instant, AllianceReport)>>>,
+}
+
+impl AllianceService {
+    /// Time until a report is generated again
+    const CACHE_TIME:    Duration = Duration::from_secs(10 * 60);
+    /// Structures with less fuel than this are marked as low on fuel
+    const LOW_FUEL_TIME: u64      = 7 * 24 * 60 * 60 * 1_000;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:         ConnectionPool,
+        eve_auth:     EveAuthService,
+        eve_data:     EveDataWrapper,
+        invalidation: InvalidationService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            eve_data,
+            invalidation,
+            reports: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Drops all cached reports as soon as the database reports a change of
+    /// the data they are based on.
+    ///
+    /// This function is blocking
+    pub async fn watch(&self) {
+        let mut changes = self.invalidation.subscribe();
+
+        loop {
+            InvalidationService::wait(
+                &mut changes,
+                vec![
+                    CacheName::CorporationAsset,
+                    CacheName::CorporationStructure,
+                    CacheName::MarketPrice,
+                ]
+            ).await;
+            self.reports.write().await.clear();
+        }
+    }
+
+    /// Summary of the assets and structures of every member corporation.
+    ///
+    /// Only corporations that are still members of the alliance and of which
+    /// a director has registered are part of the report. The report is
+    /// cached for a couple of minutes.
+    ///
+    /// # Params
+    ///
+    /// `aid`   -> Id of the alliance
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// Report containing every corporation, fails if neither the main nor
+    /// one of its alts is a member of the executor corporation
+    ///
+    pub async fn report(
+        &self,
+        aid:   AllianceId,
+        token: &str,
+    ) -> Result<AllianceReport, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut corp_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.corp_id)
+            .collect::<Vec<_>>();
+        corp_ids.push(user.corp_id);
+
+        let alliance = self
+            .eve_data
+            .character()
+            .await?
+            .alliance(aid)
+            .await?;
+        let is_executor = alliance
+            .executor_corporation_id
+            .map(|x| corp_ids.contains(&x))
+            .unwrap_or_default();
+        if !is_executor {
+            return Err(EveServerError::MissingPermission);
+        }
+
+        if let Some((created, report)) = self.reports.read().await.get(&aid) {
+            if created.elapsed() < Self::CACHE_TIME {
+                return Ok(report.clone());
+            }
+        }
+
+        let report = self.generate(aid, alliance.corporations).await?;
+        self
+            .reports
+            .write()
+            .await
+            .insert(aid, (Instant::now(), report.clone()));
+        Ok(report)
+    }
+
+    async fn generate(
+        &self,
+        aid:          AllianceId,
+        corporations: Vec<CorporationId>,
+    ) -> Result<AllianceReport, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CorporationAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CorporationAssetEntry>(CacheName::CorporationAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.alliance_id == Some(aid))
+            .filter(|x| corporations.contains(&x.corporation_id))
+            .collect::<Vec<_>>();
+
+        let keys = con
+            .keys::<_, StructureId>(CacheName::CorporationStructure)
+            .await?;
+        let structures = con
+            .mget::<_, _, CorporationStructureEntry>(CacheName::CorporationStructure, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.alliance_id == Some(aid))
+            .filter(|x| corporations.contains(&x.corporation_id))
+            .collect::<Vec<_>>();
+
+        let mut type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        let mut result = HashMap::new();
+        for asset in assets {
+            let entry = result
+                .entry(asset.corporation_id)
+                .or_insert_with(|| AllianceCorporation::new(asset.corporation_id));
+            entry.asset_count += 1;
+            entry.asset_value += prices
+                .get(&asset.type_id)
+                .copied()
+                .unwrap_or_default() * asset.quantity as f32;
+        }
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let structures = structures
+            .into_iter()
+            .map(|x| {
+                let low_fuel = x
+                    .fuel_expires
+                    .map(|x| x.saturating_sub(now) < Self::LOW_FUEL_TIME)
+                    .unwrap_or_default();
+
+                let entry = result
+                    .entry(x.corporation_id)
+                    .or_insert_with(|| AllianceCorporation::new(x.corporation_id));
+                entry.structure_count += 1;
+                if low_fuel {
+                    entry.structures_low_fuel += 1;
+                }
+
+                AllianceStructure {
+                    structure_id:   x.structure_id,
+                    corporation_id: x.corporation_id,
+                    system_id:      x.system_id,
+                    type_id:        x.type_id,
+                    state:          x.state,
+                    fuel_expires:   x.fuel_expires,
+                    low_fuel,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut corporations = result
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect::<Vec<_>>();
+        corporations.sort_by_key(|x| x.corporation_id);
+
+        Ok(AllianceReport {
+            asset_value: corporations.iter().map(|x| x.asset_value).sum(),
+            corporations,
+            structures,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AllianceReport {
+    /// Combined value of the assets of all corporations
+    pub asset_value:  f32,
+    pub corporations: Vec<AllianceCorporation>,
+    pub structures:   Vec<AllianceStructure>,
+}
+
+/// Summary of a single member corporation
+#[derive(Clone, Debug, Serialize)]
+pub struct AllianceCorporation {
+    pub corporation_id:      CorporationId,
+    pub asset_count:         u32,
+    pub asset_value:         f32,
+    pub structure_count:     u32,
+    /// Number of structures that run out of fuel within the next week
+    pub structures_low_fuel: u32,
+}
+
+impl AllianceCorporation {
+    fn new(corporation_id: CorporationId) -> Self {
+        Self {
+            corporation_id,
+            asset_count:         0,
+            asset_value:         0f32,
+            structure_count:     0,
+            structures_low_fuel: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AllianceStructure {
+    pub structure_id:   StructureId,
+    pub corporation_id: CorporationId,
+    pub system_id:      SolarSystemId,
+    pub type_id:        TypeId,
+    pub state:          String,
+    /// Timestamp in milliseconds when the fuel runs out
+    pub fuel_expires:   Option<u64>,
+    pub low_fuel:       bool,
+}
@@ -0,0 +1,276 @@
+use crate::error::EveServerError;
+use crate::npc_price::NpcPrice;
+use crate::pricing::{PriceQuery, PriceService};
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, ItemEntry};
+use caph_eve_data_wrapper::{EveDataWrapper, TypeId};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Values pasted items, similar to Janice or Evepraisal
+#[derive(Clone)]
+pub struct AppraisalService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+    price:    PriceService,
+}
+
+impl AppraisalService {
+    /// Maximum number of lines that are parsed
+    const MAX_LINES: usize = 1_000;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+        price:    PriceService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+            price,
+        }
+    }
+
+    /// Parses the pasted text and values all items with the market price of
+    /// the selected price source.
+    ///
+    /// Understands inventory and contract pastes (tab separated with the
+    /// quantity in the second column), cargo scans (`1000 Tritanium`) and
+    /// EFT style quantities (`Hobgoblin II x5`). Lines with only a name
+    /// count as a single item.
+    ///
+    /// Items that are sold or bought by NPCs, like skillbooks or blue loot,
+    /// are valued with their NPC price instead, see [NpcPrice].
+    ///
+    /// # Params
+    ///
+    /// `paste` -> Text from the clipboard
+    /// `query` -> Price source, defaults to the average price
+    ///
+    /// # Returns
+    ///
+    /// All resolved items with their volume and value, and all names that
+    /// could not be resolved
+    ///
+    pub async fn appraise(
+        &self,
+        paste: String,
+        query: PriceQuery,
+    ) -> Result<Appraisal, EveServerError> {
+        let source = query.source()?;
+        let lines = Paste::parse(&paste);
+        if lines.len() > Self::MAX_LINES {
+            return Err(EveServerError::TooManyIds);
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Item)
+            .await?;
+        let items = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.name.to_lowercase(), x))
+            .collect::<HashMap<_, _>>();
+
+        let mut quantities: HashMap<TypeId, (&ItemEntry, u64)> = HashMap::new();
+        let mut unknown = Vec::new();
+        for (name, quantity) in lines {
+            match items.get(&name.to_lowercase()) {
+                Some(x) => quantities.entry(x.item_id).or_insert((x, 0)).1 += quantity,
+                None    => unknown.push(name),
+            }
+        }
+        unknown.sort();
+        unknown.dedup();
+
+        let type_ids = quantities
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        let prices = self
+            .price
+            .prices(source, type_ids.clone())
+            .await?;
+        let types = self.eve_data.types().await?;
+
+        let mut entries = type_ids
+            .into_iter()
+            .map(|tid| {
+                let (item, quantity) = quantities[&tid];
+                let npc_price = types
+                    .type_by_id(tid)
+                    .and_then(|x| {
+                        NpcPrice::of(item.category_id, item.group_id, x)
+                            .map(|kind| (kind, NpcPrice::price(x)))
+                    });
+                let price = match npc_price {
+                    Some((_, x)) => x,
+                    None         => prices.get(&tid).copied().unwrap_or_default(),
+                };
+                AppraisalItem {
+                    type_id:   tid,
+                    name:      item.name.clone(),
+                    quantity,
+                    volume:    item.volume * quantity as f32,
+                    price,
+                    value:     price * quantity as f32,
+                    npc_price: npc_price.map(|(x, _)| x),
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| {
+            b.value
+                .partial_cmp(&a.value)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(Appraisal {
+            total_value:  entries.iter().map(|x| x.value).sum(),
+            total_volume: entries.iter().map(|x| x.volume).sum(),
+            items:        entries,
+            unknown,
+        })
+    }
+}
+
+/// Parser for the different clipboard formats of the game
+struct Paste;
+
+impl Paste {
+    /// Parses every line into name and quantity, without resolving names
+    fn parse(paste: &str) -> Vec<(String, u64)> {
+        paste
+            .lines()
+            .filter_map(Self::line)
+            .collect::<Vec<_>>()
+    }
+
+    fn line(line: &str) -> Option<(String, u64)> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        // inventory and contract pastes, the quantity is empty for
+        // unstackable items
+        if line.contains('\t') {
+            let mut columns = line.split('\t');
+            let name = columns.next()?.trim();
+            let quantity = columns
+                .next()
+                .and_then(Self::quantity)
+                .unwrap_or(1);
+            return Some((name.to_string(), quantity));
+        }
+
+        // EFT and fitting window style, `Hobgoblin II x5`
+        if let Some((name, quantity)) = line.rsplit_once(" x") {
+            if let Some(quantity) = Self::quantity(quantity) {
+                return Some((name.trim().to_string(), quantity));
+            }
+        }
+
+        // cargo scan, `1000 Tritanium`
+        if let Some((quantity, name)) = line.split_once(' ') {
+            if let Some(quantity) = Self::quantity(quantity) {
+                return Some((name.trim().to_string(), quantity));
+            }
+        }
+
+        Some((line.to_string(), 1))
+    }
+
+    /// Parses quantities with thousand separators like `1,000` or `1.000`
+    fn quantity(x: &str) -> Option<u64> {
+        let x = x
+            .trim()
+            .chars()
+            .filter(|c| !matches!(c, ',' | '.' | '\''))
+            .collect::<String>();
+        if x.is_empty() || !x.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        x.parse::<u64>().ok()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Appraisal {
+    /// Resolved items, the most valuable first
+    pub items:        Vec<AppraisalItem>,
+    /// Names that are not known
+    pub unknown:      Vec<String>,
+    pub total_value:  f32,
+    /// Volume in m3
+    pub total_volume: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppraisalItem {
+    pub type_id:   TypeId,
+    pub name:      String,
+    pub quantity:  u64,
+    /// Volume of the whole stack in m3
+    pub volume:    f32,
+    /// Market or NPC price of a single item
+    pub price:     f32,
+    /// Value of the whole stack
+    pub value:     f32,
+    /// Set if the price is the NPC price and not the market price
+    pub npc_price: Option<NpcPrice>,
+}
+
+#[cfg(test)]
+mod paste_tests {
+    use super::*;
+
+    #[test]
+    fn inventory() {
+        let paste = "Tritanium\t1,000\tMineral\t\t\t10 m3\nRifter\t\tFrigate\t\t\t27.289 m3";
+        assert_eq!(
+            Paste::parse(paste),
+            vec![
+                ("Tritanium".into(), 1000),
+                ("Rifter".into(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn cargo_scan() {
+        let paste = "1000 Tritanium\n5 Hobgoblin II\n";
+        assert_eq!(
+            Paste::parse(paste),
+            vec![
+                ("Tritanium".into(), 1000),
+                ("Hobgoblin II".into(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn eft_quantity() {
+        let paste = "Hobgoblin II x5\n\nDamage Control II\n";
+        assert_eq!(
+            Paste::parse(paste),
+            vec![
+                ("Hobgoblin II".into(), 5),
+                ("Damage Control II".into(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn name_with_number() {
+        // names that start with a number are not a cargo scan
+        assert_eq!(
+            Paste::parse("200mm AutoCannon II"),
+            vec![("200mm AutoCannon II".into(), 1)]
+        );
+    }
+}
@@ -1,21 +1,224 @@
-use crate::eve::EveAuthService;
+use crate::error::EveServerError;
+use crate::pricing::{PriceService, PriceSource};
 
 use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, ItemEntry};
+use caph_eve_data_wrapper::{CategoryId, EveDataWrapper, GroupId, LocationId, MarketGroupId, TypeId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+/// Filters the assets of characters and corporations, so that clients do
+/// not need to load all assets at once
 #[derive(Clone)]
 pub struct AssetService {
-    pool:      ConnectionPool,
-    eve_auth:  EveAuthService,
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+    price:    PriceService,
 }
 
 impl AssetService {
+    /// Creates a new instance
     pub fn new(
-        pool:      ConnectionPool,
-        eve_auth:  EveAuthService,
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+        price:    PriceService,
     ) -> Self {
         Self {
             pool,
-            eve_auth,
+            eve_data,
+            price,
         }
     }
+
+    /// Applies the filters of the query
+    ///
+    /// # Params
+    ///
+    /// `assets` -> All assets that can be returned
+    /// `stack`  -> Item, location and quantity of an asset
+    /// `query`  -> Filters
+    ///
+    /// # Returns
+    ///
+    /// The matching assets together with their name and value, sorted by
+    /// name
+    ///
+    pub async fn query<T>(
+        &self,
+        assets: Vec<T>,
+        stack:  impl Fn(&T) -> (TypeId, LocationId, u32),
+        query:  AssetQuery,
+    ) -> Result<Vec<AssetStack<T>>, EveServerError> {
+        let mut type_ids = assets
+            .iter()
+            .map(|x| stack(x).0)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+
+        let items = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids.clone())
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.item_id, x))
+            .collect::<HashMap<_, _>>();
+        let prices = self
+            .price
+            .prices(PriceSource::Average, type_ids.clone())
+            .await?;
+
+        let mut market_groups = HashMap::new();
+        if query.market_group_id.is_some() {
+            let types = self.eve_data.types().await?;
+            let groups = self.eve_data.market_groups().await?;
+            for tid in type_ids {
+                if let Some(gid) = types.type_by_id(tid).and_then(|x| x.market_group_id) {
+                    let mut ids = groups.parents(gid);
+                    ids.push(gid);
+                    market_groups.insert(tid, ids);
+                }
+            }
+        }
+
+        let stacks = assets
+            .into_iter()
+            .map(|asset| {
+                let (type_id, location_id, quantity) = stack(&asset);
+                let item = items.get(&type_id);
+                AssetStack {
+                    name:          item.map(|x| x.name.clone()).unwrap_or_default(),
+                    value:         prices.get(&type_id).copied().unwrap_or_default() * quantity as f32,
+                    category_id:   item.map(|x| x.category_id),
+                    group_id:      item.map(|x| x.group_id),
+                    market_groups: market_groups.get(&type_id).cloned().unwrap_or_default(),
+                    location_id,
+                    quantity,
+                    asset,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(query.apply(stacks))
+    }
+}
+
+/// Filters for assets, all filters that are set must match
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct AssetQuery {
+    pub category_id:     Option<CategoryId>,
+    pub group_id:        Option<GroupId>,
+    /// Also matches all items in the sub groups
+    pub market_group_id: Option<MarketGroupId>,
+    /// Case insensitive part of the item name
+    pub name:            Option<String>,
+    pub location_id:     Option<LocationId>,
+    pub min_quantity:    Option<u32>,
+    /// Minimum value of the whole stack with the average price
+    pub min_value:       Option<f32>,
+}
+
+impl AssetQuery {
+    fn matches<T>(&self, stack: &AssetStack<T>) -> bool {
+        let name = self.name.as_ref().map(|x| x.to_lowercase());
+
+        self.category_id.map(|x| stack.category_id == Some(x)).unwrap_or(true) &&
+        self.group_id.map(|x| stack.group_id == Some(x)).unwrap_or(true) &&
+        self.market_group_id.map(|x| stack.market_groups.contains(&x)).unwrap_or(true) &&
+        name.map(|x| stack.name.to_lowercase().contains(&x)).unwrap_or(true) &&
+        self.location_id.map(|x| stack.location_id == x).unwrap_or(true) &&
+        self.min_quantity.map(|x| stack.quantity >= x).unwrap_or(true) &&
+        self.min_value.map(|x| stack.value >= x).unwrap_or(true)
+    }
+
+    fn apply<T>(&self, stacks: Vec<AssetStack<T>>) -> Vec<AssetStack<T>> {
+        let mut stacks = stacks
+            .into_iter()
+            .filter(|x| self.matches(x))
+            .collect::<Vec<_>>();
+        stacks.sort_by(|a, b| a.name.cmp(&b.name));
+        stacks
+    }
+}
+
+/// Asset together with its name and value
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetStack<T> {
+    #[serde(flatten)]
+    pub asset:     T,
+    pub name:      String,
+    /// Value of the whole stack with the average price
+    pub value:     f32,
+    #[serde(skip)]
+    category_id:   Option<CategoryId>,
+    #[serde(skip)]
+    group_id:      Option<GroupId>,
+    /// Market group of the item and all its parents
+    #[serde(skip)]
+    market_groups: Vec<MarketGroupId>,
+    #[serde(skip)]
+    location_id:   LocationId,
+    #[serde(skip)]
+    quantity:      u32,
+}
+
+#[cfg(test)]
+mod asset_tests {
+    use super::*;
+
+    fn stack(name: &str, quantity: u32, value: f32) -> AssetStack<()> {
+        AssetStack {
+            asset:         (),
+            name:          name.into(),
+            value,
+            category_id:   Some(4.into()),
+            group_id:      Some(18.into()),
+            market_groups: vec![1857.into(), 1031.into()],
+            location_id:   60003760.into(),
+            quantity,
+        }
+    }
+
+    fn stacks() -> Vec<AssetStack<()>> {
+        vec![
+            stack("Tritanium", 1000, 5000.0),
+            stack("Pyerite", 500, 5000.0),
+            stack("Mexallon", 10, 500.0),
+        ]
+    }
+
+    #[test]
+    fn filters() {
+        let query = AssetQuery {
+            name: Some("ITE".into()),
+            ..AssetQuery::default()
+        };
+        assert_eq!(query.apply(stacks()).len(), 1);
+
+        let query = AssetQuery {
+            market_group_id: Some(1857.into()),
+            min_value:       Some(1000.0),
+            ..AssetQuery::default()
+        };
+        assert_eq!(query.apply(stacks()).len(), 2);
+
+        let query = AssetQuery {
+            group_id: Some(19.into()),
+            ..AssetQuery::default()
+        };
+        assert_eq!(query.apply(stacks()).len(), 0);
+    }
+
+    #[test]
+    fn sorted_by_name() {
+        let names = AssetQuery::default()
+            .apply(stacks())
+            .into_iter()
+            .map(|x| x.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Mexallon", "Pyerite", "Tritanium"]);
+    }
 }
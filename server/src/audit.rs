@@ -0,0 +1,203 @@
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{AuditLogEntry, CacheName};
+use caph_eve_data_wrapper::CharacterId;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Records sensitive operations, like logins, token refreshes, admin
+/// actions and exports of personal data.
+///
+/// The log is append only, entries are removed after two years.
+#[derive(Clone)]
+pub struct AuditService {
+    pool: ConnectionPool,
+    /// Timestamp of the last entry, the cache only keeps entries that are
+    /// newer than the last one of the same user
+    last: Arc<Mutex<u64>>,
+}
+
+impl AuditService {
+    /// Creates a new instance
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self {
+            pool,
+            last: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Adds an entry to the audit log
+    ///
+    /// # Params
+    ///
+    /// `user_id` -> Acting main
+    /// `action`  -> Operation that was done
+    /// `target`  -> Character the operation was done for, if it was not the
+    ///              acting main
+    /// `details` -> Additional information about the operation
+    ///
+    pub async fn record(
+        &self,
+        user_id: CharacterId,
+        action:  AuditAction,
+        target:  Option<CharacterId>,
+        details: impl Into<String>,
+    ) -> Result<(), EveServerError> {
+        let timestamp = {
+            let mut last = self.last.lock().await;
+            *last = Self::next_timestamp(*last, Utc::now().timestamp_millis() as u64);
+            *last
+        };
+
+        let entry = AuditLogEntry::new(
+            timestamp,
+            action.as_str().into(),
+            target,
+            details.into(),
+        );
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::AuditLog, user_id, vec![entry])
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the audit log, the route must be restricted to admins
+    ///
+    /// # Params
+    ///
+    /// `query` -> Filter by acting main and time range
+    ///
+    /// # Returns
+    ///
+    /// All matching entries, newest first
+    ///
+    pub async fn query(
+        &self,
+        query: AuditQuery,
+    ) -> Result<Vec<AuditRecord>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let user_ids = if let Some(x) = query.user_id {
+            vec![x]
+        } else {
+            con
+                .keys::<_, CharacterId>(CacheName::AuditLog)
+                .await?
+        };
+        let entries = con
+            .mget::<_, _, Vec<AuditLogEntry>>(CacheName::AuditLog, user_ids.clone())
+            .await?;
+
+        let mut result = user_ids
+            .into_iter()
+            .zip(entries)
+            .flat_map(|(user_id, entries)| {
+                entries
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |x| (user_id, x))
+            })
+            .filter(|(_, x)| query.contains(x.timestamp))
+            .map(|(user_id, x)| AuditRecord {
+                user_id,
+                timestamp: x.timestamp,
+                action:    x.action,
+                target:    x.target,
+                details:   x.details,
+            })
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(result)
+    }
+
+    /// Two entries in the same millisecond would be dropped by the cache, so
+    /// every timestamp is at least one millisecond after the last one
+    fn next_timestamp(last: u64, now: u64) -> u64 {
+        now.max(last + 1)
+    }
+}
+
+/// Operations that are recorded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    Login,
+    AddAlt,
+    TokenRefresh,
+    Deauthorize,
+    RoleChange,
+    DeleteUser,
+    Export,
+}
+
+impl AuditAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login        => "login",
+            Self::AddAlt       => "add_alt",
+            Self::TokenRefresh => "token_refresh",
+            Self::Deauthorize  => "deauthorize",
+            Self::RoleChange   => "role_change",
+            Self::DeleteUser   => "delete_user",
+            Self::Export       => "export",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct AuditQuery {
+    /// Only entries of this main
+    pub user_id: Option<CharacterId>,
+    /// Timestamp in milliseconds
+    pub start:   Option<u64>,
+    /// Timestamp in milliseconds
+    pub end:     Option<u64>,
+}
+
+impl AuditQuery {
+    fn contains(&self, timestamp: u64) -> bool {
+        self.start.map(|x| timestamp >= x).unwrap_or(true) &&
+        self.end.map(|x| timestamp <= x).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditRecord {
+    /// Acting main
+    pub user_id:   CharacterId,
+    /// Timestamp in milliseconds
+    pub timestamp: u64,
+    pub action:    String,
+    pub target:    Option<CharacterId>,
+    pub details:   String,
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use super::*;
+
+    #[test]
+    fn unique_timestamps() {
+        assert_eq!(AuditService::next_timestamp(0, 100), 100);
+        assert_eq!(AuditService::next_timestamp(100, 100), 101);
+        assert_eq!(AuditService::next_timestamp(105, 100), 106);
+    }
+
+    #[test]
+    fn time_range() {
+        let query = AuditQuery {
+            user_id: None,
+            start:   Some(10),
+            end:     Some(20),
+        };
+        assert!(query.contains(10));
+        assert!(query.contains(20));
+        assert!(!query.contains(21));
+        assert!(AuditQuery::default().contains(0));
+    }
+}
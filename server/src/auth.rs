@@ -0,0 +1,236 @@
+use crate::error::EveServerError;
+use crate::scope::ScopePreset;
+
+use caph_eve_data_wrapper::{EveClient, EveConnectError, EveOAuthUser, Url};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Settings of the eve login
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SsoConfig {
+    /// Url the eve auth server redirects to after the login, if not set
+    /// `EVE_REDIRECT_URL` is used
+    pub callback_url: Option<String>,
+    /// Scopes that are requested if the login does not select presets,
+    /// defaults to the scopes of all presets
+    pub scopes:       Vec<String>,
+}
+
+impl Default for SsoConfig {
+    fn default() -> Self {
+        Self {
+            callback_url: None,
+            scopes:       ScopePreset::resolve(ScopePreset::ALL),
+        }
+    }
+}
+
+/// OAuth2 authorization code flow with PKCE against the eve auth server.
+///
+/// Every login gets a random state and code verifier, the state is send to
+/// the eve auth server together with the challenge of the verifier. When the
+/// eve auth server redirects back, the state is used to find the login and
+/// the code is exchanged together with the verifier.
+///
+/// `T` is stored with every login and returned after the token exchange.
+///
+/// https://docs.esi.evetech.net/docs/sso/web_based_sso_flow.html
+#[derive(Clone)]
+pub struct SsoService<T> {
+    config:  SsoConfig,
+    pending: Arc<Mutex<HashMap<String, PendingLogin<T>>>>,
+}
+
+impl<T> SsoService<T> {
+    const ENV_REDIRECT: &'static str = "EVE_REDIRECT_URL";
+
+    /// Milliseconds until a login that was started must be finished
+    const MAX_PENDING_AGE: u64 = 10 * 60 * 1_000;
+    /// Length of the state and the code verifier, PKCE requires between 43
+    /// and 128 characters
+    const KEY_LENGTH:      usize = 64;
+
+    /// Creates a new instance
+    pub fn new(config: SsoConfig) -> Self {
+        Self {
+            config,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Starts a new login
+    ///
+    /// # Params
+    ///
+    /// `data`   -> Returned together with the character after the login
+    /// `scopes` -> Scopes to request, if [None] the scopes of the config are
+    ///             requested
+    ///
+    /// # Returns
+    ///
+    /// Uri to the eve auth server
+    ///
+    pub async fn start(
+        &self,
+        data:   T,
+        scopes: Option<Vec<String>>,
+    ) -> Result<Url, EveServerError> {
+        let state = Self::generate_key();
+        let verifier = Self::generate_key();
+        let scopes = scopes.unwrap_or_else(|| self.config.scopes.clone());
+        let url = self.authorize_url(&state, &Self::challenge(&verifier), &scopes)?;
+
+        let now = Self::now();
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, x| !x.is_expired(now));
+        pending.insert(state, PendingLogin {
+            verifier,
+            started: now,
+            data,
+        });
+        Ok(url)
+    }
+
+    /// Finishes a login after the eve auth server redirected back
+    ///
+    /// # Params
+    ///
+    /// `code`  -> Code from the eve auth server
+    /// `state` -> State that was created when starting the login
+    ///
+    /// # Returns
+    ///
+    /// The data given when starting the login and the character with its
+    /// tokens, [EveServerError::InvalidUser] if the state is unknown or
+    /// expired
+    ///
+    pub async fn callback(
+        &self,
+        code:  &str,
+        state: &str,
+    ) -> Result<(T, EveOAuthUser), EveServerError> {
+        // A state can only be used once, so it is removed in any case
+        let login = self
+            .pending
+            .lock()
+            .await
+            .remove(state)
+            .filter(|x| !x.is_expired(Self::now()))
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let user = EveClient::retrieve_authorization_token(code, &login.verifier).await?;
+        Ok((login.data, user))
+    }
+
+    fn authorize_url(
+        &self,
+        state:     &str,
+        challenge: &str,
+        scopes:    &[String],
+    ) -> Result<Url, EveServerError> {
+        let callback_url = match self.config.callback_url.clone() {
+            Some(x) => x,
+            None    => std::env::var(Self::ENV_REDIRECT)
+                .map_err(|_| EveConnectError::EnvError(format!("ENV var {} not present", Self::ENV_REDIRECT)))?,
+        };
+
+        let mut url = Url::parse(EveClient::EVE_LOGIN_URL)
+            .map_err(|e| EveServerError::InvalidConfig(e.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("redirect_uri", &callback_url)
+            .append_pair("client_id", &EveClient::client_id()?)
+            .append_pair("scope", &scopes.join(" "))
+            .append_pair("code_challenge", challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", state);
+        Ok(url)
+    }
+
+    /// Url safe base64 of the SHA-256 hash of the verifier, without padding
+    fn challenge(verifier: &str) -> String {
+        base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn generate_key() -> String {
+        ChaCha20Rng::from_entropy()
+            .sample_iter(&Alphanumeric)
+            .take(Self::KEY_LENGTH)
+            .map(char::from)
+            .collect::<String>()
+    }
+
+    fn now() -> u64 {
+        Utc::now().timestamp_millis() as u64
+    }
+}
+
+/// Login that was started but not finished yet
+struct PendingLogin<T> {
+    verifier: String,
+    /// Timestamp in milliseconds
+    started:  u64,
+    data:     T,
+}
+
+impl<T> PendingLogin<T> {
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.started) > SsoService::<T>::MAX_PENDING_AGE
+    }
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge() {
+        // Example from RFC 7636 appendix B
+        let challenge = SsoService::<()>::challenge("dBjftJeZ4CVP-1mB9ktkgjNNsOHrEl3j2bFAigFlNkk");
+        assert_eq!(challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+    }
+
+    #[tokio::test]
+    async fn authorize_url() {
+        std::env::set_var("EVE_CLIENT_ID", "client");
+        std::env::set_var("EVE_SECRET_KEY", "secret");
+
+        let sso = SsoService::<()>::new(SsoConfig {
+            callback_url: Some("https://example.com/callback".into()),
+            scopes:       vec!["publicData".into(), "esi-skills.read_skills.v1".into()],
+        });
+        let url = sso.start((), None).await.unwrap();
+        let query = url
+            .query_pairs()
+            .into_owned()
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(query["redirect_uri"], "https://example.com/callback");
+        assert_eq!(query["scope"], "publicData esi-skills.read_skills.v1");
+        assert_eq!(query["code_challenge_method"], "S256");
+        assert_eq!(query["state"].len(), 64);
+        assert!(sso.pending.lock().await.contains_key(&query["state"]));
+
+        let url = sso.start((), Some(vec!["publicData".into()])).await.unwrap();
+        assert!(url.query_pairs().any(|(k, v)| k == "scope" && v == "publicData"));
+    }
+
+    #[test]
+    fn pending_expires() {
+        let login = PendingLogin {
+            verifier: String::new(),
+            started:  0,
+            data:     (),
+        };
+        assert!(!login.is_expired(SsoService::<()>::MAX_PENDING_AGE));
+        assert!(login.is_expired(SsoService::<()>::MAX_PENDING_AGE + 1));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{error::EveServerError, eve::EveAuthService, industry::IndustryService};
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{Activity, BlueprintEntry, CacheName, CorporationBlueprintEntry, IndustryCostEntry, MarketPriceEntry, Material, SchematicEntry};
+use caph_db_v2::{Activity, BlueprintEntry, BlueprintHistoryEntry, CacheName, CorporationBlueprintEntry, IndustryCostEntry, InsurancePriceEntry, MarketPriceEntry, Material, SchematicEntry, SystemRegionEntry};
 use caph_eve_data_wrapper::{ItemId, SolarSystemId, TypeId};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
@@ -69,6 +69,31 @@ impl BlueprintService {
             .map_err(Into::into)
     }
 
+    /// Gets the previous versions of a blueprint, kept from older SDE
+    /// imports
+    ///
+    /// # Params
+    ///
+    /// * `bpid` -> TypeId of the blueprint
+    ///
+    /// # Returns
+    ///
+    /// All kept versions, newest first. Empty if the blueprint never changed
+    ///
+    pub async fn history(
+        &self,
+        bpid: TypeId,
+    ) -> Result<Vec<BlueprintHistoryEntry>, EveServerError> {
+        self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, Vec<BlueprintHistoryEntry>>(CacheName::BlueprintHistory, bpid)
+            .await
+            .map(|x| x.unwrap_or_default())
+            .map_err(Into::into)
+    }
+
     /// Gets a list of blueprints by id
     ///
     /// # Params
@@ -124,6 +149,23 @@ impl BlueprintService {
             .flatten()
             .collect::<Vec<_>>();
 
+        let security = con
+            .get::<_, _, SystemRegionEntry>(CacheName::SystemRegion, sid)
+            .await?
+            .map(|x| x.security)
+            .unwrap_or(1f32);
+        let facility = self.industry.facility_model(sid, security)?;
+        let system_cost_index_perc = con
+            .get::<_, _, IndustryCostEntry>(CacheName::IndustryCost, sid)
+            .await?
+            .and_then(|x| x
+                .cost_indices
+                .into_iter()
+                .find(|x| x.activity == "manufacturing")
+                .map(|x| x.cost_index)
+            )
+            .unwrap_or_default();
+
         let mut bp_costs = Vec::new();
         for bp in bps {
             let runs = bpids.get(&bp.bid).unwrap_or(&1u32);
@@ -142,6 +184,8 @@ impl BlueprintService {
                 .map(|x| (x.type_id, x))
                 .collect::<HashMap<_, _>>();
 
+            // The job fee is based on the materials without any bonus
+            let mut estimated_item_value = 0f32;
             let materials = materials
                 .into_iter()
                 .map(|x| {
@@ -149,7 +193,8 @@ impl BlueprintService {
                         .get(&x.mid)
                         .map(|x| x.adjusted_price)
                         .unwrap_or(0f32);
-                    let total = runs * x.quantity;
+                    estimated_item_value += (runs * x.quantity) as f32 * price;
+                    let total = facility.material_quantity(x.quantity, *runs);
 
                     MaterialCost {
                         mid:    x.mid,
@@ -159,41 +204,27 @@ impl BlueprintService {
                 })
                 .collect::<Vec<_>>();
 
-            // TODO: replace with database -> Requires database and frontend
-            let facility_tax_perc = self
-                .industry
-                .stations()
-                .unwrap()
-                .iter()
-                .find(|x| x.id == *sid)
-                .unwrap()
-                .engineering
-                .manufacturing;
+            let facility_tax_perc   = facility.tax;
+            let facility_bonus_perc = facility.job_cost_bonus() * 100f32;
+            let material_bonus_perc = facility.material_bonus() * 100f32;
+            let time_bonus_perc     = facility.time_bonus() * 100f32;
 
             let material_total_cost: f32 = materials
                 .iter()
                 .map(|x| x.cost)
                 .sum();
-            // TODO: consider merging this with the facility cache
-            let system_cost_index_perc = con
-                .get::<_, _, IndustryCostEntry>(CacheName::IndustryCost, sid)
-                .await?
-                .unwrap()
-                .cost_indices
-                .iter()
-                .find(|x| x.activity == "manufacturing")
-                .unwrap()
-                .cost_index;
-            // TODO: move to facility cache
-            let facility_bonus_perc = 5f32;
 
-            let system_cost_index = f32::round(material_total_cost * system_cost_index_perc);
+            let system_cost_index = f32::round(estimated_item_value * system_cost_index_perc);
             let facility_bonus = f32::round(system_cost_index * (facility_bonus_perc / 100f32));
 
             let mut production_cost = system_cost_index - facility_bonus;
-            let facility_tax = f32::round(production_cost * (facility_tax_perc as f32 / 100f32));
+            let facility_tax = f32::round(production_cost * (facility_tax_perc / 100f32));
             production_cost += facility_tax;
 
+            let time = (
+                (production.time * runs) as f32 * (1f32 - facility.time_bonus())
+            ).round() as u64;
+
             let sell_price = con
                 .get::<_, _, MarketPriceEntry>(CacheName::MarketPrice, product_id)
                 .await?
@@ -202,17 +233,44 @@ impl BlueprintService {
             let sell_price = f32::round(sell_price);
             let total_cost = material_total_cost + production_cost;
 
+            // Ships always produce a single unit per run
+            let produced = production
+                .products
+                .as_ref()
+                .and_then(|x| x.first())
+                .map(|x| x.quantity * runs)
+                .unwrap_or(*runs)
+                .max(1);
+            let unit_cost = total_cost / produced as f32;
+            let insurance = con
+                .get::<_, _, InsurancePriceEntry>(CacheName::InsurancePrice, product_id)
+                .await?
+                .map(|x| x.levels)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| InsuranceCost {
+                    net_loss: f32::round(unit_cost + x.cost - x.payout),
+                    name:     x.name,
+                    cost:     x.cost,
+                    payout:   x.payout,
+                })
+                .collect::<Vec<_>>();
+
             bp_costs.push(ManufactureCost {
                 material_total_cost,
                 facility_bonus,
                 facility_bonus_perc,
                 facility_tax,
                 facility_tax_perc,
+                material_bonus_perc,
+                time_bonus_perc,
+                time,
                 system_cost_index,
                 system_cost_index_perc,
                 production_cost,
                 total_cost,
                 sell_price,
+                insurance,
                 materials,
                 bpid: bp.bid,
             });
@@ -560,6 +618,169 @@ impl BlueprintService {
         Ok(materials)
     }
 
+    /// All reaction formulas.
+    ///
+    /// # Returns
+    ///
+    /// Every formula with its product, inputs and the time of a single run
+    ///
+    pub async fn reactions(
+        &self,
+    ) -> Result<Vec<ReactionFormula>, EveServerError> {
+        let mut formulas = self
+            .reaction_product()
+            .await?
+            .into_iter()
+            .map(|(pid, (bpid, activity))| ReactionFormula {
+                bpid,
+                pid,
+                quantity:  activity.products.as_ref().map(|x| x[0].quantity).unwrap_or(1),
+                materials: activity.materials.unwrap_or_default(),
+                time:      activity.time,
+            })
+            .collect::<Vec<_>>();
+        formulas.sort_by_key(|x| x.bpid);
+        Ok(formulas)
+    }
+
+    /// Plans all reactions that are required for the given quantity of a
+    /// reaction product.
+    ///
+    /// Intermediate products that are required by multiple reactions are
+    /// run together, before any reaction that requires them.
+    ///
+    /// # Params
+    ///
+    /// `pid`      -> Composite or hybrid material that should be produced
+    /// `quantity` -> Quantity that is required
+    ///
+    /// # Returns
+    ///
+    /// Every reaction step, first step first, and the moon materials or
+    /// other inputs that must be bought
+    ///
+    pub async fn reaction_chain(
+        &self,
+        pid:      TypeId,
+        quantity: u32,
+    ) -> Result<ReactionChain, EveServerError> {
+        let formulas = self.reaction_product().await?;
+        if !formulas.contains_key(&pid) {
+            return Err(EveServerError::BlueprintNotFound);
+        }
+
+        // Longest path from the target to every intermediate product
+        let mut depths: HashMap<TypeId, u8> = HashMap::new();
+        let mut ids_todo = VecDeque::new();
+        ids_todo.push_back((pid, 0u8));
+        while let Some((c_pid, depth)) = ids_todo.pop_front() {
+            if depths.get(&c_pid).map(|x| *x >= depth).unwrap_or(false) {
+                continue;
+            }
+            depths.insert(c_pid, depth);
+
+            if let Some((_, activity)) = formulas.get(&c_pid) {
+                activity
+                    .materials
+                    .as_ref()
+                    .unwrap_or(&Vec::new())
+                    .iter()
+                    .filter(|x| formulas.contains_key(&x.mid))
+                    .for_each(|x| ids_todo.push_back((x.mid, depth + 1)));
+            }
+        }
+
+        let mut order = depths.into_iter().collect::<Vec<_>>();
+        order.sort_by_key(|(pid, depth)| (*depth, *pid));
+
+        let mut required: HashMap<TypeId, u64> = HashMap::new();
+        required.insert(pid, quantity as u64);
+        let mut inputs: HashMap<TypeId, u64> = HashMap::new();
+        let mut steps = Vec::new();
+
+        // Every product is visited after all reactions that require it
+        for (c_pid, depth) in order {
+            let (bpid, activity) = &formulas[&c_pid];
+            let needed = required.get(&c_pid).copied().unwrap_or_default();
+            let per_run = activity
+                .products
+                .as_ref()
+                .map(|x| x[0].quantity.max(1) as u64)
+                .unwrap_or(1);
+            let runs = (needed + per_run - 1) / per_run;
+
+            let materials = activity
+                .materials
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| {
+                    let quantity = x.quantity as u64 * runs;
+                    if formulas.contains_key(&x.mid) {
+                        *required.entry(x.mid).or_default() += quantity;
+                    } else {
+                        *inputs.entry(x.mid).or_default() += quantity;
+                    }
+                    ReactionMaterial {
+                        type_id: x.mid,
+                        quantity,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            steps.push(ReactionStep {
+                bpid:      *bpid,
+                pid:       c_pid,
+                depth,
+                runs,
+                produced:  runs * per_run,
+                required:  needed,
+                materials,
+                time:      activity.time as u64 * runs,
+            });
+        }
+
+        // The deepest reactions must run first
+        steps.reverse();
+
+        let mut inputs = inputs
+            .into_iter()
+            .map(|(type_id, quantity)| ReactionMaterial {
+                type_id,
+                quantity,
+            })
+            .collect::<Vec<_>>();
+        inputs.sort_by_key(|x| x.type_id);
+
+        Ok(ReactionChain {
+            time: steps.iter().map(|x| x.time).sum(),
+            steps,
+            inputs,
+        })
+    }
+
+    /// Loads all reaction formulas and maps them into a key value format
+    /// `product -> (formula, reaction activity)`
+    async fn reaction_product(&self) -> Result<HashMap<TypeId, (TypeId, Activity)>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let bps = con
+            .keys::<_, TypeId>(CacheName::Blueprint)
+            .await?;
+        let bps = con
+            .mget::<_, _, BlueprintEntry>(CacheName::Blueprint, bps)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter_map(|x| {
+                let bid = x.bid;
+                x.reaction.map(|reaction| (bid, reaction))
+            })
+            .filter(|(_, reaction)| reaction.products.is_some())
+            .map(|(bid, reaction)| (reaction.product_id(), (bid, reaction)))
+            .collect::<HashMap<_, _>>();
+        Ok(bps)
+    }
+
     /// Loads all blueprints and maps them into a key value format.
     ///
     /// The key represents the product of the blueprint and the value is either
@@ -672,15 +893,34 @@ pub struct ManufactureCost {
     facility_bonus_perc:    f32,
     facility_tax:           f32,
     facility_tax_perc:      f32,
+    /// Material reduction of the facility and its rigs
+    material_bonus_perc:    f32,
+    /// Time reduction of the facility and its rigs
+    time_bonus_perc:        f32,
+    /// Job duration in seconds
+    time:                   u64,
     system_cost_index:      f32,
     system_cost_index_perc: f32,
     production_cost:        f32,
     total_cost:             f32,
     sell_price:             f32,
+    /// Insurance levels of the product, empty if the product is not a ship
+    insurance:              Vec<InsuranceCost>,
     materials:              Vec<MaterialCost>,
     bpid:                   TypeId,
 }
 
+#[derive(Clone, Debug, Serialize)]
+pub struct InsuranceCost {
+    /// Basic, Standard, Bronze, Silver, Gold or Platinum
+    name:     String,
+    cost:     f32,
+    payout:   f32,
+    /// Loss of a single manufactured ship that is insured and destroyed,
+    /// negative if the payout is higher than the production cost
+    net_loss: f32,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct MaterialCost {
     mid:    TypeId,
@@ -709,3 +949,51 @@ pub struct ProductionProduct {
     pub materials: Vec<Material>,
     pub depth:     u8,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ReactionQuery {
+    pub quantity: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionFormula {
+    pub bpid:      TypeId,
+    pub pid:       TypeId,
+    /// Quantity a single run produces
+    pub quantity:  u32,
+    pub materials: Vec<Material>,
+    /// Time of a single run in seconds
+    pub time:      u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionChain {
+    /// Reactions in the order they must be run
+    pub steps:  Vec<ReactionStep>,
+    /// Materials that are not produced by a reaction and must be bought
+    pub inputs: Vec<ReactionMaterial>,
+    /// Time in seconds if all steps run one after another
+    pub time:   u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionStep {
+    pub bpid:      TypeId,
+    pub pid:       TypeId,
+    /// Distance to the requested product, the requested product has 0
+    pub depth:     u8,
+    pub runs:      u64,
+    /// Quantity produced by all runs, can exceed the required quantity
+    pub produced:  u64,
+    pub required:  u64,
+    /// Inputs for all runs
+    pub materials: Vec<ReactionMaterial>,
+    /// Time in seconds for all runs
+    pub time:      u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionMaterial {
+    pub type_id:  TypeId,
+    pub quantity: u64,
+}
@@ -0,0 +1,419 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::industry::{FacilityModel, IndustryService};
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{Activity, BlueprintEntry, CacheName, CharacterBlueprintEntry, IndustryCostEntry, MarketPriceEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{ItemId, SolarSystemId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Decides which intermediates of a production are build and which are
+/// bought.
+///
+/// Only intermediates the character or one of its alts owns a blueprint for
+/// can be build. For every intermediate the cheaper option is taken, where
+/// building costs the materials, which are decided the same way, and the job
+/// fee.
+#[derive(Clone)]
+pub struct BuildPlanService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+    industry: IndustryService,
+}
+
+impl BuildPlanService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+        industry: IndustryService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            industry,
+        }
+    }
+
+    /// Calculates the cheapest plan for building the product of a
+    /// blueprint.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `bpid`  -> Blueprint of the product that should be build
+    /// `query` -> Runs and the system all jobs are installed in
+    ///
+    /// # Returns
+    ///
+    /// Tree of all materials with the decision to build or buy them
+    ///
+    pub async fn plan(
+        &self,
+        token: &str,
+        bpid:  TypeId,
+        query: BuildPlanQuery,
+    ) -> Result<BuildPlan, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let mut con = self.pool.acquire().await?;
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterBlueprint)
+            .await?;
+        // blueprint -> best material efficiency of all owned blueprints
+        let mut owned: HashMap<TypeId, u32> = HashMap::new();
+        con
+            .mget::<_, _, CharacterBlueprintEntry>(CacheName::CharacterBlueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .for_each(|x| {
+                let me = owned.entry(x.type_id).or_default();
+                *me = x.material_efficiency.max(*me);
+            });
+
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Blueprint)
+            .await?;
+        let blueprints = con
+            .mget::<_, _, BlueprintEntry>(CacheName::Blueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let root = blueprints
+            .iter()
+            .find(|x| x.bid == bpid)
+            .map(|x| x.production_activity())
+            .filter(|x| x.products.is_some())
+            .ok_or(EveServerError::BlueprintNotFound)?;
+
+        let producers = blueprints
+            .into_iter()
+            .filter(|x| x.manufacture.is_some() || x.reaction.is_some())
+            .map(|x| {
+                let activity = x.production_activity();
+                let producer = Producer {
+                    blueprint_id: x.bid,
+                    reaction:     x.reaction.is_some(),
+                    activity:     activity.clone(),
+                };
+                (activity.product_id(), producer)
+            })
+            .filter(|(_, x)| x.activity.products.is_some())
+            .collect::<HashMap<_, _>>();
+
+        let keys = con
+            .keys::<_, TypeId>(CacheName::MarketPrice)
+            .await?;
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x))
+            .collect::<HashMap<_, _>>();
+
+        let security = con
+            .get::<_, _, SystemRegionEntry>(CacheName::SystemRegion, query.system_id)
+            .await?
+            .map(|x| x.security)
+            .unwrap_or(1f32);
+        let cost_indices = con
+            .get::<_, _, IndustryCostEntry>(CacheName::IndustryCost, query.system_id)
+            .await?
+            .map(|x| x.cost_indices)
+            .unwrap_or_default();
+        let cost_index = |activity: &str| cost_indices
+            .iter()
+            .find(|x| x.activity == activity)
+            .map(|x| x.cost_index)
+            .unwrap_or_default();
+
+        let planner = Planner {
+            facility:       self.industry.facility_model(query.system_id, security)?,
+            manufacturing:  cost_index("manufacturing"),
+            reaction:       cost_index("reaction"),
+            owned,
+            producers,
+            prices:         prices
+                .iter()
+                .map(|(tid, x)| (*tid, (x.average_price, x.adjusted_price)))
+                .collect::<HashMap<_, _>>(),
+        };
+
+        let product_id = root.product_id();
+        let quantity = query.runs.max(1) as u64 * Planner::per_run(&root);
+        let root = planner.build(product_id, quantity, &mut Vec::new())
+            .ok_or(EveServerError::BlueprintNotFound)?;
+        let buy_cost = planner.buy(product_id, quantity).cost;
+
+        Ok(BuildPlan {
+            blueprint_id: bpid,
+            product_id,
+            quantity,
+            system_id:    query.system_id,
+            total_cost:   root.cost,
+            buy_cost,
+            root,
+        })
+    }
+}
+
+/// Blueprint that produces an item
+struct Producer {
+    blueprint_id: TypeId,
+    reaction:     bool,
+    activity:     Activity,
+}
+
+/// Holds everything that is needed to decide between building and buying
+struct Planner {
+    facility:      FacilityModel,
+    /// Cost index of the system for manufacturing
+    manufacturing: f32,
+    /// Cost index of the system for reactions
+    reaction:      f32,
+    /// Owned blueprints with their material efficiency
+    owned:         HashMap<TypeId, u32>,
+    producers:     HashMap<TypeId, Producer>,
+    /// Average and adjusted price of every item
+    prices:        HashMap<TypeId, (f32, f32)>,
+}
+
+impl Planner {
+    /// Decides if the item should be build or bought, depending on what is
+    /// cheaper.
+    ///
+    /// `path` contains all items from the root to the current item and
+    /// protects against cycles.
+    fn step(&self, tid: TypeId, quantity: u64, path: &mut Vec<TypeId>) -> BuildStep {
+        let buy = self.buy(tid, quantity);
+
+        let owned = self
+            .producers
+            .get(&tid)
+            .map(|x| self.owned.contains_key(&x.blueprint_id))
+            .unwrap_or_default();
+        if !owned || path.contains(&tid) {
+            return buy;
+        }
+
+        match self.build(tid, quantity, path) {
+            Some(build) if buy.unpriced || build.cost < buy.cost => build,
+            _                                                    => buy,
+        }
+    }
+
+    /// Builds the item, every material is decided with [Planner::step]
+    fn build(&self, tid: TypeId, quantity: u64, path: &mut Vec<TypeId>) -> Option<BuildStep> {
+        let producer = self.producers.get(&tid)?;
+        let me = self.owned.get(&producer.blueprint_id).copied();
+        // Reactions have no material efficiency
+        let me = if producer.reaction { None } else { me };
+
+        let runs = ((quantity + Self::per_run(&producer.activity) - 1)
+            / Self::per_run(&producer.activity)) as u32;
+
+        path.push(tid);
+        let mut estimated_item_value = 0f32;
+        let materials = producer
+            .activity
+            .materials()
+            .into_iter()
+            .map(|x| {
+                let adjusted = self.prices.get(&x.mid).map(|x| x.1).unwrap_or_default();
+                estimated_item_value += (runs * x.quantity) as f32 * adjusted;
+
+                let quantity = self.facility.material_quantity_me(
+                    x.quantity,
+                    runs,
+                    me.unwrap_or_default(),
+                );
+                self.step(x.mid, quantity as u64, path)
+            })
+            .collect::<Vec<_>>();
+        path.pop();
+
+        let cost_index = if producer.reaction { self.reaction } else { self.manufacturing };
+        let system_cost = estimated_item_value * cost_index;
+        let job_fee = system_cost * (1f32 - self.facility.job_cost_bonus());
+        let job_fee = job_fee * (1f32 + self.facility.tax / 100f32);
+
+        let cost = materials.iter().map(|x| x.cost).sum::<f32>() + job_fee;
+        Some(BuildStep {
+            type_id:             tid,
+            quantity,
+            decision:            BuildDecision::Build,
+            cost,
+            unpriced:            materials.iter().any(|x| x.unpriced),
+            blueprint_id:        Some(producer.blueprint_id),
+            owned:               self.owned.contains_key(&producer.blueprint_id),
+            material_efficiency: me,
+            runs:                Some(runs),
+            job_fee,
+            materials,
+        })
+    }
+
+    fn buy(&self, tid: TypeId, quantity: u64) -> BuildStep {
+        let price = self.prices.get(&tid).map(|x| x.0).filter(|x| *x > 0f32);
+        BuildStep {
+            type_id:             tid,
+            quantity,
+            decision:            BuildDecision::Buy,
+            cost:                price.unwrap_or_default() * quantity as f32,
+            unpriced:            price.is_none(),
+            blueprint_id:        None,
+            owned:               false,
+            material_efficiency: None,
+            runs:                None,
+            job_fee:             0f32,
+            materials:           Vec::new(),
+        }
+    }
+
+    /// Quantity a single run of the activity produces
+    fn per_run(activity: &Activity) -> u64 {
+        activity
+            .products
+            .as_ref()
+            .and_then(|x| x.first())
+            .map(|x| x.quantity as u64)
+            .unwrap_or(1)
+            .max(1)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BuildPlanQuery {
+    pub runs:      u32,
+    /// System all jobs are installed in
+    pub system_id: SolarSystemId,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuildPlan {
+    pub blueprint_id: TypeId,
+    pub product_id:   TypeId,
+    pub quantity:     u64,
+    pub system_id:    SolarSystemId,
+    /// Cost of the plan
+    pub total_cost:   f32,
+    /// Cost of buying the product instead of building it
+    pub buy_cost:     f32,
+    pub root:         BuildStep,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuildStep {
+    pub type_id:             TypeId,
+    pub quantity:            u64,
+    pub decision:            BuildDecision,
+    /// Cost of the materials and the job fee, or the market price
+    pub cost:                f32,
+    /// There is no market price for the item or one of its materials, the
+    /// cost is too low
+    pub unpriced:            bool,
+    pub blueprint_id:        Option<TypeId>,
+    /// The blueprint is owned, only the root can be build without owning
+    /// the blueprint
+    pub owned:               bool,
+    pub material_efficiency: Option<u32>,
+    pub runs:                Option<u32>,
+    pub job_fee:             f32,
+    pub materials:           Vec<BuildStep>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildDecision {
+    Build,
+    Buy,
+}
+
+#[cfg(test)]
+mod build_plan_tests {
+    use super::*;
+    use crate::industry::{FacilityKind, RigTier};
+    use caph_db_v2::Material;
+
+    /// 1 is build from 2 and 3, 2 is build from 3
+    fn planner(owned: Vec<u32>, prices: Vec<(u32, f32)>) -> Planner {
+        let producer = |bid: u32, pid: u32, materials: Vec<(u32, u32)>| {
+            let activity = Activity {
+                materials: Some(materials
+                    .into_iter()
+                    .map(|(mid, quantity)| Material::new(mid.into(), quantity, None))
+                    .collect()
+                ),
+                products:  Some(vec![Material::new(pid.into(), 1, None)]),
+                skills:    None,
+                time:      1,
+            };
+            (TypeId(pid), Producer {
+                blueprint_id: bid.into(),
+                reaction:     false,
+                activity,
+            })
+        };
+
+        Planner {
+            facility:      FacilityModel {
+                kind:         FacilityKind::NpcStation,
+                material_rig: RigTier::None,
+                time_rig:     RigTier::None,
+                security:     1f32,
+                tax:          0f32,
+            },
+            manufacturing: 0f32,
+            reaction:      0f32,
+            owned:         owned.into_iter().map(|x| (TypeId(x), 0)).collect(),
+            producers:     vec![
+                producer(10, 1, vec![(2, 2), (3, 1)]),
+                producer(20, 2, vec![(3, 10)]),
+            ]
+            .into_iter()
+            .collect(),
+            prices:        prices.into_iter().map(|(tid, x)| (TypeId(tid), (x, x))).collect(),
+        }
+    }
+
+    #[test]
+    fn builds_cheaper_intermediate() {
+        let planner = planner(vec![10, 20], vec![(2, 100f32), (3, 1f32)]);
+        let plan = planner.build(1.into(), 1, &mut Vec::new()).unwrap();
+        assert_eq!(plan.materials[0].decision, BuildDecision::Build);
+        assert_eq!(plan.cost, 21f32);
+    }
+
+    #[test]
+    fn buys_cheaper_intermediate() {
+        let planner = planner(vec![10, 20], vec![(2, 5f32), (3, 1f32)]);
+        let plan = planner.build(1.into(), 1, &mut Vec::new()).unwrap();
+        assert_eq!(plan.materials[0].decision, BuildDecision::Buy);
+        assert_eq!(plan.cost, 11f32);
+    }
+
+    #[test]
+    fn buys_without_blueprint() {
+        let planner = planner(vec![10], vec![(2, 100f32), (3, 1f32)]);
+        let plan = planner.build(1.into(), 1, &mut Vec::new()).unwrap();
+        assert_eq!(plan.materials[0].decision, BuildDecision::Buy);
+        assert_eq!(plan.cost, 201f32);
+    }
+}
@@ -0,0 +1,413 @@
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{Activity, BlueprintEntry, CacheName, ItemEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{SolarSystemId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Plans the construction of capital ships.
+///
+/// Capital ships are build in multiple stages. First the reactions and
+/// components are build, after that the hull itself. Every stage becomes a
+/// phase of the plan, a phase can only start after the previous phase is done.
+#[derive(Clone)]
+pub struct CapitalService {
+    pool: ConnectionPool,
+}
+
+impl CapitalService {
+    /// Cargo capacity of a freighter without any cargo expanders
+    const FREIGHTER_CAPACITY: f32 = 435_000f32;
+    /// Capital ships can not be build in systems with this security or higher
+    const HIGH_SEC:           f32 = 0.45f32;
+
+    /// Creates a new instance
+    pub fn new(
+        pool: ConnectionPool,
+    ) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// Creates a phased build plan for the given blueprint.
+    ///
+    /// # Params
+    ///
+    /// `bpid`  -> Blueprint of the capital ship
+    /// `query` -> Runs, build location, available slots and freighter
+    ///            capacity
+    ///
+    /// # Returns
+    ///
+    /// All phases with their scheduled jobs and the materials that must be
+    /// delivered to the build location before the phase starts. Fails if the
+    /// build location is a high sec system
+    ///
+    pub async fn plan(
+        &self,
+        bpid:  TypeId,
+        query: CapitalQuery,
+    ) -> Result<CapitalPlan, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let system = con
+            .get::<_, _, SystemRegionEntry>(CacheName::SystemRegion, query.system_id)
+            .await?
+            .ok_or(EveServerError::InvalidBuildLocation)?;
+        if system.security >= Self::HIGH_SEC {
+            return Err(EveServerError::InvalidBuildLocation);
+        }
+
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Blueprint)
+            .await?;
+        let blueprints = con
+            .mget::<_, _, BlueprintEntry>(CacheName::Blueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let root = blueprints
+            .iter()
+            .find(|x| x.bid == bpid)
+            .and_then(|x| x.manufacture.clone())
+            .filter(|x| x.products.is_some())
+            .ok_or(EveServerError::BlueprintNotFound)?;
+
+        // product -> (blueprint, activity, is reaction)
+        let producers = blueprints
+            .into_iter()
+            .filter(|x| x.manufacture.is_some() || x.reaction.is_some())
+            .map(|x| {
+                let activity = x.production_activity();
+                (activity.product_id(), (x.bid, activity, x.reaction.is_some()))
+            })
+            .filter(|(_, (_, activity, _))| activity.products.is_some())
+            .collect::<HashMap<_, _>>();
+
+        let product_id = root.product_id();
+        let depths = Self::depths(product_id, &producers);
+
+        let mut nodes = depths
+            .iter()
+            .map(|(pid, depth)| (*pid, *depth))
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|(pid, depth)| (*depth, *pid));
+
+        // Walks the tree from the hull down, every item is only visited after
+        // everything that requires it was visited
+        let mut required: HashMap<TypeId, u64> = HashMap::new();
+        required.insert(product_id, query.runs as u64 * Self::per_run(&root));
+        let mut deliveries: HashMap<u8, HashMap<TypeId, u64>> = HashMap::new();
+        let mut jobs: HashMap<u8, Vec<CapitalJob>> = HashMap::new();
+
+        for (pid, depth) in nodes {
+            let (bid, activity, reaction) = if let Some(x) = producers.get(&pid) {
+                x
+            } else {
+                continue;
+            };
+            let quantity = required.get(&pid).copied().unwrap_or_default();
+            let per_run = Self::per_run(activity);
+            let runs = ((quantity + per_run - 1) / per_run) as u32;
+            if runs == 0 {
+                continue;
+            }
+
+            for material in activity.materials.clone().unwrap_or_default() {
+                let quantity = material.quantity as u64 * runs as u64;
+                if producers.contains_key(&material.mid) {
+                    *required.entry(material.mid).or_default() += quantity;
+                } else {
+                    *deliveries
+                        .entry(depth)
+                        .or_default()
+                        .entry(material.mid)
+                        .or_default() += quantity;
+                }
+            }
+
+            let phase_jobs = jobs.entry(depth).or_default();
+            for runs in Self::split_runs(runs, query.slots) {
+                phase_jobs.push(CapitalJob {
+                    product_id:   pid,
+                    blueprint_id: *bid,
+                    runs,
+                    reaction:     *reaction,
+                    slot:         0,
+                    start:        0,
+                    duration:     activity.time as u64 * runs as u64,
+                });
+            }
+        }
+
+        let mut type_ids = deliveries
+            .values()
+            .flat_map(|x| x.keys().copied())
+            .chain(depths.keys().copied())
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let volumes = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids.clone())
+            .await?
+            .into_iter()
+            .zip(type_ids)
+            .map(|(item, tid)| (tid, item.map(|x| x.volume).unwrap_or_default()))
+            .collect::<HashMap<_, _>>();
+
+        let capacity = query.freighter_capacity.unwrap_or(Self::FREIGHTER_CAPACITY);
+        let mut levels = jobs.keys().copied().collect::<Vec<_>>();
+        levels.sort_by(|a, b| b.cmp(a));
+
+        let mut start = 0u64;
+        let mut phases = Vec::new();
+        for (index, level) in levels.into_iter().enumerate() {
+            let (jobs, duration) = Self::schedule(
+                jobs.remove(&level).unwrap_or_default(),
+                query.slots,
+                start,
+            );
+
+            let mut deliveries = deliveries
+                .remove(&level)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(type_id, quantity)| MaterialDelivery {
+                    type_id,
+                    quantity,
+                    volume: volumes.get(&type_id).copied().unwrap_or_default() * quantity as f32,
+                })
+                .collect::<Vec<_>>();
+            deliveries.sort_by_key(|x| x.type_id);
+            let volume = deliveries.iter().map(|x| x.volume).sum::<f32>();
+
+            let mut oversized = jobs
+                .iter()
+                .map(|x| x.product_id)
+                .chain(deliveries.iter().map(|x| x.type_id))
+                .filter(|x| volumes.get(x).copied().unwrap_or_default() > capacity)
+                .collect::<Vec<_>>();
+            oversized.sort();
+            oversized.dedup();
+
+            phases.push(CapitalPhase {
+                index:           index as u8,
+                start,
+                duration,
+                jobs,
+                deliveries,
+                volume,
+                freighter_trips: Self::trips(volume, capacity),
+                oversized,
+            });
+            start += duration;
+        }
+
+        Ok(CapitalPlan {
+            blueprint_id: bpid,
+            product_id,
+            runs:         query.runs,
+            system_id:    query.system_id,
+            security:     system.security,
+            duration:     start,
+            phases,
+        })
+    }
+
+    /// Longest path from the hull to every item that must be build.
+    ///
+    /// Items used in multiple stages are build in the earliest phase they are
+    /// required in.
+    fn depths(
+        product_id: TypeId,
+        producers:  &HashMap<TypeId, (TypeId, Activity, bool)>,
+    ) -> HashMap<TypeId, u8> {
+        let mut depths: HashMap<TypeId, u8> = HashMap::new();
+        let mut todo = VecDeque::new();
+        todo.push_back((product_id, 0u8));
+
+        while let Some((pid, depth)) = todo.pop_front() {
+            if depths.get(&pid).map(|x| *x >= depth).unwrap_or(false) {
+                continue;
+            }
+            depths.insert(pid, depth);
+
+            if let Some((_, activity, _)) = producers.get(&pid) {
+                activity
+                    .materials
+                    .as_ref()
+                    .unwrap_or(&Vec::new())
+                    .iter()
+                    .filter(|x| producers.contains_key(&x.mid))
+                    .for_each(|x| todo.push_back((x.mid, depth + 1)));
+            }
+        }
+
+        depths
+    }
+
+    /// Quantity a single run of the activity produces
+    fn per_run(activity: &Activity) -> u64 {
+        activity
+            .products
+            .as_ref()
+            .and_then(|x| x.first())
+            .map(|x| x.quantity as u64)
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Splits the runs into multiple jobs so that they can run in parallel
+    fn split_runs(runs: u32, slots: u32) -> Vec<u32> {
+        let jobs = slots.max(1).min(runs);
+        (0..jobs)
+            .map(|x| runs / jobs + if x < runs % jobs { 1 } else { 0 })
+            .collect::<Vec<_>>()
+    }
+
+    /// Assigns the jobs to the slots, longest job first, every job goes to
+    /// the slot that is free the earliest.
+    ///
+    /// Returns the scheduled jobs and the time until all jobs are done
+    fn schedule(
+        mut jobs: Vec<CapitalJob>,
+        slots:    u32,
+        start:    u64,
+    ) -> (Vec<CapitalJob>, u64) {
+        jobs.sort_by(|a, b| b.duration.cmp(&a.duration));
+
+        let mut loads = vec![0u64; slots.max(1) as usize];
+        for job in jobs.iter_mut() {
+            let (slot, load) = loads
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, x)| **x)
+                .map(|(slot, load)| (slot, *load))
+                .unwrap_or_default();
+            job.slot  = slot as u32;
+            job.start = start + load;
+            loads[slot] += job.duration;
+        }
+
+        let duration = loads.into_iter().max().unwrap_or_default();
+        (jobs, duration)
+    }
+
+    /// Number of freighter trips required to move the given volume
+    fn trips(volume: f32, capacity: f32) -> u32 {
+        if capacity <= 0f32 {
+            return 0;
+        }
+        (volume / capacity).ceil() as u32
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CapitalQuery {
+    pub runs:               u32,
+    /// System the capital is build in, must be low or null sec
+    pub system_id:          SolarSystemId,
+    /// Number of manufacturing and reaction slots that can be used
+    pub slots:              u32,
+    /// Capacity in m3, defaults to a freighter without expanders
+    pub freighter_capacity: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapitalPlan {
+    pub blueprint_id: TypeId,
+    pub product_id:   TypeId,
+    pub runs:         u32,
+    pub system_id:    SolarSystemId,
+    pub security:     f32,
+    /// Time in seconds until the last phase is done
+    pub duration:     u64,
+    pub phases:       Vec<CapitalPhase>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapitalPhase {
+    pub index:           u8,
+    /// Seconds after the start of the plan
+    pub start:           u64,
+    /// Seconds until all jobs of the phase are done
+    pub duration:        u64,
+    pub jobs:            Vec<CapitalJob>,
+    /// Materials that must be at the build location before the phase starts
+    pub deliveries:      Vec<MaterialDelivery>,
+    /// Total volume of all deliveries in m3
+    pub volume:          f32,
+    pub freighter_trips: u32,
+    /// Items that do not fit into the freighter and must stay at the build
+    /// location
+    pub oversized:       Vec<TypeId>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CapitalJob {
+    pub product_id:   TypeId,
+    pub blueprint_id: TypeId,
+    pub runs:         u32,
+    pub reaction:     bool,
+    pub slot:         u32,
+    /// Seconds after the start of the plan
+    pub start:        u64,
+    /// Duration in seconds without any time efficiency or structure bonus
+    pub duration:     u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaterialDelivery {
+    pub type_id:  TypeId,
+    pub quantity: u64,
+    /// Volume in m3
+    pub volume:   f32,
+}
+
+#[cfg(test)]
+mod capital_tests {
+    use super::*;
+
+    fn job(duration: u64) -> CapitalJob {
+        CapitalJob {
+            product_id:   0u32.into(),
+            blueprint_id: 0u32.into(),
+            runs:         1,
+            reaction:     false,
+            slot:         0,
+            start:        0,
+            duration,
+        }
+    }
+
+    #[test]
+    fn split_runs() {
+        assert_eq!(CapitalService::split_runs(10, 3), vec![4, 3, 3]);
+        assert_eq!(CapitalService::split_runs(2, 5), vec![1, 1]);
+        assert_eq!(CapitalService::split_runs(4, 0), vec![4]);
+    }
+
+    #[test]
+    fn schedule() {
+        let jobs = vec![job(2), job(5), job(3), job(4)];
+        let (jobs, duration) = CapitalService::schedule(jobs, 2, 10);
+
+        assert_eq!(duration, 7);
+        assert_eq!(jobs[0].duration, 5);
+        assert_eq!(jobs[0].start, 10);
+        assert_eq!(jobs[1].start, 10);
+        assert_eq!(jobs[2].start, 14);
+        assert_eq!(jobs[3].start, 15);
+    }
+
+    #[test]
+    fn trips() {
+        assert_eq!(CapitalService::trips(0f32, 435_000f32), 0);
+        assert_eq!(CapitalService::trips(435_000f32, 435_000f32), 1);
+        assert_eq!(CapitalService::trips(435_001f32, 435_000f32), 2);
+    }
+}
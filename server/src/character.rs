@@ -1,47 +1,98 @@
+use crate::asset::{AssetQuery, AssetService, AssetStack};
 use crate::error::EveServerError;
 use crate::eve::EveAuthService;
+use crate::pricing::{PriceQuery, PriceService};
+use crate::workspace::WorkspaceService;
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry};
-use caph_eve_data_wrapper::{CharacterId, CorporationId, ItemId};
+use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, CharacterCloneEntry, CharacterContractEntry, ContractEntry, CharacterPlanetEntry, CharacterSyncEntry, ItemEntry, MarketPriceEntry, NetWorthEntry, UserEntry, UserPreferenceEntry, WalletTransactionEntry, WorkspaceKey};
+use caph_eve_data_wrapper::{CategoryId, CharacterId, ContractId, CorporationId, GroupId, ItemId, LocationId, PlanetId, RegionId, SchematicId, SolarSystemId, TransactionId, TypeId};
 use caph_eve_data_wrapper::EveDataWrapper;
 use caph_eve_data_wrapper::ItemLocation;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::hash::Hash;
+use uuid::Uuid;
 
 /// Service for all character related interfaces
 #[derive(Clone)]
 pub struct CharacterService {
-    pool:     ConnectionPool,
-    eve_auth: EveAuthService,
-    eve_data: EveDataWrapper,
+    pool:      ConnectionPool,
+    asset:     AssetService,
+    eve_auth:  EveAuthService,
+    eve_data:  EveDataWrapper,
+    price:     PriceService,
+    workspace: WorkspaceService,
 }
 
 impl CharacterService {
+    /// Cargo capacity of a freighter without any cargo expanders
+    const FREIGHTER_CAPACITY: f32 = 435_000f32;
+
     /// Creates a new instance
     pub fn new(
         pool: ConnectionPool,
-        eve_auth: EveAuthService,
-        eve_data: EveDataWrapper,
+        asset:     AssetService,
+        eve_auth:  EveAuthService,
+        eve_data:  EveDataWrapper,
+        price:     PriceService,
+        workspace: WorkspaceService,
     ) -> Self {
         Self {
             pool,
+            asset,
             eve_auth,
             eve_data,
+            price,
+            workspace,
         }
     }
 
+    /// Gets the assets of the character and its alts that match the
+    /// filters
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Filters
+    ///
     pub async fn assets(
         &self,
-        token: &str
+        token: &str,
+        query: AssetQuery,
+    ) -> Result<Vec<AssetStack<CharacterAssetEntry>>, EveServerError> {
+        let assets = self.all_assets(token).await?;
+        self
+            .asset
+            .query(assets, |x| (x.type_id, x.location_id, x.quantity), query)
+            .await
+    }
+
+    /// Gets all assets of the character and its alts
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    pub async fn all_assets(
+        &self,
+        token: &str,
     ) -> Result<Vec<CharacterAssetEntry>, EveServerError> {
         let mut con = self.pool.acquire().await?;
 
-        // Check if the user exists
-        let _ = self
+        let user = self
             .eve_auth
             .lookup(&token)
             .await?
             .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
 
         let keys = con
             .keys::<_, ItemId>(CacheName::CharacterAsset)
@@ -51,10 +102,618 @@ impl CharacterService {
             .await?
             .into_iter()
             .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
             .collect::<Vec<CharacterAssetEntry>>();
         Ok(assets)
     }
 
+    /// Calculates the cost basis of every asset stack of the character and
+    /// its alts.
+    ///
+    /// The wallet transactions of the owner are used to find out what was
+    /// paid for the items. The most recent buy transactions are consumed
+    /// first, preferring transactions at the location of the stack.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// List of all asset stacks with their cost basis and unrealized profit
+    ///
+    pub async fn asset_cost_basis(
+        &self,
+        token: &str
+    ) -> Result<Vec<AssetCostBasis>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let keys = con
+            .keys::<_, TransactionId>(CacheName::WalletTransaction)
+            .await?;
+        let mut transactions = con
+            .mget::<_, _, WalletTransactionEntry>(CacheName::WalletTransaction, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.is_buy && user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+        // Newest transactions first
+        transactions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+        let type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        // Remaining quantity of every transaction that was not yet assigned
+        // to an asset stack
+        let mut remaining = transactions
+            .iter()
+            .map(|x| (x.transaction_id, x.quantity))
+            .collect::<HashMap<_, _>>();
+
+        let mut result = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let mut covered = 0u32;
+            let mut cost    = 0f32;
+
+            // First use transactions at the same location, after that all
+            // others
+            let candidates = transactions
+                .iter()
+                .filter(|x| x.user_id == asset.user_id && x.type_id == asset.type_id)
+                .filter(|x| *x.location_id == *asset.location_id)
+                .chain(
+                    transactions
+                        .iter()
+                        .filter(|x| x.user_id == asset.user_id && x.type_id == asset.type_id)
+                        .filter(|x| *x.location_id != *asset.location_id)
+                );
+            for transaction in candidates {
+                if covered == asset.quantity {
+                    break;
+                }
+
+                let left = remaining
+                    .get_mut(&transaction.transaction_id)
+                    .unwrap();
+                let take = (*left).min(asset.quantity - covered);
+                *left   -= take;
+                covered += take;
+                cost    += take as f32 * transaction.unit_price;
+            }
+
+            let cost_basis = if covered > 0 {
+                Some(cost / covered as f32)
+            } else {
+                None
+            };
+            let market_price = prices.get(&asset.type_id).copied();
+            let unrealized_profit = match (cost_basis, market_price) {
+                (Some(c), Some(m)) => Some((m - c) * covered as f32),
+                _                  => None
+            };
+
+            result.push(AssetCostBasis {
+                item_id:     asset.item_id,
+                location_id: asset.location_id,
+                type_id:     asset.type_id,
+                user_id:     asset.user_id,
+                quantity:    asset.quantity,
+                covered,
+                cost_basis,
+                market_price,
+                unrealized_profit,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Values all assets of the character and its alts and groups them by
+    /// item category, region and character.
+    ///
+    /// Items inside containers or ships are assigned to the location of the
+    /// outer most item. If that location is not a NPC station, the region is
+    /// unknown.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Price source the assets are valued with
+    ///
+    /// # Returns
+    ///
+    /// Total worth together with the value and share of every group
+    ///
+    pub async fn asset_worth(
+        &self,
+        token: &str,
+        query: PriceQuery,
+    ) -> Result<AssetWorth, EveServerError> {
+        let source = query.source()?;
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let mut type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let prices = self
+            .price
+            .prices(source, type_ids.clone())
+            .await?;
+        let categories = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.item_id, x.category_id))
+            .collect::<HashMap<_, _>>();
+
+        let regions = self
+            .eve_data
+            .stations()
+            .await?
+            .stations()
+            .iter()
+            .map(|x| (*x.station_id, x.region_id))
+            .collect::<HashMap<_, _>>();
+        let parents = assets
+            .iter()
+            .map(|x| (*x.item_id, *x.location_id))
+            .collect::<HashMap<_, _>>();
+
+        let mut by_category  = HashMap::new();
+        let mut by_region    = HashMap::new();
+        let mut by_character = HashMap::new();
+        let mut total        = 0f32;
+
+        for asset in assets {
+            let value = prices
+                .get(&asset.type_id)
+                .copied()
+                .unwrap_or_default() * asset.quantity as f32;
+            if value == 0f32 {
+                continue;
+            }
+
+            // Walk up the containers until the location is no longer an item
+            let mut location = *asset.location_id;
+            while let Some(x) = parents.get(&location) {
+                location = *x;
+            }
+
+            let category = categories.get(&asset.type_id).copied();
+            let region   = regions.get(&location).copied();
+
+            *by_category.entry(category).or_insert(0f32)        += value;
+            *by_region.entry(region).or_insert(0f32)            += value;
+            *by_character.entry(asset.user_id).or_insert(0f32)  += value;
+            total += value;
+        }
+
+        Ok(AssetWorth {
+            total,
+            by_category:  AssetWorthShare::from_map(by_category, total),
+            by_region:    AssetWorthShare::from_map(by_region, total),
+            by_character: AssetWorthShare::from_map(by_character, total),
+        })
+    }
+
+    /// Volume of all assets of the character and its alts per location,
+    /// for planning a move.
+    ///
+    /// Ships are counted with their packaged volume, containers with their
+    /// own volume and the volume of their content.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Capacity of the freighter that is used
+    ///
+    /// # Returns
+    ///
+    /// Volume and required freighter trips of every location, largest
+    /// location first
+    ///
+    pub async fn asset_volume(
+        &self,
+        token: &str,
+        query: HaulingQuery,
+    ) -> Result<Vec<AssetVolume>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let mut type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let items = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.item_id, x))
+            .collect::<HashMap<_, _>>();
+
+        let parents = assets
+            .iter()
+            .map(|x| (*x.item_id, *x.location_id))
+            .collect::<HashMap<_, _>>();
+
+        let mut locations: HashMap<u64, HashMap<Option<CategoryId>, f32>> = HashMap::new();
+        for asset in assets {
+            let item = items.get(&asset.type_id);
+            let volume = item
+                .map(|x| Self::packaged_volume(x.group_id).unwrap_or(x.volume))
+                .unwrap_or_default() * asset.quantity as f32;
+
+            // Walk up the containers until the location is no longer an item
+            let mut location = *asset.location_id;
+            while let Some(x) = parents.get(&location) {
+                location = *x;
+            }
+
+            *locations
+                .entry(location)
+                .or_default()
+                .entry(item.map(|x| x.category_id))
+                .or_default() += volume;
+        }
+
+        let capacity = query.freighter_capacity.unwrap_or(Self::FREIGHTER_CAPACITY);
+        let mut result = locations
+            .into_iter()
+            .map(|(location_id, categories)| {
+                let volume = categories.values().sum::<f32>();
+                let mut by_category = categories
+                    .into_iter()
+                    .map(|(category_id, volume)| AssetVolumeCategory {
+                        category_id,
+                        volume,
+                    })
+                    .collect::<Vec<_>>();
+                by_category.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap_or(std::cmp::Ordering::Equal));
+
+                AssetVolume {
+                    location_id:     location_id.into(),
+                    volume,
+                    freighter_trips: if capacity > 0f32 { (volume / capacity).ceil() as u32 } else { 0 },
+                    by_category,
+                }
+            })
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(result)
+    }
+
+    /// Packaged volume of ships, the SDE only contains the assembled volume.
+    ///
+    /// Capitals, freighters and other ships that can not be moved with a
+    /// freighter return [None].
+    pub(crate) fn packaged_volume(group_id: GroupId) -> Option<f32> {
+        let volume = match *group_id {
+            // Shuttle, Capsule, Prototype Exploration Ship
+            29 | 31 | 1022                             => 500f32,
+            // Frigates and their tech 2 variants
+            25 | 237 | 324 | 830 | 831 | 834 | 893 |
+            1283 | 1527                                => 2_500f32,
+            // Mining Barge, Exhumer
+            463 | 543                                  => 3_750f32,
+            // Destroyers and their tech 2 and 3 variants
+            420 | 541 | 1305 | 1534                    => 5_000f32,
+            // Strategic Cruiser
+            963                                        => 5_000f32,
+            // Cruisers and their tech 2 variants
+            26 | 358 | 832 | 833 | 894 | 906 | 1972    => 10_000f32,
+            // Battlecruisers and Command Ships
+            419 | 540 | 1201                           => 15_000f32,
+            // Industrials and Transport Ships
+            28 | 380 | 1202                            => 20_000f32,
+            // Battleships, Black Ops, Marauder
+            27 | 898 | 900                             => 50_000f32,
+            _                                          => return None,
+        };
+        Some(volume)
+    }
+
+    /// Gets the clones and implants of the character and its alts
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// Home location, active implants and jump clones of every character
+    ///
+    pub async fn clones(
+        &self,
+        token: &str,
+    ) -> Result<Vec<CharacterCloneEntry>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, CharacterCloneEntry>(CacheName::CharacterClone, user_ids)
+            .await
+            .map(|x| x.into_iter().flatten().collect::<Vec<_>>())
+            .map_err(Into::into)
+    }
+
+    /// Gets the planetary colonies of the character and its alts.
+    ///
+    /// Factories are resolved against the planet schematics of the SDE.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// All colonies with their extractors and factories, colonies with the
+    /// earliest running dry extractor first
+    ///
+    pub async fn planets(
+        &self,
+        token: &str,
+    ) -> Result<Vec<PlanetColony>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let planets = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, Vec<CharacterPlanetEntry>>(CacheName::CharacterPlanet, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let schematics = self.eve_data.planet_schematics().await?;
+        let schematics = schematics.schematics();
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let mut colonies = planets
+            .into_iter()
+            .map(|planet| {
+                let extractors = planet
+                    .pins
+                    .iter()
+                    .filter(|x| x.product_type_id.is_some())
+                    .map(|x| PlanetExtractor {
+                        pin_id:          x.pin_id,
+                        product_type_id: x.product_type_id,
+                        expiry:          x.expiry,
+                        expired:         x.expiry.map(|x| x <= now).unwrap_or(true),
+                    })
+                    .collect::<Vec<_>>();
+                let factories = planet
+                    .pins
+                    .iter()
+                    .filter_map(|x| Some((x.pin_id, x.schematic_id?)))
+                    .map(|(pin_id, schematic_id)| {
+                        let schematic = schematics.get(&schematic_id);
+                        let name = schematic
+                            .and_then(|x| x.name.get("en").cloned())
+                            .unwrap_or_default();
+                        let output = schematic
+                            .and_then(|x| x.types.iter().find(|(_, y)| !y.is_input))
+                            .map(|(tid, _)| *tid);
+                        let cycle_time = schematic
+                            .map(|x| x.cycle_time)
+                            .unwrap_or_default();
+
+                        PlanetFactory {
+                            pin_id,
+                            schematic_id,
+                            name,
+                            output,
+                            cycle_time,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let next_expiry = extractors
+                    .iter()
+                    .filter_map(|x| x.expiry)
+                    .min();
+
+                PlanetColony {
+                    planet_id:     planet.planet_id,
+                    user_id:       planet.user_id,
+                    system_id:     planet.system_id,
+                    planet_type:   planet.planet_type,
+                    upgrade_level: planet.upgrade_level,
+                    next_expiry,
+                    extractors,
+                    factories,
+                }
+            })
+            .collect::<Vec<_>>();
+        colonies.sort_by_key(|x| x.next_expiry.unwrap_or(u64::MAX));
+        Ok(colonies)
+    }
+
+    /// Gets all contracts of the character and its alts
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Optional filter for the status and type
+    ///
+    /// # Returns
+    ///
+    /// List of all matching contracts together with the volume of the
+    /// included items
+    ///
+    pub async fn contracts(
+        &self,
+        token: &str,
+        query: ContractQuery,
+    ) -> Result<Vec<CharacterContract>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ContractId>(CacheName::CharacterContract)
+            .await?;
+        let contracts = con
+            .mget::<_, _, CharacterContractEntry>(CacheName::CharacterContract, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .filter(|x| query.status.as_ref().map(|s| *s == x.status).unwrap_or(true))
+            .filter(|x| query.typ.as_ref().map(|t| *t == x.typ).unwrap_or(true))
+            .collect::<Vec<_>>();
+
+        let mut type_ids = contracts
+            .iter()
+            .flat_map(|x| x.items.iter().map(|x| x.type_id))
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let volumes = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.item_id, x.volume))
+            .collect::<HashMap<_, _>>();
+
+        let contracts = contracts
+            .into_iter()
+            .map(|x| {
+                let item_volume = x
+                    .items
+                    .iter()
+                    .filter(|x| x.is_included)
+                    .map(|x| volumes.get(&x.type_id).copied().unwrap_or_default() * x.quantity as f32)
+                    .sum();
+
+                CharacterContract {
+                    contract: x,
+                    item_volume,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(contracts)
+    }
+
     /// Resolves all blueprints for a character and its alts
     ///
     /// # Params
@@ -91,6 +750,393 @@ impl CharacterService {
         Ok(bps)
     }
 
+    /// Groups all blueprints of a character and its alts into stacks of
+    /// identical blueprints and values them based on public contracts.
+    ///
+    /// Only contracts that sell a single blueprint with the same material
+    /// and time efficiency are considered. Copies are valued per run, so
+    /// copies with a different number of runs can be compared.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// List of all stacks, sorted by their value
+    ///
+    pub async fn blueprint_stacks(
+        &self,
+        token: String
+    ) -> Result<Vec<BlueprintStack>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterBlueprint)
+            .await?;
+        let bps = con
+            .mget::<_, _, CharacterBlueprintEntry>(CacheName::CharacterBlueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let mut stacks: HashMap<BlueprintStackKey, BlueprintStack> = HashMap::new();
+        for bp in bps {
+            let key = BlueprintStackKey {
+                type_id:             bp.type_id,
+                is_copy:             bp.quantity == -2,
+                material_efficiency: bp.material_efficiency,
+                time_efficiency:     bp.time_efficiency,
+                runs:                bp.runs,
+            };
+            let stack = stacks
+                .entry(key)
+                .or_insert_with(|| BlueprintStack::from(key));
+            // A positive quantity is a stack of originals
+            stack.count += bp.quantity.max(1) as u32;
+            stack.item_ids.push(bp.item_id);
+        }
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let keys = con
+            .keys::<_, ContractId>(CacheName::Contract)
+            .await?;
+        let mut prices: HashMap<(TypeId, bool, u32, u32), Vec<f32>> = HashMap::new();
+        con
+            .mget::<_, _, ContractEntry>(CacheName::Contract, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.expire > now && x.price > 0f32)
+            .filter(|x| x.items.len() == 1)
+            .for_each(|x| {
+                let item = &x.items[0];
+                if !item.is_included || item.quantity == 0 {
+                    return;
+                }
+
+                let key = (
+                    item.type_id,
+                    item.is_blueprint_copy,
+                    item.material_efficiency,
+                    item.time_efficiency,
+                );
+                if !stacks.keys().any(|x| x.price_key() == key) {
+                    return;
+                }
+
+                let units = if item.is_blueprint_copy {
+                    item.quantity as f32 * item.runs.max(1) as f32
+                } else {
+                    item.quantity as f32
+                };
+                prices
+                    .entry(key)
+                    .or_default()
+                    .push(x.price / units);
+            });
+
+        let mut stacks = stacks
+            .into_iter()
+            .map(|(key, mut stack)| {
+                if let Some(x) = prices.get_mut(&key.price_key()) {
+                    let price = if key.is_copy {
+                        Self::median(x) * key.runs.max(1) as f32
+                    } else {
+                        Self::median(x)
+                    };
+                    stack.contracts = x.len();
+                    stack.price = Some(price);
+                    stack.value = Some(price * stack.count as f32);
+                }
+                stack.item_ids.sort();
+                stack
+            })
+            .collect::<Vec<_>>();
+        stacks.sort_by(|a, b| {
+            b.value
+                .unwrap_or_default()
+                .partial_cmp(&a.value.unwrap_or_default())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.type_id.cmp(&b.type_id))
+                .then(b.count.cmp(&a.count))
+        });
+        Ok(stacks)
+    }
+
+    /// Joins all blueprints of a character and its alts with the SDE.
+    ///
+    /// Blueprints that no longer exist in the SDE, or whose products were
+    /// removed or unpublished in a patch, are flagged as removed.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// Every blueprint together with the products it can build
+    ///
+    pub async fn blueprint_report(
+        &self,
+        token: String
+    ) -> Result<Vec<BlueprintReport>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterBlueprint)
+            .await?;
+        let bps = con
+            .mget::<_, _, CharacterBlueprintEntry>(CacheName::CharacterBlueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+
+        let blueprints = self.eve_data.blueprints().await?;
+        let types = self.eve_data.types().await?;
+        let published = |tid: TypeId| types
+            .type_by_id(tid)
+            .map(|x| x.published)
+            .unwrap_or_default();
+
+        let mut report = bps
+            .into_iter()
+            .map(|bp| {
+                let activity = blueprints
+                    .blueprints()
+                    .get(&bp.type_id)
+                    .map(|x| &x.activities)
+                    .and_then(|x| {
+                        if let Some(x) = &x.manufacturing {
+                            Some((BlueprintActivityKind::Manufacturing, x))
+                        } else {
+                            x.reaction
+                                .as_ref()
+                                .map(|x| (BlueprintActivityKind::Reaction, x))
+                        }
+                    });
+
+                let products = activity
+                    .and_then(|(_, x)| x.products.clone())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|x| BlueprintReportProduct {
+                        type_id:  x.type_id,
+                        quantity: x.quantity,
+                        removed:  !published(x.type_id),
+                    })
+                    .collect::<Vec<_>>();
+                let removed = !published(bp.type_id) ||
+                    products.is_empty() ||
+                    products.iter().all(|x| x.removed);
+
+                let is_copy = bp.quantity == -2;
+                BlueprintReport {
+                    item_id:             bp.item_id,
+                    user_id:             bp.user_id,
+                    type_id:             bp.type_id,
+                    is_copy,
+                    material_efficiency: bp.material_efficiency,
+                    time_efficiency:     bp.time_efficiency,
+                    runs:                if is_copy { Some(bp.runs.max(0) as u32) } else { None },
+                    activity:            activity.map(|(x, _)| x),
+                    products,
+                    removed,
+                }
+            })
+            .collect::<Vec<_>>();
+        report.sort_by_key(|x| (x.type_id, x.item_id));
+        Ok(report)
+    }
+
+    /// Median of the given values, the values must not be empty
+    fn median(values: &mut Vec<f32>) -> f32 {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mid = values.len() / 2;
+        if values.len() % 2 == 0 {
+            (values[mid - 1] + values[mid]) / 2f32
+        } else {
+            values[mid]
+        }
+    }
+
+    /// Shows when the data of the main and its alts was synced the last time
+    /// and when it is synced next.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// Sync state of every data type for every character
+    ///
+    pub async fn sync_status(
+        &self,
+        token: &str,
+    ) -> Result<Vec<CharacterSync>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        self.sync_status_of(&user).await
+    }
+
+    /// Sync state of a main and its alts, the caller must make sure the
+    /// requester is allowed to see it
+    ///
+    /// # Params
+    ///
+    /// `user` -> Main whose sync state is returned
+    ///
+    pub(crate) async fn sync_status_of(
+        &self,
+        user: &UserEntry,
+    ) -> Result<Vec<CharacterSync>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        // Preferences are stored for the main and apply to all alts
+        let preference = con
+            .get::<_, _, UserPreferenceEntry>(CacheName::UserPreference, user.user_id)
+            .await?;
+        let syncs = con
+            .mget::<_, _, Vec<CharacterSyncEntry>>(CacheName::CharacterSync, user_ids.clone())
+            .await?;
+
+        let result = user_ids
+            .into_iter()
+            .zip(syncs)
+            .map(|(user_id, entries)| {
+                let entries = entries.unwrap_or_default();
+                let datasets = UserPreferenceEntry::SYNC_TYPES
+                    .iter()
+                    .map(|typ| {
+                        let frequency = UserPreferenceEntry::sync_frequency(preference.as_ref(), typ);
+                        let last_sync = entries
+                            .iter()
+                            .find(|x| x.typ == *typ)
+                            .map(|x| x.timestamp);
+                        DatasetSync {
+                            typ:       typ.to_string(),
+                            frequency,
+                            last_sync,
+                            next_sync: last_sync.map(|x| x + frequency as u64 * 60 * 1_000),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                CharacterSync {
+                    user_id,
+                    datasets,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(result)
+    }
+
+    /// Net worth snapshots of the character and its alts, or of all
+    /// members if a workspace is given.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Number of days that should be returned and the optional
+    ///            workspace
+    ///
+    /// # Returns
+    ///
+    /// All snapshots in the range, oldest first, together with the change
+    /// between the first and the last snapshot
+    ///
+    pub async fn net_worth(
+        &self,
+        token: &str,
+        query: NetWorthQuery,
+    ) -> Result<Vec<NetWorth>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut con = self
+            .pool
+            .acquire()
+            .await?;
+        let keys = if let Some(x) = query.workspace_id {
+            self.workspace.require_member(&user, x).await?;
+            con
+                .keys::<_, WorkspaceKey>(CacheName::NetWorthHistory)
+                .await?
+                .into_iter()
+                .filter(|k| k.workspace_id == query.workspace_id)
+                .collect::<Vec<_>>()
+        } else {
+            user
+                .aliase
+                .iter()
+                .map(|x| x.user_id)
+                .chain(std::iter::once(user.user_id))
+                .map(WorkspaceKey::private)
+                .collect::<Vec<_>>()
+        };
+
+        let since = query
+            .days
+            .map(|x| Utc::now().timestamp_millis() as u64 - x as u64 * 24 * 60 * 60 * 1_000)
+            .unwrap_or_default();
+
+        let result = con
+            .mget::<_, _, Vec<NetWorthEntry>>(CacheName::NetWorthHistory, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .map(|(entries, key)| {
+                let entries = entries
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|x| x.timestamp >= since)
+                    .collect::<Vec<_>>();
+                NetWorth::new(key.user_id, entries)
+            })
+            .collect::<Vec<_>>();
+        Ok(result)
+    }
+
     /// Gets a blueprint by its [ItemId]
     ///
     /// # Params
@@ -216,7 +1262,7 @@ impl CharacterService {
             None
         };
         let corp_name = character_service
-            .corporation_name(character.corporation_id.into())
+            .corporation_name(character.corporation_id)
             .await?;
 
         let character = Character::new(
@@ -230,7 +1276,296 @@ impl CharacterService {
     }
 }
 
+/// Cost basis of a single asset stack
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetCostBasis {
+    item_id:           ItemId,
+    location_id:       LocationId,
+    type_id:           TypeId,
+    user_id:           CharacterId,
+    quantity:          u32,
+    /// Quantity of the stack that could be matched with a buy transaction
+    covered:           u32,
+    /// Average price paid per unit, None if no transaction was found
+    cost_basis:        Option<f32>,
+    /// Current average market price per unit
+    market_price:      Option<f32>,
+    /// Difference between market value and cost of the covered quantity
+    unrealized_profit: Option<f32>,
+}
+
+/// Planetary colony of a character
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PlanetColony {
+    planet_id:     PlanetId,
+    user_id:       CharacterId,
+    system_id:     SolarSystemId,
+    planet_type:   String,
+    upgrade_level: u8,
+    /// Timestamp in milliseconds when the first extractor runs dry
+    next_expiry:   Option<u64>,
+    extractors:    Vec<PlanetExtractor>,
+    factories:     Vec<PlanetFactory>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PlanetExtractor {
+    pin_id:          u64,
+    product_type_id: Option<TypeId>,
+    /// Timestamp in milliseconds when the extractor stops
+    expiry:          Option<u64>,
+    /// true if the extractor is no longer running
+    expired:         bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PlanetFactory {
+    pin_id:       u64,
+    schematic_id: SchematicId,
+    /// Name of the schematic
+    name:         String,
+    /// Item that is produced
+    output:       Option<TypeId>,
+    /// Cycle time in seconds
+    cycle_time:   u32,
+}
+
+/// Filter for the contracts of a character
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ContractQuery {
+    /// For example `outstanding` or `finished`
+    pub status: Option<String>,
+    /// `item_exchange`, `courier` or `auction`
+    #[serde(rename = "type")]
+    pub typ:    Option<String>,
+}
+
+/// Contract together with the volume of the included items
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CharacterContract {
+    #[serde(flatten)]
+    contract:    CharacterContractEntry,
+    /// Volume of all included items in m³, based on the item volumes
+    item_volume: f32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CharacterSync {
+    pub user_id:  CharacterId,
+    pub datasets: Vec<DatasetSync>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DatasetSync {
+    /// One of [UserPreferenceEntry::SYNC_TYPES]
+    #[serde(rename = "type")]
+    pub typ:       String,
+    /// Minutes between two syncs
+    pub frequency: u32,
+    /// Timestamp in milliseconds, None if it was never synced
+    pub last_sync: Option<u64>,
+    /// Timestamp in milliseconds, None if it was never synced
+    pub next_sync: Option<u64>,
+}
+
+/// Identifies blueprints that are identical
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct BlueprintStackKey {
+    type_id:             TypeId,
+    is_copy:             bool,
+    material_efficiency: u32,
+    time_efficiency:     u32,
+    runs:                i32,
+}
+
+impl BlueprintStackKey {
+    /// Contracts are compared without the runs, copies are valued per run
+    fn price_key(&self) -> (TypeId, bool, u32, u32) {
+        (self.type_id, self.is_copy, self.material_efficiency, self.time_efficiency)
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlueprintReport {
+    pub item_id:             ItemId,
+    pub user_id:             CharacterId,
+    pub type_id:             TypeId,
+    pub is_copy:             bool,
+    pub material_efficiency: u32,
+    pub time_efficiency:     u32,
+    /// Remaining runs of a copy, None for originals
+    pub runs:                Option<u32>,
+    /// Activity that builds the products, None if the blueprint is not in
+    /// the SDE
+    pub activity:            Option<BlueprintActivityKind>,
+    pub products:            Vec<BlueprintReportProduct>,
+    /// The blueprint or all of its products were removed from the game
+    pub removed:             bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlueprintReportProduct {
+    pub type_id:  TypeId,
+    /// Quantity produced by a single run
+    pub quantity: u32,
+    /// The product no longer exists or is unpublished
+    pub removed:  bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BlueprintActivityKind {
+    Manufacturing,
+    Reaction,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlueprintStack {
+    pub type_id:             TypeId,
+    pub is_copy:             bool,
+    pub material_efficiency: u32,
+    pub time_efficiency:     u32,
+    /// Runs of every copy in the stack, -1 for originals
+    pub runs:                i32,
+    /// Number of blueprints in the stack
+    pub count:               u32,
+    pub item_ids:            Vec<ItemId>,
+    /// Median contract price of a single blueprint, None if there is no
+    /// matching contract
+    pub price:               Option<f32>,
+    /// Value of the whole stack, None if there is no matching contract
+    pub value:               Option<f32>,
+    /// Number of contracts the price is based on
+    pub contracts:           usize,
+}
+
+impl From<BlueprintStackKey> for BlueprintStack {
+    fn from(x: BlueprintStackKey) -> Self {
+        Self {
+            type_id:             x.type_id,
+            is_copy:             x.is_copy,
+            material_efficiency: x.material_efficiency,
+            time_efficiency:     x.time_efficiency,
+            runs:                x.runs,
+            count:               0,
+            item_ids:            Vec::new(),
+            price:               None,
+            value:               None,
+            contracts:           0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NetWorthQuery {
+    /// Only snapshots of the last days, all snapshots if not set
+    pub days:         Option<u32>,
+    /// Snapshots of all members of the workspace instead of the own ones
+    pub workspace_id: Option<Uuid>,
+}
+
 #[derive(Debug, Serialize)]
+pub struct NetWorth {
+    pub user_id:      CharacterId,
+    /// Change of the asset value between the first and last snapshot
+    pub asset_delta:  i64,
+    /// Change of the wallet between the first and last snapshot
+    pub wallet_delta: i64,
+    /// Change of assets and wallet together
+    pub total_delta:  i64,
+    /// Oldest snapshot first
+    pub entries:      Vec<NetWorthEntry>,
+}
+
+impl NetWorth {
+    fn new(
+        user_id: CharacterId,
+        entries: Vec<NetWorthEntry>,
+    ) -> Self {
+        let delta = |f: fn(&NetWorthEntry) -> u64| {
+            match (entries.first(), entries.last()) {
+                (Some(a), Some(b)) => f(b) as i64 - f(a) as i64,
+                _                  => 0
+            }
+        };
+        let asset_delta = delta(|x| x.asset_value);
+        let wallet_delta = delta(|x| x.wallet_balance);
+
+        Self {
+            user_id,
+            asset_delta,
+            wallet_delta,
+            total_delta: asset_delta + wallet_delta,
+            entries,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HaulingQuery {
+    /// Capacity in m3, defaults to a freighter without expanders
+    pub freighter_capacity: Option<f32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetVolume {
+    /// Station or structure, if the root location is not known, the id of
+    /// the outermost container
+    pub location_id:     LocationId,
+    /// Volume in m3
+    pub volume:          f32,
+    pub freighter_trips: u32,
+    pub by_category:     Vec<AssetVolumeCategory>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetVolumeCategory {
+    /// None if the item is unknown
+    pub category_id: Option<CategoryId>,
+    /// Volume in m3
+    pub volume:      f32,
+}
+
+/// Worth of all assets, grouped in different ways
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetWorth {
+    /// Combined value of all assets
+    total:        f32,
+    /// Grouped by the category of the item, None if the item is unknown
+    by_category:  Vec<AssetWorthShare<Option<CategoryId>>>,
+    /// Grouped by the region the item is in, None if the region could not be
+    /// resolved
+    by_region:    Vec<AssetWorthShare<Option<RegionId>>>,
+    /// Grouped by the owning character
+    by_character: Vec<AssetWorthShare<CharacterId>>,
+}
+
+/// Value of a single group
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AssetWorthShare<T> {
+    id:    T,
+    value: f32,
+    /// Percentage of the total worth, between 0 and 100
+    share: f32,
+}
+
+impl<T: Eq + Hash> AssetWorthShare<T> {
+    /// Converts the grouped values into a list, sorted by the highest value
+    fn from_map(groups: HashMap<T, f32>, total: f32) -> Vec<Self> {
+        let mut shares = groups
+            .into_iter()
+            .map(|(id, value)| Self {
+                id,
+                value,
+                share: if total > 0f32 { value / total * 100f32 } else { 0f32 },
+            })
+            .collect::<Vec<_>>();
+        shares.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap_or(std::cmp::Ordering::Equal));
+        shares
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct WhoAmI {
     /// Name of the user
     name:             String,
@@ -267,13 +1602,13 @@ impl WhoAmI {
             ),
             alliance_icon: alliance,
             user_id,
-            corp_id: character.corporation_id.into()
+            corp_id: character.corporation_id
         }
     }
 }
 
 /// Represents a character with all its information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct Character {
     name:          String,
     portrait:      String,
@@ -314,7 +1649,7 @@ impl Character {
             alliance_icon,
             aliase,
             user_id,
-            corp_id: character.corporation_id.into()
+            corp_id: character.corporation_id
         }
     }
 }
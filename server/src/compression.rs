@@ -0,0 +1,394 @@
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, MarketPriceEntry};
+use caph_eve_data_wrapper::{EveDataWrapper, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Plans which ores should be bought to cover a mineral requirement
+#[derive(Clone)]
+pub struct CompressionService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+}
+
+impl CompressionService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+        }
+    }
+
+    /// All ores and ice that have a compressed variant.
+    ///
+    /// # Returns
+    ///
+    /// List of uncompressed and compressed type ids
+    ///
+    pub async fn ores(&self) -> Result<Vec<CompressedOre>, EveServerError> {
+        let mut ores = self
+            .eve_data
+            .types()
+            .await?
+            .compressed_ores()
+            .into_iter()
+            .map(|(ore, compressed)| CompressedOre {
+                ore,
+                compressed,
+            })
+            .collect::<Vec<_>>();
+        ores.sort_by_key(|x| x.ore);
+        Ok(ores)
+    }
+
+    /// Cheapest combination of ores that covers the given minerals after
+    /// reprocessing.
+    ///
+    /// # Params
+    ///
+    /// `request` -> Required minerals, reprocessing yield and if only
+    ///              compressed ores should be considered
+    ///
+    /// # Returns
+    ///
+    /// Ores to buy, the resulting minerals and all minerals that can not be
+    /// covered by any ore
+    ///
+    pub async fn plan(
+        &self,
+        request: CompressionRequest,
+    ) -> Result<CompressionPlan, EveServerError> {
+        let types = self.eve_data.types().await?;
+
+        let mut ore_ids = types
+            .compressed_ores()
+            .into_iter()
+            .flat_map(|(ore, compressed)| {
+                if request.only_compressed {
+                    vec![compressed]
+                } else {
+                    vec![ore, compressed]
+                }
+            })
+            .collect::<Vec<_>>();
+        ore_ids.sort();
+        ore_ids.dedup();
+
+        let prices = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, ore_ids.clone())
+            .await?
+            .into_iter()
+            .zip(ore_ids.iter())
+            .filter_map(|(x, tid)| x.map(|x| (*tid, x.average_price)))
+            .filter(|(_, price)| *price > 0f32)
+            .collect::<HashMap<_, _>>();
+
+        let mut minerals = request
+            .minerals
+            .iter()
+            .filter(|x| x.quantity > 0)
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        minerals.sort();
+        minerals.dedup();
+
+        // Ores without a price can not be bought
+        let ores = ore_ids
+            .into_iter()
+            .filter(|x| prices.contains_key(x))
+            .filter_map(|tid| {
+                let portion_size = types
+                    .type_by_id(tid)
+                    .map(|x| x.portion_size.max(1) as u64)
+                    .unwrap_or(1);
+                let materials = types.materials().get(&tid)?;
+                let yields = minerals
+                    .iter()
+                    .map(|mid| {
+                        materials
+                            .materials
+                            .iter()
+                            .find(|x| x.material_type_id == *mid)
+                            .map(|x| (x.quantity as f64 * request.efficiency as f64).floor())
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>();
+                Some(OreCandidate {
+                    type_id: tid,
+                    portion_size,
+                    cost: prices[&tid] as f64 * portion_size as f64,
+                    yields,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let required = request
+            .minerals
+            .iter()
+            .fold(HashMap::new(), |mut acc: HashMap<TypeId, u64>, x| {
+                *acc.entry(x.type_id).or_default() += x.quantity;
+                acc
+            });
+
+        // Minerals that no ore yields make the problem unsolvable
+        let (minerals, missing): (Vec<_>, Vec<_>) = minerals
+            .into_iter()
+            .enumerate()
+            .partition(|(i, _)| ores.iter().any(|x| x.yields[*i] > 0f64));
+        let missing = missing
+            .into_iter()
+            .map(|(_, mid)| mid)
+            .collect::<Vec<_>>();
+
+        let costs = ores
+            .iter()
+            .map(|x| x.cost)
+            .collect::<Vec<_>>();
+        let yields = ores
+            .iter()
+            .map(|x| minerals.iter().map(|(i, _)| x.yields[*i]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let requirements = minerals
+            .iter()
+            .map(|(_, mid)| required.get(mid).copied().unwrap_or_default() as f64)
+            .collect::<Vec<_>>();
+
+        // All minerals that can not be covered were removed, so there is
+        // always a solution
+        let portions = Self::solve(&costs, &yields, &requirements)
+            .unwrap_or_default();
+
+        let mut produced: HashMap<TypeId, u64> = HashMap::new();
+        let mut purchases = Vec::new();
+        for ((ore, yields), portions) in ores.iter().zip(yields.iter()).zip(portions) {
+            // Only full portions can be reprocessed
+            let portions = (portions - 1e-6).ceil().max(0f64) as u64;
+            if portions == 0 {
+                continue;
+            }
+
+            for ((_, mid), quantity) in minerals.iter().zip(yields) {
+                *produced.entry(*mid).or_default() += *quantity as u64 * portions;
+            }
+
+            purchases.push(OrePurchase {
+                type_id:  ore.type_id,
+                quantity: portions * ore.portion_size,
+                cost:     ore.cost as f32 * portions as f32,
+            });
+        }
+        purchases.sort_by(|a, b| b.cost.partial_cmp(&a.cost).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut result = minerals
+            .iter()
+            .map(|(_, mid)| MineralYield {
+                type_id:  *mid,
+                required: required.get(mid).copied().unwrap_or_default(),
+                produced: produced.get(mid).copied().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        result.sort_by_key(|x| x.type_id);
+
+        Ok(CompressionPlan {
+            total_cost: purchases.iter().map(|x| x.cost).sum(),
+            ores:       purchases,
+            minerals:   result,
+            missing,
+        })
+    }
+
+    /// Minimizes `costs * x` so that `yields^T * x >= required` and `x >= 0`.
+    ///
+    /// The dual of the problem has a feasible start with only slack
+    /// variables, so it is solved with the simplex method and the amount of
+    /// every ore is read from the reduced costs of the slack variables.
+    /// Bland's rule is used to prevent cycling.
+    ///
+    /// # Returns
+    ///
+    /// Amount of every ore, [None] if a requirement can not be covered
+    ///
+    fn solve(
+        costs:    &[f64],
+        yields:   &[Vec<f64>],
+        required: &[f64],
+    ) -> Option<Vec<f64>> {
+        const EPSILON: f64 = 1e-9;
+
+        let rows = costs.len();
+        let vars = required.len();
+        let cols = vars + rows;
+
+        // Every row is one ore: yields * y + slack = cost
+        let mut tableau = (0..rows)
+            .map(|i| {
+                let mut row = vec![0f64; cols + 1];
+                row[..vars].copy_from_slice(&yields[i]);
+                row[vars + i] = 1f64;
+                row[cols] = costs[i];
+                row
+            })
+            .collect::<Vec<_>>();
+        let mut objective = vec![0f64; cols + 1];
+        for (j, x) in required.iter().enumerate() {
+            objective[j] = -x;
+        }
+        let mut basis = (vars..cols).collect::<Vec<_>>();
+
+        loop {
+            let entering = if let Some(x) = (0..cols).find(|x| objective[*x] < -EPSILON) {
+                x
+            } else {
+                break;
+            };
+
+            let mut leaving: Option<usize> = None;
+            for i in 0..rows {
+                if tableau[i][entering] <= EPSILON {
+                    continue;
+                }
+                let ratio = tableau[i][cols] / tableau[i][entering];
+                leaving = match leaving {
+                    Some(l) => {
+                        let best = tableau[l][cols] / tableau[l][entering];
+                        if ratio < best - EPSILON ||
+                           (ratio < best + EPSILON && basis[i] < basis[l]) {
+                            Some(i)
+                        } else {
+                            Some(l)
+                        }
+                    },
+                    None => Some(i),
+                };
+            }
+            // Unbounded dual, the requirement can not be covered
+            let leaving = leaving?;
+
+            let pivot = tableau[leaving][entering];
+            tableau[leaving].iter_mut().for_each(|x| *x /= pivot);
+            let pivot_row = tableau[leaving].clone();
+
+            for (i, row) in tableau.iter_mut().enumerate() {
+                if i == leaving {
+                    continue;
+                }
+                let factor = row[entering];
+                if factor.abs() > EPSILON {
+                    row.iter_mut().zip(pivot_row.iter()).for_each(|(x, p)| *x -= factor * p);
+                }
+            }
+            let factor = objective[entering];
+            objective.iter_mut().zip(pivot_row.iter()).for_each(|(x, p)| *x -= factor * p);
+
+            basis[leaving] = entering;
+        }
+
+        Some(objective[vars..cols].to_vec())
+    }
+}
+
+struct OreCandidate {
+    type_id:      TypeId,
+    portion_size: u64,
+    /// Price of a single portion
+    cost:         f64,
+    /// Minerals a single portion yields after reprocessing
+    yields:       Vec<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompressedOre {
+    pub ore:        TypeId,
+    pub compressed: TypeId,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CompressionRequest {
+    pub minerals:        Vec<MineralRequirement>,
+    /// Reprocessing yield, between 0.0 and 1.0
+    pub efficiency:      f32,
+    /// Only considers compressed ores, for example when they have to be
+    /// hauled
+    #[serde(default)]
+    pub only_compressed: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MineralRequirement {
+    pub type_id:  TypeId,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompressionPlan {
+    pub total_cost: f32,
+    pub ores:       Vec<OrePurchase>,
+    pub minerals:   Vec<MineralYield>,
+    /// Minerals that can not be gained from any ore
+    pub missing:    Vec<TypeId>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrePurchase {
+    pub type_id:  TypeId,
+    /// Number of units to buy, always full portions
+    pub quantity: u64,
+    pub cost:     f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MineralYield {
+    pub type_id:  TypeId,
+    pub required: u64,
+    pub produced: u64,
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len());
+        a.iter().zip(b).for_each(|(a, b)| assert!((a - b).abs() < 1e-6, "{:?} != {:?}", a, b));
+    }
+
+    #[test]
+    fn solve_single_ore() {
+        let x = CompressionService::solve(&[10f64], &[vec![100f64]], &[1000f64]).unwrap();
+        assert_close(&x, &[10f64]);
+    }
+
+    #[test]
+    fn solve_cheapest_mix() {
+        // The first ore only yields the first mineral, the second only the
+        // second mineral and the third yields both but is expensive
+        let costs = [1f64, 1f64, 3f64];
+        let yields = [
+            vec![10f64, 0f64],
+            vec![0f64, 10f64],
+            vec![10f64, 10f64],
+        ];
+        let x = CompressionService::solve(&costs, &yields, &[100f64, 50f64]).unwrap();
+        assert_close(&x, &[10f64, 5f64, 0f64]);
+
+        // Now the third ore is cheaper than buying both
+        let costs = [1f64, 1f64, 1.5f64];
+        let x = CompressionService::solve(&costs, &yields, &[100f64, 50f64]).unwrap();
+        assert_close(&x, &[5f64, 0f64, 5f64]);
+    }
+
+    #[test]
+    fn solve_uncoverable() {
+        let x = CompressionService::solve(&[1f64], &[vec![10f64, 0f64]], &[10f64, 10f64]);
+        assert!(x.is_none());
+    }
+}
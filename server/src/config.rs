@@ -0,0 +1,345 @@
+use crate::auth::SsoConfig;
+use crate::error::EveServerError;
+use crate::public::PublicCache;
+
+use caph_eve_data_wrapper::CharacterId;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::RwLock;
+
+/// Settings of the server.
+///
+/// The settings are read from the TOML file in the environment variable
+/// `SERVER_CONFIG`, `./server.toml` if not set. Missing files or keys fall
+/// back to the defaults. The environment variables `ADMIN_USERS` and
+/// `PUBLIC_API` overwrite the values of the file.
+///
+/// Sending `SIGHUP` reloads the file, only [Settings] are applied, all other
+/// values require a restart.
+#[derive(Clone)]
+pub struct ConfigService {
+    config:   Config,
+    settings: Arc<RwLock<Settings>>,
+}
+
+impl ConfigService {
+    const ENV_CONFIG:  &'static str = "SERVER_CONFIG";
+    const ENV_ADMINS:  &'static str = "ADMIN_USERS";
+    const ENV_CACHES:  &'static str = "PUBLIC_API";
+
+    const DEFAULT_PATH: &'static str = "./server.toml";
+
+    /// ESI caches most public routes for at least a minute
+    const MIN_POLL_INTERVAL: u64 = 60;
+
+    /// Loads and validates the config
+    pub fn load() -> Result<Self, EveServerError> {
+        let (config, settings) = Self::read()?;
+        log::info!("Loaded config {:?}", config);
+
+        Ok(Self {
+            config,
+            settings: Arc::new(RwLock::new(settings)),
+        })
+    }
+
+    /// Values that are only read on startup
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Mains that are always admins
+    pub async fn admins(&self) -> Vec<CharacterId> {
+        self.settings.read().await.admins.clone()
+    }
+
+    /// Caches that are published in the public api
+    pub async fn public_caches(&self) -> Vec<PublicCache> {
+        self.settings.read().await.public_caches.clone()
+    }
+
+    /// Number of requests a single address can make per minute in the
+    /// public api
+    pub async fn public_rate_limit(&self) -> u32 {
+        self.settings.read().await.public_rate_limit
+    }
+
+    /// Time between two requests for the active incursions
+    pub async fn incursion_poll(&self) -> Duration {
+        Duration::from_secs(self.settings.read().await.incursion_poll)
+    }
+
+    /// Reloads the settings every time the process receives `SIGHUP`.
+    ///
+    /// Invalid configs are logged and the previous settings are kept.
+    ///
+    /// This function is blocking
+    pub async fn watch(&self) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(x)  => x,
+            Err(e) => {
+                log::error!("Error listening for SIGHUP {:?}", e);
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            match Self::read() {
+                Ok((config, settings)) => {
+                    if config != self.config {
+                        log::warn!("Changes outside of the settings require a restart");
+                    }
+                    log::info!("Reloaded settings {:?}", settings);
+                    *self.settings.write().await = settings;
+                },
+                Err(e) => log::error!("Error reloading config {:?}", e),
+            }
+        }
+    }
+
+    fn read() -> Result<(Config, Settings), EveServerError> {
+        let path = std::env::var(Self::ENV_CONFIG)
+            .unwrap_or_else(|_| Self::DEFAULT_PATH.into());
+        let file = match std::fs::read_to_string(&path) {
+            Ok(x)  => toml::from_str::<ConfigFile>(&x)
+                .map_err(|e| EveServerError::InvalidConfig(e.to_string()))?,
+            Err(_) => {
+                log::info!("No config at {}, using defaults", path);
+                ConfigFile::default()
+            }
+        };
+        Self::parse(
+            file,
+            std::env::var(Self::ENV_ADMINS).ok(),
+            std::env::var(Self::ENV_CACHES).ok(),
+        )
+    }
+
+    /// Applies the environment variables and validates the result
+    fn parse(
+        mut file: ConfigFile,
+        admins:   Option<String>,
+        caches:   Option<String>,
+    ) -> Result<(Config, Settings), EveServerError> {
+        if let Some(x) = admins {
+            file.settings.admins = x
+                .split(',')
+                .filter_map(|x| x.trim().parse::<u32>().ok())
+                .map(CharacterId::from)
+                .collect::<Vec<_>>();
+        }
+        if let Some(x) = caches {
+            file.settings.public_caches = x
+                .split(',')
+                .map(|x| x.trim().to_string())
+                .collect::<Vec<_>>();
+        }
+
+        let config = file.config;
+        if config.db.pool_size == 0 {
+            return Err(EveServerError::InvalidConfig("db.pool_size must be greater than 0".into()));
+        }
+        if config.db.address.parse::<SocketAddr>().is_err() {
+            return Err(EveServerError::InvalidConfig(format!("db.address {} is not an address", config.db.address)));
+        }
+        if config.db.invalidation_address.parse::<SocketAddr>().is_err() {
+            return Err(EveServerError::InvalidConfig(format!("db.invalidation_address {} is not an address", config.db.invalidation_address)));
+        }
+        if let Some(x) = config.esi.api_url.as_ref() {
+            if !x.starts_with("http://") && !x.starts_with("https://") {
+                return Err(EveServerError::InvalidConfig(format!("esi.api_url {} is not an url", x)));
+            }
+        }
+
+        if let Some(x) = config.sso.callback_url.as_ref() {
+            if !x.starts_with("http://") && !x.starts_with("https://") {
+                return Err(EveServerError::InvalidConfig(format!("sso.callback_url {} is not an url", x)));
+            }
+        }
+        if config.sso.scopes.is_empty() {
+            return Err(EveServerError::InvalidConfig("sso.scopes must not be empty".into()));
+        }
+
+        let settings = file.settings;
+        if settings.public_rate_limit == 0 {
+            return Err(EveServerError::InvalidConfig("settings.public_rate_limit must be greater than 0".into()));
+        }
+        if settings.incursion_poll < Self::MIN_POLL_INTERVAL {
+            return Err(EveServerError::InvalidConfig(format!("settings.incursion_poll must be at least {}", Self::MIN_POLL_INTERVAL)));
+        }
+
+        let settings = Settings {
+            admins:            settings.admins,
+            public_caches:     PublicCache::parse_list(&settings.public_caches.join(",")),
+            public_rate_limit: settings.public_rate_limit,
+            incursion_poll:    settings.incursion_poll,
+        };
+        Ok((config, settings))
+    }
+}
+
+/// Layout of the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    #[serde(flatten)]
+    config:   Config,
+    settings: SettingsFile,
+}
+
+/// Values that are only read on startup
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub db:       DbConfig,
+    pub server:   ServerConfig,
+    pub esi:      EsiConfig,
+    pub sso:      SsoConfig,
+    pub features: FeatureConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbConfig {
+    pub address:              String,
+    pub pool_size:            usize,
+    /// Address the invalidation messages of the database are received on,
+    /// the database listens on `CAPH_DB_INVALIDATION_ADDRESS`
+    pub invalidation_address: String,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            address:              "0.0.0.0:55555".into(),
+            pool_size:            100,
+            invalidation_address: "127.0.0.1:55557".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: ([0, 0, 0, 0], 10101).into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EsiConfig {
+    /// Overwrites the url of ESI, for example to use a mock server, if not
+    /// set `EVE_API_URL` or ESI is used
+    pub api_url: Option<String>,
+}
+
+/// Parts of the api that can be turned off
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// Caching proxy for public ESI routes under `/esi/`, for other tools
+    /// in the same network
+    pub esi_proxy:  bool,
+    pub graphql:    bool,
+    pub public_api: bool,
+    /// Websocket for events and contract snipes
+    pub websocket:  bool,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            esi_proxy:  false,
+            graphql:    true,
+            public_api: true,
+            websocket:  true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SettingsFile {
+    admins:            Vec<CharacterId>,
+    public_caches:     Vec<String>,
+    public_rate_limit: u32,
+    /// Seconds between two requests for the active incursions
+    incursion_poll:    u64,
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        Self {
+            admins:            Vec::new(),
+            public_caches:     Vec::new(),
+            public_rate_limit: 60,
+            incursion_poll:    5 * 60,
+        }
+    }
+}
+
+/// Values that can be changed without a restart
+#[derive(Clone, Debug)]
+struct Settings {
+    admins:            Vec<CharacterId>,
+    public_caches:     Vec<PublicCache>,
+    public_rate_limit: u32,
+    incursion_poll:    u64,
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let (config, settings) = ConfigService::parse(ConfigFile::default(), None, None).unwrap();
+        assert_eq!(config, Config::default());
+        assert!(settings.admins.is_empty());
+        assert_eq!(settings.incursion_poll, 300);
+    }
+
+    #[test]
+    fn file_and_env() {
+        let file = toml::from_str::<ConfigFile>(r#"
+            [db]
+            pool_size = 10
+
+            [features]
+            graphql = false
+
+            [settings]
+            admins = [1, 2]
+            public_caches = ["items"]
+        "#).unwrap();
+
+        let (config, settings) = ConfigService::parse(file, Some("3".into()), None).unwrap();
+        assert_eq!(config.db.pool_size, 10);
+        assert_eq!(config.db.address, "0.0.0.0:55555");
+        assert!(!config.features.graphql);
+        assert!(config.features.public_api);
+        assert_eq!(settings.admins, vec![3.into()]);
+        assert_eq!(settings.public_caches, vec![PublicCache::Items]);
+    }
+
+    #[test]
+    fn invalid() {
+        let file = toml::from_str::<ConfigFile>("[settings]\nincursion_poll = 5").unwrap();
+        assert!(ConfigService::parse(file, None, None).is_err());
+
+        let file = toml::from_str::<ConfigFile>("[sso]\ncallback_url = \"localhost\"").unwrap();
+        assert!(ConfigService::parse(file, None, None).is_err());
+
+        assert!(toml::from_str::<ConfigFile>("[db]\nunknown = 1").is_err());
+    }
+}
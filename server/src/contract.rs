@@ -0,0 +1,228 @@
+use crate::error::EveServerError;
+use crate::invalidation::InvalidationService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, ContractEntry, ContractItemEntry};
+use caph_eve_data_wrapper::{ContractId, LocationId, RegionId, TypeId};
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use warp::ws::{Message, WebSocket};
+
+/// Service for finding underpriced public contracts
+#[derive(Clone)]
+pub struct ContractService {
+    pool:         ConnectionPool,
+    invalidation: InvalidationService,
+}
+
+impl ContractService {
+    /// Interval in which connected websockets are checked for new contracts,
+    /// if the database does not notify about new contracts earlier
+    const FEED_INTERVAL: u64 = 60;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:         ConnectionPool,
+        invalidation: InvalidationService,
+    ) -> Self {
+        Self {
+            pool,
+            invalidation,
+        }
+    }
+
+    /// Gets all item exchange contracts that are worth more than they cost.
+    ///
+    /// # Params
+    ///
+    /// `query` -> Minimum profit a contract must have
+    ///
+    /// # Returns
+    ///
+    /// List of all contracts that are not expired and exceed the profit
+    /// threshold, sorted by profit
+    ///
+    pub async fn snipes(
+        &self,
+        query: SnipeQuery,
+    ) -> Result<Vec<ContractSnipe>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let keys = con
+            .keys::<_, ContractId>(CacheName::Contract)
+            .await?;
+        let mut snipes = con
+            .mget::<_, _, ContractEntry>(CacheName::Contract, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.expire > now)
+            .filter(|x| x.profit() >= query.min_profit)
+            .map(ContractSnipe::from)
+            .collect::<Vec<_>>();
+        snipes.sort_by(|a, b| b.profit.partial_cmp(&a.profit).unwrap());
+        Ok(snipes)
+    }
+
+    /// Searches all public item exchange contracts and appraises them.
+    ///
+    /// # Params
+    ///
+    /// `query` -> Item the contract must contain, region and whether only
+    ///            contracts below market value should be returned
+    ///
+    /// # Returns
+    ///
+    /// List of all matching contracts that are not expired, sorted by profit
+    ///
+    pub async fn search(
+        &self,
+        query: ContractSearchQuery,
+    ) -> Result<Vec<ContractAppraisal>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let keys = con
+            .keys::<_, ContractId>(CacheName::Contract)
+            .await?;
+        let mut contracts = con
+            .mget::<_, _, ContractEntry>(CacheName::Contract, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.expire > now)
+            .filter(|x| query.region_id.map(|r| r == x.region_id).unwrap_or(true))
+            .filter(|x| {
+                query
+                    .type_id
+                    .map(|t| x.items.iter().any(|i| i.is_included && i.type_id == t))
+                    .unwrap_or(true)
+            })
+            .map(ContractAppraisal::from)
+            .filter(|x| !query.below_market || x.below_market)
+            .collect::<Vec<_>>();
+        contracts.sort_by(|a, b| b.profit.partial_cmp(&a.profit).unwrap());
+        Ok(contracts)
+    }
+
+    /// Pushes new underpriced contracts to the given websocket.
+    ///
+    /// Every contract is only sent once per connection. New contracts are
+    /// checked as soon as the database reports a change. The feed stops as
+    /// soon as the client disconnects.
+    ///
+    pub async fn feed(
+        &self,
+        socket: WebSocket,
+        query:  SnipeQuery,
+    ) {
+        let (mut tx, _) = socket.split();
+        let mut sent = HashSet::new();
+        let mut changes = self.invalidation.subscribe();
+
+        loop {
+            let snipes = match self.snipes(query).await {
+                Ok(x) => x,
+                Err(e) => {
+                    log::error!("Error loading contract snipes {:?}", e);
+                    Vec::new()
+                }
+            };
+
+            let new = snipes
+                .into_iter()
+                .filter(|x| sent.insert(x.contract_id))
+                .collect::<Vec<_>>();
+            if !new.is_empty() {
+                let message = serde_json::to_string(&new).unwrap_or_default();
+                if tx.send(Message::text(message)).await.is_err() {
+                    break;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(Self::FEED_INTERVAL)) => {},
+                _ = InvalidationService::wait(&mut changes, vec![CacheName::Contract]) => {},
+            }
+        }
+    }
+}
+
+/// Query parameters for filtering contracts
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SnipeQuery {
+    #[serde(default)]
+    pub min_profit: f32,
+}
+
+/// Query parameters for searching contracts
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ContractSearchQuery {
+    /// Item that must be included in the contract
+    pub type_id:      Option<TypeId>,
+    pub region_id:    Option<RegionId>,
+    /// Only return contracts that are cheaper than their items
+    #[serde(default)]
+    pub below_market: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContractAppraisal {
+    pub contract_id:  ContractId,
+    pub expire:       u64,
+    pub location_id:  LocationId,
+    pub region_id:    RegionId,
+    pub price:        f32,
+    /// Value of the items based on the market price
+    pub value:        f32,
+    pub profit:       f32,
+    pub below_market: bool,
+    pub items:        Vec<ContractItemEntry>,
+}
+
+impl From<ContractEntry> for ContractAppraisal {
+    fn from(x: ContractEntry) -> Self {
+        let profit = x.profit();
+
+        Self {
+            contract_id:  x.contract_id,
+            expire:       x.expire,
+            location_id:  x.location_id,
+            region_id:    x.region_id,
+            price:        x.price,
+            value:        x.value,
+            profit,
+            below_market: profit > 0f32,
+            items:        x.items,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContractSnipe {
+    pub contract_id: ContractId,
+    pub expire:      u64,
+    pub location_id: LocationId,
+    pub region_id:   RegionId,
+    pub price:       f32,
+    pub value:       f32,
+    pub profit:      f32,
+}
+
+impl From<ContractEntry> for ContractSnipe {
+    fn from(x: ContractEntry) -> Self {
+        Self {
+            contract_id: x.contract_id,
+            expire:      x.expire,
+            location_id: x.location_id,
+            region_id:   x.region_id,
+            price:       x.price,
+            value:       x.value,
+            profit:      x.profit(),
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, CorporationBlueprintEntry};
+use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, CorporationAssetEntry, CorporationBlueprintEntry, UserEntry};
 use caph_eve_data_wrapper::EveDataWrapper;
 use caph_eve_data_wrapper::ItemLocation;
 use caph_eve_data_wrapper::{CharacterId, CorporationId, ItemId};
 use serde::Serialize;
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::asset::{AssetQuery, AssetService, AssetStack};
 use crate::error::EveServerError;
 use crate::eve::EveAuthService;
 
@@ -15,38 +19,181 @@ use crate::eve::EveAuthService;
 #[derive(Clone)]
 pub struct CorporationService {
     pool:     ConnectionPool,
+    asset:    AssetService,
     eve_auth: EveAuthService,
+    eve_data: EveDataWrapper,
+    /// Corporation roles of every character that was checked
+    roles:    Arc<RwLock<HashMap<CharacterId, (Instant, Vec<CorporationRole>)>>>,
 }
 
 impl CorporationService {
+    /// Time until the roles of a character are requested again
+    const ROLE_CACHE_TIME: Duration = Duration::from_secs(10 * 60);
+
     /// Creates a new instance
     pub fn new(
         pool:     ConnectionPool,
+        asset:    AssetService,
         eve_auth: EveAuthService,
+        eve_data: EveDataWrapper,
     ) -> Self {
         Self {
             pool,
+            asset,
             eve_auth,
+            eve_data,
+            roles: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    pub async fn blueprints(
+    /// Makes sure that the main or one of its alts is a member of the
+    /// corporation and has one of the given roles. Directors always pass.
+    ///
+    /// # Params
+    ///
+    /// `cid`   -> Corporation that is accessed
+    /// `token` -> Cookie from the requesting main
+    /// `roles` -> Roles that grant access
+    ///
+    /// # Returns
+    ///
+    /// The main and [EveServerError::MissingCorporationRole] if no character
+    /// has one of the roles
+    ///
+    pub async fn require_role(
         &self,
         cid:   CorporationId,
-        token: String
-    ) -> Result<Vec<CorporationBlueprintEntry>, EveServerError> {
+        token: &str,
+        roles: &[CorporationRole],
+    ) -> Result<UserEntry, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let mut members = user
+            .aliase
+            .iter()
+            .filter(|x| x.corp_id == cid)
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        if user.corp_id == cid {
+            members.insert(0, user.user_id);
+        }
+
+        for member in members {
+            let character_roles = self
+                .character_roles(token, &user, member)
+                .await?;
+            let allowed = character_roles
+                .iter()
+                .any(|x| *x == CorporationRole::Director || roles.contains(x));
+            if allowed {
+                return Ok(user);
+            }
+        }
+
+        Err(EveServerError::MissingCorporationRole(roles.to_vec()))
+    }
+
+    /// Gets the corporation roles of the main or one of its alts, the roles
+    /// are cached for a couple of minutes
+    async fn character_roles(
+        &self,
+        token: &str,
+        user:  &UserEntry,
+        uid:   CharacterId,
+    ) -> Result<Vec<CorporationRole>, EveServerError> {
+        if let Some((fetched, roles)) = self.roles.read().await.get(&uid) {
+            if fetched.elapsed() < Self::ROLE_CACHE_TIME {
+                return Ok(roles.clone());
+            }
+        }
+
+        let access_token = if user.user_id == uid {
+            self.eve_auth.refresh_token(token).await?.access_token
+        } else {
+            self.eve_auth.refresh_token_alt(token, uid).await?.access_token
+        };
+        let roles = self
+            .eve_data
+            .character()
+            .await?
+            .roles(&access_token, uid)
+            .await?
+            .iter()
+            .filter_map(|x| CorporationRole::from_esi(x))
+            .collect::<Vec<_>>();
+
+        self
+            .roles
+            .write()
+            .await
+            .insert(uid, (Instant::now(), roles.clone()));
+        Ok(roles)
+    }
+
+    /// Gets all assets of a corporation, requires the director or accountant
+    /// role
+    ///
+    /// # Params
+    ///
+    /// `cid`   -> Id of the corporation
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Filters
+    ///
+    /// # Returns
+    ///
+    /// Assets of the corporation that match the filters
+    ///
+    pub async fn assets(
+        &self,
+        cid:   CorporationId,
+        token: String,
+        query: AssetQuery,
+    ) -> Result<Vec<AssetStack<CorporationAssetEntry>>, EveServerError> {
+        self
+            .require_role(cid, &token, &[CorporationRole::Director, CorporationRole::Accountant])
+            .await?;
+
         let mut pool = self
             .pool
             .acquire()
             .await?;
 
+        let keys = pool
+            .keys::<_, ItemId>(CacheName::CorporationAsset)
+            .await?;
+        let assets = pool
+            .mget::<_, _, CorporationAssetEntry>(CacheName::CorporationAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.corporation_id == cid)
+            .collect::<Vec<_>>();
+
+        self
+            .asset
+            .query(assets, |x| (x.type_id, x.location_id, x.quantity), query)
+            .await
+    }
+
+    pub async fn blueprints(
+        &self,
+        cid:   CorporationId,
+        token: String
+    ) -> Result<Vec<CorporationBlueprintEntry>, EveServerError> {
         let char_id = self
-            .eve_auth
-            .lookup(&token)
+            .require_role(cid, &token, &[CorporationRole::Director, CorporationRole::Accountant])
             .await?
-            .ok_or(EveServerError::InvalidUser)?
             .user_id;
 
+        let mut pool = self
+            .pool
+            .acquire()
+            .await?;
+
         let blueprint_ids = pool
             .keys::<_, Uuid>(CacheName::CorporationBlueprint)
             .await?;
@@ -62,15 +209,13 @@ impl CorporationService {
 
     pub async fn set_blueprints(
         &self,
-        _:          CorporationId,
+        cid:        CorporationId,
         blueprints: Vec<CorporationBlueprintEntry>,
         token:      String,
     ) -> Result<(), EveServerError> {
         let user = self
-            .eve_auth
-            .lookup(&token)
-            .await?
-            .ok_or(EveServerError::InvalidUser)?;
+            .require_role(cid, &token, &[CorporationRole::Director, CorporationRole::Accountant])
+            .await?;
 
         let mut blueprints = blueprints;
         let blueprints = blueprints
@@ -78,7 +223,7 @@ impl CorporationService {
             .map(|x| {
                 x.id      = Uuid::new_v4();
                 x.char_id = user.user_id;
-                x.corp_id = user.corp_id;
+                x.corp_id = cid;
                 x
             })
             .map(|x| x.clone())
@@ -100,18 +245,16 @@ impl CorporationService {
         cid:   CorporationId,
         token: String
     ) -> Result<(), EveServerError> {
+        let char_id = self
+            .require_role(cid, &token, &[CorporationRole::Director, CorporationRole::Accountant])
+            .await?
+            .user_id;
+
         let mut pool = self
             .pool
             .acquire()
             .await?;
 
-        let char_id = self
-            .eve_auth
-            .lookup(&token)
-            .await?
-            .ok_or(EveServerError::InvalidUser)?
-            .user_id;
-
         let blueprint_ids = pool
             .keys::<_, Uuid>(CacheName::CorporationBlueprint)
             .await?;
@@ -134,3 +277,36 @@ impl CorporationService {
             .map_err(Into::into)
     }
 }
+
+/// Corporation roles that grant access to corporation data
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum CorporationRole {
+    Director,
+    Accountant,
+    StationManager,
+}
+
+impl CorporationRole {
+    /// Converts the role name used by ESI, all roles that are not required
+    /// by any endpoint are ignored
+    pub fn from_esi(role: &str) -> Option<Self> {
+        match role {
+            "Director"        => Some(Self::Director),
+            "Accountant"      => Some(Self::Accountant),
+            "Station_Manager" => Some(Self::StationManager),
+            _                 => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod corporation_tests {
+    use super::*;
+
+    #[test]
+    fn role_from_esi() {
+        assert_eq!(CorporationRole::from_esi("Director"), Some(CorporationRole::Director));
+        assert_eq!(CorporationRole::from_esi("Station_Manager"), Some(CorporationRole::StationManager));
+        assert_eq!(CorporationRole::from_esi("Hangar_Take_1"), None);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::character::CharacterService;
+use crate::error::EveServerError;
+use crate::universe::{RouteFlag, UniverseService};
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, ItemEntry, MarketPriceEntry, UniverseGraphEntry};
+use caph_eve_data_wrapper::{EveDataWrapper, SolarSystemId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Service for pricing and planning courier contracts
+#[derive(Clone)]
+pub struct CourierService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+    universe: UniverseService,
+}
+
+impl CourierService {
+    /// Part of the collateral that is added to the reward for a route that
+    /// only goes through null security space
+    const RISK_COLLATERAL_FEE: f32 = 0.01;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+        universe: UniverseService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+            universe,
+        }
+    }
+
+    /// Suggests reward and collateral for a courier contract.
+    ///
+    /// The base reward is calculated from the number of jumps and the volume.
+    /// The risk of the route increases the reward and adds a fee based on the
+    /// collateral.
+    ///
+    /// # Params
+    ///
+    /// `from`  -> Pickup system
+    /// `to`    -> Destination system
+    /// `query` -> Volume, value of the items and the rates to use
+    ///
+    /// # Returns
+    ///
+    /// Suggested reward and collateral, `None` if there is no route between
+    /// both systems
+    ///
+    pub async fn price(
+        &self,
+        from:  SolarSystemId,
+        to:    SolarSystemId,
+        query: CourierQuery,
+    ) -> Result<Option<CourierPrice>, EveServerError> {
+        let (jumps, risk) = if let Some(x) = self.route_risk(from, to, query.flag).await? {
+            x
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self::quote(
+            jumps,
+            risk,
+            query.volume,
+            query.value,
+            query.isk_per_jump,
+            query.isk_per_m3,
+        )))
+    }
+
+    /// Splits items into loads for the given ships and prices every load as
+    /// courier contract.
+    ///
+    /// Stacks are split between loads if they do not fit into a single ship.
+    /// Ships are counted with their packaged volume. If a maximum collateral
+    /// is given, no load exceeds it.
+    ///
+    /// # Params
+    ///
+    /// `from`    -> Pickup system
+    /// `to`      -> Destination system
+    /// `request` -> Items, ships and the rates to use
+    ///
+    /// # Returns
+    ///
+    /// Loads for every ship, `None` if there is no route between both
+    /// systems
+    ///
+    pub async fn plan(
+        &self,
+        from:    SolarSystemId,
+        to:      SolarSystemId,
+        request: HaulingRequest,
+    ) -> Result<Option<HaulingPlan>, EveServerError> {
+        let (jumps, risk) = if let Some(x) = self.route_risk(from, to, request.flag).await? {
+            x
+        } else {
+            return Ok(None);
+        };
+
+        let mut con = self.pool.acquire().await?;
+        let mut type_ids = request
+            .items
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+        let items = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, type_ids.clone())
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.item_id, x))
+            .collect::<HashMap<_, _>>();
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        let units = request
+            .items
+            .iter()
+            .map(|x| {
+                let volume = items
+                    .get(&x.type_id)
+                    .map(|x| CharacterService::packaged_volume(x.group_id).unwrap_or(x.volume))
+                    .unwrap_or_default();
+                HaulingUnit {
+                    type_id:  x.type_id,
+                    quantity: x.quantity,
+                    volume,
+                    value:    prices.get(&x.type_id).copied().unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let types = self.eve_data.types().await?;
+        let ships = request
+            .ships
+            .iter()
+            .map(|ship_id| {
+                let capacity = types
+                    .type_by_id(*ship_id)
+                    .and_then(|x| x.capacity)
+                    .unwrap_or_default() as f32;
+                let (loads, unshippable) = pack_loads(&units, capacity, request.max_collateral);
+
+                let loads = loads
+                    .into_iter()
+                    .map(|x| {
+                        let price = Self::quote(
+                            jumps,
+                            risk,
+                            x.volume,
+                            x.value,
+                            request.isk_per_jump,
+                            request.isk_per_m3,
+                        );
+                        HaulingLoad {
+                            items:  x.items,
+                            volume: x.volume,
+                            price,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                HaulingShip {
+                    ship_id: *ship_id,
+                    capacity,
+                    trips:   loads.len() as u32,
+                    reward:  loads.iter().map(|x| x.price.reward).sum(),
+                    loads,
+                    unshippable,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Some(HaulingPlan {
+            jumps,
+            risk,
+            volume: units.iter().map(|x| x.volume * x.quantity as f32).sum(),
+            value:  units.iter().map(|x| x.value * x.quantity as f32).sum(),
+            ships,
+        }))
+    }
+
+    /// Number of jumps and the risk of the route between both systems.
+    ///
+    /// Every system on the route is scored by its security, high security
+    /// systems are considered safe, low security systems count half and null
+    /// security systems count full.
+    async fn route_risk(
+        &self,
+        from: SolarSystemId,
+        to:   SolarSystemId,
+        flag: RouteFlag,
+    ) -> Result<Option<(u32, f32)>, EveServerError> {
+        let route = self
+            .universe
+            .route(from, to, flag)
+            .await?;
+        if route.is_empty() {
+            return Ok(None);
+        }
+
+        let jumps = route.len() as u32 - 1;
+        let risk = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, UniverseGraphEntry>(CacheName::UniverseGraph, route)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| Self::system_risk(x.security))
+            .collect::<Vec<_>>();
+        let risk = if risk.is_empty() {
+            0f32
+        } else {
+            risk.iter().sum::<f32>() / risk.len() as f32
+        };
+        Ok(Some((jumps, risk)))
+    }
+
+    fn quote(
+        jumps:        u32,
+        risk:         f32,
+        volume:       f32,
+        value:        f32,
+        isk_per_jump: f32,
+        isk_per_m3:   f32,
+    ) -> CourierPrice {
+        let base = jumps as f32 * isk_per_jump +
+                   volume * isk_per_m3;
+        let reward = base * (1f32 + risk) +
+                     value * risk * Self::RISK_COLLATERAL_FEE;
+
+        CourierPrice {
+            jumps,
+            risk,
+            reward,
+            collateral: value,
+        }
+    }
+
+    fn system_risk(security: f32) -> f32 {
+        if security >= 0.45 {
+            0f32
+        } else if security > 0f32 {
+            0.5f32
+        } else {
+            1f32
+        }
+    }
+}
+
+/// Query parameters for pricing a courier contract
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct CourierQuery {
+    /// Volume of the package in m³
+    pub volume:       f32,
+    /// Value of the items, used as collateral
+    #[serde(default)]
+    pub value:        f32,
+    pub isk_per_jump: f32,
+    pub isk_per_m3:   f32,
+    #[serde(default)]
+    pub flag:         RouteFlag,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CourierPrice {
+    pub jumps:      u32,
+    /// Between 0.0 (only high security) and 1.0 (only null security)
+    pub risk:       f32,
+    pub reward:     f32,
+    pub collateral: f32,
+}
+
+/// Packs the items into as few loads as possible.
+///
+/// The items are sorted by their volume, largest first. Every load is filled
+/// until either the capacity or the collateral limit is reached, stacks that
+/// do not fit are continued in the next load.
+///
+/// Returns the loads and all items that are too large for the ship or too
+/// valuable for the collateral limit.
+fn pack_loads(
+    units:          &[HaulingUnit],
+    capacity:       f32,
+    max_collateral: Option<f32>,
+) -> (Vec<PackedLoad>, Vec<HaulingItem>) {
+    let max_collateral = max_collateral.unwrap_or(f32::MAX);
+
+    let mut units = units.to_vec();
+    units.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut loads = Vec::new();
+    let mut unshippable = Vec::new();
+    let mut current = PackedLoad::default();
+    for unit in units {
+        if unit.volume > capacity || unit.value > max_collateral {
+            unshippable.push(HaulingItem {
+                type_id:  unit.type_id,
+                quantity: unit.quantity,
+            });
+            continue;
+        }
+
+        let mut remaining = unit.quantity;
+        while remaining > 0 {
+            let by_volume = if unit.volume > 0f32 {
+                ((capacity - current.volume) / unit.volume).floor() as u32
+            } else {
+                remaining
+            };
+            let by_value = if unit.value > 0f32 {
+                ((max_collateral - current.value) / unit.value).floor() as u32
+            } else {
+                remaining
+            };
+            let quantity = remaining.min(by_volume).min(by_value);
+
+            if quantity == 0 {
+                loads.push(std::mem::take(&mut current));
+                continue;
+            }
+
+            current.items.push(HaulingItem {
+                type_id: unit.type_id,
+                quantity,
+            });
+            current.volume += unit.volume * quantity as f32;
+            current.value += unit.value * quantity as f32;
+            remaining -= quantity;
+        }
+    }
+
+    if !current.items.is_empty() {
+        loads.push(current);
+    }
+    (loads, unshippable)
+}
+
+/// Item with the volume and value of a single unit
+#[derive(Clone, Debug)]
+struct HaulingUnit {
+    type_id:  TypeId,
+    quantity: u32,
+    volume:   f32,
+    value:    f32,
+}
+
+#[derive(Debug, Default)]
+struct PackedLoad {
+    items:  Vec<HaulingItem>,
+    volume: f32,
+    value:  f32,
+}
+
+/// Request body for planning the hauling of items
+#[derive(Clone, Debug, Deserialize)]
+pub struct HaulingRequest {
+    /// Items to haul, either assets or a shopping list
+    pub items:          Vec<HaulingItem>,
+    /// Ships to plan the loads for, each ship is planned on its own
+    pub ships:          Vec<TypeId>,
+    pub isk_per_jump:   f32,
+    pub isk_per_m3:     f32,
+    /// Maximum collateral of a single contract
+    #[serde(default)]
+    pub max_collateral: Option<f32>,
+    #[serde(default)]
+    pub flag:           RouteFlag,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HaulingItem {
+    pub type_id:  TypeId,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HaulingPlan {
+    pub jumps:  u32,
+    /// Between 0.0 (only high security) and 1.0 (only null security)
+    pub risk:   f32,
+    /// Total volume of all items in m³
+    pub volume: f32,
+    /// Total value of all items
+    pub value:  f32,
+    pub ships:  Vec<HaulingShip>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HaulingShip {
+    pub ship_id:     TypeId,
+    /// Cargo capacity of the ship in m³
+    pub capacity:    f32,
+    pub trips:       u32,
+    /// Sum of the rewards of all loads
+    pub reward:      f32,
+    pub loads:       Vec<HaulingLoad>,
+    /// Items that do not fit into the ship or exceed the collateral limit
+    pub unshippable: Vec<HaulingItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HaulingLoad {
+    pub items:  Vec<HaulingItem>,
+    pub volume: f32,
+    /// Suggested courier contract for the load
+    pub price:  CourierPrice,
+}
+
+#[cfg(test)]
+mod courier_tests {
+    use super::*;
+
+    fn unit(type_id: u32, quantity: u32, volume: f32, value: f32) -> HaulingUnit {
+        HaulingUnit {
+            type_id: type_id.into(),
+            quantity,
+            volume,
+            value,
+        }
+    }
+
+    #[test]
+    fn splits_stacks() {
+        let units = vec![unit(1, 25, 10f32, 1f32)];
+        let (loads, unshippable) = pack_loads(&units, 100f32, None);
+        assert!(unshippable.is_empty());
+        assert_eq!(loads.len(), 3);
+        assert_eq!(loads[0].items[0].quantity, 10);
+        assert_eq!(loads[2].items[0].quantity, 5);
+    }
+
+    #[test]
+    fn collateral_limit() {
+        let units = vec![unit(1, 10, 1f32, 100f32)];
+        let (loads, _) = pack_loads(&units, 1_000f32, Some(500f32));
+        assert_eq!(loads.len(), 2);
+        assert!(loads.iter().all(|x| x.value <= 500f32));
+    }
+
+    #[test]
+    fn too_large() {
+        let units = vec![unit(1, 1, 500f32, 1f32), unit(2, 1, 50f32, 1f32)];
+        let (loads, unshippable) = pack_loads(&units, 100f32, None);
+        assert_eq!(loads.len(), 1);
+        assert_eq!(unshippable.len(), 1);
+        assert_eq!(unshippable[0].type_id, 1.into());
+    }
+}
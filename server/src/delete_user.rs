@@ -0,0 +1,347 @@
+use crate::audit::{AuditAction, AuditService};
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, CharacterContractEntry, CharacterFittingEntry, FittingEntry, PriceAlertEntry, ProjectEntry, SessionEntry, StockRuleEntry, UserEntry, WalletTransactionEntry, WorkspaceEntry, WorkspaceKey};
+use caph_eve_data_wrapper::{CharacterId, ContractId, FittingId, ItemId, TransactionId};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Collects the keys of all entries of a cache that belong to one of the
+/// given characters, for caches that are not keyed by the character
+macro_rules! owned_keys {
+    ($con:expr, $cache:expr, $key:ty, $entry:ty, $user_ids:expr) => {{
+        let keys = $con
+            .keys::<_, $key>($cache)
+            .await?;
+        $con
+            .mget::<_, _, $entry>($cache, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .filter(|(x, _)| {
+                x
+                    .as_ref()
+                    .map(|x| $user_ids.contains(&x.user_id))
+                    .unwrap_or_default()
+            })
+            .map(|(_, key)| key)
+            .collect::<Vec<_>>()
+    }};
+}
+
+/// Deletes the given keys of a cache, if there are any
+macro_rules! delete_keys {
+    ($con:expr, $cache:expr, $keys:expr) => {{
+        if !$keys.is_empty() {
+            $con.mdel($cache, $keys.clone()).await?;
+        }
+    }};
+}
+
+/// Deletes a main, its alts and every entry that belongs to one of them.
+///
+/// The database has no transactions, so the deletion is done in a way that
+/// can be repeated until it succeeds:
+///
+/// 1. All keys that belong to the characters are collected
+/// 2. The sessions are deleted first, so the user can no longer create
+///    new data
+/// 3. All other entries are deleted
+/// 4. Steps 1 and 3 are repeated, in case the collector synced the
+///    characters in the meantime
+/// 5. The user entry is deleted last, as long as it exists the deletion
+///    can be retried
+///
+/// Every deletion is logged with the target `audit` and recorded in the
+/// audit log of the initiator.
+#[derive(Clone)]
+pub struct DeleteUserService {
+    pool:     ConnectionPool,
+    audit:    AuditService,
+    eve_auth: EveAuthService,
+    /// Only one deletion runs at a time
+    running:  Arc<Mutex<()>>,
+}
+
+impl DeleteUserService {
+    /// Maximum number of times the keys are collected again
+    const MAX_PASSES: usize = 3;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        audit:    AuditService,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            audit,
+            eve_auth,
+            running: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Deletes the requesting main and all its data
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    pub async fn delete_own(
+        &self,
+        token: &str,
+    ) -> Result<AuditEntry, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        self.delete(user.user_id, user.user_id).await
+    }
+
+    /// Deletes a main and all its data, the caller must make sure that the
+    /// initiator is allowed to delete the main
+    ///
+    /// # Params
+    ///
+    /// `uid`       -> Id of the main
+    /// `initiator` -> Main that requested the deletion
+    ///
+    /// # Returns
+    ///
+    /// The audit entry that was logged
+    ///
+    pub async fn delete(
+        &self,
+        uid:       CharacterId,
+        initiator: CharacterId,
+    ) -> Result<AuditEntry, EveServerError> {
+        let _running = self.running.lock().await;
+
+        let mut con = self.pool.acquire().await?;
+        let user = con
+            .get::<_, _, UserEntry>(CacheName::User, uid)
+            .await?
+            .ok_or(EveServerError::UserNotFound)?;
+
+        let mut deleted = DeletedEntries::default();
+        for pass in 0..Self::MAX_PASSES {
+            let delete = DeleteUser::collect(&self.pool, &user).await?;
+            // the caches keyed by the character are always deleted in the
+            // first pass
+            if pass > 0 && delete.is_empty() {
+                break;
+            }
+            delete.execute(&self.pool).await?;
+            deleted.add(&delete);
+        }
+
+        con.del(CacheName::UserLogin, user.user_id).await?;
+        con.del(CacheName::UserPreference, user.user_id).await?;
+        con.del(CacheName::UserRole, user.user_id).await?;
+        con.del(CacheName::User, user.user_id).await?;
+
+        let entry = AuditEntry {
+            action:    "delete_user",
+            user_id:   user.user_id,
+            alts:      user.aliase.iter().map(|x| x.user_id).collect::<Vec<_>>(),
+            initiator,
+            timestamp: Utc::now().timestamp() as u64 * 1_000,
+            deleted,
+        };
+        let details = serde_json::to_string(&entry)?;
+        log::info!(target: "audit", "{}", details);
+        self.audit.record(initiator, AuditAction::DeleteUser, Some(user.user_id), details).await?;
+        Ok(entry)
+    }
+}
+
+/// Keys of all entries that belong to a main and its alts
+#[derive(Debug, Default)]
+struct DeleteUser {
+    user_ids:     Vec<CharacterId>,
+    assets:       Vec<ItemId>,
+    blueprints:   Vec<ItemId>,
+    contracts:    Vec<ContractId>,
+    esi_fittings: Vec<FittingId>,
+    fittings:     Vec<Uuid>,
+    net_worth:    Vec<WorkspaceKey>,
+    price_alerts: Vec<Uuid>,
+    projects:     Vec<Uuid>,
+    sessions:     Vec<String>,
+    stock_rules:  Vec<Uuid>,
+    transactions: Vec<TransactionId>,
+    workspaces:   Vec<Uuid>,
+}
+
+impl DeleteUser {
+    /// Collects the keys of all entries that belong to the main or one of
+    /// its alts
+    async fn collect(
+        pool: &ConnectionPool,
+        user: &UserEntry,
+    ) -> Result<Self, EveServerError> {
+        let mut con = pool.acquire().await?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        Ok(Self {
+            assets:       owned_keys!(con, CacheName::CharacterAsset,     ItemId,        CharacterAssetEntry,     user_ids),
+            blueprints:   owned_keys!(con, CacheName::CharacterBlueprint, ItemId,        CharacterBlueprintEntry, user_ids),
+            contracts:    owned_keys!(con, CacheName::CharacterContract,  ContractId,    CharacterContractEntry,  user_ids),
+            esi_fittings: owned_keys!(con, CacheName::CharacterFitting,   FittingId,     CharacterFittingEntry,   user_ids),
+            fittings:     owned_keys!(con, CacheName::Fitting,            Uuid,          FittingEntry,            user_ids),
+            // keyed by the character and the workspace
+            net_worth:    con
+                .keys::<_, WorkspaceKey>(CacheName::NetWorthHistory)
+                .await?
+                .into_iter()
+                .filter(|x| user_ids.contains(&x.user_id))
+                .collect::<Vec<_>>(),
+            price_alerts: owned_keys!(con, CacheName::PriceAlert,         Uuid,          PriceAlertEntry,         user_ids),
+            projects:     owned_keys!(con, CacheName::Project,            Uuid,          ProjectEntry,            user_ids),
+            sessions:     owned_keys!(con, CacheName::Session,            String,        SessionEntry,            user_ids),
+            stock_rules:  owned_keys!(con, CacheName::StockRule,          Uuid,          StockRuleEntry,          user_ids),
+            transactions: owned_keys!(con, CacheName::WalletTransaction,  TransactionId, WalletTransactionEntry,  user_ids),
+            workspaces:   owned_keys!(con, CacheName::Workspace,          Uuid,          WorkspaceEntry,          user_ids),
+            user_ids,
+        })
+    }
+
+    /// Deletes all collected entries, the sessions first
+    async fn execute(
+        &self,
+        pool: &ConnectionPool,
+    ) -> Result<(), EveServerError> {
+        let mut con = pool.acquire().await?;
+        delete_keys!(con, CacheName::Session, self.sessions);
+
+        for cache in Self::character_caches() {
+            con.mdel(cache, self.user_ids.clone()).await?;
+        }
+
+        delete_keys!(con, CacheName::CharacterAsset,     self.assets);
+        delete_keys!(con, CacheName::CharacterBlueprint, self.blueprints);
+        delete_keys!(con, CacheName::CharacterContract,  self.contracts);
+        delete_keys!(con, CacheName::CharacterFitting,   self.esi_fittings);
+        delete_keys!(con, CacheName::Fitting,            self.fittings);
+        delete_keys!(con, CacheName::NetWorthHistory,    self.net_worth);
+        delete_keys!(con, CacheName::PriceAlert,         self.price_alerts);
+        delete_keys!(con, CacheName::Project,            self.projects);
+        delete_keys!(con, CacheName::StockRule,          self.stock_rules);
+        delete_keys!(con, CacheName::WalletTransaction,  self.transactions);
+        delete_keys!(con, CacheName::Workspace,          self.workspaces);
+        Ok(())
+    }
+
+    /// True if there is nothing left that is not keyed by the character
+    fn is_empty(&self) -> bool {
+        self.assets.is_empty() &&
+        self.blueprints.is_empty() &&
+        self.contracts.is_empty() &&
+        self.esi_fittings.is_empty() &&
+        self.fittings.is_empty() &&
+        self.net_worth.is_empty() &&
+        self.price_alerts.is_empty() &&
+        self.projects.is_empty() &&
+        self.sessions.is_empty() &&
+        self.stock_rules.is_empty() &&
+        self.transactions.is_empty() &&
+        self.workspaces.is_empty()
+    }
+
+    /// Caches that are keyed by the id of a character, they are always
+    /// deleted
+    fn character_caches() -> Vec<CacheName> {
+        vec![
+            CacheName::CharacterCalendar,
+            CacheName::CharacterClone,
+            CacheName::CharacterMining,
+            CacheName::CharacterNotification,
+            CacheName::CharacterPlanet,
+            CacheName::CharacterSkill,
+            CacheName::CharacterSync,
+            CacheName::CharacterWebhook,
+            CacheName::SkillHistory,
+        ]
+    }
+}
+
+/// Logged for every deleted user
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub action:    &'static str,
+    pub user_id:   CharacterId,
+    pub alts:      Vec<CharacterId>,
+    /// Main that requested the deletion, the user itself or an admin
+    pub initiator: CharacterId,
+    /// Timestamp in milliseconds
+    pub timestamp: u64,
+    pub deleted:   DeletedEntries,
+}
+
+/// Number of deleted entries of the caches that are not keyed by the
+/// character
+#[derive(Debug, Default, Serialize)]
+pub struct DeletedEntries {
+    pub assets:       usize,
+    pub blueprints:   usize,
+    pub contracts:    usize,
+    pub esi_fittings: usize,
+    pub fittings:     usize,
+    pub net_worth:    usize,
+    pub price_alerts: usize,
+    pub projects:     usize,
+    pub sessions:     usize,
+    pub stock_rules:  usize,
+    pub transactions: usize,
+    pub workspaces:   usize,
+}
+
+impl DeletedEntries {
+    fn add(&mut self, delete: &DeleteUser) {
+        self.assets       += delete.assets.len();
+        self.blueprints   += delete.blueprints.len();
+        self.contracts    += delete.contracts.len();
+        self.esi_fittings += delete.esi_fittings.len();
+        self.fittings     += delete.fittings.len();
+        self.net_worth    += delete.net_worth.len();
+        self.price_alerts += delete.price_alerts.len();
+        self.projects     += delete.projects.len();
+        self.sessions     += delete.sessions.len();
+        self.stock_rules  += delete.stock_rules.len();
+        self.transactions += delete.transactions.len();
+        self.workspaces   += delete.workspaces.len();
+    }
+}
+
+#[cfg(test)]
+mod delete_user_tests {
+    use super::*;
+
+    #[test]
+    fn counts_all_passes() {
+        let delete = DeleteUser {
+            assets:   vec![1u64.into(), 2u64.into()],
+            sessions: vec!["a".into()],
+            ..DeleteUser::default()
+        };
+        assert!(!delete.is_empty());
+        assert!(DeleteUser::default().is_empty());
+
+        let mut deleted = DeletedEntries::default();
+        deleted.add(&delete);
+        deleted.add(&delete);
+        assert_eq!(deleted.assets, 4);
+        assert_eq!(deleted.sessions, 2);
+    }
+}
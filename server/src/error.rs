@@ -5,11 +5,46 @@ use warp::reject::Reject;
 #[derive(Debug)]
 pub enum EveServerError {
     EveConnectError(caph_eve_data_wrapper::EveConnectError),
+    /// The eve servers are in downtime or not reachable
+    EveOffline,
     CachemError(cachem::CachemError),
     SerdeJsonError(serde_json::Error),
     InvalidUser,
+    MissingPermission,
+    /// The user does not have the role that is required for the route
+    MissingRole(crate::role::Role),
+    /// None of the characters is in the corporation or has one of the roles
+    MissingCorporationRole(Vec<crate::corporation::CorporationRole>),
+    RateLimited,
+    TooManyIds,
+    InvalidWebhook,
+    InvalidPreference,
+    /// The cache does not exist or is not published in the public api
+    CacheNotPublic,
+    /// Capital ships can only be build in low and null sec
+    InvalidBuildLocation,
+    /// Contains the line or name that could not be parsed or resolved
+    InvalidFitting(String),
+    /// Contains the column of an export that does not exist
+    UnknownColumn(String),
+    /// Contains the field of a list query that does not exist
+    UnknownField(String),
+    /// Contains the reason why the config is invalid
+    InvalidConfig(String),
+    /// Contains the name of the scope preset that does not exist
+    InvalidScopePreset(String),
+    /// Contains the price source that is unknown or misses its parameter
+    InvalidPriceSource(String),
+    /// The route is not public or not passed through by the proxy
+    EsiRouteNotAllowed,
     BlueprintNotFound,
+    FittingNotFound,
+    PriceAlertNotFound,
+    SessionNotFound,
+    StockRuleNotFound,
     TypeNotFound,
+    UserNotFound,
+    WorkspaceNotFound,
 }
 
 impl Error for EveServerError {}
@@ -24,7 +59,10 @@ impl From<cachem::CachemError> for EveServerError {
 
 impl From<caph_eve_data_wrapper::EveConnectError> for EveServerError {
     fn from(e: caph_eve_data_wrapper::EveConnectError) -> Self {
-        Self::EveConnectError(e)
+        match e {
+            caph_eve_data_wrapper::EveConnectError::EveOffline => Self::EveOffline,
+            _                                                  => Self::EveConnectError(e),
+        }
     }
 }
 
@@ -0,0 +1,238 @@
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, EsiResponseEntry};
+use caph_eve_data_wrapper::EveDataWrapper;
+use chrono::{DateTime, TimeZone, Utc};
+use std::time::Duration;
+
+/// Caching proxy for public ESI routes, so that other tools in the same
+/// network share the responses instead of requesting ESI themselves.
+///
+/// Only routes that start with one of [EsiProxyService::ALLOWED] are passed
+/// through. Responses are stored in the database until the `expires` header
+/// of ESI is reached, identical requests that arrive at the same time are
+/// only send once to ESI.
+#[derive(Clone)]
+pub struct EsiProxyService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+}
+
+impl EsiProxyService {
+    /// Public routes that can be requested through the proxy
+    const ALLOWED: &'static [&'static str] = &[
+        "markets/",
+        "status/",
+        "universe/",
+    ];
+
+    /// Milliseconds a response is cached if ESI does not send an `expires`
+    /// header
+    const DEFAULT_TTL:      u64 = 5 * 60 * 1_000;
+    /// Seconds between two removals of expired responses
+    const CLEANUP_INTERVAL: u64 = 60 * 60;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+        }
+    }
+
+    /// Gets the response of a public ESI route, from the database if it is
+    /// not expired yet
+    ///
+    /// # Params
+    ///
+    /// `path`  -> Path of the route, for example `markets/10000002/history/`
+    /// `query` -> Raw query of the request, may be empty
+    ///
+    /// # Returns
+    ///
+    /// The response or [EveServerError::EsiRouteNotAllowed] if the route is
+    /// not public
+    ///
+    pub async fn get(
+        &self,
+        path:  &str,
+        query: &str,
+    ) -> Result<ProxyResponse, EveServerError> {
+        let key = Self::key(path, query)
+            .ok_or(EveServerError::EsiRouteNotAllowed)?;
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let mut con = self.pool.acquire().await?;
+        let cached = con
+            .get::<_, _, EsiResponseEntry>(CacheName::EsiResponse, key.clone())
+            .await?;
+        match cached {
+            Some(x) if x.is_fresh(now) => {
+                return Ok(ProxyResponse::found(x, true));
+            }
+            // expired responses are better than no response during the
+            // downtime
+            Some(x) if !self.eve_data.is_esi_online() => {
+                return Ok(ProxyResponse::found(x, true));
+            }
+            _ => (),
+        }
+
+        let response = self.eve_data.fetch_esi_raw(&key).await?;
+        if response.status == 404 {
+            return Ok(ProxyResponse::not_found(response.body));
+        }
+
+        let entry = EsiResponseEntry {
+            body:    response.body,
+            expires: Self::expires_at(response.expires.as_deref(), now),
+            etag:    response.etag,
+            pages:   response.pages,
+        };
+        con
+            .set(CacheName::EsiResponse, key, entry.clone())
+            .await?;
+        Ok(ProxyResponse::found(entry, false))
+    }
+
+    /// Removes expired responses every hour, so that routes that are no
+    /// longer requested do not stay in the database.
+    ///
+    /// This function is blocking
+    pub async fn watch(&self) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(Self::CLEANUP_INTERVAL)).await;
+            if let Err(e) = self.cleanup().await {
+                log::error!("Error removing expired esi responses {:?}", e);
+            }
+        }
+    }
+
+    async fn cleanup(&self) -> Result<(), EveServerError> {
+        let now = Utc::now().timestamp_millis() as u64;
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, String>(CacheName::EsiResponse)
+            .await?;
+        let expired = con
+            .mget::<_, _, EsiResponseEntry>(CacheName::EsiResponse, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .filter(|(x, _)| x.as_ref().map(|x| !x.is_fresh(now)).unwrap_or(true))
+            .map(|(_, key)| key)
+            .collect::<Vec<_>>();
+        if !expired.is_empty() {
+            con.mdel(CacheName::EsiResponse, expired).await?;
+        }
+        Ok(())
+    }
+
+    /// Path and query that are requested from ESI and used as key in the
+    /// database, [None] if the route is not allowed
+    fn key(path: &str, query: &str) -> Option<String> {
+        let mut path = path.trim_start_matches('/').to_string();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+
+        let allowed = Self::ALLOWED
+            .iter()
+            .any(|x| path.starts_with(x));
+        if !allowed || path.split('/').any(|x| x == "..") {
+            return None;
+        }
+
+        // the order of the parameters does not change the response
+        let mut params = query
+            .split('&')
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        params.sort_unstable();
+
+        if params.is_empty() {
+            Some(path)
+        } else {
+            Some(format!("{}?{}", path, params.join("&")))
+        }
+    }
+
+    /// Timestamp in milliseconds of the `expires` header
+    fn expires_at(header: Option<&str>, now: u64) -> u64 {
+        header
+            .and_then(|x| DateTime::parse_from_rfc2822(x).ok())
+            .map(|x| x.timestamp_millis() as u64)
+            .unwrap_or(now + Self::DEFAULT_TTL)
+    }
+}
+
+/// Response of the proxy
+#[derive(Debug)]
+pub struct ProxyResponse {
+    pub body:    String,
+    /// False if ESI returned 404
+    pub found:   bool,
+    /// True if the response was served from the database
+    pub cached:  bool,
+    /// Value for the `expires` header
+    pub expires: Option<String>,
+    pub etag:    Option<String>,
+    pub pages:   u8,
+}
+
+impl ProxyResponse {
+    fn found(entry: EsiResponseEntry, cached: bool) -> Self {
+        let expires = Utc
+            .timestamp_millis(entry.expires as i64)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+
+        Self {
+            body:    entry.body,
+            found:   true,
+            cached,
+            expires: Some(expires),
+            etag:    entry.etag,
+            pages:   entry.pages,
+        }
+    }
+
+    fn not_found(body: String) -> Self {
+        Self {
+            body,
+            found:   false,
+            cached:  false,
+            expires: None,
+            etag:    None,
+            pages:   0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod esi_proxy_tests {
+    use super::*;
+
+    #[test]
+    fn allowed_routes() {
+        assert_eq!(EsiProxyService::key("/status", ""), Some("status/".into()));
+        assert_eq!(
+            EsiProxyService::key("markets/10000002/orders/", "type_id=34&order_type=all"),
+            Some("markets/10000002/orders/?order_type=all&type_id=34".into())
+        );
+        assert_eq!(EsiProxyService::key("characters/1/assets/", ""), None);
+        assert_eq!(EsiProxyService::key("universe/../characters/1/", ""), None);
+    }
+
+    #[test]
+    fn expires_header() {
+        let expires = EsiProxyService::expires_at(Some("Thu, 15 Oct 2026 12:00:00 GMT"), 0);
+        assert_eq!(expires, 1_792_065_600_000);
+        assert_eq!(EsiProxyService::expires_at(None, 100), 100 + EsiProxyService::DEFAULT_TTL);
+        assert_eq!(EsiProxyService::expires_at(Some("invalid"), 100), 100 + EsiProxyService::DEFAULT_TTL);
+    }
+}
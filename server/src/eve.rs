@@ -1,41 +1,54 @@
+use crate::audit::{AuditAction, AuditService};
+use crate::auth::{SsoConfig, SsoService};
 use crate::error::EveServerError;
+use crate::scope::ScopePreset;
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{CacheName, UserEntry};
+use caph_db_v2::{CacheName, SessionEntry, UserEntry};
 use caph_eve_data_wrapper::{CharacterId, EveOAuthUser};
 use caph_eve_data_wrapper::{EveClient, Url};
 use rand::distributions::Alphanumeric;
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-/// Describes different type of session logins
-#[derive(PartialEq)]
+/// Describes different type of pending logins
+#[derive(Clone, PartialEq)]
 enum SessionType {
     /// Login process with the main account
     Main,
     /// Login process with an alt
     /// Contains the user id of the main
     Alt(CharacterId),
-    /// Logged in user
-    /// Contains the user id of the main
-    Logged(CharacterId)
 }
 
 #[derive(Clone)]
 pub struct EveAuthService {
-    pool:     ConnectionPool,
-    sessions: Arc<Mutex<HashMap<String, SessionType>>>,
+    pool:  ConnectionPool,
+    audit: AuditService,
+    sso:   SsoService<SessionType>,
 }
 
 impl EveAuthService {
+    /// Days until a browser session expires
+    pub const SESSION_DAYS:   u64 = 30;
+    /// Maximum number of days an api token is valid
+    const MAX_API_TOKEN_DAYS: u64 = 365;
+    const DAY:                u64 = 24 * 60 * 60 * 1_000;
+
     /// Creates a new instance
-    pub fn new(pool: ConnectionPool) -> Self {
+    pub fn new(
+        pool:  ConnectionPool,
+        audit: AuditService,
+        sso:   SsoConfig,
+    ) -> Self {
         Self {
             pool,
-            sessions: Arc::new(Mutex::new(HashMap::new()))
+            audit,
+            sso: SsoService::new(sso),
         }
     }
 
@@ -56,32 +69,17 @@ impl EveAuthService {
         code: String,
         state: String
     ) -> Result<Option<String>, EveServerError> {
-        let session_entry = {
-            // Make sure that the code is valid
-            // If the code is valid, remove it from the map
-            //
-            // Also keep the session as short as possible
-            let mut session = self.sessions
-                .lock()
-                .await;
-            if session.contains_key(&state) {
-                // Unwrap is save here, because we made sure the entry exists
-                session.remove(&state).unwrap()
-            } else {
-                return Err(EveServerError::InvalidUser);
-            }
-        };
-
-        let user = EveClient::retrieve_authorization_token(&code).await?;
+        let (session_entry, user) = self.sso.callback(&code, &state).await?;
 
         if session_entry == SessionType::Main {
-            let user_token = self.generate_key();
-            self.sessions
-                .lock()
-                .await
-                .insert(user_token.clone(), SessionType::Logged(user.user_id));
+            let user_id = user.user_id;
+            self.save_main(user).await?;
+            self.save_last_login(user_id).await?;
+            self.audit.record(user_id, AuditAction::Login, None, "").await?;
 
-            self.save_login(&user_token, user).await?;
+            let user_token = self
+                .create_session(user_id, None, Self::SESSION_DAYS)
+                .await?;
             Ok(Some(user_token))
         } else if let SessionType::Alt(uid) = session_entry {
             let main = self
@@ -92,7 +90,9 @@ impl EveAuthService {
                 .await?;
 
             if let Some(main) = main {
+                let (main_id, alt_id) = (main.user_id, user.user_id);
                 self.add_alt(main, user).await?;
+                self.audit.record(main_id, AuditAction::AddAlt, Some(alt_id), "").await?;
                 Ok(None)
             } else {
                 Err(EveServerError::InvalidUser)
@@ -105,16 +105,16 @@ impl EveAuthService {
     /// Creates a new unique code and returns a eve login auth uri
     /// This function is only for main accounts
     ///
+    /// # Params
+    ///
+    /// `query` -> Scope presets to request
+    ///
     /// # Returns
     ///
     /// Uri to the eve auth server
     ///
-    pub async fn login(&self) -> Result<Url, EveServerError> {
-        let key = self.generate_key();
-        self.sessions.lock().await.insert(key.clone(), SessionType::Main);
-
-        EveClient::eve_auth_uri(&key)
-            .map_err(Into::into)
+    pub async fn login(&self, query: LoginQuery) -> Result<Url, EveServerError> {
+        self.sso.start(SessionType::Main, query.scopes()?).await
     }
 
     /// Creates a new unique code and returns a eve login auth uri
@@ -123,52 +123,232 @@ impl EveAuthService {
     /// # Params
     ///
     /// `token` -> Token of the cookie from the main user
+    /// `query` -> Scope presets to request
     ///
     /// # Returns
     ///
     /// Uri to the eve auth server
     ///
-    pub async fn login_alt(&self, token: &str) -> Result<Url, EveServerError> {
+    pub async fn login_alt(
+        &self,
+        token: &str,
+        query: LoginQuery,
+    ) -> Result<Url, EveServerError> {
         let user = self.lookup(token).await?;
 
         if let Some(x) = user {
-            let key = self.generate_key();
-            self.sessions.lock().await.insert(key.clone(), SessionType::Alt(x.user_id));
-
-            EveClient::eve_auth_uri(&key)
-                .map_err(Into::into)
+            self.sso.start(SessionType::Alt(x.user_id), query.scopes()?).await
         } else {
             Err(EveServerError::InvalidUser)
         }
     }
 
-    /// Looksup a user by its id
+    /// Looksup a user by its session token or api token
     ///
     /// # Params
     ///
     /// `token` -> Token of the user to lookup
     ///
+    /// # Returns
+    ///
+    /// The user or [None] if the token is unknown, revoked or expired
+    ///
     pub async fn lookup(
         &self,
         token: &str,
     ) -> Result<Option<UserEntry>, EveServerError> {
-        let uid = self
-            .sessions
-            .lock()
-            .await;
-        let uid = uid
-            .get(token);
-
-        if let Some(SessionType::Logged(x)) = uid {
-            self
-                .pool
-                .acquire()
-                .await?
-                .get::<_, _, UserEntry>(CacheName::User, *x)
-                .await
-                .map_err(Into::into)
+        let mut con = self.pool.acquire().await?;
+
+        let hash = Self::hash(token);
+        let session = con
+            .get::<_, _, SessionEntry>(CacheName::Session, hash.clone())
+            .await?;
+        let session = match session {
+            Some(x) => x,
+            None    => return Ok(None),
+        };
+
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        if !session.is_valid(now) {
+            con.del(CacheName::Session, hash).await?;
+            return Ok(None);
+        }
+
+        con
+            .get::<_, _, UserEntry>(CacheName::User, session.user_id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Creates a personal api token that can be used instead of the session
+    /// cookie
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Token of the main
+    /// `request` -> Name of the token and the days until it expires
+    ///
+    /// # Returns
+    ///
+    /// The new token, it is only returned once and can not be requested
+    /// again
+    ///
+    pub async fn create_api_token(
+        &self,
+        token:   &str,
+        request: ApiTokenRequest,
+    ) -> Result<ApiToken, EveServerError> {
+        let user = self
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let days = request.days.min(Self::MAX_API_TOKEN_DAYS).max(1);
+        let token = self
+            .create_session(user.user_id, Some(request.name), days)
+            .await?;
+        Ok(ApiToken {
+            id: Self::hash(&token),
+            token,
+        })
+    }
+
+    /// Lists all sessions and api tokens of the main that are not expired
+    ///
+    /// # Params
+    ///
+    /// `token` -> Token of the main
+    ///
+    pub async fn sessions(
+        &self,
+        token: &str,
+    ) -> Result<Vec<SessionInfo>, EveServerError> {
+        let user = self
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let mut con = self.pool.acquire().await?;
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let current = Self::hash(token);
+        let keys = con
+            .keys::<_, String>(CacheName::Session)
+            .await?;
+        let mut sessions = con
+            .mget::<_, _, SessionEntry>(CacheName::Session, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .filter_map(|(x, id)| x.map(|x| (id, x)))
+            .filter(|(_, x)| x.user_id == user.user_id && x.is_valid(now))
+            .map(|(id, x)| SessionInfo {
+                current: id == current,
+                id,
+                name:    x.name,
+                created: x.created,
+                expire:  x.expire,
+            })
+            .collect::<Vec<_>>();
+        sessions.sort_by_key(|x| x.created);
+        Ok(sessions)
+    }
+
+    /// Revokes a session or api token of the main
+    ///
+    /// # Params
+    ///
+    /// `token` -> Token of the main
+    /// `id`    -> Id of the session to revoke, as returned by [Self::sessions]
+    ///
+    pub async fn revoke(
+        &self,
+        token: &str,
+        id:    String,
+    ) -> Result<(), EveServerError> {
+        let user = self
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let mut con = self.pool.acquire().await?;
+        let session = con
+            .get::<_, _, SessionEntry>(CacheName::Session, id.clone())
+            .await?;
+        match session {
+            Some(x) if x.user_id == user.user_id => {
+                con
+                    .del(CacheName::Session, id)
+                    .await
+                    .map_err(Into::into)
+            },
+            _ => Err(EveServerError::SessionNotFound),
+        }
+    }
+
+    /// Revokes the session of the given token
+    ///
+    /// # Params
+    ///
+    /// `token` -> Token to revoke
+    ///
+    pub async fn logout(&self, token: &str) -> Result<(), EveServerError> {
+        self
+            .pool
+            .acquire()
+            .await?
+            .del(CacheName::Session, Self::hash(token))
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Disconnects a character from the application.
+    ///
+    /// The refresh token is revoked at the eve auth server and removed from
+    /// the database. For an alt only the alt is removed from the main.
+    /// For the main the tokens of the main and all alts are revoked and all
+    /// sessions and api tokens of the main are invalidated, the collected
+    /// data is kept until the user is deleted.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Token of the main
+    /// `uid`   -> Userid of the main or one of its alts
+    ///
+    pub async fn deauthorize(
+        &self,
+        token: &str,
+        uid:   CharacterId,
+    ) -> Result<(), EveServerError> {
+        let mut main = self
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        if main.user_id == uid {
+            Self::revoke_refresh_token(main.user_id, &main.refresh_token).await;
+            for alt in main.aliase.iter() {
+                Self::revoke_refresh_token(alt.user_id, &alt.refresh_token).await;
+            }
+
+            main.access_token.clear();
+            main.refresh_token.clear();
+            main.aliase.clear();
+            let main_id = main.user_id;
+            self.save_user(main).await?;
+            self.delete_sessions(main_id).await?;
+            self.audit.record(main_id, AuditAction::Deauthorize, None, "").await
         } else {
-            Ok(None)
+            let position = main
+                .aliase
+                .iter()
+                .position(|x| x.user_id == uid)
+                .ok_or(EveServerError::InvalidUser)?;
+            let alt = main.aliase.remove(position);
+            Self::revoke_refresh_token(alt.user_id, &alt.refresh_token).await;
+
+            let main_id = main.user_id;
+            self.save_user(main).await?;
+            self.audit.record(main_id, AuditAction::Deauthorize, Some(uid), "").await
         }
     }
 
@@ -191,7 +371,8 @@ impl EveAuthService {
             .await
             .map_err(EveServerError::from)?;
 
-        self.save_login(token, oauth.clone()).await?;
+        self.save_main(oauth.clone()).await?;
+        self.audit.record(oauth.user_id, AuditAction::TokenRefresh, None, "").await?;
 
         Ok(oauth)
     }
@@ -216,6 +397,7 @@ impl EveAuthService {
             .lookup(&token)
             .await?
             .ok_or(EveServerError::InvalidUser)?;
+        let main_id = oauth.user_id;
         let oauth = oauth
             .aliase
             .iter()
@@ -226,22 +408,97 @@ impl EveAuthService {
             .map_err(EveServerError::from)?;
 
         self.save_login_alt(token, oauth.clone()).await?;
+        self.audit.record(main_id, AuditAction::TokenRefresh, Some(uid), "").await?;
+
+        Ok(oauth)
+    }
 
+    /// Requests a new refresh token for any main or alt, only for admins
+    ///
+    /// # Param
+    ///
+    /// `admin` -> Main of the admin that requested the refresh
+    /// `uid`   -> Userid of the main or alt
+    ///
+    /// # Returns
+    ///
+    /// New oauth user
+    ///
+    pub async fn refresh_character(
+        &self,
+        admin: CharacterId,
+        uid:   CharacterId,
+    ) -> Result<EveOAuthUser, EveServerError> {
+        let mut main = self
+            .main_of(uid)
+            .await?
+            .ok_or(EveServerError::UserNotFound)?;
+
+        let character = if main.user_id == uid {
+            &mut main
+        } else {
+            main
+                .aliase
+                .iter_mut()
+                .find(|x| x.user_id == uid)
+                .ok_or(EveServerError::UserNotFound)?
+        };
+        let oauth = EveClient::retrieve_refresh_token(&character.refresh_token)
+            .await
+            .map_err(EveServerError::from)?;
+        character.access_token = oauth.access_token.clone();
+        character.refresh_token = oauth.refresh_token.clone();
+
+        self.save_user(main).await?;
+        self.audit.record(admin, AuditAction::TokenRefresh, Some(uid), "admin").await?;
         Ok(oauth)
     }
 
+    /// Finds the main of a character
+    ///
+    /// # Params
+    ///
+    /// `uid` -> Userid of the main or one of its alts
+    ///
+    /// # Returns
+    ///
+    /// The main or [None] if the character is not registered
+    ///
+    pub async fn main_of(
+        &self,
+        uid: CharacterId,
+    ) -> Result<Option<UserEntry>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, CharacterId>(CacheName::User)
+            .await?;
+        let main = con
+            .mget::<_, _, UserEntry>(CacheName::User, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .find(|x| x.user_id == uid || x.aliase.iter().any(|x| x.user_id == uid));
+        Ok(main)
+    }
+
     /// Saves the main character in the database
     ///
     /// # Params
     ///
     /// `character` -> Character with access_token and refresh_token
     ///
-    async fn save_login(
+    async fn save_main(
         &self,
-        token:     &str,
         character: EveOAuthUser
     ) -> Result<(), EveServerError> {
-        if let Some(x) = self.lookup(&token).await? {
+        let user = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, UserEntry>(CacheName::User, character.user_id)
+            .await?;
+
+        if let Some(x) = user {
             let user = UserEntry {
                 access_token: character.access_token,
                 refresh_token: character.refresh_token,
@@ -313,6 +570,27 @@ impl EveAuthService {
             .map_err(Into::into)
     }
 
+    /// Saves the current time as last login of the main, used by the dead
+    /// man switch
+    ///
+    /// # Params
+    ///
+    /// `user_id` -> Id of the main
+    ///
+    async fn save_last_login(
+        &self,
+        user_id: CharacterId
+    ) -> Result<(), EveServerError> {
+        let timestamp = Utc::now().timestamp() as u64 * 1_000;
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::UserLogin, user_id, timestamp)
+            .await
+            .map_err(Into::into)
+    }
+
     /// Adds a new alt to a main
     ///
     /// # Params
@@ -338,6 +616,90 @@ impl EveAuthService {
         self.save_user(main).await
     }
 
+    /// Revokes a refresh token at the eve auth server, the token is removed
+    /// from the database either way, so errors are only logged
+    async fn revoke_refresh_token(
+        uid:           CharacterId,
+        refresh_token: &str,
+    ) {
+        if refresh_token.is_empty() {
+            return;
+        }
+        if let Err(e) = EveClient::revoke_refresh_token(refresh_token).await {
+            log::warn!("Revoking the refresh token of {} failed {:?}", uid, e);
+        }
+    }
+
+    /// Deletes all sessions and api tokens of a main
+    ///
+    /// # Params
+    ///
+    /// `user_id` -> Id of the main
+    ///
+    async fn delete_sessions(
+        &self,
+        user_id: CharacterId,
+    ) -> Result<(), EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, String>(CacheName::Session)
+            .await?;
+        let sessions = con
+            .mget::<_, _, SessionEntry>(CacheName::Session, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .filter(|(x, _)| x.as_ref().map(|x| x.user_id == user_id).unwrap_or_default())
+            .map(|(_, id)| id)
+            .collect::<Vec<_>>();
+        if !sessions.is_empty() {
+            con.mdel(CacheName::Session, sessions).await?;
+        }
+        Ok(())
+    }
+
+    /// Creates a new session and stores the hash of its token
+    ///
+    /// # Params
+    ///
+    /// `user_id` -> Id of the main
+    /// `name`    -> Name of an api token, [None] for browser sessions
+    /// `days`    -> Days until the session expires
+    ///
+    /// # Returns
+    ///
+    /// The token of the session
+    ///
+    async fn create_session(
+        &self,
+        user_id: CharacterId,
+        name:    Option<String>,
+        days:    u64,
+    ) -> Result<String, EveServerError> {
+        let token = self.generate_key();
+        let now = Utc::now().timestamp() as u64 * 1_000;
+        let session = SessionEntry {
+            user_id,
+            name,
+            created: now,
+            expire:  now + days * Self::DAY,
+        };
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::Session, Self::hash(&token), session)
+            .await?;
+        Ok(token)
+    }
+
+    /// Only the hash of a token is stored, so a leaked database does not
+    /// leak valid tokens
+    fn hash(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
     fn generate_key(&self) -> String {
         ChaCha20Rng::from_entropy()
             .sample_iter(&Alphanumeric)
@@ -347,3 +709,58 @@ impl EveAuthService {
     }
 }
 
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct LoginQuery {
+    /// Comma separated scope presets, for example `assets,industry`, if not
+    /// set the scopes of the config are requested
+    pub features: Option<String>,
+}
+
+impl LoginQuery {
+    fn scopes(&self) -> Result<Option<Vec<String>>, EveServerError> {
+        match self.features.as_ref() {
+            Some(x) => Ok(Some(ScopePreset::resolve(&ScopePreset::parse_list(x)?))),
+            None    => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiTokenRequest {
+    pub name: String,
+    /// Days until the token expires
+    pub days: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    /// Id that is used to revoke the token
+    pub id:    String,
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id:      String,
+    /// Name of an api token, None for browser sessions
+    pub name:    Option<String>,
+    /// Timestamp in milliseconds
+    pub created: u64,
+    /// Timestamp in milliseconds
+    pub expire:  u64,
+    /// true if this is the session of the request
+    pub current: bool,
+}
+
+#[cfg(test)]
+mod eve_tests {
+    use super::*;
+
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(
+            EveAuthService::hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::industry::IndustryService;
+use crate::invalidation::InvalidationService;
+use crate::price_alert::PriceAlertService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, PriceAlertEntry, WalletTransactionEntry};
+use caph_eve_data_wrapper::{ActivityId, CharacterId, IndustryJob, TransactionId, TypeId};
+use chrono::{DateTime, Utc};
+use futures::stream::SplitSink;
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use warp::ws::{Message, WebSocket};
+
+/// Pushes events about the characters of a user to a websocket
+#[derive(Clone)]
+pub struct EventService {
+    pool:         ConnectionPool,
+    eve_auth:     EveAuthService,
+    industry:     IndustryService,
+    invalidation: InvalidationService,
+    price_alert:  PriceAlertService,
+}
+
+impl EventService {
+    /// Seconds between checking if an industry job is completed
+    const TICK:        u64      = 60;
+    /// Time until the industry jobs are requested from ESI again
+    const JOB_REFRESH: Duration = Duration::from_secs(10 * 60);
+
+    /// Creates a new instance
+    pub fn new(
+        pool:         ConnectionPool,
+        eve_auth:     EveAuthService,
+        industry:     IndustryService,
+        invalidation: InvalidationService,
+        price_alert:  PriceAlertService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            industry,
+            invalidation,
+            price_alert,
+        }
+    }
+
+    /// Sends events of the main and its alts to the websocket until the
+    /// client disconnects.
+    ///
+    /// Assets and wallet transactions are checked as soon as the database
+    /// reports a change, industry jobs are checked every minute. Price
+    /// alerts are sent as soon as they trigger.
+    ///
+    pub async fn feed(
+        &self,
+        socket: WebSocket,
+        token:  String,
+    ) {
+        let (mut tx, _) = socket.split();
+
+        let user = match self.eve_auth.lookup(&token).await {
+            Ok(Some(x)) => x,
+            _           => {
+                let _ = tx.send(Message::close()).await;
+                return;
+            }
+        };
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let mut changes = self.invalidation.subscribe();
+        let mut alerts = self.price_alert.subscribe();
+
+        // Only transactions after connecting are sent
+        let mut transactions = self
+            .transactions(&user_ids)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.transaction_id)
+            .collect::<HashSet<_>>();
+        let mut jobs: HashMap<u32, IndustryJob> = HashMap::new();
+        let mut jobs_fetched: Option<Instant> = None;
+
+        loop {
+            let mut events = Vec::new();
+
+            if jobs_fetched.map(|x| x.elapsed() >= Self::JOB_REFRESH).unwrap_or(true) {
+                match self.industry.jobs(token.clone()).await {
+                    Ok(x) => {
+                        x
+                            .into_iter()
+                            .filter(|x| x.status == "active")
+                            .for_each(|x| { jobs.entry(x.job_id).or_insert(x); });
+                    },
+                    Err(e) => log::error!("Error loading industry jobs {:?}", e),
+                }
+                jobs_fetched = Some(Instant::now());
+            }
+
+            let now = Utc::now();
+            let completed = jobs
+                .values()
+                .filter(|x| {
+                    x.end_date
+                        .parse::<DateTime<Utc>>()
+                        .map(|x| x <= now)
+                        .unwrap_or_default()
+                })
+                .map(|x| x.job_id)
+                .collect::<Vec<_>>();
+            for job_id in completed {
+                if let Some(x) = jobs.remove(&job_id) {
+                    events.push(Event::from(x));
+                }
+            }
+
+            if !Self::send(&mut tx, events).await {
+                return;
+            }
+
+            let cache = tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(Self::TICK)) => None,
+                x = InvalidationService::next(&mut changes) => Some(x),
+                x = alerts.recv() => {
+                    match x {
+                        Ok(x) if x.user_id == user.user_id => {
+                            if !Self::send(&mut tx, vec![Event::from(x)]).await {
+                                return;
+                            }
+                        },
+                        _ => (),
+                    }
+                    None
+                },
+            };
+            let cache = match cache {
+                Some(x) => x,
+                None    => continue,
+            };
+
+            let mut events = Vec::new();
+            if Self::is_cache(cache, CacheName::CharacterAsset) {
+                events.push(Event::AssetsRefreshed);
+            }
+            if Self::is_cache(cache, CacheName::WalletTransaction) {
+                let new = self
+                    .transactions(&user_ids)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|x| transactions.insert(x.transaction_id))
+                    .map(Event::from)
+                    .collect::<Vec<_>>();
+                events.extend(new);
+            }
+
+            if !Self::send(&mut tx, events).await {
+                return;
+            }
+        }
+    }
+
+    /// Sends all events, returns `false` if the client disconnected
+    async fn send(
+        tx:     &mut SplitSink<WebSocket, Message>,
+        events: Vec<Event>,
+    ) -> bool {
+        for event in events {
+            let message = serde_json::to_string(&event).unwrap_or_default();
+            if tx.send(Message::text(message)).await.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Wallet transactions of the given characters
+    async fn transactions(
+        &self,
+        user_ids: &[CharacterId],
+    ) -> Result<Vec<WalletTransactionEntry>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, TransactionId>(CacheName::WalletTransaction)
+            .await?;
+        let transactions = con
+            .mget::<_, _, WalletTransactionEntry>(CacheName::WalletTransaction, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .collect::<Vec<_>>();
+        Ok(transactions)
+    }
+
+    /// Checks if the notification is about the given cache, [None] means
+    /// that every cache was modified
+    fn is_cache(cache: Option<u8>, name: CacheName) -> bool {
+        cache.map(|x| x == name.into()).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The assets of at least one character were refreshed
+    AssetsRefreshed,
+    /// The end date of an industry job was reached
+    IndustryJobCompleted {
+        job_id:            u32,
+        installer_id:      CharacterId,
+        activity_id:       ActivityId,
+        blueprint_type_id: TypeId,
+        runs:              u32,
+    },
+    /// A market order was filled, completely or partially
+    MarketOrderFilled {
+        transaction_id: TransactionId,
+        user_id:        CharacterId,
+        type_id:        TypeId,
+        quantity:       u32,
+        unit_price:     f32,
+        is_buy:         bool,
+    },
+    /// The price of an item crossed the threshold of a price alert
+    PriceAlertTriggered {
+        id:        Uuid,
+        type_id:   TypeId,
+        above:     bool,
+        threshold: f32,
+        price:     f32,
+    },
+}
+
+impl From<IndustryJob> for Event {
+    fn from(x: IndustryJob) -> Self {
+        Self::IndustryJobCompleted {
+            job_id:            x.job_id,
+            installer_id:      x.installer_id,
+            activity_id:       x.activity_id,
+            blueprint_type_id: x.blueprint_type_id,
+            runs:              x.runs,
+        }
+    }
+}
+
+impl From<PriceAlertEntry> for Event {
+    fn from(x: PriceAlertEntry) -> Self {
+        Self::PriceAlertTriggered {
+            id:        x.id,
+            type_id:   x.type_id,
+            above:     x.above,
+            threshold: x.threshold,
+            price:     x.triggers.first().map(|x| x.price).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<WalletTransactionEntry> for Event {
+    fn from(x: WalletTransactionEntry) -> Self {
+        Self::MarketOrderFilled {
+            transaction_id: x.transaction_id,
+            user_id:        x.user_id,
+            type_id:        x.type_id,
+            quantity:       x.quantity,
+            unit_price:     x.unit_price,
+            is_buy:         x.is_buy,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::audit::{AuditAction, AuditService};
+use crate::character::CharacterService;
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::market::{MarketService, VenueQuery};
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::CacheName;
+use caph_eve_data_wrapper::{ItemId, TypeId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Exports lists as csv, so that they can be opened in a spreadsheet
+#[derive(Clone)]
+pub struct ExportService {
+    pool:      ConnectionPool,
+    audit:     AuditService,
+    character: CharacterService,
+    eve_auth:  EveAuthService,
+    market:    MarketService,
+}
+
+impl ExportService {
+    const ASSET_COLUMNS:     &'static [&'static str] = &[
+        "user_id", "item_id", "type_id", "name", "quantity", "location_id", "location_flag",
+    ];
+    const BLUEPRINT_COLUMNS: &'static [&'static str] = &[
+        "user_id", "item_id", "type_id", "name", "material_efficiency", "time_efficiency",
+        "runs", "quantity", "location_id", "location_flag",
+    ];
+    const VENUE_COLUMNS:     &'static [&'static str] = &[
+        "location_id", "name", "broker_fee", "best_sell", "sell_order", "instant", "instant_unsold",
+    ];
+
+    /// Creates a new instance
+    pub fn new(
+        pool:      ConnectionPool,
+        audit:     AuditService,
+        character: CharacterService,
+        eve_auth:  EveAuthService,
+        market:    MarketService,
+    ) -> Self {
+        Self {
+            pool,
+            audit,
+            character,
+            eve_auth,
+            market,
+        }
+    }
+
+    /// Exports the assets of the main and its alts
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Columns to export
+    ///
+    /// # Returns
+    ///
+    /// Csv with a header line and one line per asset stack
+    ///
+    pub async fn assets(
+        &self,
+        token: &str,
+        query: ExportQuery,
+    ) -> Result<String, EveServerError> {
+        self.record(token, "assets").await?;
+        let assets = self.character.all_assets(token).await?;
+        let rows = self.rows_with_names(&assets).await?;
+        Self::to_csv(&rows, &query.columns(Self::ASSET_COLUMNS))
+    }
+
+    /// Exports the blueprints of the main and its alts
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Columns to export
+    ///
+    /// # Returns
+    ///
+    /// Csv with a header line and one line per blueprint
+    ///
+    pub async fn blueprints(
+        &self,
+        token: String,
+        query: ExportQuery,
+    ) -> Result<String, EveServerError> {
+        self.record(&token, "blueprints").await?;
+        let blueprints = self.character.blueprints(token).await?;
+        let rows = self.rows_with_names(&blueprints).await?;
+        Self::to_csv(&rows, &query.columns(Self::BLUEPRINT_COLUMNS))
+    }
+
+    /// Exports the comparison of the market venues for an item
+    ///
+    /// # Params
+    ///
+    /// `tid`   -> Item to sell
+    /// `query` -> Quantity, fees of the seller and columns to export
+    ///
+    /// # Returns
+    ///
+    /// Csv with a header line and one line per venue
+    ///
+    pub async fn market_venues(
+        &self,
+        tid:   TypeId,
+        query: MarketExportQuery,
+    ) -> Result<String, EveServerError> {
+        let venues = VenueQuery {
+            quantity:   query.quantity,
+            sales_tax:  query.sales_tax,
+            broker_fee: query.broker_fee,
+        };
+        let columns = ExportQuery { columns: query.columns };
+
+        let rows = self
+            .market
+            .venues(tid, venues)
+            .await?
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::to_csv(&rows, &columns.columns(Self::VENUE_COLUMNS))
+    }
+
+    /// Records the export of personal data in the audit log
+    async fn record(
+        &self,
+        token:  &str,
+        export: &str,
+    ) -> Result<(), EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        self.audit.record(user.user_id, AuditAction::Export, None, export).await
+    }
+
+    /// Converts the entries to json objects and adds the name of their
+    /// `type_id`
+    async fn rows_with_names<T: Serialize>(
+        &self,
+        entries: &[T],
+    ) -> Result<Vec<Value>, EveServerError> {
+        let mut rows = entries
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let type_ids = rows
+            .iter()
+            .map(|x| x["type_id"].as_u64().unwrap_or_default())
+            .map(ItemId)
+            .collect::<Vec<_>>();
+        let names = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, String>(CacheName::Name, type_ids)
+            .await?;
+
+        for (row, name) in rows.iter_mut().zip(names) {
+            row["name"] = name.map(Value::String).unwrap_or(Value::Null);
+        }
+        Ok(rows)
+    }
+
+    /// Writes the given columns of every row as csv.
+    ///
+    /// Fields that contain a separator, quote or line break are quoted,
+    /// missing fields are left empty.
+    fn to_csv(
+        rows:    &[Value],
+        columns: &[String],
+    ) -> Result<String, EveServerError> {
+        if let Some(row) = rows.first() {
+            if let Some(x) = columns.iter().find(|x| row.get(x.as_str()).is_none()) {
+                return Err(EveServerError::UnknownColumn(x.clone()));
+            }
+        }
+
+        let mut csv = columns
+            .iter()
+            .map(|x| Self::escape(x))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push_str("\r\n");
+
+        for row in rows {
+            let line = columns
+                .iter()
+                .map(|x| match row.get(x.as_str()) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(x))   => Self::escape(x),
+                    Some(x)                  => Self::escape(&x.to_string()),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            csv.push_str(&line);
+            csv.push_str("\r\n");
+        }
+        Ok(csv)
+    }
+
+    fn escape(field: &str) -> String {
+        if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.into()
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ExportQuery {
+    /// Comma separated list of columns, defaults to all columns
+    pub columns: Option<String>,
+}
+
+/// Same as [VenueQuery] with the columns to export
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MarketExportQuery {
+    pub quantity:   u32,
+    /// Sales tax of the seller in percent
+    pub sales_tax:  Option<f32>,
+    /// Broker fee of the seller at NPC stations in percent
+    pub broker_fee: Option<f32>,
+    /// Comma separated list of columns, defaults to all columns
+    pub columns:    Option<String>,
+}
+
+impl ExportQuery {
+    fn columns(&self, default: &[&str]) -> Vec<String> {
+        match &self.columns {
+            Some(x) => x
+                .split(',')
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            None    => default.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn to_csv() {
+        let rows = vec![
+            json!({ "type_id": 34, "name": "Tritanium", "quantity": 100 }),
+            json!({ "type_id": 35, "name": "Pyerite, \"compressed\"", "quantity": null }),
+        ];
+        let columns = vec!["name".into(), "quantity".into()];
+
+        let csv = ExportService::to_csv(&rows, &columns).unwrap();
+        assert_eq!(
+            csv,
+            "name,quantity\r\nTritanium,100\r\n\"Pyerite, \"\"compressed\"\"\",\r\n"
+        );
+    }
+
+    #[test]
+    fn unknown_column() {
+        let rows = vec![json!({ "type_id": 34 })];
+        let columns = vec!["volume".into()];
+        assert!(ExportService::to_csv(&rows, &columns).is_err());
+    }
+
+    #[test]
+    fn columns() {
+        let query = ExportQuery { columns: Some("name, quantity,,".into()) };
+        assert_eq!(query.columns(&["type_id"]), vec!["name", "quantity"]);
+        assert_eq!(ExportQuery::default().columns(&["type_id"]), vec!["type_id"]);
+    }
+}
@@ -0,0 +1,759 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterFittingEntry, CharacterFittingItemEntry, CorporationAssetEntry, FittingEntry, ItemEntry, MarketPriceEntry};
+use caph_eve_data_wrapper::{CharacterId, CorporationId, FittingId, ItemId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Service for fittings from ESI and fittings submitted by users
+#[derive(Clone)]
+pub struct FittingService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+}
+
+impl FittingService {
+    const CATEGORY_SHIP:    u32 = 6;
+    const CATEGORY_DRONE:   u32 = 18;
+    const CATEGORY_FIGHTER: u32 = 87;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+        }
+    }
+
+    /// Gets all fittings of the character and its alts, both from ESI and
+    /// the ones that were imported
+    pub async fn fittings(
+        &self,
+        token: &str,
+    ) -> Result<Vec<Fitting>, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        let fitting_ids = con
+            .keys::<_, FittingId>(CacheName::CharacterFitting)
+            .await?;
+        let esi = con
+            .mget::<_, _, CharacterFittingEntry>(CacheName::CharacterFitting, fitting_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .map(Fitting::from);
+
+        let ids = con
+            .keys::<_, Uuid>(CacheName::Fitting)
+            .await?;
+        let fittings = con
+            .mget::<_, _, FittingEntry>(CacheName::Fitting, ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .map(Fitting::from)
+            .chain(esi)
+            .collect::<Vec<_>>();
+        Ok(fittings)
+    }
+
+    /// Imports a fitting in the EFT format.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `eft`   -> Fitting in the EFT format
+    ///
+    /// # Returns
+    ///
+    /// The stored fitting, fails if the hull is not a ship or one of the
+    /// items is unknown
+    ///
+    pub async fn import(
+        &self,
+        token: &str,
+        eft:   String,
+    ) -> Result<Fitting, EveServerError> {
+        let user_id = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?
+            .user_id;
+
+        let eft = Eft::parse(&eft)?;
+        let items = self
+            .items()
+            .await?
+            .into_iter()
+            .map(|x| (x.name.clone(), x))
+            .collect::<HashMap<_, _>>();
+
+        let ship = items
+            .get(&eft.ship)
+            .filter(|x| *x.category_id == Self::CATEGORY_SHIP)
+            .ok_or_else(|| EveServerError::InvalidFitting(eft.ship.clone()))?;
+
+        let mut fitting_items = Vec::new();
+        for (flag, name) in eft.modules {
+            let item = items
+                .get(&name)
+                .ok_or_else(|| EveServerError::InvalidFitting(name.clone()))?;
+            fitting_items.push(CharacterFittingItemEntry {
+                flag,
+                quantity: 1,
+                type_id:  item.item_id,
+            });
+        }
+        for (name, quantity) in eft.cargo {
+            let item = items
+                .get(&name)
+                .ok_or_else(|| EveServerError::InvalidFitting(name.clone()))?;
+            let flag = match *item.category_id {
+                Self::CATEGORY_DRONE   => "DroneBay",
+                Self::CATEGORY_FIGHTER => "FighterBay",
+                _                      => "Cargo",
+            };
+            fitting_items.push(CharacterFittingItemEntry {
+                flag: flag.into(),
+                quantity,
+                type_id: item.item_id,
+            });
+        }
+
+        let entry = FittingEntry {
+            id:           Uuid::new_v4(),
+            description:  String::new(),
+            items:        fitting_items,
+            name:         eft.name,
+            ship_type_id: ship.item_id,
+            user_id,
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::Fitting, entry.id, entry.clone())
+            .await?;
+        Ok(Fitting::from(entry))
+    }
+
+    /// Exports an imported fitting in the EFT format
+    pub async fn export(
+        &self,
+        token: &str,
+        id:    Uuid,
+    ) -> Result<String, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let fitting = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, FittingEntry>(CacheName::Fitting, id)
+            .await?
+            .filter(|x| user_ids.contains(&x.user_id))
+            .ok_or(EveServerError::FittingNotFound)?;
+        self.to_eft(Fitting::from(fitting)).await
+    }
+
+    /// Exports a fitting from ESI in the EFT format
+    pub async fn export_esi(
+        &self,
+        token: &str,
+        fid:   FittingId,
+    ) -> Result<String, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let fitting = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, CharacterFittingEntry>(CacheName::CharacterFitting, fid)
+            .await?
+            .filter(|x| user_ids.contains(&x.user_id))
+            .ok_or(EveServerError::FittingNotFound)?;
+        self.to_eft(Fitting::from(fitting)).await
+    }
+
+    /// Calculates the costs of a doctrine and compares the required items
+    /// with the assets of a corporation.
+    ///
+    /// Every fitting is expanded into its hull, modules, charges, drones and
+    /// cargo.
+    ///
+    /// # Params
+    ///
+    /// `token`    -> Cookie from the requesting main
+    /// `doctrine` -> Fittings with the number of ships and the corporation
+    ///               whose assets should be used, defaults to the corporation
+    ///               of the main
+    ///
+    /// # Returns
+    ///
+    /// Costs per fitting and for the whole doctrine together with all items
+    /// the corporation does not have enough of. Fails if a fitting does not
+    /// exist or neither the main nor one of its alts is a member of the
+    /// corporation
+    ///
+    pub async fn doctrine(
+        &self,
+        token:    &str,
+        doctrine: Doctrine,
+    ) -> Result<DoctrineReport, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let corporation_id = doctrine.corporation_id.unwrap_or(user.corp_id);
+        let is_member = user.corp_id == corporation_id ||
+            user.aliase.iter().any(|x| x.corp_id == corporation_id);
+        if !is_member {
+            return Err(EveServerError::MissingPermission);
+        }
+
+        let fittings = self.fittings(token).await?;
+        let mut doctrine_fittings = Vec::new();
+        for x in doctrine.fittings {
+            let fitting = fittings
+                .iter()
+                .find(|y| {
+                    (x.id.is_some() && y.id == x.id) ||
+                    (x.fitting_id.is_some() && y.fitting_id == x.fitting_id)
+                })
+                .cloned()
+                .ok_or(EveServerError::FittingNotFound)?;
+            doctrine_fittings.push((fitting, x.count));
+        }
+
+        let mut type_ids = doctrine_fittings
+            .iter()
+            .flat_map(|(x, _)| {
+                x.items
+                    .iter()
+                    .map(|x| x.type_id)
+                    .chain(std::iter::once(x.ship_type_id))
+            })
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+
+        let mut con = self.pool.acquire().await?;
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids.clone())
+            .await?
+            .into_iter()
+            .zip(type_ids.iter())
+            .map(|(x, tid)| (*tid, x.map(|x| x.average_price).unwrap_or_default()))
+            .collect::<HashMap<_, _>>();
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CorporationAsset)
+            .await?;
+        let mut available: HashMap<TypeId, u64> = HashMap::new();
+        con
+            .mget::<_, _, CorporationAssetEntry>(CacheName::CorporationAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.corporation_id == corporation_id)
+            .filter(|x| type_ids.contains(&x.type_id))
+            .for_each(|x| *available.entry(x.type_id).or_default() += x.quantity as u64);
+
+        let mut required: HashMap<TypeId, u64> = HashMap::new();
+        let mut fits = Vec::new();
+        for (fitting, count) in doctrine_fittings {
+            let mut items: HashMap<TypeId, u64> = HashMap::new();
+            *items.entry(fitting.ship_type_id).or_default() += 1;
+            for item in fitting.items.iter() {
+                *items.entry(item.type_id).or_default() += item.quantity as u64;
+            }
+
+            let unit_cost = items
+                .iter()
+                .map(|(tid, quantity)| {
+                    prices.get(tid).copied().unwrap_or_default() * *quantity as f32
+                })
+                .sum::<f32>();
+            for (tid, quantity) in items {
+                *required.entry(tid).or_default() += quantity * count as u64;
+            }
+
+            fits.push(DoctrineFitCost {
+                id:           fitting.id,
+                fitting_id:   fitting.fitting_id,
+                name:         fitting.name,
+                ship_type_id: fitting.ship_type_id,
+                count,
+                unit_cost,
+                total_cost:   unit_cost * count as f32,
+            });
+        }
+
+        let mut shortages = required
+            .into_iter()
+            .filter_map(|(type_id, required)| {
+                let available = available.get(&type_id).copied().unwrap_or_default();
+                if available >= required {
+                    return None;
+                }
+
+                let missing = required - available;
+                Some(DoctrineShortage {
+                    type_id,
+                    required,
+                    available,
+                    missing,
+                    cost: prices.get(&type_id).copied().unwrap_or_default() * missing as f32,
+                })
+            })
+            .collect::<Vec<_>>();
+        shortages.sort_by(|a, b| {
+            b.cost
+                .partial_cmp(&a.cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(DoctrineReport {
+            corporation_id,
+            total_cost:    fits.iter().map(|x| x.total_cost).sum(),
+            shortage_cost: shortages.iter().map(|x| x.cost).sum(),
+            fits,
+            shortages,
+        })
+    }
+
+    /// Deletes an imported fitting
+    pub async fn delete(
+        &self,
+        token: &str,
+        id:    Uuid,
+    ) -> Result<(), EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        con
+            .get::<_, _, FittingEntry>(CacheName::Fitting, id)
+            .await?
+            .filter(|x| user_ids.contains(&x.user_id))
+            .ok_or(EveServerError::FittingNotFound)?;
+        con
+            .del(CacheName::Fitting, id)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn to_eft(&self, fitting: Fitting) -> Result<String, EveServerError> {
+        let mut type_ids = fitting
+            .items
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        type_ids.push(fitting.ship_type_id);
+
+        let ids = type_ids
+            .iter()
+            .map(|x| ItemId::from(*x))
+            .collect::<Vec<_>>();
+        let names = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, String>(CacheName::Name, ids)
+            .await?
+            .into_iter()
+            .zip(type_ids)
+            .filter_map(|(name, tid)| name.map(|x| (tid, x)))
+            .collect::<HashMap<_, _>>();
+        Ok(Eft::write(&fitting, &names))
+    }
+
+    async fn items(&self) -> Result<Vec<ItemEntry>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Item)
+            .await?;
+        let items = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        Ok(items)
+    }
+
+    async fn user_ids(&self, token: &str) -> Result<Vec<CharacterId>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+        Ok(user_ids)
+    }
+}
+
+/// Parser and writer for the EFT fitting format.
+///
+/// ```text
+/// [Rifter, My Rifter]
+/// Damage Control II
+///
+/// 1MN Afterburner II
+///
+/// 200mm AutoCannon II, EMP S
+/// [Empty High slot]
+///
+/// Hobgoblin II x5
+/// ```
+///
+/// Modules are grouped by low, mid, high, rig and subsystem slots, every
+/// group separated by an empty line. Drones and cargo have a quantity.
+struct Eft;
+
+impl Eft {
+    /// Order of the module sections
+    const SLOTS: &'static [&'static str] = &[
+        "LoSlot",
+        "MedSlot",
+        "HiSlot",
+        "RigSlot",
+        "SubSystemSlot",
+    ];
+
+    /// Parses the EFT format without resolving any names.
+    fn parse(eft: &str) -> Result<EftFitting, EveServerError> {
+        let mut lines = eft
+            .lines()
+            .map(|x| x.trim())
+            .skip_while(|x| x.is_empty());
+
+        let header = lines
+            .next()
+            .filter(|x| x.starts_with('[') && x.ends_with(']'))
+            .ok_or_else(|| EveServerError::InvalidFitting(String::new()))?;
+        let mut header = header[1..header.len() - 1].splitn(2, ',');
+        let ship = header
+            .next()
+            .map(|x| x.trim().to_string())
+            .unwrap_or_default();
+        let name = header
+            .next()
+            .map(|x| x.trim().to_string())
+            .unwrap_or_default();
+
+        let mut sections: Vec<Vec<&str>> = vec![Vec::new()];
+        for line in lines {
+            if line.is_empty() {
+                if !sections.last().map(|x| x.is_empty()).unwrap_or(true) {
+                    sections.push(Vec::new());
+                }
+            } else if let Some(x) = sections.last_mut() {
+                x.push(line);
+            }
+        }
+
+        let mut modules = Vec::new();
+        let mut cargo = Vec::new();
+        let mut slot = 0usize;
+        for section in sections.into_iter().filter(|x| !x.is_empty()) {
+            let is_cargo = section
+                .iter()
+                .all(|x| Self::quantity(x).is_some());
+            if is_cargo || slot >= Self::SLOTS.len() {
+                for line in section {
+                    let (name, quantity) = Self::quantity(line)
+                        .unwrap_or((line, 1));
+                    cargo.push((name.to_string(), quantity));
+                }
+                continue;
+            }
+
+            for (index, line) in section.into_iter().enumerate() {
+                // Empty slots only take up the index
+                if line.starts_with("[Empty") {
+                    continue;
+                }
+
+                // Loaded charges are put into the cargo
+                let mut line = line.splitn(2, ',');
+                let module = line.next().unwrap_or_default().trim();
+                if let Some(charge) = line.next() {
+                    cargo.push((charge.trim().to_string(), 1));
+                }
+
+                let module = module.trim_end_matches("/OFFLINE").trim();
+                let flag = format!("{}{}", Self::SLOTS[slot], index);
+                modules.push((flag, module.to_string()));
+            }
+            slot += 1;
+        }
+
+        Ok(EftFitting {
+            ship,
+            name,
+            modules,
+            cargo,
+        })
+    }
+
+    /// Writes a fitting in the EFT format, items without name are skipped
+    fn write(fitting: &Fitting, names: &HashMap<TypeId, String>) -> String {
+        let name = |tid: &TypeId| names.get(tid).cloned().unwrap_or_default();
+
+        let mut eft = format!("[{}, {}]\n", name(&fitting.ship_type_id), fitting.name);
+        for slot in Self::SLOTS {
+            let mut modules = fitting
+                .items
+                .iter()
+                .filter_map(|x| {
+                    x.flag
+                        .strip_prefix(slot)
+                        .and_then(|x| x.parse::<u32>().ok())
+                        .map(|index| (index, name(&x.type_id)))
+                })
+                .collect::<Vec<_>>();
+            if modules.is_empty() {
+                continue;
+            }
+            modules.sort();
+
+            for (_, module) in modules {
+                eft.push_str(&module);
+                eft.push('\n');
+            }
+            eft.push('\n');
+        }
+
+        for bay in &["DroneBay", "FighterBay", "Cargo"] {
+            let items = fitting
+                .items
+                .iter()
+                .filter(|x| x.flag == *bay)
+                .collect::<Vec<_>>();
+            if items.is_empty() {
+                continue;
+            }
+
+            for item in items {
+                eft.push_str(&format!("{} x{}\n", name(&item.type_id), item.quantity));
+            }
+            eft.push('\n');
+        }
+
+        eft.trim_end().to_string()
+    }
+
+    /// Splits a line like `Hobgoblin II x5` into name and quantity
+    fn quantity(line: &str) -> Option<(&str, u32)> {
+        let (name, quantity) = line.rsplit_once(" x")?;
+        quantity
+            .parse::<u32>()
+            .ok()
+            .map(|x| (name.trim(), x))
+    }
+}
+
+/// Fitting in the EFT format with unresolved names
+#[derive(Debug, PartialEq)]
+struct EftFitting {
+    ship:    String,
+    name:    String,
+    /// Slot flag and name of the module
+    modules: Vec<(String, String)>,
+    /// Name and quantity of drones and cargo
+    cargo:   Vec<(String, u32)>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Fitting {
+    /// Set for imported fittings
+    pub id:           Option<Uuid>,
+    /// Set for fittings from ESI
+    pub fitting_id:   Option<FittingId>,
+    pub description:  String,
+    pub items:        Vec<CharacterFittingItemEntry>,
+    pub name:         String,
+    pub ship_type_id: TypeId,
+    pub user_id:      CharacterId,
+}
+
+impl From<FittingEntry> for Fitting {
+    fn from(x: FittingEntry) -> Self {
+        Self {
+            id:           Some(x.id),
+            fitting_id:   None,
+            description:  x.description,
+            items:        x.items,
+            name:         x.name,
+            ship_type_id: x.ship_type_id,
+            user_id:      x.user_id,
+        }
+    }
+}
+
+impl From<CharacterFittingEntry> for Fitting {
+    fn from(x: CharacterFittingEntry) -> Self {
+        Self {
+            id:           None,
+            fitting_id:   Some(x.fitting_id),
+            description:  x.description,
+            items:        x.items,
+            name:         x.name,
+            ship_type_id: x.ship_type_id,
+            user_id:      x.user_id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Doctrine {
+    /// Defaults to the corporation of the main
+    pub corporation_id: Option<CorporationId>,
+    pub fittings:       Vec<DoctrineFitting>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DoctrineFitting {
+    /// Set for imported fittings
+    pub id:         Option<Uuid>,
+    /// Set for fittings from ESI
+    pub fitting_id: Option<FittingId>,
+    /// Number of ships
+    pub count:      u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctrineReport {
+    pub corporation_id: CorporationId,
+    pub total_cost:     f32,
+    /// Cost of buying all missing items
+    pub shortage_cost:  f32,
+    pub fits:           Vec<DoctrineFitCost>,
+    /// Items the corporation does not have enough of, most expensive first
+    pub shortages:      Vec<DoctrineShortage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctrineFitCost {
+    pub id:           Option<Uuid>,
+    pub fitting_id:   Option<FittingId>,
+    pub name:         String,
+    pub ship_type_id: TypeId,
+    pub count:        u32,
+    /// Cost of a single ship
+    pub unit_cost:    f32,
+    pub total_cost:   f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctrineShortage {
+    pub type_id:   TypeId,
+    pub required:  u64,
+    pub available: u64,
+    pub missing:   u64,
+    pub cost:      f32,
+}
+
+#[cfg(test)]
+mod eft_tests {
+    use super::*;
+
+    const RIFTER: &str = "[Rifter, My Rifter]
+Damage Control II
+Gyrostabilizer II
+
+1MN Afterburner II
+
+200mm AutoCannon II, EMP S
+[Empty High slot]
+Small Energy Neutralizer II
+
+Small Projectile Burst Aerator I
+
+Hobgoblin II x5
+
+EMP S x1000";
+
+    #[test]
+    fn parse() {
+        let eft = Eft::parse(RIFTER).unwrap();
+
+        assert_eq!(eft.ship, "Rifter");
+        assert_eq!(eft.name, "My Rifter");
+        assert_eq!(
+            eft.modules,
+            vec![
+                ("LoSlot0".into(),  "Damage Control II".into()),
+                ("LoSlot1".into(),  "Gyrostabilizer II".into()),
+                ("MedSlot0".into(), "1MN Afterburner II".into()),
+                ("HiSlot0".into(),  "200mm AutoCannon II".into()),
+                ("HiSlot2".into(),  "Small Energy Neutralizer II".into()),
+                ("RigSlot0".into(), "Small Projectile Burst Aerator I".into()),
+            ]
+        );
+        assert_eq!(
+            eft.cargo,
+            vec![
+                ("EMP S".into(), 1),
+                ("Hobgoblin II".into(), 5),
+                ("EMP S".into(), 1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_header() {
+        assert!(Eft::parse("Rifter, My Rifter").is_err());
+    }
+
+    #[test]
+    fn write() {
+        let names = vec![
+            (587u32, "Rifter"),
+            (2048,   "Damage Control II"),
+            (2281,   "Hobgoblin II"),
+        ]
+        .into_iter()
+        .map(|(id, name)| (TypeId::from(id), name.to_string()))
+        .collect::<HashMap<_, _>>();
+        let item = |flag: &str, quantity: u32, tid: u32| CharacterFittingItemEntry {
+            flag: flag.into(),
+            quantity,
+            type_id: tid.into(),
+        };
+
+        let fitting = Fitting {
+            id:           None,
+            fitting_id:   None,
+            description:  String::new(),
+            items:        vec![
+                item("DroneBay", 5, 2281),
+                item("LoSlot0",  1, 2048),
+            ],
+            name:         "My Rifter".into(),
+            ship_type_id: 587.into(),
+            user_id:      0.into(),
+        };
+
+        assert_eq!(
+            Eft::write(&fitting, &names),
+            "[Rifter, My Rifter]\nDamage Control II\n\nHobgoblin II x5"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::market::MarketService;
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Request, Response, Result, Schema, SimpleObject};
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterAssetEntry, CharacterBlueprintEntry, ItemEntry, MarketPriceEntry};
+use caph_eve_data_wrapper::{CharacterId, ItemId, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type CaphSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// GraphQL api for dashboards that need data of multiple routes at once,
+/// for example the assets of all characters together with the item and its
+/// market price.
+#[derive(Clone)]
+pub struct GraphQlService {
+    pool:   ConnectionPool,
+    schema: CaphSchema,
+}
+
+impl GraphQlService {
+    /// Maximum number of items that can be requested at once
+    const MAX_IDS: usize = 1_000;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+        market:   MarketService,
+    ) -> Self {
+        let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .data(pool.clone())
+            .data(eve_auth)
+            .data(market)
+            .finish();
+
+        Self {
+            pool,
+            schema,
+        }
+    }
+
+    /// Executes a query
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting main
+    /// `request` -> GraphQL query
+    ///
+    /// # Returns
+    ///
+    /// Result of the query, errors are part of the response
+    ///
+    pub async fn execute(
+        &self,
+        token:   String,
+        request: Request,
+    ) -> Response {
+        // the loaders are created for every request so that no stale data
+        // is returned
+        let request = request
+            .data(Token(token))
+            .data(DataLoader::new(ItemLoader(self.pool.clone())))
+            .data(DataLoader::new(PriceLoader(self.pool.clone())));
+        self.schema.execute(request).await
+    }
+}
+
+/// Token of the requesting main
+struct Token(String);
+
+/// Entry point of all queries
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Main of the requesting user and all its alts
+    async fn characters(&self, ctx: &Context<'_>) -> Result<Vec<CharacterNode>> {
+        let token = ctx.data::<Token>()?;
+        let user = ctx
+            .data::<EveAuthService>()?
+            .lookup(&token.0)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let mut characters = vec![CharacterNode {
+            user_id: user.user_id,
+            is_main: true,
+        }];
+        characters.extend(user.aliase.iter().map(|x| CharacterNode {
+            user_id: x.user_id,
+            is_main: false,
+        }));
+        Ok(characters)
+    }
+
+    /// Single item
+    async fn item(&self, ctx: &Context<'_>, type_id: u32) -> Result<Option<ItemNode>> {
+        ItemNode::load(ctx, type_id.into()).await
+    }
+
+    /// Multiple items, unknown items are skipped
+    async fn items(&self, ctx: &Context<'_>, type_ids: Vec<u32>) -> Result<Vec<ItemNode>> {
+        if type_ids.len() > GraphQlService::MAX_IDS {
+            return Err(EveServerError::TooManyIds.into());
+        }
+
+        let items = ctx
+            .data::<DataLoader<ItemLoader>>()?
+            .load_many(type_ids.into_iter().map(TypeId::from))
+            .await?
+            .into_iter()
+            .map(|(_, x)| ItemNode(x))
+            .collect::<Vec<_>>();
+        Ok(items)
+    }
+}
+
+/// Character of the requesting user
+struct CharacterNode {
+    user_id: CharacterId,
+    is_main: bool,
+}
+
+#[Object(name = "Character")]
+impl CharacterNode {
+    async fn user_id(&self) -> u32 {
+        *self.user_id
+    }
+
+    async fn is_main(&self) -> bool {
+        self.is_main
+    }
+
+    /// Name of the character, if it is known
+    async fn name(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let name = ctx
+            .data::<ConnectionPool>()?
+            .acquire()
+            .await?
+            .get::<_, _, String>(CacheName::Name, ItemId(*self.user_id as u64))
+            .await?;
+        Ok(name)
+    }
+
+    async fn assets(&self, ctx: &Context<'_>) -> Result<Vec<AssetNode>> {
+        let mut con = ctx.data::<ConnectionPool>()?.acquire().await?;
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.user_id == self.user_id)
+            .map(AssetNode)
+            .collect::<Vec<_>>();
+        Ok(assets)
+    }
+
+    async fn blueprints(&self, ctx: &Context<'_>) -> Result<Vec<BlueprintNode>> {
+        let mut con = ctx.data::<ConnectionPool>()?.acquire().await?;
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterBlueprint)
+            .await?;
+        let blueprints = con
+            .mget::<_, _, CharacterBlueprintEntry>(CacheName::CharacterBlueprint, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.user_id == self.user_id)
+            .map(BlueprintNode)
+            .collect::<Vec<_>>();
+        Ok(blueprints)
+    }
+}
+
+struct AssetNode(CharacterAssetEntry);
+
+#[Object(name = "Asset")]
+impl AssetNode {
+    async fn item_id(&self) -> u64 {
+        *self.0.item_id
+    }
+
+    async fn location_id(&self) -> u64 {
+        *self.0.location_id
+    }
+
+    async fn location_flag(&self) -> &str {
+        &self.0.location_flag
+    }
+
+    async fn quantity(&self) -> u32 {
+        self.0.quantity
+    }
+
+    async fn type_id(&self) -> u32 {
+        *self.0.type_id
+    }
+
+    async fn item(&self, ctx: &Context<'_>) -> Result<Option<ItemNode>> {
+        ItemNode::load(ctx, self.0.type_id).await
+    }
+
+    /// Average market price of the whole stack
+    async fn value(&self, ctx: &Context<'_>) -> Result<Option<f32>> {
+        let value = MarketPriceNode::load(ctx, self.0.type_id)
+            .await?
+            .map(|x| x.average_price * self.0.quantity as f32);
+        Ok(value)
+    }
+}
+
+struct BlueprintNode(CharacterBlueprintEntry);
+
+#[Object(name = "Blueprint")]
+impl BlueprintNode {
+    async fn item_id(&self) -> u64 {
+        *self.0.item_id
+    }
+
+    async fn location_id(&self) -> u64 {
+        *self.0.location_id
+    }
+
+    async fn type_id(&self) -> u32 {
+        *self.0.type_id
+    }
+
+    async fn is_copy(&self) -> bool {
+        self.0.quantity == -2
+    }
+
+    async fn material_efficiency(&self) -> u32 {
+        self.0.material_efficiency
+    }
+
+    async fn time_efficiency(&self) -> u32 {
+        self.0.time_efficiency
+    }
+
+    /// Remaining runs of a copy, -1 for originals
+    async fn runs(&self) -> i32 {
+        self.0.runs
+    }
+
+    async fn item(&self, ctx: &Context<'_>) -> Result<Option<ItemNode>> {
+        ItemNode::load(ctx, self.0.type_id).await
+    }
+}
+
+struct ItemNode(ItemEntry);
+
+impl ItemNode {
+    async fn load(ctx: &Context<'_>, type_id: TypeId) -> Result<Option<Self>> {
+        let item = ctx
+            .data::<DataLoader<ItemLoader>>()?
+            .load_one(type_id)
+            .await?
+            .map(Self);
+        Ok(item)
+    }
+}
+
+#[Object(name = "Item")]
+impl ItemNode {
+    async fn type_id(&self) -> u32 {
+        *self.0.item_id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    async fn category_id(&self) -> u32 {
+        *self.0.category_id
+    }
+
+    async fn group_id(&self) -> u32 {
+        *self.0.group_id
+    }
+
+    /// Volume in m3
+    async fn volume(&self) -> f32 {
+        self.0.volume
+    }
+
+    async fn price(&self, ctx: &Context<'_>) -> Result<Option<MarketPriceNode>> {
+        MarketPriceNode::load(ctx, self.0.item_id).await
+    }
+
+    /// Undercut statistic of the last 24 hours
+    async fn undercut(&self, ctx: &Context<'_>) -> Result<Option<UndercutNode>> {
+        let undercut = ctx
+            .data::<MarketService>()?
+            .undercut(self.0.item_id)
+            .await?
+            .map(|x| UndercutNode {
+                best_price: x.best_price,
+                per_hour:   x.per_hour,
+                snapshots:  x.snapshots,
+            });
+        Ok(undercut)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "MarketPrice")]
+struct MarketPriceNode {
+    adjusted_price: f32,
+    average_price:  f32,
+}
+
+impl MarketPriceNode {
+    async fn load(ctx: &Context<'_>, type_id: TypeId) -> Result<Option<Self>> {
+        let price = ctx
+            .data::<DataLoader<PriceLoader>>()?
+            .load_one(type_id)
+            .await?
+            .map(|x| Self {
+                adjusted_price: x.adjusted_price,
+                average_price:  x.average_price,
+            });
+        Ok(price)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Undercut")]
+struct UndercutNode {
+    best_price: f32,
+    per_hour:   f32,
+    snapshots:  u32,
+}
+
+/// Loads all items that are requested while resolving a query at once
+struct ItemLoader(ConnectionPool);
+
+#[async_trait::async_trait]
+impl Loader<TypeId> for ItemLoader {
+    type Value = ItemEntry;
+    type Error = Arc<EveServerError>;
+
+    async fn load(&self, keys: &[TypeId]) -> std::result::Result<HashMap<TypeId, ItemEntry>, Self::Error> {
+        let mut con = self.0.acquire().await.map_err(|e| Arc::new(e.into()))?;
+        let entries = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, keys.to_vec())
+            .await
+            .map_err(|e| Arc::new(e.into()))?
+            .into_iter()
+            .zip(keys.iter().copied())
+            .filter_map(|(v, k)| v.map(|v| (k, v)))
+            .collect::<HashMap<_, _>>();
+        Ok(entries)
+    }
+}
+
+/// Loads all market prices that are requested while resolving a query at
+/// once
+struct PriceLoader(ConnectionPool);
+
+#[async_trait::async_trait]
+impl Loader<TypeId> for PriceLoader {
+    type Value = MarketPriceEntry;
+    type Error = Arc<EveServerError>;
+
+    async fn load(&self, keys: &[TypeId]) -> std::result::Result<HashMap<TypeId, MarketPriceEntry>, Self::Error> {
+        let mut con = self.0.acquire().await.map_err(|e| Arc::new(e.into()))?;
+        let entries = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, keys.to_vec())
+            .await
+            .map_err(|e| Arc::new(e.into()))?
+            .into_iter()
+            .zip(keys.iter().copied())
+            .filter_map(|(v, k)| v.map(|v| (k, v)))
+            .collect::<HashMap<_, _>>();
+        Ok(entries)
+    }
+}
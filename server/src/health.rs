@@ -0,0 +1,137 @@
+use cachem::v2::ConnectionPool;
+use caph_db_v2::CacheName;
+use caph_eve_data_wrapper::{EveDataWrapper, ItemId, SolarSystemId, TypeId};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Checks if the server can handle requests, used by deployments to gate
+/// traffic
+#[derive(Clone)]
+pub struct HealthService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+}
+
+impl HealthService {
+    /// Maximum age of the last successful ESI request in milliseconds, the
+    /// incursions are requested every couple of minutes
+    const MAX_ESI_AGE: u64 = 30 * 60 * 1_000;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+        }
+    }
+
+    /// Checks if the database and ESI are reachable and all required caches
+    /// are loaded.
+    ///
+    /// # Returns
+    ///
+    /// The result of every check, the server is only ready if all checks
+    /// pass
+    ///
+    pub async fn ready(&self) -> Readiness {
+        let mut caches = Vec::new();
+        let database = match self.pool.acquire().await {
+            Ok(mut con) => {
+                // caches that must contain entries before the server is
+                // ready, they are filled by the collector
+                let required = vec![
+                    ("blueprints",     con.keys::<_, TypeId>(CacheName::Blueprint).await.map(|x| x.len())),
+                    ("items",          con.keys::<_, TypeId>(CacheName::Item).await.map(|x| x.len())),
+                    ("market_prices",  con.keys::<_, TypeId>(CacheName::MarketPrice).await.map(|x| x.len())),
+                    ("names",          con.keys::<_, ItemId>(CacheName::Name).await.map(|x| x.len())),
+                    ("system_regions", con.keys::<_, SolarSystemId>(CacheName::SystemRegion).await.map(|x| x.len())),
+                ];
+
+                let mut reachable = true;
+                for (name, entries) in required {
+                    let entries = match entries {
+                        Ok(x)  => x as u64,
+                        Err(e) => {
+                            log::error!("Error reading cache {} {:?}", name, e);
+                            reachable = false;
+                            0
+                        }
+                    };
+                    caches.push(CacheStatus {
+                        name,
+                        entries,
+                        loaded: entries > 0,
+                    });
+                }
+                reachable
+            },
+            Err(e) => {
+                log::error!("Error acquiring database connection {:?}", e);
+                false
+            }
+        };
+
+        let last_esi_success = self.eve_data.last_esi_success();
+        let esi = Self::esi_reachable(last_esi_success, Self::now());
+
+        Readiness {
+            ready:      database && esi && caches.iter().all(|x| x.loaded),
+            database,
+            esi,
+            eve_online: self.eve_data.is_esi_online(),
+            last_esi_success,
+            caches,
+        }
+    }
+
+    fn esi_reachable(last_success: Option<u64>, now: u64) -> bool {
+        last_success
+            .map(|x| now.saturating_sub(x) <= Self::MAX_ESI_AGE)
+            .unwrap_or_default()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready:            bool,
+    /// A connection could be acquired and all caches could be read
+    pub database:         bool,
+    /// The last ESI request succeeded recently
+    pub esi:              bool,
+    /// False during the downtime, ready does not depend on it, because the
+    /// server can still answer from the database
+    pub eve_online:       bool,
+    /// Unix timestamp in milliseconds
+    pub last_esi_success: Option<u64>,
+    pub caches:           Vec<CacheStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStatus {
+    pub name:    &'static str,
+    pub entries: u64,
+    pub loaded:  bool,
+}
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+
+    #[test]
+    fn esi_age() {
+        let now = 100 * 60 * 1_000;
+        assert!(HealthService::esi_reachable(Some(now - 1_000), now));
+        assert!(!HealthService::esi_reachable(Some(0), now));
+        assert!(!HealthService::esi_reachable(None, now));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::ConfigService;
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::CacheName;
+use caph_eve_data_wrapper::{ConstellationId, EveDataWrapper, Incursion, ItemId, SolarSystemId};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Keeps track of all active incursions
+#[derive(Clone)]
+pub struct IncursionService {
+    pool:       ConnectionPool,
+    config:     ConfigService,
+    eve_data:   EveDataWrapper,
+    incursions: Arc<RwLock<Vec<Incursion>>>,
+}
+
+impl IncursionService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        config:   ConfigService,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            config,
+            eve_data,
+            incursions: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Periodically fetches all active incursions.
+    ///
+    /// This function is blocking
+    pub async fn poll(&self) {
+        loop {
+            self.eve_data.wait_for_esi().await;
+            match self.fetch().await {
+                Ok(x)  => *self.incursions.write().await = x,
+                Err(e) => log::error!("Error fetching incursions {:?}", e),
+            }
+
+            tokio::time::sleep(self.config.incursion_poll().await).await;
+        }
+    }
+
+    /// Gets all active incursions together with the names of the affected
+    /// constellation and systems
+    pub async fn incursions(&self) -> Result<Vec<IncursionInfo>, EveServerError> {
+        let incursions = self.incursions.read().await.clone();
+
+        let mut con = self.pool.acquire().await?;
+
+        let mut result = Vec::with_capacity(incursions.len());
+        for incursion in incursions {
+            let constellation = con
+                .get::<_, _, String>(CacheName::Name, ItemId(*incursion.constellation_id as u64))
+                .await?
+                .unwrap_or_default();
+
+            let ids = incursion
+                .infested_solar_systems
+                .iter()
+                .map(|x| ItemId(**x as u64))
+                .collect::<Vec<_>>();
+            let systems = con
+                .mget::<_, _, String>(CacheName::Name, ids)
+                .await?
+                .into_iter()
+                .zip(incursion.infested_solar_systems.iter())
+                .map(|(name, system_id)| IncursionSystem {
+                    system_id:  *system_id,
+                    name:       name.unwrap_or_default(),
+                    is_staging: *system_id == incursion.staging_solar_system_id,
+                })
+                .collect::<Vec<_>>();
+
+            result.push(IncursionInfo {
+                constellation_id:   incursion.constellation_id,
+                constellation_name: constellation,
+                has_boss:           incursion.has_boss,
+                influence:          incursion.influence,
+                state:              incursion.state,
+                systems,
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn fetch(&self) -> Result<Vec<Incursion>, EveServerError> {
+        self
+            .eve_data
+            .incursions()
+            .await?
+            .incursions()
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncursionInfo {
+    pub constellation_id:   ConstellationId,
+    pub constellation_name: String,
+    pub has_boss:           bool,
+    pub influence:          f32,
+    pub state:              String,
+    pub systems:            Vec<IncursionSystem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncursionSystem {
+    pub system_id:  SolarSystemId,
+    pub name:       String,
+    pub is_staging: bool,
+}
@@ -1,6 +1,6 @@
 use crate::{error::EveServerError, eve::EveAuthService};
 
-use caph_eve_data_wrapper::{EveDataWrapper, IndustryJob, TypeId};
+use caph_eve_data_wrapper::{EveDataWrapper, IndustryJob, SolarSystemId, TypeId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone)]
@@ -65,7 +65,7 @@ impl IndustryService {
             .corp_jobs(&token, user.corp_id)
             .await?
             .into_iter()
-            .filter(|x| x.installer_id == *user.user_id)
+            .filter(|x| x.installer_id == user.user_id)
             .collect::<Vec<_>>();
         jobs.extend(job);
         jobs.extend(jobs_corp);
@@ -79,11 +79,197 @@ impl IndustryService {
         )
         .map_err(Into::into)
     }
+
+    /// Bonuses of the manufacturing facility in the given system.
+    ///
+    /// Systems without a known facility are treated as NPC stations.
+    ///
+    /// # Params
+    ///
+    /// `sid`      -> Solar system of the facility
+    /// `security` -> Security status of the system, used for the rig bonus
+    ///
+    pub fn facility_model(
+        &self,
+        sid:      SolarSystemId,
+        security: f32,
+    ) -> Result<FacilityModel, EveServerError> {
+        let model = self
+            .stations()?
+            .into_iter()
+            .find(|x| x.id == sid)
+            .map(|x| FacilityModel {
+                kind:          FacilityKind::from(x.engineering.type_id),
+                material_rig:  RigTier::from(x.engineering.material_efficiency),
+                time_rig:      RigTier::from(x.engineering.time_efficiency),
+                security,
+                tax:           x.engineering.manufacturing,
+            })
+            .unwrap_or(FacilityModel {
+                kind:          FacilityKind::NpcStation,
+                material_rig:  RigTier::None,
+                time_rig:      RigTier::None,
+                security,
+                tax:           FacilityModel::NPC_TAX,
+            });
+        Ok(model)
+    }
+}
+
+/// Bonuses a manufacturing facility gives to jobs
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FacilityModel {
+    pub kind:         FacilityKind,
+    pub material_rig: RigTier,
+    pub time_rig:     RigTier,
+    /// Security status of the system, rigs are stronger in low and null sec
+    pub security:     f32,
+    /// Facility tax in percent
+    pub tax:          f32,
+}
+
+impl FacilityModel {
+    /// Facility tax of NPC stations in percent
+    pub const NPC_TAX: f32 = 10f32;
+
+    /// Reduction of the required materials, between 0.0 and 1.0
+    pub fn material_bonus(&self) -> f32 {
+        let structure = self.kind.material_bonus();
+        let rig = self.material_rig.material_bonus() * self.rig_multiplier();
+        1f32 - (1f32 - structure) * (1f32 - rig)
+    }
+
+    /// Reduction of the job time, between 0.0 and 1.0
+    pub fn time_bonus(&self) -> f32 {
+        let structure = self.kind.time_bonus();
+        let rig = self.time_rig.time_bonus() * self.rig_multiplier();
+        1f32 - (1f32 - structure) * (1f32 - rig)
+    }
+
+    /// Reduction of the job installation cost, between 0.0 and 1.0
+    pub fn job_cost_bonus(&self) -> f32 {
+        self.kind.job_cost_bonus()
+    }
+
+    /// Required quantity of a material after all bonuses, at least one unit
+    /// per run is always required
+    pub fn material_quantity(&self, quantity: u32, runs: u32) -> u32 {
+        self.material_quantity_me(quantity, runs, 0)
+    }
+
+    /// Same as [FacilityModel::material_quantity] but also applies the
+    /// material efficiency of the blueprint
+    pub fn material_quantity_me(&self, quantity: u32, runs: u32, me: u32) -> u32 {
+        let me = 1f32 - me.min(10) as f32 / 100f32;
+        let total = (runs * quantity) as f32 * me * (1f32 - self.material_bonus());
+        // Rounded to two decimals first, like the game does
+        let total = (total * 100f32).round() / 100f32;
+        (total.ceil() as u32).max(runs)
+    }
+
+    /// Rig bonuses scale with the security of the system
+    fn rig_multiplier(&self) -> f32 {
+        if self.security >= 0.45f32 {
+            1f32
+        } else if self.security > 0f32 {
+            1.9f32
+        } else {
+            2.1f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FacilityKind {
+    NpcStation,
+    Raitaru,
+    Azbel,
+    Sotiyo,
+    /// Citadels and refineries, no manufacturing bonus
+    Other,
+}
+
+impl FacilityKind {
+    fn material_bonus(&self) -> f32 {
+        match self {
+            Self::Raitaru | Self::Azbel | Self::Sotiyo => 0.01f32,
+            _                                          => 0f32,
+        }
+    }
+
+    fn time_bonus(&self) -> f32 {
+        match self {
+            Self::Raitaru => 0.15f32,
+            Self::Azbel   => 0.20f32,
+            Self::Sotiyo  => 0.30f32,
+            _             => 0f32,
+        }
+    }
+
+    fn job_cost_bonus(&self) -> f32 {
+        match self {
+            Self::Raitaru => 0.03f32,
+            Self::Azbel   => 0.04f32,
+            Self::Sotiyo  => 0.05f32,
+            _             => 0f32,
+        }
+    }
+}
+
+impl From<TypeId> for FacilityKind {
+    fn from(x: TypeId) -> Self {
+        match *x {
+            35825 => Self::Raitaru,
+            35826 => Self::Azbel,
+            35827 => Self::Sotiyo,
+            _     => Self::Other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RigTier {
+    None,
+    Tech1,
+    Tech2,
+}
+
+impl RigTier {
+    fn material_bonus(&self) -> f32 {
+        match self {
+            Self::None  => 0f32,
+            Self::Tech1 => 0.02f32,
+            Self::Tech2 => 0.024f32,
+        }
+    }
+
+    fn time_bonus(&self) -> f32 {
+        match self {
+            Self::None  => 0f32,
+            Self::Tech1 => 0.20f32,
+            Self::Tech2 => 0.24f32,
+        }
+    }
+}
+
+/// The station list contains the installed rig tier, 2 for tech 2 rigs and
+/// everything else for tech 1 rigs
+impl From<Option<f32>> for RigTier {
+    fn from(x: Option<f32>) -> Self {
+        match x {
+            Some(x) if x >= 2f32 => Self::Tech2,
+            Some(_)              => Self::Tech1,
+            None                 => Self::None,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Facility {
-    pub id:          u32,
+    /// Solar system of the facility
+    pub id:          SolarSystemId,
     pub name:        String,
     pub engineering: EngineeringInfo,
     pub refinery:    RefineryInfo
@@ -106,3 +292,44 @@ pub struct RefineryInfo {
     pub hybrid:      Option<f32>,
     pub type_id:     TypeId,
 }
+
+#[cfg(test)]
+mod facility_tests {
+    use super::*;
+
+    fn model(kind: FacilityKind, rig: RigTier, security: f32) -> FacilityModel {
+        FacilityModel {
+            kind,
+            material_rig: rig,
+            time_rig:     rig,
+            security,
+            tax:          0f32,
+        }
+    }
+
+    #[test]
+    fn npc_station_has_no_bonus() {
+        let model = model(FacilityKind::NpcStation, RigTier::None, 0.9f32);
+        assert_eq!(model.material_bonus(), 0f32);
+        assert_eq!(model.time_bonus(), 0f32);
+        assert_eq!(model.material_quantity(100, 10), 1000);
+    }
+
+    #[test]
+    fn raitaru_with_rigs() {
+        let high = model(FacilityKind::Raitaru, RigTier::Tech1, 0.5f32);
+        assert!((high.material_bonus() - 0.0298f32).abs() < 1e-6);
+        assert!((high.time_bonus() - 0.32f32).abs() < 1e-6);
+
+        let null = model(FacilityKind::Raitaru, RigTier::Tech1, -0.3f32);
+        assert!((null.material_bonus() - 0.05158f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn material_quantity() {
+        let model = model(FacilityKind::Raitaru, RigTier::None, 0.5f32);
+        assert_eq!(model.material_quantity(100, 10), 990);
+        // Never less than one unit per run
+        assert_eq!(model.material_quantity(1, 10), 10);
+    }
+}
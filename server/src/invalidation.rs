@@ -0,0 +1,102 @@
+use caph_db_v2::CacheName;
+use futures::future;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, Receiver, error::RecvError};
+
+/// Receives notifications from the database when a cache was modified and
+/// forwards them to all services that keep data in memory
+#[derive(Clone)]
+pub struct InvalidationService {
+    /// [None] if every cache should be considered as modified
+    tx: broadcast::Sender<Option<u8>>,
+}
+
+impl InvalidationService {
+    /// Number of notifications that are buffered for every subscriber
+    const CAPACITY:        usize = 1024;
+    /// Seconds until a lost connection to the database is reestablished
+    const RECONNECT_DELAY: u64   = 5;
+
+    /// Creates a new instance
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        Self {
+            tx,
+        }
+    }
+
+    /// Subscribes to all notifications
+    pub fn subscribe(&self) -> Receiver<Option<u8>> {
+        self.tx.subscribe()
+    }
+
+    /// Waits until one of the given caches was modified.
+    ///
+    /// If notifications were missed, it is assumed that the caches were
+    /// modified. Never returns if the sender is gone.
+    ///
+    pub async fn wait(rx: &mut Receiver<Option<u8>>, caches: Vec<CacheName>) {
+        let ids = caches
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<u8>>();
+
+        loop {
+            match Self::next(rx).await {
+                Some(x) if ids.contains(&x) => return,
+                Some(_)                     => continue,
+                None                        => return,
+            }
+        }
+    }
+
+    /// Waits for the next modified cache.
+    ///
+    /// Returns [None] if every cache should be considered as modified. Never
+    /// returns if the sender is gone.
+    ///
+    pub async fn next(rx: &mut Receiver<Option<u8>>) -> Option<u8> {
+        match rx.recv().await {
+            Ok(x)                     => x,
+            Err(RecvError::Lagged(_)) => None,
+            Err(RecvError::Closed)    => future::pending::<Option<u8>>().await,
+        }
+    }
+
+    /// Connects to the database and forwards every notification to the
+    /// subscribers, a lost connection is reestablished.
+    ///
+    /// This function is blocking
+    pub async fn listen(&self, addr: &str) {
+        loop {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => {
+                    // Notifications may have been missed while disconnected
+                    let _ = self.tx.send(None);
+
+                    let mut lines = BufReader::new(stream).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let message = match line.trim() {
+                            "*" => None,
+                            x   => match x.parse::<u8>() {
+                                Ok(x)  => Some(x),
+                                Err(_) => {
+                                    log::warn!("Invalid invalidation message {}", x);
+                                    continue;
+                                }
+                            }
+                        };
+                        // Fails if there is no subscriber
+                        let _ = self.tx.send(message);
+                    }
+                    log::warn!("Lost connection to the invalidation feed");
+                },
+                Err(e) => log::error!("Error connecting to the invalidation feed {:?}", e),
+            }
+
+            tokio::time::sleep(Duration::from_secs(Self::RECONNECT_DELAY)).await;
+        }
+    }
+}
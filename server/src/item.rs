@@ -1,7 +1,7 @@
 use crate::error::EveServerError;
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::{CacheName, ItemEntry};
+use caph_db_v2::{CacheName, ItemEntry, ReprocessEntry};
 use caph_eve_data_wrapper::TypeId;
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +11,12 @@ pub struct ItemService {
 }
 
 impl ItemService {
+    const CATEGORY_ASTEROID:       u32 = 25;
+    const GROUP_HARVESTABLE_CLOUD: u32 = 711;
+    const GROUP_ICE:               u32 = 465;
+    /// Ubiquitous, common, uncommon, rare and exceptional moon asteroids
+    const GROUP_MOON:              [u32; 5] = [1884, 1920, 1921, 1922, 1923];
+
     pub fn new(pool: ConnectionPool) -> Self {
         Self {
             pool,
@@ -46,11 +52,85 @@ impl ItemService {
             .map_err(Into::into)
     }
 
+    /// Resolves where a material comes from.
+    ///
+    /// Minerals, ice products and moon materials are sourced by reprocessing
+    /// asteroids and ice, including their compressed variants. Gas is
+    /// harvested directly, but compressed gas can be reprocessed back into
+    /// its uncompressed form, so those are returned as sources as well.
+    ///
+    /// # Params
+    ///
+    /// `tid` -> Material to resolve
+    ///
+    /// # Returns
+    ///
+    /// `None` if the item is not a raw material, otherwise the type of
+    /// material and all items that can be reprocessed into it
+    ///
     pub async fn meta(
         &self,
         tid: TypeId
     ) -> Result<Option<ItemMeta>, EveServerError> {
-        Ok(None)
+        let mut con = self
+            .pool
+            .acquire()
+            .await?;
+
+        let item = con
+            .get::<_, _, ItemEntry>(CacheName::Item, tid)
+            .await?
+            .ok_or(EveServerError::TypeNotFound)?;
+
+        let keys = con
+            .keys::<_, TypeId>(CacheName::Reprocess)
+            .await?;
+        let reprocess = con
+            .mget::<_, _, Vec<ReprocessEntry>>(CacheName::Reprocess, keys.clone())
+            .await?;
+        let sources = keys
+            .into_iter()
+            .zip(reprocess)
+            .filter_map(|(type_id, materials)| {
+                materials?
+                    .into_iter()
+                    .find(|x| x.material_id == tid)
+                    .map(|x| MaterialSource {
+                        type_id,
+                        quantity: x.quantity,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let source_ids = sources
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        let source_groups = con
+            .mget::<_, _, ItemEntry>(CacheName::Item, source_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (*x.category_id, *x.group_id))
+            .collect::<Vec<_>>();
+
+        let mtype = if *item.group_id == Self::GROUP_HARVESTABLE_CLOUD {
+            MaterialType::Gas
+        } else if source_groups.iter().any(|(_, g)| *g == Self::GROUP_ICE) {
+            MaterialType::Ice
+        } else if source_groups.iter().any(|(_, g)| Self::GROUP_MOON.contains(g)) {
+            MaterialType::Moon
+        } else if source_groups.iter().any(|(c, _)| *c == Self::CATEGORY_ASTEROID) {
+            MaterialType::Asteroid
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(ItemMeta {
+            type_id: tid,
+            mtype,
+            sources,
+        }))
     }
 }
 
@@ -68,8 +148,10 @@ pub struct MaterialSource {
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MaterialType {
     Asteroid,
+    Gas,
     Ice,
     Moon,
     PI,
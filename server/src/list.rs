@@ -0,0 +1,221 @@
+use crate::error::EveServerError;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// Query parameters that every list endpoint understands, in addition to
+/// the filters of the endpoint.
+///
+/// The entries are serialized before they are sorted and reduced, so the
+/// names of the fields are the same as in the response.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ListQuery {
+    /// Maximum number of entries, defaults to 100, at most 1000
+    pub limit:  Option<usize>,
+    /// Number of entries to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// Field to sort by, descending if it starts with `-`, for example
+    /// `-quantity`
+    pub sort:   Option<String>,
+    /// Comma separated fields that are returned, all if not set
+    pub fields: Option<String>,
+}
+
+impl ListQuery {
+    const DEFAULT_LIMIT: usize = 100;
+    const MAX_LIMIT:     usize = 1_000;
+
+    /// Sorts the entries and returns the requested page with the selected
+    /// fields
+    ///
+    /// # Params
+    ///
+    /// `entries` -> All entries of the endpoint, in their default order
+    ///
+    /// # Returns
+    ///
+    /// [EveServerError::UnknownField] if a field of `sort` or `fields` does
+    /// not exist
+    ///
+    pub fn apply<T: Serialize>(
+        &self,
+        entries: Vec<T>,
+    ) -> Result<ListPage, EveServerError> {
+        let mut entries = entries
+            .into_iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(sort) = self.sort.as_ref() {
+            let (field, descending) = match sort.strip_prefix('-') {
+                Some(x) => (x, true),
+                None    => (sort.as_str(), false),
+            };
+            Self::require_field(&entries, field)?;
+
+            // sort_by is stable, entries with the same value keep their
+            // default order
+            entries.sort_by(|a, b| {
+                let ordering = Self::compare(a.get(field), b.get(field));
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+
+        let fields = self
+            .fields
+            .as_ref()
+            .map(|x| {
+                x
+                    .split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for field in fields.iter() {
+            Self::require_field(&entries, field)?;
+        }
+
+        let limit = self
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let total = entries.len();
+        let entries = entries
+            .into_iter()
+            .skip(self.offset)
+            .take(limit)
+            .map(|x| match x {
+                Value::Object(x) if !fields.is_empty() => {
+                    Value::Object(
+                        x
+                            .into_iter()
+                            .filter(|(k, _)| fields.contains(&k.as_str()))
+                            .collect()
+                    )
+                },
+                x => x,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(ListPage {
+            entries,
+            total,
+            limit,
+            offset: self.offset,
+        })
+    }
+
+    /// Only the first entry is checked, all entries have the same type
+    fn require_field(entries: &[Value], field: &str) -> Result<(), EveServerError> {
+        match entries.first() {
+            Some(Value::Object(x)) if !x.contains_key(field) => {
+                Err(EveServerError::UnknownField(field.into()))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Numbers are compared by value, missing values and null are first
+    fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+        match (a, b) {
+            (Some(Value::Number(a)), Some(Value::Number(b))) => {
+                a.as_f64()
+                    .partial_cmp(&b.as_f64())
+                    .unwrap_or(Ordering::Equal)
+            },
+            (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+            (Some(Value::Bool(a)), Some(Value::Bool(b)))     => a.cmp(b),
+            (Some(Value::Null), Some(Value::Null))           => Ordering::Equal,
+            (None, Some(_)) | (Some(Value::Null), Some(_))   => Ordering::Less,
+            (Some(_), None) | (Some(_), Some(Value::Null))   => Ordering::Greater,
+            _                                                => Ordering::Equal,
+        }
+    }
+}
+
+/// Single page of a list endpoint
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ListPage {
+    pub entries: Vec<Value>,
+    /// Number of entries over all pages
+    pub total:   usize,
+    pub limit:   usize,
+    pub offset:  usize,
+}
+
+#[cfg(test)]
+mod list_tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Entry {
+        name:     &'static str,
+        quantity: u32,
+        location: Option<u64>,
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry { name: "Tritanium", quantity: 1000, location: Some(1) },
+            Entry { name: "Pyerite",   quantity: 500,  location: None },
+            Entry { name: "Mexallon",  quantity: 10,   location: Some(2) },
+        ]
+    }
+
+    #[test]
+    fn sort_and_page() {
+        let query = ListQuery {
+            limit:  Some(2),
+            offset: 1,
+            sort:   Some("-quantity".into()),
+            ..ListQuery::default()
+        };
+        let page = query.apply(entries()).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.entries.len(), 2);
+        assert_eq!(page.entries[0]["name"], "Pyerite");
+        assert_eq!(page.entries[1]["name"], "Mexallon");
+
+        let query = ListQuery {
+            sort: Some("location".into()),
+            ..ListQuery::default()
+        };
+        let page = query.apply(entries()).unwrap();
+        assert_eq!(page.entries[0]["name"], "Pyerite");
+    }
+
+    #[test]
+    fn fields() {
+        let query = ListQuery {
+            fields: Some("name, quantity".into()),
+            ..ListQuery::default()
+        };
+        let page = query.apply(entries()).unwrap();
+        assert_eq!(page.entries[0].as_object().map(|x| x.len()), Some(2));
+        assert!(page.entries[0].get("location").is_none());
+    }
+
+    #[test]
+    fn unknown_field() {
+        let query = ListQuery {
+            sort: Some("price".into()),
+            ..ListQuery::default()
+        };
+        assert!(query.apply(entries()).is_err());
+
+        let query = ListQuery {
+            fields: Some("name,price".into()),
+            ..ListQuery::default()
+        };
+        assert!(query.apply(entries()).is_err());
+        assert!(query.apply(Vec::<Entry>::new()).is_ok());
+    }
+}
@@ -0,0 +1,303 @@
+use crate::error::EveServerError;
+use crate::market::MarketService;
+
+use caph_eve_data_wrapper::{CorporationId, EveDataWrapper, LoyaltyOffer, TypeId};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Values the offers of NPC LP stores with the current market prices
+#[derive(Clone)]
+pub struct LpStoreService {
+    eve_data: EveDataWrapper,
+    market:   MarketService,
+    /// Offers of every corporation that was requested
+    offers:   Arc<RwLock<HashMap<CorporationId, (Instant, Vec<LoyaltyOffer>)>>>,
+}
+
+impl LpStoreService {
+    /// ESI caches the offers for an hour
+    const OFFER_CACHE_TIME: Duration = Duration::from_secs(60 * 60);
+    /// Jita IV - Moon 4 - Caldari Navy Assembly Plant
+    const JITA: u64 = 60003760;
+
+    /// Creates a new instance
+    pub fn new(
+        eve_data: EveDataWrapper,
+        market:   MarketService,
+    ) -> Self {
+        Self {
+            eve_data,
+            market,
+            offers: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Gets all npc corporations that have a LP store
+    pub async fn corporations(&self) -> Result<Vec<CorporationId>, EveServerError> {
+        let mut corporations = self
+            .eve_data
+            .corporations()
+            .await?
+            .npc_corporations_with_store();
+        corporations.sort();
+        Ok(corporations)
+    }
+
+    /// Calculates the ISK per LP of every offer of a corporation.
+    ///
+    /// The required items are bought from the cheapest Jita sell orders.
+    /// The reward is either sold with a sell order that matches the best
+    /// Jita sell price or directly into the best Jita buy order.
+    ///
+    /// # Params
+    ///
+    /// `cid` -> NPC corporation of the LP store
+    ///
+    /// # Returns
+    ///
+    /// All offers, the best ISK per LP first, `None` if the corporation has
+    /// no LP store
+    ///
+    pub async fn offers(
+        &self,
+        cid: CorporationId,
+    ) -> Result<Option<Vec<LpOffer>>, EveServerError> {
+        let has_store = self
+            .eve_data
+            .corporations()
+            .await?
+            .npc_corporation(cid)
+            .and_then(|x| x.lpoffer_tables.as_ref())
+            .map(|x| !x.is_empty())
+            .unwrap_or_default();
+        if !has_store {
+            return Ok(None);
+        }
+
+        let offers = self.store(cid).await?;
+
+        let mut type_ids = offers
+            .iter()
+            .flat_map(|x| {
+                x.required_items
+                    .iter()
+                    .map(|x| x.type_id)
+                    .chain(std::iter::once(x.type_id))
+            })
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+
+        let mut prices = HashMap::new();
+        for tid in type_ids {
+            prices.insert(tid, self.jita_price(tid).await?);
+        }
+
+        let mut result = offers
+            .into_iter()
+            .map(|x| Self::value(x, &prices))
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| {
+            b.isk_per_lp
+                .partial_cmp(&a.isk_per_lp)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(Some(result))
+    }
+
+    /// Gets the offers of the LP store, the offers are cached for an hour
+    async fn store(
+        &self,
+        cid: CorporationId,
+    ) -> Result<Vec<LoyaltyOffer>, EveServerError> {
+        if let Some((fetched, offers)) = self.offers.read().await.get(&cid) {
+            if fetched.elapsed() < Self::OFFER_CACHE_TIME {
+                return Ok(offers.clone());
+            }
+        }
+
+        let offers = self
+            .eve_data
+            .loyalty()
+            .await?
+            .offers(cid)
+            .await?;
+
+        self
+            .offers
+            .write()
+            .await
+            .insert(cid, (Instant::now(), offers.clone()));
+        Ok(offers)
+    }
+
+    /// Best sell and buy price of an item in Jita
+    async fn jita_price(
+        &self,
+        tid: TypeId,
+    ) -> Result<JitaPrice, EveServerError> {
+        let orders = self
+            .market
+            .latest_orders(tid)
+            .await?
+            .into_iter()
+            .filter(|(x, _)| *x.location_id == Self::JITA)
+            .collect::<Vec<_>>();
+
+        let sell = orders
+            .iter()
+            .filter(|(x, _)| !x.is_buy_order)
+            .map(|(x, _)| x.price)
+            .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.min(x))));
+        let buy = orders
+            .iter()
+            .filter(|(x, _)| x.is_buy_order)
+            .map(|(x, _)| x.price)
+            .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.max(x))));
+
+        Ok(JitaPrice {
+            sell,
+            buy,
+        })
+    }
+
+    fn value(
+        offer:  LoyaltyOffer,
+        prices: &HashMap<TypeId, JitaPrice>,
+    ) -> LpOffer {
+        let mut complete = true;
+        let required_items = offer
+            .required_items
+            .into_iter()
+            .map(|x| {
+                let price = prices.get(&x.type_id).and_then(|x| x.sell);
+                complete &= price.is_some();
+                LpRequiredItem {
+                    type_id:  x.type_id,
+                    quantity: x.quantity,
+                    cost:     price.unwrap_or_default() * x.quantity as f32,
+                }
+            })
+            .collect::<Vec<_>>();
+        let cost = offer.isk_cost as f32 +
+                   required_items.iter().map(|x| x.cost).sum::<f32>();
+
+        let price = prices.get(&offer.type_id);
+        let sell_value = price
+            .and_then(|x| x.sell)
+            .map(|x| x * offer.quantity as f32);
+        let buy_value = price
+            .and_then(|x| x.buy)
+            .map(|x| x * offer.quantity as f32);
+        complete &= sell_value.is_some();
+
+        let isk_per_lp = |value: Option<f32>| {
+            if offer.lp_cost == 0 {
+                return 0f32;
+            }
+            (value.unwrap_or_default() - cost) / offer.lp_cost as f32
+        };
+
+        LpOffer {
+            offer_id:           offer.offer_id,
+            type_id:            offer.type_id,
+            quantity:           offer.quantity,
+            lp_cost:            offer.lp_cost,
+            isk_cost:           offer.isk_cost,
+            required_items,
+            cost,
+            sell_value:         sell_value.unwrap_or_default(),
+            buy_value:          buy_value.unwrap_or_default(),
+            isk_per_lp:         isk_per_lp(sell_value),
+            isk_per_lp_instant: isk_per_lp(buy_value),
+            complete,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct JitaPrice {
+    sell: Option<f32>,
+    buy:  Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LpOffer {
+    pub offer_id:           u32,
+    pub type_id:            TypeId,
+    pub quantity:           u32,
+    pub lp_cost:            u32,
+    pub isk_cost:           u64,
+    pub required_items:     Vec<LpRequiredItem>,
+    /// ISK cost of the offer together with the cost of the required items
+    pub cost:               f32,
+    /// Value of the reward with the best Jita sell price
+    pub sell_value:         f32,
+    /// Value of the reward with the best Jita buy price
+    pub buy_value:          f32,
+    /// ISK per LP when selling with a sell order
+    pub isk_per_lp:         f32,
+    /// ISK per LP when selling into buy orders
+    pub isk_per_lp_instant: f32,
+    /// False if the reward or one of the required items is not sold in
+    /// Jita
+    pub complete:           bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LpRequiredItem {
+    pub type_id:  TypeId,
+    pub quantity: u32,
+    /// Cost with the best Jita sell price
+    pub cost:     f32,
+}
+
+#[cfg(test)]
+mod lp_store_tests {
+    use super::*;
+    use caph_eve_data_wrapper::LoyaltyRequiredItem;
+
+    #[test]
+    fn isk_per_lp() {
+        let offer = LoyaltyOffer {
+            offer_id:       1,
+            type_id:        1.into(),
+            quantity:       2,
+            lp_cost:        1_000,
+            isk_cost:       100_000,
+            required_items: vec![LoyaltyRequiredItem {
+                type_id:  2.into(),
+                quantity: 5,
+            }],
+            ak_cost:        None,
+        };
+        let mut prices = HashMap::new();
+        prices.insert(1.into(), JitaPrice { sell: Some(1_000_000f32), buy: Some(800_000f32) });
+        prices.insert(2.into(), JitaPrice { sell: Some(20_000f32), buy: None });
+
+        let offer = LpStoreService::value(offer, &prices);
+        assert!(offer.complete);
+        assert_eq!(offer.cost, 200_000f32);
+        assert_eq!(offer.isk_per_lp, 1_800f32);
+        assert_eq!(offer.isk_per_lp_instant, 1_400f32);
+    }
+
+    #[test]
+    fn missing_price() {
+        let offer = LoyaltyOffer {
+            offer_id:       1,
+            type_id:        1.into(),
+            quantity:       1,
+            lp_cost:        100,
+            isk_cost:       0,
+            required_items: Vec::new(),
+            ak_cost:        None,
+        };
+
+        let offer = LpStoreService::value(offer, &HashMap::new());
+        assert!(!offer.complete);
+    }
+}
@@ -2,66 +2,256 @@
 
 //! API-Server for the frontend
 
+mod admin;
+mod alliance;
+mod appraisal;
+mod asset;
+mod audit;
+mod auth;
 mod blueprint;
+mod build_plan;
+mod capital;
 mod character;
+mod compression;
+mod config;
+mod contract;
 mod corporation;
+mod courier;
+mod delete_user;
 mod error;
+mod esi_proxy;
 mod eve;
+mod event;
+mod export;
+mod fitting;
+mod graphql;
+mod health;
+mod incursion;
 mod industry;
+mod invalidation;
 mod item;
+mod list;
+mod lp_store;
+mod market;
+mod mining;
 mod name;
+mod notification;
+mod npc_price;
+mod openapi;
+mod preference;
+mod price_alert;
+mod pricing;
 mod project;
-
-use crate::blueprint::BlueprintService;
-use crate::character::CharacterService;
+mod public;
+mod reprocess;
+mod role;
+mod schedule;
+mod scope;
+mod skill_farm;
+mod stock;
+mod universe;
+mod webhook;
+mod workspace;
+
+use crate::admin::AdminService;
+use crate::alliance::AllianceService;
+use crate::appraisal::AppraisalService;
+use crate::asset::{AssetQuery, AssetService};
+use crate::audit::{AuditQuery, AuditService};
+use crate::blueprint::{BlueprintService, ReactionQuery};
+use crate::build_plan::{BuildPlanQuery, BuildPlanService};
+use crate::capital::{CapitalQuery, CapitalService};
+use crate::character::{CharacterService, ContractQuery, HaulingQuery, NetWorthQuery};
+use crate::compression::{CompressionRequest, CompressionService};
+use crate::config::ConfigService;
+use crate::contract::{ContractSearchQuery, ContractService, SnipeQuery};
 use crate::corporation::CorporationService;
+use crate::courier::{CourierQuery, CourierService, HaulingRequest};
+use crate::delete_user::DeleteUserService;
+use crate::error::EveServerError;
+use crate::esi_proxy::EsiProxyService;
+use crate::event::EventService;
+use crate::export::{ExportQuery, ExportService, MarketExportQuery};
+use crate::fitting::{Doctrine, FittingService};
+use crate::graphql::GraphQlService;
+use crate::health::HealthService;
+use crate::incursion::IncursionService;
 use crate::industry::IndustryService;
+use crate::invalidation::InvalidationService;
 use crate::item::ItemService;
+use crate::lp_store::LpStoreService;
+use crate::list::ListQuery;
+use crate::market::{MarketService, ShoppingMaterial, StructureFee, VenueQuery};
+use crate::mining::{MiningQuery, MiningService};
 use crate::name::NameService;
+use crate::notification::{NotificationService, Webhook};
+use crate::preference::PreferenceService;
+use crate::price_alert::{PriceAlert, PriceAlertService};
+use crate::pricing::{PriceQuery, PriceService};
 use crate::project::ProjectService;
+use crate::public::PublicService;
+use crate::reprocess::{ReprocessQuery, ReprocessService};
+use crate::role::{Role, RoleService};
+use crate::schedule::{ScheduleRequest, ScheduleService};
+use crate::scope::ScopeService;
+use crate::skill_farm::SkillFarmService;
+use crate::stock::{StockQuery, StockRule, StockService};
+use crate::universe::{JumpRangeQuery, NearestAgentQuery, RouteKillsQuery, RouteQuery, SovereigntyQuery, UniverseService};
+use crate::workspace::{WorkspaceRequest, WorkspaceService};
 
 use self::eve::*;
 
 use cachem::v2::ConnectionPool;
-use caph_db_v2::CorporationBlueprintEntry;
-use caph_eve_data_wrapper::{CorporationId, EveDataWrapper, TypeId};
+use caph_db_v2::{CorporationBlueprintEntry, UserPreferenceEntry};
+use caph_eve_data_wrapper::{AllianceId, CharacterId, CorporationId, EveClient, EveDataWrapper, FittingId, ItemId, SolarSystemId, StructureId, TypeId};
 use project::ProjectNew;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use uuid::Uuid;
 use warp::http::Response;
 use warp::hyper::StatusCode;
+use warp::ws::Ws;
 use warp::{Filter, Rejection, Reply};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     morgan::Morgan::init(vec!["tracing".into()]);
 
-    let pool     = ConnectionPool::new("0.0.0.0:55555", 100).await?;
-    let eve_data = EveDataWrapper::new().await?;
-
-    let eve_auth  = EveAuthService::new(pool.clone());
-    let industry  = IndustryService::new(eve_auth.clone(), eve_data.clone());
-
-    let blueprint   = BlueprintService::new(pool.clone(), eve_auth.clone(), industry.clone());
-    let character   = CharacterService::new(pool.clone(), eve_auth.clone(), eve_data.clone());
-    let corporation = CorporationService::new(pool.clone(), eve_auth.clone());
-    let item        = ItemService::new(pool.clone());
-    let name        = NameService::new(pool.clone());
-    let project     = ProjectService::new(pool.clone(), blueprint.clone(), character.clone(), eve_auth.clone());
+    let config   = ConfigService::load()?;
+    let pool     = ConnectionPool::new(&config.config().db.address, config.config().db.pool_size).await?;
+    let eve_data = match config.config().esi.api_url.as_ref() {
+        Some(x) => EveDataWrapper::with_client(EveClient::with_url(x)?).await?,
+        None    => EveDataWrapper::new().await?,
+    };
+
+    let audit        = AuditService::new(pool.clone());
+    let eve_auth     = EveAuthService::new(pool.clone(), audit.clone(), config.config().sso.clone());
+    let industry     = IndustryService::new(eve_auth.clone(), eve_data.clone());
+    let invalidation = InvalidationService::new();
+    let market       = MarketService::new(pool.clone(), eve_auth.clone());
+    let price        = PriceService::new(pool.clone(), market.clone());
+    let asset        = AssetService::new(pool.clone(), eve_data.clone(), price.clone());
+
+    let alliance     = AllianceService::new(pool.clone(), eve_auth.clone(), eve_data.clone(), invalidation.clone());
+    let appraisal    = AppraisalService::new(pool.clone(), eve_data.clone(), price.clone());
+    let blueprint    = BlueprintService::new(pool.clone(), eve_auth.clone(), industry.clone());
+    let build_plan   = BuildPlanService::new(pool.clone(), eve_auth.clone(), industry.clone());
+    let capital      = CapitalService::new(pool.clone());
+    let corporation  = CorporationService::new(pool.clone(), asset.clone(), eve_auth.clone(), eve_data.clone());
+    let workspace    = WorkspaceService::new(pool.clone(), eve_auth.clone(), corporation.clone());
+    let character    = CharacterService::new(pool.clone(), asset.clone(), eve_auth.clone(), eve_data.clone(), price.clone(), workspace.clone());
+    let delete_user  = DeleteUserService::new(pool.clone(), audit.clone(), eve_auth.clone());
+    let admin        = AdminService::new(pool.clone(), eve_auth.clone(), character.clone(), delete_user.clone());
+    let compression  = CompressionService::new(pool.clone(), eve_data.clone());
+    let contract     = ContractService::new(pool.clone(), invalidation.clone());
+    let fitting      = FittingService::new(pool.clone(), eve_auth.clone());
+    let item         = ItemService::new(pool.clone());
+    let lp_store     = LpStoreService::new(eve_data.clone(), market.clone());
+    let price_alert  = PriceAlertService::new(pool.clone(), eve_auth.clone(), invalidation.clone(), market.clone());
+    let event        = EventService::new(pool.clone(), eve_auth.clone(), industry.clone(), invalidation.clone(), price_alert.clone());
+    let graphql      = GraphQlService::new(pool.clone(), eve_auth.clone(), market.clone());
+    let health       = HealthService::new(pool.clone(), eve_data.clone());
+    let export       = ExportService::new(pool.clone(), audit.clone(), character.clone(), eve_auth.clone(), market.clone());
+    let mining       = MiningService::new(pool.clone(), eve_auth.clone());
+    let name         = NameService::new(pool.clone(), eve_data.clone());
+    let notification = NotificationService::new(pool.clone(), eve_auth.clone());
+    let preference   = PreferenceService::new(pool.clone(), eve_auth.clone());
+    let project      = ProjectService::new(pool.clone(), blueprint.clone(), character.clone(), eve_auth.clone(), market.clone());
+    let public       = PublicService::new(pool.clone(), config.clone());
+    let reprocess    = ReprocessService::new(pool.clone(), eve_auth.clone(), eve_data.clone(), industry.clone());
+    let role         = RoleService::new(pool.clone(), audit.clone(), config.clone(), eve_auth.clone());
+    let schedule     = ScheduleService::new(pool.clone(), eve_auth.clone(), industry.clone());
+    let scope        = ScopeService::new(eve_auth.clone());
+    let skill_farm   = SkillFarmService::new(pool.clone(), eve_auth.clone(), eve_data.clone());
+    let stock        = StockService::new(pool.clone(), eve_auth.clone(), price.clone(), workspace.clone());
+    let universe     = UniverseService::new(pool.clone(), eve_data.clone());
+    let courier      = CourierService::new(pool.clone(), eve_data.clone(), universe.clone());
+    let incursion    = IncursionService::new(pool.clone(), config.clone(), eve_data.clone());
+    let esi_proxy    = EsiProxyService::new(pool.clone(), eve_data.clone());
+
+    let incursion_copy = incursion.clone();
+    tokio::spawn(async move {
+        incursion_copy.poll().await;
+    });
+
+    let invalidation_address = config.config().db.invalidation_address.clone();
+    tokio::spawn(async move {
+        invalidation.listen(&invalidation_address).await;
+    });
+
+    let config_copy = config.clone();
+    tokio::spawn(async move {
+        config_copy.watch().await;
+    });
+
+    let alliance_copy = alliance.clone();
+    tokio::spawn(async move {
+        alliance_copy.watch().await;
+    });
+
+    let price_alert_copy = price_alert.clone();
+    tokio::spawn(async move {
+        price_alert_copy.watch().await;
+    });
+
+    let eve_data_copy = eve_data.clone();
+    tokio::spawn(async move {
+        eve_data_copy.poll_esi_status().await;
+    });
+
+    if config.config().features.esi_proxy {
+        let esi_proxy_copy = esi_proxy.clone();
+        tokio::spawn(async move {
+            esi_proxy_copy.watch().await;
+        });
+    }
 
     log::info!("Starting server");
 
     ApiServer::new(
+        config,
         eve_auth,
 
+        admin,
+        alliance,
+        appraisal,
+        audit,
         blueprint,
+        build_plan,
+        capital,
         character,
+        compression,
+        contract,
         corporation,
+        courier,
+        delete_user,
+        esi_proxy,
+        event,
+        export,
+        fitting,
+        graphql,
+        health,
+        incursion,
         industry,
         item,
+        lp_store,
+        market,
+        mining,
         name,
+        notification,
+        preference,
+        price_alert,
         project,
+        public,
+        reprocess,
+        role,
+        schedule,
+        scope,
+        skill_farm,
+        stock,
+        universe,
+        workspace,
     )
     .serve()
     .await;
@@ -72,40 +262,139 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// Contains all services and handles routing
 #[derive(Clone)]
 pub struct ApiServer {
+    config:    ConfigService,
     eve_auth:  EveAuthService,
 
-    blueprint:   BlueprintService,
-    character:   CharacterService,
-    corporation: CorporationService,
-    industry:    IndustryService,
-    item:        ItemService,
-    name:        NameService,
-    project:     ProjectService,
+    admin:        AdminService,
+    alliance:     AllianceService,
+    appraisal:    AppraisalService,
+    audit:        AuditService,
+    blueprint:    BlueprintService,
+    build_plan:   BuildPlanService,
+    capital:      CapitalService,
+    character:    CharacterService,
+    compression:  CompressionService,
+    contract:     ContractService,
+    corporation:  CorporationService,
+    courier:      CourierService,
+    delete_user:  DeleteUserService,
+    esi_proxy:    EsiProxyService,
+    event:        EventService,
+    export:       ExportService,
+    fitting:      FittingService,
+    graphql:      GraphQlService,
+    health:       HealthService,
+    incursion:    IncursionService,
+    industry:     IndustryService,
+    item:         ItemService,
+    lp_store:     LpStoreService,
+    market:       MarketService,
+    mining:       MiningService,
+    name:         NameService,
+    notification: NotificationService,
+    preference:   PreferenceService,
+    price_alert:  PriceAlertService,
+    project:      ProjectService,
+    public:       PublicService,
+    reprocess:    ReprocessService,
+    role:         RoleService,
+    schedule:     ScheduleService,
+    scope:        ScopeService,
+    skill_farm:   SkillFarmService,
+    stock:        StockService,
+    universe:     UniverseService,
+    workspace:    WorkspaceService,
 }
 
 impl ApiServer {
     /// Creates a new api server instance
     pub fn new(
+        config:    ConfigService,
         eve_auth:  EveAuthService,
 
-        blueprint:   BlueprintService,
-        character:   CharacterService,
-        corporation: CorporationService,
-        industry:    IndustryService,
-        item:        ItemService,
-        name:        NameService,
-        project:     ProjectService,
+        admin:        AdminService,
+        alliance:     AllianceService,
+        appraisal:    AppraisalService,
+        audit:        AuditService,
+        blueprint:    BlueprintService,
+        build_plan:   BuildPlanService,
+        capital:      CapitalService,
+        character:    CharacterService,
+        compression:  CompressionService,
+        contract:     ContractService,
+        corporation:  CorporationService,
+        courier:      CourierService,
+        delete_user:  DeleteUserService,
+        esi_proxy:    EsiProxyService,
+        event:        EventService,
+        export:       ExportService,
+        fitting:      FittingService,
+        graphql:      GraphQlService,
+        health:       HealthService,
+        incursion:    IncursionService,
+        industry:     IndustryService,
+        item:         ItemService,
+        lp_store:     LpStoreService,
+        market:       MarketService,
+        mining:       MiningService,
+        name:         NameService,
+        notification: NotificationService,
+        preference:   PreferenceService,
+        price_alert:  PriceAlertService,
+        project:      ProjectService,
+        public:       PublicService,
+        reprocess:    ReprocessService,
+        role:         RoleService,
+        schedule:     ScheduleService,
+        scope:        ScopeService,
+        skill_farm:   SkillFarmService,
+        stock:        StockService,
+        universe:     UniverseService,
+        workspace:    WorkspaceService,
     ) -> Self {
         Self {
+            config,
             eve_auth,
 
+            admin,
+            alliance,
+            appraisal,
+            audit,
             blueprint,
+            build_plan,
+            capital,
             character,
+            compression,
+            contract,
             corporation,
+            courier,
+            delete_user,
+            esi_proxy,
+            event,
+            export,
+            fitting,
+            graphql,
+            health,
+            incursion,
             industry,
             item,
+            lp_store,
+            market,
+            mining,
             name,
+            notification,
+            preference,
+            price_alert,
             project,
+            public,
+            reprocess,
+            role,
+            schedule,
+            scope,
+            skill_farm,
+            stock,
+            universe,
+            workspace,
         }
     }
 
@@ -113,7 +402,12 @@ impl ApiServer {
     ///
     /// This function is blocking
     pub async fn serve(&self) {
+        let features = self.config.config().features.clone();
+        let address = self.config.config().server.address;
+
         let _self = Arc::new(self.clone());
+        let health_self = _self.clone();
+        let esi_self = _self.clone();
         let log = warp::log::custom(|info| {
             log::info!(
                 "{} {} {} {}ms",
@@ -124,10 +418,123 @@ impl ApiServer {
             );
         });
 
-        let root = warp::any()
-            .map(move || _self.clone())
+        let server = warp::any()
+            .map(move || _self.clone());
+        let root = server
+            .clone()
             .and(warp::path!("api" / ..));
 
+        // not part of the api, used by deployments
+        let health = warp::any()
+            .map(move || health_self.clone())
+            .and(warp::path!("health" / ..));
+        let health_live = health
+            .clone()
+            .and(warp::path!("live"))
+            .and(warp::get())
+            .and_then(Self::health_live);
+        let health_ready = health
+            .clone()
+            .and(warp::path!("ready"))
+            .and(warp::get())
+            .and_then(Self::health_ready);
+        let health = health_live
+            .or(health_ready);
+
+        // not part of the api, passes public ESI routes through
+        let esi_proxy = warp::any()
+            .map(move || esi_self.clone())
+            .and(warp::path!("esi" / ..))
+            .and(Self::with_feature(features.esi_proxy))
+            .and(warp::path::tail())
+            .and(warp::get())
+            .and(
+                warp::query::raw()
+                    .or(warp::any().map(String::new))
+                    .unify()
+            )
+            .and_then(Self::esi_proxy);
+
+        let admin = root
+            .clone()
+            .and(warp::path!("admin" / ..));
+        let admin_roles = server
+            .clone()
+            .and(warp::path!("roles"))
+            .and(warp::get())
+            .and_then(Self::admin_roles);
+        let admin_set_roles = server
+            .clone()
+            .and(warp::path!("roles" / CharacterId))
+            .and(warp::put())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::admin_set_roles);
+        let admin_users = server
+            .clone()
+            .and(warp::path!("users"))
+            .and(warp::get())
+            .and_then(Self::admin_users);
+        let admin_delete_user = server
+            .clone()
+            .and(warp::path!("users" / CharacterId))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::admin_delete_user);
+        let admin_user_tokens = server
+            .clone()
+            .and(warp::path!("users" / CharacterId / "tokens"))
+            .and(warp::get())
+            .and_then(Self::admin_user_tokens);
+        let admin_refresh_token = server
+            .clone()
+            .and(warp::path!("users" / CharacterId / "tokens" / "refresh"))
+            .and(warp::post())
+            .and(Self::token())
+            .and_then(Self::admin_refresh_token);
+        let admin_user_sync = server
+            .clone()
+            .and(warp::path!("users" / CharacterId / "sync"))
+            .and(warp::get())
+            .and_then(Self::admin_user_sync);
+        let admin_audit = server
+            .clone()
+            .and(warp::path!("audit"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::admin_audit);
+        let admin = Self::with_role(admin, Role::Admin)
+            .and(
+                admin_roles
+                    .or(admin_set_roles)
+                    .or(admin_users)
+                    .or(admin_delete_user)
+                    .or(admin_user_tokens)
+                    .or(admin_refresh_token)
+                    .or(admin_user_sync)
+                    .or(admin_audit)
+            );
+
+        let alliance = root
+            .clone()
+            .and(warp::path!("alliance" / ..));
+        let alliance_report = server
+            .clone()
+            .and(warp::path!(AllianceId))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::alliance_report);
+        let alliance = Self::with_role(alliance, Role::CorpManager)
+            .and(alliance_report);
+
+        let appraisal = root
+            .clone()
+            .and(warp::path!("appraisal"))
+            .and(warp::post())
+            .and(warp::query())
+            .and(warp::body::json())
+            .and_then(Self::appraisal);
+
         let blueprint = root
             .clone()
             .and(warp::path!("blueprint" / ..));
@@ -141,8 +548,42 @@ impl ApiServer {
             .and(warp::path!(TypeId))
             .and(warp::get())
             .and_then(Self::blueprint_get);
+        let blueprint_history = blueprint
+            .clone()
+            .and(warp::path!(TypeId / "history"))
+            .and(warp::get())
+            .and_then(Self::blueprint_history);
+        let blueprint_capital = blueprint
+            .clone()
+            .and(warp::path!(TypeId / "capital"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::blueprint_capital);
+        let blueprint_plan = blueprint
+            .clone()
+            .and(warp::path!(TypeId / "plan"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::blueprint_plan);
+        let blueprint_reactions = blueprint
+            .clone()
+            .and(warp::path!("reactions"))
+            .and(warp::get())
+            .and_then(Self::blueprint_reactions);
+        let blueprint_reaction_chain = blueprint
+            .clone()
+            .and(warp::path!("reactions" / TypeId))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::blueprint_reaction_chain);
         let blueprint = blueprint_all
-            .or(blueprint_by_id);
+            .or(blueprint_by_id)
+            .or(blueprint_history)
+            .or(blueprint_capital)
+            .or(blueprint_plan)
+            .or(blueprint_reactions)
+            .or(blueprint_reaction_chain);
 
         let character = root
             .clone()
@@ -151,56 +592,314 @@ impl ApiServer {
             .clone()
             .and(warp::path!("assets"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(warp::query())
+            .and(Self::list())
+            .and(Self::token())
             .and_then(Self::character_assets);
+        let character_assets_cost = character
+            .clone()
+            .and(warp::path!("assets" / "cost"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_assets_cost);
+        let character_assets_reprocess = character
+            .clone()
+            .and(warp::path!("assets" / "reprocess"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::character_assets_reprocess);
+        let character_assets_worth = character
+            .clone()
+            .and(warp::path!("assets" / "worth"))
+            .and(warp::get())
+            .and(warp::query())
+            .and(Self::token())
+            .and_then(Self::character_assets_worth);
+        let character_assets_volume = character
+            .clone()
+            .and(warp::path!("assets" / "volume"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::character_assets_volume);
         let character_blueprints = character
             .clone()
             .and(warp::path!("blueprints"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::list())
+            .and(Self::token())
             .and_then(Self::character_blueprints);
+        let character_blueprint_stacks = character
+            .clone()
+            .and(warp::path!("blueprints" / "stacks"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_blueprint_stacks);
+        let character_blueprint_report = character
+            .clone()
+            .and(warp::path!("blueprints" / "report"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_blueprint_report);
+        let character_clones = character
+            .clone()
+            .and(warp::path!("clones"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_clones);
+        let character_contracts = character
+            .clone()
+            .and(warp::path!("contracts"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and(Self::list())
+            .and_then(Self::character_contracts);
+        let character_mining = character
+            .clone()
+            .and(warp::path!("mining"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::character_mining);
+        let character_notifications = character
+            .clone()
+            .and(warp::path!("notifications"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_notifications);
+        let character_notifications_read = character
+            .clone()
+            .and(warp::path!("notifications" / "read"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::character_notifications_read);
+        let character_notifications_webhook = character
+            .clone()
+            .and(warp::path!("notifications" / "webhook"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_notifications_webhook);
+        let character_set_notifications_webhook = character
+            .clone()
+            .and(warp::path!("notifications" / "webhook"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::character_set_notifications_webhook);
+        let character_calendar = character
+            .clone()
+            .and(warp::path!("calendar"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_calendar);
+        let character_preferences = character
+            .clone()
+            .and(warp::path!("preferences"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_preferences);
+        let character_set_preferences = character
+            .clone()
+            .and(warp::path!("preferences"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::character_set_preferences);
+        let character_planets = character
+            .clone()
+            .and(warp::path!("planets"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_planets);
+        let character_skill_farm = character
+            .clone()
+            .and(warp::path!("skillfarm"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_skill_farm);
+        let character_skill_farm_omega = character
+            .clone()
+            .and(warp::path!("skillfarm" / CharacterId / "omega"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::character_skill_farm_omega);
+        let character_net_worth = character
+            .clone()
+            .and(warp::path!("networth"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::character_net_worth);
+        let character_skill_requirements = character
+            .clone()
+            .and(warp::path!("skills" / "requirements" / TypeId))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_skill_requirements);
+        let character_skill_history = character
+            .clone()
+            .and(warp::path!("skills" / "history"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_skill_history);
+        let character_sync = character
+            .clone()
+            .and(warp::path!("sync"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::character_sync);
         let character_info = character
             .clone()
             .and(warp::path!("info"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::character_info);
         let character_item_location = character
             .clone()
             .and(warp::path!("location" / u64))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::character_item_location);
         let character = character_assets
+            .or(character_assets_cost)
+            .or(character_assets_reprocess)
+            .or(character_assets_worth)
+            .or(character_assets_volume)
             .or(character_blueprints)
+            .or(character_blueprint_stacks)
+            .or(character_blueprint_report)
+            .or(character_calendar)
+            .or(character_clones)
+            .or(character_contracts)
             .or(character_info)
+            .or(character_mining)
+            .or(character_notifications)
+            .or(character_notifications_read)
+            .or(character_notifications_webhook)
+            .or(character_set_notifications_webhook)
+            .or(character_planets)
+            .or(character_preferences)
+            .or(character_set_preferences)
+            .or(character_skill_farm)
+            .or(character_skill_farm_omega)
+            .or(character_net_worth)
+            .or(character_skill_history)
+            .or(character_skill_requirements)
+            .or(character_sync)
             .or(character_item_location);
 
+        let compression = root
+            .clone()
+            .and(warp::path!("compression" / ..));
+        let compression_ores = compression
+            .clone()
+            .and(warp::path!("ores"))
+            .and(warp::get())
+            .and_then(Self::compression_ores);
+        let compression_plan = compression
+            .clone()
+            .and(warp::path!("plan"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(Self::compression_plan);
+        let compression = compression_ores
+            .or(compression_plan);
+
+        let contract = root
+            .clone()
+            .and(warp::path!("contracts" / ..));
+        let contract_search = contract
+            .clone()
+            .and(warp::path!("search"))
+            .and(warp::get())
+            .and(warp::query())
+            .and(Self::list())
+            .and_then(Self::contract_search);
+        let contract_snipes = contract
+            .clone()
+            .and(warp::path!("snipes"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::contract_snipes);
+        let contract_snipes_ws = contract
+            .clone()
+            .and(warp::path!("snipes" / "ws"))
+            .and(Self::with_feature(features.websocket))
+            .and(warp::ws())
+            .and(warp::query())
+            .map(Self::contract_snipes_ws);
+        let contract = contract_search
+            .or(contract_snipes)
+            .or(contract_snipes_ws);
+
         let corporation = root
             .clone()
             .and(warp::path!("corporation" / ..));
-        let corporation_blueprints = corporation
+        let corporation_assets = server
+            .clone()
+            .and(warp::path!(CorporationId / "assets"))
+            .and(warp::get())
+            .and(warp::query())
+            .and(Self::list())
+            .and(Self::token())
+            .and_then(Self::corporation_assets);
+        let corporation_blueprints = server
             .clone()
             .and(warp::path!(CorporationId / "blueprints"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::list())
+            .and(Self::token())
             .and_then(Self::corporation_blueprints);
-        let corporation_set_blueprints = corporation
+        let corporation_set_blueprints = server
             .clone()
             .and(warp::path!(CorporationId / "blueprints"))
             .and(warp::post())
             .and(warp::body::json())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::corporation_set_blueprints);
-        let corporation_del_blueprints = corporation
+        let corporation_del_blueprints = server
             .clone()
             .and(warp::path!(CorporationId / "blueprints"))
             .and(warp::delete())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::corporation_delete_blueprints);
-        let corporation = corporation_blueprints
-            .or(corporation_set_blueprints)
-            .or(corporation_del_blueprints);
+        let corporation_mining = server
+            .clone()
+            .and(warp::path!(CorporationId / "mining"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::corporation_mining);
+        let corporation = Self::with_role(corporation, Role::CorpManager)
+            .and(
+                corporation_assets
+                    .or(corporation_blueprints)
+                    .or(corporation_set_blueprints)
+                    .or(corporation_del_blueprints)
+                    .or(corporation_mining)
+            );
+
+        let courier = root
+            .clone()
+            .and(warp::path!("courier" / ..));
+        let courier_price = courier
+            .clone()
+            .and(warp::path!("price" / SolarSystemId / SolarSystemId))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::courier_price);
+        let courier_plan = courier
+            .clone()
+            .and(warp::path!("plan" / SolarSystemId / SolarSystemId))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(Self::courier_plan);
+        let courier = courier_price
+            .or(courier_plan);
 
         let eve = root
             .clone()
@@ -215,23 +914,174 @@ impl ApiServer {
             .clone()
             .and(warp::path!("login"))
             .and(warp::get())
+            .and(warp::query())
             .and_then(Self::eve_login);
         let eve_login_alt = eve
             .clone()
             .and(warp::path!("login" / "alt"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(warp::query())
+            .and(Self::token())
             .and_then(Self::eve_login_alt);
         let eve_whoami = eve
             .clone()
             .and(warp::path!("whoami"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::eve_whoami);
+        let eve_roles = eve
+            .clone()
+            .and(warp::path!("roles"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::eve_roles);
+        let eve_features = eve
+            .clone()
+            .and(warp::path!("features"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::eve_features);
+        let eve_logout = eve
+            .clone()
+            .and(warp::path!("logout"))
+            .and(warp::post())
+            .and(Self::token())
+            .and_then(Self::eve_logout);
+        let eve_sessions = eve
+            .clone()
+            .and(warp::path!("sessions"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::eve_sessions);
+        let eve_create_token = eve
+            .clone()
+            .and(warp::path!("sessions"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::eve_create_token);
+        let eve_revoke_session = eve
+            .clone()
+            .and(warp::path!("sessions" / String))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::eve_revoke_session);
+        let eve_deauthorize = eve
+            .clone()
+            .and(warp::path!("deauthorize" / CharacterId))
+            .and(warp::post())
+            .and(Self::token())
+            .and_then(Self::eve_deauthorize);
+        let eve_delete_account = eve
+            .clone()
+            .and(warp::path!("account"))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::eve_delete_account);
         let eve = eve_auth
             .or(eve_login)
             .or(eve_login_alt)
-            .or(eve_whoami);
+            .or(eve_whoami)
+            .or(eve_roles)
+            .or(eve_features)
+            .or(eve_logout)
+            .or(eve_sessions)
+            .or(eve_create_token)
+            .or(eve_revoke_session)
+            .or(eve_deauthorize)
+            .or(eve_delete_account);
+
+        let event = root
+            .clone()
+            .and(warp::path!("events"))
+            .and(Self::with_feature(features.websocket))
+            .and(warp::ws())
+            .and(Self::token())
+            .map(Self::events);
+
+        let export = root
+            .clone()
+            .and(warp::path!("export" / ..));
+        let export_assets = export
+            .clone()
+            .and(warp::path!("assets"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::export_assets);
+        let export_blueprints = export
+            .clone()
+            .and(warp::path!("blueprints"))
+            .and(warp::get())
+            .and(Self::token())
+            .and(warp::query())
+            .and_then(Self::export_blueprints);
+        let export_market_venues = export
+            .clone()
+            .and(warp::path!("market" / TypeId / "venues"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::export_market_venues);
+        let export = export_assets
+            .or(export_blueprints)
+            .or(export_market_venues);
+
+        let fitting = root
+            .clone()
+            .and(warp::path!("fittings" / ..));
+        let fittings = fitting
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::fittings);
+        let fitting_import = fitting
+            .clone()
+            .and(warp::path!("eft"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::fitting_import);
+        let fitting_export = fitting
+            .clone()
+            .and(warp::path!(Uuid / "eft"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::fitting_export);
+        let fitting_export_esi = fitting
+            .clone()
+            .and(warp::path!("esi" / FittingId / "eft"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::fitting_export_esi);
+        let fitting_doctrine = fitting
+            .clone()
+            .and(warp::path!("doctrine"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::fitting_doctrine);
+        let fitting_delete = fitting
+            .clone()
+            .and(warp::path!(Uuid))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::fitting_delete);
+        let fitting = fittings
+            .or(fitting_import)
+            .or(fitting_export)
+            .or(fitting_export_esi)
+            .or(fitting_doctrine)
+            .or(fitting_delete);
+
+        let graphql = root
+            .clone()
+            .and(warp::path!("graphql"))
+            .and(Self::with_feature(features.graphql))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::graphql);
 
         let item = root
             .clone()
@@ -256,6 +1106,32 @@ impl ApiServer {
             .or(item_keys)
             .or(item_meta);
 
+        let lp_store = root
+            .clone()
+            .and(warp::path!("loyalty" / "stores" / ..));
+        let lp_store_corporations = lp_store
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and_then(Self::lp_store_corporations);
+        let lp_store_offers = lp_store
+            .clone()
+            .and(warp::path!(CorporationId))
+            .and(warp::get())
+            .and_then(Self::lp_store_offers);
+        let lp_store = lp_store_corporations
+            .or(lp_store_offers);
+
+        let incursion = root
+            .clone()
+            .and(warp::path!("incursions" / ..));
+        let incursion_all = incursion
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and_then(Self::incursion_all);
+        let incursion = incursion_all;
+
         let industry = root
             .clone()
             .and(warp::path!("industry" / ..));
@@ -263,22 +1139,104 @@ impl ApiServer {
             .clone()
             .and(warp::path!("jobs"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::industry_jobs);
         let industry_stations = industry
             .clone()
             .and(warp::path!("stations"))
             .and(warp::get())
             .and_then(Self::industry_stations);
+        let industry_schedule = industry
+            .clone()
+            .and(warp::path!("schedule"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::industry_schedule);
         let industry = industry_jobs
-            .or(industry_stations);
+            .or(industry_stations)
+            .or(industry_schedule);
+
+        let market = root
+            .clone()
+            .and(warp::path!("market" / ..));
+        let market_undercut = market
+            .clone()
+            .and(warp::path!(TypeId / "undercut"))
+            .and(warp::get())
+            .and_then(Self::market_undercut);
+        let market_orders = market
+            .clone()
+            .and(warp::path!(TypeId / "orders"))
+            .and(warp::get())
+            .and(Self::list())
+            .and_then(Self::market_orders);
+        let market_venues = market
+            .clone()
+            .and(warp::path!(TypeId / "venues"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::market_venues);
+        let market_alerts = market
+            .clone()
+            .and(warp::path!("alerts"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::market_alerts);
+        let market_alert_create = market
+            .clone()
+            .and(warp::path!("alerts"))
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::market_alert_create);
+        let market_alert_delete = market
+            .clone()
+            .and(warp::path!("alerts" / Uuid))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::market_alert_delete);
+        let market_shopping = market
+            .clone()
+            .and(warp::path!("shopping"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(Self::market_shopping);
+        let market_structures = market
+            .clone()
+            .and(warp::path!("structures"))
+            .and(warp::get())
+            .and_then(Self::market_structures);
+        let market_set_structure = market
+            .clone()
+            .and(warp::path!("structures" / StructureId))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::market_set_structure);
+        let market_del_structure = market
+            .clone()
+            .and(warp::path!("structures" / StructureId))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::market_delete_structure);
+        let market = market_undercut
+            .or(market_orders)
+            .or(market_venues)
+            .or(market_alerts)
+            .or(market_alert_create)
+            .or(market_alert_delete)
+            .or(market_shopping)
+            .or(market_structures)
+            .or(market_set_structure)
+            .or(market_del_structure);
 
         let name = root
             .clone()
             .and(warp::path("name"));
         let name_resolve = name
             .clone()
-            .and(warp::path!("resolve" / TypeId))
+            .and(warp::path!("resolve" / ItemId))
             .and(warp::get())
             .and_then(Self::name_resolve);
         let name_resolve_bulk = name
@@ -293,9 +1251,23 @@ impl ApiServer {
             .and(warp::post())
             .and(warp::body::json())
             .and_then(Self::name_resolve_name_to_id_bulk);
+        let name_resolve_public = name
+            .clone()
+            .and(warp::path!("public"))
+            .and(warp::post())
+            .and(warp::addr::remote())
+            .and(warp::body::json())
+            .and_then(Self::name_resolve_public);
         let name = name_resolve
             .or(name_resolve_bulk)
-            .or(name_resolve_name_to_id_bulk);
+            .or(name_resolve_name_to_id_bulk)
+            .or(name_resolve_public);
+
+        let openapi = root
+            .clone()
+            .and(warp::path!("openapi.json"))
+            .and(warp::get())
+            .and_then(Self::openapi);
 
         let project = root
             .clone()
@@ -304,74 +1276,81 @@ impl ApiServer {
             .clone()
             .and(warp::path::end())
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::projects);
         let project_id = project
             .clone()
             .and(warp::path!(Uuid))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_id);
         let project_delete = project
             .clone()
             .and(warp::path!(Uuid))
             .and(warp::delete())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_delete);
         let project_new = project
             .clone()
             .and(warp::path::end())
             .and(warp::post())
             .and(warp::body::json())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_new);
         let project_cost = project
             .clone()
             .and(warp::path!(Uuid / "cost"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_cost);
+        let project_shopping = project
+            .clone()
+            .and(warp::path!(Uuid / "shopping"))
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::project_shopping);
         let project_materials = project
             .clone()
             .and(warp::path!(Uuid / "materials"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_materials);
         let project_materials_raw = project
             .clone()
             .and(warp::path!(Uuid / "materials" / "raw"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_materials_raw);
         let project_materials_stored = project
             .clone()
             .and(warp::path!(Uuid / "materials" / "stored"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_materials_stored);
         let project_blueprints = project
             .clone()
             .and(warp::path!(Uuid / "blueprints"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_blueprints);
         let project_tree = project
             .clone()
             .and(warp::path!(Uuid / "tree"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_tree);
         let project_required_products = project
             .clone()
             .and(warp::path!(Uuid / "products"))
             .and(warp::get())
-            .and(warp::cookie("token"))
+            .and(Self::token())
             .and_then(Self::project_required_products);
         let project = projects
             .or(project_id)
             .or(project_delete)
             .or(project_new)
             .or(project_cost)
+            .or(project_shopping)
             .or(project_materials)
             .or(project_materials_raw)
             .or(project_materials_stored)
@@ -379,430 +1358,2003 @@ impl ApiServer {
             .or(project_tree)
             .or(project_required_products);
 
-        let api = blueprint
-            .or(character)
-            .or(corporation)
-            .or(eve)
-            .or(industry)
-            .or(item)
-            .or(name)
-            .or(project)
-            .with(log);
-
-        warp::serve(api)
-            .run(([0, 0, 0, 0], 10101))
+        let public = root
+            .clone()
+            .and(warp::path!("public" / ..))
+            .and(Self::with_feature(features.public_api));
+        let public_caches = public
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and_then(Self::public_caches);
+        let public_entries = public
+            .clone()
+            .and(warp::path!(String))
+            .and(warp::get())
+            .and(warp::addr::remote())
+            .and_then(Self::public_entries);
+        let public_entry = public
+            .clone()
+            .and(warp::path!(String / u32))
+            .and(warp::get())
+            .and(warp::addr::remote())
+            .and_then(Self::public_entry);
+        let public = public_caches
+            .or(public_entries)
+            .or(public_entry);
+
+        let stock = root
+            .clone()
+            .and(warp::path!("stock" / ..));
+        let stock_rules = stock
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::query())
+            .and(Self::token())
+            .and_then(Self::stock_rules);
+        let stock_create = stock
+            .clone()
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(Self::token())
+            .and(warp::body::json())
+            .and_then(Self::stock_create);
+        let stock_delete = stock
+            .clone()
+            .and(warp::path!(Uuid))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::stock_delete);
+        let stock = stock_rules
+            .or(stock_create)
+            .or(stock_delete);
+
+        let universe = root
+            .clone()
+            .and(warp::path!("universe" / ..));
+        let universe_route = universe
+            .clone()
+            .and(warp::path!("route" / SolarSystemId / SolarSystemId))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::universe_route);
+        let universe_route_kills = universe
+            .clone()
+            .and(warp::path!("route" / SolarSystemId / SolarSystemId / "kills"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::universe_route_kills);
+        let universe_distance = universe
+            .clone()
+            .and(warp::path!("distance" / SolarSystemId / SolarSystemId))
+            .and(warp::get())
+            .and_then(Self::universe_distance);
+        let universe_jump_range = universe
+            .clone()
+            .and(warp::path!("jump" / SolarSystemId))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::universe_jump_range);
+        let universe_nearest_agents = universe
+            .clone()
+            .and(warp::path!("agents" / SolarSystemId))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::universe_nearest_agents);
+        let universe_sovereignty = universe
+            .clone()
+            .and(warp::path!("sovereignty"))
+            .and(warp::get())
+            .and(warp::query())
+            .and_then(Self::universe_sovereignty);
+        let universe_npc_damage = universe
+            .clone()
+            .and(warp::path!("npc" / "damage"))
+            .and(warp::get())
+            .and_then(Self::universe_npc_damage);
+        let universe_system_npc_damage = universe
+            .clone()
+            .and(warp::path!("npc" / "damage" / SolarSystemId))
+            .and(warp::get())
+            .and_then(Self::universe_system_npc_damage);
+        let universe = universe_route
+            .or(universe_route_kills)
+            .or(universe_distance)
+            .or(universe_jump_range)
+            .or(universe_nearest_agents)
+            .or(universe_sovereignty)
+            .or(universe_npc_damage)
+            .or(universe_system_npc_damage);
+
+        let workspace = root
+            .clone()
+            .and(warp::path!("workspaces" / ..));
+        let workspace_list = workspace
+            .clone()
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(Self::token())
+            .and_then(Self::workspace_list);
+        let workspace_create = workspace
+            .clone()
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::workspace_create);
+        let workspace_update = workspace
+            .clone()
+            .and(warp::path!(Uuid))
+            .and(warp::put())
+            .and(warp::body::json())
+            .and(Self::token())
+            .and_then(Self::workspace_update);
+        let workspace_delete = workspace
+            .clone()
+            .and(warp::path!(Uuid))
+            .and(warp::delete())
+            .and(Self::token())
+            .and_then(Self::workspace_delete);
+        let workspace = workspace_list
+            .or(workspace_create)
+            .or(workspace_update)
+            .or(workspace_delete);
+
+        let api = admin
+            .or(alliance)
+            .or(appraisal)
+            .or(blueprint)
+            .or(character)
+            .or(compression)
+            .or(contract)
+            .or(corporation)
+            .or(courier)
+            .or(eve)
+            .or(event)
+            .or(export)
+            .or(fitting)
+            .or(graphql)
+            .or(incursion)
+            .or(industry)
+            .or(item)
+            .or(lp_store)
+            .or(market)
+            .or(name)
+            .or(openapi)
+            .or(project)
+            .or(public)
+            .or(stock)
+            .or(universe)
+            .or(workspace)
+            .or(health)
+            .or(esi_proxy)
+            .recover(Self::recover)
+            .with(log);
+
+        warp::serve(api)
+            .run(address)
             .await;
     }
 
+    /// Answers with 503 and the error `eve_offline` while the eve servers
+    /// are offline, instead of failing with the error of ESI, all other
+    /// rejections are passed on
+    async fn recover(rejection: Rejection) -> Result<impl Reply, Rejection> {
+        match rejection.find::<EveServerError>() {
+            Some(EveServerError::EveOffline) => Ok(warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({ "error": "eve_offline" })),
+                StatusCode::SERVICE_UNAVAILABLE,
+            )),
+            _ => Err(rejection),
+        }
+    }
+
+    /// Rejects all requests if the feature is turned off in the config
+    fn with_feature(
+        enabled: bool,
+    ) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::any()
+            .and_then(move || async move {
+                if enabled {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            })
+            .untuple_one()
+    }
+
+    /// Only lets the request through if the requesting user has the role.
+    ///
+    /// The filter should already match the path of the route group, so that
+    /// the role is only looked up once and unknown paths stay not found.
+    fn with_role<F>(
+        filter: F,
+        role:   Role,
+    ) -> impl Filter<Extract = (), Error = Rejection> + Clone
+    where
+        F: Filter<Extract = (Arc<Self>,), Error = Rejection> + Clone {
+
+        filter
+            .and(Self::token())
+            .and_then(move |server: Arc<Self>, token: String| async move {
+                server
+                    .role
+                    .require(&token, role)
+                    .await
+                    .map(|_| ())
+                    .map_err(warp::reject::custom)
+            })
+            .untuple_one()
+    }
+
+    /// Shared pagination, sorting and field selection of list endpoints,
+    /// see [ListQuery]
+    fn list() -> impl Filter<Extract = (ListQuery,), Error = Rejection> + Clone {
+        warp::query::<ListQuery>()
+    }
+
+    /// Token of the requesting user, either the session cookie or an api
+    /// token given as `Authorization: Bearer <token>` header
+    fn token() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+        let bearer = warp::header::<String>("authorization")
+            .and_then(|x: String| async move {
+                x
+                    .strip_prefix("Bearer ")
+                    .map(|x| x.to_string())
+                    .ok_or_else(|| warp::reject::custom(EveServerError::InvalidUser))
+            });
+
+        warp::cookie::<String>("token")
+            .or(bearer)
+            .unify()
+    }
+
+    async fn admin_roles(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .role
+            .list()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_set_roles(
+        self:  Arc<Self>,
+        uid:   CharacterId,
+        roles: Vec<Role>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .role
+            .set(&token, uid, roles)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_audit(
+        self:  Arc<Self>,
+        query: AuditQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .audit
+            .query(query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_users(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .admin
+            .users()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_delete_user(
+        self:  Arc<Self>,
+        uid:   CharacterId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .admin
+            .delete(&token, uid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_user_tokens(
+        self: Arc<Self>,
+        uid:  CharacterId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .admin
+            .tokens(uid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_refresh_token(
+        self:  Arc<Self>,
+        uid:   CharacterId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .admin
+            .refresh(&token, uid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn admin_user_sync(
+        self: Arc<Self>,
+        uid:  CharacterId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .admin
+            .sync_status(uid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn alliance_report(
+        self:  Arc<Self>,
+        aid:   AllianceId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .alliance
+            .report(aid, &token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn appraisal(
+        self:  Arc<Self>,
+        query: PriceQuery,
+        paste: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .appraisal
+            .appraise(paste, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
     async fn blueprint_all(
         self: Arc<Self>,
     ) -> Result<impl Reply, Rejection> {
         self
-            .blueprint
-            .all()
+            .blueprint
+            .all()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_get(
+        self: Arc<Self>,
+        bid:  TypeId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .blueprint
+            .by_id(bid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_history(
+        self: Arc<Self>,
+        bid:  TypeId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .blueprint
+            .history(bid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_capital(
+        self:  Arc<Self>,
+        bid:   TypeId,
+        query: CapitalQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .capital
+            .plan(bid, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_plan(
+        self:  Arc<Self>,
+        bid:   TypeId,
+        token: String,
+        query: BuildPlanQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .build_plan
+            .plan(&token, bid, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_reactions(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .blueprint
+            .reactions()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn blueprint_reaction_chain(
+        self:  Arc<Self>,
+        pid:   TypeId,
+        query: ReactionQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .blueprint
+            .reaction_chain(pid, query.quantity)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_assets(
+        self:  Arc<Self>,
+        query: AssetQuery,
+        list:  ListQuery,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .assets(&token, query)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_assets_cost(
+        self:  Arc<Self>,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .asset_cost_basis(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_assets_reprocess(
+        self:  Arc<Self>,
+        token: String,
+        query: ReprocessQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .reprocess
+            .decision(&token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_assets_worth(
+        self:  Arc<Self>,
+        query: PriceQuery,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .asset_worth(&token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_assets_volume(
+        self:  Arc<Self>,
+        token: String,
+        query: HaulingQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .asset_volume(&token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_blueprints(
+        self:  Arc<Self>,
+        list:  ListQuery,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .blueprints(token)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_blueprint_stacks(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .blueprint_stacks(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_blueprint_report(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .blueprint_report(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_clones(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .clones(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_contracts(
+        self:  Arc<Self>,
+        token: String,
+        query: ContractQuery,
+        list:  ListQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .contracts(&token, query)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_mining(
+        self:  Arc<Self>,
+        token: String,
+        query: MiningQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .mining
+            .character(&token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_notifications(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .notification
+            .notifications(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_notifications_read(
+        self:  Arc<Self>,
+        token: String,
+        ids:   Vec<u64>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .notification
+            .mark_read(&token, ids)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_notifications_webhook(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .notification
+            .webhook(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_set_notifications_webhook(
+        self:    Arc<Self>,
+        token:   String,
+        webhook: Webhook,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .notification
+            .set_webhook(&token, webhook)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_calendar(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .notification
+            .calendar(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_preferences(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .preference
+            .preferences(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_set_preferences(
+        self:       Arc<Self>,
+        token:      String,
+        preference: UserPreferenceEntry,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .preference
+            .set_preferences(&token, preference)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_planets(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .planets(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_skill_farm(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .skill_farm
+            .overview(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_skill_farm_omega(
+        self:   Arc<Self>,
+        cid:    CharacterId,
+        expiry: u64,
+        token:  String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .skill_farm
+            .set_omega(&token, cid, expiry)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_net_worth(
+        self:  Arc<Self>,
+        token: String,
+        query: NetWorthQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .net_worth(&token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_skill_history(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .skill_farm
+            .history(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_skill_requirements(
+        self:  Arc<Self>,
+        tid:   TypeId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .skill_farm
+            .requirements(&token, tid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_sync(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .sync_status(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_info(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .info(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn character_item_location(
+        self:  Arc<Self>,
+        id:    u64,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .item_location(token, id)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn compression_ores(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .compression
+            .ores()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn compression_plan(
+        self:    Arc<Self>,
+        request: CompressionRequest,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .compression
+            .plan(request)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn contract_search(
+        self:  Arc<Self>,
+        query: ContractSearchQuery,
+        list:  ListQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .contract
+            .search(query)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn contract_snipes(
+        self:  Arc<Self>,
+        query: SnipeQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .contract
+            .snipes(query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    fn contract_snipes_ws(
+        self:  Arc<Self>,
+        ws:    Ws,
+        query: SnipeQuery,
+    ) -> impl Reply {
+        ws.on_upgrade(move |socket| async move {
+            self
+                .contract
+                .feed(socket, query)
+                .await
+        })
+    }
+
+    async fn corporation_assets(
+        self:  Arc<Self>,
+        cid:   CorporationId,
+        query: AssetQuery,
+        list:  ListQuery,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .corporation
+            .assets(cid, token, query)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn corporation_blueprints(
+        self:  Arc<Self>,
+        cid:   CorporationId,
+        list:  ListQuery,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .corporation
+            .blueprints(cid, token)
+            .await
+            .and_then(|x| list.apply(x))
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn corporation_set_blueprints(
+        self:  Arc<Self>,
+        cid:   CorporationId,
+        body:  Vec<CorporationBlueprintEntry>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .corporation
+            .set_blueprints(cid, body, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn corporation_delete_blueprints(
+        self:  Arc<Self>,
+        cid:   CorporationId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .corporation
+            .delete_blueprints(cid, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn corporation_mining(
+        self:  Arc<Self>,
+        cid:   CorporationId,
+        token: String,
+        query: MiningQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .mining
+            .corporation(cid, &token, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn courier_price(
+        self:  Arc<Self>,
+        from:  SolarSystemId,
+        to:    SolarSystemId,
+        query: CourierQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .courier
+            .price(from, to, query)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn courier_plan(
+        self:    Arc<Self>,
+        from:    SolarSystemId,
+        to:      SolarSystemId,
+        request: HaulingRequest,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .courier
+            .plan(from, to, request)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_auth(
+        self:  Arc<Self>,
+        query: EveAuthQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let token = self.eve_auth.auth(query.code, query.state).await?;
+
+        if let Some(token) = token {
+            let cookie = format!(
+                "token={}; Path=/; Secure; HttpOnly; Max-Age={}",
+                token, EveAuthService::SESSION_DAYS * 24 * 60 * 60
+            );
+
+            Ok(Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header("location", "https://eve.caph.xyz")
+                .header("Set-Cookie", cookie)
+                .body("")
+                .unwrap_or_default())
+        } else {
+            Ok(Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header("location", "https://eve.caph.xyz")
+                .body("")
+                .unwrap_or_default())
+        }
+    }
+
+    async fn eve_login(
+        self:  Arc<Self>,
+        query: LoginQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let uri = self.eve_auth.login(query).await?;
+        let uri = warp::http::uri::Builder::new()
+            .scheme(uri.scheme())
+            .authority(uri.host_str().unwrap_or_default())
+            .path_and_query(&format!("{}?{}", uri.path(), uri.query().unwrap_or_default()))
+            .build()
+            .unwrap_or_default();
+        Ok(warp::redirect::temporary(uri))
+    }
+
+    async fn eve_login_alt(
+        self:  Arc<Self>,
+        query: LoginQuery,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        let uri = self.eve_auth.login_alt(&token, query).await?;
+        let uri = warp::http::uri::Builder::new()
+            .scheme(uri.scheme())
+            .authority(uri.host_str().unwrap_or_default())
+            .path_and_query(&format!("{}?{}", uri.path(), uri.query().unwrap_or_default()))
+            .build()
+            .unwrap_or_default();
+        Ok(warp::redirect::temporary(uri))
+    }
+
+    async fn eve_whoami(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .character
+            .whoami(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    fn events(
+        self:  Arc<Self>,
+        ws:    Ws,
+        token: String,
+    ) -> impl Reply {
+        ws.on_upgrade(move |socket| async move {
+            self
+                .event
+                .feed(socket, token)
+                .await
+        })
+    }
+
+    async fn eve_roles(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .role
+            .roles(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_features(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .scope
+            .features(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_logout(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .eve_auth
+            .logout(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_sessions(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .eve_auth
+            .sessions(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_create_token(
+        self:  Arc<Self>,
+        body:  ApiTokenRequest,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .eve_auth
+            .create_api_token(&token, body)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_revoke_session(
+        self:  Arc<Self>,
+        id:    String,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .eve_auth
+            .revoke(&token, id)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_deauthorize(
+        self:  Arc<Self>,
+        uid:   CharacterId,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .eve_auth
+            .deauthorize(&token, uid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn eve_delete_account(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .delete_user
+            .delete_own(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn name_resolve(
+        self:    Arc<Self>,
+        item_id: ItemId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .name
+            .resolve_id(item_id)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn name_resolve_bulk(
+        self: Arc<Self>,
+        ids:  Vec<ItemId>
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .name
+            .resolve_bulk(ids)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn name_resolve_name_to_id_bulk(
+        self:  Arc<Self>,
+        names: Vec<String>
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .name
+            .resolve_names_to_id_bulk(names)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn name_resolve_public(
+        self: Arc<Self>,
+        addr: Option<SocketAddr>,
+        ids:  Vec<u64>
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .name
+            .resolve_public(addr.map(|x| x.ip()), ids)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn openapi(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&openapi::document()))
+    }
+
+    async fn projects(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .all(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_id(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .by_id(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_delete(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .delete(id, &token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_new(
+        self:  Arc<Self>,
+        body:  ProjectNew,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .create(body, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_shopping(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .shopping_list(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_cost(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .cost(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_materials(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .materials(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_materials_raw(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .raw_materials(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_materials_stored(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .stored_materials(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_blueprints(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .blueprints(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_tree(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .trees(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn project_required_products(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .project
+            .manufacture(id, token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn public_caches(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.public.caches().await))
+    }
+
+    async fn public_entries(
+        self: Arc<Self>,
+        name: String,
+        addr: Option<SocketAddr>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .public
+            .entries(addr.map(|x| x.ip()), name, None)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn public_entry(
+        self: Arc<Self>,
+        name: String,
+        id:   u32,
+        addr: Option<SocketAddr>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .public
+            .entries(addr.map(|x| x.ip()), name, Some(id))
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn graphql(
+        self:    Arc<Self>,
+        token:   String,
+        request: async_graphql::Request,
+    ) -> Result<impl Reply, Rejection> {
+        let response = self
+            .graphql
+            .execute(token, request)
+            .await;
+        Ok(warp::reply::json(&response))
+    }
+
+    async fn health_live(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        Ok(StatusCode::OK)
+    }
+
+    async fn health_ready(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        let readiness = self.health.ready().await;
+        let status = if readiness.ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Ok(warp::reply::with_status(warp::reply::json(&readiness), status))
+    }
+
+    async fn esi_proxy(
+        self:  Arc<Self>,
+        path:  warp::path::Tail,
+        query: String,
+    ) -> Result<impl Reply, Rejection> {
+        let response = self.esi_proxy.get(path.as_str(), &query).await?;
+
+        let status = if response.found {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_FOUND
+        };
+        let mut builder = Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .header("x-cache", if response.cached { "HIT" } else { "MISS" });
+        if let Some(x) = response.expires {
+            builder = builder.header("expires", x);
+        }
+        if let Some(x) = response.etag {
+            builder = builder.header("etag", x);
+        }
+        if response.pages > 0 {
+            builder = builder.header("x-pages", response.pages.to_string());
+        }
+        Ok(builder
+            .body(response.body)
+            .unwrap_or_default())
+    }
+
+    async fn incursion_all(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .incursion
+            .incursions()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn industry_jobs(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .industry
+            .jobs(token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn industry_stations(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        let stations = self
+            .industry
+            .stations()?
+            .iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+        Ok(warp::reply::json(&stations))
+    }
+
+    async fn industry_schedule(
+        self:    Arc<Self>,
+        token:   String,
+        request: ScheduleRequest,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .schedule
+            .schedule(&token, request)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn export_assets(
+        self:  Arc<Self>,
+        token: String,
+        query: ExportQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .export
+            .assets(&token, query)
+            .await
+            .map(|x| Self::csv("assets.csv", x))
+            .map_err(Into::into)
+    }
+
+    async fn export_blueprints(
+        self:  Arc<Self>,
+        token: String,
+        query: ExportQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .export
+            .blueprints(token, query)
             .await
-            .map(|x| warp::reply::json(&x))
+            .map(|x| Self::csv("blueprints.csv", x))
             .map_err(Into::into)
     }
 
-    async fn blueprint_get(
-        self: Arc<Self>,
-        bid:  TypeId,
+    async fn export_market_venues(
+        self:  Arc<Self>,
+        tid:   TypeId,
+        query: MarketExportQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .blueprint
-            .by_id(bid)
+            .export
+            .market_venues(tid, query)
             .await
-            .map(|x| warp::reply::json(&x))
+            .map(|x| Self::csv(&format!("venues_{}.csv", *tid), x))
             .map_err(Into::into)
     }
 
-    async fn character_assets(
+    /// Wraps the csv into a response that is downloaded as a file
+    fn csv(filename: &str, csv: String) -> Response<String> {
+        Response::builder()
+            .header("Content-Type", "text/csv; charset=utf-8")
+            .header("Content-Disposition", format!("attachment; filename=\"{}\"", filename))
+            .body(csv)
+            .unwrap_or_default()
+    }
+
+    async fn fittings(
         self:  Arc<Self>,
-        token: String
+        token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .character
-            .assets(&token)
+            .fitting
+            .fittings(&token)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn character_blueprints(
+    async fn fitting_import(
         self:  Arc<Self>,
         token: String,
+        eft:   String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .character
-            .blueprints(token)
+            .fitting
+            .import(&token, eft)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn character_info(
+    async fn fitting_export(
         self:  Arc<Self>,
+        id:    Uuid,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .character
-            .info(token)
+            .fitting
+            .export(&token, id)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn character_item_location(
+    async fn fitting_export_esi(
         self:  Arc<Self>,
-        id:    u64,
+        fid:   FittingId,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .character
-            .item_location(token, id)
+            .fitting
+            .export_esi(&token, fid)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn corporation_blueprints(
-        self:  Arc<Self>,
-        cid:   CorporationId,
-        token: String,
+    async fn fitting_doctrine(
+        self:     Arc<Self>,
+        token:    String,
+        doctrine: Doctrine,
     ) -> Result<impl Reply, Rejection> {
         self
-            .corporation
-            .blueprints(cid, token)
+            .fitting
+            .doctrine(&token, doctrine)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn corporation_set_blueprints(
+    async fn fitting_delete(
         self:  Arc<Self>,
-        cid:   CorporationId,
-        body:  Vec<CorporationBlueprintEntry>,
+        id:    Uuid,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .corporation
-            .set_blueprints(cid, body, token)
+            .fitting
+            .delete(&token, id)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn corporation_delete_blueprints(
-        self:  Arc<Self>,
-        cid:   CorporationId,
-        token: String,
+    async fn item_all(
+        self: Arc<Self>
     ) -> Result<impl Reply, Rejection> {
         self
-            .corporation
-            .delete_blueprints(cid, token)
+            .item
+            .all()
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn eve_auth(
-        self:  Arc<Self>,
-        query: EveAuthQuery,
+    async fn item_keys(
+        self: Arc<Self>
     ) -> Result<impl Reply, Rejection> {
-        let token = self.eve_auth.auth(query.code, query.state).await?;
-
-        if let Some(token) = token {
-            let cookie = format!(
-                "token={}; Path=/; Secure; HttpOnly; Max-Age={}",
-                token, 31557800 // 10 years
-            );
-
-            Ok(Response::builder()
-                .status(StatusCode::MOVED_PERMANENTLY)
-                .header("location", "https://eve.caph.xyz")
-                .header("Set-Cookie", cookie)
-                .body("")
-                .unwrap_or_default())
-        } else {
-            Ok(Response::builder()
-                .status(StatusCode::MOVED_PERMANENTLY)
-                .header("location", "https://eve.caph.xyz")
-                .body("")
-                .unwrap_or_default())
-        }
+        self
+            .item
+            .keys()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
     }
 
-    async fn eve_login(
+    async fn item_meta(
         self: Arc<Self>,
+        tid:  TypeId
     ) -> Result<impl Reply, Rejection> {
-        let uri = self.eve_auth.login().await?;
-        let uri = warp::http::uri::Builder::new()
-            .scheme(uri.scheme())
-            .authority(uri.host_str().unwrap_or_default())
-            .path_and_query(&format!("{}?{}", uri.path(), uri.query().unwrap_or_default()))
-            .build()
-            .unwrap_or_default();
-        Ok(warp::redirect::temporary(uri))
+        self
+            .item
+            .meta(tid)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
     }
 
-    async fn eve_login_alt(
-        self:  Arc<Self>,
-        token: String,
+    async fn lp_store_corporations(
+        self: Arc<Self>,
     ) -> Result<impl Reply, Rejection> {
-        let uri = self.eve_auth.login_alt(&token).await?;
-        let uri = warp::http::uri::Builder::new()
-            .scheme(uri.scheme())
-            .authority(uri.host_str().unwrap_or_default())
-            .path_and_query(&format!("{}?{}", uri.path(), uri.query().unwrap_or_default()))
-            .build()
-            .unwrap_or_default();
-        Ok(warp::redirect::temporary(uri))
+        self
+            .lp_store
+            .corporations()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
     }
 
-    async fn eve_whoami(
-        self:  Arc<Self>,
-        token: String,
+    async fn lp_store_offers(
+        self: Arc<Self>,
+        cid:  CorporationId,
     ) -> Result<impl Reply, Rejection> {
         self
-            .character
-            .whoami(token)
+            .lp_store
+            .offers(cid)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn name_resolve(
-        self:    Arc<Self>,
-        item_id: TypeId,
+    async fn market_undercut(
+        self: Arc<Self>,
+        tid:  TypeId,
     ) -> Result<impl Reply, Rejection> {
         self
-            .name
-            .resolve_id(item_id)
+            .market
+            .undercut(tid)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn name_resolve_bulk(
+    async fn market_orders(
         self: Arc<Self>,
-        ids:  Vec<TypeId>
+        tid:  TypeId,
+        list: ListQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .name
-            .resolve_bulk(ids)
+            .market
+            .orders(tid)
             .await
+            .and_then(|x| list.apply(x))
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn name_resolve_name_to_id_bulk(
+    async fn market_venues(
         self:  Arc<Self>,
-        names: Vec<String>
+        tid:   TypeId,
+        query: VenueQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .name
-            .resolve_names_to_id_bulk(names)
+            .market
+            .venues(tid, query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn projects(
+    async fn market_alerts(
         self:  Arc<Self>,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .all(token)
+            .price_alert
+            .alerts(&token)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_id(
+    async fn market_alert_create(
         self:  Arc<Self>,
-        id:    Uuid,
         token: String,
+        alert: PriceAlert,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .by_id(id, token)
+            .price_alert
+            .create(&token, alert)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_delete(
+    async fn market_alert_delete(
         self:  Arc<Self>,
         id:    Uuid,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .delete(id, &token)
+            .price_alert
+            .delete(&token, id)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_new(
-        self:  Arc<Self>,
-        body:  ProjectNew,
-        token: String,
+    async fn market_shopping(
+        self:      Arc<Self>,
+        materials: Vec<ShoppingMaterial>,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .create(body, token)
+            .market
+            .shopping_list(materials)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_cost(
+    async fn market_structures(
+        self: Arc<Self>,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .market
+            .structure_fees()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn market_set_structure(
         self:  Arc<Self>,
-        id:    Uuid,
+        sid:   StructureId,
+        fee:   StructureFee,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .cost(id, token)
+            .market
+            .set_structure_fee(sid, fee, &token)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_materials(
+    async fn market_delete_structure(
         self:  Arc<Self>,
-        id:    Uuid,
+        sid:   StructureId,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .materials(id, token)
+            .market
+            .delete_structure_fee(sid, &token)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_materials_raw(
+    async fn stock_rules(
         self:  Arc<Self>,
-        id:    Uuid,
+        query: StockQuery,
         token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .raw_materials(id, token)
+            .stock
+            .rules(&token, query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_materials_stored(
+    async fn stock_create(
         self:  Arc<Self>,
-        id:    Uuid,
-        token: String
+        token: String,
+        rule:  StockRule,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .stored_materials(id, token)
+            .stock
+            .create(&token, rule)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_blueprints(
+    async fn stock_delete(
         self:  Arc<Self>,
         id:    Uuid,
-        token: String
+        token: String,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .blueprints(id, token)
+            .stock
+            .delete(&token, id)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_tree(
+    async fn universe_route(
         self:  Arc<Self>,
-        id:    Uuid,
-        token: String
+        from:  SolarSystemId,
+        to:    SolarSystemId,
+        query: RouteQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .trees(id, token)
+            .universe
+            .route(from, to, query.flag)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn project_required_products(
+    async fn universe_route_kills(
         self:  Arc<Self>,
-        id:    Uuid,
-        token: String
+        from:  SolarSystemId,
+        to:    SolarSystemId,
+        query: RouteKillsQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .project
-            .manufacture(id, token)
+            .universe
+            .route_kills(from, to, query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn industry_jobs(
+    async fn universe_sovereignty(
         self:  Arc<Self>,
-        token: String,
+        query: SovereigntyQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .industry
-            .jobs(token)
+            .universe
+            .sovereignty(query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn industry_stations(
+    async fn universe_distance(
         self: Arc<Self>,
+        from: SolarSystemId,
+        to:   SolarSystemId,
     ) -> Result<impl Reply, Rejection> {
-        let stations = self
-            .industry
-            .stations()?
-            .iter()
-            .map(|x| x.id)
-            .collect::<Vec<_>>();
-        Ok(warp::reply::json(&stations))
+        self
+            .universe
+            .distance(from, to)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
     }
 
-    async fn item_all(
-        self: Arc<Self>
+    async fn universe_jump_range(
+        self:   Arc<Self>,
+        system: SolarSystemId,
+        query:  JumpRangeQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .item
-            .all()
+            .universe
+            .jump_range(system, query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn item_keys(
-        self: Arc<Self>
+    async fn universe_nearest_agents(
+        self:   Arc<Self>,
+        system: SolarSystemId,
+        query:  NearestAgentQuery,
     ) -> Result<impl Reply, Rejection> {
         self
-            .item
-            .keys()
+            .universe
+            .nearest_agents(system, query)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
     }
 
-    async fn item_meta(
+    async fn universe_npc_damage(
         self: Arc<Self>,
-        tid:  TypeId
     ) -> Result<impl Reply, Rejection> {
         self
-            .item
-            .meta(tid)
+            .universe
+            .npc_damage()
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn universe_system_npc_damage(
+        self:   Arc<Self>,
+        system: SolarSystemId,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .universe
+            .system_npc_damage(system)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn workspace_list(
+        self:  Arc<Self>,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .workspace
+            .list(&token)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn workspace_create(
+        self:    Arc<Self>,
+        request: WorkspaceRequest,
+        token:   String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .workspace
+            .create(&token, request)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn workspace_update(
+        self:    Arc<Self>,
+        id:      Uuid,
+        request: WorkspaceRequest,
+        token:   String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .workspace
+            .update(&token, id, request)
+            .await
+            .map(|x| warp::reply::json(&x))
+            .map_err(Into::into)
+    }
+
+    async fn workspace_delete(
+        self:  Arc<Self>,
+        id:    Uuid,
+        token: String,
+    ) -> Result<impl Reply, Rejection> {
+        self
+            .workspace
+            .delete(&token, id)
             .await
             .map(|x| warp::reply::json(&x))
             .map_err(Into::into)
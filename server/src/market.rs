@@ -0,0 +1,597 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, MarketInfoEntry, MarketOrderEntry, MarketUndercutEntry, StructureFeeEntry};
+use caph_eve_data_wrapper::{ItemId, LocationId, StructureId, TypeId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Service for all market related interfaces
+#[derive(Clone)]
+pub struct MarketService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+}
+
+impl MarketService {
+    /// NPC trade hubs that are always compared
+    const NPC_HUBS: &'static [(LocationId, &'static str)] = &[
+        (LocationId(60003760), "Jita IV - Moon 4 - Caldari Navy Assembly Plant"),
+        (LocationId(60008494), "Amarr VIII (Oris) - Emperor Family Academy"),
+        (LocationId(60011866), "Dodixie IX - Moon 20 - Federation Navy Assembly Plant"),
+        (LocationId(60004588), "Rens VI - Moon 8 - Brutor Tribe Treasury"),
+        (LocationId(60005686), "Hek VIII - Moon 12 - Boundless Creation Factory"),
+    ];
+    /// Broker fee in percent at NPC stations without any skills or standings
+    const DEFAULT_NPC_BROKER_FEE: f32 = 3f32;
+    /// Sales tax in percent without any skills
+    const DEFAULT_SALES_TAX:      f32 = 8f32;
+    /// Maximum number of different items in a shopping list
+    const MAX_SHOPPING_ITEMS:     usize = 1_000;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+        }
+    }
+
+    /// Gets how often the best Jita sell price of an item is undercut.
+    ///
+    /// # Params
+    ///
+    /// `tid` -> Item to get the statistic for
+    ///
+    /// # Returns
+    ///
+    /// `None` if the item is not sold in Jita, otherwise the current best
+    /// price and the undercuts per hour over the last 24 hours
+    ///
+    pub async fn undercut(
+        &self,
+        tid: TypeId,
+    ) -> Result<Option<UndercutStats>, EveServerError> {
+        let stats = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, MarketUndercutEntry>(CacheName::MarketUndercut, tid)
+            .await?
+            .map(|x| UndercutStats {
+                type_id:    x.type_id,
+                best_price: x.best_price,
+                per_hour:   x.per_hour(),
+                snapshots:  x.snapshots.len() as u32,
+            });
+        Ok(stats)
+    }
+
+    /// Gets the open orders of an item, the sell orders first, each sorted
+    /// by their best price
+    ///
+    /// # Params
+    ///
+    /// `tid` -> Item to get the orders for
+    ///
+    pub async fn orders(
+        &self,
+        tid: TypeId,
+    ) -> Result<Vec<MarketOrder>, EveServerError> {
+        let mut orders = self
+            .latest_orders(tid)
+            .await?
+            .into_iter()
+            .map(|(order, volume_remain)| MarketOrder {
+                order,
+                volume_remain,
+            })
+            .collect::<Vec<_>>();
+        orders.sort_by(|a, b| {
+            let (a, b) = (&a.order, &b.order);
+            a.is_buy_order
+                .cmp(&b.is_buy_order)
+                .then_with(|| {
+                    let ordering = a.price
+                        .partial_cmp(&b.price)
+                        .unwrap_or(std::cmp::Ordering::Equal);
+                    if a.is_buy_order {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+        });
+        Ok(orders)
+    }
+
+    /// Compares the proceeds of selling an item at the NPC trade hubs and
+    /// at all player structures that have their broker fee configured.
+    ///
+    /// For every venue two options are calculated, creating a sell order
+    /// that matches the current best sell price and selling directly into
+    /// the buy orders.
+    ///
+    /// # Params
+    ///
+    /// `tid`   -> Item to sell
+    /// `query` -> Quantity and the fees of the seller
+    ///
+    /// # Returns
+    ///
+    /// List of all venues, the best venue first
+    ///
+    pub async fn venues(
+        &self,
+        tid:   TypeId,
+        query: VenueQuery,
+    ) -> Result<Vec<MarketVenue>, EveServerError> {
+        let sales_tax = query.sales_tax.unwrap_or(Self::DEFAULT_SALES_TAX) / 100f32;
+        let npc_broker_fee = query
+            .broker_fee
+            .unwrap_or(Self::DEFAULT_NPC_BROKER_FEE) / 100f32;
+
+        let mut by_location: HashMap<LocationId, Vec<(MarketInfoEntry, u32)>> = HashMap::new();
+        self
+            .latest_orders(tid)
+            .await?
+            .into_iter()
+            .for_each(|(info, volume)| {
+                by_location
+                    .entry(info.location_id)
+                    .or_default()
+                    .push((info, volume))
+            });
+
+        let mut con = self.pool.acquire().await?;
+        let structure_ids = con
+            .keys::<_, StructureId>(CacheName::StructureFee)
+            .await?;
+        let structures = con
+            .mget::<_, _, StructureFeeEntry>(CacheName::StructureFee, structure_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.structure_id.into(), x.name, x.broker_fee / 100f32));
+
+        let mut venues = Self::NPC_HUBS
+            .iter()
+            .map(|(id, name)| (*id, name.to_string(), npc_broker_fee))
+            .chain(structures)
+            .map(|(location_id, name, broker_fee)| {
+                let orders = by_location
+                    .get(&location_id)
+                    .cloned()
+                    .unwrap_or_default();
+                Self::venue(
+                    location_id,
+                    name,
+                    orders,
+                    query.quantity,
+                    broker_fee,
+                    sales_tax,
+                )
+            })
+            .collect::<Vec<_>>();
+        venues.sort_by(|a, b| {
+            b.best()
+                .partial_cmp(&a.best())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(venues)
+    }
+
+    /// Splits the materials over the NPC trade hubs. Every material is
+    /// bought at the hub where the whole quantity is the cheapest.
+    ///
+    /// # Params
+    ///
+    /// `materials` -> Materials and quantities to buy
+    ///
+    /// # Returns
+    ///
+    /// Materials grouped by trade hub with their cost and a text block for
+    /// the ingame multibuy window
+    ///
+    pub async fn shopping_list(
+        &self,
+        materials: Vec<ShoppingMaterial>,
+    ) -> Result<ShoppingList, EveServerError> {
+        let mut quantities: HashMap<TypeId, u32> = HashMap::new();
+        for material in materials {
+            *quantities.entry(material.type_id).or_default() += material.quantity;
+        }
+        if quantities.len() > Self::MAX_SHOPPING_ITEMS {
+            return Err(EveServerError::TooManyIds);
+        }
+
+        let type_ids = quantities
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        let ids = type_ids
+            .iter()
+            .map(|x| ItemId::from(*x))
+            .collect::<Vec<_>>();
+        let names = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, String>(CacheName::Name, ids)
+            .await?;
+
+        let mut hubs: HashMap<LocationId, Vec<ShoppingItem>> = HashMap::new();
+        let mut unavailable = Vec::new();
+        for (tid, name) in type_ids.into_iter().zip(names) {
+            let quantity = quantities[&tid];
+            let orders = self.latest_orders(tid).await?;
+
+            let cheapest = Self::NPC_HUBS
+                .iter()
+                .filter_map(|(location_id, _)| {
+                    let sell_orders = orders
+                        .iter()
+                        .filter(|(x, _)| !x.is_buy_order && x.location_id == *location_id)
+                        .map(|(x, volume)| (x.price, *volume))
+                        .collect::<Vec<_>>();
+                    Self::buy_cost(sell_orders, quantity).map(|x| (*location_id, x))
+                })
+                .min_by(|(_, a), (_, b)| {
+                    a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+                });
+
+            let item = ShoppingItem {
+                type_id: tid,
+                name:    name.unwrap_or_default(),
+                quantity,
+                cost:    cheapest.map(|(_, x)| x).unwrap_or_default(),
+            };
+            match cheapest {
+                Some((location_id, _)) => hubs.entry(location_id).or_default().push(item),
+                None                   => unavailable.push(item),
+            }
+        }
+
+        let hubs = Self::NPC_HUBS
+            .iter()
+            .filter_map(|(location_id, name)| {
+                let mut items = hubs.remove(location_id)?;
+                items.sort_by(|a, b| a.name.cmp(&b.name));
+                Some(ShoppingHub {
+                    location_id: *location_id,
+                    name:        name.to_string(),
+                    cost:        items.iter().map(|x| x.cost).sum(),
+                    multibuy:    Self::multibuy(&items),
+                    items,
+                })
+            })
+            .collect::<Vec<_>>();
+        unavailable.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(ShoppingList {
+            cost: hubs.iter().map(|x| x.cost).sum(),
+            hubs,
+            unavailable,
+        })
+    }
+
+    /// Gets all structures with a configured broker fee
+    pub async fn structure_fees(&self) -> Result<Vec<StructureFeeEntry>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let ids = con
+            .keys::<_, StructureId>(CacheName::StructureFee)
+            .await?;
+        let fees = con
+            .mget::<_, _, StructureFeeEntry>(CacheName::StructureFee, ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        Ok(fees)
+    }
+
+    /// Sets the broker fee of a player structure.
+    ///
+    /// # Params
+    ///
+    /// `sid`   -> Id of the structure
+    /// `fee`   -> Name of the structure and its broker fee
+    /// `token` -> Cookie from the requesting user
+    ///
+    pub async fn set_structure_fee(
+        &self,
+        sid:   StructureId,
+        fee:   StructureFee,
+        token: &str,
+    ) -> Result<(), EveServerError> {
+        let user_id = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?
+            .user_id;
+
+        let entry = StructureFeeEntry {
+            structure_id: sid,
+            name:         fee.name,
+            broker_fee:   fee.broker_fee,
+            updated_by:   user_id,
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::StructureFee, sid, entry)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Removes the broker fee of a player structure
+    pub async fn delete_structure_fee(
+        &self,
+        sid:   StructureId,
+        token: &str,
+    ) -> Result<(), EveServerError> {
+        self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        self
+            .pool
+            .acquire()
+            .await?
+            .del(CacheName::StructureFee, sid)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Gets the orders of an item together with their remaining volume.
+    ///
+    /// Only the orders of the latest snapshot are still open.
+    pub async fn latest_orders(
+        &self,
+        tid: TypeId,
+    ) -> Result<Vec<(MarketInfoEntry, u32)>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let orders = con
+            .get::<_, _, Vec<MarketOrderEntry>>(CacheName::MarketOrder, tid)
+            .await?
+            .unwrap_or_default();
+        let latest = orders
+            .iter()
+            .map(|x| x.timestamp)
+            .max()
+            .unwrap_or_default();
+        let orders = orders
+            .into_iter()
+            .filter(|x| x.timestamp == latest)
+            .collect::<Vec<_>>();
+
+        let order_ids = orders
+            .iter()
+            .map(|x| x.order_id)
+            .collect::<Vec<_>>();
+        let orders = con
+            .mget::<_, _, MarketInfoEntry>(CacheName::MarketInfo, order_ids)
+            .await?
+            .into_iter()
+            .zip(orders)
+            .filter_map(|(info, order)| info.map(|x| (x, order.volume_remain)))
+            .collect::<Vec<_>>();
+        Ok(orders)
+    }
+
+    /// Cost of buying the quantity from the cheapest sell orders.
+    ///
+    /// `None` if the orders do not have enough volume.
+    fn buy_cost(
+        mut sell_orders: Vec<(f32, u32)>,
+        quantity:        u32,
+    ) -> Option<f32> {
+        sell_orders.sort_by(|(a, _), (b, _)| {
+            a.partial_cmp(b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut remaining = quantity;
+        let mut cost = 0f32;
+        for (price, volume) in sell_orders {
+            if remaining == 0 {
+                break;
+            }
+
+            let bought = remaining.min(volume);
+            cost += bought as f32 * price;
+            remaining -= bought;
+        }
+
+        if remaining == 0 {
+            Some(cost)
+        } else {
+            None
+        }
+    }
+
+    /// Text that can be pasted into the ingame multibuy window
+    fn multibuy(items: &[ShoppingItem]) -> String {
+        items
+            .iter()
+            .map(|x| format!("{}\t{}", x.name, x.quantity))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Calculates the proceeds at a single venue
+    fn venue(
+        location_id: LocationId,
+        name:        String,
+        orders:      Vec<(MarketInfoEntry, u32)>,
+        quantity:    u32,
+        broker_fee:  f32,
+        sales_tax:   f32,
+    ) -> MarketVenue {
+        let best_sell = orders
+            .iter()
+            .filter(|(x, _)| !x.is_buy_order)
+            .map(|(x, _)| x.price)
+            .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.min(x))));
+        let sell_order = best_sell
+            .map(|x| x * quantity as f32 * (1f32 - broker_fee - sales_tax));
+
+        // Fill the highest buy orders first
+        let mut buy_orders = orders
+            .into_iter()
+            .filter(|(x, _)| x.is_buy_order)
+            .collect::<Vec<_>>();
+        buy_orders.sort_by(|(a, _), (b, _)| {
+            b.price
+                .partial_cmp(&a.price)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut remaining = quantity;
+        let mut instant = 0f32;
+        for (order, volume) in buy_orders {
+            if remaining == 0 {
+                break;
+            }
+
+            let sold = remaining.min(volume);
+            instant += sold as f32 * order.price;
+            remaining -= sold;
+        }
+        let instant = instant * (1f32 - sales_tax);
+
+        MarketVenue {
+            location_id,
+            name,
+            broker_fee: broker_fee * 100f32,
+            best_sell,
+            sell_order,
+            instant,
+            instant_unsold: remaining,
+        }
+    }
+}
+
+/// Open order together with its remaining volume
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MarketOrder {
+    #[serde(flatten)]
+    pub order:         MarketInfoEntry,
+    pub volume_remain: u32,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VenueQuery {
+    pub quantity:   u32,
+    /// Sales tax of the seller in percent
+    pub sales_tax:  Option<f32>,
+    /// Broker fee of the seller at NPC stations in percent
+    pub broker_fee: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StructureFee {
+    pub name:       String,
+    /// Broker fee in percent
+    pub broker_fee: f32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MarketVenue {
+    pub location_id:    LocationId,
+    pub name:           String,
+    /// Broker fee in percent that is used for the calculation
+    pub broker_fee:     f32,
+    /// Lowest sell price at the venue, None if nobody sells the item there
+    pub best_sell:      Option<f32>,
+    /// Proceeds after fees when selling with a sell order at the best price
+    pub sell_order:     Option<f32>,
+    /// Proceeds after taxes when selling into the buy orders
+    pub instant:        f32,
+    /// Quantity that could not be sold into the buy orders
+    pub instant_unsold: u32,
+}
+
+impl MarketVenue {
+    /// Best proceeds of both options
+    fn best(&self) -> f32 {
+        self.sell_order.unwrap_or_default().max(self.instant)
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ShoppingMaterial {
+    pub type_id:  TypeId,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ShoppingList {
+    /// Trade hubs in the order of [MarketService::NPC_HUBS], only hubs with
+    /// at least one item are included
+    pub hubs:        Vec<ShoppingHub>,
+    /// Items that no hub sells in the required quantity
+    pub unavailable: Vec<ShoppingItem>,
+    /// Cost of all hubs
+    pub cost:        f32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ShoppingHub {
+    pub location_id: LocationId,
+    pub name:        String,
+    pub items:       Vec<ShoppingItem>,
+    /// Cost of all items at this hub
+    pub cost:        f32,
+    /// Items in the format of the ingame multibuy window
+    pub multibuy:    String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ShoppingItem {
+    pub type_id:  TypeId,
+    pub name:     String,
+    pub quantity: u32,
+    /// Cost when buying from the cheapest sell orders, 0 if unavailable
+    pub cost:     f32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UndercutStats {
+    pub type_id:    TypeId,
+    pub best_price: f32,
+    pub per_hour:   f32,
+    /// Number of snapshots the statistic is based on
+    pub snapshots:  u32,
+}
+
+#[cfg(test)]
+mod market_tests {
+    use super::*;
+
+    #[test]
+    fn buy_cost() {
+        let orders = vec![(5f32, 10), (4f32, 10), (6f32, 100)];
+        assert_eq!(MarketService::buy_cost(orders.clone(), 15), Some(65f32));
+        assert_eq!(MarketService::buy_cost(orders.clone(), 0), Some(0f32));
+        assert_eq!(MarketService::buy_cost(orders, 121), None);
+    }
+
+    #[test]
+    fn multibuy() {
+        let items = vec![
+            ShoppingItem { type_id: 34u32.into(), name: "Tritanium".into(), quantity: 1000, cost: 0f32 },
+            ShoppingItem { type_id: 35u32.into(), name: "Pyerite".into(),   quantity: 50,   cost: 0f32 },
+        ];
+        assert_eq!(MarketService::multibuy(&items), "Tritanium\t1000\nPyerite\t50");
+    }
+}
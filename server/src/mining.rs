@@ -0,0 +1,216 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterMiningEntry, CorporationMiningEntry, MarketPriceEntry};
+use caph_eve_data_wrapper::{CorporationId, StructureId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Aggregates the mining ledgers of characters and corporations
+#[derive(Clone)]
+pub struct MiningService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+}
+
+impl MiningService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+        }
+    }
+
+    /// Mined ore of the character and all its alts.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Grouping and date range
+    ///
+    /// # Returns
+    ///
+    /// Mined quantity and its value per group, highest value first
+    ///
+    pub async fn character(
+        &self,
+        token: &str,
+        query: MiningQuery,
+    ) -> Result<Vec<MiningAggregate>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let mut con = self.pool.acquire().await?;
+        let records = con
+            .mget::<_, _, Vec<CharacterMiningEntry>>(CacheName::CharacterMining, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|x| query.contains(&x.date))
+            .map(|x| {
+                let id = match query.group {
+                    MiningGroup::Character => *x.user_id as u64,
+                    MiningGroup::System    => *x.system_id as u64,
+                    MiningGroup::Type      => *x.type_id as u64,
+                };
+                (id, x.type_id, x.quantity)
+            })
+            .collect::<Vec<_>>();
+
+        self.aggregate(records).await
+    }
+
+    /// Ore mined at the moon drills of a corporation, used for moon mining
+    /// taxes.
+    ///
+    /// # Params
+    ///
+    /// `cid`   -> Id of the corporation
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Grouping and date range
+    ///
+    /// # Returns
+    ///
+    /// Mined quantity and its value per group, highest value first. Fails
+    /// if neither the main nor one of its alts is a member of the
+    /// corporation
+    ///
+    pub async fn corporation(
+        &self,
+        cid:   CorporationId,
+        token: &str,
+        query: MiningQuery,
+    ) -> Result<Vec<MiningAggregate>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let is_member = user.corp_id == cid ||
+            user.aliase.iter().any(|x| x.corp_id == cid);
+        if !is_member {
+            return Err(EveServerError::MissingPermission);
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, StructureId>(CacheName::CorporationMining)
+            .await?;
+        let records = con
+            .mget::<_, _, Vec<CorporationMiningEntry>>(CacheName::CorporationMining, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|x| x.corporation_id == cid)
+            .filter(|x| query.contains(&x.date))
+            .map(|x| {
+                let id = match query.group {
+                    MiningGroup::Character => *x.character_id as u64,
+                    MiningGroup::System    => *x.system_id as u64,
+                    MiningGroup::Type      => *x.type_id as u64,
+                };
+                (id, x.type_id, x.quantity)
+            })
+            .collect::<Vec<_>>();
+
+        self.aggregate(records).await
+    }
+
+    /// Sums up the quantity and value of all records with the same group id
+    async fn aggregate(
+        &self,
+        records: Vec<(u64, TypeId, u64)>,
+    ) -> Result<Vec<MiningAggregate>, EveServerError> {
+        let mut type_ids = records
+            .iter()
+            .map(|(_, tid, _)| *tid)
+            .collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids.dedup();
+
+        let prices = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids.clone())
+            .await?
+            .into_iter()
+            .zip(type_ids)
+            .map(|(price, tid)| (tid, price.map(|x| x.average_price).unwrap_or_default()))
+            .collect::<HashMap<_, _>>();
+
+        let mut groups: HashMap<u64, MiningAggregate> = HashMap::new();
+        for (id, tid, quantity) in records {
+            let price = prices.get(&tid).copied().unwrap_or_default();
+            let entry = groups
+                .entry(id)
+                .or_insert(MiningAggregate {
+                    id,
+                    quantity: 0,
+                    value:    0f32,
+                });
+            entry.quantity += quantity;
+            entry.value    += quantity as f32 * price;
+        }
+
+        let mut result = groups
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MiningQuery {
+    pub group: MiningGroup,
+    /// Inclusive, format: YYYY-MM-DD
+    pub from:  Option<String>,
+    /// Inclusive, format: YYYY-MM-DD
+    pub to:    Option<String>,
+}
+
+impl MiningQuery {
+    /// Checks if the given date is in the requested range, the dates are
+    /// formatted so that they can be compared as strings
+    fn contains(&self, date: &str) -> bool {
+        let after = self.from.as_ref().map(|x| date >= x.as_str()).unwrap_or(true);
+        let before = self.to.as_ref().map(|x| date <= x.as_str()).unwrap_or(true);
+        after && before
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MiningGroup {
+    /// Groups by the pilot that mined the ore
+    Character,
+    System,
+    /// Groups by the ore type
+    Type,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MiningAggregate {
+    /// Depending on the grouping either a character, system or type id
+    pub id:       u64,
+    pub quantity: u64,
+    pub value:    f32,
+}
@@ -1,30 +1,54 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::error::EveServerError;
 
 use cachem::v2::ConnectionPool;
 use caph_db_v2::CacheName;
-use caph_eve_data_wrapper::TypeId;
+use caph_eve_data_wrapper::{EveDataWrapper, ItemId, UniverseNameService};
+use serde::Serialize;
+use tokio::sync::RwLock;
 
 #[derive(Clone)]
-pub struct NameService(ConnectionPool);
+pub struct NameService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+    /// Names that were resolved using ESI
+    resolved: Arc<RwLock<HashMap<u64, PublicName>>>,
+    /// Number of public requests per address in the current window
+    requests: Arc<RwLock<HashMap<IpAddr, (Instant, u32)>>>,
+}
 
 impl NameService {
-    pub fn new(pool: ConnectionPool) -> Self {
-        Self(pool)
+    /// Number of public requests a single address can make per window
+    const RATE_LIMIT:        u32      = 30;
+    const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+            resolved: Arc::new(RwLock::new(HashMap::new())),
+            requests: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
-    pub async fn resolve_id(&self, tid: TypeId) -> Result<Option<String>, EveServerError> {
-        self.0
+    pub async fn resolve_id(&self, iid: ItemId) -> Result<Option<String>, EveServerError> {
+        self.pool
             .acquire()
             .await?
-            .get::<_, _, String>(CacheName::Name, tid)
+            .get::<_, _, String>(CacheName::Name, iid)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn resolve_bulk(&self, ids: Vec<TypeId>) -> Result<Vec<String>, EveServerError> {
-        let res = self.0
+    pub async fn resolve_bulk(&self, ids: Vec<ItemId>) -> Result<Vec<String>, EveServerError> {
+        let res = self.pool
             .acquire()
             .await?
             .mget::<_, _, String>(CacheName::Name, ids)
@@ -39,17 +63,17 @@ impl NameService {
     pub async fn resolve_names_to_id_bulk(
         &self,
         names: Vec<String>
-    ) -> Result<HashMap<u32, String>, EveServerError> {
+    ) -> Result<HashMap<ItemId, String>, EveServerError> {
         let mut names = names;
         names.sort();
         names.dedup();
 
-        let mut pool = self.0
+        let mut pool = self.pool
             .acquire()
             .await?;
 
         let keys = pool
-            .keys::<_, u32>(CacheName::Name)
+            .keys::<_, ItemId>(CacheName::Name)
             .await?;
         let names = pool
             .mget::<_, _, String>(CacheName::Name, keys.clone())
@@ -64,4 +88,140 @@ impl NameService {
         dbg!(names.len());
         Ok(names)
     }
+
+    /// Resolves arbitrary ids to their name and category.
+    ///
+    /// Ids are first looked up in the database, everything else is resolved
+    /// using ESI and kept in memory. Intended for external tools, so every
+    /// address is rate limited.
+    ///
+    /// # Params
+    ///
+    /// `addr` -> Address of the requester, used for rate limiting
+    /// `ids`  -> Up to 1000 ids to resolve
+    ///
+    /// # Returns
+    ///
+    /// Name and category of every id that could be resolved
+    ///
+    pub async fn resolve_public(
+        &self,
+        addr: Option<IpAddr>,
+        ids:  Vec<u64>,
+    ) -> Result<Vec<PublicName>, EveServerError> {
+        if ids.len() > UniverseNameService::MAX_IDS {
+            return Err(EveServerError::TooManyIds);
+        }
+        if let Some(addr) = addr {
+            self.rate_limit(addr).await?;
+        }
+
+        let mut ids = ids;
+        ids.sort();
+        ids.dedup();
+
+        let mut result = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        {
+            let resolved = self.resolved.read().await;
+            for id in ids {
+                if let Some(x) = resolved.get(&id) {
+                    result.push(x.clone());
+                } else {
+                    missing.push(id);
+                }
+            }
+        }
+
+        // Everything from the SDE is in the name cache
+        let ids = missing
+            .iter()
+            .map(|x| ItemId(*x))
+            .collect::<Vec<_>>();
+        let cached = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, String>(CacheName::Name, ids)
+            .await?
+            .into_iter()
+            .zip(missing.iter())
+            .filter_map(|(name, id)| Some((*id, name?)))
+            .collect::<HashMap<_, _>>();
+        for (id, name) in cached.iter() {
+            result.push(PublicName {
+                id:       *id,
+                name:     name.clone(),
+                category: Self::category(*id).into(),
+            });
+        }
+        let mut missing = missing
+            .into_iter()
+            .filter(|x| !cached.contains_key(x))
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            let names = self
+                .eve_data
+                .universe_names()
+                .await?
+                .resolve(&missing)
+                .await
+                .unwrap_or_default();
+
+            let mut resolved = self.resolved.write().await;
+            for name in names {
+                let name = PublicName {
+                    id:       name.id,
+                    name:     name.name,
+                    category: name.category,
+                };
+                missing.retain(|x| *x != name.id);
+                resolved.insert(name.id, name.clone());
+                result.push(name);
+            }
+        }
+
+        if !missing.is_empty() {
+            log::warn!("Could not resolve {} ids", missing.len());
+        }
+
+        result.sort_by_key(|x| x.id);
+        Ok(result)
+    }
+
+    async fn rate_limit(&self, addr: IpAddr) -> Result<(), EveServerError> {
+        let mut requests = self.requests.write().await;
+        requests.retain(|_, (start, _)| start.elapsed() < Self::RATE_LIMIT_WINDOW);
+
+        let (_, count) = requests
+            .entry(addr)
+            .or_insert_with(|| (Instant::now(), 0));
+        if *count >= Self::RATE_LIMIT {
+            return Err(EveServerError::RateLimited);
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    /// Guesses the category of an id from the database based on its range
+    fn category(id: u64) -> &'static str {
+        match id {
+            500_000..=599_999       => "faction",
+            10_000_000..=19_999_999 => "region",
+            20_000_000..=29_999_999 => "constellation",
+            30_000_000..=39_999_999 => "solar_system",
+            60_000_000..=69_999_999 => "station",
+            _                       => "inventory_type",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PublicName {
+    pub id:       u64,
+    pub name:     String,
+    /// alliance, character, constellation, corporation, inventory_type,
+    /// region, solar_system, station or faction
+    pub category: String,
 }
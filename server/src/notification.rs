@@ -0,0 +1,187 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CalendarEventEntry, CharacterNotificationEntry};
+use caph_eve_data_wrapper::CharacterId;
+use serde::{Deserialize, Serialize};
+
+/// Service for the notifications and calendar events of characters
+#[derive(Clone)]
+pub struct NotificationService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+}
+
+impl NotificationService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+        }
+    }
+
+    /// Gets all notifications of the character and its alts, newest first
+    pub async fn notifications(
+        &self,
+        token: &str,
+    ) -> Result<Vec<CharacterNotificationEntry>, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut notifications = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, Vec<CharacterNotificationEntry>>(CacheName::CharacterNotification, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
+        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(notifications)
+    }
+
+    /// Marks the given notifications as read.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `ids`   -> Notification ids, ids of other users are ignored
+    ///
+    pub async fn mark_read(
+        &self,
+        token: &str,
+        ids:   Vec<u64>,
+    ) -> Result<(), EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        for user_id in user_ids {
+            let entries = con
+                .get::<_, _, Vec<CharacterNotificationEntry>>(CacheName::CharacterNotification, user_id)
+                .await?;
+            let mut entries = if let Some(x) = entries {
+                x
+            } else {
+                continue;
+            };
+
+            let mut changed = false;
+            entries
+                .iter_mut()
+                .filter(|x| !x.is_read && ids.contains(&x.notification_id))
+                .for_each(|x| {
+                    x.is_read = true;
+                    changed = true;
+                });
+
+            if changed {
+                con.set(CacheName::CharacterNotification, user_id, entries).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets all upcoming calendar events of the character and its alts,
+    /// ordered by date
+    pub async fn calendar(
+        &self,
+        token: &str,
+    ) -> Result<Vec<CalendarEventEntry>, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut events = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, Vec<CalendarEventEntry>>(CacheName::CharacterCalendar, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
+        events.sort_by_key(|x| x.date);
+        Ok(events)
+    }
+
+    /// Gets the configured webhook of the main
+    pub async fn webhook(
+        &self,
+        token: &str,
+    ) -> Result<Webhook, EveServerError> {
+        let user_id = self.main_id(token).await?;
+
+        let url = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, String>(CacheName::CharacterWebhook, user_id)
+            .await?;
+        Ok(Webhook { url })
+    }
+
+    /// Sets the webhook that structure attack and fuel notifications of the
+    /// main and all its alts are sent to.
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting main
+    /// `webhook` -> Discord compatible webhook, if the url is not set, the
+    ///              webhook is removed
+    ///
+    pub async fn set_webhook(
+        &self,
+        token:   &str,
+        webhook: Webhook,
+    ) -> Result<(), EveServerError> {
+        let user_id = self.main_id(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        if let Some(url) = webhook.url {
+            if !url.starts_with("https://") {
+                return Err(EveServerError::InvalidWebhook);
+            }
+
+            con.set(CacheName::CharacterWebhook, user_id, url).await?;
+        } else {
+            con.del(CacheName::CharacterWebhook, user_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn main_id(&self, token: &str) -> Result<CharacterId, EveServerError> {
+        self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .map(|x| x.user_id)
+            .ok_or(EveServerError::InvalidUser)
+    }
+
+    async fn user_ids(&self, token: &str) -> Result<Vec<CharacterId>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+        Ok(user_ids)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Webhook {
+    pub url: Option<String>,
+}
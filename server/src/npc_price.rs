@@ -0,0 +1,68 @@
+use caph_eve_data_wrapper::{CategoryId, GroupId, TypeIdEntry};
+use serde::Serialize;
+
+/// Items that are not priced by players.
+///
+/// NPC seeded items are sold by NPC sell orders and fixed value items are
+/// bought by NPC buy orders, both at the base price of the item. Player
+/// orders for these items are rare and often far off, so the market price
+/// is not a good value for them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NpcPrice {
+    /// Skillbooks and blueprints that are sold at NPC stations
+    Seeded,
+    /// Loot that NPCs buy for a fixed price, like blue loot
+    FixedValue,
+}
+
+impl NpcPrice {
+    /// Blueprints
+    const CATEGORY_BLUEPRINT:       u32 = 9;
+    /// Skillbooks
+    const CATEGORY_SKILL:           u32 = 16;
+    /// Overseer's Personal Effects dropped in DED sites
+    const GROUP_OVERSEER_EFFECTS:   u32 = 493;
+    /// Blue loot dropped by sleepers in wormholes
+    const GROUP_SLEEPER_COMPONENTS: u32 = 880;
+
+    /// Checks if an item has a NPC price.
+    ///
+    /// Only blueprints that are listed on the market and have no meta group
+    /// are seeded, tech 2 and faction blueprints are not.
+    ///
+    /// # Params
+    ///
+    /// `category_id` -> Category of the item
+    /// `group_id`    -> Group of the item
+    /// `entry`       -> SDE entry of the item
+    ///
+    /// # Returns
+    ///
+    /// The kind of NPC price, `None` if the item is priced by players
+    ///
+    pub fn of(
+        category_id: CategoryId,
+        group_id:    GroupId,
+        entry:       &TypeIdEntry,
+    ) -> Option<Self> {
+        if !entry.published || entry.base_price.unwrap_or_default() <= 0f64 {
+            return None;
+        }
+
+        match (*category_id, *group_id) {
+            (_, Self::GROUP_OVERSEER_EFFECTS)   |
+            (_, Self::GROUP_SLEEPER_COMPONENTS) => Some(Self::FixedValue),
+            (Self::CATEGORY_SKILL, _)           => Some(Self::Seeded),
+            (Self::CATEGORY_BLUEPRINT, _)
+                if entry.market_group_id.is_some() &&
+                   entry.meta_group_id.is_none()  => Some(Self::Seeded),
+            _                                    => None,
+        }
+    }
+
+    /// Price of a single item, the base price from the SDE
+    pub fn price(entry: &TypeIdEntry) -> f32 {
+        entry.base_price.unwrap_or_default() as f32
+    }
+}
@@ -0,0 +1,604 @@
+use crate::asset::{AssetQuery, AssetStack};
+use crate::audit::AuditQuery;
+use crate::character::{AssetCostBasis, AssetVolume, AssetWorth, BlueprintReport, BlueprintStack, Character, CharacterContract, CharacterSync, ContractQuery, HaulingQuery, NetWorthQuery, PlanetColony, WhoAmI};
+use crate::eve::LoginQuery;
+use crate::export::{ExportQuery, MarketExportQuery};
+use crate::list::ListQuery;
+use crate::market::{MarketOrder, MarketVenue, ShoppingList, ShoppingMaterial, StructureFee, UndercutStats, VenueQuery};
+use crate::pricing::PriceQuery;
+use crate::scope::ScopePreset;
+use crate::stock::StockQuery;
+
+use caph_db_v2::{CharacterAssetEntry, CharacterBlueprintEntry, CharacterCloneEntry, StructureFeeEntry};
+use caph_eve_data_wrapper::ItemLocation;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// Generates a schema for the type, the schema references the components
+/// of the document
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// Builds the OpenAPI document of all routes that are served under
+/// `/api/openapi.json`.
+///
+/// Request and response bodies of the character and market routes are
+/// generated from their structs, all other routes only document the path,
+/// the parameters and if a token is required.
+///
+pub fn document() -> Value {
+    let mut api = OpenApi::new();
+
+    api.add(Operation::get("/api/openapi.json", "meta", "This document"));
+
+    api.add(Operation::get("/api/admin/roles", "admin", "Roles of all users with more than the member role").auth());
+    api.add(Operation::put("/api/admin/roles/{user_id}", "admin", "Sets the roles of a main").auth().json_body());
+    api.add(
+        Operation::get("/api/admin/audit", "admin", "Audit log filtered by main and time range")
+            .auth()
+            .query::<AuditQuery>()
+    );
+    api.add(Operation::get("/api/admin/users", "admin", "All registered mains with their alts and last login").auth());
+    api.add(Operation::delete("/api/admin/users/{user_id}", "admin", "Deletes a main, its alts and all their data").auth());
+    api.add(Operation::get("/api/admin/users/{user_id}/tokens", "admin", "Token status of a main and its alts").auth());
+    api.add(Operation::post("/api/admin/users/{user_id}/tokens/refresh", "admin", "Refreshes the token of a main or alt").auth());
+    api.add(Operation::get("/api/admin/users/{user_id}/sync", "admin", "Last sync of the data of a main and its alts").auth());
+
+    api.add(Operation::get("/api/alliance/{alliance_id}", "alliance", "Report about the alliance").auth());
+
+    api.add(
+        Operation::post("/api/appraisal", "appraisal", "Values pasted items with the market price of the selected price source")
+            .query::<PriceQuery>()
+            .json_body()
+    );
+
+    api.add(Operation::get("/api/blueprint", "blueprint", "All blueprints"));
+    api.add(Operation::get("/api/blueprint/{type_id}", "blueprint", "Single blueprint"));
+    api.add(Operation::get("/api/blueprint/{type_id}/history", "blueprint", "Price history of the product"));
+    api.add(Operation::get("/api/blueprint/{type_id}/capital", "blueprint", "Cost to build a capital"));
+    api.add(Operation::get("/api/blueprint/{type_id}/plan", "blueprint", "Cheapest plan to build the product with the owned blueprints").auth());
+    api.add(Operation::get("/api/blueprint/reactions", "blueprint", "All reactions"));
+    api.add(Operation::get("/api/blueprint/reactions/{type_id}", "blueprint", "Reaction chain of the product"));
+
+    api.add(
+        Operation::get("/api/character/assets", "character", "Filtered assets of the main and its alts")
+            .auth()
+            .query::<AssetQuery>()
+            .list()
+            .response::<AssetStack<CharacterAssetEntry>>()
+    );
+    api.add(
+        Operation::get("/api/character/assets/cost", "character", "Cost basis of all asset stacks")
+            .auth()
+            .response::<Vec<AssetCostBasis>>()
+    );
+    api.add(Operation::get("/api/character/assets/reprocess", "character", "Reprocessing value of the assets").auth());
+    api.add(
+        Operation::get("/api/character/assets/worth", "character", "Worth of all assets")
+            .auth()
+            .query::<PriceQuery>()
+            .response::<AssetWorth>()
+    );
+    api.add(
+        Operation::get("/api/character/assets/volume", "character", "Volume of the assets per location")
+            .auth()
+            .query::<HaulingQuery>()
+            .response::<Vec<AssetVolume>>()
+    );
+    api.add(
+        Operation::get("/api/character/blueprints", "character", "Blueprints of the main and its alts")
+            .auth()
+            .list()
+            .response::<CharacterBlueprintEntry>()
+    );
+    api.add(
+        Operation::get("/api/character/blueprints/stacks", "character", "Identical blueprints grouped and valued")
+            .auth()
+            .response::<Vec<BlueprintStack>>()
+    );
+    api.add(
+        Operation::get("/api/character/blueprints/report", "character", "Owned blueprints joined with the SDE")
+            .auth()
+            .response::<Vec<BlueprintReport>>()
+    );
+    api.add(Operation::get("/api/character/calendar", "character", "Upcoming calendar events").auth());
+    api.add(
+        Operation::get("/api/character/clones", "character", "Clones of the main and its alts")
+            .auth()
+            .response::<Vec<CharacterCloneEntry>>()
+    );
+    api.add(
+        Operation::get("/api/character/contracts", "character", "Contracts of the main and its alts")
+            .auth()
+            .query::<ContractQuery>()
+            .list()
+            .response::<CharacterContract>()
+    );
+    api.add(
+        Operation::get("/api/character/info", "character", "Main together with its alts")
+            .auth()
+            .response::<Character>()
+    );
+    api.add(
+        Operation::get("/api/character/location/{item_id}", "character", "Location of an item")
+            .auth()
+            .response::<Option<ItemLocation>>()
+    );
+    api.add(Operation::get("/api/character/mining", "character", "Mining ledger").auth());
+    api.add(Operation::get("/api/character/notifications", "character", "Notifications of the main and its alts").auth());
+    api.add(Operation::post("/api/character/notifications/read", "character", "Marks notifications as read").auth().body::<Vec<u64>>());
+    api.add(Operation::get("/api/character/notifications/webhook", "character", "Configured webhook").auth());
+    api.add(Operation::post("/api/character/notifications/webhook", "character", "Sets the webhook").auth().json_body());
+    api.add(
+        Operation::get("/api/character/planets", "character", "Planetary colonies")
+            .auth()
+            .response::<Vec<PlanetColony>>()
+    );
+    api.add(
+        Operation::get("/api/character/networth", "character", "Daily net worth snapshots of the character and its alts or of a workspace")
+            .auth()
+            .query::<NetWorthQuery>()
+    );
+    api.add(Operation::get("/api/character/preferences", "character", "Preferences of the main").auth());
+    api.add(Operation::post("/api/character/preferences", "character", "Sets the preferences of the main").auth().json_body());
+    api.add(Operation::get("/api/character/skillfarm", "character", "Skill farm overview").auth());
+    api.add(Operation::post("/api/character/skillfarm/{user_id}/omega", "character", "Sets the omega expiry of a character").auth().body::<u64>());
+    api.add(Operation::get("/api/character/skills/history", "character", "Skill point history").auth());
+    api.add(Operation::get("/api/character/skills/requirements/{type_id}", "character", "Checks the required skills of a type").auth());
+    api.add(
+        Operation::get("/api/character/sync", "character", "Last and next sync of every dataset")
+            .auth()
+            .response::<Vec<CharacterSync>>()
+    );
+
+    api.add(Operation::get("/api/compression/ores", "compression", "Compressible ores"));
+    api.add(Operation::post("/api/compression/plan", "compression", "Cheapest compression plan").json_body());
+
+    api.add(Operation::get("/api/contracts/search", "contract", "Searches public contracts").list());
+    api.add(Operation::get("/api/contracts/snipes", "contract", "Contracts below market value"));
+    api.add(Operation::get("/api/contracts/snipes/ws", "contract", "Websocket with new snipes"));
+
+    api.add(
+        Operation::get("/api/corporation/{corporation_id}/assets", "corporation", "Filtered assets of the corporation")
+            .auth()
+            .query::<AssetQuery>()
+            .list()
+    );
+    api.add(
+        Operation::get("/api/corporation/{corporation_id}/blueprints", "corporation", "Blueprints of the corporation")
+            .auth()
+            .list()
+    );
+    api.add(Operation::post("/api/corporation/{corporation_id}/blueprints", "corporation", "Sets the blueprints of the corporation").auth().json_body());
+    api.add(Operation::delete("/api/corporation/{corporation_id}/blueprints", "corporation", "Deletes the blueprints of the corporation").auth());
+    api.add(Operation::get("/api/corporation/{corporation_id}/mining", "corporation", "Mining observers").auth());
+
+    api.add(Operation::get("/api/courier/price/{origin}/{destination}", "courier", "Price for a courier contract"));
+    api.add(Operation::post("/api/courier/plan/{origin}/{destination}", "courier", "Splits items into loads for hauling ships and prices them as courier contracts").json_body());
+
+    api.add(Operation::get("/api/eve/auth", "eve", "Callback of the EVE SSO"));
+    api.add(Operation::get("/api/eve/login", "eve", "Redirects to the EVE SSO").query::<LoginQuery>());
+    api.add(Operation::get("/api/eve/login/alt", "eve", "Redirects to the EVE SSO to add an alt").auth().query::<LoginQuery>());
+    api.add(
+        Operation::get("/api/eve/whoami", "eve", "Requesting main")
+            .auth()
+            .response::<WhoAmI>()
+    );
+    api.add(Operation::get("/api/eve/roles", "eve", "Roles of the requesting main").auth());
+    api.add(Operation::get("/api/eve/features", "eve", "Scope presets with the characters that granted them").auth());
+    api.add(Operation::post("/api/eve/logout", "eve", "Ends the session").auth());
+    api.add(Operation::get("/api/eve/sessions", "eve", "Sessions and api tokens").auth());
+    api.add(Operation::post("/api/eve/sessions", "eve", "Creates an api token").auth().json_body());
+    api.add(Operation::delete("/api/eve/sessions/{session_id}", "eve", "Revokes a session").auth());
+    api.add(Operation::post("/api/eve/deauthorize/{user_id}", "eve", "Revokes the eve token of the main or an alt, for the main all sessions are ended").auth());
+    api.add(Operation::delete("/api/eve/account", "eve", "Deletes the main, its alts and all their data").auth());
+
+    api.add(Operation::get("/api/events", "event", "Websocket with character events").auth());
+
+    api.add(
+        Operation::get("/api/export/assets", "export", "Assets of the main and its alts as csv")
+            .auth()
+            .query::<ExportQuery>()
+    );
+    api.add(
+        Operation::get("/api/export/blueprints", "export", "Blueprints of the main and its alts as csv")
+            .auth()
+            .query::<ExportQuery>()
+    );
+    api.add(
+        Operation::get("/api/export/market/{type_id}/venues", "export", "Proceeds at the trade hubs and structures as csv")
+            .query::<MarketExportQuery>()
+    );
+
+    api.add(Operation::get("/api/fittings", "fitting", "Fittings of the main").auth());
+    api.add(Operation::post("/api/fittings/eft", "fitting", "Imports a fitting in EFT format").auth().json_body());
+    api.add(Operation::get("/api/fittings/{fitting_id}/eft", "fitting", "Exports a fitting in EFT format").auth());
+    api.add(Operation::get("/api/fittings/esi/{esi_fitting_id}/eft", "fitting", "Exports an ingame fitting in EFT format").auth());
+    api.add(Operation::post("/api/fittings/doctrine", "fitting", "Checks which characters can fly a doctrine").auth().json_body());
+    api.add(Operation::delete("/api/fittings/{fitting_id}", "fitting", "Deletes a fitting").auth());
+
+    api.add(Operation::post("/api/graphql", "graphql", "GraphQL query over characters, assets, blueprints, items and market data").auth().json_body());
+
+    api.add(Operation::get("/health/live", "health", "Responds as long as the server is running"));
+    api.add(Operation::get("/health/ready", "health", "Checks the database, ESI and the caches, 503 if not ready"));
+
+    api.add(Operation::get("/esi/{path}", "esi", "Cached response of a public universe, market or status route of ESI, only if the proxy is enabled"));
+
+    api.add(Operation::get("/api/incursions", "incursion", "Active incursions"));
+
+    api.add(Operation::get("/api/industry/jobs", "industry", "Industry jobs of the main and its alts").auth());
+    api.add(Operation::post("/api/industry/schedule", "industry", "Schedules production jobs over the slots of the main and its alts").auth().json_body());
+    api.add(Operation::get("/api/industry/stations", "industry", "Stations with industry services"));
+
+    api.add(Operation::get("/api/items", "item", "All items"));
+    api.add(Operation::get("/api/items/keys", "item", "Type ids of all items"));
+    api.add(Operation::get("/api/items/{type_id}/meta", "item", "Meta variants of an item"));
+
+    api.add(Operation::get("/api/loyalty/stores", "loyalty", "NPC corporations with a LP store"));
+    api.add(Operation::get("/api/loyalty/stores/{corporation_id}", "loyalty", "ISK per LP of every offer of a LP store"));
+
+    api.add(
+        Operation::get("/api/market/{type_id}/undercut", "market", "Undercut statistics of an item")
+            .response::<Option<UndercutStats>>()
+    );
+    api.add(
+        Operation::get("/api/market/{type_id}/orders", "market", "Open orders of an item")
+            .list()
+            .response::<MarketOrder>()
+    );
+    api.add(
+        Operation::get("/api/market/{type_id}/venues", "market", "Proceeds at the trade hubs and structures")
+            .query::<VenueQuery>()
+            .response::<Vec<MarketVenue>>()
+    );
+    api.add(Operation::get("/api/market/alerts", "market", "Price alerts of the main with their triggers").auth());
+    api.add(Operation::post("/api/market/alerts", "market", "Creates a price alert").auth().json_body());
+    api.add(Operation::delete("/api/market/alerts/{price_alert_id}", "market", "Deletes a price alert").auth());
+    api.add(
+        Operation::post("/api/market/shopping", "market", "Shopping list split by trade hub")
+            .body::<Vec<ShoppingMaterial>>()
+            .response::<ShoppingList>()
+    );
+    api.add(
+        Operation::get("/api/market/structures", "market", "Broker fees of structures")
+            .response::<Vec<StructureFeeEntry>>()
+    );
+    api.add(
+        Operation::post("/api/market/structures/{structure_id}", "market", "Sets the broker fee of a structure")
+            .auth()
+            .body::<StructureFee>()
+    );
+    api.add(Operation::delete("/api/market/structures/{structure_id}", "market", "Deletes the broker fee of a structure").auth());
+
+    api.add(Operation::get("/api/name/resolve/{item_id}", "name", "Name of an id"));
+    api.add(Operation::post("/api/name/resolve/bulk", "name", "Names of multiple ids").json_body());
+    api.add(Operation::post("/api/name/resolve/bulk/id", "name", "Ids of multiple names").json_body());
+    api.add(Operation::post("/api/name/public", "name", "Resolves public names").json_body());
+
+    api.add(Operation::get("/api/projects", "project", "Projects of the main").auth());
+    api.add(Operation::post("/api/projects", "project", "Creates a project").auth().json_body());
+    api.add(Operation::get("/api/projects/{project_id}", "project", "Single project").auth());
+    api.add(Operation::delete("/api/projects/{project_id}", "project", "Deletes a project").auth());
+    api.add(Operation::get("/api/projects/{project_id}/cost", "project", "Cost of the project").auth());
+    api.add(
+        Operation::get("/api/projects/{project_id}/shopping", "project", "Shopping list of the missing raw materials")
+            .auth()
+            .response::<ShoppingList>()
+    );
+    api.add(Operation::get("/api/projects/{project_id}/materials", "project", "Required materials").auth());
+    api.add(Operation::get("/api/projects/{project_id}/materials/raw", "project", "Required raw materials").auth());
+    api.add(Operation::get("/api/projects/{project_id}/materials/stored", "project", "Stored materials").auth());
+    api.add(Operation::get("/api/projects/{project_id}/blueprints", "project", "Required blueprints").auth());
+    api.add(Operation::get("/api/projects/{project_id}/tree", "project", "Production tree").auth());
+    api.add(Operation::get("/api/projects/{project_id}/products", "project", "Required intermediate products").auth());
+
+    api.add(Operation::get("/api/public", "public", "Published caches"));
+    api.add(Operation::get("/api/public/{cache}", "public", "All entries of a published cache"));
+    api.add(Operation::get("/api/public/{cache}/{id}", "public", "Single entry of a published cache"));
+
+    api.add(
+        Operation::get("/api/stock", "stock", "Stock rules of the main or a workspace with the stock of the last check and its value")
+            .auth()
+            .query::<StockQuery>()
+    );
+    api.add(Operation::post("/api/stock", "stock", "Creates a stock rule").auth().json_body());
+    api.add(Operation::delete("/api/stock/{stock_rule_id}", "stock", "Deletes a stock rule").auth());
+
+    api.add(Operation::get("/api/workspaces", "workspace", "Workspaces the main is a member of").auth());
+    api.add(Operation::post("/api/workspaces", "workspace", "Creates a workspace for a corporation or a group of mains").auth().json_body());
+    api.add(Operation::put("/api/workspaces/{workspace_id}", "workspace", "Changes the name and members of a workspace").auth().json_body());
+    api.add(Operation::delete("/api/workspaces/{workspace_id}", "workspace", "Deletes a workspace, its stock rules and net worth snapshots").auth());
+
+    api.add(Operation::get("/api/universe/route/{origin}/{destination}", "universe", "Route between two systems"));
+    api.add(Operation::get("/api/universe/route/{origin}/{destination}/kills", "universe", "Recent kills along a route"));
+    api.add(Operation::get("/api/universe/distance/{origin}/{destination}", "universe", "Distance between two systems"));
+    api.add(Operation::get("/api/universe/jump/{system_id}", "universe", "Systems in jump range"));
+    api.add(Operation::get("/api/universe/agents/{system_id}", "universe", "Nearest agents of a corporation"));
+    api.add(Operation::get("/api/universe/sovereignty", "universe", "Sovereignty of all systems"));
+    api.add(Operation::get("/api/universe/npc/damage", "universe", "Damage profiles of all NPC factions"));
+    api.add(Operation::get("/api/universe/npc/damage/{system_id}", "universe", "Damage profile of the NPCs in a system"));
+
+    api.document()
+}
+
+/// Collects the operations and the schemas they use
+struct OpenApi {
+    gen:   SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl OpenApi {
+    fn new() -> Self {
+        Self {
+            gen:   SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    fn add(&mut self, op: Operation) {
+        let mut parameters = Self::path_params(op.path);
+        if let Some(query) = op.query {
+            parameters.extend(self.query_params(query));
+        }
+        if op.list {
+            parameters.extend(self.query_params(SchemaGenerator::subschema_for::<ListQuery>));
+        }
+
+        let mut operation = json!({
+            "tags":      [op.tag],
+            "summary":   op.summary,
+            "responses": {
+                "200": { "description": "Success" }
+            }
+        });
+        if !parameters.is_empty() {
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if op.auth {
+            operation["security"] = json!([{ "cookie": [] }, { "bearer": [] }]);
+            operation["responses"]["401"] = json!({ "description": "Missing or invalid token" });
+        }
+        // routes without the scopes return no data, clients should disable them
+        if let Some(preset) = ScopePreset::of_route(op.path) {
+            operation["x-scope-preset"] = json!(preset);
+        }
+        if let Some(body) = op.body {
+            let schema = body
+                .map(|x| self.schema(x))
+                .unwrap_or_else(|| json!({ "type": "object" }));
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } }
+            });
+        }
+        if let Some(response) = op.response {
+            let mut schema = self.schema(response);
+            if op.list {
+                schema = Self::list_page(schema);
+            }
+            operation["responses"]["200"]["content"] = json!({
+                "application/json": { "schema": schema }
+            });
+        }
+
+        let path = self
+            .paths
+            .entry(op.path)
+            .or_insert_with(|| json!({}));
+        path[op.method] = operation;
+    }
+
+    fn document(self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title":   "Caph",
+                "version": env!("CARGO_PKG_VERSION")
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": self.gen.definitions(),
+                "securitySchemes": {
+                    "cookie": { "type": "apiKey", "in": "cookie", "name": "token" },
+                    "bearer": { "type": "http", "scheme": "bearer" }
+                }
+            }
+        })
+    }
+
+    fn schema(&mut self, schema: SchemaFn) -> Value {
+        serde_json::to_value(schema(&mut self.gen)).unwrap_or_default()
+    }
+
+    /// Every field of the query struct is a single parameter
+    fn query_params(&mut self, query: SchemaFn) -> Vec<Value> {
+        let schema = match query(&mut self.gen) {
+            Schema::Object(x) => x,
+            Schema::Bool(_)   => return Vec::new(),
+        };
+        // structs are added to the components and referenced
+        let schema = match schema.reference.clone() {
+            Some(x) => {
+                let name = x.trim_start_matches("#/components/schemas/");
+                match self.gen.definitions().get(name) {
+                    Some(Schema::Object(x)) => x.clone(),
+                    _                       => return Vec::new(),
+                }
+            },
+            None    => schema,
+        };
+        let object = match schema.object {
+            Some(x) => x,
+            None    => return Vec::new(),
+        };
+
+        object
+            .properties
+            .iter()
+            .map(|(name, schema)| json!({
+                "name":     name,
+                "in":       "query",
+                "required": object.required.contains(name),
+                "schema":   schema
+            }))
+            .collect::<Vec<_>>()
+    }
+
+    /// Schema of a [crate::list::ListPage] with the given entries
+    fn list_page(entry: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "entries": { "type": "array", "items": entry },
+                "total":   { "type": "integer" },
+                "limit":   { "type": "integer" },
+                "offset":  { "type": "integer" }
+            }
+        })
+    }
+
+    /// Parameters are written as `{name}` in the path
+    fn path_params(path: &str) -> Vec<Value> {
+        path
+            .split('/')
+            .filter(|x| x.starts_with('{') && x.ends_with('}'))
+            .map(|x| x.trim_matches(|c| c == '{' || c == '}'))
+            .map(|name| {
+                let schema = match name {
+                    "fitting_id"     |
+                    "price_alert_id" |
+                    "project_id"     |
+                    "stock_rule_id"  |
+                    "workspace_id"      => json!({ "type": "string", "format": "uuid" }),
+                    "cache"          |
+                    "path"           |
+                    "session_id"        => json!({ "type": "string" }),
+                    _                   => json!({ "type": "integer", "format": "int64" }),
+                };
+                json!({
+                    "name":     name,
+                    "in":       "path",
+                    "required": true,
+                    "schema":   schema
+                })
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+/// Single route
+struct Operation {
+    method:   &'static str,
+    path:     &'static str,
+    tag:      &'static str,
+    summary:  &'static str,
+    /// Requires the token cookie or a bearer token
+    auth:     bool,
+    query:    Option<SchemaFn>,
+    /// Takes a [ListQuery] and returns a page of the response
+    list:     bool,
+    /// [None] if the route takes a json body that is not documented
+    body:     Option<Option<SchemaFn>>,
+    response: Option<SchemaFn>,
+}
+
+impl Operation {
+    fn new(
+        method:  &'static str,
+        path:    &'static str,
+        tag:     &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            tag,
+            summary,
+            auth:     false,
+            query:    None,
+            list:     false,
+            body:     None,
+            response: None,
+        }
+    }
+
+    fn get(path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        Self::new("get", path, tag, summary)
+    }
+
+    fn post(path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        Self::new("post", path, tag, summary)
+    }
+
+    fn put(path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        Self::new("put", path, tag, summary)
+    }
+
+    fn delete(path: &'static str, tag: &'static str, summary: &'static str) -> Self {
+        Self::new("delete", path, tag, summary)
+    }
+
+    fn auth(mut self) -> Self {
+        self.auth = true;
+        self
+    }
+
+    fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+
+    fn list(mut self) -> Self {
+        self.list = true;
+        self
+    }
+
+    fn body<T: JsonSchema>(mut self) -> Self {
+        self.body = Some(Some(SchemaGenerator::subschema_for::<T>));
+        self
+    }
+
+    fn json_body(mut self) -> Self {
+        self.body = Some(None);
+        self
+    }
+
+    fn response<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+}
+
+#[cfg(test)]
+mod openapi_tests {
+    use super::*;
+
+    #[test]
+    fn path_params() {
+        let params = OpenApi::path_params("/api/projects/{project_id}/cost");
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0]["name"], "project_id");
+        assert_eq!(params[0]["schema"]["format"], "uuid");
+
+        assert!(OpenApi::path_params("/api/items").is_empty());
+    }
+
+    #[test]
+    fn document_contains_schemas() {
+        let document = document();
+        assert!(document["paths"]["/api/character/assets"]["get"].is_object());
+        assert!(document["components"]["schemas"]["CharacterBlueprintEntry"].is_object());
+        assert_eq!(document["paths"]["/api/character/assets"]["get"]["x-scope-preset"], "assets");
+
+        let params = &document["paths"]["/api/market/{type_id}/venues"]["get"]["parameters"];
+        assert_eq!(params.as_array().map(|x| x.len()), Some(4));
+
+        let blueprints = &document["paths"]["/api/character/blueprints"]["get"];
+        assert_eq!(blueprints["parameters"].as_array().map(|x| x.len()), Some(4));
+        assert_eq!(
+            blueprints["responses"]["200"]["content"]["application/json"]["schema"]["properties"]["entries"]["type"],
+            "array"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, UserPreferenceEntry};
+
+/// Service for the preferences of a user
+#[derive(Clone)]
+pub struct PreferenceService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+}
+
+impl PreferenceService {
+    /// The collector runs every 30 minutes, syncing more often is not
+    /// possible
+    const MIN_FREQUENCY:    u32 = 30;
+    /// Minimum days without login before the data is exported
+    const MIN_DEADMAN_DAYS: u32 = 7;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+        }
+    }
+
+    /// Gets the preferences of the main, if nothing is configured the
+    /// defaults are returned
+    pub async fn preferences(
+        &self,
+        token: &str,
+    ) -> Result<UserPreferenceEntry, EveServerError> {
+        let user_id = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?
+            .user_id;
+
+        let preference = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, UserPreferenceEntry>(CacheName::UserPreference, user_id)
+            .await?
+            .unwrap_or(UserPreferenceEntry {
+                user_id,
+                sync_start:      None,
+                sync_end:        None,
+                frequencies:     Vec::new(),
+                deadman_days:    None,
+                deadman_webhook: None,
+            });
+        Ok(preference)
+    }
+
+    /// Sets the preferences of the main.
+    ///
+    /// # Params
+    ///
+    /// `token`      -> Cookie from the requesting main
+    /// `preference` -> New preferences, the sync hours must be between 0 and
+    ///                 23, every frequency must be at least 30 minutes and
+    ///                 the dead man switch needs at least 7 days and a
+    ///                 https webhook
+    ///
+    pub async fn set_preferences(
+        &self,
+        token:      &str,
+        preference: UserPreferenceEntry,
+    ) -> Result<(), EveServerError> {
+        let user_id = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?
+            .user_id;
+
+        let valid_hours = [preference.sync_start, preference.sync_end]
+            .iter()
+            .flatten()
+            .all(|x| *x < 24);
+        let valid_frequencies = preference
+            .frequencies
+            .iter()
+            .all(|x| {
+                UserPreferenceEntry::SYNC_TYPES.contains(&x.typ.as_str()) &&
+                x.minutes >= Self::MIN_FREQUENCY
+            });
+        let valid_deadman = preference
+            .deadman_days
+            .map(|x| x >= Self::MIN_DEADMAN_DAYS)
+            .unwrap_or(true) &&
+            preference
+                .deadman_webhook
+                .as_ref()
+                .map(|x| x.starts_with("https://"))
+                .unwrap_or(true);
+        if !valid_hours || !valid_frequencies || !valid_deadman {
+            return Err(EveServerError::InvalidPreference);
+        }
+
+        let mut preference = preference;
+        preference.user_id = user_id;
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::UserPreference, user_id, preference)
+            .await
+            .map_err(Into::into)
+    }
+}
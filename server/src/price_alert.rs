@@ -0,0 +1,405 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::invalidation::InvalidationService;
+use crate::market::MarketService;
+use crate::webhook::Webhook;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, MarketInfoEntry, MarketPriceEntry, PriceAlertEntry, PriceAlertTriggerEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{CharacterId, ItemId, LocationId, RegionId, SolarSystemId, TypeId};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, Receiver};
+use uuid::Uuid;
+
+/// Service for price alerts of users.
+///
+/// The alerts are checked every time the market orders or prices are
+/// refreshed, but at least once per hour. Triggered alerts are stored, sent to the webhook of the main and to all
+/// connected websockets.
+#[derive(Clone)]
+pub struct PriceAlertService {
+    pool:         ConnectionPool,
+    eve_auth:     EveAuthService,
+    invalidation: InvalidationService,
+    market:       MarketService,
+    tx:           broadcast::Sender<PriceAlertEntry>,
+}
+
+impl PriceAlertService {
+    /// Number of triggered alerts that are buffered for every websocket
+    const CAPACITY:     usize = 256;
+    /// Maximum number of triggers that are kept per alert
+    const MAX_TRIGGERS: usize = 50;
+    /// Time after which the alerts are checked even if no market cache was
+    /// modified
+    const MAX_WAIT:     Duration = Duration::from_secs(60 * 60);
+
+    /// Creates a new instance
+    pub fn new(
+        pool:         ConnectionPool,
+        eve_auth:     EveAuthService,
+        invalidation: InvalidationService,
+        market:       MarketService,
+    ) -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        Self {
+            pool,
+            eve_auth,
+            invalidation,
+            market,
+            tx,
+        }
+    }
+
+    /// Checks all alerts every time the market orders or prices were
+    /// modified, or when nothing was modified for [Self::MAX_WAIT].
+    ///
+    /// This function is blocking
+    pub async fn watch(&self) {
+        let mut changes = self.invalidation.subscribe();
+
+        loop {
+            let caches = vec![CacheName::MarketOrder, CacheName::MarketPrice];
+            let _ = tokio::time::timeout(
+                Self::MAX_WAIT,
+                InvalidationService::wait(&mut changes, caches)
+            ).await;
+            if let Err(e) = self.check().await {
+                log::error!("Error checking price alerts {:?}", e);
+            }
+        }
+    }
+
+    /// Subscribes to all triggered alerts, the receiver is responsible for
+    /// filtering the alerts of its user
+    pub fn subscribe(&self) -> Receiver<PriceAlertEntry> {
+        self.tx.subscribe()
+    }
+
+    /// Gets all alerts of the main together with their triggers
+    pub async fn alerts(
+        &self,
+        token: &str,
+    ) -> Result<Vec<PriceAlertEntry>, EveServerError> {
+        let user_id = self.main_id(token).await?;
+
+        let alerts = self
+            .all()
+            .await?
+            .into_iter()
+            .filter(|x| x.user_id == user_id)
+            .collect::<Vec<_>>();
+        Ok(alerts)
+    }
+
+    /// Creates a new alert.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `alert` -> Item, market and threshold to watch
+    ///
+    /// # Returns
+    ///
+    /// The created alert, it is first checked after the next market refresh
+    ///
+    pub async fn create(
+        &self,
+        token: &str,
+        alert: PriceAlert,
+    ) -> Result<PriceAlertEntry, EveServerError> {
+        let user_id = self.main_id(token).await?;
+
+        let entry = PriceAlertEntry {
+            id:          Uuid::new_v4(),
+            user_id,
+            type_id:     alert.type_id,
+            region_id:   alert.region_id,
+            location_id: alert.location_id,
+            buy_order:   alert.buy_order,
+            above:       alert.above,
+            threshold:   alert.threshold,
+            active:      false,
+            triggers:    Vec::new(),
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::PriceAlert, entry.id, entry.clone())
+            .await?;
+        Ok(entry)
+    }
+
+    /// Deletes an alert of the main
+    pub async fn delete(
+        &self,
+        token: &str,
+        id:    Uuid,
+    ) -> Result<(), EveServerError> {
+        let user_id = self.main_id(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        con
+            .get::<_, _, PriceAlertEntry>(CacheName::PriceAlert, id)
+            .await?
+            .filter(|x| x.user_id == user_id)
+            .ok_or(EveServerError::PriceAlertNotFound)?;
+        con
+            .del(CacheName::PriceAlert, id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Compares all alerts with the current orders and triggers the ones
+    /// that crossed their threshold
+    async fn check(&self) -> Result<(), EveServerError> {
+        let mut by_type: HashMap<TypeId, Vec<PriceAlertEntry>> = HashMap::new();
+        self
+            .all()
+            .await?
+            .into_iter()
+            .for_each(|x| by_type.entry(x.type_id).or_default().push(x));
+        if by_type.is_empty() {
+            return Ok(());
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let system_ids = con
+            .keys::<_, SolarSystemId>(CacheName::SystemRegion)
+            .await?;
+        let regions = con
+            .mget::<_, _, SystemRegionEntry>(CacheName::SystemRegion, system_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.system_id, x.region_id))
+            .collect::<HashMap<_, _>>();
+
+        let type_ids = by_type.keys().copied().collect::<Vec<_>>();
+        let averages = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let mut changed = HashMap::new();
+        for (tid, alerts) in by_type {
+            let orders = self
+                .market
+                .latest_orders(tid)
+                .await?
+                .into_iter()
+                .map(|(x, _)| x)
+                .collect::<Vec<_>>();
+
+            for mut alert in alerts {
+                // Without any known order of the item, the average price is
+                // the best guess
+                let price = if orders.is_empty() {
+                    averages.get(&tid).copied()
+                } else {
+                    Self::best_price(&alert, &orders, &regions)
+                };
+                let price = match price {
+                    Some(x) => x,
+                    None    => continue,
+                };
+
+                let crossed = Self::crossed(&alert, price);
+                if crossed && !alert.active {
+                    alert.active = true;
+                    alert.triggers.insert(0, PriceAlertTriggerEntry {
+                        timestamp: now,
+                        price,
+                    });
+                    alert.triggers.truncate(Self::MAX_TRIGGERS);
+                    self.notify(&alert, price).await?;
+                    changed.insert(alert.id, alert);
+                } else if !crossed && alert.active {
+                    alert.active = false;
+                    changed.insert(alert.id, alert);
+                }
+            }
+        }
+
+        if !changed.is_empty() {
+            con.mset(CacheName::PriceAlert, changed).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the triggered alert to the websockets and the webhook of the
+    /// main
+    async fn notify(
+        &self,
+        alert: &PriceAlertEntry,
+        price: f32,
+    ) -> Result<(), EveServerError> {
+        // No websocket may be connected
+        let _ = self.tx.send(alert.clone());
+
+        let mut con = self.pool.acquire().await?;
+        let webhook = if let Some(x) = con
+            .get::<_, _, String>(CacheName::CharacterWebhook, alert.user_id)
+            .await? {
+            x
+        } else {
+            return Ok(());
+        };
+        let name = con
+            .get::<_, _, String>(CacheName::Name, ItemId::from(alert.type_id))
+            .await?
+            .unwrap_or_else(|| alert.type_id.to_string());
+
+        let message = format!(
+            "**Price alert**\n{} {} price is {} {} ({})",
+            name,
+            if alert.buy_order { "buy" } else { "sell" },
+            if alert.above { "above" } else { "below" },
+            alert.threshold,
+            price
+        );
+        Webhook::send(&webhook, &message).await;
+        Ok(())
+    }
+
+    /// Lowest sell or highest buy price of the orders that match the region
+    /// and location of the alert
+    fn best_price(
+        alert:   &PriceAlertEntry,
+        orders:  &[MarketInfoEntry],
+        regions: &HashMap<SolarSystemId, RegionId>,
+    ) -> Option<f32> {
+        let prices = orders
+            .iter()
+            .filter(|x| x.is_buy_order == alert.buy_order)
+            .filter(|x| alert.location_id.map(|y| x.location_id == y).unwrap_or(true))
+            .filter(|x| {
+                alert
+                    .region_id
+                    .map(|y| regions.get(&x.system_id) == Some(&y))
+                    .unwrap_or(true)
+            })
+            .map(|x| x.price);
+
+        if alert.buy_order {
+            prices.fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.max(x))))
+        } else {
+            prices.fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.min(x))))
+        }
+    }
+
+    /// Checks if the price is on the alerting side of the threshold
+    fn crossed(alert: &PriceAlertEntry, price: f32) -> bool {
+        if alert.above {
+            price > alert.threshold
+        } else {
+            price < alert.threshold
+        }
+    }
+
+    async fn all(&self) -> Result<Vec<PriceAlertEntry>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, Uuid>(CacheName::PriceAlert)
+            .await?;
+        let alerts = con
+            .mget::<_, _, PriceAlertEntry>(CacheName::PriceAlert, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        Ok(alerts)
+    }
+
+    async fn main_id(&self, token: &str) -> Result<CharacterId, EveServerError> {
+        self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .map(|x| x.user_id)
+            .ok_or(EveServerError::InvalidUser)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceAlert {
+    pub type_id:     TypeId,
+    /// Only compare orders in this region
+    pub region_id:   Option<RegionId>,
+    /// Only compare orders at this station or structure
+    pub location_id: Option<LocationId>,
+    /// Compare with the highest buy order instead of the lowest sell order
+    #[serde(default)]
+    pub buy_order:   bool,
+    /// Trigger when the price is above the threshold instead of below
+    #[serde(default)]
+    pub above:       bool,
+    pub threshold:   f32,
+}
+
+#[cfg(test)]
+mod price_alert_tests {
+    use super::*;
+    use caph_eve_data_wrapper::OrderId;
+
+    fn alert(buy_order: bool, above: bool) -> PriceAlertEntry {
+        PriceAlertEntry {
+            id:          Uuid::nil(),
+            user_id:     CharacterId(1),
+            type_id:     TypeId::from(34u32),
+            region_id:   Some(RegionId(10000002)),
+            location_id: None,
+            buy_order,
+            above,
+            threshold:   5f32,
+            active:      false,
+            triggers:    Vec::new(),
+        }
+    }
+
+    fn order(system_id: u32, price: f32, is_buy_order: bool) -> MarketInfoEntry {
+        MarketInfoEntry {
+            issued:       0,
+            expire:       0,
+            order_id:     OrderId(0),
+            location_id:  LocationId(60003760),
+            system_id:    SolarSystemId(system_id),
+            type_id:      TypeId::from(34u32),
+            volume_total: 1,
+            price,
+            is_buy_order,
+        }
+    }
+
+    #[test]
+    fn best_price() {
+        let regions = vec![
+            (SolarSystemId(30000142), RegionId(10000002)),
+            (SolarSystemId(30002187), RegionId(10000043)),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        let orders = vec![
+            order(30000142, 4f32, false),
+            order(30000142, 6f32, false),
+            order(30002187, 3f32, false),
+            order(30000142, 2f32, true),
+        ];
+
+        let sell = alert(false, false);
+        assert_eq!(PriceAlertService::best_price(&sell, &orders, &regions), Some(4f32));
+        assert!(PriceAlertService::crossed(&sell, 4f32));
+
+        let buy = alert(true, true);
+        assert_eq!(PriceAlertService::best_price(&buy, &orders, &regions), Some(2f32));
+        assert!(!PriceAlertService::crossed(&buy, 2f32));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::error::EveServerError;
+use crate::market::MarketService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, MarketInfoEntry, MarketPriceEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{LocationId, RegionId, SolarSystemId, TypeId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Strategies to value items, selectable per request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceStrategy {
+    /// Average price of ESI over all regions
+    Average,
+    /// Lowest sell order in Jita 4-4
+    JitaSellMin,
+    /// Highest buy order in Jita 4-4
+    JitaBuyMax,
+    /// Volume weighted average of the cheapest sell orders in Jita 4-4 that
+    /// make up 5% of the sell volume
+    JitaFivePercent,
+    /// Volume weighted average of all sell orders in a region
+    RegionAverage,
+    /// Lowest sell order at a station or structure
+    Station,
+}
+
+/// Query parameters that select the price source of a valuation
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema)]
+pub struct PriceQuery {
+    /// Defaults to the average price
+    pub price:       Option<PriceStrategy>,
+    /// Required for `region_average`
+    pub region_id:   Option<RegionId>,
+    /// Required for `station`
+    pub location_id: Option<LocationId>,
+}
+
+/// Resolved strategy together with its parameter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PriceSource {
+    Average,
+    JitaSellMin,
+    JitaBuyMax,
+    JitaFivePercent,
+    RegionAverage(RegionId),
+    Station(LocationId),
+}
+
+impl PriceSource {
+    /// Jita IV - Moon 4 - Caldari Navy Assembly Plant
+    const JITA_STATION: LocationId = LocationId(60003760);
+
+    /// Parses the format of [PriceSource::as_string], used to store the
+    /// source, for example together with a stock rule
+    pub fn parse(x: &str) -> Result<Self, EveServerError> {
+        let invalid = || EveServerError::InvalidPriceSource(x.into());
+        let (strategy, param) = match x.split_once(':') {
+            Some((strategy, param)) => (strategy, Some(param)),
+            None                    => (x, None),
+        };
+
+        match (strategy, param) {
+            ("average", None)                => Ok(Self::Average),
+            ("jita_sell_min", None)          => Ok(Self::JitaSellMin),
+            ("jita_buy_max", None)           => Ok(Self::JitaBuyMax),
+            ("jita_five_percent", None)      => Ok(Self::JitaFivePercent),
+            ("region_average", Some(param))  => {
+                param
+                    .parse::<u32>()
+                    .map(|x| Self::RegionAverage(x.into()))
+                    .map_err(|_| invalid())
+            },
+            ("station", Some(param))         => {
+                param
+                    .parse::<u64>()
+                    .map(|x| Self::Station(x.into()))
+                    .map_err(|_| invalid())
+            },
+            _                                => Err(invalid()),
+        }
+    }
+
+    /// Strategy with its parameter separated by `:`, for example
+    /// `region_average:10000002`
+    pub fn as_string(&self) -> String {
+        match self {
+            Self::Average          => "average".into(),
+            Self::JitaSellMin      => "jita_sell_min".into(),
+            Self::JitaBuyMax       => "jita_buy_max".into(),
+            Self::JitaFivePercent  => "jita_five_percent".into(),
+            Self::RegionAverage(x) => format!("region_average:{}", **x),
+            Self::Station(x)       => format!("station:{}", **x),
+        }
+    }
+
+    /// Price of a single item from the open orders, [None] if there is no
+    /// matching order
+    ///
+    /// # Params
+    ///
+    /// `orders`  -> Open orders with their remaining volume
+    /// `regions` -> Region of every system
+    ///
+    fn price(
+        &self,
+        orders:  &[(MarketInfoEntry, u32)],
+        regions: &HashMap<SolarSystemId, RegionId>,
+    ) -> Option<f32> {
+        let at = |location: LocationId, buy: bool| {
+            orders
+                .iter()
+                .filter(move |(x, _)| x.location_id == location && x.is_buy_order == buy)
+                .map(|(x, volume)| (x.price, *volume))
+        };
+
+        match self {
+            Self::Average            => None,
+            Self::JitaSellMin        => at(Self::JITA_STATION, false)
+                .map(|(x, _)| x)
+                .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.min(x)))),
+            Self::JitaBuyMax         => at(Self::JITA_STATION, true)
+                .map(|(x, _)| x)
+                .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.max(x)))),
+            Self::JitaFivePercent    => {
+                Self::five_percent(at(Self::JITA_STATION, false).collect())
+            },
+            Self::RegionAverage(rid) => {
+                let (value, volume) = orders
+                    .iter()
+                    .filter(|(x, _)| !x.is_buy_order)
+                    .filter(|(x, _)| regions.get(&x.system_id) == Some(rid))
+                    .fold((0f64, 0u64), |(value, volume), (x, v)| {
+                        (value + x.price as f64 * *v as f64, volume + *v as u64)
+                    });
+                if volume == 0 {
+                    None
+                } else {
+                    Some((value / volume as f64) as f32)
+                }
+            },
+            Self::Station(lid)       => at(*lid, false)
+                .map(|(x, _)| x)
+                .fold(None, |acc: Option<f32>, x| Some(acc.map_or(x, |y| y.min(x)))),
+        }
+    }
+
+    /// Volume weighted average of the cheapest orders that make up 5% of
+    /// the total volume
+    fn five_percent(mut orders: Vec<(f32, u32)>) -> Option<f32> {
+        orders.sort_by(|(a, _), (b, _)| {
+            a.partial_cmp(b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let total = orders.iter().map(|(_, x)| *x as u64).sum::<u64>();
+        // at least one item, so that small markets still have a price
+        let mut remaining = (total / 20).max(1);
+        let mut value = 0f64;
+        let mut volume = 0u64;
+        for (price, order_volume) in orders {
+            if remaining == 0 {
+                break;
+            }
+
+            let taken = remaining.min(order_volume as u64);
+            value += price as f64 * taken as f64;
+            volume += taken;
+            remaining -= taken;
+        }
+
+        if volume == 0 {
+            None
+        } else {
+            Some((value / volume as f64) as f32)
+        }
+    }
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        Self::Average
+    }
+}
+
+impl PriceQuery {
+    /// Resolves the strategy with its parameter
+    ///
+    /// # Returns
+    ///
+    /// [EveServerError::InvalidPriceSource] if the parameter of the strategy
+    /// is missing
+    ///
+    pub fn source(&self) -> Result<PriceSource, EveServerError> {
+        let missing = |x: &str| EveServerError::InvalidPriceSource(x.into());
+        match self.price.unwrap_or(PriceStrategy::Average) {
+            PriceStrategy::Average         => Ok(PriceSource::Average),
+            PriceStrategy::JitaSellMin     => Ok(PriceSource::JitaSellMin),
+            PriceStrategy::JitaBuyMax      => Ok(PriceSource::JitaBuyMax),
+            PriceStrategy::JitaFivePercent => Ok(PriceSource::JitaFivePercent),
+            PriceStrategy::RegionAverage   => self
+                .region_id
+                .map(PriceSource::RegionAverage)
+                .ok_or_else(|| missing("region_average requires region_id")),
+            PriceStrategy::Station         => self
+                .location_id
+                .map(PriceSource::Station)
+                .ok_or_else(|| missing("station requires location_id")),
+        }
+    }
+}
+
+/// Single place to get item prices, so that all valuations use the same
+/// source.
+///
+/// Items without an order for the selected strategy fall back to the
+/// average price, so that a valuation never misses items that are only
+/// traded elsewhere.
+#[derive(Clone)]
+pub struct PriceService {
+    pool:   ConnectionPool,
+    market: MarketService,
+}
+
+impl PriceService {
+    /// Creates a new instance
+    pub fn new(
+        pool:   ConnectionPool,
+        market: MarketService,
+    ) -> Self {
+        Self {
+            pool,
+            market,
+        }
+    }
+
+    /// Gets the price of every item
+    ///
+    /// # Params
+    ///
+    /// `source`   -> Strategy that is used
+    /// `type_ids` -> Items to get the price for
+    ///
+    /// # Returns
+    ///
+    /// Price of all items that have a price
+    ///
+    pub async fn prices(
+        &self,
+        source:   PriceSource,
+        type_ids: Vec<TypeId>,
+    ) -> Result<HashMap<TypeId, f32>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let mut prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, type_ids.clone())
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+        if source == PriceSource::Average {
+            return Ok(prices);
+        }
+
+        let regions = if let PriceSource::RegionAverage(_) = source {
+            let keys = con
+                .keys::<_, SolarSystemId>(CacheName::SystemRegion)
+                .await?;
+            con
+                .mget::<_, _, SystemRegionEntry>(CacheName::SystemRegion, keys)
+                .await?
+                .into_iter()
+                .flatten()
+                .map(|x| (x.system_id, x.region_id))
+                .collect::<HashMap<_, _>>()
+        } else {
+            HashMap::new()
+        };
+
+        for tid in type_ids {
+            let orders = self.market.latest_orders(tid).await?;
+            if let Some(x) = source.price(&orders, &regions) {
+                prices.insert(tid, x);
+            }
+        }
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod pricing_tests {
+    use super::*;
+
+    fn order(location_id: u64, system_id: u32, price: f32, is_buy_order: bool) -> MarketInfoEntry {
+        MarketInfoEntry {
+            issued:       0,
+            expire:       0,
+            order_id:     1.into(),
+            location_id:  location_id.into(),
+            system_id:    system_id.into(),
+            type_id:      34.into(),
+            volume_total: 100,
+            price,
+            is_buy_order,
+        }
+    }
+
+    #[test]
+    fn source_roundtrip() {
+        let sources = vec![
+            PriceSource::Average,
+            PriceSource::JitaSellMin,
+            PriceSource::JitaBuyMax,
+            PriceSource::JitaFivePercent,
+            PriceSource::RegionAverage(10000002.into()),
+            PriceSource::Station(60008494.into()),
+        ];
+        for source in sources {
+            assert_eq!(PriceSource::parse(&source.as_string()).unwrap(), source);
+        }
+        assert!(PriceSource::parse("station").is_err());
+        assert!(PriceSource::parse("average:1").is_err());
+        assert!(PriceSource::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn query_requires_parameter() {
+        let query = PriceQuery {
+            price: Some(PriceStrategy::RegionAverage),
+            ..PriceQuery::default()
+        };
+        assert!(query.source().is_err());
+        assert_eq!(PriceQuery::default().source().unwrap(), PriceSource::Average);
+    }
+
+    #[test]
+    fn order_prices() {
+        let orders = vec![
+            (order(60003760, 30000142, 5.0, false), 10),
+            (order(60003760, 30000142, 6.0, false), 10),
+            (order(60003760, 30000142, 4.0, true), 10),
+            (order(60003760, 30000142, 3.0, true), 10),
+            (order(60008494, 30002187, 7.0, false), 30),
+        ];
+        let regions = vec![
+            (30000142.into(), 10000002.into()),
+            (30002187.into(), 10000043.into()),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let price = |x: PriceSource| x.price(&orders, &regions);
+        assert_eq!(price(PriceSource::JitaSellMin), Some(5.0));
+        assert_eq!(price(PriceSource::JitaBuyMax), Some(4.0));
+        assert_eq!(price(PriceSource::RegionAverage(10000002.into())), Some(5.5));
+        assert_eq!(price(PriceSource::Station(60008494.into())), Some(7.0));
+        assert_eq!(price(PriceSource::Station(1.into())), None);
+        assert_eq!(price(PriceSource::Average), None);
+    }
+
+    #[test]
+    fn five_percent() {
+        // 5% of 200 are 10 items, taken from the two cheapest orders
+        let orders = vec![(6.0, 100), (4.0, 5), (5.0, 95)];
+        assert_eq!(PriceSource::five_percent(orders), Some(4.5));
+        assert_eq!(PriceSource::five_percent(vec![(4.0, 1)]), Some(4.0));
+        assert_eq!(PriceSource::five_percent(Vec::new()), None);
+    }
+}
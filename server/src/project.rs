@@ -2,6 +2,7 @@ use crate::blueprint::{BlueprintInfo, BlueprintService, BlueprintTreeEntry, Manu
 use crate::character::CharacterService;
 use crate::error::EveServerError;
 use crate::eve::EveAuthService;
+use crate::market::{MarketService, ShoppingList, ShoppingMaterial};
 
 use cachem::v2::ConnectionPool;
 use caph_db_v2::{CacheName, CharacterAssetEntry, Material, ProjectBlueprintEntry, ProjectEntry};
@@ -16,6 +17,7 @@ pub struct ProjectService {
     blueprint: BlueprintService,
     character: CharacterService,
     eve_auth:  EveAuthService,
+    market:    MarketService,
 }
 
 impl ProjectService {
@@ -24,12 +26,14 @@ impl ProjectService {
         blueprint: BlueprintService,
         character: CharacterService,
         eve_auth:  EveAuthService,
+        market:    MarketService,
     ) -> Self {
         Self {
             pool,
             blueprint,
             character,
             eve_auth,
+            market,
         }
     }
 
@@ -316,6 +320,39 @@ impl ProjectService {
         Ok(assets)
     }
 
+    /// Shopping list for all raw materials of the project that are not yet
+    /// in the project chest
+    pub async fn shopping_list(
+        &self,
+        id:    Uuid,
+        token: String,
+    ) -> Result<ShoppingList, EveServerError> {
+        let mut stored: HashMap<TypeId, u32> = HashMap::new();
+        self
+            .stored_materials(id, token.clone())
+            .await?
+            .into_iter()
+            .for_each(|x| *stored.entry(x.type_id).or_default() += x.quantity);
+
+        let materials = self
+            .raw_materials(id, token)
+            .await?
+            .into_iter()
+            .map(|x| ShoppingMaterial {
+                type_id:  x.mid,
+                quantity: x.quantity.saturating_sub(
+                    stored.get(&x.mid).copied().unwrap_or_default()
+                ),
+            })
+            .filter(|x| x.quantity > 0)
+            .collect::<Vec<_>>();
+
+        self
+            .market
+            .shopping_list(materials)
+            .await
+    }
+
     pub async fn trees(
         &self,
         id:    Uuid,
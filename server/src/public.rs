@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::ConfigService;
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, IndustryCostEntry, ItemEntry, MarketPriceEntry, SovereigntyEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{ItemId, SolarSystemId, TypeId};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+/// Loads all entries or a single entry of a cache and converts them into a
+/// json object with the key of the cache as key
+macro_rules! entries {
+    ($con:expr, $cache:expr, $idx:ty, $val:ty, $id:expr) => {{
+        let keys = match $id {
+            Some(x) => vec![<$idx>::from(x)],
+            None    => $con.keys::<_, $idx>($cache).await?,
+        };
+        let entries = $con
+            .mget::<_, _, $val>($cache, keys.clone())
+            .await?
+            .into_iter()
+            .zip(keys)
+            .filter_map(|(v, k)| v.map(|v| (k.to_string(), v)))
+            .collect::<HashMap<_, _>>();
+        serde_json::to_value(entries)?
+    }};
+}
+
+/// Publishes selected caches that contain no character data as a read only
+/// api, so that the dataset can be mirrored.
+///
+/// The caches are selected with the config, see
+/// [ConfigService::public_caches]. Without it no cache is published.
+#[derive(Clone)]
+pub struct PublicService {
+    pool:     ConnectionPool,
+    config:   ConfigService,
+    /// Number of requests per address in the current window
+    requests: Arc<RwLock<HashMap<IpAddr, (Instant, u32)>>>,
+}
+
+impl PublicService {
+    const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+    /// Creates a new instance
+    pub fn new(
+        pool:   ConnectionPool,
+        config: ConfigService,
+    ) -> Self {
+        Self {
+            pool,
+            config,
+            requests: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Names of all published caches
+    pub async fn caches(&self) -> Vec<&'static str> {
+        self
+            .config
+            .public_caches()
+            .await
+            .iter()
+            .map(|x| x.name())
+            .collect::<Vec<_>>()
+    }
+
+    /// Gets all entries of a published cache
+    ///
+    /// # Params
+    ///
+    /// `addr` -> Address of the requester, used for rate limiting
+    /// `name` -> Name of the cache
+    /// `id`   -> Only returns the entry with the id, if set
+    ///
+    /// # Returns
+    ///
+    /// Object with the id of the entry as key and the entry as value
+    ///
+    pub async fn entries(
+        &self,
+        addr: Option<IpAddr>,
+        name: String,
+        id:   Option<u32>,
+    ) -> Result<Value, EveServerError> {
+        let cache = self
+            .config
+            .public_caches()
+            .await
+            .into_iter()
+            .find(|x| x.name() == name)
+            .ok_or(EveServerError::CacheNotPublic)?;
+        if let Some(addr) = addr {
+            self.rate_limit(addr).await?;
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let entries = match cache {
+            PublicCache::IndustryCosts => {
+                entries!(con, CacheName::IndustryCost, SolarSystemId, IndustryCostEntry, id)
+            },
+            PublicCache::Items => {
+                entries!(con, CacheName::Item, TypeId, ItemEntry, id)
+            },
+            PublicCache::MarketPrices => {
+                entries!(con, CacheName::MarketPrice, TypeId, MarketPriceEntry, id)
+            },
+            PublicCache::Names => {
+                entries!(con, CacheName::Name, ItemId, String, id.map(u64::from))
+            },
+            PublicCache::Sovereignty => {
+                entries!(con, CacheName::Sovereignty, SolarSystemId, SovereigntyEntry, id)
+            },
+            PublicCache::SystemRegions => {
+                entries!(con, CacheName::SystemRegion, SolarSystemId, SystemRegionEntry, id)
+            },
+        };
+        Ok(entries)
+    }
+
+    async fn rate_limit(&self, addr: IpAddr) -> Result<(), EveServerError> {
+        let limit = self.config.public_rate_limit().await;
+        let mut requests = self.requests.write().await;
+        requests.retain(|_, (start, _)| start.elapsed() < Self::RATE_LIMIT_WINDOW);
+
+        let (_, count) = requests
+            .entry(addr)
+            .or_insert_with(|| (Instant::now(), 0));
+        if *count >= limit {
+            return Err(EveServerError::RateLimited);
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Caches that can be published, only caches without any character data
+/// are allowed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum PublicCache {
+    IndustryCosts,
+    Items,
+    MarketPrices,
+    Names,
+    Sovereignty,
+    SystemRegions,
+}
+
+impl PublicCache {
+    /// Name of the cache in the api
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::IndustryCosts => "industry_costs",
+            Self::Items         => "items",
+            Self::MarketPrices  => "market_prices",
+            Self::Names         => "names",
+            Self::Sovereignty   => "sovereignty",
+            Self::SystemRegions => "system_regions",
+        }
+    }
+
+    /// Parses a comma separated list of cache names, unknown names are
+    /// ignored
+    pub fn parse_list(list: &str) -> Vec<Self> {
+        let all = [
+            Self::IndustryCosts,
+            Self::Items,
+            Self::MarketPrices,
+            Self::Names,
+            Self::Sovereignty,
+            Self::SystemRegions,
+        ];
+
+        let mut caches = Vec::new();
+        for name in list.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            match all.iter().find(|x| x.name() == name) {
+                Some(x) if !caches.contains(x) => caches.push(*x),
+                Some(_)                        => continue,
+                None                           => log::warn!("Unknown public cache {}", name),
+            }
+        }
+        caches
+    }
+}
+
+#[cfg(test)]
+mod public_tests {
+    use super::*;
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(
+            PublicCache::parse_list("items, market_prices,items,,character_assets"),
+            vec![PublicCache::Items, PublicCache::MarketPrices]
+        );
+        assert!(PublicCache::parse_list("").is_empty());
+    }
+}
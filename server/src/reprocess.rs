@@ -0,0 +1,198 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::industry::{Facility, IndustryService};
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterAssetEntry, MarketPriceEntry, ReprocessEntry};
+use caph_eve_data_wrapper::{EveDataWrapper, ItemId, LocationId, TypeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Service for deciding if items should be sold or reprocessed
+#[derive(Clone)]
+pub struct ReprocessService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+    eve_data: EveDataWrapper,
+    industry: IndustryService,
+}
+
+impl ReprocessService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+        eve_data: EveDataWrapper,
+        industry: IndustryService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            eve_data,
+            industry,
+        }
+    }
+
+    /// Compares the value of selling every item stack in a hangar directly
+    /// with the value of reprocessing it and selling the materials.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Hangar location, reprocessing yield and tax
+    ///
+    /// # Returns
+    ///
+    /// Decision for every item stack in the hangar and the facilities that
+    /// were considered
+    ///
+    pub async fn decision(
+        &self,
+        token: &str,
+        query: ReprocessQuery,
+    ) -> Result<ReprocessDecision, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let keys = con
+            .keys::<_, ItemId>(CacheName::CharacterAsset)
+            .await?;
+        let assets = con
+            .mget::<_, _, CharacterAssetEntry>(CacheName::CharacterAsset, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| user_ids.contains(&x.user_id))
+            .filter(|x| x.location_id == query.location_id)
+            .collect::<Vec<_>>();
+
+        let type_ids = assets
+            .iter()
+            .map(|x| x.type_id)
+            .collect::<Vec<_>>();
+        let materials = con
+            .mget::<_, _, Vec<ReprocessEntry>>(CacheName::Reprocess, type_ids.clone())
+            .await?
+            .into_iter()
+            .zip(type_ids.iter())
+            .filter_map(|(x, tid)| x.map(|x| (*tid, x)))
+            .collect::<HashMap<_, _>>();
+
+        let mut price_ids = type_ids;
+        price_ids.extend(
+            materials
+                .values()
+                .flatten()
+                .map(|x| x.material_id)
+        );
+        price_ids.sort();
+        price_ids.dedup();
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(CacheName::MarketPrice, price_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.type_id, x.average_price))
+            .collect::<HashMap<_, _>>();
+
+        let types = self.eve_data.types().await?;
+
+        let mut items = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let sell_value = prices
+                .get(&asset.type_id)
+                .map(|x| *x * asset.quantity as f32)
+                .unwrap_or_default();
+
+            // Items can only be reprocessed in full portions, leftovers stay
+            // in the hangar
+            let portion_size = types
+                .type_by_id(asset.type_id)
+                .map(|x| x.portion_size.max(1) as u32)
+                .unwrap_or(1);
+            let portions = asset.quantity / portion_size;
+
+            let reprocess_value = materials
+                .get(&asset.type_id)
+                .map(|x| x
+                    .iter()
+                    .map(|x| {
+                        let quantity = (
+                            (x.quantity * portions) as f32 * query.efficiency
+                        ).floor();
+                        let price = prices
+                            .get(&x.material_id)
+                            .copied()
+                            .unwrap_or_default();
+                        quantity * price * (1f32 - query.tax)
+                    })
+                    .sum::<f32>()
+                );
+
+            let action = match reprocess_value {
+                Some(x) if x > sell_value => ReprocessAction::Reprocess,
+                _                         => ReprocessAction::Sell,
+            };
+
+            items.push(ReprocessItem {
+                item_id:         asset.item_id,
+                type_id:         asset.type_id,
+                quantity:        asset.quantity,
+                sell_value,
+                reprocess_value: reprocess_value.unwrap_or_default(),
+                action,
+            });
+        }
+
+        Ok(ReprocessDecision {
+            facilities: self.industry.stations()?,
+            items,
+        })
+    }
+}
+
+/// Query parameters for the reprocessing decision
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReprocessQuery {
+    /// Location of the hangar
+    pub location_id: LocationId,
+    /// Reprocessing yield of the user, between 0.0 and 1.0
+    pub efficiency:  f32,
+    /// Reprocessing tax, between 0.0 and 1.0
+    #[serde(default)]
+    pub tax:         f32,
+}
+
+#[derive(Serialize)]
+pub struct ReprocessDecision {
+    pub facilities: Vec<Facility>,
+    pub items:      Vec<ReprocessItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReprocessItem {
+    pub item_id:         ItemId,
+    pub type_id:         TypeId,
+    pub quantity:        u32,
+    pub sell_value:      f32,
+    pub reprocess_value: f32,
+    pub action:          ReprocessAction,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReprocessAction {
+    Reprocess,
+    Sell,
+}
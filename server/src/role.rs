@@ -0,0 +1,221 @@
+use crate::audit::{AuditAction, AuditService};
+use crate::config::ConfigService;
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, UserEntry, UserRoleEntry};
+use caph_eve_data_wrapper::CharacterId;
+use serde::{Deserialize, Serialize};
+
+/// Manages which routes a user can access
+///
+/// Admins can be bootstrapped with the config, see
+/// [ConfigService::admins].
+#[derive(Clone)]
+pub struct RoleService {
+    pool:     ConnectionPool,
+    audit:    AuditService,
+    config:   ConfigService,
+    eve_auth: EveAuthService,
+}
+
+impl RoleService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        audit:    AuditService,
+        config:   ConfigService,
+        eve_auth: EveAuthService,
+    ) -> Self {
+        Self {
+            pool,
+            audit,
+            config,
+            eve_auth,
+        }
+    }
+
+    /// Gets the roles of the requesting main
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// All roles of the main, every user is at least a member
+    ///
+    pub async fn roles(
+        &self,
+        token: &str,
+    ) -> Result<Vec<Role>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        self.user_roles(user.user_id).await
+    }
+
+    /// Makes sure that the requesting main has the given role
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `role`  -> Required role
+    ///
+    /// # Returns
+    ///
+    /// The main if it has the role, otherwise [EveServerError::MissingRole]
+    ///
+    pub async fn require(
+        &self,
+        token: &str,
+        role:  Role,
+    ) -> Result<UserEntry, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        if self.user_roles(user.user_id).await?.contains(&role) {
+            Ok(user)
+        } else {
+            Err(EveServerError::MissingRole(role))
+        }
+    }
+
+    /// Lists the roles of all users that have more than the member role,
+    /// the route must be restricted to admins
+    ///
+    pub async fn list(
+        &self,
+    ) -> Result<Vec<UserRoles>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+        let mut user_ids = con
+            .keys::<_, CharacterId>(CacheName::UserRole)
+            .await?;
+        user_ids.extend(self.config.admins().await);
+        user_ids.sort();
+        user_ids.dedup();
+
+        let mut result = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids {
+            result.push(UserRoles {
+                user_id,
+                roles: self.user_roles(user_id).await?,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Sets the roles of a main, the route must be restricted to admins
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting admin
+    /// `user_id` -> Main whose roles are set
+    /// `roles`   -> New roles, the member role is always granted
+    ///
+    pub async fn set(
+        &self,
+        token:   &str,
+        user_id: CharacterId,
+        roles:   Vec<Role>,
+    ) -> Result<(), EveServerError> {
+        let admin = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+
+        let entry = UserRoleEntry {
+            admin:        roles.contains(&Role::Admin),
+            corp_manager: roles.contains(&Role::CorpManager),
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::UserRole, user_id, entry)
+            .await?;
+
+        let details = roles
+            .iter()
+            .map(|x| format!("{:?}", x))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.audit.record(admin.user_id, AuditAction::RoleChange, Some(user_id), details).await
+    }
+
+    async fn user_roles(
+        &self,
+        user_id: CharacterId,
+    ) -> Result<Vec<Role>, EveServerError> {
+        let mut entry = self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, UserRoleEntry>(CacheName::UserRole, user_id)
+            .await?
+            .unwrap_or_default();
+        entry.admin |= self.config.admins().await.contains(&user_id);
+        Ok(Role::from_entry(&entry))
+    }
+}
+
+/// Roles a user can have, every role includes the roles below it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    CorpManager,
+    Member,
+}
+
+impl Role {
+    /// Resolves all roles of an entry, including the ones implied by a
+    /// higher role
+    fn from_entry(entry: &UserRoleEntry) -> Vec<Self> {
+        let mut roles = Vec::new();
+        if entry.admin {
+            roles.push(Self::Admin);
+        }
+        if entry.admin || entry.corp_manager {
+            roles.push(Self::CorpManager);
+        }
+        roles.push(Self::Member);
+        roles
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserRoles {
+    pub user_id: CharacterId,
+    pub roles:   Vec<Role>,
+}
+
+#[cfg(test)]
+mod role_tests {
+    use super::*;
+
+    #[test]
+    fn roles_from_entry() {
+        let entry = UserRoleEntry::default();
+        assert_eq!(Role::from_entry(&entry), vec![Role::Member]);
+
+        let entry = UserRoleEntry {
+            admin:        false,
+            corp_manager: true,
+        };
+        assert_eq!(Role::from_entry(&entry), vec![Role::CorpManager, Role::Member]);
+
+        let entry = UserRoleEntry {
+            admin:        true,
+            corp_manager: false,
+        };
+        assert_eq!(Role::from_entry(&entry), vec![Role::Admin, Role::CorpManager, Role::Member]);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::industry::IndustryService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{BlueprintEntry, CacheName, CharacterSkillEntry, SystemRegionEntry};
+use caph_eve_data_wrapper::{CharacterId, SolarSystemId, TypeId};
+use serde::{Deserialize, Serialize};
+
+/// Schedules production jobs over the industry slots of a main and its
+/// alts.
+///
+/// The number of slots and the job duration of every character depend on
+/// their skills. Every target is split into as many jobs as there are slots,
+/// every job is assigned to the slot that finishes it first.
+#[derive(Clone)]
+pub struct ScheduleService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+    industry: IndustryService,
+}
+
+impl ScheduleService {
+    /// Adds one manufacturing slot per level
+    const MASS_PRODUCTION:          u32 = 3387;
+    /// Adds one manufacturing slot per level
+    const ADV_MASS_PRODUCTION:      u32 = 24625;
+    /// Adds one reaction slot per level
+    const MASS_REACTIONS:           u32 = 45748;
+    /// Adds one reaction slot per level
+    const ADV_MASS_REACTIONS:       u32 = 45749;
+    /// Reduces the manufacturing time by 4% per level
+    const INDUSTRY:                 u32 = 3380;
+    /// Reduces the manufacturing time by 3% per level
+    const ADV_INDUSTRY:             u32 = 3388;
+    /// Reduces the reaction time by 4% per level
+    const REACTIONS:                u32 = 45746;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+        industry: IndustryService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            industry,
+        }
+    }
+
+    /// Creates a schedule for all targets.
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting main
+    /// `request` -> Blueprints with their runs and the system all jobs are
+    ///              installed in
+    ///
+    /// # Returns
+    ///
+    /// Every slot of every character with the jobs scheduled in it
+    ///
+    pub async fn schedule(
+        &self,
+        token:   &str,
+        request: ScheduleRequest,
+    ) -> Result<Schedule, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+
+        let mut con = self.pool.acquire().await?;
+        let characters = con
+            .mget::<_, _, CharacterSkillEntry>(CacheName::CharacterSkill, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let security = con
+            .get::<_, _, SystemRegionEntry>(CacheName::SystemRegion, request.system_id)
+            .await?
+            .map(|x| x.security)
+            .unwrap_or(1f32);
+        let facility = self.industry.facility_model(request.system_id, security)?;
+
+        let bpids = request
+            .targets
+            .iter()
+            .map(|x| x.blueprint_id)
+            .collect::<Vec<_>>();
+        let blueprints = con
+            .mget::<_, _, BlueprintEntry>(CacheName::Blueprint, bpids)
+            .await?;
+
+        let mut slots = Vec::new();
+        for character in characters.iter() {
+            for kind in [JobKind::Manufacturing, JobKind::Reaction] {
+                let time_factor = Self::time_factor(character, kind) *
+                    (1f32 - facility.time_bonus());
+                for slot in 0..Self::slots(character, kind) {
+                    slots.push(Slot {
+                        user_id: character.user_id,
+                        kind,
+                        slot,
+                        time_factor,
+                        jobs: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        let mut jobs = Vec::new();
+        for (target, blueprint) in request.targets.into_iter().zip(blueprints) {
+            let blueprint = blueprint.ok_or(EveServerError::BlueprintNotFound)?;
+            let kind = if blueprint.manufacture.is_some() {
+                JobKind::Manufacturing
+            } else {
+                JobKind::Reaction
+            };
+            let activity = blueprint.production_activity();
+
+            let count = slots.iter().filter(|x| x.kind == kind).count() as u32;
+            for runs in split_runs(target.runs, count) {
+                jobs.push(UnscheduledJob {
+                    blueprint_id: blueprint.bid,
+                    product_id:   activity.product_id(),
+                    kind,
+                    runs,
+                    time_per_run: activity.time as u64,
+                });
+            }
+        }
+
+        let unscheduled = schedule(&mut slots, jobs);
+        let duration = slots
+            .iter()
+            .map(|x| x.end())
+            .max()
+            .unwrap_or_default();
+
+        Ok(Schedule {
+            system_id: request.system_id,
+            duration,
+            slots:     slots
+                .into_iter()
+                .filter(|x| !x.jobs.is_empty())
+                .map(|x| ScheduleSlot {
+                    user_id: x.user_id,
+                    kind:    x.kind,
+                    slot:    x.slot,
+                    jobs:    x.jobs,
+                })
+                .collect::<Vec<_>>(),
+            unscheduled,
+        })
+    }
+
+    /// Number of slots a character has for the given job kind
+    fn slots(character: &CharacterSkillEntry, kind: JobKind) -> u32 {
+        let (skill, advanced) = match kind {
+            JobKind::Manufacturing => (Self::MASS_PRODUCTION, Self::ADV_MASS_PRODUCTION),
+            JobKind::Reaction      => (Self::MASS_REACTIONS, Self::ADV_MASS_REACTIONS),
+        };
+        let skill = character.skill_level(skill.into());
+        let advanced = character.skill_level(advanced.into());
+
+        // without the skill reactions can not be started at all
+        if kind == JobKind::Reaction && character.skill_level(Self::REACTIONS.into()) == 0 {
+            return 0;
+        }
+        1 + skill + advanced
+    }
+
+    /// Multiplier for the job duration based on the skills of the character
+    fn time_factor(character: &CharacterSkillEntry, kind: JobKind) -> f32 {
+        match kind {
+            JobKind::Manufacturing => {
+                let industry = character.skill_level(Self::INDUSTRY.into()) as f32;
+                let advanced = character.skill_level(Self::ADV_INDUSTRY.into()) as f32;
+                (1f32 - 0.04 * industry) * (1f32 - 0.03 * advanced)
+            },
+            JobKind::Reaction      => {
+                let reactions = character.skill_level(Self::REACTIONS.into()) as f32;
+                1f32 - 0.04 * reactions
+            }
+        }
+    }
+}
+
+/// Splits the runs into multiple jobs so that they can run in parallel
+fn split_runs(runs: u32, slots: u32) -> Vec<u32> {
+    let jobs = slots.max(1).min(runs);
+    if jobs == 0 {
+        return Vec::new();
+    }
+    (0..jobs)
+        .map(|x| runs / jobs + if x < runs % jobs { 1 } else { 0 })
+        .collect::<Vec<_>>()
+}
+
+/// Assigns the jobs to the slots, longest job first, every job goes to the
+/// slot that finishes it the earliest.
+///
+/// Returns all jobs that could not be scheduled because there is no slot
+/// for them.
+fn schedule(slots: &mut Vec<Slot>, mut jobs: Vec<UnscheduledJob>) -> Vec<UnscheduledJob> {
+    jobs.sort_by(|a, b| b.duration().cmp(&a.duration()));
+
+    let mut unscheduled = Vec::new();
+    for job in jobs {
+        let slot = slots
+            .iter_mut()
+            .filter(|x| x.kind == job.kind)
+            .min_by_key(|x| x.end() + x.duration(&job));
+        let slot = if let Some(x) = slot {
+            x
+        } else {
+            unscheduled.push(job);
+            continue;
+        };
+
+        let start = slot.end();
+        let end = start + slot.duration(&job);
+        slot.jobs.push(ScheduledJob {
+            blueprint_id: job.blueprint_id,
+            product_id:   job.product_id,
+            runs:         job.runs,
+            start,
+            end,
+        });
+    }
+    unscheduled
+}
+
+/// Industry slot of a character while the schedule is created
+struct Slot {
+    user_id:     CharacterId,
+    kind:        JobKind,
+    slot:        u32,
+    /// Multiplier for the job duration from skills and the facility
+    time_factor: f32,
+    jobs:        Vec<ScheduledJob>,
+}
+
+impl Slot {
+    /// Time in seconds when the last job of the slot is done
+    fn end(&self) -> u64 {
+        self.jobs.last().map(|x| x.end).unwrap_or_default()
+    }
+
+    /// Time in seconds the job takes in this slot
+    fn duration(&self, job: &UnscheduledJob) -> u64 {
+        (job.duration() as f32 * self.time_factor).ceil() as u64
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScheduleRequest {
+    /// System all jobs are installed in
+    pub system_id: SolarSystemId,
+    pub targets:   Vec<ScheduleTarget>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScheduleTarget {
+    pub blueprint_id: TypeId,
+    pub runs:         u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Manufacturing,
+    Reaction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Schedule {
+    pub system_id:   SolarSystemId,
+    /// Time in seconds until all jobs are done
+    pub duration:    u64,
+    /// All slots that have at least one job, one row of a gantt chart
+    pub slots:       Vec<ScheduleSlot>,
+    /// Jobs no character has a slot for
+    pub unscheduled: Vec<UnscheduledJob>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleSlot {
+    pub user_id: CharacterId,
+    pub kind:    JobKind,
+    /// Index of the slot of the character
+    pub slot:    u32,
+    pub jobs:    Vec<ScheduledJob>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledJob {
+    pub blueprint_id: TypeId,
+    pub product_id:   TypeId,
+    pub runs:         u32,
+    /// Seconds after the start of the schedule
+    pub start:        u64,
+    /// Seconds after the start of the schedule
+    pub end:          u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnscheduledJob {
+    pub blueprint_id: TypeId,
+    pub product_id:   TypeId,
+    pub kind:         JobKind,
+    pub runs:         u32,
+    /// Duration of a single run without any bonus
+    pub time_per_run: u64,
+}
+
+impl UnscheduledJob {
+    /// Duration of the job without any bonus
+    fn duration(&self) -> u64 {
+        self.time_per_run * self.runs as u64
+    }
+}
+
+#[cfg(test)]
+mod schedule_tests {
+    use super::*;
+
+    fn slot(user_id: u32, time_factor: f32) -> Slot {
+        Slot {
+            user_id: user_id.into(),
+            kind:    JobKind::Manufacturing,
+            slot:    0,
+            time_factor,
+            jobs:    Vec::new(),
+        }
+    }
+
+    fn job(runs: u32, kind: JobKind) -> UnscheduledJob {
+        UnscheduledJob {
+            blueprint_id: 1.into(),
+            product_id:   2.into(),
+            kind,
+            runs,
+            time_per_run: 100,
+        }
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(split_runs(10, 3), vec![4, 3, 3]);
+        assert_eq!(split_runs(2, 5), vec![1, 1]);
+        assert!(split_runs(0, 5).is_empty());
+    }
+
+    #[test]
+    fn prefers_faster_slot() {
+        let mut slots = vec![slot(1, 1f32), slot(2, 0.5f32)];
+        let unscheduled = schedule(&mut slots, vec![job(10, JobKind::Manufacturing)]);
+        assert!(unscheduled.is_empty());
+        assert!(slots[0].jobs.is_empty());
+        assert_eq!(slots[1].jobs[0].end, 500);
+    }
+
+    #[test]
+    fn balances_slots() {
+        let mut slots = vec![slot(1, 1f32), slot(2, 1f32)];
+        let jobs = vec![
+            job(5, JobKind::Manufacturing),
+            job(3, JobKind::Manufacturing),
+            job(2, JobKind::Manufacturing),
+        ];
+        schedule(&mut slots, jobs);
+        assert_eq!(slots[0].end(), 500);
+        assert_eq!(slots[1].end(), 500);
+    }
+
+    #[test]
+    fn no_slot() {
+        let mut slots = vec![slot(1, 1f32)];
+        let unscheduled = schedule(&mut slots, vec![job(1, JobKind::Reaction)]);
+        assert_eq!(unscheduled.len(), 1);
+    }
+}
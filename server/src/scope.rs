@@ -0,0 +1,294 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use caph_db_v2::UserEntry;
+use caph_eve_data_wrapper::{CharacterId, EveOAuthPayload};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Routes that only return data if at least one character granted the
+/// scopes of the preset
+const ROUTES: &[(&str, ScopePreset)] = &[
+    ("/api/blueprint/{type_id}/plan",                ScopePreset::Assets),
+    ("/api/character/assets",                        ScopePreset::Assets),
+    ("/api/character/assets/cost",                   ScopePreset::Assets),
+    ("/api/character/assets/reprocess",              ScopePreset::Assets),
+    ("/api/character/assets/volume",                 ScopePreset::Assets),
+    ("/api/character/assets/worth",                  ScopePreset::Assets),
+    ("/api/character/blueprints",                    ScopePreset::Assets),
+    ("/api/character/blueprints/report",             ScopePreset::Assets),
+    ("/api/character/blueprints/stacks",             ScopePreset::Assets),
+    ("/api/character/location/{item_id}",            ScopePreset::Assets),
+    ("/api/export/assets",                           ScopePreset::Assets),
+    ("/api/export/blueprints",                       ScopePreset::Assets),
+    ("/api/stock",                                   ScopePreset::Assets),
+    ("/api/character/calendar",                      ScopePreset::Character),
+    ("/api/character/clones",                        ScopePreset::Character),
+    ("/api/character/notifications",                 ScopePreset::Character),
+    ("/api/character/skillfarm",                     ScopePreset::Character),
+    ("/api/character/skills/history",                ScopePreset::Character),
+    ("/api/character/skills/requirements/{type_id}", ScopePreset::Character),
+    ("/api/corporation/{corporation_id}/assets",     ScopePreset::Director),
+    ("/api/corporation/{corporation_id}/blueprints", ScopePreset::Director),
+    ("/api/corporation/{corporation_id}/mining",     ScopePreset::Director),
+    ("/api/fittings/esi/{esi_fitting_id}/eft",       ScopePreset::Fittings),
+    ("/api/character/mining",                        ScopePreset::Industry),
+    ("/api/character/planets",                       ScopePreset::Industry),
+    ("/api/industry/jobs",                           ScopePreset::Industry),
+    ("/api/market/structures",                       ScopePreset::Market),
+    ("/api/character/contracts",                     ScopePreset::Wallet),
+    ("/api/character/networth",                      ScopePreset::Wallet),
+];
+
+/// Named sets of ESI scopes, so that characters only need to grant the
+/// scopes of the features they want to use
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScopePreset {
+    Assets,
+    Character,
+    Director,
+    Fittings,
+    Industry,
+    Market,
+    Wallet,
+}
+
+impl ScopePreset {
+    /// Granted by every login
+    pub const PUBLIC_SCOPE: &'static str = "publicData";
+
+    pub const ALL: &'static [Self] = &[
+        Self::Assets,
+        Self::Character,
+        Self::Director,
+        Self::Fittings,
+        Self::Industry,
+        Self::Market,
+        Self::Wallet,
+    ];
+
+    /// ESI scopes of the preset
+    pub fn scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Assets    => &[
+                "esi-assets.read_assets.v1",
+                "esi-characters.read_blueprints.v1",
+                "esi-universe.read_structures.v1",
+            ],
+            Self::Character => &[
+                "esi-calendar.read_calendar_events.v1",
+                "esi-characters.read_notifications.v1",
+                "esi-characterstats.read.v1",
+                "esi-clones.read_clones.v1",
+                "esi-clones.read_implants.v1",
+                "esi-skills.read_skillqueue.v1",
+                "esi-skills.read_skills.v1",
+            ],
+            Self::Director  => &[
+                "esi-assets.read_corporation_assets.v1",
+                "esi-characters.read_corporation_roles.v1",
+                "esi-corporations.read_structures.v1",
+                "esi-industry.read_corporation_jobs.v1",
+                "esi-industry.read_corporation_mining.v1",
+            ],
+            Self::Fittings  => &[
+                "esi-fittings.read_fittings.v1",
+                "esi-fittings.write_fittings.v1",
+            ],
+            Self::Industry  => &[
+                "esi-characters.read_agents_research.v1",
+                "esi-industry.read_character_jobs.v1",
+                "esi-industry.read_character_mining.v1",
+                "esi-planets.manage_planets.v1",
+            ],
+            Self::Market    => &[
+                "esi-markets.structure_markets.v1",
+                "esi-search.search_structures.v1",
+                "esi-universe.read_structures.v1",
+            ],
+            Self::Wallet    => &[
+                "esi-contracts.read_character_contracts.v1",
+                "esi-markets.read_character_orders.v1",
+                "esi-wallet.read_character_wallet.v1",
+            ],
+        }
+    }
+
+    /// Parses a comma separated list of preset names
+    ///
+    /// # Params
+    ///
+    /// `list` -> For example `assets,industry`
+    ///
+    /// # Returns
+    ///
+    /// The presets or [EveServerError::InvalidScopePreset] with the first
+    /// unknown name
+    ///
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, EveServerError> {
+        list
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                Self::ALL
+                    .iter()
+                    .find(|preset| preset.name() == x)
+                    .copied()
+                    .ok_or_else(|| EveServerError::InvalidScopePreset(x.into()))
+            })
+            .collect()
+    }
+
+    /// All ESI scopes of the given presets together with the public scope,
+    /// sorted and without duplicates
+    pub fn resolve(presets: &[Self]) -> Vec<String> {
+        let mut scopes = presets
+            .iter()
+            .flat_map(|x| x.scopes().iter())
+            .chain(std::iter::once(&Self::PUBLIC_SCOPE))
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes.dedup();
+        scopes
+    }
+
+    /// True if all scopes of the preset are in the given scopes
+    pub fn is_granted(&self, scopes: &[String]) -> bool {
+        self
+            .scopes()
+            .iter()
+            .all(|x| scopes.iter().any(|y| y == x))
+    }
+
+    /// Preset that is required for a route, [None] if the route works
+    /// without any scope
+    pub fn of_route(path: &str) -> Option<Self> {
+        ROUTES
+            .iter()
+            .find(|(route, _)| *route == path)
+            .map(|(_, preset)| *preset)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Assets    => "assets",
+            Self::Character => "character",
+            Self::Director  => "director",
+            Self::Fittings  => "fittings",
+            Self::Industry  => "industry",
+            Self::Market    => "market",
+            Self::Wallet    => "wallet",
+        }
+    }
+
+    fn routes(&self) -> Vec<&'static str> {
+        ROUTES
+            .iter()
+            .filter(|(_, preset)| preset == self)
+            .map(|(route, _)| *route)
+            .collect()
+    }
+}
+
+/// Shows which features can be used with the scopes the main and its alts
+/// granted, routes of features without any character should be disabled
+/// instead of being requested
+#[derive(Clone)]
+pub struct ScopeService {
+    eve_auth: EveAuthService,
+}
+
+impl ScopeService {
+    /// Creates a new instance
+    pub fn new(eve_auth: EveAuthService) -> Self {
+        Self {
+            eve_auth,
+        }
+    }
+
+    /// Gets all presets with the characters that granted them
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    pub async fn features(
+        &self,
+        token: &str,
+    ) -> Result<Vec<Feature>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        Ok(Self::features_of(&user))
+    }
+
+    fn features_of(user: &UserEntry) -> Vec<Feature> {
+        let characters = std::iter::once(user)
+            .chain(user.aliase.iter())
+            .map(|x| {
+                let scopes = EveOAuthPayload::decode(&x.access_token)
+                    .map(|x| x.scp)
+                    .unwrap_or_default();
+                (x.user_id, scopes)
+            })
+            .collect::<Vec<_>>();
+
+        ScopePreset::ALL
+            .iter()
+            .map(|preset| Feature {
+                preset:  *preset,
+                granted: characters
+                    .iter()
+                    .filter(|(_, scopes)| preset.is_granted(scopes))
+                    .map(|(user_id, _)| *user_id)
+                    .collect::<Vec<_>>(),
+                routes:  preset.routes(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Feature {
+    pub preset:  ScopePreset,
+    /// Characters that granted all scopes of the preset, if empty the
+    /// routes return no data
+    pub granted: Vec<CharacterId>,
+    pub routes:  Vec<&'static str>,
+}
+
+#[cfg(test)]
+mod scope_tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_resolve() {
+        let presets = ScopePreset::parse_list("assets, wallet").unwrap();
+        assert_eq!(presets, vec![ScopePreset::Assets, ScopePreset::Wallet]);
+        assert!(ScopePreset::parse_list("assets,unknown").is_err());
+
+        let scopes = ScopePreset::resolve(&[ScopePreset::Assets, ScopePreset::Market]);
+        assert!(scopes.contains(&ScopePreset::PUBLIC_SCOPE.to_string()));
+        assert_eq!(scopes.iter().filter(|x| *x == "esi-universe.read_structures.v1").count(), 1);
+    }
+
+    #[test]
+    fn granted() {
+        let scopes = ScopePreset::resolve(&[ScopePreset::Fittings]);
+        assert!(ScopePreset::Fittings.is_granted(&scopes));
+        assert!(!ScopePreset::Assets.is_granted(&scopes));
+    }
+
+    #[test]
+    fn unique_routes() {
+        for (route, preset) in ROUTES {
+            assert_eq!(ScopePreset::of_route(route), Some(*preset));
+        }
+        assert_eq!(ScopePreset::of_route("/api/items"), None);
+    }
+}
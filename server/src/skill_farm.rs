@@ -0,0 +1,378 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, CharacterSkillEntry, MarketPriceEntry, SkillHistoryEntry};
+use caph_eve_data_wrapper::{CharacterId, EveDataWrapper, SkillRequirement, TypeId};
+use serde::Serialize;
+
+/// Service for managing characters that are used for skill farming
+#[derive(Clone)]
+pub struct SkillFarmService {
+    pool:     ConnectionPool,
+    eve_auth: EveAuthService,
+    eve_data: EveDataWrapper,
+}
+
+impl SkillFarmService {
+    /// Skillpoints that a character must keep and can not be extracted
+    const MIN_SP:              u64 = 5_000_000;
+    /// Skillpoints that are removed by a single extractor
+    const EXTRACT_SP:          u64 = 500_000;
+    const SKILL_EXTRACTOR:     u32 = 40519;
+    const LARGE_SKILL_INJECTOR: u32 = 40520;
+
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_auth: EveAuthService,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            eve_data,
+        }
+    }
+
+    /// Overview over the skill farm of the character and its alts.
+    ///
+    /// The training speed is calculated from the attributes of the character
+    /// and the skill that is currently in training. Implants are already
+    /// part of the attributes.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// Training and extraction information for every character
+    ///
+    pub async fn overview(
+        &self,
+        token: &str,
+    ) -> Result<Vec<SkillFarmCharacter>, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        let characters = con
+            .mget::<_, _, CharacterSkillEntry>(CacheName::CharacterSkill, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let prices = con
+            .mget::<_, _, MarketPriceEntry>(
+                CacheName::MarketPrice,
+                vec![
+                    TypeId::from(Self::SKILL_EXTRACTOR),
+                    TypeId::from(Self::LARGE_SKILL_INJECTOR),
+                ]
+            )
+            .await?
+            .into_iter()
+            .map(|x| x.map(|x| x.average_price).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let extract_profit = prices[1] - prices[0];
+
+        let dogma = self.eve_data.dogma().await?;
+
+        let result = characters
+            .into_iter()
+            .map(|x| {
+                let sp_per_hour = x
+                    .training
+                    .and_then(|tid| dogma.skill_attributes(tid))
+                    .map(|(primary, secondary)| Self::sp_per_hour(
+                        x.attribute(primary),
+                        x.attribute(secondary),
+                    ))
+                    .unwrap_or_default();
+                let extractable = Self::extractable(x.total_sp);
+                let missing_sp = Self::EXTRACT_SP - Self::sp_since_extract(x.total_sp);
+                let hours_until_extract = if sp_per_hour > 0f32 {
+                    Some(missing_sp as f32 / sp_per_hour)
+                } else {
+                    None
+                };
+
+                SkillFarmCharacter {
+                    user_id:             x.user_id,
+                    total_sp:            x.total_sp,
+                    unallocated_sp:      x.unallocated_sp,
+                    training:            x.training,
+                    sp_per_hour,
+                    extractable,
+                    hours_until_extract,
+                    extract_profit,
+                    omega_expiry:        x.omega_expiry,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(result)
+    }
+
+    /// Sets the date the omega subscription of a character ends.
+    ///
+    /// # Params
+    ///
+    /// `token`  -> Cookie from the requesting main
+    /// `cid`    -> Id of the character, must be the main or one of its alts
+    /// `expiry` -> Timestamp in milliseconds
+    ///
+    pub async fn set_omega(
+        &self,
+        token:  &str,
+        cid:    CharacterId,
+        expiry: u64,
+    ) -> Result<(), EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+        if !user_ids.contains(&cid) {
+            return Err(EveServerError::InvalidUser);
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let mut entry = con
+            .get::<_, _, CharacterSkillEntry>(CacheName::CharacterSkill, cid)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        entry.omega_expiry = Some(expiry);
+        con
+            .set(CacheName::CharacterSkill, cid, entry)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Skillpoint history of the character and its alts.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    /// # Returns
+    ///
+    /// All snapshots of every character and since when skillpoints are
+    /// unallocated
+    ///
+    pub async fn history(
+        &self,
+        token: &str,
+    ) -> Result<Vec<SkillHistory>, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let history = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, Vec<SkillHistoryEntry>>(CacheName::SkillHistory, user_ids.clone())
+            .await?
+            .into_iter()
+            .zip(user_ids)
+            .map(|(entries, user_id)| {
+                let entries = entries.unwrap_or_default();
+                SkillHistory {
+                    user_id,
+                    unallocated_since: Self::unallocated_since(&entries),
+                    entries,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(history)
+    }
+
+    /// Checks if the character and its alts have all skills that are
+    /// required for a type.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `tid`   -> Type that should be flown or used
+    ///
+    /// # Returns
+    ///
+    /// Skill tree of the type and the missing skills of every character
+    ///
+    pub async fn requirements(
+        &self,
+        token: &str,
+        tid:   TypeId,
+    ) -> Result<SkillRequirements, EveServerError> {
+        let user_ids = self.user_ids(token).await?;
+
+        let dogma = self.eve_data.dogma().await?;
+        let tree = dogma.skill_tree(tid);
+        let mut required = dogma
+            .skill_tree_flat(tid)
+            .into_iter()
+            .collect::<Vec<_>>();
+        required.sort_by_key(|(skill_id, _)| *skill_id);
+
+        let characters = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, CharacterSkillEntry>(CacheName::CharacterSkill, user_ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| {
+                let missing = required
+                    .iter()
+                    .map(|(skill_id, level)| MissingSkill {
+                        skill_id: *skill_id,
+                        required: *level as u32,
+                        trained:  x.skill_level(*skill_id),
+                    })
+                    .filter(|x| x.trained < x.required)
+                    .collect::<Vec<_>>();
+                SkillCheck {
+                    user_id: x.user_id,
+                    can_use: missing.is_empty(),
+                    missing,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(SkillRequirements {
+            type_id: tid,
+            tree,
+            characters,
+        })
+    }
+
+    async fn user_ids(&self, token: &str) -> Result<Vec<CharacterId>, EveServerError> {
+        let user = self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)?;
+        let mut user_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.user_id)
+            .collect::<Vec<_>>();
+        user_ids.push(user.user_id);
+        Ok(user_ids)
+    }
+
+    /// Timestamp of the first snapshot since which the character has
+    /// unallocated skillpoints, None if all skillpoints are allocated
+    fn unallocated_since(entries: &[SkillHistoryEntry]) -> Option<u64> {
+        entries
+            .iter()
+            .rev()
+            .take_while(|x| x.unallocated_sp > 0)
+            .last()
+            .map(|x| x.timestamp)
+    }
+
+    /// Skillpoints an omega character trains per hour
+    fn sp_per_hour(primary: u32, secondary: u32) -> f32 {
+        (primary as f32 + secondary as f32 / 2f32) * 60f32
+    }
+
+    /// Number of extractors that can be used on the character
+    fn extractable(total_sp: u64) -> u64 {
+        total_sp.saturating_sub(Self::MIN_SP) / Self::EXTRACT_SP
+    }
+
+    /// Skillpoints that were trained since the last possible extraction
+    fn sp_since_extract(total_sp: u64) -> u64 {
+        total_sp.saturating_sub(Self::MIN_SP) % Self::EXTRACT_SP
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkillFarmCharacter {
+    pub user_id:             CharacterId,
+    pub total_sp:            u64,
+    pub unallocated_sp:      u32,
+    /// Skill that is currently in training
+    pub training:            Option<TypeId>,
+    pub sp_per_hour:         f32,
+    /// Number of skill extractors that can be used right now
+    pub extractable:         u64,
+    /// Hours until the next 500k skillpoints can be extracted, None if
+    /// nothing is in training
+    pub hours_until_extract: Option<f32>,
+    /// Price of a large skill injector minus the price of a skill extractor
+    pub extract_profit:      f32,
+    /// Timestamp in milliseconds when the omega subscription ends
+    pub omega_expiry:        Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkillRequirements {
+    pub type_id:    TypeId,
+    /// All required skills including their prerequisites
+    pub tree:       Vec<SkillRequirement>,
+    pub characters: Vec<SkillCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkillCheck {
+    pub user_id: CharacterId,
+    /// True if the character has trained all required skills
+    pub can_use: bool,
+    pub missing: Vec<MissingSkill>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissingSkill {
+    pub skill_id: TypeId,
+    pub required: u32,
+    /// Active level of the skill, 0 if it is not trained
+    pub trained:  u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkillHistory {
+    pub user_id:           CharacterId,
+    /// Timestamp in milliseconds since when the character has unallocated
+    /// skillpoints, None if all are allocated
+    pub unallocated_since: Option<u64>,
+    /// Oldest snapshot first
+    pub entries:           Vec<SkillHistoryEntry>,
+}
+
+#[cfg(test)]
+mod skill_farm_tests {
+    use super::*;
+
+    #[test]
+    fn sp_per_hour() {
+        // 27 primary and 21 secondary, a fully remapped character without
+        // implants
+        assert_eq!(SkillFarmService::sp_per_hour(27, 21), 2250f32);
+    }
+
+    #[test]
+    fn extractable() {
+        assert_eq!(SkillFarmService::extractable(4_000_000), 0);
+        assert_eq!(SkillFarmService::extractable(5_499_999), 0);
+        assert_eq!(SkillFarmService::extractable(5_500_000), 1);
+        assert_eq!(SkillFarmService::extractable(7_250_000), 4);
+    }
+
+    #[test]
+    fn sp_since_extract() {
+        assert_eq!(SkillFarmService::sp_since_extract(4_000_000), 0);
+        assert_eq!(SkillFarmService::sp_since_extract(5_200_000), 200_000);
+        assert_eq!(SkillFarmService::sp_since_extract(5_500_000), 0);
+    }
+
+    #[test]
+    fn unallocated_since() {
+        let entries = vec![
+            SkillHistoryEntry::new(1, 100, 50),
+            SkillHistoryEntry::new(2, 150, 0),
+            SkillHistoryEntry::new(3, 200, 10),
+            SkillHistoryEntry::new(4, 200, 10),
+        ];
+        assert_eq!(SkillFarmService::unallocated_since(&entries), Some(3));
+        assert_eq!(SkillFarmService::unallocated_since(&entries[..2]), None);
+        assert_eq!(SkillFarmService::unallocated_since(&[]), None);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+use crate::pricing::{PriceQuery, PriceService, PriceSource};
+use crate::workspace::WorkspaceService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, StockRuleEntry, UserEntry};
+use caph_eve_data_wrapper::{LocationId, TypeId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Service for the stock rules of a main.
+///
+/// The rules are checked by the collector, which sends an alert to the
+/// webhook of the main when the stock falls below the minimum.
+///
+/// Rules can be shared with a workspace, then every member of the workspace
+/// can see and delete them. The assets of the main that created the rule
+/// are counted.
+///
+/// Every rule has its own price source, that is used to value the stock.
+#[derive(Clone)]
+pub struct StockService {
+    pool:      ConnectionPool,
+    eve_auth:  EveAuthService,
+    price:     PriceService,
+    workspace: WorkspaceService,
+}
+
+impl StockService {
+    /// Creates a new instance
+    pub fn new(
+        pool:      ConnectionPool,
+        eve_auth:  EveAuthService,
+        price:     PriceService,
+        workspace: WorkspaceService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            price,
+            workspace,
+        }
+    }
+
+    /// Gets the rules together with the stock of the last check and its
+    /// value
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `query` -> Workspace of the rules, without one the rules of the main
+    ///            that are not shared are returned
+    ///
+    pub async fn rules(
+        &self,
+        token: &str,
+        query: StockQuery,
+    ) -> Result<Vec<StockRuleValue>, EveServerError> {
+        let user = self.user(token).await?;
+        if let Some(x) = query.workspace_id {
+            self.workspace.require_member(&user, x).await?;
+        }
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, Uuid>(CacheName::StockRule)
+            .await?;
+        let rules = con
+            .mget::<_, _, StockRuleEntry>(CacheName::StockRule, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| match query.workspace_id {
+                Some(_) => x.workspace_id == query.workspace_id,
+                None    => x.workspace_id.is_none() && x.user_id == user.user_id,
+            })
+            .collect::<Vec<_>>();
+
+        let mut by_source: HashMap<String, Vec<TypeId>> = HashMap::new();
+        for rule in rules.iter() {
+            by_source
+                .entry(rule.price_source.clone())
+                .or_default()
+                .push(rule.type_id);
+        }
+        let mut prices = HashMap::new();
+        for (source, type_ids) in by_source {
+            let source_prices = self
+                .price
+                .prices(PriceSource::parse(&source).unwrap_or_default(), type_ids)
+                .await?;
+            prices.insert(source, source_prices);
+        }
+
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let price = prices[&rule.price_source]
+                    .get(&rule.type_id)
+                    .copied()
+                    .unwrap_or_default();
+                StockRuleValue {
+                    value: price * rule.current as f32,
+                    rule,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(rules)
+    }
+
+    /// Creates a new rule.
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `rule`  -> Item, location, the minimum stock and optionally the
+    ///            workspace the rule is shared with and the price source
+    ///
+    /// # Returns
+    ///
+    /// The created rule, the stock is set after the next check
+    ///
+    pub async fn create(
+        &self,
+        token: &str,
+        rule:  StockRule,
+    ) -> Result<StockRuleEntry, EveServerError> {
+        let user = self.user(token).await?;
+        if let Some(x) = rule.workspace_id {
+            self.workspace.require_member(&user, x).await?;
+        }
+        let price_source = rule.price.source()?;
+
+        let entry = StockRuleEntry {
+            id:             Uuid::new_v4(),
+            user_id:        user.user_id,
+            workspace_id:   rule.workspace_id,
+            type_id:        rule.type_id,
+            location_id:    rule.location_id,
+            minimum:        rule.minimum,
+            include_market: rule.include_market,
+            price_source:   price_source.as_string(),
+            current:        0,
+            alerted:        false,
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::StockRule, entry.id, entry.clone())
+            .await?;
+        Ok(entry)
+    }
+
+    /// Deletes a rule of the main or of one of its workspaces
+    pub async fn delete(
+        &self,
+        token: &str,
+        id:    Uuid,
+    ) -> Result<(), EveServerError> {
+        let user = self.user(token).await?;
+
+        let mut con = self.pool.acquire().await?;
+        let rule = con
+            .get::<_, _, StockRuleEntry>(CacheName::StockRule, id)
+            .await?
+            .ok_or(EveServerError::StockRuleNotFound)?;
+        match rule.workspace_id {
+            Some(x) => {
+                self
+                    .workspace
+                    .require_member(&user, x)
+                    .await
+                    .map_err(|_| EveServerError::StockRuleNotFound)?;
+            },
+            None if rule.user_id != user.user_id => {
+                return Err(EveServerError::StockRuleNotFound);
+            },
+            None => (),
+        }
+
+        con
+            .del(CacheName::StockRule, id)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn user(&self, token: &str) -> Result<UserEntry, EveServerError> {
+        self
+            .eve_auth
+            .lookup(&token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct StockQuery {
+    /// Workspace the rules are shared with
+    pub workspace_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockRule {
+    pub type_id:        TypeId,
+    /// Station or structure the items are stored at
+    pub location_id:    LocationId,
+    pub minimum:        u32,
+    /// Also count the sell orders at the location
+    #[serde(default)]
+    pub include_market: bool,
+    /// Shares the rule with the members of the workspace
+    #[serde(default)]
+    pub workspace_id:   Option<Uuid>,
+    /// Source for the value of the stock, defaults to the average price
+    #[serde(flatten)]
+    pub price:          PriceQuery,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockRuleValue {
+    #[serde(flatten)]
+    pub rule:  StockRuleEntry,
+    /// Value of the current stock with the price source of the rule
+    pub value: f32,
+}
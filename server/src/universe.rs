@@ -0,0 +1,556 @@
+use crate::error::EveServerError;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, KillmailEntry, SovereigntyEntry, SystemRegionEntry, UniverseGraphEntry};
+use caph_eve_data_wrapper::{AgentId, AllianceId, CorporationId, DivisionId, EveDataWrapper, FactionId, JumpShipClass, NpcDamageProfile, RegionId, SolarSystemId};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Service for all universe related interfaces
+#[derive(Clone)]
+pub struct UniverseService {
+    pool:     ConnectionPool,
+    eve_data: EveDataWrapper,
+}
+
+impl UniverseService {
+    /// Creates a new instance
+    pub fn new(
+        pool:     ConnectionPool,
+        eve_data: EveDataWrapper,
+    ) -> Self {
+        Self {
+            pool,
+            eve_data,
+        }
+    }
+
+    /// Joins the owner of all systems with their region and security.
+    ///
+    /// # Params
+    ///
+    /// `query` -> Optional filter for region, alliance and faction
+    ///
+    /// # Returns
+    ///
+    /// All systems matching the filter together with their owners
+    ///
+    pub async fn sovereignty(
+        &self,
+        query: SovereigntyQuery,
+    ) -> Result<Vec<SystemSovereignty>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let keys = con
+            .keys::<_, SolarSystemId>(CacheName::Sovereignty)
+            .await?;
+        let sov = con
+            .mget::<_, _, SovereigntyEntry>(CacheName::Sovereignty, keys.clone())
+            .await?;
+        let systems = con
+            .mget::<_, _, SystemRegionEntry>(CacheName::SystemRegion, keys)
+            .await?;
+
+        let result = sov
+            .into_iter()
+            .zip(systems)
+            .filter_map(|(sov, system)| Some((sov?, system?)))
+            .map(|(sov, system)| SystemSovereignty {
+                system_id:      sov.system_id,
+                region_id:      system.region_id,
+                security:       system.security,
+                alliance_id:    sov.alliance_id,
+                corporation_id: sov.corporation_id,
+                faction_id:     sov.faction_id,
+                fw_owner:       sov.fw_owner,
+                fw_occupier:    sov.fw_occupier,
+                fw_contested:   sov.fw_contested,
+            })
+            .filter(|x| query.region_id.map_or(true, |y| x.region_id == y))
+            .filter(|x| query.alliance_id.map_or(true, |y| x.alliance_id == Some(y)))
+            .filter(|x| query.faction_id.map_or(true, |y| {
+                x.faction_id == Some(y) || x.fw_occupier == Some(y)
+            }))
+            .collect::<Vec<_>>();
+        Ok(result)
+    }
+
+    /// Distance between two systems in lightyears
+    pub async fn distance(
+        &self,
+        from: SolarSystemId,
+        to:   SolarSystemId,
+    ) -> Result<Option<f64>, EveServerError> {
+        self
+            .eve_data
+            .systems()
+            .await
+            .map(|x| x.distance_ly(from, to))
+            .map_err(Into::into)
+    }
+
+    /// All systems that are in jump range of the given system
+    pub async fn jump_range(
+        &self,
+        system: SolarSystemId,
+        query:  JumpRangeQuery,
+    ) -> Result<Vec<JumpRangeSystem>, EveServerError> {
+        let systems = self
+            .eve_data
+            .systems()
+            .await?
+            .systems_in_jump_range(system, query.ship_class, query.jdc_level)
+            .into_iter()
+            .map(|(system_id, distance)| JumpRangeSystem {
+                system_id,
+                distance,
+            })
+            .collect::<Vec<_>>();
+        Ok(systems)
+    }
+
+    /// Damage profiles of all NPC factions
+    pub async fn npc_damage(
+        &self,
+    ) -> Result<Vec<NpcDamageProfile>, EveServerError> {
+        let mut profiles = self
+            .eve_data
+            .npc_damage()
+            .await?
+            .profiles()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        profiles.sort_by_key(|x| x.faction_id);
+        Ok(profiles)
+    }
+
+    /// Damage profile of the NPCs in a system.
+    ///
+    /// The NPCs are determined by the faction that owns the system in the
+    /// SDE. Systems held by players have no faction, so there is no profile
+    /// for them.
+    pub async fn system_npc_damage(
+        &self,
+        system: SolarSystemId,
+    ) -> Result<Option<NpcDamageProfile>, EveServerError> {
+        let faction_id = self
+            .eve_data
+            .systems()
+            .await?
+            .eve_systems()
+            .iter()
+            .find(|x| x.solar_system_id == system)
+            .and_then(|x| x.faction_id);
+
+        if let Some(x) = faction_id {
+            Ok(self.eve_data.npc_damage().await?.by_faction(x))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Finds the agents of a corporation that are the fewest jumps away from
+    /// a system.
+    ///
+    /// # Params
+    ///
+    /// `system` -> System to start from
+    /// `query`  -> Corporation and optional level and division of the agents
+    ///
+    /// # Returns
+    ///
+    /// All matching agents that can be reached by stargates, sorted by the
+    /// number of jumps
+    ///
+    pub async fn nearest_agents(
+        &self,
+        system: SolarSystemId,
+        query:  NearestAgentQuery,
+    ) -> Result<Vec<NearestAgent>, EveServerError> {
+        let agents   = self.eve_data.agents().await?;
+        let stations = self.eve_data.stations().await?;
+        let research = self.eve_data.research_agents().await?;
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, SolarSystemId>(CacheName::UniverseGraph)
+            .await?;
+        let graph = con
+            .mget::<_, _, UniverseGraphEntry>(CacheName::UniverseGraph, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.system_id, x))
+            .collect::<HashMap<_, _>>();
+        let jumps = jump_distances(&graph, system);
+
+        let mut result = agents
+            .agents_by_corporation(query.corporation_id)
+            .into_iter()
+            .filter(|x| query.level.map_or(true, |y| x.level == y))
+            .filter(|x| query.division_id.map_or(true, |y| x.division_id == y))
+            .filter_map(|x| {
+                let system_id = agents.agent_system(x.agent_id, &stations)?;
+                Some(NearestAgent {
+                    agent_id:       x.agent_id,
+                    corporation_id: x.corporation_id,
+                    division_id:    x.division_id,
+                    level:          x.level,
+                    research:       research.research_agent(x.agent_id).is_some(),
+                    system_id,
+                    jumps:          *jumps.get(&system_id)?,
+                })
+            })
+            .collect::<Vec<_>>();
+        result.sort_by_key(|x| (x.jumps, x.agent_id));
+        Ok(result)
+    }
+
+    /// Calculates a route between two systems, similar to the autopilot
+    /// ingame.
+    ///
+    /// # Params
+    ///
+    /// `from` -> System to start from
+    /// `to`   -> Destination system
+    /// `flag` -> Type of route that should be calculated
+    ///
+    /// # Returns
+    ///
+    /// List of all systems on the route, including the start and destination.
+    /// If there is no route between both systems, an empty list is returned.
+    ///
+    pub async fn route(
+        &self,
+        from: SolarSystemId,
+        to:   SolarSystemId,
+        flag: RouteFlag,
+    ) -> Result<Vec<SolarSystemId>, EveServerError> {
+        let mut con = self.pool.acquire().await?;
+
+        let keys = con
+            .keys::<_, SolarSystemId>(CacheName::UniverseGraph)
+            .await?;
+        let graph = con
+            .mget::<_, _, UniverseGraphEntry>(CacheName::UniverseGraph, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|x| (x.system_id, x))
+            .collect::<HashMap<_, _>>();
+
+        Ok(find_route(&graph, from, to, flag))
+    }
+
+    /// Collects all recent kills in the systems along a route.
+    ///
+    /// # Params
+    ///
+    /// `from`  -> System to start from
+    /// `to`    -> Destination system
+    /// `query` -> Type of route and how many hours to look back
+    ///
+    /// # Returns
+    ///
+    /// Every system on the route together with its kills
+    ///
+    pub async fn route_kills(
+        &self,
+        from:  SolarSystemId,
+        to:    SolarSystemId,
+        query: RouteKillsQuery,
+    ) -> Result<Vec<RouteKills>, EveServerError> {
+        let route = self.route(from, to, query.flag).await?;
+
+        let hours = query.hours.unwrap_or(1);
+        let oldest = (Utc::now().timestamp() as u64 - hours * 60 * 60) * 1_000;
+        let kills = self
+            .pool
+            .acquire()
+            .await?
+            .mget::<_, _, Vec<KillmailEntry>>(CacheName::Killmail, route.clone())
+            .await?;
+
+        let kills = route
+            .into_iter()
+            .zip(kills)
+            .map(|(system_id, kills)| {
+                let kills = kills
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|x| x.timestamp >= oldest)
+                    .collect::<Vec<_>>();
+                RouteKills {
+                    system_id,
+                    kills,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(kills)
+    }
+}
+
+/// Dijkstra over the jump graph.
+///
+/// Every jump costs one, jumps into systems that should be avoided by the
+/// given flag get a penalty, so that they are only taken if there is no other
+/// way.
+fn find_route(
+    graph: &HashMap<SolarSystemId, UniverseGraphEntry>,
+    from:  SolarSystemId,
+    to:    SolarSystemId,
+    flag:  RouteFlag,
+) -> Vec<SolarSystemId> {
+    const PENALTY: u32 = 50_000;
+
+    let mut costs    = HashMap::new();
+    let mut previous = HashMap::new();
+    let mut queue    = BinaryHeap::new();
+
+    costs.insert(from, 0u32);
+    queue.push(Reverse((0u32, from)));
+
+    while let Some(Reverse((cost, system))) = queue.pop() {
+        if system == to {
+            break;
+        }
+
+        if cost > *costs.get(&system).unwrap_or(&u32::MAX) {
+            continue;
+        }
+
+        let neighbours = graph
+            .get(&system)
+            .map(|x| x.neighbours.clone())
+            .unwrap_or_default();
+        for neighbour in neighbours {
+            let high_sec = graph
+                .get(&neighbour)
+                .map(|x| x.security >= 0.45)
+                .unwrap_or_default();
+            let penalty = match flag {
+                RouteFlag::Secure   if !high_sec => PENALTY,
+                RouteFlag::Insecure if high_sec  => PENALTY,
+                _                                => 0,
+            };
+
+            let next = cost + 1 + penalty;
+            if next < *costs.get(&neighbour).unwrap_or(&u32::MAX) {
+                costs.insert(neighbour, next);
+                previous.insert(neighbour, system);
+                queue.push(Reverse((next, neighbour)));
+            }
+        }
+    }
+
+    if !costs.contains_key(&to) {
+        return Vec::new();
+    }
+
+    let mut route = vec![to];
+    let mut current = to;
+    while let Some(x) = previous.get(&current) {
+        route.push(*x);
+        current = *x;
+    }
+    route.reverse();
+    route
+}
+
+/// Breadth first search over the jump graph.
+///
+/// Returns the number of jumps to every system that can be reached from
+/// the given system.
+fn jump_distances(
+    graph: &HashMap<SolarSystemId, UniverseGraphEntry>,
+    from:  SolarSystemId,
+) -> HashMap<SolarSystemId, u32> {
+    let mut jumps = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    jumps.insert(from, 0u32);
+    queue.push_back(from);
+
+    while let Some(system) = queue.pop_front() {
+        let next = jumps[&system] + 1;
+        let neighbours = graph
+            .get(&system)
+            .map(|x| x.neighbours.clone())
+            .unwrap_or_default();
+        for neighbour in neighbours {
+            if !jumps.contains_key(&neighbour) {
+                jumps.insert(neighbour, next);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    jumps
+}
+
+/// Query parameters for finding the nearest agents
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct NearestAgentQuery {
+    pub corporation_id: CorporationId,
+    pub level:          Option<u8>,
+    pub division_id:    Option<DivisionId>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NearestAgent {
+    pub agent_id:       AgentId,
+    pub corporation_id: CorporationId,
+    pub division_id:    DivisionId,
+    pub level:          u8,
+    /// The agent offers research
+    pub research:       bool,
+    pub system_id:      SolarSystemId,
+    pub jumps:          u32,
+}
+
+/// Query parameters for finding systems in jump range
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct JumpRangeQuery {
+    pub ship_class: JumpShipClass,
+    /// Level of Jump Drive Calibration
+    #[serde(default)]
+    pub jdc_level:  u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JumpRangeSystem {
+    pub system_id: SolarSystemId,
+    /// Distance in lightyears
+    pub distance:  f64,
+}
+
+/// Optional filters for the sovereignty of systems
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SovereigntyQuery {
+    pub region_id:   Option<RegionId>,
+    pub alliance_id: Option<AllianceId>,
+    /// Matches the owning faction and the faction warfare occupier
+    pub faction_id:  Option<FactionId>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemSovereignty {
+    pub system_id:      SolarSystemId,
+    pub region_id:      RegionId,
+    pub security:       f32,
+    pub alliance_id:    Option<AllianceId>,
+    pub corporation_id: Option<CorporationId>,
+    pub faction_id:     Option<FactionId>,
+    pub fw_owner:       Option<FactionId>,
+    pub fw_occupier:    Option<FactionId>,
+    pub fw_contested:   Option<String>,
+}
+
+/// Query parameters for collecting kills along a route
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RouteKillsQuery {
+    #[serde(default)]
+    pub flag:  RouteFlag,
+    /// Hours to look back, defaults to one hour
+    pub hours: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteKills {
+    pub system_id: SolarSystemId,
+    pub kills:     Vec<KillmailEntry>,
+}
+
+/// Query parameters for calculating a route
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RouteQuery {
+    #[serde(default)]
+    pub flag: RouteFlag,
+}
+
+/// Type of route that should be calculated
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteFlag {
+    /// Least amount of jumps
+    Shortest,
+    /// Prefer high security systems
+    Secure,
+    /// Prefer low and null security systems
+    Insecure,
+}
+
+impl Default for RouteFlag {
+    fn default() -> Self {
+        Self::Shortest
+    }
+}
+
+#[cfg(test)]
+mod route_tests {
+    use super::*;
+
+    /// 1 (1.0) - 2 (0.1) - 3 (1.0)
+    ///   \                /
+    ///    4 (0.9) - 5 (0.8)
+    fn graph() -> HashMap<SolarSystemId, UniverseGraphEntry> {
+        vec![
+            (1u32, 1.0f32, vec![2u32, 4]),
+            (2,    0.1,    vec![1, 3]),
+            (3,    1.0,    vec![2, 5]),
+            (4,    0.9,    vec![1, 5]),
+            (5,    0.8,    vec![4, 3]),
+        ]
+        .into_iter()
+        .map(|(sid, security, neighbours)| {
+            let neighbours = neighbours.into_iter().map(Into::into).collect();
+            (sid.into(), UniverseGraphEntry::new(sid.into(), security, neighbours))
+        })
+        .collect()
+    }
+
+    fn ids(ids: Vec<u32>) -> Vec<SolarSystemId> {
+        ids.into_iter().map(Into::into).collect()
+    }
+
+    #[test]
+    fn jump_distance() {
+        let jumps = jump_distances(&graph(), 1.into());
+        assert_eq!(jumps.len(), 5);
+        assert_eq!(jumps[&SolarSystemId(3)], 2);
+        assert_eq!(jumps[&SolarSystemId(5)], 2);
+    }
+
+    #[test]
+    fn shortest() {
+        let route = find_route(&graph(), 1.into(), 3.into(), RouteFlag::Shortest);
+        assert_eq!(route, ids(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn secure() {
+        let route = find_route(&graph(), 1.into(), 3.into(), RouteFlag::Secure);
+        assert_eq!(route, ids(vec![1, 4, 5, 3]));
+    }
+
+    #[test]
+    fn insecure() {
+        let route = find_route(&graph(), 1.into(), 3.into(), RouteFlag::Insecure);
+        assert_eq!(route, ids(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn same_system() {
+        let route = find_route(&graph(), 1.into(), 1.into(), RouteFlag::Shortest);
+        assert_eq!(route, ids(vec![1]));
+    }
+
+    #[test]
+    fn unreachable() {
+        let route = find_route(&graph(), 1.into(), 6.into(), RouteFlag::Shortest);
+        assert!(route.is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+use reqwest::Client;
+use serde_json::json;
+
+/// Sends messages to a discord compatible webhook
+pub struct Webhook;
+
+impl Webhook {
+    /// Discord rejects messages that are longer
+    const MAX_LENGTH: usize = 2000;
+
+    /// Posts the given message to the webhook, errors are only logged
+    pub async fn send(url: &str, message: &str) {
+        let content = message
+            .chars()
+            .take(Self::MAX_LENGTH)
+            .collect::<String>();
+
+        let result = Client::new()
+            .post(url)
+            .json(&json!({ "content": content }))
+            .send()
+            .await
+            .and_then(|x| x.error_for_status());
+        if let Err(e) = result {
+            log::error!("Error sending webhook {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::corporation::CorporationService;
+use crate::error::EveServerError;
+use crate::eve::EveAuthService;
+
+use cachem::v2::ConnectionPool;
+use caph_db_v2::{CacheName, StockRuleEntry, UserEntry, WorkspaceEntry, WorkspaceKey};
+use caph_eve_data_wrapper::{CharacterId, CorporationId};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Workspaces group mains that share data.
+///
+/// A workspace either belongs to a corporation, then every main with the
+/// main or an alt in the corporation is a member, or to a user defined
+/// group of mains. Data of a workspace is only visible to its members,
+/// everything without a workspace is only visible to the main that created
+/// it.
+///
+/// Only stock rules and net worth snapshots can belong to a workspace.
+/// Projects, fittings and price alerts are always private to their main.
+#[derive(Clone)]
+pub struct WorkspaceService {
+    pool:        ConnectionPool,
+    eve_auth:    EveAuthService,
+    corporation: CorporationService,
+}
+
+impl WorkspaceService {
+    /// Creates a new instance
+    pub fn new(
+        pool:        ConnectionPool,
+        eve_auth:    EveAuthService,
+        corporation: CorporationService,
+    ) -> Self {
+        Self {
+            pool,
+            eve_auth,
+            corporation,
+        }
+    }
+
+    /// Gets all workspaces the main is a member of
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    ///
+    pub async fn list(
+        &self,
+        token: &str,
+    ) -> Result<Vec<WorkspaceEntry>, EveServerError> {
+        let user = self.user(token).await?;
+        let corp_ids = Self::corp_ids(&user);
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, Uuid>(CacheName::Workspace)
+            .await?;
+        let mut workspaces = con
+            .mget::<_, _, WorkspaceEntry>(CacheName::Workspace, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.is_member(user.user_id, &corp_ids))
+            .collect::<Vec<_>>();
+        workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(workspaces)
+    }
+
+    /// Creates a new workspace, for a corporation workspace one of the
+    /// characters must be a director of the corporation
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting main
+    /// `request` -> Name, corporation and members of the workspace
+    ///
+    pub async fn create(
+        &self,
+        token:   &str,
+        request: WorkspaceRequest,
+    ) -> Result<WorkspaceEntry, EveServerError> {
+        let user = self.user(token).await?;
+        if let Some(cid) = request.corporation_id {
+            self.corporation.require_role(cid, token, &[]).await?;
+        }
+
+        let entry = WorkspaceEntry {
+            id:             Uuid::new_v4(),
+            name:           request.name,
+            user_id:        user.user_id,
+            corporation_id: request.corporation_id,
+            members:        request.members,
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::Workspace, entry.id, entry.clone())
+            .await?;
+        Ok(entry)
+    }
+
+    /// Changes the name and the members of a workspace, only the main that
+    /// created it can change it
+    ///
+    /// # Params
+    ///
+    /// `token`   -> Cookie from the requesting main
+    /// `id`      -> Id of the workspace
+    /// `request` -> New name, corporation and members
+    ///
+    pub async fn update(
+        &self,
+        token:   &str,
+        id:      Uuid,
+        request: WorkspaceRequest,
+    ) -> Result<WorkspaceEntry, EveServerError> {
+        let user = self.user(token).await?;
+        let workspace = self.owned(&user, id).await?;
+        let new_corporation = request
+            .corporation_id
+            .filter(|x| Some(*x) != workspace.corporation_id);
+        if let Some(cid) = new_corporation {
+            self.corporation.require_role(cid, token, &[]).await?;
+        }
+
+        let entry = WorkspaceEntry {
+            name:           request.name,
+            corporation_id: request.corporation_id,
+            members:        request.members,
+            ..workspace
+        };
+        self
+            .pool
+            .acquire()
+            .await?
+            .set(CacheName::Workspace, entry.id, entry.clone())
+            .await?;
+        Ok(entry)
+    }
+
+    /// Deletes a workspace together with all data that belongs to it, only
+    /// the main that created it can delete it
+    ///
+    /// # Params
+    ///
+    /// `token` -> Cookie from the requesting main
+    /// `id`    -> Id of the workspace
+    ///
+    pub async fn delete(
+        &self,
+        token: &str,
+        id:    Uuid,
+    ) -> Result<(), EveServerError> {
+        let user = self.user(token).await?;
+        self.owned(&user, id).await?;
+
+        let mut con = self.pool.acquire().await?;
+        let keys = con
+            .keys::<_, Uuid>(CacheName::StockRule)
+            .await?;
+        let rules = con
+            .mget::<_, _, StockRuleEntry>(CacheName::StockRule, keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .filter(|x| x.workspace_id == Some(id))
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+        if !rules.is_empty() {
+            con.mdel(CacheName::StockRule, rules).await?;
+        }
+
+        let snapshots = con
+            .keys::<_, WorkspaceKey>(CacheName::NetWorthHistory)
+            .await?
+            .into_iter()
+            .filter(|x| x.workspace_id == Some(id))
+            .collect::<Vec<_>>();
+        if !snapshots.is_empty() {
+            con.mdel(CacheName::NetWorthHistory, snapshots).await?;
+        }
+
+        con
+            .del(CacheName::Workspace, id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Makes sure that the main is a member of the workspace
+    ///
+    /// # Params
+    ///
+    /// `user` -> Requesting main
+    /// `id`   -> Id of the workspace
+    ///
+    /// # Returns
+    ///
+    /// The workspace, [EveServerError::WorkspaceNotFound] if it does not
+    /// exist or the main is not a member, so that other workspaces stay
+    /// invisible
+    ///
+    pub async fn require_member(
+        &self,
+        user: &UserEntry,
+        id:   Uuid,
+    ) -> Result<WorkspaceEntry, EveServerError> {
+        self
+            .pool
+            .acquire()
+            .await?
+            .get::<_, _, WorkspaceEntry>(CacheName::Workspace, id)
+            .await?
+            .filter(|x| x.is_member(user.user_id, &Self::corp_ids(user)))
+            .ok_or(EveServerError::WorkspaceNotFound)
+    }
+
+    /// Gets the workspace if the main created it
+    async fn owned(
+        &self,
+        user: &UserEntry,
+        id:   Uuid,
+    ) -> Result<WorkspaceEntry, EveServerError> {
+        let workspace = self.require_member(user, id).await?;
+        if workspace.user_id == user.user_id {
+            Ok(workspace)
+        } else {
+            Err(EveServerError::MissingPermission)
+        }
+    }
+
+    async fn user(&self, token: &str) -> Result<UserEntry, EveServerError> {
+        self
+            .eve_auth
+            .lookup(token)
+            .await?
+            .ok_or(EveServerError::InvalidUser)
+    }
+
+    /// Corporations of the main and its alts
+    fn corp_ids(user: &UserEntry) -> Vec<CorporationId> {
+        let mut corp_ids = user
+            .aliase
+            .iter()
+            .map(|x| x.corp_id)
+            .collect::<Vec<_>>();
+        corp_ids.push(user.corp_id);
+        corp_ids
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceRequest {
+    pub name:           String,
+    /// Every main with a character in the corporation is a member
+    #[serde(default)]
+    pub corporation_id: Option<CorporationId>,
+    /// Additional members
+    #[serde(default)]
+    pub members:        Vec<CharacterId>,
+}
+
+#[cfg(test)]
+mod workspace_tests {
+    use super::*;
+
+    fn workspace(corporation_id: Option<u32>, members: Vec<u32>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id:             Uuid::new_v4(),
+            name:           "test".into(),
+            user_id:        1.into(),
+            corporation_id: corporation_id.map(Into::into),
+            members:        members.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    #[test]
+    fn membership() {
+        let group = workspace(None, vec![2]);
+        assert!(group.is_member(1.into(), &[]));
+        assert!(group.is_member(2.into(), &[]));
+        assert!(!group.is_member(3.into(), &[]));
+
+        let corporation = workspace(Some(100), Vec::new());
+        assert!(corporation.is_member(3.into(), &[100.into()]));
+        assert!(!corporation.is_member(3.into(), &[200.into()]));
+    }
+}